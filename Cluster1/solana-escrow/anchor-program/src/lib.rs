@@ -0,0 +1,275 @@
+//! The escrow as an Anchor program, for clients built on Anchor's IDL and account macros. The
+//! native program stays canonical: every instruction here checks its accounts with Anchor
+//! constraints, then runs the native processor on them, which keeps the native account layouts
+//! and does the same checks again.
+//!
+//! The typed instructions cover an escrow's lifecycle. Any other instruction, or one of these
+//! with options they don't take, is sent encoded as for the native program and reaches the
+//! native processor through `fallback`, with the accounts listed for it there.
+
+// the results Anchor's handlers return
+#![allow(clippy::result_large_err)]
+
+pub mod state;
+
+use anchor_lang::{prelude::*, Bumps};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use solana_escrow::{
+    instruction::EscrowInstruction,
+    processor::Processor,
+    state::{
+        DepositAmount, HashLock, PaymentAmount, PriceBand, QuoteConvention, RoundingMode, TakerGate,
+    },
+};
+
+use crate::state::EscrowAccount;
+
+declare_id!("AoJP7pAndEAjZrY2hBTFG9PL2vRVN1jQ6VsA3KA7owAc");
+
+#[program]
+pub mod solana_escrow_anchor {
+    use super::*;
+
+    /// InitEscrow asking `amount` of token Y, split across the payout accounts passed as
+    /// remaining accounts by `payout_bps`. Its other options are left at their defaults.
+    pub fn init_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitEscrow<'info>>,
+        amount: u64,
+        payout_bps: Vec<u16>,
+        unlock_slots: u64,
+        partial_fill: bool,
+    ) -> Result<()> {
+        let instruction = EscrowInstruction::InitEscrow {
+            amount: PaymentAmount(amount),
+            payout_bps,
+            rounding_mode: RoundingMode::default(),
+            taker_commitment: None,
+            unlock_slots,
+            vault_deposit: DepositAmount(0),
+            settlement_program: None,
+            partial_fill,
+            taker_gate: TakerGate::default(),
+            hash_lock: HashLock::default(),
+            gas_rebate: 0,
+            price_band: PriceBand::default(),
+            quote_convention: QuoteConvention::default(),
+            fill_cooldown_slots: 0,
+            has_refund_token_account: false,
+            has_deposit_mint: false,
+            designated_taker: None,
+        };
+        process_native(ctx, instruction)
+    }
+
+    /// Exchange, named for the taker's side so its discriminator can't start with a native
+    /// instruction's tag
+    pub fn take<'info>(
+        ctx: Context<'_, '_, '_, 'info, Take<'info>>,
+        amount: u64,
+        taker_salt: Option<[u8; 32]>,
+        valid_until_slot: u64,
+        fill_amount: u64,
+        max_payment: u64,
+    ) -> Result<()> {
+        let instruction = EscrowInstruction::Exchange {
+            amount: DepositAmount(amount),
+            taker_salt,
+            valid_until_slot,
+            fill_amount: DepositAmount(fill_amount),
+            max_payment: PaymentAmount(max_payment),
+        };
+        process_native(ctx, instruction)
+    }
+
+    pub fn reset_time_lock<'info>(
+        ctx: Context<'_, '_, '_, 'info, ResetTimeLock<'info>>,
+        unlock_slots: u64,
+    ) -> Result<()> {
+        process_native(ctx, EscrowInstruction::ResetTimeLock { unlock_slots })
+    }
+
+    pub fn cancel<'info>(ctx: Context<'_, '_, '_, 'info, Cancel<'info>>) -> Result<()> {
+        process_native(ctx, EscrowInstruction::Cancel {})
+    }
+
+    pub fn cancel_expired<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelExpired<'info>>,
+    ) -> Result<()> {
+        process_native(ctx, EscrowInstruction::CancelExpired)
+    }
+
+    /// Instructions encoded for the native program
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        Ok(Processor::process(program_id, accounts, data)?)
+    }
+}
+
+/// Runs `instruction` through the native processor with the accounts in the order `T` lists
+/// them, which is the native one, then the remaining accounts
+fn process_native<'info, T: Bumps + ToAccountInfos<'info>>(
+    ctx: Context<'_, '_, '_, 'info, T>,
+    instruction: EscrowInstruction,
+) -> Result<()> {
+    let mut accounts = ctx.accounts.to_account_infos();
+    accounts.extend_from_slice(ctx.remaining_accounts);
+    Ok(Processor::process(
+        ctx.program_id,
+        &accounts,
+        &instruction.pack(),
+    )?)
+}
+
+/// Followed by the payout accounts and whatever else the config requires, as for the native
+/// InitEscrow
+#[derive(Accounts)]
+pub struct InitEscrow<'info> {
+    pub initializer: Signer<'info>,
+    /// The token account holding the deposit, handed to its vault authority
+    #[account(mut, token::token_program = token_program)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// The initializer's token account for token Y
+    #[account(token::token_program = token_program)]
+    pub initializer_receive_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: a rent-exempt account of this program's, which the escrow is laid out in
+    #[account(mut, owner = crate::ID)]
+    pub escrow: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: the stats PDA
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: UncheckedAccount<'info>,
+    /// CHECK: the operator config, read by the native processor
+    #[account(owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+}
+
+/// Followed by whatever else the escrow and its config require, as for the native Exchange
+#[derive(Accounts)]
+pub struct Take<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut, token::mint = expected_mint, token::token_program = token_program)]
+    pub taker_send_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = deposit_mint, token::token_program = token_program)]
+    pub taker_receive_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: the escrow's vault
+    #[account(mut, address = escrow.temp_token_account_pubkey)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: the escrow's initializer, receiving its rent
+    #[account(mut, address = escrow.initializer_pubkey)]
+    pub initializer: UncheckedAccount<'info>,
+    /// CHECK: where the payment goes, which the escrow's receipt or settlement hook decides
+    #[account(mut)]
+    pub initializer_receive_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(address = escrow.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: the vault authority PDA
+    #[account(seeds = [b"escrow", vault.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: the stats PDA
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: UncheckedAccount<'info>,
+    /// CHECK: the escrow's config
+    #[account(address = escrow.config_pubkey)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: the fee recipient's token account, for token Y or the config's fee mint
+    #[account(mut)]
+    pub fee_account: UncheckedAccount<'info>,
+    #[account(address = escrow.deposit_mint)]
+    pub deposit_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = escrow.expected_mint)]
+    pub expected_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct ResetTimeLock<'info> {
+    #[account(address = escrow.initializer_pubkey)]
+    pub initializer: Signer<'info>,
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+}
+
+/// Followed by whatever else the escrow requires, as for the native Cancel
+#[derive(Accounts)]
+pub struct Cancel<'info> {
+    /// The initializer, or the holder of the escrow's receipt
+    pub authority: Signer<'info>,
+    /// CHECK: the escrow's vault
+    #[account(mut, address = escrow.temp_token_account_pubkey)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: the escrow's initializer, receiving its rent
+    #[account(mut, address = escrow.initializer_pubkey)]
+    pub initializer: UncheckedAccount<'info>,
+    /// CHECK: the account the deposit is refunded to, which the escrow and its receipt decide
+    #[account(mut)]
+    pub refund_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(address = escrow.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: the vault authority PDA
+    #[account(seeds = [b"escrow", vault.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: the stats PDA
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: UncheckedAccount<'info>,
+    #[account(address = escrow.deposit_mint)]
+    pub deposit_mint: InterfaceAccount<'info, Mint>,
+}
+
+/// Followed by whatever else the escrow requires, as for the native CancelExpired
+#[derive(Accounts)]
+pub struct CancelExpired<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    /// CHECK: the escrow's vault
+    #[account(mut, address = escrow.temp_token_account_pubkey)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: the escrow's initializer, receiving its rent
+    #[account(mut, address = escrow.initializer_pubkey)]
+    pub initializer: UncheckedAccount<'info>,
+    /// CHECK: the account the deposit is refunded to, which the escrow and its receipt decide
+    #[account(mut)]
+    pub refund_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(address = escrow.token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: the vault authority PDA
+    #[account(seeds = [b"escrow", vault.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: the stats PDA
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: UncheckedAccount<'info>,
+    #[account(address = escrow.deposit_mint)]
+    pub deposit_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::Discriminator;
+    use solana_escrow::instruction::VIEW_TAG_BASE;
+
+    use super::instruction;
+
+    #[test]
+    fn discriminators_dont_start_with_a_native_tag() {
+        // a native instruction's first byte is its tag, so one of 8 bytes or more could only be
+        // taken for a typed instruction here if its tag started the discriminator
+        let native_tags: Vec<u8> = (0..50).chain(VIEW_TAG_BASE..VIEW_TAG_BASE + 3).collect();
+        for discriminator in [
+            instruction::InitEscrow::DISCRIMINATOR,
+            instruction::Take::DISCRIMINATOR,
+            instruction::ResetTimeLock::DISCRIMINATOR,
+            instruction::Cancel::DISCRIMINATOR,
+            instruction::CancelExpired::DISCRIMINATOR,
+        ] {
+            assert!(!native_tags.contains(&discriminator[0]));
+        }
+    }
+}
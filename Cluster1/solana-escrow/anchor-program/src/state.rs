@@ -0,0 +1,44 @@
+use std::{ops::Deref, rc::Rc};
+
+use anchor_lang::{prelude::*, solana_program::program_pack::Pack};
+use solana_escrow::state::Escrow;
+
+/// A native escrow account, read in its own layout, without an Anchor discriminator, so the
+/// constraints can check the accounts passed along with it. The native processor writes it,
+/// so it's never serialized back on exit.
+#[derive(Clone)]
+pub struct EscrowAccount(Rc<Escrow>);
+
+impl AccountDeserialize for EscrowAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let escrow = Escrow::unpack(buf.get(..Escrow::LEN).unwrap_or(buf))?;
+        Ok(Self(Rc::new(escrow)))
+    }
+}
+
+impl AccountSerialize for EscrowAccount {}
+
+impl Owner for EscrowAccount {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl Deref for EscrowAccount {
+    type Target = Escrow;
+
+    fn deref(&self) -> &Escrow {
+        &self.0
+    }
+}
+
+// left out of the IDL like Anchor's own `ProgramData`, having no discriminator to list
+#[cfg(feature = "idl-build")]
+mod idl_build {
+    use super::*;
+
+    impl anchor_lang::IdlBuild for EscrowAccount {}
+    impl anchor_lang::Discriminator for EscrowAccount {
+        const DISCRIMINATOR: [u8; 8] = [u8::MAX; 8];
+    }
+}
@@ -0,0 +1,237 @@
+//! The Anchor program running the native escrow: its typed instructions checked by their
+//! constraints, and native ones through its fallback
+
+#[path = "../../program/tests/common/mod.rs"]
+mod common;
+
+use anchor_lang::{error::ErrorCode, InstructionData, ToAccountMetas};
+use common::{Bank, Market, Offer, OfferTerms, DEFAULT_FEE_BPS, DEFAULT_UNLOCK_SLOTS};
+use solana_escrow::{
+    instruction::{stats_pda, vault_authority_pda},
+    state::{PaymentAmount, EXPIRY_SLOTS},
+};
+use solana_escrow_anchor::{accounts, instruction};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    program_error::ProgramError, pubkey::Pubkey,
+};
+
+/// The Anchor program's entrypoint, taking the accounts as the runtime hands them over,
+/// living as long as the slice they're in. The bank's outlive the whole instruction.
+fn anchor_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = unsafe { &*(accounts as *const [AccountInfo]) };
+    solana_escrow_anchor::entry(program_id, accounts, data)
+}
+
+/// A market whose escrow program is the Anchor one, set up through its fallback
+fn anchor_market() -> Market {
+    let bank = Bank::with_program(solana_escrow_anchor::ID, anchor_entry);
+    Market::with_bank(bank, DEFAULT_FEE_BPS)
+}
+
+fn anchor_instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: solana_escrow_anchor::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn take_instruction(market: &Market, offer: &Offer, taker: &Pubkey) -> Instruction {
+    anchor_instruction(
+        accounts::Take {
+            taker: *taker,
+            taker_send_account: market.y_account(taker),
+            taker_receive_account: market.x_account(taker),
+            vault: offer.vault,
+            initializer: offer.initializer,
+            initializer_receive_account: market.y_account(&offer.initializer),
+            escrow: offer.escrow_account,
+            token_program: spl_token::id(),
+            vault_authority: vault_authority_pda(&market.program_id(), &offer.vault),
+            stats: market.stats_pda(),
+            config: market.config,
+            fee_account: market.fee_account(),
+            deposit_mint: offer.deposit_mint,
+            expected_mint: offer.expected_mint,
+        },
+        instruction::Take {
+            amount: market.bank.token_balance(&offer.vault),
+            taker_salt: None,
+            valid_until_slot: 0,
+            fill_amount: 0,
+            max_payment: 0,
+        },
+    )
+}
+
+fn cancel_instruction(market: &Market, offer: &Offer, vault: &Pubkey) -> Instruction {
+    anchor_instruction(
+        accounts::Cancel {
+            authority: offer.initializer,
+            vault: *vault,
+            initializer: offer.initializer,
+            refund_account: market.x_account(&offer.initializer),
+            escrow: offer.escrow_account,
+            token_program: spl_token::id(),
+            vault_authority: vault_authority_pda(&market.program_id(), vault),
+            stats: market.stats_pda(),
+            deposit_mint: offer.deposit_mint,
+        },
+        instruction::Cancel {},
+    )
+}
+
+fn constraint_error(code: ErrorCode) -> ProgramError {
+    ProgramError::Custom(code as u32)
+}
+
+#[test]
+fn native_instructions_fall_back_to_the_native_processor() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    assert_eq!(
+        market.escrow(&offer).unwrap().expected_amount,
+        PaymentAmount(300)
+    );
+
+    market.exchange(&offer, &bob, 0).unwrap();
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+    assert_eq!(market.bank.token_balance(&market.y_account(&bob)), 700);
+    assert!(market.escrow(&offer).is_none());
+}
+
+#[test]
+fn init_escrow_and_take() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let offer = market.open_vault(&alice, 500).unwrap();
+
+    let init = anchor_instruction(
+        accounts::InitEscrow {
+            initializer: alice,
+            vault: offer.vault,
+            initializer_receive_account: market.y_account(&alice),
+            escrow: offer.escrow_account,
+            token_program: spl_token::id(),
+            stats: stats_pda(&market.program_id()),
+            config: market.config,
+        },
+        instruction::InitEscrow {
+            amount: 300,
+            payout_bps: vec![],
+            unlock_slots: DEFAULT_UNLOCK_SLOTS,
+            partial_fill: false,
+        },
+    );
+    market.bank.process(&init, &[alice]).unwrap();
+    let escrow = market.escrow(&offer).unwrap();
+    assert_eq!(escrow.initializer_pubkey, alice);
+    assert_eq!(escrow.expected_amount, PaymentAmount(300));
+
+    let take = take_instruction(&market, &offer, &bob);
+    market.bank.process(&take, &[bob]).unwrap();
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+    assert_eq!(market.bank.token_balance(&market.y_account(&bob)), 700);
+    assert!(market.escrow(&offer).is_none());
+}
+
+#[test]
+fn cancel_refunds_the_deposit() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let instruction = cancel_instruction(&market, &offer, &offer.vault);
+    market.bank.process(&instruction, &[alice]).unwrap();
+    assert_eq!(market.bank.token_balance(&market.x_account(&alice)), 1_000);
+    assert!(market.escrow(&offer).is_none());
+}
+
+#[test]
+fn a_vault_the_escrow_doesnt_hold_is_rejected() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let other = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let instruction = cancel_instruction(&market, &offer, &other.vault);
+    assert_eq!(
+        market.bank.process(&instruction, &[alice]),
+        Err(constraint_error(ErrorCode::ConstraintAddress))
+    );
+    assert_eq!(market.bank.token_balance(&offer.vault), 500);
+    assert_eq!(market.bank.token_balance(&other.vault), 500);
+}
+
+#[test]
+fn only_the_initializer_resets_the_time_lock() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let mallory = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let reset = |initializer: Pubkey| {
+        anchor_instruction(
+            accounts::ResetTimeLock {
+                initializer,
+                escrow: offer.escrow_account,
+            },
+            instruction::ResetTimeLock {
+                unlock_slots: 2 * DEFAULT_UNLOCK_SLOTS,
+            },
+        )
+    };
+
+    assert_eq!(
+        market.bank.process(&reset(mallory), &[mallory]),
+        Err(constraint_error(ErrorCode::ConstraintAddress))
+    );
+    let unlock_time = market.escrow(&offer).unwrap().unlock_time;
+    market.bank.process(&reset(alice), &[alice]).unwrap();
+    assert_eq!(
+        market.escrow(&offer).unwrap().unlock_time,
+        unlock_time + DEFAULT_UNLOCK_SLOTS
+    );
+}
+
+#[test]
+fn anyone_cancels_an_expired_escrow() {
+    let mut market = anchor_market();
+    let alice = market.party(1_000, 0);
+    let carol = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let instruction = anchor_instruction(
+        accounts::CancelExpired {
+            cranker: carol,
+            vault: offer.vault,
+            initializer: alice,
+            refund_account: market.x_account(&alice),
+            escrow: offer.escrow_account,
+            token_program: spl_token::id(),
+            vault_authority: vault_authority_pda(&market.program_id(), &offer.vault),
+            stats: market.stats_pda(),
+            deposit_mint: offer.deposit_mint,
+        },
+        instruction::CancelExpired {},
+    );
+
+    market.bank.warp(DEFAULT_UNLOCK_SLOTS + EXPIRY_SLOTS);
+    market.bank.process(&instruction, &[carol]).unwrap();
+    assert_eq!(market.bank.token_balance(&market.x_account(&alice)), 1_000);
+    assert!(market.escrow(&offer).is_none());
+}
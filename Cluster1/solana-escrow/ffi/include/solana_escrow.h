@@ -0,0 +1,251 @@
+/* C interface to the escrow program's instruction builders and escrow decoder, see src/lib.rs */
+#ifndef SOLANA_ESCROW_H
+#define SOLANA_ESCROW_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define ESCROW_FFI_OK 0
+#define ESCROW_FFI_NULL_POINTER -1
+#define ESCROW_FFI_TOO_LARGE -2
+#define ESCROW_FFI_INVALID_DATA -3
+
+#define ESCROW_FFI_MAX_ACCOUNTS 32
+#define ESCROW_FFI_MAX_DATA 246
+#define ESCROW_MAX_PAYOUTS 4
+#define ESCROW_DEFAULT_UNLOCK_SLOTS 100 /* InitEscrow accepts 10 to 216000 */
+
+typedef struct EscrowFfiAccountMeta {
+  uint8_t pubkey[32];
+  bool is_signer;
+  bool is_writable;
+} EscrowFfiAccountMeta;
+
+typedef struct EscrowFfiInstruction {
+  uint8_t program_id[32];
+  EscrowFfiAccountMeta accounts[ESCROW_FFI_MAX_ACCOUNTS];
+  size_t account_count;
+  uint8_t data[ESCROW_FFI_MAX_DATA];
+  size_t data_len;
+} EscrowFfiInstruction;
+
+typedef struct EscrowFfiPayout {
+  uint8_t token_account[32];
+  uint16_t bps;
+} EscrowFfiPayout;
+
+typedef struct EscrowFfiState {
+  uint8_t flags;
+  uint8_t initializer[32];
+  uint8_t temp_token_account[32];
+  uint8_t receive_token_account[32];
+  uint64_t expected_amount;
+  uint64_t unlock_time;
+  uint8_t payout_count;
+  EscrowFfiPayout payouts[ESCROW_MAX_PAYOUTS];
+  uint8_t config[32];
+  uint8_t expected_mint[32];
+  uint64_t init_slot;
+  uint8_t rounding_mode;
+  uint64_t lifetime_expiry_slot; /* 0 when the escrow never expires */
+  uint8_t refund_token_account[32]; /* all zero for the initializer's associated token account */
+  uint8_t taker_commitment[32]; /* all zero for an offer any taker can fill */
+  uint8_t deposit_mint_risk; /* MintRiskFlags bits, 0x80 when the mint wasn't assessed */
+  uint64_t vault_deposit; /* share of a shared vault, 0 when temp_token_account is the escrow's own */
+  uint8_t settlement_program[32]; /* all zero when the proceeds go to the receive account */
+  uint8_t vault_authority_bump; /* bump of the PDA owning temp_token_account */
+  uint8_t receipt_mint_bump; /* 0 until the escrow has a receipt */
+  uint8_t settlement_bump; /* 0 unless the escrow has a settlement hook */
+  uint16_t features; /* EscrowFeatures bits the escrow was opened with */
+  uint64_t filled_amount; /* token X partial fills have released so far */
+  uint64_t last_reset_slot; /* slot of the last ResetTimeLock, 0 if there was none */
+  uint8_t reset_count; /* ResetTimeLocks so far */
+  uint64_t taker_min_lamports; /* lamports a taker's wallet must hold, 0 for no balance check */
+  uint8_t taker_badge_mint[32]; /* badge takers may hold instead, all zero for none */
+  uint8_t deposit_mint[32]; /* mint of the token the initializer offers */
+  uint8_t token_program[32]; /* token program every token CPI of the escrow must use */
+  uint8_t hash_lock_hash[32]; /* sha256 a Redeem's preimage must hash to, all zero for none */
+  uint8_t hash_lock_recipient[32]; /* the only signer Redeem releases the deposit to */
+  uint64_t gas_rebate; /* lamports Exchange pays the taker out of the escrow account */
+  uint8_t permit_eth_signer[20]; /* Ethereum address that may sign permits, all zero for none */
+  uint8_t price_account[32]; /* Pyth price account fills are priced at, all zero for none */
+  int16_t price_offset_bps; /* basis points the oracle price is moved by */
+  uint8_t quote_convention; /* 0 for raw amounts, 1 for an interest-bearing mint's UI amounts */
+  uint64_t fill_cooldown_slots; /* slots after a partial fill before the next one, 0 for none */
+  uint64_t last_fill_slot; /* slot of the last partial fill, 0 if there was none */
+  uint8_t designated_taker[32]; /* the only taker who may fill the offer, all zero for any */
+} EscrowFfiState;
+
+/* the return_data of a TimeStatus instruction, the cancel window closes at unlock */
+typedef struct EscrowFfiTimeStatus {
+  uint64_t current_slot;
+  uint64_t slots_until_unlock;
+  uint64_t slots_until_expiry;
+  uint64_t seconds_until_unlock;
+  uint64_t seconds_until_expiry;
+} EscrowFfiTimeStatus;
+
+/* All pubkeys are 32-byte buffers. Optional accounts (audit_log, payouts, refund_token_account,
+   allowlist_entry, deposit_mint) may be NULL, as may taker_commitment and taker_salt (32 bytes)
+   for offers any taker can fill. A valid_until_slot of 0 sets no fill deadline, and a
+   vault_deposit of 0 gives the escrow temp_token_account as its own vault, in which case
+   vault_deposit_source may be NULL. settlement_program may be NULL unless the escrow settles
+   its proceeds into the program its config whitelists. partial_fill lets takers fill less
+   than the whole deposit. Takers must then hold at least taker_min_lamports or some of the
+   taker_badge_mint token, if either is set; taker_badge_mint may be NULL. hash_lock_hash
+   (32 bytes) and hash_lock_recipient make the escrow a hash-timelock only the recipient may
+   Redeem, and are both NULL otherwise. gas_rebate lamports are paid to the taker out of the
+   escrow account, which must hold them on top of its rent. price_account may be NULL unless
+   fills are priced at that Pyth price account, moved by price_offset_bps. fill_cooldown_slots
+   keeps a partially filled escrow from being filled again for that many slots, 0 for none.
+   designated_taker may be NULL unless only that signer may fill the offer. rate_counter may
+   be NULL unless the config limits inits per slot. */
+
+int32_t escrow_init_escrow(const uint8_t *program_id,
+                           const uint8_t *initializer,
+                           const uint8_t *temp_token_account,
+                           const uint8_t *receive_token_account,
+                           const uint8_t *escrow_account,
+                           const uint8_t *config,
+                           uint64_t amount,
+                           const EscrowFfiPayout *payouts,
+                           size_t payout_count,
+                           uint8_t rounding_mode,
+                           const uint8_t *audit_log,
+                           const uint8_t *refund_token_account,
+                           const uint8_t *allowlist_entry,
+                           const uint8_t *taker_commitment,
+                           const uint8_t *deposit_mint,
+                           uint64_t unlock_slots,
+                           const uint8_t *vault_deposit_source,
+                           uint64_t vault_deposit,
+                           const uint8_t *settlement_program,
+                           bool partial_fill,
+                           uint64_t taker_min_lamports,
+                           const uint8_t *taker_badge_mint,
+                           const uint8_t *hash_lock_hash,
+                           const uint8_t *hash_lock_recipient,
+                           uint64_t gas_rebate,
+                           const uint8_t *price_account,
+                           int16_t price_offset_bps,
+                           uint64_t fill_cooldown_slots,
+                           const uint8_t *designated_taker,
+                           const uint8_t *rate_counter,
+                           EscrowFfiInstruction *out);
+
+/* deposit_mint and expected_mint are the mints of tokens X and Y, which every transfer is
+   checked against, and fee_mint is required along with taker_fee_mint_account.
+   payout_accounts holds payout_count consecutive 32-byte pubkeys. A fill_amount of 0 takes
+   the whole deposit, and a max_payment of 0 sets no limit on the price. pair_stats may be NULL unless the config has a volume cap,
+   taker_allowlist unless the escrow restricts its takers, and taker_badge_account unless its
+   taker gate has a badge mint. nft_metadata is the metadata account of an NFT deposit and
+   royalty_creator_accounts its royalty_creator_count creator token accounts, NULL for other
+   deposits. insurance_pool and insurance_pool_token_account are NULL unless the escrow is
+   insured. basket_mints and basket_token_accounts hold basket_asset_count consecutive 32-byte
+   pubkeys each, the mints of the escrow's basket assets in its order and the taker's token
+   accounts receiving them, and may be NULL when it has no basket. For an escrow with a settlement
+   hook, initializer_receive_token_account is the staging account and settlement_accounts the
+   settlement_account_count accounts settlement_program needs. price_account is the escrow's
+   Pyth price account when it's oracle-priced, and NULL otherwise. */
+int32_t escrow_exchange(const uint8_t *program_id,
+                        const uint8_t *taker,
+                        const uint8_t *taker_sending_token_account,
+                        const uint8_t *taker_receiving_token_account,
+                        const uint8_t *temp_token_account,
+                        const uint8_t *initializer,
+                        const uint8_t *initializer_receive_token_account,
+                        const uint8_t *escrow_account,
+                        const uint8_t *config,
+                        const uint8_t *fee_token_account,
+                        const uint8_t *deposit_mint,
+                        const uint8_t *expected_mint,
+                        uint64_t amount,
+                        const uint8_t *payout_accounts,
+                        size_t payout_count,
+                        const uint8_t *audit_log,
+                        const uint8_t *taker_fee_mint_account,
+                        const uint8_t *fee_mint,
+                        const uint8_t *taker_salt,
+                        uint64_t valid_until_slot,
+                        uint64_t fill_amount,
+                        uint64_t max_payment,
+                        const uint8_t *pair_stats,
+                        const uint8_t *taker_allowlist,
+                        const uint8_t *taker_badge_account,
+                        const uint8_t *nft_metadata,
+                        const uint8_t *royalty_creator_accounts,
+                        size_t royalty_creator_count,
+                        const uint8_t *insurance_pool,
+                        const uint8_t *insurance_pool_token_account,
+                        const uint8_t *basket_mints,
+                        const uint8_t *basket_token_accounts,
+                        size_t basket_asset_count,
+                        const uint8_t *settlement_program,
+                        const EscrowFfiAccountMeta *settlement_accounts,
+                        size_t settlement_account_count,
+                        const uint8_t *price_account,
+                        EscrowFfiInstruction *out);
+
+/* deposit_mint is the mint of token X, which the refund is checked against. basket_mints and
+   basket_token_accounts hold basket_asset_count pubkeys each, the mints of the escrow's basket
+   assets and the initializer's token accounts to refund them to, NULL when it has none.
+   mutual_taker_refund_account and mutual_expected_mint are the token account the taker paid
+   from and the expected mint of a mutual deposit, NULL when the escrow isn't one.
+   receipt_holder signs in the initializer's place once the receipt was sold on, NULL when the
+   initializer still holds it. */
+int32_t escrow_cancel(const uint8_t *program_id,
+                      const uint8_t *initializer,
+                      const uint8_t *temp_token_account,
+                      const uint8_t *initializer_token_account,
+                      const uint8_t *escrow_account,
+                      const uint8_t *deposit_mint,
+                      const uint8_t *audit_log,
+                      const uint8_t *receipt_holder,
+                      const uint8_t *receipt_expected_mint,
+                      const uint8_t *basket_mints,
+                      const uint8_t *basket_token_accounts,
+                      size_t basket_asset_count,
+                      const uint8_t *mutual_taker_refund_account,
+                      const uint8_t *mutual_expected_mint,
+                      EscrowFfiInstruction *out);
+
+/* creates initializer's shared vault for mint, see vault_deposit */
+int32_t escrow_init_vault(const uint8_t *program_id,
+                          const uint8_t *initializer,
+                          const uint8_t *mint,
+                          EscrowFfiInstruction *out);
+
+/* creates a wSOL account for owner holding lamports of their SOL, to pass to
+   escrow_init_escrow as the temp token account or to escrow_exchange as the taker's sending
+   account. wsol_account is a new keypair, which signs along with the owner. */
+int32_t escrow_wrap_sol(const uint8_t *program_id,
+                        const uint8_t *owner,
+                        const uint8_t *wsol_account,
+                        uint64_t lamports,
+                        EscrowFfiInstruction *out);
+
+/* allocates initializer's escrow accounts first_index..first_index + count, up to 16, which
+   escrow_init_escrow can then open offers in without creating an account */
+int32_t escrow_precreate_escrows(const uint8_t *program_id,
+                                 const uint8_t *initializer,
+                                 uint64_t first_index,
+                                 uint8_t count,
+                                 EscrowFfiInstruction *out);
+
+/* writes the 32-byte address of initializer's precreated escrow account at index to out */
+int32_t escrow_precreated_escrow_address(const uint8_t *program_id,
+                                         const uint8_t *initializer,
+                                         uint64_t index,
+                                         uint8_t *out);
+
+/* writes the 32-byte commitment a private offer records for taker to out */
+int32_t escrow_taker_commitment(const uint8_t *taker, const uint8_t *salt, uint8_t *out);
+
+int32_t escrow_decode(const uint8_t *data, size_t data_len, EscrowFfiState *out);
+
+int32_t escrow_decode_time_status(const uint8_t *data,
+                                  size_t data_len,
+                                  EscrowFfiTimeStatus *out);
+
+#endif
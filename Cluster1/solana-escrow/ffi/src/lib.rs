@@ -0,0 +1,810 @@
+use std::slice;
+
+use solana_escrow::{
+    instruction,
+    state::{
+        self, DepositAmount, Escrow, HashLock, PaymentAmount, PriceBand, QuoteConvention,
+        RoundingMode, TakerGate, TimeStatus, MAX_PAYOUTS,
+    },
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+pub const ESCROW_FFI_OK: i32 = 0;
+pub const ESCROW_FFI_NULL_POINTER: i32 = -1;
+pub const ESCROW_FFI_TOO_LARGE: i32 = -2;
+pub const ESCROW_FFI_INVALID_DATA: i32 = -3;
+
+/// Enough for an Exchange with every payout account, the audit log, a fee mint account and a
+/// settlement hook with a dozen accounts of its own
+pub const ESCROW_FFI_MAX_ACCOUNTS: usize = 32;
+pub const ESCROW_FFI_MAX_DATA: usize = instruction::MAX_INSTRUCTION_DATA_LEN;
+pub const ESCROW_DEFAULT_UNLOCK_SLOTS: u64 = state::DEFAULT_UNLOCK_SLOTS;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EscrowFfiAccountMeta {
+    pub pubkey: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// A serialized instruction in fixed-size buffers, so callers never free memory allocated on the Rust side
+#[repr(C)]
+pub struct EscrowFfiInstruction {
+    pub program_id: [u8; 32],
+    pub accounts: [EscrowFfiAccountMeta; ESCROW_FFI_MAX_ACCOUNTS],
+    pub account_count: usize,
+    pub data: [u8; ESCROW_FFI_MAX_DATA],
+    pub data_len: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EscrowFfiPayout {
+    pub token_account: [u8; 32],
+    pub bps: u16,
+}
+
+#[repr(C)]
+pub struct EscrowFfiState {
+    pub flags: u8,
+    pub initializer: [u8; 32],
+    pub temp_token_account: [u8; 32],
+    pub receive_token_account: [u8; 32],
+    pub expected_amount: u64,
+    pub unlock_time: u64,
+    pub payout_count: u8,
+    pub payouts: [EscrowFfiPayout; MAX_PAYOUTS],
+    pub config: [u8; 32],
+    pub expected_mint: [u8; 32],
+    pub init_slot: u64,
+    pub rounding_mode: u8,
+    /// Slot from which the escrow can only be reaped, 0 for never
+    pub lifetime_expiry_slot: u64,
+    pub refund_token_account: [u8; 32],
+    /// All zero for an offer any taker can fill
+    pub taker_commitment: [u8; 32],
+    /// `MintRiskFlags` bits of the deposited mint
+    pub deposit_mint_risk: u8,
+    /// The escrow's share of a shared vault, 0 when `temp_token_account` is its own
+    pub vault_deposit: u64,
+    /// Program Exchange settles the proceeds into, all zero to pay the receive account
+    pub settlement_program: [u8; 32],
+    /// Bump of the PDA owning `temp_token_account`
+    pub vault_authority_bump: u8,
+    /// Bump of the receipt mint PDA, 0 until the escrow has a receipt
+    pub receipt_mint_bump: u8,
+    /// Bump of the settlement PDA, 0 unless the escrow has a settlement hook
+    pub settlement_bump: u8,
+    /// `EscrowFeatures` bits the escrow was opened with
+    pub features: u16,
+    /// Token X partial fills have released so far
+    pub filled_amount: u64,
+    /// Slot of the last ResetTimeLock, 0 if there was none
+    pub last_reset_slot: u64,
+    /// ResetTimeLocks so far
+    pub reset_count: u8,
+    /// Lamports a taker's wallet must hold, 0 for no balance check
+    pub taker_min_lamports: u64,
+    /// Badge token takers may hold instead, all zero for none
+    pub taker_badge_mint: [u8; 32],
+    /// Mint of the token the initializer offers
+    pub deposit_mint: [u8; 32],
+    /// Token program every token CPI of the escrow must use
+    pub token_program: [u8; 32],
+    /// sha256 a Redeem's preimage must hash to, all zero for no hash lock
+    pub hash_lock_hash: [u8; 32],
+    /// The only signer Redeem releases the deposit to
+    pub hash_lock_recipient: [u8; 32],
+    /// Lamports Exchange pays the taker out of the escrow account, 0 for none
+    pub gas_rebate: u64,
+    /// Ethereum address that may sign permits for the initializer, all zero for none
+    pub permit_eth_signer: [u8; 20],
+    /// Pyth price account fills are priced at, all zero for a fixed price
+    pub price_account: [u8; 32],
+    /// Basis points the oracle price is moved by
+    pub price_offset_bps: i16,
+    /// What `expected_amount` is denominated in, a `QuoteConvention`
+    pub quote_convention: u8,
+    /// Slots after a partial fill before the next one, 0 for none
+    pub fill_cooldown_slots: u64,
+    /// Slot of the last partial fill, 0 if there was none
+    pub last_fill_slot: u64,
+    /// The only taker who may fill the offer, all zero for any taker
+    pub designated_taker: [u8; 32],
+}
+
+/// The return_data of a TimeStatus instruction. The cancel window closes at unlock.
+#[repr(C)]
+pub struct EscrowFfiTimeStatus {
+    pub current_slot: u64,
+    pub slots_until_unlock: u64,
+    pub slots_until_expiry: u64,
+    pub seconds_until_unlock: u64,
+    pub seconds_until_expiry: u64,
+}
+
+/// Pairs the `count` mints at `mints` with the `count` token accounts at `token_accounts`, or
+/// None if either is null while `count` isn't 0
+unsafe fn basket(
+    mints: *const u8,
+    token_accounts: *const u8,
+    count: usize,
+) -> Option<Vec<(Pubkey, Pubkey)>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    if mints.is_null() || token_accounts.is_null() {
+        return None;
+    }
+    let pubkeys = |ptr: *const u8| {
+        slice::from_raw_parts(ptr, count * 32)
+            .chunks(32)
+            .map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap()))
+    };
+    Some(pubkeys(mints).zip(pubkeys(token_accounts)).collect())
+}
+
+unsafe fn pubkey(ptr: *const u8) -> Option<Pubkey> {
+    if ptr.is_null() {
+        return None;
+    }
+    let bytes: [u8; 32] = slice::from_raw_parts(ptr, 32).try_into().ok()?;
+    Some(Pubkey::new_from_array(bytes))
+}
+
+fn write_instruction(ix: Instruction, out: &mut EscrowFfiInstruction) -> i32 {
+    if ix.accounts.len() > ESCROW_FFI_MAX_ACCOUNTS || ix.data.len() > ESCROW_FFI_MAX_DATA {
+        return ESCROW_FFI_TOO_LARGE;
+    }
+    out.program_id = ix.program_id.to_bytes();
+    for (meta, meta_out) in ix.accounts.iter().zip(out.accounts.iter_mut()) {
+        *meta_out = EscrowFfiAccountMeta {
+            pubkey: meta.pubkey.to_bytes(),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        };
+    }
+    out.account_count = ix.accounts.len();
+    out.data[..ix.data.len()].copy_from_slice(&ix.data);
+    out.data_len = ix.data.len();
+    ESCROW_FFI_OK
+}
+
+/// Builds an InitEscrow instruction into `out`. `payouts` may be null when `payout_count` is 0
+/// and `audit_log` may be null when the config has no audit log. `rounding_mode` is a
+/// `RoundingMode` discriminant, 0 for the default. `refund_token_account` may be null to only
+/// allow refunds to the initializer's associated token account, `allowlist_entry` when the
+/// config doesn't gate InitEscrow, and `taker_commitment` (32 bytes, see
+/// `escrow_taker_commitment`) for an offer any taker can fill. `deposit_mint` may be null,
+/// leaving the escrow's mint risk unassessed. `unlock_slots` is the cancellation window,
+/// `ESCROW_DEFAULT_UNLOCK_SLOTS` unless the initializer picks one. To deposit into the
+/// initializer's shared vault, pass it as `temp_token_account` with a non-zero `vault_deposit`
+/// taken from `vault_deposit_source`; otherwise `vault_deposit_source` may be null.
+/// `settlement_program` may be null unless the proceeds should settle into the program the
+/// config whitelists. `partial_fill` lets takers fill less than the whole deposit. Takers must
+/// then hold at least `taker_min_lamports` or some of the `taker_badge_mint` token, if either is
+/// set; `taker_badge_mint` may be null. `hash_lock_hash` (32 bytes) and `hash_lock_recipient`
+/// make the escrow a hash-timelock only the recipient may Redeem, and are both null otherwise.
+/// `gas_rebate` is paid to the taker out of the escrow account, which must hold it on top of
+/// its rent. `price_account` may be null unless fills are priced at that Pyth price account,
+/// moved by `price_offset_bps`. `fill_cooldown_slots` keeps a partially filled escrow from
+/// being filled again for that many slots, 0 for none. `designated_taker` may be null unless
+/// only that signer may fill the offer. `rate_counter` may be null unless the config limits
+/// inits per slot.
+///
+/// # Safety
+///
+/// Every non-null pubkey pointer must point to 32 readable bytes, `payouts` to `payout_count`
+/// entries, and `out` to a writable `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_init_escrow(
+    program_id: *const u8,
+    initializer: *const u8,
+    temp_token_account: *const u8,
+    receive_token_account: *const u8,
+    escrow_account: *const u8,
+    config: *const u8,
+    amount: u64,
+    payouts: *const EscrowFfiPayout,
+    payout_count: usize,
+    rounding_mode: u8,
+    audit_log: *const u8,
+    refund_token_account: *const u8,
+    allowlist_entry: *const u8,
+    taker_commitment: *const u8,
+    deposit_mint: *const u8,
+    unlock_slots: u64,
+    vault_deposit_source: *const u8,
+    vault_deposit: u64,
+    settlement_program: *const u8,
+    partial_fill: bool,
+    taker_min_lamports: u64,
+    taker_badge_mint: *const u8,
+    hash_lock_hash: *const u8,
+    hash_lock_recipient: *const u8,
+    gas_rebate: u64,
+    price_account: *const u8,
+    price_offset_bps: i16,
+    fill_cooldown_slots: u64,
+    designated_taker: *const u8,
+    rate_counter: *const u8,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let Some(rounding_mode) = RoundingMode::from_u8(rounding_mode) else {
+        return ESCROW_FFI_INVALID_DATA;
+    };
+    let payouts = match (payouts.is_null(), payout_count) {
+        (_, 0) => Vec::new(),
+        (true, _) => return ESCROW_FFI_NULL_POINTER,
+        (false, count) => slice::from_raw_parts(payouts, count)
+            .iter()
+            .map(|payout| (Pubkey::new_from_array(payout.token_account), payout.bps))
+            .collect(),
+    };
+    let (
+        Some(program_id),
+        Some(initializer),
+        Some(temp_token_account),
+        Some(receive_token_account),
+        Some(escrow_account),
+        Some(config),
+        Some(out),
+    ) = (
+        pubkey(program_id),
+        pubkey(initializer),
+        pubkey(temp_token_account),
+        pubkey(receive_token_account),
+        pubkey(escrow_account),
+        pubkey(config),
+        out.as_mut(),
+    )
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    let vault_deposit_source = pubkey(vault_deposit_source);
+    let vault_deposit = match (vault_deposit_source.as_ref(), vault_deposit) {
+        (_, 0) => None,
+        (None, _) => return ESCROW_FFI_NULL_POINTER,
+        (Some(source), amount) => Some((source, DepositAmount(amount))),
+    };
+    match instruction::init_escrow(
+        &program_id,
+        &initializer,
+        &temp_token_account,
+        &receive_token_account,
+        &escrow_account,
+        &spl_token::id(),
+        &config,
+        PaymentAmount(amount),
+        &payouts,
+        rounding_mode,
+        pubkey(taker_commitment).map(Pubkey::to_bytes),
+        unlock_slots,
+        pubkey(audit_log).as_ref(),
+        pubkey(allowlist_entry).as_ref(),
+        vault_deposit,
+        pubkey(settlement_program).as_ref(),
+        partial_fill,
+        TakerGate {
+            min_lamports: taker_min_lamports,
+            badge_mint: pubkey(taker_badge_mint).unwrap_or_default(),
+        },
+        HashLock {
+            hash: pubkey(hash_lock_hash).map(Pubkey::to_bytes).unwrap_or_default(),
+            recipient: pubkey(hash_lock_recipient).unwrap_or_default(),
+        },
+        gas_rebate,
+        PriceBand {
+            price_account: pubkey(price_account).unwrap_or_default(),
+            offset_bps: price_offset_bps,
+        },
+        // the escrows built here are spl-token ones, whose mints accrue no interest
+        QuoteConvention::Raw,
+        fill_cooldown_slots,
+        pubkey(designated_taker).as_ref(),
+        pubkey(rate_counter).as_ref(),
+        pubkey(refund_token_account).as_ref(),
+        pubkey(deposit_mint).as_ref(),
+    ) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Builds an Exchange instruction into `out`. `deposit_mint` and `expected_mint` are the mints
+/// of tokens X and Y. `payout_accounts` may be null when `payout_count` is 0, `audit_log` when
+/// the escrow isn't audited, `taker_fee_mint_account` and `fee_mint` when the config has no fee
+/// mint and `taker_salt` (32 bytes) when the offer isn't private.
+/// `valid_until_slot` is the last slot the fill may land in, 0 for no deadline, and
+/// `fill_amount` how much of the deposit to take, 0 for all of it. `max_payment` is the most
+/// token Y the taker will pay, 0 for no limit. `pair_stats` may be null
+/// unless the config has a volume cap, `taker_allowlist` unless the escrow restricts its
+/// takers, and `taker_badge_account` unless its taker gate has a badge mint. `nft_metadata` is
+/// the metadata account of an NFT deposit, followed by the `royalty_creator_count` creator token
+/// accounts in `royalty_creator_accounts`, and may be null for other deposits.
+/// `insurance_pool_token_account` is required along with `insurance_pool` for an insured
+/// escrow, and both may be null otherwise. For an escrow with a basket, `basket_mints` holds the
+/// mint of each of its `basket_asset_count` assets, in its order, and `basket_token_accounts`
+/// the taker's token accounts receiving them; both may be null when the count is 0.
+/// For an escrow with a settlement hook, `initializer_receive_token_account` is the staging account and
+/// `settlement_program` is followed by the `settlement_account_count` accounts it needs;
+/// otherwise it and `settlement_accounts` may be null. `price_account` is the escrow's Pyth
+/// price account when it's oracle-priced, and null otherwise.
+///
+/// # Safety
+///
+/// Every non-null pubkey pointer must point to 32 readable bytes, `payout_accounts` to
+/// `payout_count` consecutive pubkeys, `royalty_creator_accounts` to `royalty_creator_count`,
+/// `basket_mints` and `basket_token_accounts` to `basket_asset_count` each, `settlement_accounts` to `settlement_account_count` entries, and `out` to a writable
+/// `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_exchange(
+    program_id: *const u8,
+    taker: *const u8,
+    taker_sending_token_account: *const u8,
+    taker_receiving_token_account: *const u8,
+    temp_token_account: *const u8,
+    initializer: *const u8,
+    initializer_receive_token_account: *const u8,
+    escrow_account: *const u8,
+    config: *const u8,
+    fee_token_account: *const u8,
+    deposit_mint: *const u8,
+    expected_mint: *const u8,
+    amount: u64,
+    payout_accounts: *const u8,
+    payout_count: usize,
+    audit_log: *const u8,
+    taker_fee_mint_account: *const u8,
+    fee_mint: *const u8,
+    taker_salt: *const u8,
+    valid_until_slot: u64,
+    fill_amount: u64,
+    max_payment: u64,
+    pair_stats: *const u8,
+    taker_allowlist: *const u8,
+    taker_badge_account: *const u8,
+    nft_metadata: *const u8,
+    royalty_creator_accounts: *const u8,
+    royalty_creator_count: usize,
+    insurance_pool: *const u8,
+    insurance_pool_token_account: *const u8,
+    basket_mints: *const u8,
+    basket_token_accounts: *const u8,
+    basket_asset_count: usize,
+    settlement_program: *const u8,
+    settlement_accounts: *const EscrowFfiAccountMeta,
+    settlement_account_count: usize,
+    price_account: *const u8,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let settlement_accounts: Vec<AccountMeta> =
+        match (settlement_accounts.is_null(), settlement_account_count) {
+            (_, 0) => Vec::new(),
+            (true, _) => return ESCROW_FFI_NULL_POINTER,
+            (false, count) => slice::from_raw_parts(settlement_accounts, count)
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: Pubkey::new_from_array(meta.pubkey),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+        };
+    let settlement_program = pubkey(settlement_program);
+    let payout_accounts: Vec<Pubkey> = match (payout_accounts.is_null(), payout_count) {
+        (_, 0) => Vec::new(),
+        (true, _) => return ESCROW_FFI_NULL_POINTER,
+        (false, count) => slice::from_raw_parts(payout_accounts, count * 32)
+            .chunks(32)
+            .map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap()))
+            .collect(),
+    };
+    let nft_metadata = pubkey(nft_metadata);
+    let royalty_creator_accounts: Vec<Pubkey> =
+        match (royalty_creator_accounts.is_null(), royalty_creator_count) {
+            (_, 0) => Vec::new(),
+            (true, _) => return ESCROW_FFI_NULL_POINTER,
+            (false, count) => slice::from_raw_parts(royalty_creator_accounts, count * 32)
+                .chunks(32)
+                .map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap()))
+                .collect(),
+        };
+    let (
+        Some(program_id),
+        Some(taker),
+        Some(taker_sending_token_account),
+        Some(taker_receiving_token_account),
+        Some(temp_token_account),
+        Some(initializer),
+        Some(initializer_receive_token_account),
+        Some(escrow_account),
+        Some(config),
+        Some(fee_token_account),
+        Some(deposit_mint),
+        Some(expected_mint),
+        Some(out),
+    ) = (
+        pubkey(program_id),
+        pubkey(taker),
+        pubkey(taker_sending_token_account),
+        pubkey(taker_receiving_token_account),
+        pubkey(temp_token_account),
+        pubkey(initializer),
+        pubkey(initializer_receive_token_account),
+        pubkey(escrow_account),
+        pubkey(config),
+        pubkey(fee_token_account),
+        pubkey(deposit_mint),
+        pubkey(expected_mint),
+        out.as_mut(),
+    )
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    let taker_fee_mint = match (pubkey(taker_fee_mint_account), pubkey(fee_mint)) {
+        (None, _) => None,
+        (Some(_), None) => return ESCROW_FFI_NULL_POINTER,
+        (Some(account), Some(fee_mint)) => Some((account, fee_mint)),
+    };
+    let insurance = match (pubkey(insurance_pool), pubkey(insurance_pool_token_account)) {
+        (None, _) => None,
+        (Some(_), None) => return ESCROW_FFI_NULL_POINTER,
+        (Some(pool), Some(pool_token_account)) => Some((pool, pool_token_account)),
+    };
+    let Some(basket) = basket(basket_mints, basket_token_accounts, basket_asset_count) else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    match instruction::exchange(
+        &program_id,
+        &taker,
+        &taker_sending_token_account,
+        &taker_receiving_token_account,
+        &temp_token_account,
+        &initializer,
+        &initializer_receive_token_account,
+        &escrow_account,
+        &spl_token::id(),
+        &config,
+        &fee_token_account,
+        &deposit_mint,
+        &expected_mint,
+        DepositAmount(amount),
+        pubkey(taker_salt).map(Pubkey::to_bytes),
+        valid_until_slot,
+        DepositAmount(fill_amount),
+        PaymentAmount(max_payment),
+        &payout_accounts,
+        pubkey(audit_log).as_ref(),
+        taker_fee_mint.as_ref().map(|(account, fee_mint)| (account, fee_mint)),
+        pubkey(pair_stats).as_ref(),
+        pubkey(taker_allowlist).as_ref(),
+        pubkey(taker_badge_account).as_ref(),
+        nft_metadata
+            .as_ref()
+            .map(|metadata| (metadata, royalty_creator_accounts.as_slice())),
+        insurance.as_ref().map(|(pool, pool_token_account)| (pool, pool_token_account)),
+        &basket,
+        settlement_program
+            .as_ref()
+            .map(|program| (program, settlement_accounts.as_slice())),
+        pubkey(price_account).as_ref(),
+    ) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Builds a Cancel instruction into `out`. `deposit_mint` is the mint of token X. `audit_log`
+/// may be null when the escrow isn't audited, and `receipt_expected_mint` when it has no receipt.
+/// `receipt_holder` signs in the initializer's place when someone else holds the receipt, and
+/// may be null when the initializer still does.
+/// `basket_mints` and `basket_token_accounts` are the mints of the escrow's `basket_asset_count`
+/// basket assets and the initializer's token accounts to refund them to, and may be null when
+/// it has no basket. `mutual_taker_refund_account` and `mutual_expected_mint` are the token
+/// account the taker paid from and the expected mint of a mutual deposit, and may be null when
+/// the escrow isn't one.
+///
+/// # Safety
+///
+/// Every non-null pubkey pointer must point to 32 readable bytes, `basket_mints` and
+/// `basket_token_accounts` to `basket_asset_count` consecutive pubkeys each, and `out` to a
+/// writable `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_cancel(
+    program_id: *const u8,
+    initializer: *const u8,
+    temp_token_account: *const u8,
+    initializer_token_account: *const u8,
+    escrow_account: *const u8,
+    deposit_mint: *const u8,
+    audit_log: *const u8,
+    receipt_holder: *const u8,
+    receipt_expected_mint: *const u8,
+    basket_mints: *const u8,
+    basket_token_accounts: *const u8,
+    basket_asset_count: usize,
+    mutual_taker_refund_account: *const u8,
+    mutual_expected_mint: *const u8,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let Some(basket) = basket(basket_mints, basket_token_accounts, basket_asset_count) else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    let mutual_taker_refund_account = pubkey(mutual_taker_refund_account);
+    let mutual_expected_mint = pubkey(mutual_expected_mint);
+    let (
+        Some(program_id),
+        Some(initializer),
+        Some(temp_token_account),
+        Some(initializer_token_account),
+        Some(escrow_account),
+        Some(deposit_mint),
+        Some(out),
+    ) = (
+        pubkey(program_id),
+        pubkey(initializer),
+        pubkey(temp_token_account),
+        pubkey(initializer_token_account),
+        pubkey(escrow_account),
+        pubkey(deposit_mint),
+        out.as_mut(),
+    )
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    let receipt_holder = pubkey(receipt_holder).unwrap_or(initializer);
+    let receipt =
+        pubkey(receipt_expected_mint).map(|expected_mint| (receipt_holder, expected_mint));
+    match instruction::cancel(
+        &program_id,
+        &initializer,
+        &temp_token_account,
+        &initializer_token_account,
+        &escrow_account,
+        &spl_token::id(),
+        &deposit_mint,
+        pubkey(audit_log).as_ref(),
+        receipt.as_ref().map(|(holder, expected_mint)| (holder, expected_mint)),
+        &basket,
+        mutual_taker_refund_account.as_ref().zip(mutual_expected_mint.as_ref()),
+    ) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Builds an InitVault instruction into `out`, creating `initializer`'s shared vault for `mint`
+///
+/// # Safety
+///
+/// Every pubkey pointer must point to 32 readable bytes and `out` to a writable
+/// `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_init_vault(
+    program_id: *const u8,
+    initializer: *const u8,
+    mint: *const u8,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let (Some(program_id), Some(initializer), Some(mint), Some(out)) =
+        (pubkey(program_id), pubkey(initializer), pubkey(mint), out.as_mut())
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    match instruction::init_vault(&program_id, &initializer, &mint, &spl_token::id()) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Builds a WrapSol instruction into `out`, creating a wSOL account for `owner` holding
+/// `lamports` of their SOL. `wsol_account` is a new keypair, which signs along with the owner.
+///
+/// # Safety
+///
+/// Every pubkey pointer must point to 32 readable bytes and `out` to a writable
+/// `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_wrap_sol(
+    program_id: *const u8,
+    owner: *const u8,
+    wsol_account: *const u8,
+    lamports: u64,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let (Some(program_id), Some(owner), Some(wsol_account), Some(out)) =
+        (pubkey(program_id), pubkey(owner), pubkey(wsol_account), out.as_mut())
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    match instruction::wrap_sol(&program_id, &owner, &wsol_account, &spl_token::id(), lamports) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Builds a PrecreateEscrows instruction into `out`, allocating `initializer`'s escrow accounts
+/// `first_index..first_index + count` for later InitEscrows
+///
+/// # Safety
+///
+/// Every pubkey pointer must point to 32 readable bytes and `out` to a writable
+/// `EscrowFfiInstruction`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_precreate_escrows(
+    program_id: *const u8,
+    initializer: *const u8,
+    first_index: u64,
+    count: u8,
+    out: *mut EscrowFfiInstruction,
+) -> i32 {
+    let (Some(program_id), Some(initializer), Some(out)) =
+        (pubkey(program_id), pubkey(initializer), out.as_mut())
+    else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    match instruction::precreate_escrows(&program_id, &initializer, first_index, count) {
+        Ok(ix) => write_instruction(ix, out),
+        Err(_) => ESCROW_FFI_INVALID_DATA,
+    }
+}
+
+/// Writes the address of `initializer`'s precreated escrow account at `index` to the 32 bytes
+/// at `out`
+///
+/// # Safety
+///
+/// `program_id` and `initializer` must each point to 32 readable bytes and `out` to 32
+/// writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_precreated_escrow_address(
+    program_id: *const u8,
+    initializer: *const u8,
+    index: u64,
+    out: *mut u8,
+) -> i32 {
+    let (Some(program_id), Some(initializer)) = (pubkey(program_id), pubkey(initializer)) else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    if out.is_null() {
+        return ESCROW_FFI_NULL_POINTER;
+    }
+    let address = instruction::precreated_escrow_pda(&program_id, &initializer, index);
+    slice::from_raw_parts_mut(out, 32).copy_from_slice(address.as_ref());
+    ESCROW_FFI_OK
+}
+
+/// Writes the commitment a private offer's InitEscrow records for `taker` to the 32 bytes at
+/// `out`. The taker later reveals `salt` to fill it.
+///
+/// # Safety
+///
+/// `taker`, `salt` and `out` must each point to 32 bytes, `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_taker_commitment(
+    taker: *const u8,
+    salt: *const u8,
+    out: *mut u8,
+) -> i32 {
+    let (Some(taker), Some(salt)) = (pubkey(taker), pubkey(salt)) else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    if out.is_null() {
+        return ESCROW_FFI_NULL_POINTER;
+    }
+    let commitment = state::taker_commitment(&taker, &salt.to_bytes());
+    slice::from_raw_parts_mut(out, 32).copy_from_slice(&commitment);
+    ESCROW_FFI_OK
+}
+
+/// Decodes an initialized escrow account's data into `out`
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes and `out` to a writable `EscrowFfiState`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_decode(
+    data: *const u8,
+    data_len: usize,
+    out: *mut EscrowFfiState,
+) -> i32 {
+    let Some(out) = out.as_mut() else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    if data.is_null() {
+        return ESCROW_FFI_NULL_POINTER;
+    }
+    let Ok(escrow) = Escrow::unpack(slice::from_raw_parts(data, data_len)) else {
+        return ESCROW_FFI_INVALID_DATA;
+    };
+
+    let mut payouts = [EscrowFfiPayout {
+        token_account: [0; 32],
+        bps: 0,
+    }; MAX_PAYOUTS];
+    for (payout, payout_out) in escrow.payouts.iter().zip(payouts.iter_mut()) {
+        payout_out.token_account = payout.token_account_pubkey.to_bytes();
+        payout_out.bps = payout.bps;
+    }
+    *out = EscrowFfiState {
+        flags: escrow.flags.bits(),
+        initializer: escrow.initializer_pubkey.to_bytes(),
+        temp_token_account: escrow.temp_token_account_pubkey.to_bytes(),
+        receive_token_account: escrow
+            .initializer_token_to_receive_account_pubkey
+            .to_bytes(),
+        expected_amount: escrow.expected_amount.0,
+        unlock_time: escrow.unlock_time,
+        payout_count: escrow.payout_count,
+        payouts,
+        config: escrow.config_pubkey.to_bytes(),
+        expected_mint: escrow.expected_mint.to_bytes(),
+        init_slot: escrow.init_slot,
+        rounding_mode: escrow.rounding_mode as u8,
+        lifetime_expiry_slot: escrow.lifetime_expiry_slot,
+        refund_token_account: escrow.refund_token_account_pubkey.to_bytes(),
+        taker_commitment: escrow.taker_commitment,
+        deposit_mint_risk: escrow.deposit_mint_risk.bits(),
+        vault_deposit: escrow.vault_deposit.0,
+        settlement_program: escrow.settlement_program.to_bytes(),
+        vault_authority_bump: escrow.vault_authority_bump,
+        receipt_mint_bump: escrow.receipt_mint_bump,
+        settlement_bump: escrow.settlement_bump,
+        features: escrow.features.bits(),
+        filled_amount: escrow.filled_amount.0,
+        last_reset_slot: escrow.last_reset_slot,
+        reset_count: escrow.reset_count,
+        taker_min_lamports: escrow.taker_gate.min_lamports,
+        taker_badge_mint: escrow.taker_gate.badge_mint.to_bytes(),
+        deposit_mint: escrow.deposit_mint.to_bytes(),
+        token_program: escrow.token_program.to_bytes(),
+        hash_lock_hash: escrow.hash_lock.hash,
+        hash_lock_recipient: escrow.hash_lock.recipient.to_bytes(),
+        gas_rebate: escrow.gas_rebate,
+        permit_eth_signer: escrow.permit_eth_signer,
+        price_account: escrow.price_band.price_account.to_bytes(),
+        price_offset_bps: escrow.price_band.offset_bps,
+        quote_convention: escrow.quote_convention as u8,
+        fill_cooldown_slots: escrow.fill_cooldown_slots,
+        last_fill_slot: escrow.last_fill_slot,
+        designated_taker: escrow.designated_taker.to_bytes(),
+    };
+    ESCROW_FFI_OK
+}
+
+/// Decodes the return_data of a TimeStatus instruction into `out`
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes and `out` to a writable
+/// `EscrowFfiTimeStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn escrow_decode_time_status(
+    data: *const u8,
+    data_len: usize,
+    out: *mut EscrowFfiTimeStatus,
+) -> i32 {
+    let Some(out) = out.as_mut() else {
+        return ESCROW_FFI_NULL_POINTER;
+    };
+    if data.is_null() {
+        return ESCROW_FFI_NULL_POINTER;
+    }
+    let Some(status) = TimeStatus::unpack(slice::from_raw_parts(data, data_len)) else {
+        return ESCROW_FFI_INVALID_DATA;
+    };
+    *out = EscrowFfiTimeStatus {
+        current_slot: status.current_slot,
+        slots_until_unlock: status.slots_until_unlock,
+        slots_until_expiry: status.slots_until_expiry,
+        seconds_until_unlock: status.seconds_until_unlock,
+        seconds_until_expiry: status.seconds_until_expiry,
+    };
+    ESCROW_FFI_OK
+}
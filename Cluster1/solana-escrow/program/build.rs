@@ -0,0 +1,98 @@
+//! Embeds where the program was built from, read back on-chain by the BuildInfo instruction.
+//! See `src/build_info.rs`. Also generates the error catalog behind
+//! `error::ERROR_CATALOG_JSON` from the `EscrowError` enum in `src/error.rs`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8(out.stdout).ok().map(|s| s.trim().to_string())
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// One JSON object per `EscrowError` variant, in declaration order so each one's `code` is
+/// its `ProgramError::Custom` value. `message` is the `#[error]` text and `description` its
+/// doc comment, for front-ends to key localized messages on `name`.
+fn error_catalog(source: &str) -> String {
+    let body = source
+        .split("pub enum EscrowError {")
+        .nth(1)
+        .and_then(|rest| rest.split("\n}").next())
+        .expect("src/error.rs declares EscrowError");
+    let mut entries = Vec::new();
+    let mut docs: Vec<&str> = Vec::new();
+    let mut message = "";
+    for line in body.lines().map(str::trim) {
+        if let Some(doc) = line.strip_prefix("///") {
+            docs.push(doc.trim());
+        } else if let Some(text) = line.strip_prefix("#[error(\"").and_then(|l| l.strip_suffix("\")]")) {
+            message = text;
+        } else if let Some(name) = line.strip_suffix(',') {
+            entries.push(format!(
+                "  {{\"code\": {}, \"name\": {}, \"message\": {}, \"description\": {}}}",
+                entries.len(),
+                json_string(name),
+                json_string(message),
+                json_string(&docs.join(" ")),
+            ));
+            docs.clear();
+            message = "";
+        }
+    }
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+fn main() {
+    let git_hash = output("git", &["rev-parse", "HEAD"]).unwrap_or_default();
+    // untracked files don't change the binary, so only tracked modifications count
+    let dirty = matches!(
+        output("git", &["status", "--porcelain", "--untracked-files=no"]),
+        Some(status) if !status.is_empty()
+    );
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_default();
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=ESCROW_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=ESCROW_GIT_DIRTY={}", dirty);
+    println!("cargo:rustc-env=ESCROW_RUSTC_VERSION={}", rustc_version);
+    println!("cargo:rustc-env=ESCROW_FEATURES={}", features.join(","));
+
+    let error_source = fs::read_to_string("src/error.rs").expect("read src/error.rs");
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set for build scripts");
+    fs::write(Path::new(&out_dir).join("error_catalog.json"), error_catalog(&error_source))
+        .expect("write the error catalog");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    for path in ["logs/HEAD", "index"] {
+        if let Some(path) = output("git", &["rev-parse", "--git-path", path]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
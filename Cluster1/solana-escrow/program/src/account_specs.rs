@@ -0,0 +1,725 @@
+//! Machine-readable form of the "Accounts expected" lists documented on each
+//! [EscrowInstruction](../instruction/enum.EscrowInstruction.html) variant, for SDK generators
+//! and transaction inspectors. Keep both in sync with the processor when accounts change.
+
+/// Whether an account must be passed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccountPresence {
+    Required,
+    /// Only passed when the state involved calls for it, e.g. the audit log of an audited escrow
+    Conditional,
+    /// The caller may leave it out
+    Optional,
+    /// One account per payout of the escrow, possibly none
+    PerPayout,
+    /// One account per creator owed royalties by the deposited NFT's metadata, possibly none
+    PerRoyaltyCreator,
+    /// One account per asset in the escrow's basket, interleaved with the other entries of
+    /// this presence in the list's order, possibly none
+    PerBasketAsset,
+    /// As many consecutive accounts as the instruction data's count asks for
+    Repeated,
+}
+
+/// One entry of an instruction's account list
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AccountSpec {
+    /// Position in the list when every entry is passed exactly once. Entries after a
+    /// non-required one shift by however many accounts it actually took.
+    pub index: u8,
+    pub name: &'static str,
+    pub writable: bool,
+    pub signer: bool,
+    pub presence: AccountPresence,
+}
+
+const fn required(index: u8, name: &'static str, writable: bool, signer: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer,
+        presence: AccountPresence::Required,
+    }
+}
+
+const fn conditional(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::Conditional,
+    }
+}
+
+const fn optional(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::Optional,
+    }
+}
+
+const fn per_payout(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::PerPayout,
+    }
+}
+
+const fn per_royalty_creator(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::PerRoyaltyCreator,
+    }
+}
+
+const fn per_basket_asset(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::PerBasketAsset,
+    }
+}
+
+const fn repeated(index: u8, name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        index,
+        name,
+        writable,
+        signer: false,
+        presence: AccountPresence::Repeated,
+    }
+}
+
+pub const INIT_ESCROW_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "receive_token_account", false, false),
+    required(3, "escrow_account", true, false),
+    required(4, "token_program", false, false),
+    required(5, "stats", true, false),
+    required(6, "config", false, false),
+    per_payout(7, "payout_token_account", false),
+    conditional(8, "audit_log", true),
+    conditional(9, "allowlist_entry", false),
+    conditional(10, "vault_deposit_source", true),
+    conditional(11, "rate_counter", true),
+    optional(12, "refund_token_account", false),
+    optional(13, "deposit_mint", false),
+];
+
+pub const EXCHANGE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "taker", true, true),
+    required(1, "taker_send_token_account", true, false),
+    required(2, "taker_receive_token_account", true, false),
+    required(3, "temp_token_account", true, false),
+    required(4, "initializer_main_account", true, false),
+    required(5, "initializer_receive_token_account", true, false),
+    required(6, "escrow_account", true, false),
+    required(7, "token_program", false, false),
+    required(8, "vault_authority", false, false),
+    required(9, "stats", true, false),
+    required(10, "config", false, false),
+    required(11, "fee_token_account", true, false),
+    required(12, "deposit_mint", false, false),
+    required(13, "expected_mint", false, false),
+    conditional(14, "price_account", false),
+    per_payout(15, "payout_token_account", true),
+    conditional(16, "audit_log", true),
+    conditional(17, "taker_fee_mint_account", true),
+    conditional(18, "fee_mint", false),
+    conditional(19, "pair_stats", true),
+    conditional(20, "taker_allowlist", false),
+    conditional(21, "taker_badge_account", false),
+    conditional(22, "nft_metadata", false),
+    per_royalty_creator(23, "royalty_creator_token_account", true),
+    conditional(24, "insurance_pool", false),
+    conditional(25, "insurance_pool_token_account", true),
+    conditional(26, "basket", true),
+    per_basket_asset(27, "basket_vault", true),
+    per_basket_asset(28, "taker_basket_token_account", true),
+    per_basket_asset(29, "basket_mint", false),
+    conditional(30, "settlement_program", false),
+    conditional(31, "settlement_pda", false),
+];
+
+/// An Exchange's accounts but for a settlement hook's, which run to the end of the list
+pub const EXCHANGE_WITH_PERMIT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "taker", true, true),
+    required(1, "taker_send_token_account", true, false),
+    required(2, "taker_receive_token_account", true, false),
+    required(3, "temp_token_account", true, false),
+    required(4, "initializer_main_account", true, false),
+    required(5, "initializer_receive_token_account", true, false),
+    required(6, "escrow_account", true, false),
+    required(7, "token_program", false, false),
+    required(8, "vault_authority", false, false),
+    required(9, "stats", true, false),
+    required(10, "config", false, false),
+    required(11, "fee_token_account", true, false),
+    required(12, "deposit_mint", false, false),
+    required(13, "expected_mint", false, false),
+    per_payout(14, "payout_token_account", true),
+    conditional(15, "audit_log", true),
+    conditional(16, "taker_fee_mint_account", true),
+    conditional(17, "fee_mint", false),
+    conditional(18, "pair_stats", true),
+    conditional(19, "taker_allowlist", false),
+    conditional(20, "taker_badge_account", false),
+    conditional(21, "nft_metadata", false),
+    per_royalty_creator(22, "royalty_creator_token_account", true),
+    conditional(23, "insurance_pool", false),
+    conditional(24, "insurance_pool_token_account", true),
+    conditional(25, "basket", true),
+    per_basket_asset(26, "basket_vault", true),
+    per_basket_asset(27, "taker_basket_token_account", true),
+    per_basket_asset(28, "basket_mint", false),
+    required(29, "instructions_sysvar", false, false),
+];
+
+pub const RESET_TIME_LOCK_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "escrow_account", true, false),
+];
+
+pub const CANCEL_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "initializer_main_account", true, false),
+    required(3, "refund_token_account", true, false),
+    required(4, "escrow_account", true, false),
+    required(5, "token_program", false, false),
+    required(6, "vault_authority", false, false),
+    required(7, "stats", true, false),
+    required(8, "deposit_mint", false, false),
+    conditional(9, "audit_log", true),
+    conditional(10, "receipt_mint", true),
+    conditional(11, "receipt_token_account", true),
+    conditional(12, "proceeds_account", true),
+    conditional(13, "basket", true),
+    per_basket_asset(14, "basket_vault", true),
+    per_basket_asset(15, "refund_basket_token_account", true),
+    per_basket_asset(16, "basket_mint", false),
+    conditional(17, "mutual_deposit", true),
+    conditional(18, "taker_vault", true),
+    conditional(19, "taker_refund_token_account", true),
+    conditional(20, "expected_mint", false),
+];
+
+pub const INIT_STATS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "payer", true, true),
+    required(1, "stats", true, false),
+    required(2, "system_program", false, false),
+];
+
+pub const INIT_CONFIG_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", true, true),
+    required(1, "config", true, false),
+    required(2, "fee_recipient", false, false),
+    required(3, "system_program", false, false),
+];
+
+pub const UPDATE_CONFIG_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+    required(2, "fee_recipient", false, false),
+];
+
+pub const INIT_AUDIT_LOG_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", true, true),
+    required(1, "config", true, false),
+    required(2, "audit_log", true, false),
+    required(3, "system_program", false, false),
+];
+
+pub const TIME_STATUS_ACCOUNTS: &[AccountSpec] = &[required(0, "escrow_account", false, false)];
+
+pub const UPDATE_DEPLOYMENT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "upgrade_authority", true, true),
+    required(1, "deployment", true, false),
+    required(2, "program_data", false, false),
+    required(3, "system_program", false, false),
+];
+
+pub const VALIDATE_EXCHANGE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "taker", false, true),
+    required(1, "taker_send_token_account", false, false),
+    required(2, "taker_receive_token_account", false, false),
+    required(3, "temp_token_account", false, false),
+    required(4, "initializer_main_account", false, false),
+    required(5, "initializer_receive_token_account", false, false),
+    required(6, "escrow_account", false, false),
+    required(7, "token_program", false, false),
+    required(8, "vault_authority", false, false),
+    required(9, "stats", false, false),
+    required(10, "config", false, false),
+    required(11, "fee_token_account", false, false),
+    required(12, "deposit_mint", false, false),
+    required(13, "expected_mint", false, false),
+    conditional(14, "price_account", false),
+    per_payout(15, "payout_token_account", false),
+    conditional(16, "taker_fee_mint_account", false),
+    conditional(17, "fee_mint", false),
+    conditional(18, "pair_stats", false),
+    conditional(19, "taker_allowlist", false),
+    conditional(20, "taker_badge_account", false),
+    conditional(21, "nft_metadata", false),
+    per_royalty_creator(22, "royalty_creator_token_account", false),
+    conditional(23, "insurance_pool", false),
+    conditional(24, "insurance_pool_token_account", false),
+    conditional(25, "basket", false),
+    per_basket_asset(26, "basket_vault", false),
+    per_basket_asset(27, "taker_basket_token_account", false),
+    per_basket_asset(28, "basket_mint", false),
+    conditional(29, "settlement_program", false),
+    conditional(30, "settlement_pda", false),
+];
+
+pub const MINT_RECEIPT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", true, false),
+    required(2, "receipt_mint", true, false),
+    required(3, "receipt_token_account", true, false),
+    required(4, "proceeds_account", true, false),
+    required(5, "expected_mint", false, false),
+    required(6, "token_program", false, false),
+    required(7, "associated_token_program", false, false),
+    required(8, "system_program", false, false),
+    required(9, "rent_sysvar", false, false),
+];
+
+pub const CLAIM_PROCEEDS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "holder", true, true),
+    required(1, "receipt_token_account", true, false),
+    required(2, "receipt_mint", true, false),
+    required(3, "escrow_account", false, false),
+    required(4, "proceeds_account", true, false),
+    required(5, "holder_token_account", true, false),
+    required(6, "token_program", false, false),
+];
+
+pub const REAP_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "reaper", false, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "initializer_main_account", true, false),
+    required(3, "refund_token_account", true, false),
+    required(4, "escrow_account", true, false),
+    required(5, "token_program", false, false),
+    required(6, "vault_authority", false, false),
+    required(7, "stats", true, false),
+    required(8, "deposit_mint", false, false),
+    conditional(9, "audit_log", true),
+    conditional(10, "receipt_mint", true),
+    conditional(11, "receipt_token_account", false),
+    conditional(12, "proceeds_account", true),
+    conditional(13, "basket", true),
+    per_basket_asset(14, "basket_vault", true),
+    per_basket_asset(15, "refund_basket_token_account", true),
+    per_basket_asset(16, "basket_mint", false),
+];
+
+pub const SET_FEE_MINT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+    optional(2, "fee_mint", false),
+];
+
+pub const UPDATE_FEE_RATE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "fee_rate_authority", false, true),
+    required(1, "config", true, false),
+];
+
+pub const RECOVER_CLOSED_MINT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "initializer_main_account", true, false),
+    required(3, "refund_token_account", true, false),
+    required(4, "escrow_account", true, false),
+    required(5, "token_program", false, false),
+    required(6, "vault_authority", false, false),
+    required(7, "stats", true, false),
+    required(8, "closed_mint", false, false),
+    required(9, "deposit_mint", false, false),
+    conditional(10, "audit_log", true),
+];
+
+pub const SET_GATED_ROLES_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+];
+
+pub const SET_ALLOWLIST_ENTRY_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", true, true),
+    required(1, "config", false, false),
+    required(2, "member", false, false),
+    required(3, "allowlist_entry", true, false),
+    required(4, "system_program", false, false),
+];
+
+pub const BUILD_INFO_ACCOUNTS: &[AccountSpec] = &[];
+
+pub const INIT_VAULT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "vault", true, false),
+    required(2, "mint", false, false),
+    required(3, "vault_authority", false, false),
+    required(4, "token_program", false, false),
+    required(5, "system_program", false, false),
+    required(6, "rent_sysvar", false, false),
+];
+
+pub const SET_SETTLEMENT_PROGRAM_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+    optional(2, "settlement_program", false),
+];
+
+pub const SET_VOLUME_CAP_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+];
+
+pub const INIT_PAIR_STATS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "payer", true, true),
+    required(1, "config", false, false),
+    required(2, "pair_stats", true, false),
+    required(3, "deposit_mint", false, false),
+    required(4, "expected_mint", false, false),
+    required(5, "system_program", false, false),
+];
+
+pub const CANCEL_EXPIRED_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "cranker", true, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "initializer_main_account", true, false),
+    required(3, "refund_token_account", true, false),
+    required(4, "escrow_account", true, false),
+    required(5, "token_program", false, false),
+    required(6, "vault_authority", false, false),
+    required(7, "stats", true, false),
+    required(8, "deposit_mint", false, false),
+    conditional(9, "audit_log", true),
+    conditional(10, "receipt_mint", true),
+    conditional(11, "receipt_token_account", false),
+    conditional(12, "proceeds_account", true),
+    conditional(13, "basket", true),
+    per_basket_asset(14, "basket_vault", true),
+    per_basket_asset(15, "refund_basket_token_account", true),
+    per_basket_asset(16, "basket_mint", false),
+];
+
+pub const FORCE_CLOSE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "temp_token_account", true, false),
+    required(2, "initializer_main_account", true, false),
+    required(3, "refund_token_account", true, false),
+    required(4, "escrow_account", true, false),
+    required(5, "token_program", false, false),
+    required(6, "vault_authority", false, false),
+    required(7, "stats", true, false),
+    required(8, "admin", false, true),
+    required(9, "config", false, false),
+    required(10, "deposit_mint", false, false),
+    conditional(11, "audit_log", true),
+];
+
+pub const SET_PAUSED_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+];
+
+pub const SET_UNLOCK_BOUNDS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+];
+
+pub const ADD_ALLOWED_TAKER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", true, false),
+    required(2, "taker_allowlist", true, false),
+    required(3, "system_program", false, false),
+];
+
+pub const REMOVE_ALLOWED_TAKER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", false, false),
+    required(2, "taker_allowlist", true, false),
+];
+
+pub const MAKE_COUNTER_OFFER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "taker", true, true),
+    required(1, "escrow_account", false, false),
+    required(2, "counter_offer", true, false),
+    required(3, "system_program", false, false),
+];
+
+pub const ACCEPT_COUNTER_OFFER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "escrow_account", true, false),
+    required(2, "counter_offer", true, false),
+    required(3, "taker", true, false),
+    required(4, "config", false, false),
+];
+
+pub const UPDATE_EXPECTED_AMOUNT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "escrow_account", true, false),
+    required(2, "config", false, false),
+];
+
+pub const SET_ESCROW_TAGS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", false, false),
+    required(2, "escrow_tags", true, false),
+    required(3, "system_program", false, false),
+    required(4, "stats", true, false),
+];
+
+pub const WRAP_SOL_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "owner", true, true),
+    required(1, "wsol_account", true, true),
+    required(2, "native_mint", false, false),
+    required(3, "token_program", false, false),
+    required(4, "system_program", false, false),
+];
+
+pub const INIT_INSURANCE_POOL_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", true, true),
+    required(1, "config", false, false),
+    required(2, "insurance_pool", true, false),
+    required(3, "system_program", false, false),
+];
+
+pub const INSURE_ESCROW_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "escrow_account", true, false),
+    required(2, "insurance_pool", false, false),
+];
+
+pub const PAY_CLAIM_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", false, false),
+    required(2, "insurance_pool", true, false),
+    required(3, "pool_token_account", true, false),
+    required(4, "claimant_token_account", true, false),
+    required(5, "mint", false, false),
+    required(6, "token_program", false, false),
+];
+
+pub const ADD_BASKET_ASSET_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", true, false),
+    required(2, "basket", true, false),
+    required(3, "source_token_account", true, false),
+    required(4, "basket_vault", true, false),
+    required(5, "mint", false, false),
+    required(6, "token_program", false, false),
+    required(7, "associated_token_program", false, false),
+    required(8, "system_program", false, false),
+];
+
+pub const RECLAIM_BASKET_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", false, false),
+    required(2, "basket", true, false),
+    required(3, "token_program", false, false),
+    per_basket_asset(4, "basket_vault", true),
+    per_basket_asset(5, "refund_basket_token_account", true),
+    per_basket_asset(6, "basket_mint", false),
+];
+
+pub const MULTI_EXCHANGE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "matcher", false, true),
+    required(1, "token_program", false, false),
+    required(2, "stats", true, false),
+    required(3, "escrow_account_a", true, false),
+    required(4, "vault_a", true, false),
+    required(5, "initializer_a", true, false),
+    required(6, "initializer_receive_token_account_a", true, false),
+    required(7, "vault_authority_a", false, false),
+    required(8, "config_a", false, false),
+    required(9, "fee_token_account_a", true, false),
+    required(10, "deposit_mint_a", false, false),
+    required(11, "escrow_account_b", true, false),
+    required(12, "vault_b", true, false),
+    required(13, "initializer_b", true, false),
+    required(14, "initializer_receive_token_account_b", true, false),
+    required(15, "vault_authority_b", false, false),
+    required(16, "config_b", false, false),
+    required(17, "fee_token_account_b", true, false),
+    required(18, "deposit_mint_b", false, false),
+    required(19, "escrow_account_c", true, false),
+    required(20, "vault_c", true, false),
+    required(21, "initializer_c", true, false),
+    required(22, "initializer_receive_token_account_c", true, false),
+    required(23, "vault_authority_c", false, false),
+    required(24, "config_c", false, false),
+    required(25, "fee_token_account_c", true, false),
+    required(26, "deposit_mint_c", false, false),
+];
+
+pub const DEPOSIT_INITIALIZER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "escrow_account", true, false),
+    required(2, "mutual_deposit", true, false),
+    required(3, "taker_vault", true, false),
+    required(4, "expected_mint", false, false),
+    required(5, "deposit_mint", false, false),
+    required(6, "token_program", false, false),
+    required(7, "associated_token_program", false, false),
+    required(8, "system_program", false, false),
+];
+
+pub const DEPOSIT_TAKER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "taker", false, true),
+    required(1, "escrow_account", false, false),
+    required(2, "mutual_deposit", true, false),
+    required(3, "taker_sending_token_account", true, false),
+    required(4, "taker_vault", true, false),
+    required(5, "taker_receive_token_account", false, false),
+    required(6, "expected_mint", false, false),
+    required(7, "token_program", false, false),
+];
+
+pub const SETTLE_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "settler", false, true),
+    required(1, "escrow_account", true, false),
+    required(2, "temp_token_account", true, false),
+    required(3, "vault_authority", false, false),
+    required(4, "mutual_deposit", true, false),
+    required(5, "taker_vault", true, false),
+    required(6, "initializer_main_account", true, false),
+    required(7, "initializer_receive_token_account", true, false),
+    required(8, "taker_receive_token_account", true, false),
+    required(9, "token_program", false, false),
+    required(10, "stats", true, false),
+    required(11, "config", false, false),
+    required(12, "fee_token_account", true, false),
+    required(13, "deposit_mint", false, false),
+    required(14, "expected_mint", false, false),
+];
+
+pub const REDEEM_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "recipient", false, true),
+    required(1, "recipient_receive_token_account", true, false),
+    required(2, "escrow_account", true, false),
+    required(3, "temp_token_account", true, false),
+    required(4, "vault_authority", false, false),
+    required(5, "initializer_main_account", true, false),
+    required(6, "token_program", false, false),
+    required(7, "stats", true, false),
+    required(8, "config", false, false),
+    required(9, "deposit_mint", false, false),
+    conditional(10, "audit_log", true),
+];
+
+pub const SET_INIT_RATE_LIMIT_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "admin", false, true),
+    required(1, "config", true, false),
+];
+
+pub const INIT_RATE_COUNTER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "payer", true, true),
+    required(1, "config", false, false),
+    required(2, "rate_counter", true, false),
+    required(3, "system_program", false, false),
+];
+
+pub const SET_PERMIT_ETH_SIGNER_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", false, true),
+    required(1, "escrow_account", true, false),
+];
+
+pub const PRECREATE_ESCROWS_ACCOUNTS: &[AccountSpec] = &[
+    required(0, "initializer", true, true),
+    required(1, "system_program", false, false),
+    repeated(2, "escrow_account", true),
+];
+
+pub const GET_ESCROW_ACCOUNTS: &[AccountSpec] = &[required(0, "escrow_account", false, false)];
+
+pub const GET_CONFIG_ACCOUNTS: &[AccountSpec] = &[required(0, "config", false, false)];
+
+pub const GET_PAIR_STATS_ACCOUNTS: &[AccountSpec] = &[required(0, "pair_stats", false, false)];
+
+/// Every instruction's name and account list, indexed by its tag
+pub const INSTRUCTION_ACCOUNTS: &[(&str, &[AccountSpec])] = &[
+    ("InitEscrow", INIT_ESCROW_ACCOUNTS),
+    ("Exchange", EXCHANGE_ACCOUNTS),
+    ("ResetTimeLock", RESET_TIME_LOCK_ACCOUNTS),
+    ("Cancel", CANCEL_ACCOUNTS),
+    ("InitStats", INIT_STATS_ACCOUNTS),
+    ("InitConfig", INIT_CONFIG_ACCOUNTS),
+    ("UpdateConfig", UPDATE_CONFIG_ACCOUNTS),
+    ("InitAuditLog", INIT_AUDIT_LOG_ACCOUNTS),
+    ("TimeStatus", TIME_STATUS_ACCOUNTS),
+    ("UpdateDeployment", UPDATE_DEPLOYMENT_ACCOUNTS),
+    ("ValidateExchange", VALIDATE_EXCHANGE_ACCOUNTS),
+    ("MintReceipt", MINT_RECEIPT_ACCOUNTS),
+    ("ClaimProceeds", CLAIM_PROCEEDS_ACCOUNTS),
+    ("Reap", REAP_ACCOUNTS),
+    ("SetFeeMint", SET_FEE_MINT_ACCOUNTS),
+    ("UpdateFeeRate", UPDATE_FEE_RATE_ACCOUNTS),
+    ("RecoverClosedMint", RECOVER_CLOSED_MINT_ACCOUNTS),
+    ("SetGatedRoles", SET_GATED_ROLES_ACCOUNTS),
+    ("SetAllowlistEntry", SET_ALLOWLIST_ENTRY_ACCOUNTS),
+    ("BuildInfo", BUILD_INFO_ACCOUNTS),
+    ("InitVault", INIT_VAULT_ACCOUNTS),
+    ("SetSettlementProgram", SET_SETTLEMENT_PROGRAM_ACCOUNTS),
+    ("SetVolumeCap", SET_VOLUME_CAP_ACCOUNTS),
+    ("InitPairStats", INIT_PAIR_STATS_ACCOUNTS),
+    ("PrecreateEscrows", PRECREATE_ESCROWS_ACCOUNTS),
+    ("CancelExpired", CANCEL_EXPIRED_ACCOUNTS),
+    ("ForceClose", FORCE_CLOSE_ACCOUNTS),
+    ("SetPaused", SET_PAUSED_ACCOUNTS),
+    ("SetUnlockBounds", SET_UNLOCK_BOUNDS_ACCOUNTS),
+    ("AddAllowedTaker", ADD_ALLOWED_TAKER_ACCOUNTS),
+    ("RemoveAllowedTaker", REMOVE_ALLOWED_TAKER_ACCOUNTS),
+    ("MakeCounterOffer", MAKE_COUNTER_OFFER_ACCOUNTS),
+    ("AcceptCounterOffer", ACCEPT_COUNTER_OFFER_ACCOUNTS),
+    ("UpdateExpectedAmount", UPDATE_EXPECTED_AMOUNT_ACCOUNTS),
+    ("SetEscrowTags", SET_ESCROW_TAGS_ACCOUNTS),
+    ("WrapSol", WRAP_SOL_ACCOUNTS),
+    ("InitInsurancePool", INIT_INSURANCE_POOL_ACCOUNTS),
+    ("InsureEscrow", INSURE_ESCROW_ACCOUNTS),
+    ("PayClaim", PAY_CLAIM_ACCOUNTS),
+    ("AddBasketAsset", ADD_BASKET_ASSET_ACCOUNTS),
+    ("ReclaimBasket", RECLAIM_BASKET_ACCOUNTS),
+    ("MultiExchange", MULTI_EXCHANGE_ACCOUNTS),
+    ("DepositInitializer", DEPOSIT_INITIALIZER_ACCOUNTS),
+    ("DepositTaker", DEPOSIT_TAKER_ACCOUNTS),
+    ("Settle", SETTLE_ACCOUNTS),
+    ("Redeem", REDEEM_ACCOUNTS),
+    ("ExchangeWithPermit", EXCHANGE_WITH_PERMIT_ACCOUNTS),
+    ("SetInitRateLimit", SET_INIT_RATE_LIMIT_ACCOUNTS),
+    ("InitRateCounter", INIT_RATE_COUNTER_ACCOUNTS),
+    ("SetPermitEthSigner", SET_PERMIT_ETH_SIGNER_ACCOUNTS),
+];
+
+/// Name and account list of each view instruction, indexed by its tag less
+/// [VIEW_TAG_BASE](../instruction/constant.VIEW_TAG_BASE.html)
+pub const VIEW_INSTRUCTION_ACCOUNTS: &[(&str, &[AccountSpec])] = &[
+    ("GetEscrow", GET_ESCROW_ACCOUNTS),
+    ("GetConfig", GET_CONFIG_ACCOUNTS),
+    ("GetPairStats", GET_PAIR_STATS_ACCOUNTS),
+];
+
+/// Name and account list of the instruction with `tag`, from either table above
+pub fn instruction_accounts(tag: u8) -> Option<(&'static str, &'static [AccountSpec])> {
+    match tag.checked_sub(crate::instruction::VIEW_TAG_BASE) {
+        Some(view_index) => VIEW_INSTRUCTION_ACCOUNTS.get(view_index as usize).copied(),
+        None => INSTRUCTION_ACCOUNTS.get(tag as usize).copied(),
+    }
+}
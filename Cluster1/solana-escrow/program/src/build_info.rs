@@ -0,0 +1,59 @@
+//! Build provenance embedded by build.rs and written to return_data by the `BuildInfo`
+//! instruction, so a deployed binary can be matched against the audited source.
+
+use arrayref::mut_array_refs;
+
+/// The crate version the program was built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the program was built from, empty when built outside a git checkout
+pub const GIT_HASH: &str = env!("ESCROW_GIT_HASH");
+/// Whether tracked files differed from `GIT_HASH` at build time
+pub const GIT_DIRTY: &str = env!("ESCROW_GIT_DIRTY");
+/// Output of `rustc --version` for the compiler that built the program
+pub const RUSTC_VERSION: &str = env!("ESCROW_RUSTC_VERSION");
+/// Enabled cargo features, comma separated and sorted
+pub const FEATURES: &str = env!("ESCROW_FEATURES");
+
+/// Fixed-size snapshot of the constants above. Strings are zero-padded, and truncated if they
+/// don't fit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: [u8; 16],
+    pub git_hash: [u8; 40],
+    pub git_dirty: bool,
+    pub rustc_version: [u8; 64],
+    pub features: [u8; 64],
+}
+
+fn padded<const N: usize>(s: &str) -> [u8; N] {
+    let mut buf = [0u8; N];
+    let len = s.len().min(N);
+    buf[..len].copy_from_slice(&s.as_bytes()[..len]);
+    buf
+}
+
+impl BuildInfo {
+    pub const LEN: usize = 185;
+
+    pub fn current() -> Self {
+        BuildInfo {
+            version: padded(VERSION),
+            git_hash: padded(GIT_HASH),
+            git_dirty: GIT_DIRTY == "true",
+            rustc_version: padded(RUSTC_VERSION),
+            features: padded(FEATURES),
+        }
+    }
+
+    pub fn pack(&self) -> [u8; BuildInfo::LEN] {
+        let mut buf = [0u8; BuildInfo::LEN];
+        let (version_dst, git_hash_dst, git_dirty_dst, rustc_version_dst, features_dst) =
+            mut_array_refs![&mut buf, 16, 40, 1, 64, 64];
+        *version_dst = self.version;
+        *git_hash_dst = self.git_hash;
+        git_dirty_dst[0] = self.git_dirty as u8;
+        *rustc_version_dst = self.rustc_version;
+        *features_dst = self.features;
+        buf
+    }
+}
@@ -1,6 +1,10 @@
 use thiserror::Error;
 
-use solana_program::program_error::ProgramError;
+use std::ptr;
+
+use solana_program::{
+    account_info::AccountInfo, program::set_return_data, program_error::ProgramError,
+};
 
 #[derive(Error, Debug, Copy, Clone)]
 pub enum EscrowError {
@@ -16,10 +20,262 @@ pub enum EscrowError {
     /// Amount Overflow
     #[error("Amount Overflow")]
     AmountOverflow,
+    /// Invalid Payout Split
+    #[error("Invalid Payout Split")]
+    InvalidPayoutSplit,
+    /// Invalid Config
+    #[error("Invalid Config")]
+    InvalidConfig,
+    /// Expected Amount Outside Operator Limits
+    #[error("Expected Amount Outside Operator Limits")]
+    AmountOutOfBounds,
+    /// The initializer's receive account was closed or reassigned, pass their ATA instead
+    #[error("Receive Account Unavailable")]
+    ReceiveAccountUnavailable,
+    /// Receipts can't represent escrows that split their payment across payouts, settle it
+    /// into a program or can be partially filled, and an escrow with a receipt can't be
+    /// repriced
+    #[error("Receipt Not Allowed")]
+    ReceiptNotAllowed,
+    /// The escrow hasn't been filled, so there are no proceeds to claim yet
+    #[error("Escrow Not Settled")]
+    EscrowNotSettled,
+    /// The escrow outlived its config's maximum lifetime and can only be reaped
+    #[error("Escrow Expired")]
+    EscrowExpired,
+    /// Only escrows past their lifetime can be reaped
+    #[error("Escrow Not Expired")]
+    EscrowNotExpired,
+    /// One of the escrow's mints no longer exists
+    #[error("Mint Closed")]
+    MintClosed,
+    /// The signer lacks the allowlist role the config requires for this instruction
+    #[error("Not Allowlisted")]
+    NotAllowlisted,
+    /// The escrow account was already closed by a fill, cancel or reap. Clients racing another
+    /// transaction can treat this as benign.
+    #[error("Escrow Already Settled")]
+    EscrowAlreadySettled,
+    /// The taker's salt doesn't reveal them as the counterparty of a private offer
+    #[error("Taker Commitment Mismatch")]
+    TakerCommitmentMismatch,
+    /// The requested unlock duration is outside `MIN_UNLOCK_SLOTS..=MAX_UNLOCK_SLOTS`
+    #[error("Unlock Slots Out Of Bounds")]
+    UnlockSlotsOutOfBounds,
+    /// The taker's `valid_until_slot` has passed
+    #[error("Fill Deadline Passed")]
+    FillDeadlinePassed,
+    /// The escrow's time lock doesn't allow this yet, or anymore
+    #[error("Time Constraint Was Not Satisfied")]
+    TimeConstraintWasNotSatisfied,
+    /// A shared vault holds less than the deposit the escrow recorded, which should never happen
+    #[error("Vault Shortfall")]
+    VaultShortfall,
+    /// The escrow's settlement program isn't the one its config whitelists
+    #[error("Settlement Not Allowed")]
+    SettlementNotAllowed,
+    /// The settlement program didn't take all of the initializer's proceeds out of staging
+    #[error("Settlement Incomplete")]
+    SettlementIncomplete,
+    /// The fill would take the mint pair past its config's volume cap for the current window
+    #[error("Volume Cap Exceeded")]
+    VolumeCapExceeded,
+    /// The escrow combines optional features the program can't settle together, see
+    /// `EscrowFeatures`
+    #[error("Unsupported Features")]
+    UnsupportedFeatures,
+    /// The fill is empty, exceeds what's left of the deposit, is partial for an escrow without
+    /// partial fills, or is priced at nothing or at everything that's left to pay
+    #[error("Invalid Fill Amount")]
+    InvalidFillAmount,
+    /// The escrow's config admin has paused opening and filling escrows
+    #[error("Config Paused")]
+    ConfigPaused,
+    /// The escrow's time lock was reset less than `MIN_RESET_INTERVAL_SLOTS` ago, or
+    /// `MAX_TIME_LOCK_RESETS` times already
+    #[error("Time Lock Reset Limited")]
+    TimeLockResetLimited,
+    /// The escrow restricts fills to its taker allowlist and the taker isn't on it
+    #[error("Taker Not Allowed")]
+    TakerNotAllowed,
+    /// The escrow's taker allowlist already holds `MAX_ALLOWED_TAKERS` takers
+    #[error("Taker Allowlist Full")]
+    TakerAllowlistFull,
+    /// The escrow was partially filled after the counter-offer was made, leaving less deposit
+    /// than it priced
+    #[error("Counter Offer Outdated")]
+    CounterOfferOutdated,
+    /// The escrow can no longer be repriced, a partial fill has already taken part of it
+    #[error("Escrow Partially Filled")]
+    EscrowPartiallyFilled,
+    /// An escrow tag has an empty key, or two tags share a key
+    #[error("Invalid Escrow Tags")]
+    InvalidEscrowTags,
+    /// An escrow's tags can only be set in the slot it is initialized in, and only once
+    #[error("Escrow Tags Fixed")]
+    EscrowTagsFixed,
+    /// The taker meets none of the checks the escrow's taker gate sets: their wallet holds
+    /// too few lamports and they hold no badge token
+    #[error("Taker Gate Not Met")]
+    TakerGateNotMet,
+    /// The fill would cost the taker more token Y than the `max_payment` they passed
+    #[error("Payment Above Max")]
+    PaymentAboveMax,
+    /// The operator fee and the deposited NFT's royalties add up to more than the payment
+    #[error("Royalties Exceed Proceeds")]
+    RoyaltiesExceedProceeds,
+    /// The insurance premium comes to more than the payment leaves after the fee and royalties
+    #[error("Premium Exceeds Proceeds")]
+    PremiumExceedsProceeds,
+    /// The escrow's basket already holds `state::MAX_BASKET_ASSETS` other mints
+    #[error("Basket Full")]
+    BasketFull,
+    /// The escrows passed to MultiExchange don't chain into a ring: one's deposit isn't in the
+    /// mint the escrow before it expects, or falls short of its expected amount
+    #[error("Ring Mismatch")]
+    RingMismatch,
+    /// The escrow is a mutual deposit, which only Settle fills and only Cancel closes
+    #[error("Mutual Deposit Only")]
+    MutualDepositOnly,
+    /// Settle found no deposit from the escrow's taker yet
+    #[error("Taker Deposit Missing")]
+    TakerDepositMissing,
+    /// The escrow is hash-locked, which only Redeem fills
+    #[error("Hash Locked")]
+    HashLocked,
+    /// Redeem's preimage doesn't hash to the escrow's hash lock
+    #[error("Preimage Mismatch")]
+    PreimageMismatch,
+    /// ExchangeWithPermit found no ed25519 program instruction right before it verifying the
+    /// initializer's signature over exactly its permit, nor a secp256k1 one verifying the
+    /// escrow's permit Ethereum signer's
+    #[error("Permit Invalid")]
+    PermitInvalid,
+    /// The config's escrows already reached its `max_inits_per_slot` this slot, retry in the next
+    #[error("Rate Limited")]
+    RateLimited,
+    /// The escrow account doesn't hold its gas rebate on top of its rent
+    #[error("Gas Rebate Unfunded")]
+    GasRebateUnfunded,
+    /// The price account passed for an oracle-priced escrow isn't its trading Pyth price
+    #[error("Oracle Invalid")]
+    OracleInvalid,
+    /// The oracle price was last published too many slots ago, see
+    /// `state::MAX_ORACLE_STALENESS_SLOTS`
+    #[error("Oracle Stale")]
+    OracleStale,
+    /// The oracle price's confidence interval is too wide, see
+    /// `state::MAX_ORACLE_CONFIDENCE_BPS`
+    #[error("Oracle Uncertain")]
+    OracleUncertain,
+    /// The token account is frozen or can't take plain transfers, or its mint has a Token-2022
+    /// extension the escrow can't transfer through, see `primitives::assert_transferable`
+    #[error("Unsupported Token Extension")]
+    UnsupportedTokenExtension,
+    /// The escrow was partially filled less than its `fill_cooldown_slots` ago
+    #[error("Fill Cooling Down")]
+    FillCoolingDown,
+    /// The escrow names a designated taker and the taker signing isn't them
+    #[error("Not Designated Taker")]
+    NotDesignatedTaker,
 }
 
+/// JSON array describing every `EscrowError`, generated by build.rs: `code` (the
+/// `ProgramError::Custom` value), `name`, `message` (the `Display` text) and `description`.
+/// Codes are stable since variants are only ever appended, so front-ends can key localized
+/// messages on them.
+pub const ERROR_CATALOG_JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/error_catalog.json"));
+
 impl From<EscrowError> for ProgramError {
     fn from(e: EscrowError) -> Self {
         ProgramError::Custom(e as u32)
     }
 }
+
+/// Structured failure reason written to return_data when an account fails validation,
+/// so clients simulating a transaction can tell which account was rejected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FailureReason {
+    /// The `ProgramError` code, as returned by `u64::from(ProgramError)`
+    pub error_code: u64,
+    /// Index of the offending account in the instruction's account list
+    pub account_index: u8,
+}
+
+impl FailureReason {
+    pub const LEN: usize = 9;
+
+    pub fn pack(&self) -> [u8; FailureReason::LEN] {
+        let mut buf = [0u8; FailureReason::LEN];
+        buf[..8].copy_from_slice(&self.error_code.to_le_bytes());
+        buf[8] = self.account_index;
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Option<Self> {
+        if input.len() != FailureReason::LEN {
+            return None;
+        }
+        let error_code = u64::from_le_bytes(input[..8].try_into().ok()?);
+        Some(FailureReason {
+            error_code,
+            account_index: input[8],
+        })
+    }
+}
+
+/// Records a [FailureReason](struct.FailureReason.html) for the account at `account_index`
+/// in return_data and hands the error back for propagation.
+pub fn account_error<E: Into<ProgramError>>(error: E, account_index: u8) -> ProgramError {
+    let error = error.into();
+    let reason = FailureReason {
+        error_code: u64::from(error.clone()),
+        account_index,
+    };
+    set_return_data(&reason.pack());
+    error
+}
+
+/// An instruction's accounts, handed out in order like `next_account_info` takes them from a
+/// `slice::Iter`, that can also tell where any of them sits in the list. Handlers report a
+/// failing account by the account itself, so the index in the `FailureReason` is always its
+/// real position, however many optional accounts came before it.
+pub struct AccountCursor<'a, 'b> {
+    accounts: &'a [AccountInfo<'b>],
+    next: usize,
+}
+
+impl<'a, 'b> AccountCursor<'a, 'b> {
+    pub fn new(accounts: &'a [AccountInfo<'b>]) -> Self {
+        AccountCursor { accounts, next: 0 }
+    }
+
+    /// The accounts not handed out yet
+    pub fn as_slice(&self) -> &'a [AccountInfo<'b>] {
+        &self.accounts[self.next..]
+    }
+
+    /// Position of `account` in the instruction's accounts. Every account a handler holds
+    /// came from them, anything else is reported at `u8::MAX`.
+    pub fn index_of(&self, account: &AccountInfo<'b>) -> u8 {
+        self.accounts
+            .iter()
+            .position(|candidate| ptr::eq(candidate, account))
+            .map_or(u8::MAX, |index| index as u8)
+    }
+
+    /// `account_error` for `account`, at its position in the instruction's accounts
+    pub fn error<E: Into<ProgramError>>(&self, error: E, account: &AccountInfo<'b>) -> ProgramError {
+        account_error(error, self.index_of(account))
+    }
+}
+
+impl<'a, 'b> Iterator for AccountCursor<'a, 'b> {
+    type Item = &'a AccountInfo<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let account = self.accounts.get(self.next)?;
+        self.next += 1;
+        Some(account)
+    }
+}
@@ -0,0 +1,518 @@
+//! Human-readable summaries of the escrow instructions in a transaction, for wallets to show
+//! before signing. Only the transaction itself is read: amounts are the raw base units in the
+//! instruction data, and whatever on-chain state decides, like an Exchange's price or fee, is
+//! described by the account it is read from.
+
+use solana_program::{message::Message, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{
+    account_specs::{instruction_accounts, AccountPresence, AccountSpec},
+    instruction::EscrowInstruction,
+    state::{QuoteConvention, CANCEL_EXPIRED_REWARD_LAMPORTS, EXPIRY_SLOTS},
+};
+
+/// An account passed to an escrow instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectedAccount {
+    /// Its name in the instruction's [account_specs](../account_specs/index.html) entry, `None`
+    /// from the first entry whose presence depends on on-chain state
+    pub name: Option<&'static str>,
+    pub pubkey: Pubkey,
+    /// Whether the transaction has it sign
+    pub signer: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSummary {
+    /// Position of the instruction in the transaction
+    pub index: usize,
+    pub name: &'static str,
+    pub accounts: Vec<InspectedAccount>,
+    /// What the instruction does, one sentence per effect: who pays what to whom, time
+    /// locks and fees. Empty for instructions that only touch program bookkeeping.
+    pub effects: Vec<String>,
+}
+
+/// Summarizes the instructions of `message` addressed to `program_id`, in transaction order.
+/// Fails on one that doesn't unpack, which a wallet should flag rather than have the user sign
+/// blind.
+pub fn inspect(message: &Message, program_id: &Pubkey) -> Result<Vec<InstructionSummary>, ProgramError> {
+    message
+        .instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, ix)| message.account_keys.get(ix.program_id_index as usize) == Some(program_id))
+        .map(|(index, ix)| {
+            let instruction = EscrowInstruction::unpack(&ix.data)?;
+            let (name, specs) =
+                instruction_accounts(ix.data[0]).ok_or(ProgramError::InvalidInstructionData)?;
+            let keys = ix
+                .accounts
+                .iter()
+                .map(|key_index| message.account_keys.get(*key_index as usize).copied())
+                .collect::<Option<Vec<_>>>()
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            let accounts: Vec<_> = account_names(specs, &keys, &instruction)
+                .into_iter()
+                .zip(ix.accounts.iter().zip(keys))
+                .map(|(name, (key_index, pubkey))| InspectedAccount {
+                    name,
+                    pubkey,
+                    signer: (*key_index as usize) < message.header.num_required_signatures as usize,
+                })
+                .collect();
+            let effects = effects(&instruction, &accounts);
+            Ok(InstructionSummary {
+                index,
+                name,
+                accounts,
+                effects,
+            })
+        })
+        .collect()
+}
+
+/// Names `keys` after `specs` as far as the instruction data tells how many accounts each
+/// entry takes
+fn account_names(
+    specs: &[AccountSpec],
+    keys: &[Pubkey],
+    instruction: &EscrowInstruction,
+) -> Vec<Option<&'static str>> {
+    let mut names = Vec::with_capacity(keys.len());
+    for spec in specs {
+        let count = match (spec.presence, instruction) {
+            (AccountPresence::Required, _) => 1,
+            (AccountPresence::PerPayout, EscrowInstruction::InitEscrow { payout_bps, .. }) => payout_bps.len(),
+            (AccountPresence::Repeated, EscrowInstruction::PrecreateEscrows { count, .. }) => *count as usize,
+            _ => break,
+        };
+        names.extend(std::iter::repeat_n(Some(spec.name), count));
+    }
+    names.resize(keys.len(), None);
+    names
+}
+
+fn effects(instruction: &EscrowInstruction, accounts: &[InspectedAccount]) -> Vec<String> {
+    let account = |name: &str| {
+        accounts
+            .iter()
+            .find(|account| account.name == Some(name))
+            .map_or_else(|| format!("<missing {}>", name), |account| account.pubkey.to_string())
+    };
+    match instruction {
+        EscrowInstruction::InitEscrow {
+            amount,
+            payout_bps,
+            taker_commitment,
+            unlock_slots,
+            vault_deposit,
+            settlement_program,
+            partial_fill,
+            taker_gate,
+            hash_lock,
+            gas_rebate,
+            price_band,
+            quote_convention,
+            fill_cooldown_slots,
+            designated_taker,
+            ..
+        } => {
+            let mut effects = vec![if vault_deposit.0 == 0 {
+                format!(
+                    "{} locks the tokens in {} in escrow {}",
+                    account("initializer"),
+                    account("temp_token_account"),
+                    account("escrow_account"),
+                )
+            } else {
+                format!(
+                    "{} moves {} base units into their shared vault {} for escrow {}",
+                    account("initializer"),
+                    vault_deposit.0,
+                    account("temp_token_account"),
+                    account("escrow_account"),
+                )
+            }];
+            effects.push(format!(
+                "The taker must pay {} base units of the mint of {}",
+                amount.0,
+                account("receive_token_account"),
+            ));
+            if let Some(settlement_program) = settlement_program {
+                effects.push(format!("The payment is settled into program {}", settlement_program));
+            } else if payout_bps.is_empty() {
+                effects.push(format!("The payment goes to {}", account("receive_token_account")));
+            } else {
+                let payouts = accounts
+                    .iter()
+                    .filter(|account| account.name == Some("payout_token_account"))
+                    .zip(payout_bps)
+                    .map(|(account, bps)| format!("{} bps to {}", bps, account.pubkey))
+                    .collect::<Vec<_>>();
+                effects.push(format!("The payment is split {}", payouts.join(", ")));
+            }
+            effects.push(format!(
+                "The operator fee set by config {} is taken out of the payment",
+                account("config"),
+            ));
+            effects.push(format!(
+                "The initializer can cancel for {} slots, then again once it goes {} more unfilled",
+                unlock_slots, EXPIRY_SLOTS,
+            ));
+            if taker_commitment.is_some() {
+                effects.push("Only the taker committed to can fill it".to_string());
+            }
+            if let Some(designated_taker) = designated_taker {
+                effects.push(format!("Only {} can fill it", designated_taker));
+            }
+            if *partial_fill {
+                effects.push("Takers may fill part of the deposit at a time".to_string());
+            }
+            match (taker_gate.min_lamports, taker_gate.has_badge()) {
+                (0, false) => {}
+                (0, true) => effects.push(format!(
+                    "Only takers holding a {} badge can fill it",
+                    taker_gate.badge_mint,
+                )),
+                (min_lamports, false) => effects.push(format!(
+                    "Only takers whose wallet holds at least {} lamports can fill it",
+                    min_lamports,
+                )),
+                (min_lamports, true) => effects.push(format!(
+                    "Only takers whose wallet holds at least {} lamports or who hold a {} badge can fill it",
+                    min_lamports, taker_gate.badge_mint,
+                )),
+            }
+            if hash_lock.is_set() {
+                effects.push(format!(
+                    "Only {} can take the deposit, by revealing the preimage of its hash lock before it expires",
+                    hash_lock.recipient,
+                ));
+            }
+            if *gas_rebate != 0 {
+                effects.push(format!(
+                    "The taker who fills it is paid {} lamports out of {}, the initializer gets them back if it closes unfilled",
+                    gas_rebate,
+                    account("escrow_account"),
+                ));
+            }
+            if price_band.is_set() {
+                effects.push(format!(
+                    "Fills are priced at the Pyth price in {} moved by {} bps, never below the expected amount",
+                    price_band.price_account,
+                    price_band.offset_bps,
+                ));
+            }
+            if *quote_convention == QuoteConvention::InterestAdjusted {
+                effects.push(
+                    "The expected amount is in UI units of an interest-bearing mint, fills pay what it's worth in raw units at the time".to_string(),
+                );
+            }
+            if *fill_cooldown_slots != 0 {
+                effects.push(format!(
+                    "After each partial fill, nobody can fill it again for {} slots",
+                    fill_cooldown_slots,
+                ));
+            }
+            effects
+        }
+        EscrowInstruction::Exchange {
+            amount,
+            valid_until_slot,
+            fill_amount,
+            max_payment,
+            ..
+        }
+        | EscrowInstruction::ValidateExchange {
+            amount,
+            valid_until_slot,
+            fill_amount,
+            max_payment,
+            ..
+        } => {
+            let mut effects = Vec::new();
+            if matches!(instruction, EscrowInstruction::ValidateExchange { .. }) {
+                effects.push("Only checks the fill below would succeed, moving no tokens".to_string());
+            }
+            let fill = if fill_amount.0 == 0 { amount.0 } else { fill_amount.0 };
+            effects.push(format!(
+                "{} receives {} base units from {} into {}",
+                account("taker"),
+                fill,
+                account("temp_token_account"),
+                account("taker_receive_token_account"),
+            ));
+            effects.push(format!(
+                "{} pays the price escrow {} asks for them from {} to {} or the escrow's payouts",
+                account("taker"),
+                account("escrow_account"),
+                account("taker_send_token_account"),
+                account("initializer_receive_token_account"),
+            ));
+            effects.push(format!(
+                "The operator fee set by config {} goes to {}",
+                account("config"),
+                account("fee_token_account"),
+            ));
+            effects.push(format!("Fails unless the escrow holds exactly {} base units", amount.0));
+            if *valid_until_slot != 0 {
+                effects.push(format!("Fails after slot {}", valid_until_slot));
+            }
+            if max_payment.0 != 0 {
+                effects.push(format!("Fails if the price comes to more than {} base units", max_payment.0));
+            }
+            effects
+        }
+        EscrowInstruction::ResetTimeLock { unlock_slots } => vec![format!(
+            "{} extends the cancellation window of escrow {} to {} slots from now",
+            account("initializer"),
+            account("escrow_account"),
+            unlock_slots,
+        )],
+        EscrowInstruction::Cancel {} | EscrowInstruction::RecoverClosedMint => vec![format!(
+            "{} takes the deposit in {} back to {} and closes escrow {}, its rent going to {}",
+            account("initializer"),
+            account("temp_token_account"),
+            account("refund_token_account"),
+            account("escrow_account"),
+            account("initializer_main_account"),
+        )],
+        EscrowInstruction::Reap => vec![format!(
+            "{} closes escrow {} past its lifetime, refunding the deposit to {} and the rent to {}",
+            account("reaper"),
+            account("escrow_account"),
+            account("refund_token_account"),
+            account("initializer_main_account"),
+        )],
+        EscrowInstruction::CancelExpired => vec![
+            format!(
+                "{} closes expired escrow {}, refunding the deposit to {} and the rent to {}",
+                account("cranker"),
+                account("escrow_account"),
+                account("refund_token_account"),
+                account("initializer_main_account"),
+            ),
+            format!(
+                "{} earns {} lamports of the rent",
+                account("cranker"),
+                CANCEL_EXPIRED_REWARD_LAMPORTS,
+            ),
+        ],
+        EscrowInstruction::ForceClose => vec![format!(
+            "{} and config admin {} force close escrow {}, refunding what is left in {} to {}",
+            account("initializer"),
+            account("admin"),
+            account("escrow_account"),
+            account("temp_token_account"),
+            account("refund_token_account"),
+        )],
+        EscrowInstruction::MintReceipt => vec![format!(
+            "{} mints a receipt for escrow {} into {}, which from then on owns the proceeds",
+            account("initializer"),
+            account("escrow_account"),
+            account("receipt_token_account"),
+        )],
+        EscrowInstruction::ClaimProceeds => vec![format!(
+            "{} burns the receipt in {} and claims the proceeds of escrow {} into {}",
+            account("holder"),
+            account("receipt_token_account"),
+            account("escrow_account"),
+            account("holder_token_account"),
+        )],
+        EscrowInstruction::PrecreateEscrows { first_index, count } => vec![format!(
+            "{} pays the rent of {} escrow accounts, indices {} to {}",
+            account("initializer"),
+            count,
+            first_index,
+            first_index.saturating_add(*count as u64).saturating_sub(1),
+        )],
+        EscrowInstruction::InitConfig { fee_bps, .. } | EscrowInstruction::UpdateConfig { fee_bps, .. } => {
+            vec![format!(
+                "{} sets the fee of config {} to {} bps, paid to token accounts of {}",
+                account("admin"),
+                account("config"),
+                fee_bps,
+                account("fee_recipient"),
+            )]
+        }
+        EscrowInstruction::SetPaused { paused } => vec![format!(
+            "{} {} opening and filling escrows under config {}",
+            account("admin"),
+            if *paused { "pauses" } else { "resumes" },
+            account("config"),
+        )],
+        EscrowInstruction::AddAllowedTaker { taker } => vec![format!(
+            "{} lets {} fill escrow {}, which from then on only fills for its allowed takers",
+            account("initializer"),
+            taker,
+            account("escrow_account"),
+        )],
+        EscrowInstruction::RemoveAllowedTaker { taker } => vec![format!(
+            "{} stops {} from filling escrow {}",
+            account("initializer"),
+            taker,
+            account("escrow_account"),
+        )],
+        EscrowInstruction::MakeCounterOffer { expected_amount } if expected_amount.0 == 0 => vec![format!(
+            "{} withdraws their counter-offer for escrow {}",
+            account("taker"),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::MakeCounterOffer { expected_amount } => vec![format!(
+            "{} offers to pay {} base units for what is left of escrow {}",
+            account("taker"),
+            expected_amount.0,
+            account("escrow_account"),
+        )],
+        EscrowInstruction::AcceptCounterOffer => vec![format!(
+            "{} reprices escrow {} at the counter-offer of {}, open to any taker the escrow allows",
+            account("initializer"),
+            account("escrow_account"),
+            account("taker"),
+        )],
+        EscrowInstruction::UpdateExpectedAmount { expected_amount } => vec![format!(
+            "{} reprices escrow {} at {} base units",
+            account("initializer"),
+            account("escrow_account"),
+            expected_amount.0,
+        )],
+        EscrowInstruction::SetEscrowTags { tags } if tags.is_empty() => vec![format!(
+            "{} closes the tags of settled escrow {}",
+            account("initializer"),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::SetEscrowTags { tags } => vec![format!(
+            "{} tags escrow {} with {}",
+            account("initializer"),
+            account("escrow_account"),
+            tags.iter()
+                .map(|tag| format!(
+                    "{}={}",
+                    String::from_utf8_lossy(&tag.key).trim_end_matches('\0'),
+                    String::from_utf8_lossy(&tag.value).trim_end_matches('\0'),
+                ))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )],
+        EscrowInstruction::WrapSol { lamports } => vec![format!(
+            "{} wraps {} lamports of SOL into new wSOL account {}",
+            account("owner"),
+            lamports,
+            account("wsol_account"),
+        )],
+        EscrowInstruction::InitInsurancePool { premium_bps } => vec![format!(
+            "{} sets the premium of insurance pool {} to {} bps of each insured fill",
+            account("admin"),
+            account("insurance_pool"),
+            premium_bps,
+        )],
+        EscrowInstruction::InsureEscrow => vec![format!(
+            "{} insures escrow {} with pool {}, whose premium its fills pay from then on",
+            account("initializer"),
+            account("escrow_account"),
+            account("insurance_pool"),
+        )],
+        EscrowInstruction::PayClaim { amount } => vec![format!(
+            "{} pays a claim of {} base units from {} to {}",
+            account("admin"),
+            amount,
+            account("pool_token_account"),
+            account("claimant_token_account"),
+        )],
+        EscrowInstruction::AddBasketAsset { amount } => vec![format!(
+            "{} adds {} base units of {} from {} to the basket {} of escrow {}",
+            account("initializer"),
+            amount.0,
+            account("mint"),
+            account("source_token_account"),
+            account("basket"),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::MultiExchange => ["a", "b", "c"]
+            .iter()
+            .zip(["b", "c", "a"])
+            .map(|(leg, next)| {
+                format!(
+                    "{} receives the deposit of escrow {} for escrow {}, which closes",
+                    account(&format!("initializer_{}", leg)),
+                    account(&format!("escrow_account_{}", next)),
+                    account(&format!("escrow_account_{}", leg)),
+                )
+            })
+            .collect(),
+        EscrowInstruction::ReclaimBasket => vec![format!(
+            "{} takes back the basket {} of closed escrow {} and closes it",
+            account("initializer"),
+            account("basket"),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::DepositInitializer { taker } => vec![format!(
+            "{} makes escrow {} a mutual deposit for taker {}, to be paid into {}",
+            account("initializer"),
+            account("escrow_account"),
+            taker,
+            account("taker_vault"),
+        )],
+        EscrowInstruction::DepositTaker => vec![format!(
+            "{} deposits the expected amount of escrow {} from {} into {}",
+            account("taker"),
+            account("escrow_account"),
+            account("taker_sending_token_account"),
+            account("taker_vault"),
+        )],
+        EscrowInstruction::Settle => vec![
+            format!(
+                "{} receives the deposit of escrow {}, which closes",
+                account("taker_receive_token_account"),
+                account("escrow_account"),
+            ),
+            format!(
+                "{} receives the taker's payment out of {}, less the operator fee",
+                account("initializer_receive_token_account"),
+                account("taker_vault"),
+            ),
+        ],
+        EscrowInstruction::ExchangeWithPermit {
+            amount,
+            payment,
+            valid_until_slot,
+        } => vec![
+            format!(
+                "{} receives all {} base units of escrow {} from {} into {}",
+                account("taker"),
+                amount.0,
+                account("escrow_account"),
+                account("temp_token_account"),
+                account("taker_receive_token_account"),
+            ),
+            format!(
+                "{} pays {} base units, the price the initializer's permit names, from {} to {} or the escrow's payouts",
+                account("taker"),
+                payment.0,
+                account("taker_send_token_account"),
+                account("initializer_receive_token_account"),
+            ),
+            "Fails unless the instruction before it verifies the initializer's signature over the permit, or its Ethereum signer's"
+                .to_string(),
+            format!("Fails after slot {}", valid_until_slot),
+        ],
+        EscrowInstruction::SetPermitEthSigner { eth_address } if *eth_address == [0; 20] => vec![format!(
+            "{} removes the Ethereum signer of escrow {}'s permits",
+            account("initializer"),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::SetPermitEthSigner { eth_address } => vec![format!(
+            "{} lets 0x{} sign escrow {}'s permits",
+            account("initializer"),
+            eth_address.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            account("escrow_account"),
+        )],
+        EscrowInstruction::Redeem { .. } => vec![format!(
+            "{} receives the deposit of escrow {} for revealing its preimage, and the escrow closes",
+            account("recipient_receive_token_account"),
+            account("escrow_account"),
+        )],
+        _ => Vec::new(),
+    }
+}
@@ -1,75 +1,1866 @@
 use solana_program::{program_error::ProgramError, pubkey::Pubkey, instruction::{Instruction, AccountMeta}};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_transfer_hook_interface::get_extra_account_metas_address;
 use std::convert::TryInto;
 use std::mem::size_of;
 
+use crate::account_specs::{
+    AccountSpec, ACCEPT_COUNTER_OFFER_ACCOUNTS, ADD_ALLOWED_TAKER_ACCOUNTS, BUILD_INFO_ACCOUNTS,
+    CANCEL_ACCOUNTS, CANCEL_EXPIRED_ACCOUNTS, CLAIM_PROCEEDS_ACCOUNTS, EXCHANGE_ACCOUNTS,
+    FORCE_CLOSE_ACCOUNTS, GET_CONFIG_ACCOUNTS, GET_ESCROW_ACCOUNTS, GET_PAIR_STATS_ACCOUNTS,
+    INIT_AUDIT_LOG_ACCOUNTS, INIT_CONFIG_ACCOUNTS, INIT_ESCROW_ACCOUNTS, INIT_PAIR_STATS_ACCOUNTS,
+    INIT_STATS_ACCOUNTS, INIT_VAULT_ACCOUNTS, MAKE_COUNTER_OFFER_ACCOUNTS, MINT_RECEIPT_ACCOUNTS,
+    PRECREATE_ESCROWS_ACCOUNTS, REAP_ACCOUNTS, RECOVER_CLOSED_MINT_ACCOUNTS,
+    REMOVE_ALLOWED_TAKER_ACCOUNTS, RESET_TIME_LOCK_ACCOUNTS, SET_ALLOWLIST_ENTRY_ACCOUNTS,
+    SET_ESCROW_TAGS_ACCOUNTS, SET_FEE_MINT_ACCOUNTS, SET_GATED_ROLES_ACCOUNTS,
+    SET_PAUSED_ACCOUNTS, SET_SETTLEMENT_PROGRAM_ACCOUNTS, SET_UNLOCK_BOUNDS_ACCOUNTS,
+    SET_VOLUME_CAP_ACCOUNTS, TIME_STATUS_ACCOUNTS, UPDATE_CONFIG_ACCOUNTS,
+    UPDATE_DEPLOYMENT_ACCOUNTS, UPDATE_EXPECTED_AMOUNT_ACCOUNTS, UPDATE_FEE_RATE_ACCOUNTS,
+    VALIDATE_EXCHANGE_ACCOUNTS, WRAP_SOL_ACCOUNTS, INIT_INSURANCE_POOL_ACCOUNTS,
+    INSURE_ESCROW_ACCOUNTS, PAY_CLAIM_ACCOUNTS, ADD_BASKET_ASSET_ACCOUNTS, RECLAIM_BASKET_ACCOUNTS,
+    MULTI_EXCHANGE_ACCOUNTS, DEPOSIT_INITIALIZER_ACCOUNTS, DEPOSIT_TAKER_ACCOUNTS, SETTLE_ACCOUNTS,
+    REDEEM_ACCOUNTS, EXCHANGE_WITH_PERMIT_ACCOUNTS, SET_INIT_RATE_LIMIT_ACCOUNTS,
+    INIT_RATE_COUNTER_ACCOUNTS, SET_PERMIT_ETH_SIGNER_ACCOUNTS,
+};
 use crate::error::EscrowError::InvalidInstruction;
+use crate::primitives::native_mint;
+use crate::state::{
+    DepositAmount, EscrowTag, FeeTier, HashLock, PaymentAmount, PriceBand, QuoteConvention,
+    RoundingMode, TakerGate, DEFAULT_UNLOCK_SLOTS, MAX_ESCROW_TAGS, MAX_PAYOUTS, TOTAL_PAYOUT_BPS,
+};
 
+/// The longest valid encoding: an InitEscrow with every payout and optional field. Anything
+/// longer is rejected before it is parsed.
+pub const MAX_INSTRUCTION_DATA_LEN: usize =
+    1 + 8 + 1 + MAX_PAYOUTS * 2 + 2 + 1 + 32 + 8 + 8 + 32 + TakerGate::LEN + HashLock::LEN + 8
+        + PriceBand::LEN + 1 + 8 + 32;
+
+/// Which of InitEscrow's optional fields are encoded after its payout list, one bit each. The
+/// fields follow the bitmask in the order of their bits, and a field whose bit is clear isn't
+/// encoded at all. Unknown bits are rejected.
+pub struct InitEscrowFields;
+
+impl InitEscrowFields {
+    pub const ROUNDING_MODE: u16 = 1 << 0;
+    pub const TAKER_COMMITMENT: u16 = 1 << 1;
+    pub const UNLOCK_SLOTS: u16 = 1 << 2;
+    pub const VAULT_DEPOSIT: u16 = 1 << 3;
+    pub const SETTLEMENT_PROGRAM: u16 = 1 << 4;
+    /// Has no bytes of its own, the bit is the flag
+    pub const PARTIAL_FILL: u16 = 1 << 5;
+    pub const TAKER_GATE: u16 = 1 << 6;
+    pub const HASH_LOCK: u16 = 1 << 7;
+    pub const GAS_REBATE: u16 = 1 << 8;
+    pub const PRICE_BAND: u16 = 1 << 9;
+    pub const QUOTE_CONVENTION: u16 = 1 << 10;
+    pub const FILL_COOLDOWN_SLOTS: u16 = 1 << 11;
+    /// Has no bytes of its own, the bit says the optional refund token account is passed
+    pub const REFUND_TOKEN_ACCOUNT: u16 = 1 << 12;
+    /// Has no bytes of its own, the bit says the deposited mint is passed
+    pub const DEPOSIT_MINT: u16 = 1 << 13;
+    pub const DESIGNATED_TAKER: u16 = 1 << 14;
+    const KNOWN: u16 = 0b111_1111_1111_1111;
+}
+
+/// Upper bounds on the compute units each instruction consumes in its most expensive path (audit
+/// log enabled, receipts, PDA bumps found late), with headroom. Clients can request these with the
+/// compute budget program instead of the 200k default to pay lower priority fees. See
+/// [EscrowInstruction::compute_unit_limit](enum.EscrowInstruction.html#method.compute_unit_limit).
+pub const INIT_ESCROW_COMPUTE_UNITS: u32 = 40_000;
+pub const EXCHANGE_COMPUTE_UNITS: u32 = 70_000;
+/// Added to `EXCHANGE_COMPUTE_UNITS` for each payout account the escrow pays
+pub const EXCHANGE_PAYOUT_COMPUTE_UNITS: u32 = 8_000;
+pub const RESET_TIME_LOCK_COMPUTE_UNITS: u32 = 10_000;
+pub const CANCEL_COMPUTE_UNITS: u32 = 70_000;
+pub const INIT_STATS_COMPUTE_UNITS: u32 = 25_000;
+pub const INIT_CONFIG_COMPUTE_UNITS: u32 = 25_000;
+pub const UPDATE_CONFIG_COMPUTE_UNITS: u32 = 10_000;
+pub const INIT_AUDIT_LOG_COMPUTE_UNITS: u32 = 30_000;
+pub const TIME_STATUS_COMPUTE_UNITS: u32 = 5_000;
+pub const UPDATE_DEPLOYMENT_COMPUTE_UNITS: u32 = 30_000;
+pub const MINT_RECEIPT_COMPUTE_UNITS: u32 = 150_000;
+pub const CLAIM_PROCEEDS_COMPUTE_UNITS: u32 = 50_000;
+pub const REAP_COMPUTE_UNITS: u32 = 60_000;
+pub const SET_FEE_MINT_COMPUTE_UNITS: u32 = 10_000;
+pub const UPDATE_FEE_RATE_COMPUTE_UNITS: u32 = 5_000;
+pub const RECOVER_CLOSED_MINT_COMPUTE_UNITS: u32 = 60_000;
+pub const SET_GATED_ROLES_COMPUTE_UNITS: u32 = 5_000;
+pub const SET_ALLOWLIST_ENTRY_COMPUTE_UNITS: u32 = 25_000;
+pub const BUILD_INFO_COMPUTE_UNITS: u32 = 2_000;
+pub const INIT_VAULT_COMPUTE_UNITS: u32 = 20_000;
+pub const SET_SETTLEMENT_PROGRAM_COMPUTE_UNITS: u32 = 10_000;
+pub const SET_VOLUME_CAP_COMPUTE_UNITS: u32 = 5_000;
+pub const INIT_PAIR_STATS_COMPUTE_UNITS: u32 = 25_000;
+/// Per escrow account PrecreateEscrows allocates
+pub const PRECREATE_ESCROW_COMPUTE_UNITS: u32 = 25_000;
+pub const CANCEL_EXPIRED_COMPUTE_UNITS: u32 = 60_000;
+pub const FORCE_CLOSE_COMPUTE_UNITS: u32 = 60_000;
+pub const SET_PAUSED_COMPUTE_UNITS: u32 = 5_000;
+pub const SET_UNLOCK_BOUNDS_COMPUTE_UNITS: u32 = 5_000;
+pub const ADD_ALLOWED_TAKER_COMPUTE_UNITS: u32 = 25_000;
+pub const REMOVE_ALLOWED_TAKER_COMPUTE_UNITS: u32 = 5_000;
+pub const MAKE_COUNTER_OFFER_COMPUTE_UNITS: u32 = 25_000;
+pub const ACCEPT_COUNTER_OFFER_COMPUTE_UNITS: u32 = 10_000;
+pub const UPDATE_EXPECTED_AMOUNT_COMPUTE_UNITS: u32 = 10_000;
+pub const SET_ESCROW_TAGS_COMPUTE_UNITS: u32 = 25_000;
+pub const WRAP_SOL_COMPUTE_UNITS: u32 = 20_000;
+pub const INIT_INSURANCE_POOL_COMPUTE_UNITS: u32 = 25_000;
+pub const INSURE_ESCROW_COMPUTE_UNITS: u32 = 10_000;
+pub const PAY_CLAIM_COMPUTE_UNITS: u32 = 30_000;
+pub const ADD_BASKET_ASSET_COMPUTE_UNITS: u32 = 60_000;
+pub const RECLAIM_BASKET_COMPUTE_UNITS: u32 = 10_000;
+pub const MULTI_EXCHANGE_COMPUTE_UNITS: u32 = 150_000;
+pub const DEPOSIT_INITIALIZER_COMPUTE_UNITS: u32 = 60_000;
+pub const DEPOSIT_TAKER_COMPUTE_UNITS: u32 = 25_000;
+pub const SETTLE_COMPUTE_UNITS: u32 = 90_000;
+pub const REDEEM_COMPUTE_UNITS: u32 = 60_000;
+/// Added to an Exchange's units for ExchangeWithPermit's permit check and repricing
+pub const PERMIT_COMPUTE_UNITS: u32 = 10_000;
+pub const SET_INIT_RATE_LIMIT_COMPUTE_UNITS: u32 = 5_000;
+pub const INIT_RATE_COUNTER_COMPUTE_UNITS: u32 = 25_000;
+pub const SET_PERMIT_ETH_SIGNER_COMPUTE_UNITS: u32 = 5_000;
+/// Refunding a mutual deposit's taker and closing their vault
+pub const MUTUAL_DEPOSIT_COMPUTE_UNITS: u32 = 20_000;
+/// Transferring one basket asset out and closing its vault
+pub const BASKET_ASSET_COMPUTE_UNITS: u32 = 15_000;
+pub const VIEW_COMPUTE_UNITS: u32 = 5_000;
+
+/// First tag of the view instructions, which never write to an account and only set
+/// return_data, so other programs can read state through CPI
+pub const VIEW_TAG_BASE: u8 = 200;
+
+// unpacked once per instruction and never stored, so InitEscrow's optional fields stay inline
+/// Instructions moving tokens with `TransferChecked` take, after the accounts listed for them,
+/// whatever a Token-2022 transfer hook of those tokens needs: the hook program, the mint's
+/// `ExtraAccountMetaList` and each account the list names, in any order, see
+/// `append_transfer_hook_accounts`. ClaimProceeds and a shared vault's deposit at InitEscrow
+/// transfer without the mint, which Token-2022 refuses for a mint with a hook.
+#[allow(clippy::large_enum_variant)]
 pub enum EscrowInstruction {
-    /// Starts the trade by creating and populating an escrow account and transferring ownership of the given temp token account to the PDA
+    /// Starts the trade by creating and populating an escrow account and transferring ownership of the given temp token account to its vault authority PDA
     ///
     ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` The account of the person initializing the escrow
-    /// 1. `[writable]` Temporary token account that should be created prior to this instruction and owned by the initializer
+    /// 1. `[writable]` Temporary token account that should be created prior to this instruction and owned by the initializer,
+    ///    or with a `vault_deposit`, the initializer's shared vault PDA for token X created by InitVault
     /// 2. `[]` The initializer's token account for the token they will receive should the trade go through
     /// 3. `[writable]` The escrow account, it will hold all necessary info about the trade.
-    /// 4. `[]` The rent sysvar
-    /// 5. `[]` The token program
+    ///    Either a fresh rent-exempt account owned by the program or one of the initializer's
+    ///    precreated ones, see PrecreateEscrows.
+    /// 4. `[]` The token program, which the escrow records and requires from then on
+    /// 5. `[writable]` The stats PDA tracking rent locked in open escrows
+    /// 6. `[]` The operator config PDA the escrow is opened under
+    /// 7..7+N. `[]` Optional payout token accounts for token Y, one per entry in `payout_bps`
+    /// 7+N. `[writable]` The operator's audit log PDA, required if the config has one enabled
+    /// 7+N+A. `[]` The initializer's allowlist entry PDA, required if the config gates InitEscrow
+    /// 7+N+A+L. `[writable]` The initializer's token account for token X the `vault_deposit` is
+    ///    moved into the shared vault from, required with one
+    /// 7+N+A+L+S. `[writable]` The config's rate counter PDA, required if the config limits
+    ///    inits per slot
+    /// 7+N+A+L+S+C. `[]` The initializer's token account for token X that Cancel and Reap refund
+    ///    the deposit to, passed with `has_refund_token_account`. Without it they only refund to
+    ///    the initializer's associated token account.
+    /// 7+N+A+L+S+C+R. `[]` The mint of the deposited token X, passed with `has_deposit_mint` and
+    ///    assessed into the escrow's `deposit_mint_risk`. Without it the escrow is stored as
+    ///    `MintRiskFlags::UNASSESSED`.
+    ///
+    /// Each optional account sits at the position above whenever the config, the data or its flag
+    /// calls for it, and is never told apart from another by its key.
+    ///
+    /// The token accounts and the mint must be usable with the escrow's plain transfers, else
+    /// InitEscrow fails with `UnsupportedTokenExtension`, see `primitives::assert_transferable`
+    /// and `primitives::assert_supported_mint`.
+    ///
+    /// Rent is read with `Rent::get()`, the rent sysvar isn't passed.
+    ///
+    /// Data: the tag and `amount`, then optionally the payout list, a u16 `InitEscrowFields`
+    /// bitmask and each field whose bit is set, in bit order. A field whose bit is clear takes
+    /// its default, so no field is told apart from another by how many bytes follow.
     InitEscrow {
         /// The amount party A expects to receive of token Y
-        amount: u64,
+        amount: PaymentAmount,
+        /// Weights in basis points splitting the taker's payment across the payout accounts,
+        /// summing to exactly 10000. Empty to pay the receive account in full.
+        payout_bps: Vec<u16>,
+        /// How the fee is rounded, see `state::RoundingMode`. Encoded as a byte under
+        /// `InitEscrowFields::ROUNDING_MODE`.
+        rounding_mode: RoundingMode,
+        /// `state::taker_commitment` of the only taker allowed to fill the offer. Encoded as
+        /// 32 bytes under `InitEscrowFields::TAKER_COMMITMENT`, which mustn't be all zero.
+        taker_commitment: Option<[u8; 32]>,
+        /// Slots after init the initializer can cancel in, within `state::MIN_UNLOCK_SLOTS` and
+        /// `state::MAX_UNLOCK_SLOTS`. Encoded as 8 bytes under `InitEscrowFields::UNLOCK_SLOTS`.
+        /// Defaults to `state::DEFAULT_UNLOCK_SLOTS`.
+        unlock_slots: u64,
+        /// Amount of token X to deposit into the initializer's shared vault instead of handing
+        /// the escrow a vault of its own, 0 for the latter. Encoded as 8 bytes under
+        /// `InitEscrowFields::VAULT_DEPOSIT`.
+        vault_deposit: DepositAmount,
+        /// Program to settle the initializer's proceeds into on Exchange, which must be the one
+        /// the config whitelists. Not available with payouts, which fails with
+        /// UnsupportedFeatures. Encoded as 32 bytes under `InitEscrowFields::SETTLEMENT_PROGRAM`,
+        /// which mustn't be the default pubkey.
+        settlement_program: Option<Pubkey>,
+        /// Whether takers may fill less than the whole deposit. Encoded as the
+        /// `InitEscrowFields::PARTIAL_FILL` bit alone.
+        partial_fill: bool,
+        /// Who may fill the offer, see `state::TakerGate`. Encoded as the minimum lamports
+        /// (8 bytes) and badge mint (32 bytes) under `InitEscrowFields::TAKER_GATE`, and left
+        /// out for an offer open to any taker.
+        taker_gate: TakerGate,
+        /// Makes the escrow a hash-timelock for a cross-chain swap, see `state::HashLock`: only
+        /// Redeem fills it, and Cancel only refunds it from expiry on. Encoded as the hash
+        /// (32 bytes) and recipient (32 bytes) under `InitEscrowFields::HASH_LOCK`, and left out
+        /// for a plain offer. A hash without a recipient, or the other way around, is rejected.
+        /// Combining it with payouts, a taker commitment, a settlement hook, partial fills or a
+        /// taker gate fails with `UnsupportedFeatures`.
+        hash_lock: HashLock,
+        /// Lamports Exchange pays the taker toward their transaction fees, 0 for none. The
+        /// initializer funds them by creating the escrow account with this much above its
+        /// rent, or InitEscrow fails with `GasRebateUnfunded`, and gets them back with the rent
+        /// if the escrow closes unfilled. Encoded as 8 bytes under `InitEscrowFields::GAS_REBATE`.
+        /// Combining it with partial fills or a hash lock fails with `UnsupportedFeatures`.
+        gas_rebate: u64,
+        /// Prices fills off a Pyth price account instead of `amount` alone, see
+        /// `state::PriceBand`, with `amount` as the floor. Encoded as the price account
+        /// (32 bytes) and the offset in basis points (i16) under `InitEscrowFields::PRICE_BAND`,
+        /// and left out for a fixed price. An offset without a price account, or of -10000 or
+        /// less, is rejected. Combining it with a settlement hook, partial fills, a mutual
+        /// deposit or a hash lock fails with `UnsupportedFeatures`.
+        price_band: PriceBand,
+        /// What `amount` is denominated in, see `state::QuoteConvention`. Pass
+        /// `InterestAdjusted` to quote a Token-2022 interest-bearing expected mint in UI
+        /// amounts, with the interest it accrues until the fill. Encoded as a byte under
+        /// `InitEscrowFields::QUOTE_CONVENTION`. Combining it with a price band fails with
+        /// `UnsupportedFeatures`.
+        quote_convention: QuoteConvention,
+        /// Slots after each partial fill during which the escrow can't be filled again, 0 for
+        /// none, so a taker can't pick the offer off several times in one slot. Encoded as 8
+        /// bytes under `InitEscrowFields::FILL_COOLDOWN_SLOTS`. Setting it without partial
+        /// fills fails with `UnsupportedFeatures`.
+        fill_cooldown_slots: u64,
+        /// Whether the refund token account is passed. Encoded as the
+        /// `InitEscrowFields::REFUND_TOKEN_ACCOUNT` bit alone.
+        has_refund_token_account: bool,
+        /// Whether the deposited mint is passed for assessment. Encoded as the
+        /// `InitEscrowFields::DEPOSIT_MINT` bit alone.
+        has_deposit_mint: bool,
+        /// The only taker who may fill the offer, signing Exchange as the taker. Unlike a taker
+        /// commitment it's stored in the clear, see `state::Escrow::designated_taker`. Encoded
+        /// as 32 bytes under `InitEscrowFields::DESIGNATED_TAKER`, which mustn't be the default
+        /// pubkey. Combining it with a hash lock or a mutual deposit fails with
+        /// `UnsupportedFeatures`.
+        designated_taker: Option<Pubkey>,
     },
     /// Accepts a trade
     ///
     ///
     /// Accounts expected:
     ///
-    /// 0. `[signer]` The account of the person taking the trade
-    /// 1. `[writable]` The taker's token account for the token they send
-    /// 2. `[writable]` The taker's token account for the token they will receive should the trade go through
+    /// 0. `[writable, signer]` The account of the person taking the trade, receiving any SOL
+    ///    unwrapped from accounts 1 and 2
+    /// 1. `[writable]` The taker's token account for the token they send, in the escrow's expected mint.
+    ///    A wSOL account the fill empties is closed, returning its rent to the taker.
+    /// 2. `[writable]` The taker's token account for the token they will receive should the trade go through.
+    ///    Any token account the taker owns for the deposited mint, not necessarily their
+    ///    associated one. A wSOL account is closed after the fill, unwrapping it into the taker's
+    ///    wallet.
     /// 3. `[writable]` The PDA's temp token account to get tokens from and eventually close
     /// 4. `[writable]` The initializer's main account to send their rent fees to
-    /// 5. `[writable]` The initializer's token account that will receive tokens
+    /// 5. `[writable]` The initializer's token account that will receive tokens, or their
+    ///    associated token account for the expected mint if that account has been closed.
+    ///    For an escrow with a receipt, the receipt's proceeds account instead, and for one
+    ///    with a settlement hook, the settlement PDA's associated token account for the
+    ///    expected mint, which the proceeds are staged in.
     /// 6. `[writable]` The escrow account holding the escrow info
-    /// 7. `[]` The token program
-    /// 8. `[]` The PDA account
+    /// 7. `[]` The token program the escrow was opened with
+    /// 8. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 9. `[writable]` The stats PDA tracking rent locked in open escrows
+    /// 10. `[]` The operator config PDA the escrow was opened under
+    /// 11. `[writable]` The operator's fee token account for token Y, owned by the config's fee recipient
+    /// 12. `[]` The mint of token X
+    /// 13. `[]` The mint of token Y
+    /// 14. `[]` The escrow's Pyth price account, required if it is oracle-priced. Its key must
+    ///    be the one recorded at init.
+    /// 14+P..14+P+N. `[writable]` The payout token accounts registered at init, in the same order
+    /// 14+P+N. `[writable]` The operator's audit log PDA, required if the escrow is audited
+    /// 14+P+N+A. `[writable]` The taker's token account for the fee mint, required if the config
+    ///    has one. Account 11 must then be the fee recipient's account for the fee mint.
+    /// 15+P+N+A. `[]` The config's fee mint, required along with it
+    /// 14+P+N+A+F. `[writable]` The mint pair's stats PDA, derived from
+    ///    `[b"pair_stats", config, deposit_mint, expected_mint]`, required if the config has a
+    ///    volume cap
+    /// 14+P+N+A+F+V. `[]` The escrow's taker allowlist PDA, derived from `[b"takers", escrow]`,
+    ///    required if the escrow has one
+    /// 14+P+N+A+F+V+T. `[]` The taker's token account for the escrow's badge mint, required if its
+    ///    taker gate has one, even when the taker's lamports already pass it
+    /// 14+P+N+A+F+V+T+B. `[]` The deposited mint's Metaplex metadata account, derived from
+    ///    `[b"metadata", metadata program, deposit_mint]` by the metadata program, required if
+    ///    token X is an NFT (a supply of 1 and no decimals), whether or not the account exists
+    /// 15+P+N+A+F+V+T+B..15+P+N+A+F+V+T+B+C. `[writable]` A token account for token Y owned by each
+    ///    creator with a share of the NFT's royalties, in the metadata's order
+    /// 14+P+N+A+F+V+T+B+M. `[]` The config's insurance pool PDA, derived from
+    ///    `[b"insurance", config]`, required if the escrow is insured
+    /// 15+P+N+A+F+V+T+B+M. `[writable]` The pool's token account for token Y, required along with it
+    /// 14+P+N+A+F+V+T+B+M+I. `[writable]` The escrow's basket PDA, derived from `[b"basket", escrow]`,
+    ///    required if the escrow has one
+    /// 15+P+N+A+F+V+T+B+M+I..15+P+N+A+F+V+T+B+M+I+3K. For each asset in the basket, in its order:
+    ///    `[writable]` the asset's vault, `[writable]` a token account of the taker's for the
+    ///    asset's mint, and `[]` the mint
+    /// 14+P+N+A+F+V+T+B+M+I+K. `[]` The escrow's settlement program, required if it has a settlement hook
+    /// 15+P+N+A+F+V+T+B+M+I+K. `[]` The settlement PDA, derived from `[b"settlement"]`, required along with it
+    /// 16+P+N+A+F+V+T+B+M+I+K.. Whatever accounts the settlement program needs, passed through as they are
+    ///
+    /// Every transfer is a `TransferChecked` against the mint accounts passed.
+    ///
+    /// The creators of an NFT deposit get the metadata's seller fee share of the payment, split
+    /// by their shares, before the initializer is paid what is left after the operator fee.
+    /// An insured escrow's fills then pay the pool's premium share of the payment out of the
+    /// same proceeds.
+    ///
+    /// A basket goes to the taker along with the deposit, its vaults emptied and closed, and
+    /// their rent and the basket's returned to the initializer's main account.
+    ///
+    /// With a settlement hook, the settlement program is invoked once the proceeds are staged,
+    /// with the amount as a little-endian u64 and the accounts `[signer]` settlement PDA,
+    /// `[writable]` staging account, `[]` initializer's main account as the beneficiary,
+    /// `[]` token program, then the passed-through ones. It must move the whole amount out
+    /// of staging. Its compute comes on top of `compute_unit_limit`.
+    ///
+    /// A partial fill pays and settles its share like a full one, then leaves the escrow and
+    /// its vault open with the rest of the deposit and of the expected amount.
+    ///
+    /// An oracle-priced escrow's payment is the price account's quote for the deposit, moved
+    /// by its band's offset, or the expected amount if that is more. The price must have been
+    /// published within `state::MAX_ORACLE_STALENESS_SLOTS`, or the fill fails with
+    /// `OracleStale`, and its confidence be within `state::MAX_ORACLE_CONFIDENCE_BPS`, or it
+    /// fails with `OracleUncertain`. Takers should set `max_payment`.
+    ///
+    /// An escrow quoted in `state::QuoteConvention::InterestAdjusted` units is paid the raw
+    /// amount of token Y its quote is worth at the fill's unix timestamp, rounded up. A partial
+    /// fill draws its share of the quote down, not of the raw amount.
     Exchange {
         /// the amount the taker expects to be paid in the other token, as a u64 because that's the max possible supply of a token
-        amount: u64,
+        amount: DepositAmount,
+        /// Salt revealing the taker as the one committed to at init, as 32 optional bytes
+        /// after the amount. Required for private offers.
+        taker_salt: Option<[u8; 32]>,
+        /// Last slot the taker accepts the fill in, 0 for no deadline. Encoded as 8 optional
+        /// bytes after the salt, which then has to be present, all zero if there is none.
+        valid_until_slot: u64,
+        /// Token X to take out of the deposit, 0 for all of it. Less is a partial fill, which
+        /// the escrow must have been opened with `partial_fill` for, paying its share of the
+        /// expected amount rounded by the escrow's rounding mode. `amount` stays the whole
+        /// deposit left. Encoded as 8 optional bytes after the deadline, which then has to be
+        /// present. Any fill within the escrow's `fill_cooldown_slots` of its last partial fill
+        /// fails with `FillCoolingDown`.
+        fill_amount: DepositAmount,
+        /// Most raw token Y the taker will pay for the fill, 0 for no limit. The fill fails with
+        /// `PaymentAboveMax` above it, and what it comes in under is recorded as the fill's
+        /// price improvement. Encoded as 8 optional bytes after the fill amount, which then has
+        /// to be present, 0 for the whole deposit.
+        max_payment: PaymentAmount,
     },
     //Reset Time lock and time_out
+    /// Extends the escrow's cancellation window to `unlock_slots` from now. It can only be
+    /// extended while still open, so a locked offer never becomes cancellable again, at most
+    /// `state::MAX_TIME_LOCK_RESETS` times and `state::MIN_RESET_INTERVAL_SLOTS` apart.
+    ///
+    /// A mutual deposit's taker who already deposited isn't held to the window: Settle takes
+    /// them through at any slot, so a reset while they're committed only leaves the
+    /// initializer longer to Cancel and refund them, never less time to settle.
+    ///
     /// 0. `[signer]` The initializer that is reseting the timelock
     /// 1. `[writable]` The escrow account holding the escrow info
-    ResetTimeLock {n},
+    ResetTimeLock {
+        /// Slots from now the initializer can cancel in, within `state::MIN_UNLOCK_SLOTS` and
+        /// `state::MAX_UNLOCK_SLOTS`, and ending no earlier than the current window
+        unlock_slots: u64,
+    },
     //Cancel Escrow
-    /// 0. `[signer]` The initializer that is canceling their escrow
+    /// An escrow of native SOL in a vault of its own is unwrapped: its vault is closed straight
+    /// to the initializer's main account, deposit and rent together, and the refund account is
+    /// left untouched. A hash-locked escrow can't be cancelled in its cancellation window, only
+    /// from its expiry slot on, once its recipient can no longer Redeem it.
+    ///
+    /// An escrow with a receipt is cancelled by whoever holds the receipt, in the same window.
+    /// They sign in the initializer's place, burn the receipt, and are refunded the deposit and
+    /// any basket, the initializer still receiving the rent.
+    ///
+    /// 0. `[signer]` The initializer that is canceling their escrow, or the receipt holder
     /// 1. `[writable]` The PDA's temp token account to get tokens from and eventually close
-    /// 3. `[writable]` The initializer's token account that will receive tokens
+    /// 2. `[writable]` The initializer's main account, receiving the rent
+    /// 3. `[writable]` The escrow's refund account: the one recorded at init, or the initializer's
+    ///    associated token account for token X if none was. With a receipt, the holder's
+    ///    associated token account for token X
     /// 4. `[writable]` The escrow account holding the escrow info
-    /// 5. `[]` The token program
-    /// 6. `[]` The PDA account
-    Cancel { }
+    /// 5. `[]` The token program the escrow was opened with
+    /// 6. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 7. `[writable]` The stats PDA tracking rent locked in open escrows
+    /// 8. `[]` The mint of token X, which the refund is a `TransferChecked` against
+    /// 9. `[writable]` The operator's audit log PDA, required if the escrow is audited
+    /// 9+A. `[writable]` The receipt mint PDA, required if the escrow has a receipt
+    /// 10+A. `[writable]` The holder's receipt token account, whose receipt is burned
+    /// 11+A. `[writable]` The receipt's proceeds account, closed along with the escrow
+    /// 9+A+R. `[writable]` The escrow's basket PDA, required if the escrow has one
+    /// 10+A+R..10+A+R+3K. For each asset in the basket, in its order: `[writable]` the asset's
+    ///    vault, `[writable]` a token account of the signer's for the asset's mint, and
+    ///    `[]` the mint. The basket is refunded there and closed with its vaults.
+    /// 9+A. `[writable]` The mutual deposit PDA, required if the escrow is a mutual deposit
+    /// 10+A. `[writable]` The taker vault
+    /// 11+A. `[writable]` The token account the taker paid from, refunded any deposit of theirs
+    /// 12+A. `[]` The expected mint
+    Cancel { },
+    /// Creates the program-wide stats PDA that tracks rent locked in open escrows
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The account paying for the stats account
+    /// 1. `[writable]` The stats PDA, derived from `[b"stats"]`
+    /// 2. `[]` The system program
+    InitStats,
+    /// Creates an operator's config PDA, giving a front-end its own fee and limit policy
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The operator admin, paying for the config account
+    /// 1. `[writable]` The config PDA, derived from `[b"config", admin]`
+    /// 2. `[]` The wallet that will own the operator's fee token accounts
+    /// 3. `[]` The system program
+    InitConfig {
+        /// Share of the taker's payment routed to the operator, in basis points
+        fee_bps: u16,
+        /// Smallest expected amount an escrow may ask for
+        min_expected_amount: PaymentAmount,
+        /// Largest expected amount an escrow may ask for, 0 for no limit
+        max_expected_amount: PaymentAmount,
+        /// Lower fees for offers that rested on-chain at least `min_age_slots`, by ascending age
+        fee_tiers: Vec<FeeTier>,
+        /// Slots after init at which escrows under this config expire, 0 for no limit
+        max_lifetime_slots: u64,
+    },
+    /// Updates an operator's config
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    /// 2. `[]` The wallet that will own the operator's fee token accounts
+    UpdateConfig {
+        fee_bps: u16,
+        min_expected_amount: PaymentAmount,
+        max_expected_amount: PaymentAmount,
+        fee_tiers: Vec<FeeTier>,
+        max_lifetime_slots: u64,
+    },
+    /// Creates the audit log PDA for an operator config and starts recording its escrows in it
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The operator admin, paying for the audit log account
+    /// 1. `[writable]` The admin's config PDA
+    /// 2. `[writable]` The audit log PDA, derived from `[b"audit", config]`
+    /// 3. `[]` The system program
+    InitAuditLog,
+    /// Read-only probe writing the slots and estimated seconds left until the escrow unlocks,
+    /// which closes its cancel window, and until it expires to return_data, see
+    /// `state::TimeStatus`
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The escrow account
+    TimeStatus,
+    /// Records the deployed version in the deployment PDA, creating it on first use
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The program's upgrade authority, paying for the deployment account
+    /// 1. `[writable]` The deployment PDA, derived from `[b"deployment"]`
+    /// 2. `[]` The program's program data account
+    /// 3. `[]` The system program
+    UpdateDeployment {
+        major: u16,
+        minor: u16,
+        patch: u16,
+        migration_notes_hash: [u8; 32],
+    },
+    /// Read-only dry run of Exchange: runs the same signer, vault, amount, receive account,
+    /// config, fee account and payout checks without moving tokens, failing the same way
+    /// Exchange would. On success it writes a `state::ExchangeQuote` to return_data.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// The same as Exchange, all of them read-only, except the audit log isn't needed. The
+    /// taker's fee mint account and the fee mint, if any, come right after the payout accounts. The pair stats
+    /// are checked against the volume cap without recording the fill. The NFT metadata and
+    /// creator accounts are checked, and the quote carries the royalties, as do the insurance
+    /// pool accounts and the premium. The basket accounts are checked. The settlement program
+    /// and PDA are checked, but the settlement program isn't invoked or passed anything. An
+    /// oracle-priced escrow's price account comes right after the mints, and the quote is at
+    /// its price.
+    ValidateExchange {
+        amount: DepositAmount,
+        taker_salt: Option<[u8; 32]>,
+        valid_until_slot: u64,
+        fill_amount: DepositAmount,
+        max_payment: PaymentAmount,
+    },
+    /// Mints a single receipt token for an open escrow to the initializer, making the position
+    /// transferable. Whoever holds the receipt when the escrow is filled can claim the payment
+    /// with ClaimProceeds, and only they can cancel, burning it. Not available for
+    /// escrows with payouts, a settlement hook or partial fills, nor for an expected mint with a
+    /// transfer hook, which ClaimProceeds' transfer couldn't run.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The initializer, paying for the new accounts
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The receipt mint PDA, derived from `[b"receipt", escrow]`
+    /// 3. `[writable]` The initializer's associated token account for the receipt mint
+    /// 4. `[writable]` The proceeds account: the receipt mint PDA's associated token account
+    ///    for the escrow's expected mint, which Exchange pays into
+    /// 5. `[]` The escrow's expected mint
+    /// 6. `[]` The token program the escrow was opened with
+    /// 7. `[]` The associated token account program
+    /// 8. `[]` The system program
+    /// 9. `[]` The rent sysvar
+    MintReceipt,
+    /// Burns a receipt of a filled escrow and pays its holder the proceeds
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The receipt holder, receiving the proceeds account's rent
+    /// 1. `[writable]` The holder's receipt token account
+    /// 2. `[writable]` The receipt mint PDA
+    /// 3. `[]` The escrow account, closed by the fill; only its address is used
+    /// 4. `[writable]` The receipt's proceeds account
+    /// 5. `[writable]` The holder's token account for the escrow's expected mint
+    /// 6. `[]` The token program
+    ClaimProceeds,
+    /// Closes an escrow past its config's maximum lifetime, refunding the deposit to the
+    /// initializer and their rent to their main account. Anyone may call it. An escrow with a
+    /// receipt refunds its holder instead, to their associated token account for token X.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The reaper
+    /// 1. `[writable]` The PDA's temp token account
+    /// 2. `[writable]` The initializer's main account, receiving the rent
+    /// 3. `[writable]` The escrow's refund account, as for Cancel
+    /// 4. `[writable]` The escrow account
+    /// 5. `[]` The token program the escrow was opened with
+    /// 6. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 7. `[writable]` The stats PDA
+    /// 8. `[]` The deposit mint, whose decimals the refund is checked against
+    /// 9. `[writable]` The audit log PDA, if the escrow is audited
+    /// 9+A. `[writable]` The receipt mint PDA, required if the escrow has a receipt
+    /// 10+A. `[]` The holder's receipt token account, left holding the receipt
+    /// 11+A. `[writable]` The receipt's proceeds account, closed along with the escrow
+    /// 9+A+R. `[writable]` The escrow's basket PDA, required if the escrow has one
+    /// 10+A+R..10+A+R+3K. For each asset in the basket, in its order, as for Cancel. The
+    ///    basket is refunded along with the deposit and closed with its vaults.
+    Reap,
+    /// Makes takers under an operator's config pay the fee in a designated fee mint, converted
+    /// from its token Y value at `fee_mint_rate`, instead of slicing it from their payment.
+    /// Leaving out the fee mint account switches back to token Y fees.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    /// 2. `[]` The fee mint, optional
+    SetFeeMint {
+        /// Fee mint base units per `state::FEE_RATE_SCALE` base units of token Y
+        fee_mint_rate: u64,
+        /// Key that may push rate updates with UpdateFeeRate, the default pubkey for admin only
+        fee_rate_oracle: Pubkey,
+    },
+    /// Updates the fee mint rate of a config that charges fees in a fee mint
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The config's fee rate oracle or admin
+    /// 1. `[writable]` The config PDA
+    UpdateFeeRate {
+        fee_mint_rate: u64,
+    },
+    /// Lets the initializer unwind an escrow whose deposit or expected mint has been closed,
+    /// which can't be filled anymore, without waiting out the cancel time lock. Whatever is
+    /// left in the vault is refunded as for Cancel and both accounts are closed. Escrows with a
+    /// receipt are not covered.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The PDA's temp token account
+    /// 2. `[writable]` The initializer's main account, receiving the rent
+    /// 3. `[writable]` The escrow's refund account, as for Cancel
+    /// 4. `[writable]` The escrow account
+    /// 5. `[]` The token program the escrow was opened with
+    /// 6. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 7. `[writable]` The stats PDA
+    /// 8. `[]` The closed mint's address, either the vault's mint or the escrow's expected mint
+    /// 9. `[]` The deposit mint, whose decimals any refund is checked against; the same address
+    ///    as account 8 when it's the deposit mint that was closed
+    /// 10. `[writable]` The audit log PDA, if the escrow is audited
+    RecoverClosedMint,
+    /// Restricts the instructions behind the given `AllowlistEntry` role bits to signers on the
+    /// config's allowlist, 0 to open everything up again
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    SetGatedRoles {
+        gated_roles: u8,
+    },
+    /// Sets a signer's `AllowlistEntry` roles under the admin's config, creating the entry PDA
+    /// on first use. 0 revokes every role.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The operator admin, paying for the entry
+    /// 1. `[]` The config PDA
+    /// 2. `[]` The allowlisted signer
+    /// 3. `[writable]` The allowlist entry PDA, derived from `[b"allowlist", config, member]`
+    /// 4. `[]` The system program
+    SetAllowlistEntry {
+        roles: u8,
+    },
+    /// Read-only probe writing the git commit, compiler and features the program was built
+    /// with to return_data, see `build_info::BuildInfo`
+    ///
+    ///
+    /// Accounts expected: none
+    BuildInfo,
+    /// Creates the initializer's shared vault for a mint: a token account owned by its vault
+    /// authority PDA that any number of their escrows deposit into with InitEscrow's
+    /// `vault_deposit`, so they pay its rent once rather than per escrow. Each escrow records its own share and is
+    /// paid out exactly that; the vault is closed to the initializer by whichever settlement
+    /// leaves it empty. Its rent isn't counted in the stats' locked rent.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The initializer, paying for the vault
+    /// 1. `[writable]` The vault PDA, derived from `[b"vault", initializer, mint]`
+    /// 2. `[]` The mint of token X
+    /// 3. `[]` The vault's authority PDA, derived from `[b"escrow", vault]`, made its owner
+    /// 4. `[]` The token program
+    /// 5. `[]` The system program
+    /// 6. `[]` The rent sysvar
+    InitVault,
+    /// Whitelists the program escrows under an operator's config may settle the initializer's
+    /// proceeds into, such as a stake pool or LP adapter. Leaving out the program account stops
+    /// new escrows from opting in, and fills of existing ones fail until they are cancelled or
+    /// a program is whitelisted again.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    /// 2. `[]` The settlement program, optional
+    SetSettlementProgram,
+    /// Caps the token X volume fills of any one mint pair under the admin's config may release
+    /// per window, a blunt brake on drains. Fills then need the pair's stats PDA, see
+    /// InitPairStats, and fail with `VolumeCapExceeded` until the window rolls over.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    SetVolumeCap {
+        /// Length of a window in slots, non-zero unless the cap is being lifted
+        volume_window_slots: u64,
+        /// Token X base units a pair's fills may release per window, 0 to lift the cap
+        max_window_volume: u64,
+    },
+    /// Creates the stats PDA tracking a mint pair's fill volume under a config, which fills
+    /// under a capped config must pass. Anyone may create it.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The account paying for the pair stats
+    /// 1. `[]` The config PDA
+    /// 2. `[writable]` The pair stats PDA, derived from
+    ///    `[b"pair_stats", config, deposit_mint, expected_mint]`
+    /// 3. `[]` The mint of token X
+    /// 4. `[]` The mint of token Y
+    /// 5. `[]` The system program
+    InitPairStats,
+    /// Allocates `count` escrow accounts for the initializer up front, so opening an offer
+    /// later takes only InitEscrow on one of them, with no account to create and no new
+    /// keypair to sign. They're reserved for the initializer: InitEscrow rejects anyone else.
+    /// Each account's rent returns to the initializer when its escrow closes, after which
+    /// its index can be precreated again. Lamports someone already sent to an address count
+    /// toward its rent, so funding it first can't block its creation.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The initializer, paying the rent
+    /// 1. `[]` The system program
+    /// 2..2+count. `[writable]` The escrow account PDAs, derived from
+    ///    `[b"escrow_account", initializer, index]` with consecutive little-endian u64 indices
+    ///    from `first_index`
+    PrecreateEscrows {
+        /// Index of the first account to create
+        first_index: u64,
+        /// Number of accounts, from 1 to `state::MAX_PRECREATED_ESCROWS`
+        count: u8,
+    },
+    /// Refunds an offer that has expired, `state::EXPIRY_SLOTS` past its unlock time, and
+    /// closes the escrow so abandoned deposits go back without the initializer coming back
+    /// online. Anyone may call it and is paid `state::CANCEL_EXPIRED_REWARD_LAMPORTS` out of
+    /// the escrow account's rent, the rest returning to the initializer. An escrow with a
+    /// receipt refunds its holder instead, as for Reap.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The cranker, receiving the reward
+    /// 1. `[writable]` The PDA's temp token account
+    /// 2. `[writable]` The initializer's main account, receiving the rest of the rent
+    /// 3. `[writable]` The escrow's refund account, as for Cancel
+    /// 4. `[writable]` The escrow account
+    /// 5. `[]` The token program the escrow was opened with
+    /// 6. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 7. `[writable]` The stats PDA
+    /// 8. `[]` The deposit mint, whose decimals the refund is checked against
+    /// 9. `[writable]` The audit log PDA, if the escrow is audited
+    /// 9+A. `[writable]` The receipt mint PDA, required if the escrow has a receipt
+    /// 10+A. `[]` The holder's receipt token account, left holding the receipt
+    /// 11+A. `[writable]` The receipt's proceeds account, closed along with the escrow
+    /// 9+A+R. `[writable]` The escrow's basket PDA, required if the escrow has one
+    /// 10+A+R..10+A+R+3K. For each asset in the basket, in its order, as for Cancel. The
+    ///    basket is refunded along with the deposit and closed with its vaults.
+    CancelExpired,
+    /// Recovery path for an escrow a program bug left inconsistent, e.g. with its vault drained
+    /// or closed while the state stays open, which no other instruction can unwind. Needs both
+    /// the initializer and their config's admin to sign. Whatever is left of the escrow's
+    /// deposit is refunded as for Cancel, ignoring time locks and shortfalls, and both
+    /// accounts are closed. Escrows with a receipt are not covered.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The PDA's temp token account, possibly already closed
+    /// 2. `[writable]` The initializer's main account, receiving the rent
+    /// 3. `[writable]` The escrow's refund account, as for Cancel
+    /// 4. `[writable]` The escrow account
+    /// 5. `[]` The token program the escrow was opened with
+    /// 6. `[]` The vault authority PDA, derived from `[b"escrow", vault]`
+    /// 7. `[writable]` The stats PDA
+    /// 8. `[signer]` The admin of the escrow's config
+    /// 9. `[]` The escrow's config account
+    /// 10. `[]` The deposit mint, whose decimals the refund is checked against
+    /// 11. `[writable]` The audit log PDA, if the escrow is audited
+    ForceClose,
+    /// Halts or resumes InitEscrow and Exchange under the admin's config, which then fail with
+    /// `ConfigPaused`. Cancel, Reap and the other ways out keep working while paused.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    SetPaused {
+        paused: bool,
+    },
+    /// Narrows the cancellation windows InitEscrow accepts under the admin's config, within
+    /// `state::MIN_UNLOCK_SLOTS..=state::MAX_UNLOCK_SLOTS`. Open escrows keep their windows,
+    /// and ResetTimeLock stays bound by the program's limits only.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    SetUnlockBounds {
+        /// Shortest window, 0 for the program's minimum
+        min_unlock_slots: u64,
+        /// Longest window, 0 for the program's maximum
+        max_unlock_slots: u64,
+    },
+    /// Lets `taker` fill the initializer's escrow, creating its taker allowlist on first use.
+    /// From then on Exchange only accepts takers on the list, at most
+    /// `state::MAX_ALLOWED_TAKERS` of them.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer, paying for the allowlist
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The taker allowlist PDA, derived from `[b"takers", escrow]`
+    /// 3. `[]` The system program
+    AddAllowedTaker {
+        taker: Pubkey,
+    },
+    /// Takes `taker` off the escrow's allowlist. The escrow stays restricted to the list even
+    /// once it is empty. Once the escrow is closed, the allowlist is closed along with
+    /// whichever taker is passed, its rent going back to the initializer.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer
+    /// 1. `[]` The escrow account
+    /// 2. `[writable]` The taker allowlist PDA
+    RemoveAllowedTaker {
+        taker: Pubkey,
+    },
+    /// Proposes a different price for an open escrow, stored until the initializer accepts it
+    /// with AcceptCounterOffer. Each taker has one counter-offer per escrow, which a new one
+    /// replaces. The taker pays its rent and gets it back once it is accepted or withdrawn.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The taker
+    /// 1. `[]` The escrow account
+    /// 2. `[writable]` The counter-offer PDA, derived from `[b"counter_offer", escrow, taker]`
+    /// 3. `[]` The system program
+    MakeCounterOffer {
+        /// Token Y the taker offers for what's left of the deposit, 0 to withdraw the
+        /// counter-offer, which also works once the escrow is closed
+        expected_amount: PaymentAmount,
+    },
+    /// Reprices the escrow at a taker's counter-offer, within the config's bounds as for
+    /// InitEscrow, and closes the counter-offer. The new price is open to every taker the
+    /// escrow allows, so an initializer keeping it for the proposer adds them to a taker
+    /// allowlist in the same transaction. Not available for escrows with a receipt, and fails
+    /// with `CounterOfferOutdated` once a partial fill has followed the counter-offer.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The counter-offer PDA
+    /// 3. `[writable]` The taker who made it, receiving its rent
+    /// 4. `[]` The escrow's config account
+    AcceptCounterOffer,
+    /// Reprices an open escrow nobody has filled any of yet, within the config's bounds as for
+    /// InitEscrow, instead of cancelling and opening it again. Fails with
+    /// `EscrowPartiallyFilled` once a partial fill has gone through, and isn't available for
+    /// escrows with a receipt.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The escrow account
+    /// 2. `[]` The escrow's config account
+    UpdateExpectedAmount {
+        expected_amount: PaymentAmount,
+    },
+    /// Labels an escrow with up to `state::MAX_ESCROW_TAGS` `(key, value)` tags, like
+    /// `desk=alpha`, in a PDA clients can filter with memcmp, and logs them after a
+    /// `SetEscrowTags` event. Only takes effect in the slot the escrow was initialized in, so
+    /// it's sent in the same transaction as InitEscrow, and fails with `EscrowTagsFixed` once
+    /// tags are set. Without tags it closes the tags of an escrow that has since been settled,
+    /// returning their rent to the initializer.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer, paying for the tags PDA
+    /// 1. `[]` The escrow account
+    /// 2. `[writable]` The tags PDA, derived from `[b"tags", escrow]`
+    /// 3. `[]` The system program
+    /// 4. `[writable]` The stats PDA, for the event's sequence number
+    SetEscrowTags {
+        /// Encoded as a count byte followed by that many `state::EscrowTag`s
+        tags: Vec<EscrowTag>,
+    },
+    /// Creates a wSOL token account owned by the signer and holding `lamports` of their SOL,
+    /// so nobody has to wrap SOL by hand. An initializer offering SOL passes it to InitEscrow
+    /// as the temp token account in the same transaction, and a taker paying in SOL passes it
+    /// to Exchange as their sending account. Exchange and Cancel unwrap SOL on the way out.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The owner of the new account, paying the lamports and its rent
+    /// 1. `[writable, signer]` The new wSOL token account, not created yet
+    /// 2. `[]` The token program's native mint
+    /// 3. `[]` The token program
+    /// 4. `[]` The system program
+    WrapSol {
+        lamports: u64,
+    },
+    /// Sets up the config's insurance pool, or changes the premium of an existing one. The
+    /// operator funds the pool by transferring tokens into token accounts the pool PDA owns,
+    /// such as its associated token accounts, one per mint it covers.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The operator admin, paying for the pool account
+    /// 1. `[]` The admin's config PDA
+    /// 2. `[writable]` The insurance pool PDA, derived from `[b"insurance", config]`
+    /// 3. `[]` The system program
+    InitInsurancePool {
+        /// Share of an insured fill's payment routed to the pool, at most
+        /// `state::MAX_INSURANCE_PREMIUM_BPS`
+        premium_bps: u16,
+    },
+    /// Opts an open escrow into its config's insurance pool, so that each later fill pays the
+    /// pool's premium. Takes effect for good; the escrow can't leave the pool again.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The escrow account
+    /// 2. `[]` The insurance pool PDA of the escrow's config
+    InsureEscrow,
+    /// Pays `amount` out of one of the insurance pool's token accounts, at the discretion of
+    /// the config's admin
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[]` The admin's config PDA
+    /// 2. `[writable]` The insurance pool PDA
+    /// 3. `[writable]` The pool's token account to pay from
+    /// 4. `[writable]` The claimant's token account for the same mint
+    /// 5. `[]` The mint
+    /// 6. `[]` The token program
+    PayClaim {
+        amount: u64,
+    },
+    /// Adds `amount` of another token to the escrow's basket, which a fill hands the taker
+    /// along with the deposit for the same expected amount. The first asset creates the basket
+    /// and the escrow then only fills whole; escrows opened with `partial_fill` fail with
+    /// `UnsupportedFeatures`. Adding a mint the basket already holds tops it up, and a basket
+    /// holds at most `state::MAX_BASKET_ASSETS` mints. Every asset must belong to the escrow's
+    /// token program.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer, paying for the basket and its vaults
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The basket PDA, derived from `[b"basket", escrow]`
+    /// 3. `[writable]` The initializer's token account to take the asset from
+    /// 4. `[writable]` The asset's vault: the basket PDA's associated token account for the
+    ///    mint, created if it doesn't exist yet
+    /// 5. `[]` The asset's mint
+    /// 6. `[]` The token program the escrow was opened with
+    /// 7. `[]` The associated token account program
+    /// 8. `[]` The system program
+    AddBasketAsset {
+        amount: DepositAmount,
+    },
+    /// Returns a basket to the initializer once its escrow closed without paying it out, e.g.
+    /// by ForceClose or RecoverClosedMint, and closes it with its vaults.
+    /// Fails with `EscrowNotSettled` while the escrow is open.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer, receiving the rent
+    /// 1. `[]` The escrow account
+    /// 2. `[writable]` The basket PDA
+    /// 3. `[]` The token program the escrow was opened with
+    /// 4..4+3K. For each asset in the basket, in its order: `[writable]` the asset's vault,
+    ///    `[writable]` a token account of the initializer's for the asset's mint, and `[]` the mint
+    ReclaimBasket,
+    /// Settles three escrows against each other at once, with no taker: escrows A, B and C
+    /// whose mints chain into a ring, A's expected mint being B's deposit mint, B's expected
+    /// mint C's and C's A's. Each initializer receives the whole deposit of the escrow after
+    /// theirs, less their config's operator fee, and every escrow closes. Fails with
+    /// `RingMismatch` unless each deposit covers the expected amount of the escrow before it.
+    /// Anyone may match a ring.
+    ///
+    /// The escrows must share a token program and be plain offers: ones with payouts, a
+    /// settlement hook, a receipt, an audit log, a taker commitment, allowlist or gate, a
+    /// basket or insurance, an NFT deposit, or a config charging fees in a fee mint or capping
+    /// volume fail with `UnsupportedFeatures`.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Whoever matches the ring
+    /// 1. `[]` The token program the escrows were opened with
+    /// 2. `[writable]` The stats PDA, derived from `[b"stats"]`
+    /// 3+8L..11+8L. For each escrow L of A, B and C, in that order:
+    ///    0. `[writable]` The escrow account
+    ///    1. `[writable]` Its vault
+    ///    2. `[writable]` Its initializer's main account, receiving the rent
+    ///    3. `[writable]` Its initializer's token account for their expected mint, receiving
+    ///       the next escrow's deposit
+    ///    4. `[]` Its vault authority PDA
+    ///    5. `[]` Its config
+    ///    6. `[writable]` The config fee recipient's token account for the escrow's expected
+    ///       mint, receiving the fee out of the next escrow's deposit
+    ///    7. `[]` Its deposit mint
+    MultiExchange,
+    /// Turns an open escrow into a mutual deposit: rather than taking it with Exchange, `taker`
+    /// deposits their payment with DepositTaker, and Settle then swaps the two deposits. The
+    /// initializer's leg is already in the vault from InitEscrow. Creates the mutual deposit PDA
+    /// and the vault the taker's payment goes into. From here on only Settle fills the escrow,
+    /// only Cancel closes it, and everything else taking or closing it fails with
+    /// `MutualDepositOnly`.
+    ///
+    /// Escrows with payouts, a settlement hook, a receipt, an audit log, a taker commitment,
+    /// allowlist or gate, a basket or insurance, or an NFT deposit fail with
+    /// `UnsupportedFeatures`.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable, signer]` The initializer, paying for the mutual deposit and its vault
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The mutual deposit PDA, derived from `[b"mutual", escrow]`
+    /// 3. `[writable]` The taker vault: the mutual deposit PDA's associated token account for
+    ///    the expected mint
+    /// 4. `[]` The expected mint
+    /// 5. `[]` The deposit mint
+    /// 6. `[]` The token program the escrow was opened with
+    /// 7. `[]` The associated token account program
+    /// 8. `[]` The system program
+    DepositInitializer {
+        taker: Pubkey,
+    },
+    /// The mutual deposit's taker deposits exactly the escrow's expected amount into the taker
+    /// vault, naming their token account to receive the deposit into at Settle. The account
+    /// the payment comes from is where Cancel refunds it. Fails with `EscrowExpired` once the
+    /// escrow expired, and with `AccountAlreadyInitialized` if the taker already deposited.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The taker named by DepositInitializer
+    /// 1. `[]` The escrow account
+    /// 2. `[writable]` The mutual deposit PDA
+    /// 3. `[writable]` The taker's token account for the expected mint to pay from
+    /// 4. `[writable]` The taker vault
+    /// 5. `[]` The taker's token account for the deposit mint, to receive the deposit into
+    /// 6. `[]` The expected mint
+    /// 7. `[]` The token program the escrow was opened with
+    DepositTaker,
+    /// Swaps a mutual deposit: the escrow's deposit goes to the taker and their payment to the
+    /// initializer, less the config's operator fee, and the escrow closes with its vaults and
+    /// mutual deposit PDA. Anyone may settle, even after the escrow expired. Fails with
+    /// `TakerDepositMissing` before DepositTaker, and configs charging fees in a fee mint or
+    /// capping volume fail with `UnsupportedFeatures`.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` Whoever settles
+    /// 1. `[writable]` The escrow account
+    /// 2. `[writable]` The escrow's vault
+    /// 3. `[]` The vault authority PDA
+    /// 4. `[writable]` The mutual deposit PDA
+    /// 5. `[writable]` The taker vault
+    /// 6. `[writable]` The initializer's main account, receiving the rent
+    /// 7. `[writable]` The initializer's token account for the expected mint, receiving the
+    ///    payment
+    /// 8. `[writable]` The token account the taker named at DepositTaker, receiving the deposit
+    /// 9. `[]` The token program the escrow was opened with
+    /// 10. `[writable]` The stats PDA, derived from `[b"stats"]`
+    /// 11. `[]` The escrow's config
+    /// 12. `[writable]` The config fee recipient's token account for the expected mint
+    /// 13. `[]` The deposit mint
+    /// 14. `[]` The expected mint
+    Settle,
+    /// Redeems a hash-locked escrow: its recipient reveals the preimage of the hash lock's hash
+    /// and takes the whole deposit, with no payment or fee, as the other leg of the swap is
+    /// paid on another chain against that same preimage. The escrow closes with its vault.
+    /// Fails with `PreimageMismatch` for a wrong preimage, with `EscrowExpired` from the
+    /// escrow's expiry slot on, when only the initializer's Cancel refund is left, and with
+    /// `UnsupportedFeatures` for an escrow without a hash lock.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The hash lock's recipient
+    /// 1. `[writable]` The recipient's token account for the deposit mint, receiving the deposit
+    /// 2. `[writable]` The escrow account
+    /// 3. `[writable]` The escrow's vault
+    /// 4. `[]` The vault authority PDA
+    /// 5. `[writable]` The initializer's main account, receiving the rent
+    /// 6. `[]` The token program the escrow was opened with
+    /// 7. `[writable]` The stats PDA, derived from `[b"stats"]`
+    /// 8. `[]` The escrow's config
+    /// 9. `[]` The deposit mint
+    /// 10. `[writable]` The audit log, required if the escrow has one
+    Redeem {
+        preimage: [u8; 32],
+    },
+    /// Fills an escrow whole at the price its initializer approved off-chain for this taker,
+    /// see `state::Permit`. The initializer signs the permit message and anyone may relay it:
+    /// the instruction right before this one must be an ed25519 program instruction verifying
+    /// that signature over exactly the permit, or this fails with `PermitInvalid`. An escrow
+    /// with a permit Ethereum signer, see SetPermitEthSigner, also takes a secp256k1 program
+    /// instruction verifying that address's signature over `Permit::eth_message`. The escrow's
+    /// expected amount becomes the permit's `payment`, which must be within the config's
+    /// bounds, and the fill then runs as an Exchange of the whole deposit with a `max_payment`
+    /// of `payment`. Escrows with a receipt, a mutual deposit or a partial fill fail as they
+    /// would for UpdateExpectedAmount, ones with a settlement hook with `UnsupportedFeatures`,
+    /// and a private offer fails its commitment check, the permit already naming its taker.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0..N. The accounts of an Exchange of the escrow, in its order
+    /// N. `[]` The instructions sysvar
+    ExchangeWithPermit {
+        /// The deposit the taker expects, as for Exchange
+        amount: DepositAmount,
+        /// What the taker pays for the whole deposit, as signed by the initializer
+        payment: PaymentAmount,
+        /// Last slot the permit may be used in, as signed by the initializer. Permits always
+        /// expire, so 0 is rejected.
+        valid_until_slot: u64,
+    },
+    /// Limits how many escrows InitEscrow may open under the admin's config in one slot, a
+    /// brake on spam bloating the program's accounts during an incident. Inits then need the
+    /// config's rate counter PDA, see InitRateCounter, and fail with `RateLimited` once a slot
+    /// has had its share; they can be retried in the next one.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The operator admin
+    /// 1. `[writable]` The config PDA
+    SetInitRateLimit {
+        /// InitEscrows allowed per slot, 0 to lift the limit
+        max_inits_per_slot: u32,
+    },
+    /// Creates the PDA counting a config's InitEscrows per slot, which inits under a
+    /// rate-limited config must pass. Anyone may create it.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The account paying for the rate counter
+    /// 1. `[]` The config PDA
+    /// 2. `[writable]` The rate counter PDA, derived from `[b"rate_counter", config]`
+    /// 3. `[]` The system program
+    InitRateCounter,
+    /// Lets an Ethereum key sign the escrow's permits in place of the initializer, see
+    /// ExchangeWithPermit, so a counterparty without a Solana wallet can approve fills. The
+    /// initializer's own ed25519 permits keep working.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The initializer
+    /// 1. `[writable]` The escrow account
+    SetPermitEthSigner {
+        /// The signer's Ethereum address, all zero to remove it
+        eth_address: [u8; 20],
+    },
+    /// View (tag 200): writes the escrow's state to return_data, in the escrow account's
+    /// layout. A program can CPI into it rather than parse or hardcode the account format.
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The escrow account
+    GetEscrow,
+    /// View (tag 201): writes an operator config's state to return_data, in its account layout
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The config PDA
+    GetConfig,
+    /// View (tag 202): writes a mint pair's volume stats to return_data, in their account
+    /// layout
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The pair stats PDA
+    GetPairStats,
 }
 
 impl EscrowInstruction {
-    /// Unpacks a byte buffer into a [EscrowInstruction](enum.EscrowInstruction.html).
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+    /// Compute unit limit to request for this instruction. `payout_count` is the escrow's number of
+    /// payouts, plus the royalty creators of an NFT deposit and the assets of its basket, and only
+    /// matters for Exchange and ValidateExchange.
+    ///
+    /// Cancel, Reap, CancelExpired and ReclaimBasket of an escrow with a basket need
+    /// `BASKET_ASSET_COMPUTE_UNITS` more per asset, and Cancel of a mutual deposit
+    /// `MUTUAL_DEPOSIT_COMPUTE_UNITS` more.
+    pub fn compute_unit_limit(&self, payout_count: usize) -> u32 {
+        match self {
+            Self::InitEscrow { .. } => INIT_ESCROW_COMPUTE_UNITS,
+            Self::Exchange { .. } | Self::ValidateExchange { .. } => {
+                EXCHANGE_COMPUTE_UNITS + EXCHANGE_PAYOUT_COMPUTE_UNITS * payout_count as u32
+            }
+            Self::ResetTimeLock { .. } => RESET_TIME_LOCK_COMPUTE_UNITS,
+            Self::Cancel { .. } => CANCEL_COMPUTE_UNITS,
+            Self::InitStats => INIT_STATS_COMPUTE_UNITS,
+            Self::InitConfig { .. } => INIT_CONFIG_COMPUTE_UNITS,
+            Self::UpdateConfig { .. } => UPDATE_CONFIG_COMPUTE_UNITS,
+            Self::InitAuditLog => INIT_AUDIT_LOG_COMPUTE_UNITS,
+            Self::TimeStatus => TIME_STATUS_COMPUTE_UNITS,
+            Self::UpdateDeployment { .. } => UPDATE_DEPLOYMENT_COMPUTE_UNITS,
+            Self::MintReceipt => MINT_RECEIPT_COMPUTE_UNITS,
+            Self::ClaimProceeds => CLAIM_PROCEEDS_COMPUTE_UNITS,
+            Self::Reap => REAP_COMPUTE_UNITS,
+            Self::SetFeeMint { .. } => SET_FEE_MINT_COMPUTE_UNITS,
+            Self::UpdateFeeRate { .. } => UPDATE_FEE_RATE_COMPUTE_UNITS,
+            Self::RecoverClosedMint => RECOVER_CLOSED_MINT_COMPUTE_UNITS,
+            Self::SetGatedRoles { .. } => SET_GATED_ROLES_COMPUTE_UNITS,
+            Self::SetAllowlistEntry { .. } => SET_ALLOWLIST_ENTRY_COMPUTE_UNITS,
+            Self::BuildInfo => BUILD_INFO_COMPUTE_UNITS,
+            Self::InitVault => INIT_VAULT_COMPUTE_UNITS,
+            Self::SetSettlementProgram => SET_SETTLEMENT_PROGRAM_COMPUTE_UNITS,
+            Self::SetVolumeCap { .. } => SET_VOLUME_CAP_COMPUTE_UNITS,
+            Self::InitPairStats => INIT_PAIR_STATS_COMPUTE_UNITS,
+            Self::PrecreateEscrows { count, .. } => PRECREATE_ESCROW_COMPUTE_UNITS * *count as u32,
+            Self::CancelExpired => CANCEL_EXPIRED_COMPUTE_UNITS,
+            Self::ForceClose => FORCE_CLOSE_COMPUTE_UNITS,
+            Self::SetPaused { .. } => SET_PAUSED_COMPUTE_UNITS,
+            Self::SetUnlockBounds { .. } => SET_UNLOCK_BOUNDS_COMPUTE_UNITS,
+            Self::AddAllowedTaker { .. } => ADD_ALLOWED_TAKER_COMPUTE_UNITS,
+            Self::RemoveAllowedTaker { .. } => REMOVE_ALLOWED_TAKER_COMPUTE_UNITS,
+            Self::MakeCounterOffer { .. } => MAKE_COUNTER_OFFER_COMPUTE_UNITS,
+            Self::AcceptCounterOffer => ACCEPT_COUNTER_OFFER_COMPUTE_UNITS,
+            Self::UpdateExpectedAmount { .. } => UPDATE_EXPECTED_AMOUNT_COMPUTE_UNITS,
+            Self::SetEscrowTags { .. } => SET_ESCROW_TAGS_COMPUTE_UNITS,
+            Self::WrapSol { .. } => WRAP_SOL_COMPUTE_UNITS,
+            Self::InitInsurancePool { .. } => INIT_INSURANCE_POOL_COMPUTE_UNITS,
+            Self::InsureEscrow => INSURE_ESCROW_COMPUTE_UNITS,
+            Self::PayClaim { .. } => PAY_CLAIM_COMPUTE_UNITS,
+            Self::AddBasketAsset { .. } => ADD_BASKET_ASSET_COMPUTE_UNITS,
+            Self::ReclaimBasket => RECLAIM_BASKET_COMPUTE_UNITS,
+            Self::MultiExchange => MULTI_EXCHANGE_COMPUTE_UNITS,
+            Self::DepositInitializer { .. } => DEPOSIT_INITIALIZER_COMPUTE_UNITS,
+            Self::DepositTaker => DEPOSIT_TAKER_COMPUTE_UNITS,
+            Self::Settle => SETTLE_COMPUTE_UNITS,
+            Self::Redeem { .. } => REDEEM_COMPUTE_UNITS,
+            Self::ExchangeWithPermit { .. } => {
+                EXCHANGE_COMPUTE_UNITS
+                    + PERMIT_COMPUTE_UNITS
+                    + EXCHANGE_PAYOUT_COMPUTE_UNITS * payout_count as u32
+            }
+            Self::SetInitRateLimit { .. } => SET_INIT_RATE_LIMIT_COMPUTE_UNITS,
+            Self::InitRateCounter => INIT_RATE_COUNTER_COMPUTE_UNITS,
+            Self::SetPermitEthSigner { .. } => SET_PERMIT_ETH_SIGNER_COMPUTE_UNITS,
+            Self::GetEscrow | Self::GetConfig | Self::GetPairStats => VIEW_COMPUTE_UNITS,
+        }
+    }
+
+    /// The instruction's account list, see [account_specs](../account_specs/index.html)
+    pub fn account_specs(&self) -> &'static [AccountSpec] {
+        match self {
+            Self::InitEscrow { .. } => INIT_ESCROW_ACCOUNTS,
+            Self::Exchange { .. } => EXCHANGE_ACCOUNTS,
+            Self::ResetTimeLock { .. } => RESET_TIME_LOCK_ACCOUNTS,
+            Self::Cancel { .. } => CANCEL_ACCOUNTS,
+            Self::InitStats => INIT_STATS_ACCOUNTS,
+            Self::InitConfig { .. } => INIT_CONFIG_ACCOUNTS,
+            Self::UpdateConfig { .. } => UPDATE_CONFIG_ACCOUNTS,
+            Self::InitAuditLog => INIT_AUDIT_LOG_ACCOUNTS,
+            Self::TimeStatus => TIME_STATUS_ACCOUNTS,
+            Self::UpdateDeployment { .. } => UPDATE_DEPLOYMENT_ACCOUNTS,
+            Self::ValidateExchange { .. } => VALIDATE_EXCHANGE_ACCOUNTS,
+            Self::MintReceipt => MINT_RECEIPT_ACCOUNTS,
+            Self::ClaimProceeds => CLAIM_PROCEEDS_ACCOUNTS,
+            Self::Reap => REAP_ACCOUNTS,
+            Self::SetFeeMint { .. } => SET_FEE_MINT_ACCOUNTS,
+            Self::UpdateFeeRate { .. } => UPDATE_FEE_RATE_ACCOUNTS,
+            Self::RecoverClosedMint => RECOVER_CLOSED_MINT_ACCOUNTS,
+            Self::SetGatedRoles { .. } => SET_GATED_ROLES_ACCOUNTS,
+            Self::SetAllowlistEntry { .. } => SET_ALLOWLIST_ENTRY_ACCOUNTS,
+            Self::BuildInfo => BUILD_INFO_ACCOUNTS,
+            Self::InitVault => INIT_VAULT_ACCOUNTS,
+            Self::SetSettlementProgram => SET_SETTLEMENT_PROGRAM_ACCOUNTS,
+            Self::SetVolumeCap { .. } => SET_VOLUME_CAP_ACCOUNTS,
+            Self::InitPairStats => INIT_PAIR_STATS_ACCOUNTS,
+            Self::PrecreateEscrows { .. } => PRECREATE_ESCROWS_ACCOUNTS,
+            Self::CancelExpired => CANCEL_EXPIRED_ACCOUNTS,
+            Self::ForceClose => FORCE_CLOSE_ACCOUNTS,
+            Self::SetPaused { .. } => SET_PAUSED_ACCOUNTS,
+            Self::SetUnlockBounds { .. } => SET_UNLOCK_BOUNDS_ACCOUNTS,
+            Self::AddAllowedTaker { .. } => ADD_ALLOWED_TAKER_ACCOUNTS,
+            Self::RemoveAllowedTaker { .. } => REMOVE_ALLOWED_TAKER_ACCOUNTS,
+            Self::MakeCounterOffer { .. } => MAKE_COUNTER_OFFER_ACCOUNTS,
+            Self::AcceptCounterOffer => ACCEPT_COUNTER_OFFER_ACCOUNTS,
+            Self::UpdateExpectedAmount { .. } => UPDATE_EXPECTED_AMOUNT_ACCOUNTS,
+            Self::SetEscrowTags { .. } => SET_ESCROW_TAGS_ACCOUNTS,
+            Self::WrapSol { .. } => WRAP_SOL_ACCOUNTS,
+            Self::InitInsurancePool { .. } => INIT_INSURANCE_POOL_ACCOUNTS,
+            Self::InsureEscrow => INSURE_ESCROW_ACCOUNTS,
+            Self::PayClaim { .. } => PAY_CLAIM_ACCOUNTS,
+            Self::AddBasketAsset { .. } => ADD_BASKET_ASSET_ACCOUNTS,
+            Self::ReclaimBasket => RECLAIM_BASKET_ACCOUNTS,
+            Self::MultiExchange => MULTI_EXCHANGE_ACCOUNTS,
+            Self::DepositInitializer { .. } => DEPOSIT_INITIALIZER_ACCOUNTS,
+            Self::DepositTaker => DEPOSIT_TAKER_ACCOUNTS,
+            Self::Settle => SETTLE_ACCOUNTS,
+            Self::Redeem { .. } => REDEEM_ACCOUNTS,
+            Self::ExchangeWithPermit { .. } => EXCHANGE_WITH_PERMIT_ACCOUNTS,
+            Self::SetInitRateLimit { .. } => SET_INIT_RATE_LIMIT_ACCOUNTS,
+            Self::InitRateCounter => INIT_RATE_COUNTER_ACCOUNTS,
+            Self::SetPermitEthSigner { .. } => SET_PERMIT_ETH_SIGNER_ACCOUNTS,
+            Self::GetEscrow => GET_ESCROW_ACCOUNTS,
+            Self::GetConfig => GET_CONFIG_ACCOUNTS,
+            Self::GetPairStats => GET_PAIR_STATS_ACCOUNTS,
+        }
+    }
+
+    /// Unpacks a byte buffer into a [EscrowInstruction](enum.EscrowInstruction.html).
+    ///
+    /// Every variant must be encoded with exactly its own length; trailing bytes are rejected.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() > MAX_INSTRUCTION_DATA_LEN {
+            return Err(InvalidInstruction.into());
+        }
+        let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
 
         Ok(match tag {
-            0 => Self::InitEscrow {
-                amount: Self::unpack_amount(rest)?,
-            },
-            1 => Self::Exchange {
-                amount: Self::unpack_amount(rest)?,
-            },
-            2 => Self::ResetTimeLock { },
-            3 => Self::Cancel { },
+            0 => {
+                let amount = PaymentAmount(Self::unpack_amount(rest)?);
+                let (payout_bps, rest) = Self::unpack_payout_bps(rest.get(8..).unwrap_or_default())?;
+                let (fields, mut rest) = match rest {
+                    [] => (0, rest),
+                    [low, high, rest @ ..] => (u16::from_le_bytes([*low, *high]), rest),
+                    _ => return Err(InvalidInstruction.into()),
+                };
+                if fields & !InitEscrowFields::KNOWN != 0 {
+                    return Err(InvalidInstruction.into());
+                }
+                let present = |field: u16| fields & field != 0;
+                let input = &mut rest;
+                let rounding_mode = if present(InitEscrowFields::ROUNDING_MODE) {
+                    RoundingMode::from_u8(Self::take(input, 1)?[0]).ok_or(InvalidInstruction)?
+                } else {
+                    RoundingMode::default()
+                };
+                let taker_commitment = if present(InitEscrowFields::TAKER_COMMITMENT) {
+                    let commitment: [u8; 32] =
+                        Self::take(input, 32)?.try_into().map_err(|_| InvalidInstruction)?;
+                    if commitment == [0; 32] {
+                        return Err(InvalidInstruction.into());
+                    }
+                    Some(commitment)
+                } else {
+                    None
+                };
+                let unlock_slots = if present(InitEscrowFields::UNLOCK_SLOTS) {
+                    Self::unpack_amount(Self::take(input, 8)?)?
+                } else {
+                    DEFAULT_UNLOCK_SLOTS
+                };
+                let vault_deposit = if present(InitEscrowFields::VAULT_DEPOSIT) {
+                    DepositAmount(Self::unpack_amount(Self::take(input, 8)?)?)
+                } else {
+                    DepositAmount(0)
+                };
+                let settlement_program = if present(InitEscrowFields::SETTLEMENT_PROGRAM) {
+                    let program = Self::unpack_pubkey(Self::take(input, 32)?)?;
+                    if program == Pubkey::default() {
+                        return Err(InvalidInstruction.into());
+                    }
+                    Some(program)
+                } else {
+                    None
+                };
+                let partial_fill = present(InitEscrowFields::PARTIAL_FILL);
+                let taker_gate = if present(InitEscrowFields::TAKER_GATE) {
+                    let gate = Self::take(input, TakerGate::LEN)?;
+                    TakerGate {
+                        min_lamports: Self::unpack_amount(gate)?,
+                        badge_mint: Self::unpack_pubkey(&gate[8..])?,
+                    }
+                } else {
+                    TakerGate::default()
+                };
+                let hash_lock = if present(InitEscrowFields::HASH_LOCK) {
+                    let lock = Self::take(input, HashLock::LEN)?;
+                    HashLock {
+                        hash: lock[..32].try_into().map_err(|_| InvalidInstruction)?,
+                        recipient: Self::unpack_pubkey(&lock[32..])?,
+                    }
+                } else {
+                    HashLock::default()
+                };
+                if hash_lock.is_set() != (hash_lock.recipient != Pubkey::default()) {
+                    return Err(InvalidInstruction.into());
+                }
+                let gas_rebate = if present(InitEscrowFields::GAS_REBATE) {
+                    Self::unpack_amount(Self::take(input, 8)?)?
+                } else {
+                    0
+                };
+                let price_band = if present(InitEscrowFields::PRICE_BAND) {
+                    let band = Self::take(input, PriceBand::LEN)?;
+                    PriceBand {
+                        price_account: Self::unpack_pubkey(&band[..32])?,
+                        offset_bps: i16::from_le_bytes([band[32], band[33]]),
+                    }
+                } else {
+                    PriceBand::default()
+                };
+                if (!price_band.is_set() && price_band.offset_bps != 0)
+                    || price_band.offset_bps <= -(TOTAL_PAYOUT_BPS as i16)
+                {
+                    return Err(InvalidInstruction.into());
+                }
+                let quote_convention = if present(InitEscrowFields::QUOTE_CONVENTION) {
+                    QuoteConvention::from_u8(Self::take(input, 1)?[0]).ok_or(InvalidInstruction)?
+                } else {
+                    QuoteConvention::default()
+                };
+                let fill_cooldown_slots = if present(InitEscrowFields::FILL_COOLDOWN_SLOTS) {
+                    Self::unpack_amount(Self::take(input, 8)?)?
+                } else {
+                    0
+                };
+                let has_refund_token_account = present(InitEscrowFields::REFUND_TOKEN_ACCOUNT);
+                let has_deposit_mint = present(InitEscrowFields::DEPOSIT_MINT);
+                let designated_taker = if present(InitEscrowFields::DESIGNATED_TAKER) {
+                    let taker = Self::unpack_pubkey(Self::take(input, 32)?)?;
+                    if taker == Pubkey::default() {
+                        return Err(InvalidInstruction.into());
+                    }
+                    Some(taker)
+                } else {
+                    None
+                };
+                if !input.is_empty() {
+                    return Err(InvalidInstruction.into());
+                }
+                Self::InitEscrow {
+                    amount,
+                    payout_bps,
+                    rounding_mode,
+                    taker_commitment,
+                    unlock_slots,
+                    vault_deposit,
+                    settlement_program,
+                    partial_fill,
+                    taker_gate,
+                    hash_lock,
+                    gas_rebate,
+                    price_band,
+                    quote_convention,
+                    fill_cooldown_slots,
+                    has_refund_token_account,
+                    has_deposit_mint,
+                    designated_taker,
+                }
+            }
+            1 => Self::unpack_exchange(rest, false)?,
+            2 => {
+                Self::expect_len(rest, 8)?;
+                Self::ResetTimeLock {
+                    unlock_slots: Self::unpack_amount(rest)?,
+                }
+            }
+            3 => {
+                Self::expect_len(rest, 0)?;
+                Self::Cancel { }
+            }
+            4 => {
+                Self::expect_len(rest, 0)?;
+                Self::InitStats
+            }
+            5 | 6 => {
+                let fee_bps = rest
+                    .get(..2)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u16::from_le_bytes)
+                    .ok_or(InvalidInstruction)?;
+                let min_expected_amount =
+                    PaymentAmount(Self::unpack_amount(rest.get(2..).unwrap_or_default())?);
+                let max_expected_amount =
+                    PaymentAmount(Self::unpack_amount(rest.get(10..).unwrap_or_default())?);
+                let (fee_tiers, rest) = Self::unpack_fee_tiers(rest.get(18..).unwrap_or_default())?;
+                let max_lifetime_slots = match rest {
+                    [] => 0,
+                    _ => {
+                        Self::expect_len(rest, 8)?;
+                        Self::unpack_amount(rest)?
+                    }
+                };
+                if *tag == 5 {
+                    Self::InitConfig {
+                        fee_bps,
+                        min_expected_amount,
+                        max_expected_amount,
+                        fee_tiers,
+                        max_lifetime_slots,
+                    }
+                } else {
+                    Self::UpdateConfig {
+                        fee_bps,
+                        min_expected_amount,
+                        max_expected_amount,
+                        fee_tiers,
+                        max_lifetime_slots,
+                    }
+                }
+            }
+            7 => {
+                Self::expect_len(rest, 0)?;
+                Self::InitAuditLog
+            }
+            8 => {
+                Self::expect_len(rest, 0)?;
+                Self::TimeStatus
+            }
+            9 => {
+                Self::expect_len(rest, 38)?;
+                let version = rest.get(..6).ok_or(InvalidInstruction)?;
+                let migration_notes_hash = rest
+                    .get(6..38)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(InvalidInstruction)?;
+                Self::UpdateDeployment {
+                    major: u16::from_le_bytes([version[0], version[1]]),
+                    minor: u16::from_le_bytes([version[2], version[3]]),
+                    patch: u16::from_le_bytes([version[4], version[5]]),
+                    migration_notes_hash,
+                }
+            }
+            10 => Self::unpack_exchange(rest, true)?,
+            11 => {
+                Self::expect_len(rest, 0)?;
+                Self::MintReceipt
+            }
+            12 => {
+                Self::expect_len(rest, 0)?;
+                Self::ClaimProceeds
+            }
+            13 => {
+                Self::expect_len(rest, 0)?;
+                Self::Reap
+            }
+            14 => {
+                Self::expect_len(rest, 40)?;
+                let fee_rate_oracle = rest
+                    .get(8..40)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(Pubkey::new_from_array)
+                    .ok_or(InvalidInstruction)?;
+                Self::SetFeeMint {
+                    fee_mint_rate: Self::unpack_amount(rest)?,
+                    fee_rate_oracle,
+                }
+            }
+            15 => {
+                Self::expect_len(rest, 8)?;
+                Self::UpdateFeeRate {
+                    fee_mint_rate: Self::unpack_amount(rest)?,
+                }
+            }
+            16 => {
+                Self::expect_len(rest, 0)?;
+                Self::RecoverClosedMint
+            }
+            17 => {
+                Self::expect_len(rest, 1)?;
+                Self::SetGatedRoles {
+                    gated_roles: rest[0],
+                }
+            }
+            18 => {
+                Self::expect_len(rest, 1)?;
+                Self::SetAllowlistEntry { roles: rest[0] }
+            }
+            19 => {
+                Self::expect_len(rest, 0)?;
+                Self::BuildInfo
+            }
+            20 => {
+                Self::expect_len(rest, 0)?;
+                Self::InitVault
+            }
+            21 => {
+                Self::expect_len(rest, 0)?;
+                Self::SetSettlementProgram
+            }
+            22 => {
+                Self::expect_len(rest, 16)?;
+                Self::SetVolumeCap {
+                    volume_window_slots: Self::unpack_amount(rest)?,
+                    max_window_volume: Self::unpack_amount(&rest[8..])?,
+                }
+            }
+            23 => {
+                Self::expect_len(rest, 0)?;
+                Self::InitPairStats
+            }
+            24 => {
+                Self::expect_len(rest, 9)?;
+                Self::PrecreateEscrows {
+                    first_index: Self::unpack_amount(rest)?,
+                    count: rest[8],
+                }
+            }
+            25 => {
+                Self::expect_len(rest, 0)?;
+                Self::CancelExpired
+            }
+            26 => {
+                Self::expect_len(rest, 0)?;
+                Self::ForceClose
+            }
+            27 => {
+                Self::expect_len(rest, 1)?;
+                Self::SetPaused {
+                    paused: match rest[0] {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(InvalidInstruction.into()),
+                    },
+                }
+            }
+            28 => {
+                Self::expect_len(rest, 16)?;
+                Self::SetUnlockBounds {
+                    min_unlock_slots: Self::unpack_amount(rest)?,
+                    max_unlock_slots: Self::unpack_amount(&rest[8..])?,
+                }
+            }
+            29 => {
+                Self::expect_len(rest, 32)?;
+                Self::AddAllowedTaker {
+                    taker: Self::unpack_pubkey(rest)?,
+                }
+            }
+            30 => {
+                Self::expect_len(rest, 32)?;
+                Self::RemoveAllowedTaker {
+                    taker: Self::unpack_pubkey(rest)?,
+                }
+            }
+            31 => {
+                Self::expect_len(rest, 8)?;
+                Self::MakeCounterOffer {
+                    expected_amount: PaymentAmount(Self::unpack_amount(rest)?),
+                }
+            }
+            32 => {
+                Self::expect_len(rest, 0)?;
+                Self::AcceptCounterOffer
+            }
+            33 => {
+                Self::expect_len(rest, 8)?;
+                Self::UpdateExpectedAmount {
+                    expected_amount: PaymentAmount(Self::unpack_amount(rest)?),
+                }
+            }
+            34 => {
+                let (count, tags) = rest.split_first().ok_or(InvalidInstruction)?;
+                if *count as usize > MAX_ESCROW_TAGS {
+                    return Err(InvalidInstruction.into());
+                }
+                Self::expect_len(tags, *count as usize * EscrowTag::LEN)?;
+                Self::SetEscrowTags {
+                    tags: tags
+                        .chunks_exact(EscrowTag::LEN)
+                        .map(|tag| tag.try_into().map(EscrowTag::unpack))
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| InvalidInstruction)?,
+                }
+            }
+            35 => {
+                Self::expect_len(rest, 8)?;
+                Self::WrapSol {
+                    lamports: Self::unpack_amount(rest)?,
+                }
+            }
+            36 => {
+                Self::expect_len(rest, 2)?;
+                Self::InitInsurancePool {
+                    premium_bps: u16::from_le_bytes([rest[0], rest[1]]),
+                }
+            }
+            37 => {
+                Self::expect_len(rest, 0)?;
+                Self::InsureEscrow
+            }
+            38 => {
+                Self::expect_len(rest, 8)?;
+                Self::PayClaim {
+                    amount: Self::unpack_amount(rest)?,
+                }
+            }
+            39 => {
+                Self::expect_len(rest, 8)?;
+                Self::AddBasketAsset {
+                    amount: DepositAmount(Self::unpack_amount(rest)?),
+                }
+            }
+            40 => {
+                Self::expect_len(rest, 0)?;
+                Self::ReclaimBasket
+            }
+            41 => {
+                Self::expect_len(rest, 0)?;
+                Self::MultiExchange
+            }
+            42 => {
+                Self::expect_len(rest, 32)?;
+                Self::DepositInitializer {
+                    taker: Self::unpack_pubkey(rest)?,
+                }
+            }
+            43 => {
+                Self::expect_len(rest, 0)?;
+                Self::DepositTaker
+            }
+            44 => {
+                Self::expect_len(rest, 0)?;
+                Self::Settle
+            }
+            45 => {
+                Self::expect_len(rest, 32)?;
+                Self::Redeem {
+                    preimage: rest.try_into().map_err(|_| InvalidInstruction)?,
+                }
+            }
+            46 => {
+                Self::expect_len(rest, 24)?;
+                let valid_until_slot = Self::unpack_amount(&rest[16..])?;
+                if valid_until_slot == 0 {
+                    return Err(InvalidInstruction.into());
+                }
+                Self::ExchangeWithPermit {
+                    amount: DepositAmount(Self::unpack_amount(rest)?),
+                    payment: PaymentAmount(Self::unpack_amount(&rest[8..])?),
+                    valid_until_slot,
+                }
+            }
+            47 => {
+                Self::expect_len(rest, 4)?;
+                Self::SetInitRateLimit {
+                    max_inits_per_slot: u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]),
+                }
+            }
+            48 => {
+                Self::expect_len(rest, 0)?;
+                Self::InitRateCounter
+            }
+            49 => {
+                Self::expect_len(rest, 20)?;
+                Self::SetPermitEthSigner {
+                    eth_address: rest.try_into().map_err(|_| InvalidInstruction)?,
+                }
+            }
+            200 => {
+                Self::expect_len(rest, 0)?;
+                Self::GetEscrow
+            }
+            201 => {
+                Self::expect_len(rest, 0)?;
+                Self::GetConfig
+            }
+            202 => {
+                Self::expect_len(rest, 0)?;
+                Self::GetPairStats
+            }
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    /// Exchange, or ValidateExchange for a `dry_run`, which share an encoding: the amount, then
+    /// the taker's salt for private offers, the deadline, the fill amount and the max payment
+    fn unpack_exchange(input: &[u8], dry_run: bool) -> Result<Self, ProgramError> {
+        let amount = DepositAmount(Self::unpack_amount(input)?);
+        let (taker_salt, valid_until_slot, fill_amount, max_payment) = match input.len() {
+            8 => (None, 0, 0, 0),
+            40 => (<[u8; 32]>::try_from(&input[8..]).ok().filter(|salt| *salt != [0; 32]), 0, 0, 0),
+            48 | 56 | 64 => (
+                <[u8; 32]>::try_from(&input[8..40]).ok().filter(|salt| *salt != [0; 32]),
+                Self::unpack_amount(&input[40..])?,
+                match input.get(48..) {
+                    Some(fill_amount) if !fill_amount.is_empty() => Self::unpack_amount(fill_amount)?,
+                    _ => 0,
+                },
+                match input.get(56..) {
+                    Some(max_payment) if !max_payment.is_empty() => Self::unpack_amount(max_payment)?,
+                    _ => 0,
+                },
+            ),
             _ => return Err(InvalidInstruction.into()),
+        };
+        let fill_amount = DepositAmount(fill_amount);
+        let max_payment = PaymentAmount(max_payment);
+        Ok(if dry_run {
+            Self::ValidateExchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            }
+        } else {
+            Self::Exchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            }
         })
     }
 
+    fn pack_exchange(
+        buf: &mut Vec<u8>,
+        amount: &DepositAmount,
+        taker_salt: &Option<[u8; 32]>,
+        valid_until_slot: u64,
+        fill_amount: DepositAmount,
+        max_payment: PaymentAmount,
+    ) {
+        buf.extend_from_slice(&amount.0.to_le_bytes());
+        let fill_bytes = fill_amount.0 != 0 || max_payment.0 != 0;
+        let deadline_bytes = valid_until_slot != 0 || fill_bytes;
+        if taker_salt.is_some() || deadline_bytes {
+            buf.extend_from_slice(&taker_salt.unwrap_or_default());
+        }
+        if deadline_bytes {
+            buf.extend_from_slice(&valid_until_slot.to_le_bytes());
+        }
+        if fill_bytes {
+            buf.extend_from_slice(&fill_amount.0.to_le_bytes());
+        }
+        if max_payment.0 != 0 {
+            buf.extend_from_slice(&max_payment.0.to_le_bytes());
+        }
+    }
+
+    fn expect_len(input: &[u8], len: usize) -> Result<(), ProgramError> {
+        if input.len() != len {
+            return Err(InvalidInstruction.into());
+        }
+        Ok(())
+    }
+
     fn unpack_amount(input: &[u8]) -> Result<u64, ProgramError> {
         let amount = input
             .get(..8)
@@ -79,23 +1870,428 @@ impl EscrowInstruction {
         Ok(amount)
     }
 
+    /// Splits the next `len` bytes off `input`
+    fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], ProgramError> {
+        if input.len() < len {
+            return Err(InvalidInstruction.into());
+        }
+        let (field, rest) = input.split_at(len);
+        *input = rest;
+        Ok(field)
+    }
+
+    fn unpack_pubkey(input: &[u8]) -> Result<Pubkey, ProgramError> {
+        input
+            .get(..32)
+            .and_then(|slice| slice.try_into().ok())
+            .map(Pubkey::new_from_array)
+            .ok_or_else(|| InvalidInstruction.into())
+    }
+
+    /// Payout weights are encoded as a count byte followed by that many u16s; no bytes at all means no split.
+    /// Returns the weights and whatever follows them.
+    fn unpack_payout_bps(input: &[u8]) -> Result<(Vec<u16>, &[u8]), ProgramError> {
+        let (count, rest) = match input.split_first() {
+            Some(split) => split,
+            None => return Ok((Vec::new(), input)),
+        };
+        let bps_len = *count as usize * 2;
+        let bps = rest
+            .get(..bps_len)
+            .ok_or(InvalidInstruction)?
+            .chunks(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect();
+        Ok((bps, &rest[bps_len..]))
+    }
+
+    /// Fee tiers are encoded like payout weights: a count byte followed by that many
+    /// (u64 min age in slots, u16 fee bps) pairs, or nothing at all for no tiers.
+    /// Returns the tiers and whatever follows them.
+    fn unpack_fee_tiers(input: &[u8]) -> Result<(Vec<FeeTier>, &[u8]), ProgramError> {
+        let (count, rest) = match input.split_first() {
+            Some(split) => split,
+            None => return Ok((Vec::new(), input)),
+        };
+        let tiers_len = *count as usize * 10;
+        let fee_tiers = rest
+            .get(..tiers_len)
+            .ok_or(InvalidInstruction)?
+            .chunks(10)
+            .map(|chunk| FeeTier {
+                min_age_slots: u64::from_le_bytes(chunk[..8].try_into().unwrap()),
+                fee_bps: u16::from_le_bytes([chunk[8], chunk[9]]),
+            })
+            .collect();
+        Ok((fee_tiers, &rest[tiers_len..]))
+    }
+
+    /// The lifetime follows the tier list, so a non-zero one forces the count byte even without tiers
+    fn pack_fee_tiers(buf: &mut Vec<u8>, fee_tiers: &[FeeTier], max_lifetime_slots: u64) {
+        if !fee_tiers.is_empty() || max_lifetime_slots != 0 {
+            buf.push(fee_tiers.len() as u8);
+            for fee_tier in fee_tiers {
+                buf.extend_from_slice(&fee_tier.min_age_slots.to_le_bytes());
+                buf.extend_from_slice(&fee_tier.fee_bps.to_le_bytes());
+            }
+        }
+        if max_lifetime_slots != 0 {
+            buf.extend_from_slice(&max_lifetime_slots.to_le_bytes());
+        }
+    }
+
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
-        match &*self {
-            Self::InitEscrow { amount } => {
+        match self {
+            Self::InitEscrow {
+                amount,
+                payout_bps,
+                rounding_mode,
+                taker_commitment,
+                unlock_slots,
+                vault_deposit,
+                settlement_program,
+                partial_fill,
+                taker_gate,
+                hash_lock,
+                gas_rebate,
+                price_band,
+                quote_convention,
+                fill_cooldown_slots,
+                has_refund_token_account,
+                has_deposit_mint,
+                designated_taker,
+            } => {
                 buf.push(0);
-                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend_from_slice(&amount.0.to_le_bytes());
+                let fields = [
+                    (InitEscrowFields::ROUNDING_MODE, *rounding_mode != RoundingMode::default()),
+                    (InitEscrowFields::TAKER_COMMITMENT, taker_commitment.is_some()),
+                    (InitEscrowFields::UNLOCK_SLOTS, *unlock_slots != DEFAULT_UNLOCK_SLOTS),
+                    (InitEscrowFields::VAULT_DEPOSIT, vault_deposit.0 != 0),
+                    (InitEscrowFields::SETTLEMENT_PROGRAM, settlement_program.is_some()),
+                    (InitEscrowFields::PARTIAL_FILL, *partial_fill),
+                    (InitEscrowFields::TAKER_GATE, !taker_gate.is_open()),
+                    (InitEscrowFields::HASH_LOCK, hash_lock.is_set()),
+                    (InitEscrowFields::GAS_REBATE, *gas_rebate != 0),
+                    (InitEscrowFields::PRICE_BAND, price_band.is_set()),
+                    (InitEscrowFields::QUOTE_CONVENTION, *quote_convention != QuoteConvention::default()),
+                    (InitEscrowFields::FILL_COOLDOWN_SLOTS, *fill_cooldown_slots != 0),
+                    (InitEscrowFields::REFUND_TOKEN_ACCOUNT, *has_refund_token_account),
+                    (InitEscrowFields::DEPOSIT_MINT, *has_deposit_mint),
+                    (InitEscrowFields::DESIGNATED_TAKER, designated_taker.is_some()),
+                ]
+                .iter()
+                .filter(|(_, present)| *present)
+                .fold(0, |fields, (field, _)| fields | field);
+                let present = |field: u16| fields & field != 0;
+                if !payout_bps.is_empty() || fields != 0 {
+                    buf.push(payout_bps.len() as u8);
+                    for bps in payout_bps {
+                        buf.extend_from_slice(&bps.to_le_bytes());
+                    }
+                }
+                if fields != 0 {
+                    buf.extend_from_slice(&fields.to_le_bytes());
+                }
+                if present(InitEscrowFields::ROUNDING_MODE) {
+                    buf.push(*rounding_mode as u8);
+                }
+                if let Some(taker_commitment) = taker_commitment {
+                    buf.extend_from_slice(taker_commitment);
+                }
+                if present(InitEscrowFields::UNLOCK_SLOTS) {
+                    buf.extend_from_slice(&unlock_slots.to_le_bytes());
+                }
+                if present(InitEscrowFields::VAULT_DEPOSIT) {
+                    buf.extend_from_slice(&vault_deposit.0.to_le_bytes());
+                }
+                if let Some(settlement_program) = settlement_program {
+                    buf.extend_from_slice(settlement_program.as_ref());
+                }
+                if present(InitEscrowFields::TAKER_GATE) {
+                    buf.extend_from_slice(&taker_gate.min_lamports.to_le_bytes());
+                    buf.extend_from_slice(taker_gate.badge_mint.as_ref());
+                }
+                if present(InitEscrowFields::HASH_LOCK) {
+                    buf.extend_from_slice(&hash_lock.hash);
+                    buf.extend_from_slice(hash_lock.recipient.as_ref());
+                }
+                if present(InitEscrowFields::GAS_REBATE) {
+                    buf.extend_from_slice(&gas_rebate.to_le_bytes());
+                }
+                if present(InitEscrowFields::PRICE_BAND) {
+                    buf.extend_from_slice(price_band.price_account.as_ref());
+                    buf.extend_from_slice(&price_band.offset_bps.to_le_bytes());
+                }
+                if present(InitEscrowFields::QUOTE_CONVENTION) {
+                    buf.push(*quote_convention as u8);
+                }
+                if present(InitEscrowFields::FILL_COOLDOWN_SLOTS) {
+                    buf.extend_from_slice(&fill_cooldown_slots.to_le_bytes());
+                }
+                if let Some(designated_taker) = designated_taker {
+                    buf.extend_from_slice(designated_taker.as_ref());
+                }
             }
-            Self::Exchange { amount } => {
+            Self::Exchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            } => {
                 buf.push(1);
-                buf.extend_from_slice(&amount.to_le_bytes());
+                Self::pack_exchange(
+                    &mut buf,
+                    amount,
+                    taker_salt,
+                    *valid_until_slot,
+                    *fill_amount,
+                    *max_payment,
+                );
             }
-            Self::ResetTimeLock {  } => {
+            Self::ResetTimeLock { unlock_slots } => {
                 buf.push(2);
+                buf.extend_from_slice(&unlock_slots.to_le_bytes());
             }
             Self::Cancel {  } => {
                 buf.push(3);
             }
+            Self::InitStats => {
+                buf.push(4);
+            }
+            Self::InitConfig {
+                fee_bps,
+                min_expected_amount,
+                max_expected_amount,
+                fee_tiers,
+                max_lifetime_slots,
+            } => {
+                buf.push(5);
+                buf.extend_from_slice(&fee_bps.to_le_bytes());
+                buf.extend_from_slice(&min_expected_amount.0.to_le_bytes());
+                buf.extend_from_slice(&max_expected_amount.0.to_le_bytes());
+                Self::pack_fee_tiers(&mut buf, fee_tiers, *max_lifetime_slots);
+            }
+            Self::UpdateConfig {
+                fee_bps,
+                min_expected_amount,
+                max_expected_amount,
+                fee_tiers,
+                max_lifetime_slots,
+            } => {
+                buf.push(6);
+                buf.extend_from_slice(&fee_bps.to_le_bytes());
+                buf.extend_from_slice(&min_expected_amount.0.to_le_bytes());
+                buf.extend_from_slice(&max_expected_amount.0.to_le_bytes());
+                Self::pack_fee_tiers(&mut buf, fee_tiers, *max_lifetime_slots);
+            }
+            Self::InitAuditLog => {
+                buf.push(7);
+            }
+            Self::TimeStatus => {
+                buf.push(8);
+            }
+            Self::UpdateDeployment {
+                major,
+                minor,
+                patch,
+                migration_notes_hash,
+            } => {
+                buf.push(9);
+                buf.extend_from_slice(&major.to_le_bytes());
+                buf.extend_from_slice(&minor.to_le_bytes());
+                buf.extend_from_slice(&patch.to_le_bytes());
+                buf.extend_from_slice(migration_notes_hash);
+            }
+            Self::ValidateExchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            } => {
+                buf.push(10);
+                Self::pack_exchange(
+                    &mut buf,
+                    amount,
+                    taker_salt,
+                    *valid_until_slot,
+                    *fill_amount,
+                    *max_payment,
+                );
+            }
+            Self::MintReceipt => {
+                buf.push(11);
+            }
+            Self::ClaimProceeds => {
+                buf.push(12);
+            }
+            Self::Reap => {
+                buf.push(13);
+            }
+            Self::SetFeeMint {
+                fee_mint_rate,
+                fee_rate_oracle,
+            } => {
+                buf.push(14);
+                buf.extend_from_slice(&fee_mint_rate.to_le_bytes());
+                buf.extend_from_slice(fee_rate_oracle.as_ref());
+            }
+            Self::UpdateFeeRate { fee_mint_rate } => {
+                buf.push(15);
+                buf.extend_from_slice(&fee_mint_rate.to_le_bytes());
+            }
+            Self::RecoverClosedMint => {
+                buf.push(16);
+            }
+            Self::SetGatedRoles { gated_roles } => {
+                buf.push(17);
+                buf.push(*gated_roles);
+            }
+            Self::SetAllowlistEntry { roles } => {
+                buf.push(18);
+                buf.push(*roles);
+            }
+            Self::BuildInfo => {
+                buf.push(19);
+            }
+            Self::InitVault => {
+                buf.push(20);
+            }
+            Self::SetSettlementProgram => {
+                buf.push(21);
+            }
+            Self::SetVolumeCap {
+                volume_window_slots,
+                max_window_volume,
+            } => {
+                buf.push(22);
+                buf.extend_from_slice(&volume_window_slots.to_le_bytes());
+                buf.extend_from_slice(&max_window_volume.to_le_bytes());
+            }
+            Self::InitPairStats => {
+                buf.push(23);
+            }
+            Self::PrecreateEscrows { first_index, count } => {
+                buf.push(24);
+                buf.extend_from_slice(&first_index.to_le_bytes());
+                buf.push(*count);
+            }
+            Self::CancelExpired => {
+                buf.push(25);
+            }
+            Self::ForceClose => {
+                buf.push(26);
+            }
+            Self::SetPaused { paused } => {
+                buf.push(27);
+                buf.push(*paused as u8);
+            }
+            Self::SetUnlockBounds {
+                min_unlock_slots,
+                max_unlock_slots,
+            } => {
+                buf.push(28);
+                buf.extend_from_slice(&min_unlock_slots.to_le_bytes());
+                buf.extend_from_slice(&max_unlock_slots.to_le_bytes());
+            }
+            Self::AddAllowedTaker { taker } => {
+                buf.push(29);
+                buf.extend_from_slice(taker.as_ref());
+            }
+            Self::RemoveAllowedTaker { taker } => {
+                buf.push(30);
+                buf.extend_from_slice(taker.as_ref());
+            }
+            Self::MakeCounterOffer { expected_amount } => {
+                buf.push(31);
+                buf.extend_from_slice(&expected_amount.0.to_le_bytes());
+            }
+            Self::AcceptCounterOffer => {
+                buf.push(32);
+            }
+            Self::UpdateExpectedAmount { expected_amount } => {
+                buf.push(33);
+                buf.extend_from_slice(&expected_amount.0.to_le_bytes());
+            }
+            Self::SetEscrowTags { tags } => {
+                buf.push(34);
+                buf.push(tags.len() as u8);
+                for tag in tags {
+                    buf.extend_from_slice(&tag.pack());
+                }
+            }
+            Self::WrapSol { lamports } => {
+                buf.push(35);
+                buf.extend_from_slice(&lamports.to_le_bytes());
+            }
+            Self::InitInsurancePool { premium_bps } => {
+                buf.push(36);
+                buf.extend_from_slice(&premium_bps.to_le_bytes());
+            }
+            Self::InsureEscrow => {
+                buf.push(37);
+            }
+            Self::PayClaim { amount } => {
+                buf.push(38);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::AddBasketAsset { amount } => {
+                buf.push(39);
+                buf.extend_from_slice(&amount.0.to_le_bytes());
+            }
+            Self::ReclaimBasket => {
+                buf.push(40);
+            }
+            Self::MultiExchange => {
+                buf.push(41);
+            }
+            Self::DepositInitializer { taker } => {
+                buf.push(42);
+                buf.extend_from_slice(taker.as_ref());
+            }
+            Self::DepositTaker => {
+                buf.push(43);
+            }
+            Self::Settle => {
+                buf.push(44);
+            }
+            Self::Redeem { preimage } => {
+                buf.push(45);
+                buf.extend_from_slice(preimage);
+            }
+            Self::ExchangeWithPermit {
+                amount,
+                payment,
+                valid_until_slot,
+            } => {
+                buf.push(46);
+                buf.extend_from_slice(&amount.0.to_le_bytes());
+                buf.extend_from_slice(&payment.0.to_le_bytes());
+                buf.extend_from_slice(&valid_until_slot.to_le_bytes());
+            }
+            Self::SetInitRateLimit { max_inits_per_slot } => {
+                buf.push(47);
+                buf.extend_from_slice(&max_inits_per_slot.to_le_bytes());
+            }
+            Self::InitRateCounter => {
+                buf.push(48);
+            }
+            Self::SetPermitEthSigner { eth_address } => {
+                buf.push(49);
+                buf.extend_from_slice(eth_address);
+            }
+            Self::GetEscrow => {
+                buf.push(VIEW_TAG_BASE);
+            }
+            Self::GetConfig => {
+                buf.push(VIEW_TAG_BASE + 1);
+            }
+            Self::GetPairStats => {
+                buf.push(VIEW_TAG_BASE + 2);
+            }
         }
         buf
     }
@@ -106,7 +2302,19 @@ impl EscrowInstruction {
     /// 1. `[writable]` Temporary token account that should be created prior to this instruction and owned by the initializer
     /// 2. `[]` The initializer's token account for the token they will receive should the trade go through
     /// 3. `[writable]` The escrow account, it will hold all necessary info about the trade.
-    /// 5. `[]` The token program
+    /// 4. `[]` The token program
+    /// 5. `[writable]` The stats PDA
+    /// 6. `[]` The operator config PDA
+    /// 7..7+N. `[]` The payout token accounts
+    /// 7+N. `[writable]` The audit log PDA, if the config has one
+    /// 7+N+A. `[]` Optional token account of the initializer's for the deposited mint that Cancel
+    ///    and Reap refund to; without it they refund to the initializer's associated token account
+    ///
+    /// With `vault_deposit`, the token account to take the deposit from and its amount,
+    /// `pda_token_acct` must be the initializer's `vault_pda` for the deposited mint.
+    /// `settlement_program` must be the one `config` whitelists. `rate_counter` is the config's
+    /// `rate_counter_pda`, required if it limits inits per slot.
+#[allow(clippy::too_many_arguments)]
 pub fn init_escrow(
     program_id:&Pubkey,
     initiator: &Pubkey,
@@ -114,19 +2322,68 @@ pub fn init_escrow(
     init_token_acct:&Pubkey,
     escrow_account: &Pubkey,
     token_program: &Pubkey,
-    amount: u64,
+    config: &Pubkey,
+    amount: PaymentAmount,
+    payouts: &[(Pubkey, u16)],
+    rounding_mode: RoundingMode,
+    taker_commitment: Option<[u8; 32]>,
+    unlock_slots: u64,
+    audit_log: Option<&Pubkey>,
+    allowlist_entry: Option<&Pubkey>,
+    vault_deposit: Option<(&Pubkey, DepositAmount)>,
+    settlement_program: Option<&Pubkey>,
+    partial_fill: bool,
+    taker_gate: TakerGate,
+    hash_lock: HashLock,
+    gas_rebate: u64,
+    price_band: PriceBand,
+    quote_convention: QuoteConvention,
+    fill_cooldown_slots: u64,
+    designated_taker: Option<&Pubkey>,
+    rate_counter: Option<&Pubkey>,
+    refund_token_account: Option<&Pubkey>,
+    deposit_mint: Option<&Pubkey>,
 ) -> Result<Instruction, ProgramError> {
     let data = EscrowInstruction::InitEscrow {
         amount,
+        payout_bps: payouts.iter().map(|(_, bps)| *bps).collect(),
+        rounding_mode,
+        taker_commitment,
+        unlock_slots,
+        vault_deposit: vault_deposit.map_or(DepositAmount(0), |(_, amount)| amount),
+        settlement_program: settlement_program.copied(),
+        partial_fill,
+        taker_gate,
+        hash_lock,
+        gas_rebate,
+        price_band,
+        quote_convention,
+        fill_cooldown_slots,
+        has_refund_token_account: refund_token_account.is_some(),
+        has_deposit_mint: deposit_mint.is_some(),
+        designated_taker: designated_taker.copied(),
     }.pack();
 
-    let accounts = vec![
-        AccountMeta::new(*initiator, true),
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*initiator, true),
         AccountMeta::new(*pda_token_acct, false),
         AccountMeta::new_readonly(*init_token_acct, false),
         AccountMeta::new(*escrow_account, false),
         AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*config, false),
     ];
+    accounts.extend(
+        payouts
+            .iter()
+            .map(|(payout_account, _)| AccountMeta::new_readonly(*payout_account, false)),
+    );
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+    accounts.extend(allowlist_entry.map(|entry| AccountMeta::new_readonly(*entry, false)));
+    accounts.extend(vault_deposit.map(|(source, _)| AccountMeta::new(*source, false)));
+    accounts.extend(rate_counter.map(|rate_counter| AccountMeta::new(*rate_counter, false)));
+    accounts.extend(refund_token_account.map(|account| AccountMeta::new_readonly(*account, false)));
+    accounts.extend(deposit_mint.map(|mint| AccountMeta::new_readonly(*mint, false)));
 
     Ok(Instruction {
         program_id: *program_id,
@@ -135,6 +2392,7 @@ pub fn init_escrow(
     })
 }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn exchange(
         program_id: &Pubkey,
         taker: &Pubkey,
@@ -145,13 +2403,36 @@ pub fn init_escrow(
         initializer_main_account: &Pubkey,
         escrow_account: &Pubkey,
         token_program: &Pubkey,
-        amount: u64,
+        config: &Pubkey,
+        fee_token_account: &Pubkey,
+        deposit_mint: &Pubkey,
+        expected_mint: &Pubkey,
+        amount: DepositAmount,
+        taker_salt: Option<[u8; 32]>,
+        valid_until_slot: u64,
+        fill_amount: DepositAmount,
+        max_payment: PaymentAmount,
+        payout_accounts: &[Pubkey],
+        audit_log: Option<&Pubkey>,
+        taker_fee_mint_account: Option<(&Pubkey, &Pubkey)>,
+        pair_stats: Option<&Pubkey>,
+        taker_allowlist: Option<&Pubkey>,
+        taker_badge_account: Option<&Pubkey>,
+        nft_royalties: Option<(&Pubkey, &[Pubkey])>,
+        insurance: Option<(&Pubkey, &Pubkey)>,
+        basket: &[(Pubkey, Pubkey)],
+        settlement: Option<(&Pubkey, &[AccountMeta])>,
+        price_account: Option<&Pubkey>,
     ) -> Result<Instruction, ProgramError> {
         let data = EscrowInstruction::Exchange {
             amount,
+            taker_salt,
+            valid_until_slot,
+            fill_amount,
+            max_payment,
         }.pack();
     
-        let accounts = vec![
+        let mut accounts = vec![
             AccountMeta::new(*taker, true),
             AccountMeta::new(*taker_token_account, false),
             AccountMeta::new(*taker_token_account2, false),
@@ -160,11 +2441,2410 @@ pub fn init_escrow(
             AccountMeta::new(*initializer_main_account, false),
             AccountMeta::new(*escrow_account, false),
             AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+            AccountMeta::new(stats_pda(program_id), false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new(*fee_token_account, false),
+            AccountMeta::new_readonly(*deposit_mint, false),
+            AccountMeta::new_readonly(*expected_mint, false),
         ];
+        accounts.extend(price_account.map(|account| AccountMeta::new_readonly(*account, false)));
+        accounts.extend(payout_accounts.iter().map(|payout_account| AccountMeta::new(*payout_account, false)));
+        accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+        if let Some((account, fee_mint)) = taker_fee_mint_account {
+            accounts.push(AccountMeta::new(*account, false));
+            accounts.push(AccountMeta::new_readonly(*fee_mint, false));
+        }
+        accounts.extend(pair_stats.map(|pair_stats| AccountMeta::new(*pair_stats, false)));
+        accounts.extend(taker_allowlist.map(|taker_allowlist| AccountMeta::new_readonly(*taker_allowlist, false)));
+        accounts.extend(taker_badge_account.map(|account| AccountMeta::new_readonly(*account, false)));
+        if let Some((metadata, creator_accounts)) = nft_royalties {
+            accounts.push(AccountMeta::new_readonly(*metadata, false));
+            accounts.extend(creator_accounts.iter().map(|account| AccountMeta::new(*account, false)));
+        }
+        if let Some((insurance_pool, pool_token_account)) = insurance {
+            accounts.push(AccountMeta::new_readonly(*insurance_pool, false));
+            accounts.push(AccountMeta::new(*pool_token_account, false));
+        }
+        accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, true));
+        if let Some((settlement_program, settlement_accounts)) = settlement {
+            accounts.push(AccountMeta::new_readonly(*settlement_program, false));
+            accounts.push(AccountMeta::new_readonly(settlement_pda(program_id), false));
+            accounts.extend_from_slice(settlement_accounts);
+        }
     
         Ok(Instruction {
             program_id: *program_id,
             accounts,
             data,
         })
-    }
\ No newline at end of file
+    }
+
+/// Builds the ValidateExchange dry run for the same accounts an `exchange` would use
+#[allow(clippy::too_many_arguments)]
+pub fn validate_exchange(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    taker_token_account: &Pubkey,
+    taker_token_account2: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer_token_account: &Pubkey,
+    initializer_main_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    config: &Pubkey,
+    fee_token_account: &Pubkey,
+    deposit_mint: &Pubkey,
+    expected_mint: &Pubkey,
+    amount: DepositAmount,
+    taker_salt: Option<[u8; 32]>,
+    valid_until_slot: u64,
+    fill_amount: DepositAmount,
+    max_payment: PaymentAmount,
+    payout_accounts: &[Pubkey],
+    taker_fee_mint_account: Option<(&Pubkey, &Pubkey)>,
+    pair_stats: Option<&Pubkey>,
+    taker_allowlist: Option<&Pubkey>,
+    taker_badge_account: Option<&Pubkey>,
+    nft_royalties: Option<(&Pubkey, &[Pubkey])>,
+    insurance: Option<(&Pubkey, &Pubkey)>,
+    basket: &[(Pubkey, Pubkey)],
+    settlement_program: Option<&Pubkey>,
+    price_account: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::ValidateExchange {
+        amount,
+        taker_salt,
+        valid_until_slot,
+        fill_amount,
+        max_payment,
+    }
+    .pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*taker, true),
+        AccountMeta::new_readonly(*taker_token_account, false),
+        AccountMeta::new_readonly(*taker_token_account2, false),
+        AccountMeta::new_readonly(*temp_token_account, false),
+        AccountMeta::new_readonly(*initializer_token_account, false),
+        AccountMeta::new_readonly(*initializer_main_account, false),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new_readonly(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(*fee_token_account, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+        AccountMeta::new_readonly(*expected_mint, false),
+    ];
+    accounts.extend(price_account.map(|account| AccountMeta::new_readonly(*account, false)));
+    accounts.extend(payout_accounts.iter().map(|payout_account| AccountMeta::new_readonly(*payout_account, false)));
+    if let Some((account, fee_mint)) = taker_fee_mint_account {
+        accounts.push(AccountMeta::new_readonly(*account, false));
+        accounts.push(AccountMeta::new_readonly(*fee_mint, false));
+    }
+    accounts.extend(pair_stats.map(|pair_stats| AccountMeta::new_readonly(*pair_stats, false)));
+    accounts.extend(taker_allowlist.map(|taker_allowlist| AccountMeta::new_readonly(*taker_allowlist, false)));
+    accounts.extend(taker_badge_account.map(|account| AccountMeta::new_readonly(*account, false)));
+    if let Some((metadata, creator_accounts)) = nft_royalties {
+        accounts.push(AccountMeta::new_readonly(*metadata, false));
+        accounts.extend(creator_accounts.iter().map(|account| AccountMeta::new_readonly(*account, false)));
+    }
+    if let Some((insurance_pool, pool_token_account)) = insurance {
+        accounts.push(AccountMeta::new_readonly(*insurance_pool, false));
+        accounts.push(AccountMeta::new_readonly(*pool_token_account, false));
+    }
+    accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, false));
+    if let Some(settlement_program) = settlement_program {
+        accounts.push(AccountMeta::new_readonly(*settlement_program, false));
+        accounts.push(AccountMeta::new_readonly(settlement_pda(program_id), false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Builds a ResetTimeLock extending the escrow's cancellation window to `unlock_slots` from now
+pub fn reset_time_lock(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    unlock_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*initializer, true),
+            AccountMeta::new(*escrow_account, false),
+        ],
+        data: EscrowInstruction::ResetTimeLock { unlock_slots }.pack(),
+    })
+}
+
+/// The initializer is passed both as the signer and as the account their rent is returned to.
+/// `receipt` is the receipt's holder, who signs instead, and the escrow's expected mint if it
+/// has a receipt. `basket` lists the mint of each asset in the escrow's basket, in its order,
+/// with the signer's token
+/// account to refund it to. `mutual_deposit` is the token account the taker paid from and the
+/// escrow's expected mint if it's a mutual deposit.
+#[allow(clippy::too_many_arguments)]
+pub fn cancel(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    deposit_mint: &Pubkey,
+    audit_log: Option<&Pubkey>,
+    receipt: Option<(&Pubkey, &Pubkey)>,
+    basket: &[(Pubkey, Pubkey)],
+    mutual_deposit: Option<(&Pubkey, &Pubkey)>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::Cancel {}.pack();
+
+    let signer = receipt.map_or(initializer, |(holder, _)| holder);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+    accounts.extend(receipt_accounts(program_id, escrow_account, token_program, receipt, true));
+    accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, true));
+    if let Some((taker_refund_account, expected_mint)) = mutual_deposit {
+        accounts.extend([
+            AccountMeta::new(mutual_deposit_pda(program_id, escrow_account), false),
+            AccountMeta::new(taker_vault(program_id, escrow_account, expected_mint, token_program), false),
+            AccountMeta::new(*taker_refund_account, false),
+            AccountMeta::new_readonly(*expected_mint, false),
+        ]);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the PDA owning `vault`, which every escrow paid out of it signs as
+pub fn vault_authority_pda(program_id: &Pubkey, vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", vault.as_ref()], program_id).0
+}
+
+/// Returns the address of the program-wide stats PDA
+pub fn stats_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"stats"], program_id).0
+}
+
+pub fn init_stats(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitStats.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of an operator's config PDA
+pub fn config_pda(program_id: &Pubkey, admin: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"config", admin.as_ref()], program_id).0
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn init_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    fee_recipient: &Pubkey,
+    fee_bps: u16,
+    min_expected_amount: PaymentAmount,
+    max_expected_amount: PaymentAmount,
+    fee_tiers: &[FeeTier],
+    max_lifetime_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitConfig {
+        fee_bps,
+        min_expected_amount,
+        max_expected_amount,
+        fee_tiers: fee_tiers.to_vec(),
+        max_lifetime_slots,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+        AccountMeta::new_readonly(*fee_recipient, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    fee_recipient: &Pubkey,
+    fee_bps: u16,
+    min_expected_amount: PaymentAmount,
+    max_expected_amount: PaymentAmount,
+    fee_tiers: &[FeeTier],
+    max_lifetime_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::UpdateConfig {
+        fee_bps,
+        min_expected_amount,
+        max_expected_amount,
+        fee_tiers: fee_tiers.to_vec(),
+        max_lifetime_slots,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+        AccountMeta::new_readonly(*fee_recipient, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the audit log PDA for an operator config
+pub fn audit_log_pda(program_id: &Pubkey, config: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"audit", config.as_ref()], program_id).0
+}
+
+pub fn init_audit_log(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitAuditLog.pack();
+
+    let config = config_pda(program_id, admin);
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(config, false),
+        AccountMeta::new(audit_log_pda(program_id, &config), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn time_status(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::TimeStatus.pack();
+
+    let accounts = vec![AccountMeta::new_readonly(*escrow_account, false)];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Builds the view instruction `view`, one of GetEscrow, GetConfig or GetPairStats, over the
+/// account it reads
+pub fn view(
+    program_id: &Pubkey,
+    view: EscrowInstruction,
+    account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    if !matches!(
+        view,
+        EscrowInstruction::GetEscrow | EscrowInstruction::GetConfig | EscrowInstruction::GetPairStats
+    ) {
+        return Err(InvalidInstruction.into());
+    }
+    let data = view.pack();
+
+    let accounts = vec![AccountMeta::new_readonly(*account, false)];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the program-wide deployment PDA
+pub fn deployment_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"deployment"], program_id).0
+}
+
+pub fn update_deployment(
+    program_id: &Pubkey,
+    upgrade_authority: &Pubkey,
+    major: u16,
+    minor: u16,
+    patch: u16,
+    migration_notes_hash: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::UpdateDeployment {
+        major,
+        minor,
+        patch,
+        migration_notes_hash,
+    }
+    .pack();
+
+    let program_data = Pubkey::find_program_address(
+        &[program_id.as_ref()],
+        &solana_program::bpf_loader_upgradeable::id(),
+    )
+    .0;
+    let accounts = vec![
+        AccountMeta::new(*upgrade_authority, true),
+        AccountMeta::new(deployment_pda(program_id), false),
+        AccountMeta::new_readonly(program_data, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `fee_mint` of `None` switches the config back to fees in token Y
+pub fn set_fee_mint(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    fee_mint: Option<&Pubkey>,
+    fee_mint_rate: u64,
+    fee_rate_oracle: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetFeeMint {
+        fee_mint_rate,
+        fee_rate_oracle: *fee_rate_oracle,
+    }
+    .pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+    accounts.extend(fee_mint.map(|fee_mint| AccountMeta::new_readonly(*fee_mint, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `updater` is the config's fee rate oracle or its admin
+pub fn update_fee_rate(
+    program_id: &Pubkey,
+    updater: &Pubkey,
+    config: &Pubkey,
+    fee_mint_rate: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::UpdateFeeRate { fee_mint_rate }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*updater, true),
+        AccountMeta::new(*config, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of an escrow's receipt mint PDA
+pub fn receipt_mint_pda(program_id: &Pubkey, escrow_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt", escrow_account.as_ref()], program_id).0
+}
+
+pub fn mint_receipt(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    expected_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::MintReceipt.pack();
+
+    let receipt_mint = receipt_mint_pda(program_id, escrow_account);
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(receipt_mint, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(initializer, &receipt_mint, token_program),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(&receipt_mint, expected_mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*expected_mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `holder_token_account` receives the proceeds, in the escrow's expected mint
+pub fn claim_proceeds(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    escrow_account: &Pubkey,
+    expected_mint: &Pubkey,
+    holder_token_account: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::ClaimProceeds.pack();
+
+    let receipt_mint = receipt_mint_pda(program_id, escrow_account);
+    let accounts = vec![
+        AccountMeta::new(*holder, true),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(holder, &receipt_mint, token_program),
+            false,
+        ),
+        AccountMeta::new(receipt_mint, false),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(&receipt_mint, expected_mint, token_program),
+            false,
+        ),
+        AccountMeta::new(*holder_token_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `initializer_token_account` is refunded the deposit and must be the escrow's refund account,
+/// or with a receipt its holder's associated token account. `receipt` is the receipt's holder
+/// and the escrow's expected mint if it has one. `basket` lists the mint of each asset in the
+/// escrow's basket, in its order, with the token account to refund it to.
+#[allow(clippy::too_many_arguments)]
+pub fn reap(
+    program_id: &Pubkey,
+    reaper: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer: &Pubkey,
+    initializer_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    deposit_mint: &Pubkey,
+    audit_log: Option<&Pubkey>,
+    receipt: Option<(&Pubkey, &Pubkey)>,
+    basket: &[(Pubkey, Pubkey)],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::Reap.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*reaper, true),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+    accounts.extend(receipt_accounts(program_id, escrow_account, token_program, receipt, false));
+    accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// As for `reap`
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_expired(
+    program_id: &Pubkey,
+    cranker: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer: &Pubkey,
+    initializer_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    deposit_mint: &Pubkey,
+    audit_log: Option<&Pubkey>,
+    receipt: Option<(&Pubkey, &Pubkey)>,
+    basket: &[(Pubkey, Pubkey)],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::CancelExpired.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*cranker, true),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+    accounts.extend(receipt_accounts(program_id, escrow_account, token_program, receipt, false));
+    accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn force_close(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    admin: &Pubkey,
+    config: &Pubkey,
+    deposit_mint: &Pubkey,
+    audit_log: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::ForceClose.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `closed_mint` is whichever of the escrow's mints no longer exists, `deposit_mint` the mint
+/// of the escrow's vault
+#[allow(clippy::too_many_arguments)]
+pub fn recover_closed_mint(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    closed_mint: &Pubkey,
+    deposit_mint: &Pubkey,
+    audit_log: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::RecoverClosedMint.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*closed_mint, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of a signer's allowlist entry PDA under a config
+pub fn allowlist_entry_pda(program_id: &Pubkey, config: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"allowlist", config.as_ref(), member.as_ref()], program_id).0
+}
+
+pub fn set_gated_roles(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    gated_roles: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetGatedRoles { gated_roles }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_allowlist_entry(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    member: &Pubkey,
+    roles: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetAllowlistEntry { roles }.pack();
+
+    let config = config_pda(program_id, admin);
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new_readonly(*member, false),
+        AccountMeta::new(allowlist_entry_pda(program_id, &config, member), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn build_info(program_id: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::BuildInfo.pack();
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![],
+        data,
+    })
+}
+
+/// Returns the address of the initializer's shared vault for `mint`
+pub fn vault_pda(program_id: &Pubkey, initializer: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", initializer.as_ref(), mint.as_ref()], program_id).0
+}
+
+pub fn init_vault(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitVault.pack();
+
+    let vault = vault_pda(program_id, initializer, mint);
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, &vault), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the program-wide settlement PDA, which owns the staging accounts
+/// settlement hooks are paid out of
+pub fn settlement_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"settlement"], program_id).0
+}
+
+/// Returns the staging account Exchange pays a settlement hook's proceeds into, which must
+/// exist before the fill
+pub fn settlement_staging_account(
+    program_id: &Pubkey,
+    expected_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    get_associated_token_address_with_program_id(&settlement_pda(program_id), expected_mint, token_program)
+}
+
+/// `settlement_program` of `None` stops escrows under the admin's config from settling into one
+pub fn set_settlement_program(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    settlement_program: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetSettlementProgram.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+    accounts.extend(settlement_program.map(|program| AccountMeta::new_readonly(*program, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `max_window_volume` of 0 lifts the admin's config's volume cap
+pub fn set_volume_cap(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    volume_window_slots: u64,
+    max_window_volume: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetVolumeCap {
+        volume_window_slots,
+        max_window_volume,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_paused(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    paused: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetPaused { paused }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_unlock_bounds(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    min_unlock_slots: u64,
+    max_unlock_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetUnlockBounds {
+        min_unlock_slots,
+        max_unlock_slots,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the escrow's taker allowlist PDA
+pub fn taker_allowlist_pda(program_id: &Pubkey, escrow_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"takers", escrow_account.as_ref()], program_id).0
+}
+
+pub fn add_allowed_taker(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    taker: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::AddAllowedTaker { taker: *taker }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(taker_allowlist_pda(program_id, escrow_account), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn remove_allowed_taker(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    taker: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::RemoveAllowedTaker { taker: *taker }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(taker_allowlist_pda(program_id, escrow_account), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the taker's counter-offer PDA for an escrow
+pub fn counter_offer_pda(program_id: &Pubkey, escrow_account: &Pubkey, taker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"counter_offer", escrow_account.as_ref(), taker.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+/// `expected_amount` of 0 withdraws the taker's counter-offer
+pub fn make_counter_offer(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    escrow_account: &Pubkey,
+    expected_amount: PaymentAmount,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::MakeCounterOffer { expected_amount }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*taker, true),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(counter_offer_pda(program_id, escrow_account, taker), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn accept_counter_offer(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    taker: &Pubkey,
+    config: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::AcceptCounterOffer.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(counter_offer_pda(program_id, escrow_account, taker), false),
+        AccountMeta::new(*taker, false),
+        AccountMeta::new_readonly(*config, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn update_expected_amount(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    config: &Pubkey,
+    expected_amount: PaymentAmount,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::UpdateExpectedAmount { expected_amount }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(*config, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the escrow's tags PDA
+pub fn escrow_tags_pda(program_id: &Pubkey, escrow_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"tags", escrow_account.as_ref()], program_id).0
+}
+
+/// Empty `tags` close the tags of a settled escrow
+pub fn set_escrow_tags(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    tags: Vec<EscrowTag>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetEscrowTags { tags }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(escrow_tags_pda(program_id, escrow_account), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new(stats_pda(program_id), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `wsol_account` is a new keypair, which signs along with the owner
+pub fn wrap_sol(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    wsol_account: &Pubkey,
+    token_program: &Pubkey,
+    lamports: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::WrapSol { lamports }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*wsol_account, true),
+        AccountMeta::new_readonly(native_mint(token_program), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of a config's insurance pool PDA
+pub fn insurance_pool_pda(program_id: &Pubkey, config: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"insurance", config.as_ref()], program_id).0
+}
+
+/// `premium_bps` of an insured fill's payment goes to the admin's config's insurance pool
+pub fn init_insurance_pool(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    premium_bps: u16,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitInsurancePool { premium_bps }.pack();
+
+    let config = config_pda(program_id, admin);
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new(insurance_pool_pda(program_id, &config), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn insure_escrow(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    config: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InsureEscrow.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(insurance_pool_pda(program_id, config), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Pays a claim out of the admin's config's insurance pool, from its associated token account
+/// for `mint`
+pub fn pay_claim(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    claimant_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::PayClaim { amount }.pack();
+
+    let config = config_pda(program_id, admin);
+    let insurance_pool = insurance_pool_pda(program_id, &config);
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new(insurance_pool, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(&insurance_pool, mint, token_program),
+            false,
+        ),
+        AccountMeta::new(*claimant_token_account, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of an escrow's basket PDA
+pub fn basket_pda(program_id: &Pubkey, escrow_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"basket", escrow_account.as_ref()], program_id).0
+}
+
+/// Returns the address of the vault holding a basket's asset of `mint`
+pub fn basket_vault(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    get_associated_token_address_with_program_id(&basket_pda(program_id, escrow_account), mint, token_program)
+}
+
+/// Appends what `mint`'s transfer hook needs to an instruction moving tokens of it: the hook
+/// program, the mint's `ExtraAccountMetaList` and `extra_accounts`, the accounts the list
+/// resolves to, as the hook interface's offchain helpers find them
+pub fn append_transfer_hook_accounts(
+    instruction: &mut Instruction,
+    mint: &Pubkey,
+    hook_program_id: &Pubkey,
+    extra_accounts: &[AccountMeta],
+) {
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(*hook_program_id, false),
+        AccountMeta::new_readonly(get_extra_account_metas_address(mint, hook_program_id), false),
+    ]);
+    instruction.accounts.extend_from_slice(extra_accounts);
+}
+
+/// The basket PDA followed by each asset's vault, recipient token account and mint, for the
+/// `(mint, recipient token account)` pairs of `basket`; none at all if it's empty
+fn basket_accounts(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    basket: &[(Pubkey, Pubkey)],
+    writable: bool,
+) -> Vec<AccountMeta> {
+    if basket.is_empty() {
+        return Vec::new();
+    }
+    let meta = |pubkey: Pubkey| {
+        if writable {
+            AccountMeta::new(pubkey, false)
+        } else {
+            AccountMeta::new_readonly(pubkey, false)
+        }
+    };
+    let mut accounts = vec![meta(basket_pda(program_id, escrow_account))];
+    for (mint, recipient) in basket {
+        accounts.push(meta(basket_vault(program_id, escrow_account, mint, token_program)));
+        accounts.push(meta(*recipient));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    accounts
+}
+
+/// The receipt mint PDA, the holder's receipt token account and the proceeds account, for
+/// the `(holder, expected mint)` of `receipt`; none at all without one. The receipt token
+/// account is only writable when the receipt is burned.
+fn receipt_accounts(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    receipt: Option<(&Pubkey, &Pubkey)>,
+    burn: bool,
+) -> Vec<AccountMeta> {
+    let Some((holder, expected_mint)) = receipt else {
+        return Vec::new();
+    };
+    let receipt_mint = receipt_mint_pda(program_id, escrow_account);
+    let receipt_token_account =
+        get_associated_token_address_with_program_id(holder, &receipt_mint, token_program);
+    vec![
+        AccountMeta::new(receipt_mint, false),
+        if burn {
+            AccountMeta::new(receipt_token_account, false)
+        } else {
+            AccountMeta::new_readonly(receipt_token_account, false)
+        },
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(&receipt_mint, expected_mint, token_program),
+            false,
+        ),
+    ]
+}
+
+/// Moves `amount` of `mint` from `source_token_account` into the escrow's basket
+#[allow(clippy::too_many_arguments)]
+pub fn add_basket_asset(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    source_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: DepositAmount,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::AddBasketAsset { amount }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(basket_pda(program_id, escrow_account), false),
+        AccountMeta::new(*source_token_account, false),
+        AccountMeta::new(basket_vault(program_id, escrow_account, mint, token_program), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `basket` lists the mint of each asset in the basket, in its order, with the initializer's
+/// token account to return it to
+pub fn reclaim_basket(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    token_program: &Pubkey,
+    basket: &[(Pubkey, Pubkey)],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::ReclaimBasket.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(basket_pda(program_id, escrow_account), false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+    accounts.extend(basket_accounts(program_id, escrow_account, token_program, basket, true).into_iter().skip(1));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// One escrow of a MultiExchange ring and the accounts settling it
+pub struct RingLeg {
+    pub escrow_account: Pubkey,
+    pub vault: Pubkey,
+    pub initializer: Pubkey,
+    /// The initializer's token account for their expected mint
+    pub initializer_receive_token_account: Pubkey,
+    pub config: Pubkey,
+    /// The config fee recipient's token account for the escrow's expected mint
+    pub fee_token_account: Pubkey,
+    pub deposit_mint: Pubkey,
+}
+
+/// Settles `legs` as a ring, each initializer receiving the next leg's deposit
+pub fn multi_exchange(
+    program_id: &Pubkey,
+    matcher: &Pubkey,
+    token_program: &Pubkey,
+    legs: &[RingLeg; 3],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::MultiExchange.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*matcher, true),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(stats_pda(program_id), false),
+    ];
+    for leg in legs {
+        accounts.extend([
+            AccountMeta::new(leg.escrow_account, false),
+            AccountMeta::new(leg.vault, false),
+            AccountMeta::new(leg.initializer, false),
+            AccountMeta::new(leg.initializer_receive_token_account, false),
+            AccountMeta::new_readonly(vault_authority_pda(program_id, &leg.vault), false),
+            AccountMeta::new_readonly(leg.config, false),
+            AccountMeta::new(leg.fee_token_account, false),
+            AccountMeta::new_readonly(leg.deposit_mint, false),
+        ]);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of an escrow's mutual deposit PDA
+pub fn mutual_deposit_pda(program_id: &Pubkey, escrow_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mutual", escrow_account.as_ref()], program_id).0
+}
+
+/// Returns the address of the vault holding a mutual deposit's taker payment
+pub fn taker_vault(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+    expected_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    get_associated_token_address_with_program_id(
+        &mutual_deposit_pda(program_id, escrow_account),
+        expected_mint,
+        token_program,
+    )
+}
+
+pub fn deposit_initializer(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    taker: &Pubkey,
+    expected_mint: &Pubkey,
+    deposit_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::DepositInitializer { taker: *taker }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(mutual_deposit_pda(program_id, escrow_account), false),
+        AccountMeta::new(taker_vault(program_id, escrow_account, expected_mint, token_program), false),
+        AccountMeta::new_readonly(*expected_mint, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn deposit_taker(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    escrow_account: &Pubkey,
+    taker_sending_token_account: &Pubkey,
+    taker_receive_token_account: &Pubkey,
+    expected_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::DepositTaker.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*taker, true),
+        AccountMeta::new_readonly(*escrow_account, false),
+        AccountMeta::new(mutual_deposit_pda(program_id, escrow_account), false),
+        AccountMeta::new(*taker_sending_token_account, false),
+        AccountMeta::new(taker_vault(program_id, escrow_account, expected_mint, token_program), false),
+        AccountMeta::new_readonly(*taker_receive_token_account, false),
+        AccountMeta::new_readonly(*expected_mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `taker_receive_token_account` is the account the taker named at DepositTaker
+#[allow(clippy::too_many_arguments)]
+pub fn settle(
+    program_id: &Pubkey,
+    settler: &Pubkey,
+    escrow_account: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer: &Pubkey,
+    initializer_receive_token_account: &Pubkey,
+    taker_receive_token_account: &Pubkey,
+    token_program: &Pubkey,
+    config: &Pubkey,
+    fee_token_account: &Pubkey,
+    deposit_mint: &Pubkey,
+    expected_mint: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::Settle.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*settler, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(mutual_deposit_pda(program_id, escrow_account), false),
+        AccountMeta::new(taker_vault(program_id, escrow_account, expected_mint, token_program), false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new(*initializer_receive_token_account, false),
+        AccountMeta::new(*taker_receive_token_account, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(*fee_token_account, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+        AccountMeta::new_readonly(*expected_mint, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `audit_log` is required if the escrow has one
+#[allow(clippy::too_many_arguments)]
+pub fn redeem(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    recipient_receive_token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    temp_token_account: &Pubkey,
+    initializer: &Pubkey,
+    token_program: &Pubkey,
+    config: &Pubkey,
+    deposit_mint: &Pubkey,
+    preimage: [u8; 32],
+    audit_log: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::Redeem { preimage }.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*recipient, true),
+        AccountMeta::new(*recipient_receive_token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new(*temp_token_account, false),
+        AccountMeta::new_readonly(vault_authority_pda(program_id, temp_token_account), false),
+        AccountMeta::new(*initializer, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(stats_pda(program_id), false),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+    ];
+    accounts.extend(audit_log.map(|audit_log| AccountMeta::new(*audit_log, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Turns an Exchange of the whole deposit built by `exchange` into an ExchangeWithPermit at
+/// the initializer's permitted `payment`. The transaction must carry the ed25519 program
+/// instruction verifying their signature over `state::Permit::message` right before it, or
+/// the secp256k1 one verifying the escrow's permit Ethereum signer's over
+/// `state::Permit::eth_message`.
+pub fn exchange_with_permit(
+    exchange: Instruction,
+    payment: PaymentAmount,
+    valid_until_slot: u64,
+) -> Result<Instruction, ProgramError> {
+    let EscrowInstruction::Exchange { amount, .. } = EscrowInstruction::unpack(&exchange.data)? else {
+        return Err(InvalidInstruction.into());
+    };
+    let data = EscrowInstruction::ExchangeWithPermit {
+        amount,
+        payment,
+        valid_until_slot,
+    }
+    .pack();
+
+    let mut accounts = exchange.accounts;
+    accounts.push(AccountMeta::new_readonly(solana_program::sysvar::instructions::id(), false));
+
+    Ok(Instruction {
+        program_id: exchange.program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `max_inits_per_slot` of 0 lifts the admin's config's init rate limit
+pub fn set_init_rate_limit(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    max_inits_per_slot: u32,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetInitRateLimit { max_inits_per_slot }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(config_pda(program_id, admin), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the PDA counting a config's InitEscrows per slot
+pub fn rate_counter_pda(program_id: &Pubkey, config: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"rate_counter", config.as_ref()], program_id).0
+}
+
+pub fn init_rate_counter(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    config: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitRateCounter.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(rate_counter_pda(program_id, config), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// An `eth_address` of all zero removes the escrow's permit Ethereum signer
+pub fn set_permit_eth_signer(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    escrow_account: &Pubkey,
+    eth_address: [u8; 20],
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::SetPermitEthSigner { eth_address }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*escrow_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the stats PDA tracking a mint pair's fill volume under a config
+pub fn pair_stats_pda(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    deposit_mint: &Pubkey,
+    expected_mint: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"pair_stats", config.as_ref(), deposit_mint.as_ref(), expected_mint.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn init_pair_stats(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    config: &Pubkey,
+    deposit_mint: &Pubkey,
+    expected_mint: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::InitPairStats.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(pair_stats_pda(program_id, config, deposit_mint, expected_mint), false),
+        AccountMeta::new_readonly(*deposit_mint, false),
+        AccountMeta::new_readonly(*expected_mint, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Returns the address of the initializer's precreated escrow account at `index`
+pub fn precreated_escrow_pda(program_id: &Pubkey, initializer: &Pubkey, index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"escrow_account", initializer.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+    .0
+}
+
+/// Builds a PrecreateEscrows for the initializer's accounts `first_index..first_index + count`
+pub fn precreate_escrows(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    first_index: u64,
+    count: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = EscrowInstruction::PrecreateEscrows { first_index, count }.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    accounts.extend((0..count as u64).map(|i| {
+        AccountMeta::new(precreated_escrow_pda(program_id, initializer, first_index + i), false)
+    }));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_specs::AccountPresence;
+    use crate::state::{MAX_BASKET_ASSETS, MAX_PRECREATED_ESCROWS, MAX_ROYALTY_CREATORS};
+
+    /// Payload length of every fixed-length variant, by tag
+    const FIXED_PAYLOAD_LENS: &[(u8, usize)] = &[
+        (2, 8),
+        (3, 0),
+        (4, 0),
+        (7, 0),
+        (8, 0),
+        (9, 38),
+        (11, 0),
+        (12, 0),
+        (13, 0),
+        (14, 40),
+        (15, 8),
+        (16, 0),
+        (17, 1),
+        (18, 1),
+        (19, 0),
+        (20, 0),
+        (21, 0),
+        (22, 16),
+        (23, 0),
+        (24, 9),
+        (25, 0),
+        (26, 0),
+        (27, 1),
+        (28, 16),
+        (29, 32),
+        (30, 32),
+        (31, 8),
+        (32, 0),
+        (33, 8),
+        (35, 8),
+        (36, 2),
+        (37, 0),
+        (38, 8),
+        (39, 8),
+        (40, 0),
+        (41, 0),
+        (42, 32),
+        (43, 0),
+        (44, 0),
+        (45, 32),
+        (46, 24),
+        (47, 4),
+        (48, 0),
+        (49, 20),
+        (200, 0),
+        (201, 0),
+        (202, 0),
+    ];
+
+    /// The longest encoding of every variable-length variant, with each optional field present
+    fn full_variable_encodings() -> Vec<Vec<u8>> {
+        let key = Pubkey::new_from_array([7; 32]);
+        let fee_tiers = vec![
+            FeeTier {
+                min_age_slots: 10,
+                fee_bps: 50,
+            },
+            FeeTier {
+                min_age_slots: 100,
+                fee_bps: 25,
+            },
+        ];
+        vec![
+            EscrowInstruction::InitEscrow {
+                amount: PaymentAmount(1_000),
+                payout_bps: vec![TOTAL_PAYOUT_BPS / MAX_PAYOUTS as u16; MAX_PAYOUTS],
+                rounding_mode: RoundingMode::HalfEven,
+                taker_commitment: Some([1; 32]),
+                unlock_slots: 500,
+                vault_deposit: DepositAmount(10),
+                settlement_program: Some(key),
+                partial_fill: true,
+                taker_gate: TakerGate {
+                    min_lamports: 1,
+                    badge_mint: key,
+                },
+                hash_lock: HashLock {
+                    hash: [2; 32],
+                    recipient: key,
+                },
+                gas_rebate: 5_000,
+                price_band: PriceBand {
+                    price_account: key,
+                    offset_bps: -100,
+                },
+                quote_convention: QuoteConvention::InterestAdjusted,
+                fill_cooldown_slots: 20,
+                has_refund_token_account: true,
+                has_deposit_mint: true,
+                designated_taker: Some(key),
+            }
+            .pack(),
+            EscrowInstruction::Exchange {
+                amount: DepositAmount(10),
+                taker_salt: Some([3; 32]),
+                valid_until_slot: 1_000,
+                fill_amount: DepositAmount(5),
+                max_payment: PaymentAmount(600),
+            }
+            .pack(),
+            EscrowInstruction::InitConfig {
+                fee_bps: 30,
+                min_expected_amount: PaymentAmount(1),
+                max_expected_amount: PaymentAmount(u64::MAX),
+                fee_tiers: fee_tiers.clone(),
+                max_lifetime_slots: 10_000,
+            }
+            .pack(),
+            EscrowInstruction::UpdateConfig {
+                fee_bps: 30,
+                min_expected_amount: PaymentAmount(1),
+                max_expected_amount: PaymentAmount(u64::MAX),
+                fee_tiers,
+                max_lifetime_slots: 10_000,
+            }
+            .pack(),
+            EscrowInstruction::ValidateExchange {
+                amount: DepositAmount(10),
+                taker_salt: Some([3; 32]),
+                valid_until_slot: 1_000,
+                fill_amount: DepositAmount(5),
+                max_payment: PaymentAmount(600),
+            }
+            .pack(),
+            EscrowInstruction::SetEscrowTags {
+                tags: vec![EscrowTag::new(b"desk", b"otc").unwrap(); MAX_ESCROW_TAGS],
+            }
+            .pack(),
+        ]
+    }
+
+    fn with_trailing_byte(input: &[u8]) -> Vec<u8> {
+        let mut input = input.to_vec();
+        input.push(0);
+        input
+    }
+
+    #[test]
+    fn every_tag_is_covered() {
+        let mut tags: Vec<u8> = FIXED_PAYLOAD_LENS.iter().map(|(tag, _)| *tag).collect();
+        tags.extend(full_variable_encodings().iter().map(|input| input[0]));
+        for tag in 0..=u8::MAX {
+            let known = EscrowInstruction::unpack(&[tag]).is_ok()
+                || (1..=MAX_INSTRUCTION_DATA_LEN)
+                    .any(|len| EscrowInstruction::unpack(&[vec![tag], vec![1; len]].concat()).is_ok());
+            assert_eq!(known, tags.contains(&tag), "tag {}", tag);
+        }
+    }
+
+    #[test]
+    fn fixed_length_variants_reject_truncated_and_trailing_bytes() {
+        for (tag, len) in FIXED_PAYLOAD_LENS {
+            let input = [vec![*tag], vec![1; *len]].concat();
+            assert!(EscrowInstruction::unpack(&input).is_ok(), "tag {}", tag);
+            assert_eq!(EscrowInstruction::unpack(&input).unwrap().pack(), input, "tag {}", tag);
+            for end in 0..input.len() {
+                assert!(EscrowInstruction::unpack(&input[..end]).is_err(), "tag {} cut to {}", tag, end);
+            }
+            assert!(
+                EscrowInstruction::unpack(&with_trailing_byte(&input)).is_err(),
+                "tag {} with a trailing byte",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn variable_length_variants_reject_truncated_and_trailing_bytes() {
+        for input in full_variable_encodings() {
+            let tag = input[0];
+            assert!(input.len() <= MAX_INSTRUCTION_DATA_LEN, "tag {}", tag);
+            assert_eq!(EscrowInstruction::unpack(&input).unwrap().pack(), input, "tag {}", tag);
+            assert!(
+                EscrowInstruction::unpack(&input[..input.len() - 1]).is_err(),
+                "tag {} missing its last byte",
+                tag
+            );
+            assert!(
+                EscrowInstruction::unpack(&with_trailing_byte(&input)).is_err(),
+                "tag {} with a trailing byte",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn compute_unit_limits_fit_a_transaction() {
+        // the runtime's per-transaction cap, which no single instruction's worst case may exceed
+        const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+        let max_payout_count = MAX_PAYOUTS + MAX_ROYALTY_CREATORS + MAX_BASKET_ASSETS;
+        let instructions = FIXED_PAYLOAD_LENS
+            .iter()
+            .map(|(tag, len)| [vec![*tag], vec![1; *len]].concat())
+            .chain(full_variable_encodings())
+            .map(|input| EscrowInstruction::unpack(&input).unwrap())
+            .chain([EscrowInstruction::PrecreateEscrows {
+                first_index: 0,
+                count: MAX_PRECREATED_ESCROWS,
+            }]);
+        for instruction in instructions {
+            let limit = instruction.compute_unit_limit(max_payout_count)
+                + BASKET_ASSET_COMPUTE_UNITS * MAX_BASKET_ASSETS as u32
+                + MUTUAL_DEPOSIT_COMPUTE_UNITS;
+            assert!(limit <= MAX_TRANSACTION_COMPUTE_UNITS, "tag {}", instruction.pack()[0]);
+        }
+    }
+
+    /// Which of an instruction's non-required accounts a builder was asked to pass
+    #[derive(Clone, Copy, Default)]
+    struct Passed<'a> {
+        present: &'a [&'a str],
+        payouts: usize,
+        royalty_creators: usize,
+        basket_assets: usize,
+        repeated: usize,
+    }
+
+    /// Checks `instruction`'s accounts are exactly the entries of its `account_specs` that
+    /// `passed` calls for, in order and with the spec's writable and signer flags
+    fn assert_matches_specs(instruction: &Instruction, passed: Passed) {
+        let specs = EscrowInstruction::unpack(&instruction.data).unwrap().account_specs();
+        let mut expected = Vec::new();
+        let mut i = 0;
+        while i < specs.len() {
+            let spec = &specs[i];
+            match spec.presence {
+                AccountPresence::Required => expected.push(spec),
+                AccountPresence::Conditional | AccountPresence::Optional => {
+                    if passed.present.contains(&spec.name) {
+                        expected.push(spec);
+                    }
+                }
+                AccountPresence::PerPayout => {
+                    expected.extend(std::iter::repeat_n(spec, passed.payouts));
+                }
+                AccountPresence::PerRoyaltyCreator => {
+                    expected.extend(std::iter::repeat_n(spec, passed.royalty_creators));
+                }
+                AccountPresence::PerBasketAsset => {
+                    let group_len = specs[i..]
+                        .iter()
+                        .take_while(|spec| spec.presence == AccountPresence::PerBasketAsset)
+                        .count();
+                    for _ in 0..passed.basket_assets {
+                        expected.extend(&specs[i..i + group_len]);
+                    }
+                    i += group_len;
+                    continue;
+                }
+                AccountPresence::Repeated => {
+                    expected.extend(std::iter::repeat_n(spec, passed.repeated));
+                }
+            }
+            i += 1;
+        }
+        for name in passed.present {
+            assert!(expected.iter().any(|spec| spec.name == *name), "no account spec {}", name);
+        }
+        assert_eq!(instruction.accounts.len(), expected.len(), "tag {}", instruction.data[0]);
+        for (meta, spec) in instruction.accounts.iter().zip(expected) {
+            assert_eq!(
+                (meta.is_writable, meta.is_signer),
+                (spec.writable, spec.signer),
+                "tag {} account {}",
+                instruction.data[0],
+                spec.name
+            );
+        }
+    }
+
+    #[test]
+    fn builders_match_account_specs() {
+        let program_id = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let pair = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payout_accounts = [pair.0, pair.1];
+        let basket = [pair];
+        let two_assets = [pair, pair];
+        let none = Passed::default;
+
+        let init_escrow = |payouts: &[(Pubkey, u16)], optional: Option<&Pubkey>| {
+            super::init_escrow(
+                &program_id,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                PaymentAmount(1),
+                payouts,
+                RoundingMode::default(),
+                None,
+                DEFAULT_UNLOCK_SLOTS,
+                optional,
+                optional,
+                optional.map(|source| (source, DepositAmount(1))),
+                None,
+                false,
+                TakerGate::default(),
+                HashLock::default(),
+                0,
+                PriceBand::default(),
+                QuoteConvention::default(),
+                0,
+                None,
+                optional,
+                optional,
+                optional,
+            )
+            .unwrap()
+        };
+        assert_matches_specs(&init_escrow(&[], None), none());
+        assert_matches_specs(
+            &init_escrow(&[(key, 5_000), (key, 5_000)], Some(&key)),
+            Passed {
+                present: &[
+                    "audit_log",
+                    "allowlist_entry",
+                    "vault_deposit_source",
+                    "rate_counter",
+                    "refund_token_account",
+                    "deposit_mint",
+                ],
+                payouts: 2,
+                ..none()
+            },
+        );
+
+        let exchange = |full: bool| {
+            super::exchange(
+                &program_id,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                DepositAmount(1),
+                None,
+                0,
+                DepositAmount(0),
+                PaymentAmount(0),
+                if full { &payout_accounts[..] } else { &[] },
+                full.then_some(&key),
+                full.then_some((&key, &key)),
+                full.then_some(&key),
+                full.then_some(&key),
+                full.then_some(&key),
+                full.then_some((&key, &[pair.0][..])),
+                full.then_some((&key, &key)),
+                if full { &basket[..] } else { &[] },
+                full.then_some((&key, &[][..])),
+                full.then_some(&key),
+            )
+            .unwrap()
+        };
+        let full_exchange = Passed {
+            present: &[
+                "audit_log",
+                "taker_fee_mint_account",
+                "fee_mint",
+                "pair_stats",
+                "taker_allowlist",
+                "taker_badge_account",
+                "nft_metadata",
+                "insurance_pool",
+                "insurance_pool_token_account",
+                "basket",
+                "settlement_program",
+                "settlement_pda",
+                "price_account",
+            ],
+            payouts: 2,
+            royalty_creators: 1,
+            basket_assets: 1,
+            ..none()
+        };
+        assert_matches_specs(&exchange(false), none());
+        assert_matches_specs(&exchange(true), full_exchange);
+        assert_matches_specs(
+            &super::exchange_with_permit(exchange(false), PaymentAmount(1), 1).unwrap(),
+            none(),
+        );
+
+        let validate_exchange = |full: bool| {
+            super::validate_exchange(
+                &program_id,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                DepositAmount(1),
+                None,
+                0,
+                DepositAmount(0),
+                PaymentAmount(0),
+                if full { &payout_accounts[..] } else { &[] },
+                full.then_some((&key, &key)),
+                full.then_some(&key),
+                full.then_some(&key),
+                full.then_some(&key),
+                full.then_some((&key, &[pair.0][..])),
+                full.then_some((&key, &key)),
+                if full { &basket[..] } else { &[] },
+                full.then_some(&key),
+                full.then_some(&key),
+            )
+            .unwrap()
+        };
+        assert_matches_specs(&validate_exchange(false), none());
+        assert_matches_specs(
+            &validate_exchange(true),
+            Passed {
+                present: &full_exchange.present[1..],
+                ..full_exchange
+            },
+        );
+
+        let cancel = |full: bool| {
+            super::cancel(
+                &program_id,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                full.then_some(&key),
+                full.then_some((&key, &key)),
+                if full { &two_assets[..] } else { &[] },
+                full.then_some((&key, &key)),
+            )
+            .unwrap()
+        };
+        assert_matches_specs(&cancel(false), none());
+        assert_matches_specs(
+            &cancel(true),
+            Passed {
+                present: &[
+                    "audit_log",
+                    "receipt_mint",
+                    "receipt_token_account",
+                    "proceeds_account",
+                    "basket",
+                    "mutual_deposit",
+                    "taker_vault",
+                    "taker_refund_token_account",
+                    "expected_mint",
+                ],
+                basket_assets: 2,
+                ..none()
+            },
+        );
+
+        type CloseBuilder = fn(
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            &Pubkey,
+            Option<&Pubkey>,
+            Option<(&Pubkey, &Pubkey)>,
+            &[(Pubkey, Pubkey)],
+        ) -> Result<Instruction, ProgramError>;
+        for builder in [super::reap as CloseBuilder, super::cancel_expired] {
+            let build = |full: bool| {
+                builder(
+                    &program_id,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    full.then_some(&key),
+                    full.then_some((&key, &key)),
+                    if full { &basket[..] } else { &[] },
+                )
+                .unwrap()
+            };
+            assert_matches_specs(&build(false), none());
+            assert_matches_specs(
+                &build(true),
+                Passed {
+                    present: &[
+                        "audit_log",
+                        "receipt_mint",
+                        "receipt_token_account",
+                        "proceeds_account",
+                        "basket",
+                    ],
+                    basket_assets: 1,
+                    ..none()
+                },
+            );
+        }
+        for audit_log in [None, Some(&key)] {
+            let audited = Passed {
+                present: if audit_log.is_some() { &["audit_log"] } else { &[] },
+                ..none()
+            };
+            assert_matches_specs(
+                &super::force_close(
+                    &program_id,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    audit_log,
+                )
+                .unwrap(),
+                audited,
+            );
+            assert_matches_specs(
+                &super::recover_closed_mint(
+                    &program_id,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    audit_log,
+                )
+                .unwrap(),
+                audited,
+            );
+            assert_matches_specs(
+                &super::redeem(
+                    &program_id,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    [0; 32],
+                    audit_log,
+                )
+                .unwrap(),
+                audited,
+            );
+        }
+        for passed in [None, Some(&key)] {
+            let present: &[&str] = if passed.is_some() { &["fee_mint"] } else { &[] };
+            assert_matches_specs(
+                &super::set_fee_mint(&program_id, &key, passed, 1, &key).unwrap(),
+                Passed { present, ..none() },
+            );
+            let present: &[&str] = if passed.is_some() { &["settlement_program"] } else { &[] };
+            assert_matches_specs(
+                &super::set_settlement_program(&program_id, &key, passed).unwrap(),
+                Passed { present, ..none() },
+            );
+        }
+        for basket in [&[][..], &two_assets[..]] {
+            assert_matches_specs(
+                &super::reclaim_basket(&program_id, &key, &key, &key, basket).unwrap(),
+                Passed {
+                    basket_assets: basket.len(),
+                    ..none()
+                },
+            );
+        }
+        assert_matches_specs(
+            &super::precreate_escrows(&program_id, &key, 0, MAX_PRECREATED_ESCROWS).unwrap(),
+            Passed {
+                repeated: MAX_PRECREATED_ESCROWS as usize,
+                ..none()
+            },
+        );
+
+        let leg = || RingLeg {
+            escrow_account: key,
+            vault: key,
+            initializer: key,
+            initializer_receive_token_account: key,
+            config: key,
+            fee_token_account: key,
+            deposit_mint: key,
+        };
+        let fee_tiers = [FeeTier {
+            min_age_slots: 1,
+            fee_bps: 1,
+        }];
+        for instruction in [
+            super::reset_time_lock(&program_id, &key, &key, 1).unwrap(),
+            super::init_stats(&program_id, &key).unwrap(),
+            super::init_config(&program_id, &key, &key, 1, PaymentAmount(1), PaymentAmount(2), &fee_tiers, 1)
+                .unwrap(),
+            super::update_config(&program_id, &key, &key, 1, PaymentAmount(1), PaymentAmount(2), &fee_tiers, 1)
+                .unwrap(),
+            super::init_audit_log(&program_id, &key).unwrap(),
+            super::time_status(&program_id, &key).unwrap(),
+            super::view(&program_id, EscrowInstruction::GetEscrow, &key).unwrap(),
+            super::view(&program_id, EscrowInstruction::GetConfig, &key).unwrap(),
+            super::view(&program_id, EscrowInstruction::GetPairStats, &key).unwrap(),
+            super::update_deployment(&program_id, &key, 1, 2, 3, [0; 32]).unwrap(),
+            super::mint_receipt(&program_id, &key, &key, &key, &key).unwrap(),
+            super::claim_proceeds(&program_id, &key, &key, &key, &key, &key).unwrap(),
+            super::update_fee_rate(&program_id, &key, &key, 1).unwrap(),
+            super::set_gated_roles(&program_id, &key, 1).unwrap(),
+            super::set_allowlist_entry(&program_id, &key, &key, 1).unwrap(),
+            super::build_info(&program_id).unwrap(),
+            super::init_vault(&program_id, &key, &key, &key).unwrap(),
+            super::set_volume_cap(&program_id, &key, 1, 1).unwrap(),
+            super::init_pair_stats(&program_id, &key, &key, &key, &key).unwrap(),
+            super::set_paused(&program_id, &key, true).unwrap(),
+            super::set_unlock_bounds(&program_id, &key, 1, 2).unwrap(),
+            super::add_allowed_taker(&program_id, &key, &key, &key).unwrap(),
+            super::remove_allowed_taker(&program_id, &key, &key, &key).unwrap(),
+            super::make_counter_offer(&program_id, &key, &key, PaymentAmount(1)).unwrap(),
+            super::accept_counter_offer(&program_id, &key, &key, &key, &key).unwrap(),
+            super::update_expected_amount(&program_id, &key, &key, &key, PaymentAmount(1)).unwrap(),
+            super::set_escrow_tags(&program_id, &key, &key, vec![EscrowTag::new(b"k", b"v").unwrap()])
+                .unwrap(),
+            super::wrap_sol(&program_id, &key, &key, &key, 1).unwrap(),
+            super::init_insurance_pool(&program_id, &key, 1).unwrap(),
+            super::insure_escrow(&program_id, &key, &key, &key).unwrap(),
+            super::pay_claim(&program_id, &key, &key, &key, &key, 1).unwrap(),
+            super::add_basket_asset(&program_id, &key, &key, &key, &key, &key, DepositAmount(1)).unwrap(),
+            super::multi_exchange(&program_id, &key, &key, &[leg(), leg(), leg()]).unwrap(),
+            super::deposit_initializer(&program_id, &key, &key, &key, &key, &key, &key).unwrap(),
+            super::deposit_taker(&program_id, &key, &key, &key, &key, &key, &key).unwrap(),
+            super::settle(&program_id, &key, &key, &key, &key, &key, &key, &key, &key, &key, &key, &key)
+                .unwrap(),
+            super::set_init_rate_limit(&program_id, &key, 1).unwrap(),
+            super::init_rate_counter(&program_id, &key, &key).unwrap(),
+            super::set_permit_eth_signer(&program_id, &key, &key, [1; 20]).unwrap(),
+        ] {
+            assert_matches_specs(&instruction, none());
+        }
+    }
+
+    /// An InitEscrow of 1000 with no payouts and only the fields in `fields`, followed by `bytes`
+    fn init_escrow_data(fields: u16, bytes: &[u8]) -> Vec<u8> {
+        [&[0][..], &1_000u64.to_le_bytes(), &[0], &fields.to_le_bytes(), bytes].concat()
+    }
+
+    #[test]
+    fn init_escrow_fields_are_decoded_by_their_bits() {
+        let key = Pubkey::new_from_array([7; 32]);
+        // the cooldown alone, which a positional tail would have read as the unlock slots
+        let input = init_escrow_data(InitEscrowFields::FILL_COOLDOWN_SLOTS, &20u64.to_le_bytes());
+        match EscrowInstruction::unpack(&input).unwrap() {
+            EscrowInstruction::InitEscrow {
+                unlock_slots,
+                fill_cooldown_slots,
+                ..
+            } => assert_eq!((unlock_slots, fill_cooldown_slots), (DEFAULT_UNLOCK_SLOTS, 20)),
+            _ => panic!("not an InitEscrow"),
+        }
+        assert_eq!(EscrowInstruction::unpack(&input).unwrap().pack(), input);
+        // the partial fill bit has no bytes, and a taker gate follows it directly
+        let gate = [&5u64.to_le_bytes()[..], key.as_ref()].concat();
+        let input = init_escrow_data(
+            InitEscrowFields::PARTIAL_FILL | InitEscrowFields::TAKER_GATE,
+            &gate,
+        );
+        match EscrowInstruction::unpack(&input).unwrap() {
+            EscrowInstruction::InitEscrow {
+                partial_fill,
+                taker_gate,
+                settlement_program,
+                ..
+            } => {
+                assert!(partial_fill);
+                assert_eq!((taker_gate.min_lamports, taker_gate.badge_mint), (5, key));
+                assert_eq!(settlement_program, None);
+            }
+            _ => panic!("not an InitEscrow"),
+        }
+        assert_eq!(EscrowInstruction::unpack(&input).unwrap().pack(), input);
+    }
+
+    #[test]
+    fn init_escrow_rejects_unknown_bits_and_empty_options() {
+        assert!(EscrowInstruction::unpack(&init_escrow_data(1 << 15, &[])).is_err());
+        // a set bit with its field missing
+        assert!(EscrowInstruction::unpack(&init_escrow_data(InitEscrowFields::UNLOCK_SLOTS, &[])).is_err());
+        // an option's bit stands for Some, so it can't carry the zero value
+        for field in [
+            InitEscrowFields::TAKER_COMMITMENT,
+            InitEscrowFields::SETTLEMENT_PROGRAM,
+            InitEscrowFields::DESIGNATED_TAKER,
+        ] {
+            assert!(EscrowInstruction::unpack(&init_escrow_data(field, &[0; 32])).is_err());
+            assert!(EscrowInstruction::unpack(&init_escrow_data(field, &[1; 32])).is_ok());
+        }
+    }
+
+    #[test]
+    fn exchange_reads_an_all_zero_salt_as_none_at_every_length() {
+        for len in [40, 48, 56, 64] {
+            let mut input = vec![1];
+            input.extend_from_slice(&10u64.to_le_bytes());
+            input.resize(1 + len, 0);
+            match EscrowInstruction::unpack(&input).unwrap() {
+                EscrowInstruction::Exchange { taker_salt, .. } => {
+                    assert_eq!(taker_salt, None, "length {}", len)
+                }
+                _ => panic!("not an Exchange"),
+            }
+        }
+    }
+
+    #[test]
+    fn full_init_escrow_is_the_longest_encoding() {
+        let init_escrow = &full_variable_encodings()[0];
+        assert_eq!(init_escrow.len(), MAX_INSTRUCTION_DATA_LEN);
+        assert!(EscrowInstruction::unpack(&vec![0; MAX_INSTRUCTION_DATA_LEN + 1]).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! The program's hard invariant: outside of an Exchange's settlement, it never holds both legs
+//! of a trade for an escrow, the deposit in its vault and the payment for it. Mutual-deposit
+//! escrows are the one exception by design, holding both from DepositTaker until Settle.
+//! These checks panic when a fill breaks it, and only run in a `paranoid` build, the way
+//! `debug_assert!` only runs in a debug one. Run the scenarios against a paranoid build to
+//! catch a feature that lets the two legs overlap.
+
+use solana_program::{account_info::AccountInfo, program_pack::Pack, pubkey::Pubkey};
+
+use crate::{
+    primitives::unpack_token_account,
+    state::{DepositAmount, Escrow},
+};
+
+/// `assert!` in a `paranoid` build, nothing in any other
+macro_rules! paranoid_assert {
+    ($($arg:tt)+) => {
+        if cfg!(feature = "paranoid") {
+            assert!($($arg)+);
+        }
+    };
+}
+
+fn is_closed(account: &AccountInfo) -> bool {
+    account.lamports() == 0 || account.data_is_empty()
+}
+
+/// Checks a fill released exactly `fill` out of a vault that held `vault_amount_before`, and
+/// left the escrow holding at most its deposit: closed once the deposit is gone, or with
+/// the rest of a partially filled deposit and none of the payment, which only a receipt's
+/// proceeds account would keep
+pub fn assert_one_leg_after_fill(
+    escrow_account: &AccountInfo,
+    vault: &AccountInfo,
+    token_program: &Pubkey,
+    vault_amount_before: u64,
+    fill: DepositAmount,
+) {
+    if !cfg!(feature = "paranoid") {
+        return;
+    }
+    let vault_amount = if is_closed(vault) {
+        0
+    } else {
+        unpack_token_account(vault, token_program)
+            .expect("the vault of a filled escrow isn't a token account")
+            .amount
+    };
+    paranoid_assert!(
+        vault_amount_before.checked_sub(fill.0) == Some(vault_amount),
+        "the vault released {} instead of the fill's {}",
+        vault_amount_before.saturating_sub(vault_amount),
+        fill.0,
+    );
+    if is_closed(escrow_account) {
+        return;
+    }
+    let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data().unwrap())
+        .expect("a partially filled escrow doesn't unpack");
+    paranoid_assert!(
+        !escrow_info.flags.has_receipt(),
+        "a partially filled escrow holds a receipt's proceeds along with its deposit",
+    );
+    paranoid_assert!(
+        escrow_info.deposit(vault_amount).is_ok_and(|deposit| deposit.0 > 0),
+        "a partially filled escrow's vault doesn't cover the rest of its deposit",
+    );
+}
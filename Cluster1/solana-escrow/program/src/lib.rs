@@ -1,7 +1,20 @@
+// account lists number entries like `7+N.`, which markdown doesn't take for list items
+#![allow(clippy::doc_lazy_continuation)]
+
+pub mod account_specs;
+pub mod build_info;
 pub mod error;
+pub mod inspect;
 pub mod instruction;
-pub mod processor;
+pub mod primitives;
 pub mod state;
 
-#[cfg(not(feature = "no-entrypoint"))]
+// what only runs in the program, left out of wasm32 builds of the instruction builders and
+// state layouts; native builds keep it for the tests, which run the processor in-process
+#[cfg(not(target_arch = "wasm32"))]
+pub mod invariants;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod processor;
+
+#[cfg(all(not(feature = "no-entrypoint"), not(target_arch = "wasm32")))]
 pub mod entrypoint;
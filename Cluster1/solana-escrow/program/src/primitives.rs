@@ -0,0 +1,1012 @@
+use solana_program::{
+    account_info::AccountInfo,
+    ed25519_program,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    secp256k1_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+use spl_token_2022::extension::{
+    confidential_transfer::ConfidentialTransferAccount, default_account_state::DefaultAccountState,
+    interest_bearing_mint::InterestBearingConfig, memo_transfer::memo_required, transfer_hook,
+    BaseStateWithExtensions, ExtensionType,
+    StateWithExtensions,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use solana_program::program::{invoke, invoke_signed};
+#[cfg(not(target_arch = "wasm32"))]
+use spl_transfer_hook_interface::onchain::add_extra_accounts_for_execute_cpi;
+
+use crate::{
+    error::{AccountCursor, EscrowError},
+    state::{interest_scale, Escrow},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::state::DepositAmount;
+
+/// Whether escrows can hold tokens of `program_id`: the spl-token program or Token-2022. An
+/// escrow's tokens all live under the one token program passed to its instructions.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == spl_token::id() || *program_id == spl_token_2022::id()
+}
+
+/// The mint `token_program` wraps native SOL in
+pub fn native_mint(token_program: &Pubkey) -> Pubkey {
+    if *token_program == spl_token_2022::id() {
+        spl_token_2022::native_mint::id()
+    } else {
+        spl_token::native_mint::id()
+    }
+}
+
+/// Unpacks a token account of `token_program`. A Token-2022 account's extensions must parse,
+/// but only `assert_transferable` looks at them. Fails with `IncorrectProgramId` if the account
+/// belongs to another program.
+pub fn unpack_token_account(account: &AccountInfo, token_program: &Pubkey) -> Result<TokenAccount, ProgramError> {
+    if account.owner != token_program || !is_token_program(token_program) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = account.try_borrow_data()?;
+    if *token_program == spl_token_2022::id() {
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+    } else if data.len() != TokenAccount::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    TokenAccount::unpack(&data[..TokenAccount::LEN])
+}
+
+/// Unpacks a mint of `token_program`. A Token-2022 mint's extensions must parse, but only
+/// `assert_supported_mint` looks at them. Fails with `IncorrectProgramId` if the account
+/// belongs to another program.
+pub fn unpack_mint(account: &AccountInfo, token_program: &Pubkey) -> Result<Mint, ProgramError> {
+    if account.owner != token_program || !is_token_program(token_program) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = account.try_borrow_data()?;
+    if *token_program == spl_token_2022::id() {
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    } else if data.len() != Mint::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Mint::unpack(&data[..Mint::LEN])
+}
+
+/// Checks tokens can move in and out of `account` with the plain transfers escrows make.
+/// Fails with `UnsupportedTokenExtension` if the account is frozen, or if it belongs to a
+/// non-transferable Token-2022 mint. It also fails if the account only takes confidential
+/// transfers or requires a memo on incoming transfers.
+pub fn assert_transferable(account: &AccountInfo, token_program: &Pubkey) -> ProgramResult {
+    if unpack_token_account(account, token_program)?.is_frozen() {
+        return Err(EscrowError::UnsupportedTokenExtension.into());
+    }
+    if *token_program != spl_token_2022::id() {
+        return Ok(());
+    }
+    let data = account.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+    let extension_types = state.get_extension_types()?;
+    if extension_types.contains(&ExtensionType::NonTransferableAccount) || memo_required(&state)
+    {
+        return Err(EscrowError::UnsupportedTokenExtension.into());
+    }
+    if let Ok(confidential) = state.get_extension::<ConfidentialTransferAccount>() {
+        if !bool::from(confidential.allow_non_confidential_credits) {
+            return Err(EscrowError::UnsupportedTokenExtension.into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks an escrow can hold and transfer tokens of `mint`. Fails with
+/// `UnsupportedTokenExtension` if it's a Token-2022 mint that is non-transferable or that
+/// freezes new accounts by default. A transfer hook is fine, see `invoke_transfer_checked`.
+pub fn assert_supported_mint(mint: &AccountInfo, token_program: &Pubkey) -> ProgramResult {
+    unpack_mint(mint, token_program)?;
+    if *token_program != spl_token_2022::id() {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let extension_types = state.get_extension_types()?;
+    if extension_types.contains(&ExtensionType::NonTransferable) {
+        return Err(EscrowError::UnsupportedTokenExtension.into());
+    }
+    if let Ok(default_account_state) = state.get_extension::<DefaultAccountState>() {
+        if default_account_state.state == spl_token_2022::state::AccountState::Frozen as u8 {
+            return Err(EscrowError::UnsupportedTokenExtension.into());
+        }
+    }
+    Ok(())
+}
+
+/// A `Transfer` instruction for `token_program`. Token-2022 deprecates it for
+/// `TransferChecked`, but still takes it for mints without transfer fees or hooks.
+/// Exchange and Cancel use `token_transfer_checked` instead.
+#[allow(deprecated)]
+pub fn token_transfer(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::instruction::transfer(token_program, source, destination, authority, &[], amount)
+}
+
+/// A `TransferChecked` instruction for `token_program`, which the token program fails unless
+/// `mint` is the source's mint and has `decimals`
+pub fn token_transfer_checked(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::instruction::transfer_checked(
+        token_program,
+        source,
+        mint,
+        destination,
+        authority,
+        &[],
+        amount,
+        decimals,
+    )
+}
+
+/// The program `mint`'s Token-2022 transfer hook calls, if it has one
+pub fn transfer_hook_program(
+    mint: &AccountInfo,
+    token_program: &Pubkey,
+) -> Result<Option<Pubkey>, ProgramError> {
+    unpack_mint(mint, token_program)?;
+    if *token_program != spl_token_2022::id() {
+        return Ok(None);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    Ok(transfer_hook::get_program_id(&state))
+}
+
+/// Transfers `amount` with `TransferChecked`, signed by `authority` or, for a PDA, with
+/// `signer_seeds`. When `mint` has a transfer hook, the hook program, its
+/// `ExtraAccountMetaList` and every account the list resolves to are appended for the token
+/// program to call the hook with. They're found by address among `hook_accounts`, the
+/// instruction's remaining accounts, where the list says they must be; any missing there fails
+/// the transfer with `IncorrectAccount`.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn invoke_transfer_checked<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    hook_accounts: &[AccountInfo<'a>],
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let mut transfer_ix = token_transfer_checked(
+        token_program.key,
+        source.key,
+        mint.key,
+        destination.key,
+        authority.key,
+        amount,
+        decimals,
+    )?;
+    let mut transfer_accounts = vec![
+        source.clone(),
+        mint.clone(),
+        destination.clone(),
+        authority.clone(),
+        token_program.clone(),
+    ];
+    if let Some(hook_program) = transfer_hook_program(mint, token_program.key)? {
+        add_extra_accounts_for_execute_cpi(
+            &mut transfer_ix,
+            &mut transfer_accounts,
+            &hook_program,
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            amount,
+            hook_accounts,
+        )?;
+    }
+    invoke_signed(&transfer_ix, &transfer_accounts, signer_seeds)
+}
+
+/// Decimals of the mint account passed for `expected_mint`, to transfer it with
+/// `token_transfer_checked`
+pub fn mint_decimals(
+    mint: &AccountInfo,
+    expected_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<u8, ProgramError> {
+    if mint.key != expected_mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(unpack_mint(mint, token_program)?.decimals)
+}
+
+/// Factor `mint`'s UI amounts have grown by over its raw amounts at `unix_timestamp`, see
+/// `state::interest_scale`. 1 for any mint without Token-2022's interest-bearing extension.
+pub fn mint_interest_scale(
+    mint: &AccountInfo,
+    token_program: &Pubkey,
+    unix_timestamp: i64,
+) -> Result<f64, ProgramError> {
+    unpack_mint(mint, token_program)?;
+    if *token_program != spl_token_2022::id() {
+        return Ok(1.);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let config = match state.get_extension::<InterestBearingConfig>() {
+        Ok(config) => config,
+        Err(_) => return Ok(1.),
+    };
+    let last_update_timestamp = i64::from(config.last_update_timestamp);
+    let pre_update_seconds = last_update_timestamp
+        .checked_sub(config.initialization_timestamp.into())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let current_seconds = unix_timestamp
+        .checked_sub(last_update_timestamp)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    Ok(interest_scale(
+        config.pre_update_average_rate.into(),
+        pre_update_seconds,
+        config.current_rate.into(),
+        current_seconds,
+    ))
+}
+
+/// Address of the PDA for `seeds`, which end with the bump recorded when the PDA was first
+/// found. Hot paths use it instead of having `find_program_address` search for the bump again.
+pub fn pda_with_bump(program_id: &Pubkey, seeds: &[&[u8]]) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_program_address(seeds, program_id).map_err(|_| ProgramError::InvalidSeeds)
+}
+
+/// Checks `authority` is the PDA owning the escrow's vault, `[b"escrow", vault]` at the bump
+/// recorded at init. Nothing is required of the account's data or lamports, the PDA is never
+/// created.
+pub fn assert_vault_authority(
+    program_id: &Pubkey,
+    escrow_info: &Escrow,
+    authority: &AccountInfo,
+) -> ProgramResult {
+    let expected = pda_with_bump(
+        program_id,
+        &[
+            b"escrow",
+            escrow_info.temp_token_account_pubkey.as_ref(),
+            &[escrow_info.vault_authority_bump],
+        ],
+    )?;
+    if *authority.key != expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Moves `amount` out of a vault token account owned by its vault authority, signing with
+/// `bump`. Given the vault's mint account and its decimals, the transfer is checked against them
+/// and runs the mint's transfer hook with `hook_accounts`, see `invoke_transfer_checked`.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_from_vault<'a>(
+    token_program: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    mint: Option<(&AccountInfo<'a>, u8)>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    hook_accounts: &[AccountInfo<'a>],
+    bump: u8,
+    amount: DepositAmount,
+) -> ProgramResult {
+    let signer_seeds: &[&[u8]] = &[&b"escrow"[..], vault.key.as_ref(), &[bump]];
+    match mint {
+        Some((mint, decimals)) => invoke_transfer_checked(
+            token_program,
+            vault,
+            mint,
+            destination,
+            authority,
+            hook_accounts,
+            amount.0,
+            decimals,
+            &[signer_seeds],
+        ),
+        None => {
+            let transfer_ix = token_transfer(
+                token_program.key,
+                vault.key,
+                destination.key,
+                authority.key,
+                amount.0,
+            )?;
+            invoke_signed(
+                &transfer_ix,
+                &[
+                    vault.clone(),
+                    destination.clone(),
+                    authority.clone(),
+                    token_program.clone(),
+                ],
+                &[signer_seeds],
+            )
+        }
+    }
+}
+
+/// Closes an empty vault token account owned by its vault authority, sending its rent to
+/// `rent_destination`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn close_vault<'a>(
+    token_program: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    rent_destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    bump: u8,
+) -> ProgramResult {
+    let close_ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        vault.key,
+        rent_destination.key,
+        authority.key,
+        &[authority.key],
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            vault.clone(),
+            rent_destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        &[&[&b"escrow"[..], vault.key.as_ref(), &[bump]]],
+    )
+}
+
+/// Closes `owner`'s wSOL `account`, unwrapping its balance along with its rent into the owner's
+/// wallet. The owner must have signed the instruction.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn unwrap_native<'a>(
+    token_program: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+) -> ProgramResult {
+    let close_ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        account.key,
+        owner.key,
+        owner.key,
+        &[owner.key],
+    )?;
+    invoke(
+        &close_ix,
+        &[account.clone(), owner.clone(), owner.clone(), token_program.clone()],
+    )
+}
+
+/// Closes a receipt's empty proceeds account, owned by the receipt mint PDA of `escrow`,
+/// sending its rent to `rent_destination`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn close_proceeds_account<'a>(
+    token_program: &AccountInfo<'a>,
+    proceeds_account: &AccountInfo<'a>,
+    rent_destination: &AccountInfo<'a>,
+    receipt_mint: &AccountInfo<'a>,
+    escrow: &Pubkey,
+    receipt_bump: u8,
+) -> ProgramResult {
+    let close_ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        proceeds_account.key,
+        rent_destination.key,
+        receipt_mint.key,
+        &[receipt_mint.key],
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            proceeds_account.clone(),
+            rent_destination.clone(),
+            receipt_mint.clone(),
+            token_program.clone(),
+        ],
+        &[&[&b"receipt"[..], escrow.as_ref(), &[receipt_bump]]],
+    )
+}
+
+/// Closes an account owned by this program, sending its lamports to `rent_destination` and wiping its data
+pub fn close_state_account(account: &AccountInfo, rent_destination: &AccountInfo) -> ProgramResult {
+    **rent_destination.try_borrow_mut_lamports()? = rent_destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(EscrowError::AmountOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    *account.try_borrow_mut_data()? = &mut [];
+    Ok(())
+}
+
+/// Settling closes the escrow's state account, so a transaction that lost a race against the
+/// settling one finds it drained. Report that as `EscrowAlreadySettled` rather than letting the
+/// empty account fail to unpack.
+pub fn assert_escrow_not_settled(escrow: &AccountInfo) -> ProgramResult {
+    if escrow.lamports() == 0 || escrow.data_is_empty() {
+        return Err(EscrowError::EscrowAlreadySettled.into());
+    }
+    Ok(())
+}
+
+/// Checks the vault and initializer accounts passed to an instruction are the ones recorded
+/// in the escrow, reporting the position in `accounts` of whichever doesn't match.
+pub fn assert_escrow_matches<'a>(
+    escrow_info: &Escrow,
+    accounts: &AccountCursor<'_, 'a>,
+    vault: &AccountInfo<'a>,
+    initializer: &AccountInfo<'a>,
+) -> ProgramResult {
+    if escrow_info.temp_token_account_pubkey != *vault.key {
+        return Err(accounts.error(ProgramError::InvalidAccountData, vault));
+    }
+    if escrow_info.initializer_pubkey != *initializer.key {
+        return Err(accounts.error(ProgramError::InvalidAccountData, initializer));
+    }
+    Ok(())
+}
+
+/// Loads the instruction right before the current one and its index, where a permit's
+/// signature is verified
+pub fn load_previous_instruction(
+    instructions_sysvar: &AccountInfo,
+) -> Result<(u16, Instruction), ProgramError> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    let previous = current.checked_sub(1).ok_or(EscrowError::PermitInvalid)?;
+    Ok((previous, load_instruction_at_checked(previous as usize, instructions_sysvar)?))
+}
+
+/// Checks `ix` is an ed25519 program instruction verifying a single signature of `signer`
+/// over exactly `message`. The ed25519 program fails the transaction on a bad signature, so
+/// only what it was asked to verify is left to check.
+pub fn assert_ed25519_signed(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> ProgramResult {
+    if ix.program_id != ed25519_program::id() || ix.data.first() != Some(&1) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    // the signature count and a padding byte, then the offsets of the one signature
+    let offset = |field: usize| {
+        ix.data
+            .get(2 + field * 2..4 + field * 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .ok_or(EscrowError::PermitInvalid)
+    };
+    let (signature_ix, pubkey_offset, pubkey_ix) = (offset(1)?, offset(2)?, offset(3)?);
+    let (message_offset, message_size, message_ix) = (offset(4)?, offset(5)?, offset(6)?);
+    // pointing at another instruction's data could verify bytes this check never sees
+    if [signature_ix, pubkey_ix, message_ix].iter().any(|index| *index != u16::MAX) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    let pubkey = ix.data.get(pubkey_offset as usize..pubkey_offset as usize + 32);
+    let signed = ix
+        .data
+        .get(message_offset as usize..message_offset as usize + message_size as usize);
+    if pubkey != Some(signer.as_ref()) || signed != Some(message) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    Ok(())
+}
+
+/// Checks `ix`, at `ix_index` in the transaction, is a secp256k1 program instruction
+/// verifying a single signature of the Ethereum address `signer` over exactly `message`. As
+/// with ed25519, the program fails the transaction unless the signature recovers to the
+/// address it names.
+pub fn assert_secp256k1_signed(
+    ix: &Instruction,
+    ix_index: u16,
+    signer: &[u8; 20],
+    message: &[u8],
+) -> ProgramResult {
+    if ix.program_id != secp256k1_program::id() || ix.data.first() != Some(&1) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    // the signature count, then the offsets of the one signature, each with the index of
+    // the instruction its bytes are in
+    let offset = |at: usize| {
+        ix.data
+            .get(at..at + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or(EscrowError::PermitInvalid)
+    };
+    let index = |at: usize| ix.data.get(at).copied().ok_or(EscrowError::PermitInvalid);
+    let (address_offset, message_offset, message_size) = (offset(4)?, offset(7)?, offset(9)?);
+    // pointing at another instruction's data could verify bytes this check never sees
+    if [index(3)?, index(6)?, index(11)?].iter().any(|index| *index as u16 != ix_index) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    let address = ix.data.get(address_offset..address_offset + 20);
+    let signed = ix.data.get(message_offset..message_offset + message_size);
+    if *signer == [0; 20] || address != Some(signer.as_ref()) || signed != Some(message) {
+        return Err(EscrowError::PermitInvalid.into());
+    }
+    Ok(())
+}
+
+/// Checks the token program passed to an instruction is the one the escrow's vault belonged
+/// to at init, so a caller can't swap spl-token and Token-2022 for the escrow's CPIs
+pub fn assert_escrow_token_program(escrow_info: &Escrow, token_program: &AccountInfo) -> ProgramResult {
+    if escrow_info.token_program != *token_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program_option::COption;
+    use spl_token::state::AccountState;
+    use spl_token_2022::extension::{
+        memo_transfer::MemoTransfer, non_transferable::{NonTransferable, NonTransferableAccount},
+        transfer_hook::{TransferHook, TransferHookAccount}, BaseStateWithExtensionsMut,
+        StateWithExtensionsMut,
+    };
+
+    fn account_info<'a>(
+        key: &'a Pubkey,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+        owner: &'a Pubkey,
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, false, true, lamports, data, owner, false, 0)
+    }
+
+    fn token_account(state: AccountState) -> spl_token::state::Account {
+        spl_token::state::Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 10,
+            state,
+            ..Default::default()
+        }
+    }
+
+    fn mint() -> Mint {
+        Mint {
+            mint_authority: COption::Some(Pubkey::new_unique()),
+            supply: 10,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+    }
+
+    fn spl_token_mint() -> Vec<u8> {
+        let mut data = vec![0; Mint::LEN];
+        mint().pack_into_slice(&mut data);
+        data
+    }
+
+    fn spl_token_account(state: AccountState) -> Vec<u8> {
+        let mut data = vec![0; TokenAccount::LEN];
+        token_account(state).pack_into_slice(&mut data);
+        data
+    }
+
+    /// A Token-2022 account with `extensions`, which `init` fills in
+    fn token_2022_account(
+        extensions: &[ExtensionType],
+        init: impl FnOnce(&mut StateWithExtensionsMut<spl_token_2022::state::Account>),
+    ) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(extensions)
+            .unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data)
+                .unwrap();
+        // the base layouts are the same, Token-2022 only appends extensions
+        state.base =
+            spl_token_2022::state::Account::unpack_from_slice(&spl_token_account(AccountState::Initialized))
+                .unwrap();
+        state.pack_base();
+        state.init_account_type().unwrap();
+        init(&mut state);
+        data
+    }
+
+    /// A Token-2022 mint with `extensions`, which `init` fills in
+    fn token_2022_mint(
+        extensions: &[ExtensionType],
+        init: impl FnOnce(&mut StateWithExtensionsMut<spl_token_2022::state::Mint>),
+    ) -> Vec<u8> {
+        let len =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+                .unwrap();
+        state.base = spl_token_2022::state::Mint::unpack_from_slice(&spl_token_mint()).unwrap();
+        state.pack_base();
+        state.init_account_type().unwrap();
+        init(&mut state);
+        data
+    }
+
+    fn check_account(
+        check: fn(&AccountInfo, &Pubkey) -> ProgramResult,
+        mut data: Vec<u8>,
+        token_program: &Pubkey,
+    ) -> ProgramResult {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1;
+        check(&account_info(&key, &mut lamports, &mut data, token_program), token_program)
+    }
+
+    fn assert_transferable_data(data: Vec<u8>, token_program: &Pubkey) -> ProgramResult {
+        check_account(assert_transferable, data, token_program)
+    }
+
+    fn assert_supported_mint_data(data: Vec<u8>, token_program: &Pubkey) -> ProgramResult {
+        check_account(assert_supported_mint, data, token_program)
+    }
+
+    #[test]
+    fn unpack_token_account_checks_owner_and_layout() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1;
+        let mut data = spl_token_account(AccountState::Initialized);
+        let account = account_info(&key, &mut lamports, &mut data, &spl_token::ID);
+        assert_eq!(unpack_token_account(&account, &spl_token::ID).unwrap().amount, 10);
+        assert_eq!(
+            unpack_token_account(&account, &spl_token_2022::ID).unwrap_err(),
+            ProgramError::IncorrectProgramId
+        );
+
+        let system_program = solana_program::system_program::ID;
+        let mut data = spl_token_account(AccountState::Initialized);
+        let account = account_info(&key, &mut lamports, &mut data, &system_program);
+        assert_eq!(
+            unpack_token_account(&account, &system_program).unwrap_err(),
+            ProgramError::IncorrectProgramId
+        );
+
+        let mut data = spl_token_account(AccountState::Initialized);
+        data.push(0);
+        let account = account_info(&key, &mut lamports, &mut data, &spl_token::ID);
+        assert_eq!(
+            unpack_token_account(&account, &spl_token::ID).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        let mut data = token_2022_account(&[ExtensionType::TransferHookAccount], |state| {
+            state.init_extension::<TransferHookAccount>(true).unwrap();
+        });
+        let account = account_info(&key, &mut lamports, &mut data, &spl_token_2022::ID);
+        assert_eq!(unpack_token_account(&account, &spl_token_2022::ID).unwrap().amount, 10);
+    }
+
+    #[test]
+    fn assert_transferable_rejects_unsupported_accounts() {
+        let unsupported = Err(EscrowError::UnsupportedTokenExtension.into());
+        assert_eq!(
+            assert_transferable_data(spl_token_account(AccountState::Initialized), &spl_token::ID),
+            Ok(())
+        );
+        assert_eq!(
+            assert_transferable_data(spl_token_account(AccountState::Frozen), &spl_token::ID),
+            unsupported
+        );
+        assert_eq!(
+            assert_transferable_data(token_2022_account(&[], |_| {}), &spl_token_2022::ID),
+            Ok(())
+        );
+        // the hook runs with the accounts passed for it, see `invoke_transfer_checked`
+        assert_eq!(
+            assert_transferable_data(
+                token_2022_account(&[ExtensionType::TransferHookAccount], |state| {
+                    state.init_extension::<TransferHookAccount>(true).unwrap();
+                }),
+                &spl_token_2022::ID
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            assert_transferable_data(
+                token_2022_account(&[ExtensionType::NonTransferableAccount], |state| {
+                    state.init_extension::<NonTransferableAccount>(true).unwrap();
+                }),
+                &spl_token_2022::ID
+            ),
+            unsupported
+        );
+        for require_memos in [false, true] {
+            assert_eq!(
+                assert_transferable_data(
+                    token_2022_account(&[ExtensionType::MemoTransfer], |state| {
+                        state.init_extension::<MemoTransfer>(true).unwrap().require_incoming_transfer_memos =
+                            require_memos.into();
+                    }),
+                    &spl_token_2022::ID
+                ),
+                if require_memos { unsupported.clone() } else { Ok(()) }
+            );
+        }
+        for allow_credits in [false, true] {
+            assert_eq!(
+                assert_transferable_data(
+                    token_2022_account(&[ExtensionType::ConfidentialTransferAccount], |state| {
+                        state
+                            .init_extension::<ConfidentialTransferAccount>(true)
+                            .unwrap()
+                            .allow_non_confidential_credits = allow_credits.into();
+                    }),
+                    &spl_token_2022::ID
+                ),
+                if allow_credits { Ok(()) } else { unsupported.clone() }
+            );
+        }
+    }
+
+    #[test]
+    fn assert_supported_mint_rejects_unsupported_mints() {
+        let unsupported = Err(EscrowError::UnsupportedTokenExtension.into());
+        assert_eq!(assert_supported_mint_data(spl_token_mint(), &spl_token::ID), Ok(()));
+        let key = Pubkey::new_unique();
+        let (mut lamports, mut data) = (1, spl_token_mint());
+        let spl_token_mint = account_info(&key, &mut lamports, &mut data, &spl_token::ID);
+        assert_eq!(
+            assert_supported_mint(&spl_token_mint, &spl_token_2022::ID),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(
+            assert_supported_mint_data(token_2022_mint(&[], |_| {}), &spl_token_2022::ID),
+            Ok(())
+        );
+        assert_eq!(
+            assert_supported_mint_data(
+                token_2022_mint(&[ExtensionType::TransferHook], |state| {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }),
+                &spl_token_2022::ID
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            assert_supported_mint_data(
+                token_2022_mint(&[ExtensionType::NonTransferable], |state| {
+                    state.init_extension::<NonTransferable>(true).unwrap();
+                }),
+                &spl_token_2022::ID
+            ),
+            unsupported
+        );
+        for state in [AccountState::Initialized, AccountState::Frozen] {
+            assert_eq!(
+                assert_supported_mint_data(
+                    token_2022_mint(&[ExtensionType::DefaultAccountState], |mint| {
+                        mint.init_extension::<DefaultAccountState>(true).unwrap().state = state as u8;
+                    }),
+                    &spl_token_2022::ID
+                ),
+                if state == AccountState::Frozen { unsupported.clone() } else { Ok(()) }
+            );
+        }
+    }
+
+    #[test]
+    fn assert_escrow_matches_reports_the_mismatched_account() {
+        let mut escrow_info = Escrow::unpack_unchecked(&[0; Escrow::LEN]).unwrap();
+        let (vault_key, initializer_key, other_key) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        escrow_info.temp_token_account_pubkey = vault_key;
+        escrow_info.initializer_pubkey = initializer_key;
+        let (mut vault_lamports, mut initializer_lamports, mut other_lamports) = (1, 1, 1);
+        let (mut vault_data, mut initializer_data, mut other_data) = (vec![], vec![], vec![]);
+        let owner = Pubkey::new_unique();
+        let vault = account_info(&vault_key, &mut vault_lamports, &mut vault_data, &owner);
+        let initializer =
+            account_info(&initializer_key, &mut initializer_lamports, &mut initializer_data, &owner);
+        let other = account_info(&other_key, &mut other_lamports, &mut other_data, &owner);
+        let accounts = [vault, initializer, other];
+        let cursor = AccountCursor::new(&accounts);
+        let [vault, initializer, other] = &accounts;
+
+        assert_eq!(assert_escrow_matches(&escrow_info, &cursor, vault, initializer), Ok(()));
+        assert_eq!(
+            assert_escrow_matches(&escrow_info, &cursor, other, initializer),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            assert_escrow_matches(&escrow_info, &cursor, vault, other),
+            Err(ProgramError::InvalidAccountData)
+        );
+        // the initializer passed as the vault, and the vault as the initializer
+        assert_eq!(
+            assert_escrow_matches(&escrow_info, &cursor, initializer, vault),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn close_state_account_moves_lamports_and_wipes_data() {
+        let (key, destination_key, owner) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut destination_lamports) = (5, 7);
+        let (mut data, mut destination_data) = (vec![1; 16], vec![]);
+        let account = account_info(&key, &mut lamports, &mut data, &owner);
+        let destination =
+            account_info(&destination_key, &mut destination_lamports, &mut destination_data, &owner);
+        close_state_account(&account, &destination).unwrap();
+        assert_eq!((account.lamports(), destination.lamports()), (0, 12));
+        assert!(account.data_is_empty());
+        assert_eq!(assert_escrow_not_settled(&account), Err(EscrowError::EscrowAlreadySettled.into()));
+
+        **destination.try_borrow_mut_lamports().unwrap() = u64::MAX;
+        **account.try_borrow_mut_lamports().unwrap() = 1;
+        assert_eq!(
+            close_state_account(&account, &destination),
+            Err(EscrowError::AmountOverflow.into())
+        );
+    }
+
+    #[test]
+    fn assert_vault_authority_only_takes_the_canonical_pda() {
+        let (program_id, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (canonical, bump) = Pubkey::find_program_address(&[b"escrow", vault.as_ref()], &program_id);
+        let mut escrow_info = Escrow::unpack_unchecked(&[0; Escrow::LEN]).unwrap();
+        escrow_info.temp_token_account_pubkey = vault;
+        escrow_info.vault_authority_bump = bump;
+        let check = |key: &Pubkey| {
+            let (mut lamports, mut data) = (0, vec![]);
+            let authority = account_info(key, &mut lamports, &mut data, &program_id);
+            assert_vault_authority(&program_id, &escrow_info, &authority)
+        };
+        assert_eq!(check(&canonical), Ok(()));
+
+        // the same seeds at a lower bump still derive an address off the curve
+        let non_canonical = (0..bump)
+            .rev()
+            .find_map(|bump| {
+                Pubkey::create_program_address(&[b"escrow", vault.as_ref(), &[bump]], &program_id).ok()
+            })
+            .unwrap();
+        let other_vault = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        for related in [
+            non_canonical,
+            Pubkey::find_program_address(&[b"escrow", other_vault.as_ref()], &program_id).0,
+            Pubkey::find_program_address(&[b"vault", vault.as_ref()], &program_id).0,
+            Pubkey::find_program_address(&[b"escrow", vault.as_ref()], &other_program).0,
+            vault,
+        ] {
+            assert_eq!(check(&related), Err(ProgramError::InvalidSeeds));
+        }
+
+        // lamports or data on the canonical address don't matter, it is only ever a signer
+        let (mut lamports, mut data) = (1_000, vec![1; 8]);
+        let funded = account_info(&canonical, &mut lamports, &mut data, &other_program);
+        assert_eq!(assert_vault_authority(&program_id, &escrow_info, &funded), Ok(()));
+    }
+
+    /// An ed25519 program instruction verifying one signature of `signer` over `message`,
+    /// laid out as the ed25519 program expects with everything in its own data
+    fn ed25519_instruction(signer: &Pubkey, message: &[u8]) -> Instruction {
+        const HEADER_LEN: u16 = 16;
+        let (pubkey_offset, signature_offset) = (HEADER_LEN, HEADER_LEN + 32);
+        let message_offset = signature_offset + 64;
+        let mut data = vec![1, 0];
+        for field in [
+            signature_offset,
+            u16::MAX,
+            pubkey_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+    }
+
+    #[test]
+    fn assert_ed25519_signed_checks_what_was_verified() {
+        let (signer, message) = (Pubkey::new_unique(), b"permit".to_vec());
+        let ix = ed25519_instruction(&signer, &message);
+        let invalid = Err(EscrowError::PermitInvalid.into());
+        assert_eq!(assert_ed25519_signed(&ix, &signer, &message), Ok(()));
+        assert_eq!(assert_ed25519_signed(&ix, &Pubkey::new_unique(), &message), invalid);
+        assert_eq!(assert_ed25519_signed(&ix, &signer, b"permiT"), invalid);
+        assert_eq!(assert_ed25519_signed(&ix, &signer, b"permit and more"), invalid);
+
+        let mut other_program = ix.clone();
+        other_program.program_id = secp256k1_program::id();
+        assert_eq!(assert_ed25519_signed(&other_program, &signer, &message), invalid);
+
+        let mut two_signatures = ix.clone();
+        two_signatures.data[0] = 2;
+        assert_eq!(assert_ed25519_signed(&two_signatures, &signer, &message), invalid);
+
+        // each of the signature's, pubkey's and message's bytes in another instruction
+        for index_at in [4, 8, 14] {
+            let mut elsewhere = ix.clone();
+            elsewhere.data[index_at..index_at + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert_eq!(assert_ed25519_signed(&elsewhere, &signer, &message), invalid);
+        }
+
+        let mut truncated = ix.clone();
+        truncated.data.truncate(ix.data.len() - 1);
+        assert_eq!(assert_ed25519_signed(&truncated, &signer, &message), invalid);
+        truncated.data.truncate(10);
+        assert_eq!(assert_ed25519_signed(&truncated, &signer, &message), invalid);
+    }
+
+    /// A secp256k1 program instruction verifying one signature of the Ethereum address `signer`
+    /// over `message`, laid out as the secp256k1 program expects with everything in its own data
+    fn secp256k1_instruction(ix_index: u8, signer: &[u8; 20], message: &[u8]) -> Instruction {
+        const HEADER_LEN: u16 = 12;
+        let (address_offset, signature_offset) = (HEADER_LEN, HEADER_LEN + 20);
+        let message_offset = signature_offset + 65;
+        let mut data = vec![1];
+        data.extend_from_slice(&signature_offset.to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(&address_offset.to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(&message_offset.to_le_bytes());
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(signer);
+        data.extend_from_slice(&[0; 65]);
+        data.extend_from_slice(message);
+        Instruction::new_with_bytes(secp256k1_program::id(), &data, vec![])
+    }
+
+    #[test]
+    fn assert_secp256k1_signed_checks_what_was_verified() {
+        let (signer, message) = ([7; 20], b"permit".to_vec());
+        let ix = secp256k1_instruction(3, &signer, &message);
+        let invalid = Err(EscrowError::PermitInvalid.into());
+        assert_eq!(assert_secp256k1_signed(&ix, 3, &signer, &message), Ok(()));
+        assert_eq!(assert_secp256k1_signed(&ix, 3, &[8; 20], &message), invalid);
+        assert_eq!(assert_secp256k1_signed(&ix, 3, &signer, b"permiT"), invalid);
+        assert_eq!(assert_secp256k1_signed(&ix, 3, &signer, b"permit and more"), invalid);
+
+        // a zero address is what a client that never set a permit signer would pass
+        let unset = secp256k1_instruction(3, &[0; 20], &message);
+        assert_eq!(assert_secp256k1_signed(&unset, 3, &[0; 20], &message), invalid);
+
+        let mut other_program = ix.clone();
+        other_program.program_id = ed25519_program::id();
+        assert_eq!(assert_secp256k1_signed(&other_program, 3, &signer, &message), invalid);
+
+        let mut two_signatures = ix.clone();
+        two_signatures.data[0] = 2;
+        assert_eq!(assert_secp256k1_signed(&two_signatures, 3, &signer, &message), invalid);
+
+        // the instruction isn't where its offsets say its bytes are
+        assert_eq!(assert_secp256k1_signed(&ix, 2, &signer, &message), invalid);
+        for index_at in [3, 6, 11] {
+            let mut elsewhere = ix.clone();
+            elsewhere.data[index_at] = 0;
+            assert_eq!(assert_secp256k1_signed(&elsewhere, 3, &signer, &message), invalid);
+        }
+
+        let mut truncated = ix.clone();
+        truncated.data.truncate(ix.data.len() - 1);
+        assert_eq!(assert_secp256k1_signed(&truncated, 3, &signer, &message), invalid);
+        truncated.data.truncate(8);
+        assert_eq!(assert_secp256k1_signed(&truncated, 3, &signer, &message), invalid);
+    }
+}
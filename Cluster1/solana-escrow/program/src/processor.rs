@@ -3,20 +3,74 @@ use solana_program::{
     entrypoint::ProgramResult,
     program_error::ProgramError,
     msg,
+    log::sol_log_data,
     pubkey::Pubkey,
     program_pack::{Pack, IsInitialized},
-    sysvar::{rent::Rent, Sysvar, clock::Clock},
+    secp256k1_program,
+    sysvar::{self, rent::Rent, Sysvar, clock::Clock},
     program::invoke,
-    program::invoke_signed
+    program::invoke_signed,
+    program::set_return_data,
+    instruction::{AccountMeta, Instruction},
+    system_instruction,
+    bpf_loader_upgradeable,
 };
 
-use spl_token::state::Account;
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token::state::Mint;
 
-use crate::{instruction::EscrowInstruction, error::EscrowError, state::Escrow};
+use crate::{build_info::BuildInfo, instruction::EscrowInstruction, error::{AccountCursor, EscrowError}, invariants::assert_one_leg_after_fill, primitives::{assert_ed25519_signed, assert_secp256k1_signed, assert_supported_mint, assert_transferable, load_previous_instruction, assert_escrow_matches, assert_escrow_not_settled, assert_escrow_token_program, assert_vault_authority, close_proceeds_account, is_token_program, pda_with_bump, close_state_account, close_vault, mint_decimals, mint_interest_scale, native_mint, invoke_transfer_checked, token_transfer, transfer_from_vault, transfer_hook_program, unpack_mint, unpack_token_account, unwrap_native}, state::{taker_commitment, AllowlistEntry, AuditAction, AuditEntry, AuditLog, Basket, BasketAsset, Config, CounterOffer, DepositAmount, Deployment, Escrow, EscrowEvent, EscrowFeatures, EscrowFlags, EscrowStats, EscrowTag, EscrowTags, ExchangeQuote, FeeTier, FillEvent, HashLock, InsurancePool, MintRiskFlags, MutualDeposit, PairStats, PaymentAmount, Payout, OraclePrice, Permit, PriceBand, QuoteConvention, RateCounter, RoundingMode, Royalties, TakerAllowlist, TakerGate, TimeStatus, AUDIT_LOG_CAPACITY, CANCEL_EXPIRED_REWARD_LAMPORTS, MAX_ALLOWED_TAKERS, MAX_BASKET_ASSETS, MAX_ESCROW_TAGS, MAX_FEE_TIERS, MAX_INSURANCE_PREMIUM_BPS, MAX_PAYOUTS, MAX_PRECREATED_ESCROWS, MAX_TIME_LOCK_RESETS, MAX_UNLOCK_SLOTS, METADATA_PROGRAM_ID, MIN_RESET_INTERVAL_SLOTS, PYTH_PROGRAM_ID, MIN_UNLOCK_SLOTS, TOTAL_PAYOUT_BPS}};
 
 use spl_token::state::Account as TokenAccount;
 
-use crate::{error::EscrowError, instruction::EscrowInstruction, state::Escrow};
+/// One asset of a basket on its way out of its vault, with the accounts passed for it
+struct BasketLeg<'a, 'b> {
+    vault: &'b AccountInfo<'a>,
+    destination: &'b AccountInfo<'a>,
+    mint: &'b AccountInfo<'a>,
+    decimals: u8,
+    /// The vault's whole balance
+    amount: u64,
+}
+
+/// Escrows settled by MultiExchange, each initializer receiving the next one's deposit
+const RING_LEGS: usize = 3;
+/// Features MultiExchange can't settle, as they need a taker or an account it isn't passed,
+/// or split the payment
+const RING_UNSUPPORTED_FEATURES: u16 = EscrowFeatures::PAYOUTS
+    | EscrowFeatures::PRIVATE
+    | EscrowFeatures::DESIGNATED_TAKER
+    | EscrowFeatures::SETTLEMENT_HOOK
+    | EscrowFeatures::TAKER_ALLOWLIST
+    | EscrowFeatures::TAKER_GATE
+    | EscrowFeatures::INSURED
+    | EscrowFeatures::BASKET
+    | EscrowFeatures::MUTUAL_DEPOSIT
+    | EscrowFeatures::HASH_LOCK
+    | EscrowFeatures::GAS_REBATE
+    | EscrowFeatures::ORACLE_PRICE
+    | EscrowFeatures::INTEREST_ADJUSTED;
+
+/// One escrow of a MultiExchange ring, checked and with the accounts passed for it
+struct RingLeg<'a, 'b> {
+    escrow_account: &'b AccountInfo<'a>,
+    vault: &'b AccountInfo<'a>,
+    initializers_main_account: &'b AccountInfo<'a>,
+    initializers_token_to_receive_account: &'b AccountInfo<'a>,
+    pda_account: &'b AccountInfo<'a>,
+    fee_token_account: &'b AccountInfo<'a>,
+    deposit_mint: &'b AccountInfo<'a>,
+    deposit_decimals: u8,
+    vault_amount: u64,
+    deposit: DepositAmount,
+    /// What the initializer expects for the whole deposit
+    payment: PaymentAmount,
+    fee_bps: u16,
+    fee_recipient: Pubkey,
+    escrow_info: Escrow,
+}
 
 pub struct Processor;
 impl Processor {
@@ -28,99 +82,710 @@ impl Processor {
         let instruction = EscrowInstruction::unpack(instruction_data)?;
 
         match instruction {
-            EscrowInstruction::InitEscrow { amount } => {
+            EscrowInstruction::InitEscrow {
+                amount,
+                payout_bps,
+                rounding_mode,
+                taker_commitment,
+                unlock_slots,
+                vault_deposit,
+                settlement_program,
+                partial_fill,
+                taker_gate,
+                hash_lock,
+                gas_rebate,
+                price_band,
+                quote_convention,
+                fill_cooldown_slots,
+                has_refund_token_account,
+                has_deposit_mint,
+                designated_taker,
+            } => {
                 msg!("Instruction: InitEscrow");
-                Self::process_init_escrow(accounts, amount, program_id)
+                Self::process_init_escrow(
+                    accounts,
+                    amount,
+                    payout_bps,
+                    rounding_mode,
+                    taker_commitment,
+                    unlock_slots,
+                    vault_deposit,
+                    settlement_program,
+                    partial_fill,
+                    taker_gate,
+                    hash_lock,
+                    gas_rebate,
+                    price_band,
+                    quote_convention,
+                    fill_cooldown_slots,
+                    has_refund_token_account,
+                    has_deposit_mint,
+                    designated_taker,
+                    program_id,
+                )
             }
-            EscrowInstruction::Exchange { amount } => {
+            EscrowInstruction::Exchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            } => {
                 msg!("Instruction: Exchange");
-                Self::process_exchange(accounts, amount, program_id)
+                Self::process_exchange(
+                    accounts,
+                    amount,
+                    taker_salt,
+                    valid_until_slot,
+                    fill_amount,
+                    max_payment,
+                    false,
+                    program_id,
+                )
             }
-            EscrowInstruction::ResetTimeLock { } => {
+            EscrowInstruction::ResetTimeLock { unlock_slots } => {
                 msg!("Instruction: ResetTimeLock");
-                Self::process_reset_timelock(accounts, program_id)
+                Self::process_reset_timelock(accounts, unlock_slots, program_id)
             }
             EscrowInstruction::Cancel { } => {
                 msg!("Instruction: Cancel");
                 Self::process_cancel(accounts, program_id)
             }
+            EscrowInstruction::InitStats => {
+                msg!("Instruction: InitStats");
+                Self::process_init_stats(accounts, program_id)
+            }
+            EscrowInstruction::InitConfig {
+                fee_bps,
+                min_expected_amount,
+                max_expected_amount,
+                fee_tiers,
+                max_lifetime_slots,
+            } => {
+                msg!("Instruction: InitConfig");
+                Self::process_init_config(
+                    accounts,
+                    fee_bps,
+                    min_expected_amount,
+                    max_expected_amount,
+                    &fee_tiers,
+                    max_lifetime_slots,
+                    program_id,
+                )
+            }
+            EscrowInstruction::UpdateConfig {
+                fee_bps,
+                min_expected_amount,
+                max_expected_amount,
+                fee_tiers,
+                max_lifetime_slots,
+            } => {
+                msg!("Instruction: UpdateConfig");
+                Self::process_update_config(
+                    accounts,
+                    fee_bps,
+                    min_expected_amount,
+                    max_expected_amount,
+                    &fee_tiers,
+                    max_lifetime_slots,
+                    program_id,
+                )
+            }
+            EscrowInstruction::InitAuditLog => {
+                msg!("Instruction: InitAuditLog");
+                Self::process_init_audit_log(accounts, program_id)
+            }
+            EscrowInstruction::TimeStatus => {
+                msg!("Instruction: TimeStatus");
+                Self::process_time_status(accounts, program_id)
+            }
+            EscrowInstruction::UpdateDeployment {
+                major,
+                minor,
+                patch,
+                migration_notes_hash,
+            } => {
+                msg!("Instruction: UpdateDeployment");
+                Self::process_update_deployment(
+                    accounts,
+                    major,
+                    minor,
+                    patch,
+                    migration_notes_hash,
+                    program_id,
+                )
+            }
+            EscrowInstruction::ValidateExchange {
+                amount,
+                taker_salt,
+                valid_until_slot,
+                fill_amount,
+                max_payment,
+            } => {
+                msg!("Instruction: ValidateExchange");
+                Self::process_exchange(
+                    accounts,
+                    amount,
+                    taker_salt,
+                    valid_until_slot,
+                    fill_amount,
+                    max_payment,
+                    true,
+                    program_id,
+                )
+            }
+            EscrowInstruction::MintReceipt => {
+                msg!("Instruction: MintReceipt");
+                Self::process_mint_receipt(accounts, program_id)
+            }
+            EscrowInstruction::ClaimProceeds => {
+                msg!("Instruction: ClaimProceeds");
+                Self::process_claim_proceeds(accounts, program_id)
+            }
+            EscrowInstruction::Reap => {
+                msg!("Instruction: Reap");
+                Self::process_reap(accounts, AuditAction::Reap, program_id)
+            }
+            EscrowInstruction::SetFeeMint {
+                fee_mint_rate,
+                fee_rate_oracle,
+            } => {
+                msg!("Instruction: SetFeeMint");
+                Self::process_set_fee_mint(accounts, fee_mint_rate, fee_rate_oracle, program_id)
+            }
+            EscrowInstruction::UpdateFeeRate { fee_mint_rate } => {
+                msg!("Instruction: UpdateFeeRate");
+                Self::process_update_fee_rate(accounts, fee_mint_rate, program_id)
+            }
+            EscrowInstruction::RecoverClosedMint => {
+                msg!("Instruction: RecoverClosedMint");
+                Self::process_recover_closed_mint(accounts, program_id)
+            }
+            EscrowInstruction::SetGatedRoles { gated_roles } => {
+                msg!("Instruction: SetGatedRoles");
+                Self::process_set_gated_roles(accounts, gated_roles, program_id)
+            }
+            EscrowInstruction::SetAllowlistEntry { roles } => {
+                msg!("Instruction: SetAllowlistEntry");
+                Self::process_set_allowlist_entry(accounts, roles, program_id)
+            }
+            EscrowInstruction::BuildInfo => {
+                msg!("Instruction: BuildInfo");
+                set_return_data(&BuildInfo::current().pack());
+                Ok(())
+            }
+            EscrowInstruction::InitVault => {
+                msg!("Instruction: InitVault");
+                Self::process_init_vault(accounts, program_id)
+            }
+            EscrowInstruction::SetSettlementProgram => {
+                msg!("Instruction: SetSettlementProgram");
+                Self::process_set_settlement_program(accounts, program_id)
+            }
+            EscrowInstruction::SetVolumeCap {
+                volume_window_slots,
+                max_window_volume,
+            } => {
+                msg!("Instruction: SetVolumeCap");
+                Self::process_set_volume_cap(accounts, volume_window_slots, max_window_volume, program_id)
+            }
+            EscrowInstruction::InitPairStats => {
+                msg!("Instruction: InitPairStats");
+                Self::process_init_pair_stats(accounts, program_id)
+            }
+            EscrowInstruction::PrecreateEscrows { first_index, count } => {
+                msg!("Instruction: PrecreateEscrows");
+                Self::process_precreate_escrows(accounts, first_index, count, program_id)
+            }
+            EscrowInstruction::CancelExpired => {
+                msg!("Instruction: CancelExpired");
+                Self::process_reap(accounts, AuditAction::CancelExpired, program_id)
+            }
+            EscrowInstruction::ForceClose => {
+                msg!("Instruction: ForceClose");
+                Self::process_force_close(accounts, program_id)
+            }
+            EscrowInstruction::SetPaused { paused } => {
+                msg!("Instruction: SetPaused");
+                Self::process_set_paused(accounts, paused, program_id)
+            }
+            EscrowInstruction::SetUnlockBounds {
+                min_unlock_slots,
+                max_unlock_slots,
+            } => {
+                msg!("Instruction: SetUnlockBounds");
+                Self::process_set_unlock_bounds(accounts, min_unlock_slots, max_unlock_slots, program_id)
+            }
+            EscrowInstruction::AddAllowedTaker { taker } => {
+                msg!("Instruction: AddAllowedTaker");
+                Self::process_add_allowed_taker(accounts, &taker, program_id)
+            }
+            EscrowInstruction::RemoveAllowedTaker { taker } => {
+                msg!("Instruction: RemoveAllowedTaker");
+                Self::process_remove_allowed_taker(accounts, &taker, program_id)
+            }
+            EscrowInstruction::MakeCounterOffer { expected_amount } => {
+                msg!("Instruction: MakeCounterOffer");
+                Self::process_make_counter_offer(accounts, expected_amount, program_id)
+            }
+            EscrowInstruction::AcceptCounterOffer => {
+                msg!("Instruction: AcceptCounterOffer");
+                Self::process_accept_counter_offer(accounts, program_id)
+            }
+            EscrowInstruction::UpdateExpectedAmount { expected_amount } => {
+                msg!("Instruction: UpdateExpectedAmount");
+                Self::process_update_expected_amount(accounts, expected_amount, program_id)
+            }
+            EscrowInstruction::SetEscrowTags { tags } => {
+                msg!("Instruction: SetEscrowTags");
+                Self::process_set_escrow_tags(accounts, tags, program_id)
+            }
+            EscrowInstruction::WrapSol { lamports } => {
+                msg!("Instruction: WrapSol");
+                Self::process_wrap_sol(accounts, lamports)
+            }
+            EscrowInstruction::InitInsurancePool { premium_bps } => {
+                msg!("Instruction: InitInsurancePool");
+                Self::process_init_insurance_pool(accounts, premium_bps, program_id)
+            }
+            EscrowInstruction::InsureEscrow => {
+                msg!("Instruction: InsureEscrow");
+                Self::process_insure_escrow(accounts, program_id)
+            }
+            EscrowInstruction::PayClaim { amount } => {
+                msg!("Instruction: PayClaim");
+                Self::process_pay_claim(accounts, amount, program_id)
+            }
+            EscrowInstruction::AddBasketAsset { amount } => {
+                msg!("Instruction: AddBasketAsset");
+                Self::process_add_basket_asset(accounts, amount, program_id)
+            }
+            EscrowInstruction::ReclaimBasket => {
+                msg!("Instruction: ReclaimBasket");
+                Self::process_reclaim_basket(accounts, program_id)
+            }
+            EscrowInstruction::MultiExchange => {
+                msg!("Instruction: MultiExchange");
+                Self::process_multi_exchange(accounts, program_id)
+            }
+            EscrowInstruction::DepositInitializer { taker } => {
+                msg!("Instruction: DepositInitializer");
+                Self::process_deposit_initializer(accounts, taker, program_id)
+            }
+            EscrowInstruction::DepositTaker => {
+                msg!("Instruction: DepositTaker");
+                Self::process_deposit_taker(accounts, program_id)
+            }
+            EscrowInstruction::Settle => {
+                msg!("Instruction: Settle");
+                Self::process_settle(accounts, program_id)
+            }
+            EscrowInstruction::Redeem { preimage } => {
+                msg!("Instruction: Redeem");
+                Self::process_redeem(accounts, &preimage, program_id)
+            }
+            EscrowInstruction::ExchangeWithPermit {
+                amount,
+                payment,
+                valid_until_slot,
+            } => {
+                msg!("Instruction: ExchangeWithPermit");
+                Self::process_exchange_with_permit(
+                    accounts,
+                    amount,
+                    payment,
+                    valid_until_slot,
+                    program_id,
+                )
+            }
+            EscrowInstruction::SetInitRateLimit { max_inits_per_slot } => {
+                msg!("Instruction: SetInitRateLimit");
+                Self::process_set_init_rate_limit(accounts, max_inits_per_slot, program_id)
+            }
+            EscrowInstruction::InitRateCounter => {
+                msg!("Instruction: InitRateCounter");
+                Self::process_init_rate_counter(accounts, program_id)
+            }
+            EscrowInstruction::SetPermitEthSigner { eth_address } => {
+                msg!("Instruction: SetPermitEthSigner");
+                Self::process_set_permit_eth_signer(accounts, eth_address, program_id)
+            }
+            EscrowInstruction::GetEscrow => {
+                msg!("Instruction: GetEscrow");
+                Self::process_view::<Escrow>(accounts, program_id)
+            }
+            EscrowInstruction::GetConfig => {
+                msg!("Instruction: GetConfig");
+                Self::process_view::<Config>(accounts, program_id)
+            }
+            EscrowInstruction::GetPairStats => {
+                msg!("Instruction: GetPairStats");
+                Self::process_view::<PairStats>(accounts, program_id)
+            }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn process_init_escrow(
         accounts: &[AccountInfo],
-        amount: u64,
+        amount: PaymentAmount,
+        payout_bps: Vec<u16>,
+        rounding_mode: RoundingMode,
+        taker_commitment: Option<[u8; 32]>,
+        unlock_slots: u64,
+        vault_deposit: DepositAmount,
+        settlement_program: Option<Pubkey>,
+        partial_fill: bool,
+        taker_gate: TakerGate,
+        hash_lock: HashLock,
+        gas_rebate: u64,
+        price_band: PriceBand,
+        quote_convention: QuoteConvention,
+        fill_cooldown_slots: u64,
+        has_refund_token_account: bool,
+        has_deposit_mint: bool,
+        designated_taker: Option<Pubkey>,
         program_id: &Pubkey,
     ) -> ProgramResult {
-        let unlock_time = Clock::get()?.slot + 100;
-        let account_info_iter = &mut accounts.iter();
+        if !(MIN_UNLOCK_SLOTS..=MAX_UNLOCK_SLOTS).contains(&unlock_slots) {
+            return Err(EscrowError::UnlockSlotsOutOfBounds.into());
+        }
+        let account_info_iter = &mut AccountCursor::new(accounts);
         let initializer = next_account_info(account_info_iter)?;
 
         if !initializer.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
         }
 
+        // the token program comes later, checked to be the one both token accounts belong to
         let temp_token_account = next_account_info(account_info_iter)?;
+        let temp_token_account_info = unpack_token_account(temp_token_account, temp_token_account.owner)
+            .map_err(|e| account_info_iter.error(e, temp_token_account))?;
+        let deposit_mint = temp_token_account_info.mint;
+        assert_transferable(temp_token_account, temp_token_account.owner)
+            .map_err(|e| account_info_iter.error(e, temp_token_account))?;
+        // only InitVault can create the PDA, so it already belongs to its vault authority
+        if vault_deposit.0 != 0
+            && *temp_token_account.key
+                != Pubkey::find_program_address(
+                    &[b"vault", initializer.key.as_ref(), deposit_mint.as_ref()],
+                    program_id,
+                )
+                .0
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, temp_token_account));
+        }
 
         let token_to_receive_account = next_account_info(account_info_iter)?;
-        if *token_to_receive_account.owner != spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
-        }
+        let receive_mint = unpack_token_account(token_to_receive_account, temp_token_account.owner)
+            .map_err(|e| account_info_iter.error(e, token_to_receive_account))?
+            .mint;
+        assert_transferable(token_to_receive_account, temp_token_account.owner)
+            .map_err(|e| account_info_iter.error(e, token_to_receive_account))?;
 
         let escrow_account = next_account_info(account_info_iter)?;
-        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        let rent = Rent::get()?;
 
         if !rent.is_exempt(escrow_account.lamports(), escrow_account.data_len()) {
-            return Err(EscrowError::NotRentExempt.into());
+            return Err(account_info_iter.error(EscrowError::NotRentExempt, escrow_account));
+        }
+        if !rent.is_exempt(
+            escrow_account.lamports().saturating_sub(gas_rebate),
+            escrow_account.data_len(),
+        ) {
+            return Err(account_info_iter.error(EscrowError::GasRebateUnfunded, escrow_account));
         }
 
         let mut escrow_info = Escrow::unpack_unchecked(&escrow_account.try_borrow_data()?)?;
         if escrow_info.is_initialized() {
-            return Err(ProgramError::AccountAlreadyInitialized);
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                escrow_account,
+            ));
+        }
+        // a precreated account is reserved for the initializer who paid its rent
+        if escrow_info.initializer_pubkey != Pubkey::default()
+            && escrow_info.initializer_pubkey != *initializer.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, escrow_account));
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        if token_program.key != temp_token_account.owner {
+            return Err(account_info_iter.error(ProgramError::IncorrectProgramId, token_program));
+        }
+        let stats_account = next_account_info(account_info_iter)?;
+
+        let config_account = next_account_info(account_info_iter)?;
+        let config_info = Self::load_config(config_account, program_id)
+            .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.paused {
+            return Err(account_info_iter.error(EscrowError::ConfigPaused, config_account));
+        }
+        if !config_info.unlock_slots_bounds().contains(&unlock_slots) {
+            return Err(EscrowError::UnlockSlotsOutOfBounds.into());
+        }
+        if !config_info.allows_expected_amount(amount) {
+            return Err(EscrowError::AmountOutOfBounds.into());
+        }
+
+        if payout_bps.len() > MAX_PAYOUTS {
+            return Err(EscrowError::InvalidPayoutSplit.into());
+        }
+        if let Some(settlement_program) = settlement_program {
+            if !config_info.allows_settlement_into(&settlement_program) {
+                return Err(EscrowError::SettlementNotAllowed.into());
+            }
+        }
+        if !payout_bps.is_empty() {
+            if payout_bps.contains(&0)
+                || payout_bps.iter().map(|bps| *bps as u32).sum::<u32>() != TOTAL_PAYOUT_BPS as u32
+            {
+                return Err(EscrowError::InvalidPayoutSplit.into());
+            }
+            for (i, bps) in payout_bps.iter().enumerate() {
+                let payout_account = next_account_info(account_info_iter)?;
+                let payout_account_info = unpack_token_account(payout_account, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, payout_account))?;
+                if payout_account_info.mint != receive_mint {
+                    return Err(account_info_iter.error(
+                        EscrowError::InvalidPayoutSplit,
+                        payout_account,
+                    ));
+                }
+                assert_transferable(payout_account, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, payout_account))?;
+                escrow_info.payouts[i] = Payout {
+                    token_account_pubkey: *payout_account.key,
+                    bps: *bps,
+                };
+            }
         }
 
-        escrow_info.is_initialized = true;
+        escrow_info.flags.set(EscrowFlags::INITIALIZED, true);
         escrow_info.initializer_pubkey = *initializer.key;
         escrow_info.temp_token_account_pubkey = *temp_token_account.key;
         escrow_info.initializer_token_to_receive_account_pubkey = *token_to_receive_account.key;
         escrow_info.expected_amount = amount;
+        escrow_info.payout_count = payout_bps.len() as u8;
+        escrow_info.config_pubkey = *config_account.key;
+        escrow_info.expected_mint = receive_mint;
+        escrow_info.deposit_mint = deposit_mint;
+        escrow_info.token_program = *token_program.key;
+        escrow_info.init_slot = Clock::get()?.slot;
+        escrow_info.unlock_time = escrow_info.init_slot + unlock_slots;
+        escrow_info.rounding_mode = rounding_mode;
+        escrow_info.taker_commitment = taker_commitment.unwrap_or_default();
+        escrow_info.vault_deposit = vault_deposit;
+        escrow_info.settlement_program = settlement_program.unwrap_or_default();
+        escrow_info.taker_gate = taker_gate;
+        escrow_info.hash_lock = hash_lock;
+        escrow_info.gas_rebate = gas_rebate;
+        escrow_info.price_band = price_band;
+        escrow_info.quote_convention = quote_convention;
+        escrow_info.fill_cooldown_slots = fill_cooldown_slots;
+        escrow_info.designated_taker = designated_taker.unwrap_or_default();
+        if settlement_program.is_some() {
+            escrow_info.settlement_bump = Pubkey::find_program_address(&[b"settlement"], program_id).1;
+        }
+        let gated = config_info.gates(AllowlistEntry::INIT_ESCROW);
+        let mut features = escrow_info.implied_features();
+        if gated {
+            features |= EscrowFeatures::ALLOWLIST;
+        }
+        if partial_fill {
+            features |= EscrowFeatures::PARTIAL_FILL;
+        } else if fill_cooldown_slots != 0 {
+            // a whole fill closes the escrow, leaving nothing to cool down
+            return Err(EscrowError::UnsupportedFeatures.into());
+        }
+        escrow_info.features =
+            EscrowFeatures::from_bits(features).ok_or(EscrowError::UnsupportedFeatures)?;
+        // a shared vault already belongs to this authority, InitVault handed it over
+        let (vault_authority, vault_authority_bump) = Pubkey::find_program_address(
+            &[b"escrow", temp_token_account.key.as_ref()],
+            program_id,
+        );
+        escrow_info.vault_authority_bump = vault_authority_bump;
+        if config_info.max_lifetime_slots != 0 {
+            escrow_info.lifetime_expiry_slot =
+                escrow_info.init_slot.saturating_add(config_info.max_lifetime_slots);
+        }
+        escrow_info.flags.set(EscrowFlags::AUDITED, config_info.audit_log_enabled);
+
+        if config_info.audit_log_enabled {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                config_account.key,
+                program_id,
+                AuditAction::InitEscrow,
+                escrow_account.key,
+                initializer.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        if gated {
+            let allowlist_entry = next_account_info(account_info_iter)?;
+            Self::assert_allowlisted(
+                allowlist_entry,
+                config_account.key,
+                initializer.key,
+                AllowlistEntry::INIT_ESCROW,
+                program_id,
+            )
+            .map_err(|e| account_info_iter.error(e, allowlist_entry))?;
+        }
+
+        let vault_deposit_source = if escrow_info.shares_vault() {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+
+        if config_info.has_init_rate_limit() {
+            let rate_counter_account = next_account_info(account_info_iter)?;
+            let mut rate_counter_info =
+                Self::load_rate_counter(rate_counter_account, config_account.key, program_id)
+                    .map_err(|e| account_info_iter.error(e, rate_counter_account))?;
+            rate_counter_info
+                .record_init(escrow_info.init_slot, &config_info)
+                .map_err(|e| account_info_iter.error(e, rate_counter_account))?;
+            RateCounter::pack(rate_counter_info, &mut rate_counter_account.try_borrow_mut_data()?)?;
+        }
+
+        if has_refund_token_account {
+            let refund_account = next_account_info(account_info_iter)?;
+            let refund_account_info = unpack_token_account(refund_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, refund_account))?;
+            if refund_account_info.owner != *initializer.key || refund_account_info.mint != deposit_mint {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    refund_account,
+                ));
+            }
+            assert_transferable(refund_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, refund_account))?;
+            escrow_info.refund_token_account_pubkey = *refund_account.key;
+        }
+
+        escrow_info.deposit_mint_risk = if has_deposit_mint {
+            let mint_account = next_account_info(account_info_iter)?;
+            if *mint_account.key != deposit_mint || mint_account.owner != temp_token_account.owner {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, mint_account));
+            }
+            assert_supported_mint(mint_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, mint_account))?;
+            MintRiskFlags::assess(&mint_account.try_borrow_data()?)
+                .ok_or_else(|| {
+                    account_info_iter.error(ProgramError::InvalidAccountData, mint_account)
+                })?
+        } else {
+            MintRiskFlags::unassessed()
+        };
+        let deposit_mint_risk = escrow_info.deposit_mint_risk;
+        escrow_info.flags.set(EscrowFlags::RISK_FLAGGED, !deposit_mint_risk.is_empty());
 
         Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
-        let (pda, _nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
 
-        let token_program = next_account_info(account_info_iter)?;
-        let owner_change_ix = spl_token::instruction::set_authority(
-            token_program.key,
-            temp_token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            initializer.key,
-            &[&initializer.key],
-        )?;
+        if let Some(vault_deposit_source) = vault_deposit_source {
+            let deposit_ix = token_transfer(
+                token_program.key,
+                vault_deposit_source.key,
+                temp_token_account.key,
+                initializer.key,
+                vault_deposit.0,
+            )?;
+            msg!("Calling the token program to deposit into the shared vault...");
+            invoke(
+                &deposit_ix,
+                &[
+                    vault_deposit_source.clone(),
+                    temp_token_account.clone(),
+                    initializer.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            // every escrow in the vault is paid its recorded deposit, so the vault must
+            // have grown by exactly as much as this one recorded
+            let vault_amount = unpack_token_account(temp_token_account, token_program.key)?.amount;
+            if temp_token_account_info.amount.checked_add(vault_deposit.0) != Some(vault_amount) {
+                return Err(account_info_iter.error(
+                    EscrowError::VaultShortfall,
+                    vault_deposit_source,
+                ));
+            }
+        } else {
+            let owner_change_ix = spl_token_2022::instruction::set_authority(
+                token_program.key,
+                temp_token_account.key,
+                Some(&vault_authority),
+                spl_token_2022::instruction::AuthorityType::AccountOwner,
+                initializer.key,
+                &[initializer.key],
+            )?;
 
-        msg!("Calling the token program to transfer token account ownership...");
-        invoke(
-            &owner_change_ix,
-            &[
-                temp_token_account.clone(),
-                initializer.clone(),
-                token_program.clone(),
-            ],
+            msg!("Calling the token program to transfer token account ownership...");
+            invoke(
+                &owner_change_ix,
+                &[
+                    temp_token_account.clone(),
+                    initializer.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        // a shared vault's rent stays with the vault, it isn't locked up by any one escrow, and
+        // the gas rebate isn't rent at all
+        let escrow_rent = escrow_account.lamports() - gas_rebate;
+        let locked_rent = if vault_deposit.0 != 0 {
+            escrow_rent
+        } else {
+            escrow_rent
+                .checked_add(temp_token_account.lamports())
+                .ok_or(EscrowError::AmountOverflow)?
+        };
+        Self::record_escrow_rent(stats_account, program_id, true, locked_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::InitEscrow,
+            escrow_account.key,
+            initializer.key,
+            deposit_mint_risk,
         )?;
 
         Ok(())
     }
 
+    /// With `dry_run` set, runs every check up to the first transfer, then reports the
+    /// `ExchangeQuote` in return_data instead of moving tokens or touching any state.
+    #[allow(clippy::too_many_arguments)]
     fn process_exchange(
         accounts: &[AccountInfo],
-        amount_expected_by_taker: u64,
+        amount_expected_by_taker: DepositAmount,
+        taker_salt: Option<[u8; 32]>,
+        valid_until_slot: u64,
+        fill_amount: DepositAmount,
+        max_payment: PaymentAmount,
+        dry_run: bool,
         program_id: &Pubkey,
     ) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
+        // a fill that sat in a retry queue past the taker's deadline may be at a stale price
+        if valid_until_slot != 0 && Clock::get()?.slot > valid_until_slot {
+            return Err(EscrowError::FillDeadlinePassed.into());
+        }
+
+        let account_info_iter = &mut AccountCursor::new(accounts);
         let taker = next_account_info(account_info_iter)?;
 
         if !taker.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, taker));
         }
 
         let takers_sending_token_account = next_account_info(account_info_iter)?;
@@ -128,113 +793,772 @@ impl Processor {
         let takers_token_to_receive_account = next_account_info(account_info_iter)?;
 
         let pdas_temp_token_account = next_account_info(account_info_iter)?;
-        let pdas_temp_token_account_info =
-            TokenAccount::unpack(&pdas_temp_token_account.try_borrow_data()?)?;
-        let (pda, nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
-
-        if amount_expected_by_taker != pdas_temp_token_account_info.amount {
-            return Err(EscrowError::ExpectedAmountMismatch.into());
-        }
-
         let initializers_main_account = next_account_info(account_info_iter)?;
         let initializers_token_to_receive_account = next_account_info(account_info_iter)?;
         let escrow_account = next_account_info(account_info_iter)?;
+        // the vault is closed along with the escrow, so check before unpacking it
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
 
-        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        let mut escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        if escrow_info.is_hash_locked() {
+            return Err(account_info_iter.error(EscrowError::HashLocked, escrow_account));
+        }
+        // the token program comes later, checked to be the one recorded at init
+        let deposit_token_program = escrow_info.token_program;
+        let vault = unpack_token_account(pdas_temp_token_account, &deposit_token_program)
+            .map_err(|e| account_info_iter.error(e, pdas_temp_token_account))?;
+        let vault_amount = vault.amount;
+        // an open escrow's vault only runs dry if its mint was burned down and closed, and
+        // either way there is nothing left to fill
+        if vault_amount == 0 {
+            return Err(account_info_iter.error(EscrowError::MintClosed, pdas_temp_token_account));
+        }
 
-        if escrow_info.temp_token_account_pubkey != *pdas_temp_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
+        let deposit = escrow_info.deposit(vault_amount)
+            .map_err(|e| account_info_iter.error(e, pdas_temp_token_account))?;
+        if amount_expected_by_taker != deposit {
+            return Err(account_info_iter.error(
+                EscrowError::ExpectedAmountMismatch,
+                pdas_temp_token_account,
+            ));
         }
+        let fill = if fill_amount.0 == 0 { deposit } else { fill_amount };
+        // the fixed price, or an oracle-priced escrow's floor
+        let payment = escrow_info
+            .fill_payment(fill, deposit)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
 
-        if escrow_info.initializer_pubkey != *initializers_main_account.key {
-            return Err(ProgramError::InvalidAccountData);
+        assert_escrow_matches(
+            &escrow_info,
+            account_info_iter,
+            pdas_temp_token_account,
+            initializers_main_account,
+        )?;
+        if vault.mint != escrow_info.deposit_mint {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                pdas_temp_token_account,
+            ));
+        }
+        // the taker has to pay in the mint the initializer asked for, however the amounts line up
+        let takers_sending_info =
+            unpack_token_account(takers_sending_token_account, &deposit_token_program)
+                .map_err(|e| account_info_iter.error(e, takers_sending_token_account))?;
+        if takers_sending_info.mint != escrow_info.expected_mint {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                takers_sending_token_account,
+            ));
+        }
+        // any token account of the taker's for the deposited mint will do, not only their
+        // associated one, but never someone else's
+        let takers_receive_info =
+            unpack_token_account(takers_token_to_receive_account, &deposit_token_program)
+                .map_err(|e| account_info_iter.error(e, takers_token_to_receive_account))?;
+        if takers_receive_info.owner != *taker.key
+            || takers_receive_info.mint != escrow_info.deposit_mint
+        {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                takers_token_to_receive_account,
+            ));
+        }
+        assert_transferable(takers_token_to_receive_account, &deposit_token_program)
+            .map_err(|e| account_info_iter.error(e, takers_token_to_receive_account))?;
+        if escrow_info.is_past_lifetime(Clock::get()?.slot) {
+            return Err(account_info_iter.error(EscrowError::EscrowExpired, escrow_account));
+        }
+        if escrow_info.is_cooling_down(Clock::get()?.slot) {
+            return Err(account_info_iter.error(EscrowError::FillCoolingDown, escrow_account));
+        }
+        if escrow_info.is_private()
+            && taker_salt.map(|salt| taker_commitment(taker.key, &salt))
+                != Some(escrow_info.taker_commitment)
+        {
+            return Err(account_info_iter.error(EscrowError::TakerCommitmentMismatch, taker));
+        }
+        if escrow_info.has_designated_taker() && escrow_info.designated_taker != *taker.key {
+            return Err(account_info_iter.error(EscrowError::NotDesignatedTaker, taker));
         }
 
-        if escrow_info.initializer_token_to_receive_account_pubkey
+        if escrow_info.flags.has_receipt() {
+            // the receipt holder claims the payment from the proceeds account later
+            let receipt_mint = pda_with_bump(
+                program_id,
+                &[b"receipt", escrow_account.key.as_ref(), &[escrow_info.receipt_mint_bump]],
+            )
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+            if get_associated_token_address_with_program_id(
+                &receipt_mint,
+                &escrow_info.expected_mint,
+                &deposit_token_program,
+            ) != *initializers_token_to_receive_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializers_token_to_receive_account,
+                ));
+            }
+        } else if escrow_info.has_settlement_hook() {
+            // the settlement program takes the proceeds out of staging within this instruction
+            let settlement_pda =
+                pda_with_bump(program_id, &[b"settlement", &[escrow_info.settlement_bump]])
+                    .map_err(|e| account_info_iter.error(e, escrow_account))?;
+            if get_associated_token_address_with_program_id(
+                &settlement_pda,
+                &escrow_info.expected_mint,
+                &deposit_token_program,
+            ) != *initializers_token_to_receive_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializers_token_to_receive_account,
+                ));
+            }
+        } else if escrow_info.initializer_token_to_receive_account_pubkey
             != *initializers_token_to_receive_account.key
+            && get_associated_token_address_with_program_id(
+                &escrow_info.initializer_pubkey,
+                &escrow_info.expected_mint,
+                &deposit_token_program,
+            ) != *initializers_token_to_receive_account.key
         {
-            return Err(ProgramError::InvalidAccountData);
+            // a taker may substitute the initializer's ATA when the receive account recorded at init is gone
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                initializers_token_to_receive_account,
+            ));
+        }
+        if escrow_info.payouts().is_empty()
+            && !escrow_info.flags.has_receipt()
+            && !escrow_info.has_settlement_hook()
+        {
+            Self::assert_receive_account_usable(
+                initializers_token_to_receive_account,
+                &deposit_token_program,
+                &escrow_info,
+            )
+            .map_err(|e| account_info_iter.error(e, initializers_token_to_receive_account))?;
         }
 
         let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        let pda_account = next_account_info(account_info_iter)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account)
+            .map_err(|e| account_info_iter.error(e, pda_account))?;
+        let bump = escrow_info.vault_authority_bump;
+        let stats_account = next_account_info(account_info_iter)?;
 
-        let transfer_to_initializer_ix = spl_token::instruction::transfer(
-            token_program.key,
-            takers_sending_token_account.key,
-            initializers_token_to_receive_account.key,
-            taker.key,
-            &[&taker.key],
-            escrow_info.expected_amount,
-        )?;
-        msg!("Calling the token program to transfer tokens to the escrow's initializer...");
-        invoke(
-            &transfer_to_initializer_ix,
-            &[
-                takers_sending_token_account.clone(),
-                initializers_token_to_receive_account.clone(),
-                taker.clone(),
-                token_program.clone(),
-            ],
-        )?;
+        let config_account = next_account_info(account_info_iter)?;
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.paused {
+            return Err(account_info_iter.error(EscrowError::ConfigPaused, config_account));
+        }
 
-        let pda_account = next_account_info(account_info_iter)?;
+        let fee_token_account = next_account_info(account_info_iter)?;
+        // every transfer is checked against its mint, so the token program rejects a mint
+        // mix-up and can apply the mint's Token-2022 extensions
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let deposit_decimals = mint_decimals(deposit_mint, &vault.mint, token_program.key)
+            .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+        let expected_mint = next_account_info(account_info_iter)?;
+        let expected_decimals =
+            mint_decimals(expected_mint, &escrow_info.expected_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, expected_mint))?;
+        // an oracle-priced escrow's price account comes right after the mints, and is only
+        // read once its key is the one recorded at init
+        let price_account = if escrow_info.is_oracle_priced() {
+            let price_account = next_account_info(account_info_iter)?;
+            if *price_account.key != escrow_info.price_band.price_account
+                || *price_account.owner != PYTH_PROGRAM_ID
+            {
+                return Err(account_info_iter.error(EscrowError::OracleInvalid, price_account));
+            }
+            let price = OraclePrice::unpack_pyth(&price_account.try_borrow_data()?)
+                .map_err(|_| account_info_iter.error(EscrowError::OracleInvalid, price_account))?;
+            price
+                .check_usable(Clock::get()?.slot)
+                .map_err(|e| account_info_iter.error(e, price_account))?;
+            Some(price)
+        } else {
+            None
+        };
+        let payment = match price_account {
+            Some(price) => escrow_info
+                .price_band
+                .payment(&price, fill, deposit_decimals, expected_decimals, escrow_info.rounding_mode)
+                .ok_or(EscrowError::AmountOverflow)?
+                .max(payment),
+            None => payment,
+        };
+        // partial fills draw the quote down, while the taker pays what it's worth in raw units
+        let quoted_payment = payment;
+        let interest_scale = match escrow_info.quote_convention {
+            QuoteConvention::Raw => 1.,
+            QuoteConvention::InterestAdjusted => {
+                mint_interest_scale(expected_mint, token_program.key, Clock::get()?.unix_timestamp)
+                    .map_err(|e| account_info_iter.error(e, expected_mint))?
+            }
+        };
+        let payment = escrow_info
+            .quote_convention
+            .raw_payment(quoted_payment, interest_scale)
+            .ok_or(EscrowError::AmountOverflow)?;
+        // what the taker would have paid at their limit and didn't, for price improvement stats
+        let price_improvement = if max_payment.0 == 0 {
+            PaymentAmount(0)
+        } else {
+            max_payment
+                .checked_sub(payment)
+                .ok_or(EscrowError::PaymentAboveMax)?
+        };
+        let offer_age_slots = Clock::get()?.slot.saturating_sub(escrow_info.init_slot);
+        let fee_bps = config_info.fee_bps_for_age(offer_age_slots);
+        let initializer_proceeds = PaymentAmount(escrow_info.rounding_mode.maker_share(
+            payment.0,
+            TOTAL_PAYOUT_BPS.saturating_sub(fee_bps) as u64,
+            TOTAL_PAYOUT_BPS as u64,
+        ));
+        let fee = payment
+            .checked_sub(initializer_proceeds)
+            .ok_or(EscrowError::AmountOverflow)?;
+        // with a fee mint the fee is only valued in token Y, the initializer keeps the whole payment
+        let (fee, initializer_proceeds, fee_mint_amount) = if config_info.has_fee_mint() {
+            let fee_mint_amount = config_info
+                .fee_in_fee_mint(fee)
+                .ok_or(EscrowError::AmountOverflow)?;
+            (PaymentAmount(0), payment, fee_mint_amount)
+        } else {
+            (fee, initializer_proceeds, 0)
+        };
+        if fee.0 > 0 || fee_mint_amount > 0 {
+            let fee_token_account_info = unpack_token_account(fee_token_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, fee_token_account))?;
+            let fee_account_mint = if config_info.has_fee_mint() {
+                config_info.fee_mint
+            } else {
+                escrow_info.expected_mint
+            };
+            if fee_token_account_info.owner != config_info.fee_recipient_pubkey
+                || fee_token_account_info.mint != fee_account_mint
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    fee_token_account,
+                ));
+            }
+        }
+        let mut payout_accounts = Vec::with_capacity(escrow_info.payouts().len());
+        for payout in escrow_info.payouts() {
+            let payout_account = next_account_info(account_info_iter)?;
+            if payout.token_account_pubkey != *payout_account.key {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    payout_account,
+                ));
+            }
+            payout_accounts.push(payout_account);
+        }
+        let audit_log_account = if escrow_info.flags.is_audited() && !dry_run {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let takers_fee_mint = if config_info.has_fee_mint() {
+            let takers_fee_mint_account = next_account_info(account_info_iter)?;
+            let fee_mint = next_account_info(account_info_iter)?;
+            let fee_mint_decimals = mint_decimals(fee_mint, &config_info.fee_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, fee_mint))?;
+            Some((takers_fee_mint_account, fee_mint, fee_mint_decimals))
+        } else {
+            None
+        };
+        let pair_stats = if config_info.has_volume_cap() {
+            let pair_stats_account = next_account_info(account_info_iter)?;
+            let mut pair_stats_info = Self::load_pair_stats(
+                pair_stats_account,
+                config_account.key,
+                &vault.mint,
+                &escrow_info.expected_mint,
+                program_id,
+            )
+            .map_err(|e| account_info_iter.error(e, pair_stats_account))?;
+            pair_stats_info
+                .record_fill(fill, Clock::get()?.slot, &config_info)
+                .map_err(|e| account_info_iter.error(e, pair_stats_account))?;
+            if max_payment.0 != 0 {
+                pair_stats_info.record_price_improvement(price_improvement);
+            }
+            Some((pair_stats_account, pair_stats_info))
+        } else {
+            None
+        };
+        if escrow_info.has_taker_allowlist() {
+            let taker_allowlist_account = next_account_info(account_info_iter)?;
+            let taker_allowlist =
+                Self::load_taker_allowlist(taker_allowlist_account, escrow_account.key, program_id)
+                    .map_err(|e| account_info_iter.error(e, taker_allowlist_account))?;
+            if !taker_allowlist.allows(taker.key) {
+                return Err(account_info_iter.error(EscrowError::TakerNotAllowed, taker));
+            }
+        }
+        if escrow_info.has_taker_gate() {
+            let taker_gate = escrow_info.taker_gate;
+            let badge_amount = if taker_gate.has_badge() {
+                let badge_account = next_account_info(account_info_iter)?;
+                let badge = unpack_token_account(badge_account, badge_account.owner)
+                    .map_err(|e| account_info_iter.error(e, badge_account))?;
+                if badge.owner != *taker.key || badge.mint != taker_gate.badge_mint {
+                    return Err(account_info_iter.error(
+                        ProgramError::InvalidAccountData,
+                        badge_account,
+                    ));
+                }
+                badge.amount
+            } else {
+                0
+            };
+            if !taker_gate.admits(taker.lamports(), badge_amount) {
+                return Err(account_info_iter.error(EscrowError::TakerGateNotMet, taker));
+            }
+        }
+        // an NFT's creators are paid their royalties out of the initializer's proceeds
+        let deposit_is_nft =
+            deposit_decimals == 0 && unpack_mint(deposit_mint, token_program.key)?.supply == 1;
+        let royalty_payouts = if deposit_is_nft {
+            let metadata_account = next_account_info(account_info_iter)?;
+            if *metadata_account.key != Royalties::metadata_address(&vault.mint) {
+                return Err(account_info_iter.error(ProgramError::InvalidSeeds, metadata_account));
+            }
+            // an NFT minted without metadata owes no royalties
+            let royalties = if *metadata_account.owner == METADATA_PROGRAM_ID {
+                Royalties::unpack_metadata(&metadata_account.try_borrow_data()?, &vault.mint)
+                    .map_err(|e| account_info_iter.error(e, metadata_account))?
+            } else {
+                Royalties::default()
+            };
+            let mut royalty_payouts = Vec::with_capacity(royalties.creators.len());
+            for (creator, royalty) in royalties.creators.iter().zip(royalties.split(payment)) {
+                let creator_account = next_account_info(account_info_iter)?;
+                let creator_account_info = unpack_token_account(creator_account, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, creator_account))?;
+                if creator_account_info.owner != creator.address
+                    || creator_account_info.mint != escrow_info.expected_mint
+                {
+                    return Err(account_info_iter.error(
+                        ProgramError::InvalidAccountData,
+                        creator_account,
+                    ));
+                }
+                royalty_payouts.push((creator_account, royalty));
+            }
+            royalty_payouts
+        } else {
+            Vec::new()
+        };
+        let royalties = PaymentAmount(royalty_payouts.iter().map(|(_, royalty)| royalty.0).sum());
+        let initializer_proceeds = initializer_proceeds
+            .checked_sub(royalties)
+            .ok_or(EscrowError::RoyaltiesExceedProceeds)?;
+        // an insured fill's premium comes out of the initializer's proceeds too
+        let (insurance_premium, pool_token_account) = if escrow_info.is_insured() {
+            let insurance_pool_account = next_account_info(account_info_iter)?;
+            let insurance_pool_info = Self::load_insurance_pool(
+                insurance_pool_account,
+                config_account.key,
+                program_id,
+            )
+            .map_err(|e| account_info_iter.error(e, insurance_pool_account))?;
+            let pool_token_account = next_account_info(account_info_iter)?;
+            let pool_token_info = unpack_token_account(pool_token_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, pool_token_account))?;
+            if pool_token_info.owner != *insurance_pool_account.key
+                || pool_token_info.mint != escrow_info.expected_mint
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    pool_token_account,
+                ));
+            }
+            (payment.bps_share(insurance_pool_info.premium_bps), Some(pool_token_account))
+        } else {
+            (PaymentAmount(0), None)
+        };
+        let initializer_proceeds = initializer_proceeds
+            .checked_sub(insurance_premium)
+            .ok_or(EscrowError::PremiumExceedsProceeds)?;
+        let payout_amounts = Self::split_payment(initializer_proceeds, escrow_info.payouts())?;
+        // the basket goes to the taker along with the deposit
+        let basket = if escrow_info.has_basket() {
+            let basket_account = next_account_info(account_info_iter)?;
+            let basket_info = Self::load_basket(basket_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, basket_account))?;
+            let legs = Self::basket_legs(
+                account_info_iter,
+                &basket_info,
+                taker.key,
+                token_program.key,
+            )?;
+            Some((basket_account, basket_info, legs))
+        } else {
+            None
+        };
+        let settlement = if escrow_info.has_settlement_hook() {
+            let settlement_program = next_account_info(account_info_iter)?;
+            // delisting the program halts fills until the initializer cancels
+            if *settlement_program.key != escrow_info.settlement_program
+                || !config_info.allows_settlement_into(settlement_program.key)
+            {
+                return Err(account_info_iter.error(
+                    EscrowError::SettlementNotAllowed,
+                    settlement_program,
+                ));
+            }
+            let settlement_pda = next_account_info(account_info_iter)?;
+            let bump = escrow_info.settlement_bump;
+            if *settlement_pda.key != pda_with_bump(program_id, &[b"settlement", &[bump]])? {
+                return Err(account_info_iter.error(ProgramError::InvalidSeeds, settlement_pda));
+            }
+            let staged_before =
+                unpack_token_account(initializers_token_to_receive_account, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, initializers_token_to_receive_account))?
+                    .amount;
+            Some((settlement_program, settlement_pda, bump, staged_before))
+        } else {
+            None
+        };
+
+        if dry_run {
+            let quote = ExchangeQuote {
+                deposit: fill,
+                fee,
+                initializer_proceeds,
+                fee_mint_amount,
+                royalties,
+                insurance_premium,
+            };
+            set_return_data(&quote.pack());
+            msg!("Exchange would succeed");
+            return Ok(());
+        }
+
+        if let Some((pair_stats_account, pair_stats_info)) = pair_stats {
+            PairStats::pack(pair_stats_info, &mut pair_stats_account.try_borrow_mut_data()?)?;
+        }
+
+        if fee.0 > 0 {
+            msg!("Calling the token program to transfer the operator fee...");
+            invoke_transfer_checked(
+                token_program,
+                takers_sending_token_account,
+                expected_mint,
+                fee_token_account,
+                taker,
+                account_info_iter.as_slice(),
+                fee.0,
+                expected_decimals,
+                &[],
+            )?;
+        }
+        if let Some((takers_fee_mint_account, fee_mint, fee_mint_decimals)) =
+            takers_fee_mint.filter(|_| fee_mint_amount > 0)
+        {
+            msg!("Calling the token program to transfer the operator fee in the fee mint...");
+            invoke_transfer_checked(
+                token_program,
+                takers_fee_mint_account,
+                fee_mint,
+                fee_token_account,
+                taker,
+                account_info_iter.as_slice(),
+                fee_mint_amount,
+                fee_mint_decimals,
+                &[],
+            )?;
+        }
+
+        for (i, (creator_account, royalty)) in royalty_payouts.into_iter().enumerate() {
+            if royalty.0 == 0 {
+                continue;
+            }
+            msg!("Calling the token program to transfer royalties to creator {}...", i);
+            invoke_transfer_checked(
+                token_program,
+                takers_sending_token_account,
+                expected_mint,
+                creator_account,
+                taker,
+                account_info_iter.as_slice(),
+                royalty.0,
+                expected_decimals,
+                &[],
+            )?;
+        }
+
+        if let Some(pool_token_account) = pool_token_account.filter(|_| insurance_premium.0 > 0) {
+            msg!("Calling the token program to transfer the insurance premium...");
+            invoke_transfer_checked(
+                token_program,
+                takers_sending_token_account,
+                expected_mint,
+                pool_token_account,
+                taker,
+                account_info_iter.as_slice(),
+                insurance_premium.0,
+                expected_decimals,
+                &[],
+            )?;
+        }
+
+        if escrow_info.payouts().is_empty() {
+            msg!("Calling the token program to transfer tokens to the escrow's initializer...");
+            invoke_transfer_checked(
+                token_program,
+                takers_sending_token_account,
+                expected_mint,
+                initializers_token_to_receive_account,
+                taker,
+                account_info_iter.as_slice(),
+                initializer_proceeds.0,
+                expected_decimals,
+                &[],
+            )?;
+            if let Some((settlement_program, settlement_pda, bump, staged_before)) = settlement {
+                Self::settle_proceeds(
+                    settlement_program,
+                    settlement_pda,
+                    bump,
+                    initializers_token_to_receive_account,
+                    initializers_main_account,
+                    token_program,
+                    account_info_iter.as_slice(),
+                    initializer_proceeds,
+                )?;
+                let staged_after =
+                    unpack_token_account(initializers_token_to_receive_account, token_program.key)?
+                        .amount;
+                if staged_after != staged_before {
+                    return Err(account_info_iter.error(
+                        EscrowError::SettlementIncomplete,
+                        initializers_token_to_receive_account,
+                    ));
+                }
+            }
+        } else {
+            for (i, (payout_account, payout_amount)) in
+                payout_accounts.into_iter().zip(payout_amounts).enumerate()
+            {
+                msg!("Calling the token program to transfer tokens to payout account {}...", i);
+                invoke_transfer_checked(
+                    token_program,
+                    takers_sending_token_account,
+                    expected_mint,
+                    payout_account,
+                    taker,
+                    account_info_iter.as_slice(),
+                    payout_amount.0,
+                    expected_decimals,
+                    &[],
+                )?;
+            }
+        }
 
-        let transfer_to_taker_ix = spl_token::instruction::transfer(
-            token_program.key,
-            pdas_temp_token_account.key,
-            takers_token_to_receive_account.key,
-            &pda,
-            &[&pda],
-            pdas_temp_token_account_info.amount,
-        )?;
         msg!("Calling the token program to transfer tokens to the taker...");
-        invoke_signed(
-            &transfer_to_taker_ix,
-            &[
-                pdas_temp_token_account.clone(),
-                takers_token_to_receive_account.clone(),
-                pda_account.clone(),
-                token_program.clone(),
-            ],
-            &[&[&b"escrow"[..], &[nonce]]],
+        transfer_from_vault(
+            token_program,
+            pdas_temp_token_account,
+            Some((deposit_mint, deposit_decimals)),
+            takers_token_to_receive_account,
+            pda_account,
+            account_info_iter.as_slice(),
+            bump,
+            fill,
         )?;
+        // SOL reaches the taker unwrapped, and a wSOL account they paid from isn't left empty
+        if takers_receive_info.is_native() {
+            msg!("Calling the token program to unwrap the taker's SOL...");
+            unwrap_native(token_program, takers_token_to_receive_account, taker)?;
+        }
+        if takers_sending_info.is_native()
+            && takers_sending_info.owner == *taker.key
+            && takers_sending_token_account.key != takers_token_to_receive_account.key
+            && unpack_token_account(takers_sending_token_account, token_program.key)?.amount == 0
+        {
+            msg!("Calling the token program to close the taker's emptied wSOL account...");
+            unwrap_native(token_program, takers_sending_token_account, taker)?;
+        }
 
-        let close_pdas_temp_acc_ix = spl_token::instruction::close_account(
-            token_program.key,
-            pdas_temp_token_account.key,
-            initializers_main_account.key,
-            &pda,
-            &[&pda],
-        )?;
-        msg!("Calling the token program to close pda's temp account...");
-        invoke_signed(
-            &close_pdas_temp_acc_ix,
-            &[
-                pdas_temp_token_account.clone(),
-                initializers_main_account.clone(),
-                pda_account.clone(),
-                token_program.clone(),
-            ],
-            &[&[&b"escrow"[..], &[nonce]]],
+        if let Some(audit_log_account) = audit_log_account {
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                AuditAction::Exchange,
+                escrow_account.key,
+                taker.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        let full_fill = fill == deposit;
+        if full_fill {
+            let reclaimed_rent =
+                Self::escrow_rent(&escrow_info, escrow_account, pdas_temp_token_account)?;
+            Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+                .map_err(|e| account_info_iter.error(e, stats_account))?;
+        } else {
+            Self::load_stats(stats_account, program_id)
+                .map_err(|e| account_info_iter.error(e, stats_account))?;
+        }
+        Self::emit_event(
+            stats_account,
+            AuditAction::Exchange,
+            escrow_account.key,
+            taker.key,
+            escrow_info.deposit_mint_risk,
         )?;
+        let fill_event = FillEvent {
+            escrow_pubkey: *escrow_account.key,
+            fill,
+            payment,
+            max_payment,
+            price_improvement,
+        };
+        sol_log_data(&[&fill_event.pack()]);
+
+        if !full_fill {
+            // the rest of the deposit stays up for the next taker, in the same vault
+            escrow_info.record_partial_fill(fill, quoted_payment, Clock::get()?.slot);
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+            assert_one_leg_after_fill(
+                escrow_account,
+                pdas_temp_token_account,
+                token_program.key,
+                vault_amount,
+                fill,
+            );
+            return Ok(());
+        }
+
+        if let Some((basket_account, basket_info, legs)) = basket {
+            Self::release_basket(
+                basket_account,
+                &basket_info,
+                legs,
+                token_program,
+                initializers_main_account,
+                account_info_iter.as_slice(),
+            )?;
+        }
+
+        // a shared vault stays open for the initializer's other escrows until it's empty
+        if vault_amount == deposit.0 {
+            msg!("Calling the token program to close pda's temp account...");
+            close_vault(
+                token_program,
+                pdas_temp_token_account,
+                initializers_main_account,
+                pda_account,
+                bump,
+            )?;
+        }
+
+        if escrow_info.gas_rebate != 0 {
+            // the rent was counted without it, the rest of the lamports go to the initializer
+            **escrow_account.try_borrow_mut_lamports()? -= escrow_info.gas_rebate;
+            **taker.try_borrow_mut_lamports()? = taker
+                .lamports()
+                .checked_add(escrow_info.gas_rebate)
+                .ok_or(EscrowError::AmountOverflow)?;
+        }
 
         msg!("Closing the escrow account...");
-        **initializers_main_account.try_borrow_mut_lamports()? = initializers_main_account
-            .lamports()
-            .checked_add(escrow_account.lamports())
-            .ok_or(EscrowError::AmountOverflow)?;
-        **escrow_account.try_borrow_mut_lamports()? = 0;
-        *escrow_account.try_borrow_mut_data()? = &mut [];
+        close_state_account(escrow_account, initializers_main_account)?;
+        assert_one_leg_after_fill(
+            escrow_account,
+            pdas_temp_token_account,
+            token_program.key,
+            vault_amount,
+            fill,
+        );
 
         Ok(())
     }
 
-    fn process_cancel(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
+    fn process_reset_timelock(
+        accounts: &[AccountInfo],
+        unlock_slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
         let initializer = next_account_info(account_info_iter)?;
-
         if !initializer.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        // takers rely on a locked offer staying up until it expires
+        let current_slot = Clock::get()?.slot;
+        if current_slot > escrow_info.cancel_window_close_slot() {
+            return Err(account_info_iter.error(
+                EscrowError::TimeConstraintWasNotSatisfied,
+                escrow_account,
+            ));
+        }
+        let unlock_time = current_slot.saturating_add(unlock_slots);
+        if !(MIN_UNLOCK_SLOTS..=MAX_UNLOCK_SLOTS).contains(&unlock_slots)
+            || unlock_time < escrow_info.unlock_time
+        {
+            return Err(EscrowError::UnlockSlotsOutOfBounds.into());
+        }
+        // takers sizing up an offer by its window shouldn't see it pushed back over and over
+        if escrow_info.reset_count >= MAX_TIME_LOCK_RESETS
+            || (escrow_info.reset_count != 0
+                && current_slot < escrow_info.last_reset_slot.saturating_add(MIN_RESET_INTERVAL_SLOTS))
+        {
+            return Err(account_info_iter.error(EscrowError::TimeLockResetLimited, escrow_account));
+        }
+
+        escrow_info.unlock_time = unlock_time;
+        escrow_info.last_reset_slot = current_slot;
+        escrow_info.reset_count += 1;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_cancel(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let canceller = next_account_info(account_info_iter)?;
+
+        if !canceller.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                canceller,
+            ));
         }
 
         let pda_token_account = next_account_info(account_info_iter)?;
@@ -242,89 +1566,4397 @@ impl Processor {
         let initializer_sent_token_account = next_account_info(account_info_iter)?;
         let escrow_account = next_account_info(account_info_iter)?;
 
-        if escrow_account.owner != program_id || escrow_account.is_writable == false {
-            return Err(ProgramError::IllegalOwner);
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
         }
 
         let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
         let current_slot = Clock::get()?.slot;
-        if current_slot > escrow_info.unlock_time && escrow_info.unlock_time + 1000 > current_slot {
-            return Err(EscrowError::TimeConstraintWasNotSatisfied.into());
+        // a hash lock's recipient may redeem until expiry, so its refund only comes after
+        let in_cancel_window =
+            current_slot <= escrow_info.cancel_window_close_slot() && !escrow_info.is_hash_locked();
+        if !in_cancel_window && escrow_info.expiry_slot() > current_slot {
+            return Err(account_info_iter.error(
+                EscrowError::TimeConstraintWasNotSatisfied,
+                escrow_account,
+            ));
         }
 
-        if escrow_info.temp_token_account_pubkey != *temp_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
+        if escrow_info.temp_token_account_pubkey != *pda_token_account.key {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                pda_token_account,
+            ));
         }
 
         if escrow_info.initializer_pubkey != *initializer_main_account.key {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        if escrow_info.initializer_token_to_receive_account_pubkey != *initializers_token_to_receive_account.key {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                initializer_main_account,
+            ));
         }
 
-        if escrow_info.initializer_pubkey != *initializer.key {
-            return Err(ProgramError::InvalidAccountData);
+        // the receipt holder owns the position, so they cancel it in the initializer's place,
+        // proving they hold the receipt by burning it
+        let has_receipt = escrow_info.flags.has_receipt();
+        if !has_receipt && escrow_info.initializer_pubkey != *canceller.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, canceller));
         }
 
         let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
         let pda_account_info = next_account_info(account_info_iter)?;
-        let pda_token_account_info =
-            TokenAccount::unpack(&pda_token_account.try_borrow_data()?)?;
+        let pda_token_account_info = unpack_token_account(pda_token_account, token_program.key)
+            .map_err(|e| account_info_iter.error(e, pda_token_account))?;
+        let deposit = escrow_info
+            .deposit(pda_token_account_info.amount)
+            .map_err(|e| account_info_iter.error(e, pda_token_account))?;
 
-        let (pda, nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
+        // a signature alone doesn't choose where the deposit goes, so a phished initializer
+        // can't be tricked into refunding someone else
+        let refund_destination = if has_receipt {
+            get_associated_token_address_with_program_id(
+                canceller.key,
+                &pda_token_account_info.mint,
+                token_program.key,
+            )
+        } else {
+            escrow_info.refund_destination(&pda_token_account_info.mint, token_program.key)
+        };
+        if refund_destination != *initializer_sent_token_account.key {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                initializer_sent_token_account,
+            ));
+        }
 
-        //transfer tokens back to initializer
-        let transfer_to_initializer_ix = spl_token::instruction::transfer(
-            token_program.key,
-            pda_token_account.key,
-            initializer_sent_token_account.key,
-            &pda,
-            &[&pda],
-            pda_token_account_info.amount
-        )?;
-        msg!("Calling the token program to transfer tokens back to the initializer...");
-        invoke_signed(
-            &transfer_to_initializer_ix,
-            &[
-                pda_token_account.clone(),
-                initializer_sent_token_account.clone(),
-                pda_account_info.clone(),
-                token_program.clone(),
-            ],
-            &[&[&b"escrow"[..], &[nonce]]],
-        )?;
+        assert_vault_authority(program_id, &escrow_info, pda_account_info)
+            .map_err(|e| account_info_iter.error(e, pda_account_info))?;
+        let bump = escrow_info.vault_authority_bump;
 
-        let close_escrow_token_acct_ix = spl_token::instruction::close_account(
-            token_program.key,
-            pda_token_account.key,
-            initializer_main_account.key,
-            &pda,
-            &[&pda],
-        )?;
-        msg!("Calling the token program to close the escrow token account...");
-        invoke_signed(
-            &close_escrow_token_acct_ix,
-            &[
-                pda_token_account.clone(),
-                initializer_main_account.clone(),
-                pda_account_info.clone(),
-                token_program.clone(),
-            ],
-            &[&[&b"escrow"[..], &[nonce]]],
+        let stats_account = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let deposit_decimals =
+            mint_decimals(deposit_mint, &pda_token_account_info.mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, pda_token_account)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::Cancel,
+            escrow_account.key,
+            canceller.key,
+            escrow_info.deposit_mint_risk,
         )?;
 
-        msg!("Closing the escrow account...");
-        **initializer_main_account.try_borrow_mut_lamports()? = initializer_main_account
-            .lamports()
-            .checked_add(escrow_account.lamports())
-            .ok_or(EscrowError::AmountOverflow)?;
-        **escrow_account.try_borrow_mut_lamports()? = 0;
-        *escrow_account.try_borrow_mut_data()? = &mut [];
+        if escrow_info.flags.is_audited() {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                AuditAction::Cancel,
+                escrow_account.key,
+                canceller.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        if has_receipt {
+            let receipt_mint = next_account_info(account_info_iter)?;
+            let receipt_token_account = next_account_info(account_info_iter)?;
+            let proceeds_account = next_account_info(account_info_iter)?;
+            let holder = Self::receipt_holder(
+                account_info_iter,
+                escrow_account,
+                &escrow_info,
+                receipt_mint,
+                receipt_token_account,
+                proceeds_account,
+                token_program,
+                program_id,
+            )?;
+            if holder != *canceller.key {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    receipt_token_account,
+                ));
+            }
+
+            let burn_ix = spl_token_2022::instruction::burn(
+                token_program.key,
+                receipt_token_account.key,
+                receipt_mint.key,
+                canceller.key,
+                &[canceller.key],
+                1,
+            )?;
+            msg!("Calling the token program to burn the receipt...");
+            invoke(
+                &burn_ix,
+                &[
+                    receipt_token_account.clone(),
+                    receipt_mint.clone(),
+                    canceller.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+
+            msg!("Calling the token program to close the proceeds account...");
+            close_proceeds_account(
+                token_program,
+                proceeds_account,
+                initializer_main_account,
+                receipt_mint,
+                escrow_account.key,
+                escrow_info.receipt_mint_bump,
+            )?;
+        }
+
+        if escrow_info.has_basket() {
+            let basket_account = next_account_info(account_info_iter)?;
+            let basket_info = Self::load_basket(basket_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, basket_account))?;
+            let legs = Self::basket_legs(
+                account_info_iter,
+                &basket_info,
+                canceller.key,
+                token_program.key,
+            )?;
+            Self::release_basket(
+                basket_account,
+                &basket_info,
+                legs,
+                token_program,
+                initializer_main_account,
+                account_info_iter.as_slice(),
+            )?;
+        }
+
+        if escrow_info.is_mutual_deposit() {
+            let mutual_deposit_account = next_account_info(account_info_iter)?;
+            let taker_vault = next_account_info(account_info_iter)?;
+            let taker_refund_account = next_account_info(account_info_iter)?;
+            let expected_mint = next_account_info(account_info_iter)?;
+            let mutual_deposit_info =
+                Self::load_mutual_deposit(mutual_deposit_account, escrow_account.key, program_id)
+                    .map_err(|e| account_info_iter.error(e, mutual_deposit_account))?;
+            if get_associated_token_address_with_program_id(
+                mutual_deposit_account.key,
+                &escrow_info.expected_mint,
+                token_program.key,
+            ) != *taker_vault.key
+            {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, taker_vault));
+            }
+            if mutual_deposit_info.is_funded()
+                && mutual_deposit_info.taker_refund_account_pubkey != *taker_refund_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    taker_refund_account,
+                ));
+            }
+            let expected_decimals =
+                mint_decimals(expected_mint, &escrow_info.expected_mint, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, expected_mint))?;
+            Self::release_mutual_deposit(
+                mutual_deposit_account,
+                &mutual_deposit_info,
+                taker_vault,
+                expected_mint,
+                expected_decimals,
+                &[(taker_refund_account, mutual_deposit_info.taker_deposit)],
+                token_program,
+                initializer_main_account,
+                account_info_iter.as_slice(),
+            )?;
+        }
+
+        // closing a wSOL vault holding nothing but this deposit hands it back as SOL, which
+        // only works for the initializer: a receipt holder is refunded the wrapped tokens
+        let vault_emptied = pda_token_account_info.amount == deposit.0;
+        if !(vault_emptied && pda_token_account_info.is_native() && !has_receipt) {
+            //transfer tokens back to initializer
+            msg!("Calling the token program to transfer tokens back to the initializer...");
+            transfer_from_vault(
+                token_program,
+                pda_token_account,
+                Some((deposit_mint, deposit_decimals)),
+                initializer_sent_token_account,
+                pda_account_info,
+                account_info_iter.as_slice(),
+                bump,
+                deposit,
+            )?;
+        }
+
+        if vault_emptied {
+            msg!("Calling the token program to close the escrow token account...");
+            close_vault(
+                token_program,
+                pda_token_account,
+                initializer_main_account,
+                pda_account_info,
+                bump,
+            )?;
+        }
+
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializer_main_account)?;
+
+        Ok(())
+    }
+
+    /// Invokes an escrow's settlement program on proceeds just paid into `staging`, signing as
+    /// the settlement PDA so it can move them. The PDA owns nothing but staging accounts, which
+    /// are drained within every fill, so there is nothing at rest for the program to take.
+    #[allow(clippy::too_many_arguments)]
+    fn settle_proceeds<'a>(
+        settlement_program: &AccountInfo<'a>,
+        settlement_pda: &AccountInfo<'a>,
+        bump: u8,
+        staging: &AccountInfo<'a>,
+        beneficiary: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        passthrough_accounts: &[AccountInfo<'a>],
+        amount: PaymentAmount,
+    ) -> ProgramResult {
+        let mut account_metas = vec![
+            AccountMeta::new_readonly(*settlement_pda.key, true),
+            AccountMeta::new(*staging.key, false),
+            AccountMeta::new_readonly(*beneficiary.key, false),
+            AccountMeta::new_readonly(*token_program.key, false),
+        ];
+        account_metas.extend(passthrough_accounts.iter().map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        }));
+        let settle_ix = Instruction {
+            program_id: *settlement_program.key,
+            accounts: account_metas,
+            data: amount.0.to_le_bytes().to_vec(),
+        };
+
+        let mut account_infos = vec![
+            settlement_pda.clone(),
+            staging.clone(),
+            beneficiary.clone(),
+            token_program.clone(),
+        ];
+        account_infos.extend(passthrough_accounts.iter().cloned());
+        account_infos.push(settlement_program.clone());
+
+        msg!("Calling the settlement program to settle the initializer's proceeds...");
+        invoke_signed(&settle_ix, &account_infos, &[&[&b"settlement"[..], &[bump]]])
+    }
+
+    /// Checks the account the initializer is paid into still exists as a token account they own
+    /// for the expected mint, so a closed or reassigned account fails up front rather than
+    /// inside the token program, or worse, pays someone else. It must also still take
+    /// transfers, see `assert_transferable`.
+    fn assert_receive_account_usable(
+        receive_account: &AccountInfo,
+        token_program: &Pubkey,
+        escrow_info: &Escrow,
+    ) -> ProgramResult {
+        assert_transferable(receive_account, token_program)?;
+        let receive_account_info = unpack_token_account(receive_account, token_program)
+            .map_err(|_| EscrowError::ReceiveAccountUnavailable)?;
+        if receive_account_info.owner != escrow_info.initializer_pubkey
+            || receive_account_info.mint != escrow_info.expected_mint
+        {
+            return Err(EscrowError::ReceiveAccountUnavailable.into());
+        }
+        Ok(())
+    }
+
+    /// Splits `amount` by the payouts' bps weights, rounding down for every payout except
+    /// the last, which receives the remainder so the parts always add up to `amount`.
+    fn split_payment(
+        amount: PaymentAmount,
+        payouts: &[Payout],
+    ) -> Result<Vec<PaymentAmount>, ProgramError> {
+        let mut remaining = amount;
+        let mut amounts = Vec::with_capacity(payouts.len());
+        for (i, payout) in payouts.iter().enumerate() {
+            let payout_amount = if i + 1 == payouts.len() {
+                remaining
+            } else {
+                amount.bps_share(payout.bps)
+            };
+            remaining = remaining
+                .checked_sub(payout_amount)
+                .ok_or(EscrowError::AmountOverflow)?;
+            amounts.push(payout_amount);
+        }
+        Ok(amounts)
+    }
+
+    fn process_mint_receipt(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let receipt_mint = next_account_info(account_info_iter)?;
+        let receipt_token_account = next_account_info(account_info_iter)?;
+        let proceeds_account = next_account_info(account_info_iter)?;
+        let expected_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let associated_token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let rent_sysvar = next_account_info(account_info_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        if escrow_info.flags.has_receipt() {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                receipt_mint,
+            ));
+        }
+        // a hooked escrow's proceeds never rest anywhere a receipt holder could claim them from,
+        // a mutual deposit's go to the initializer at Settle, and a hash lock's are paid on
+        // another chain
+        if escrow_info.features.intersects(
+            EscrowFeatures::PAYOUTS
+                | EscrowFeatures::SETTLEMENT_HOOK
+                | EscrowFeatures::PARTIAL_FILL
+                | EscrowFeatures::MUTUAL_DEPOSIT
+                | EscrowFeatures::HASH_LOCK,
+        ) {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+        if escrow_info.expected_mint != *expected_mint.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, expected_mint));
+        }
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        // ClaimProceeds pays the holder out without the mint, which can't run a transfer hook
+        if transfer_hook_program(expected_mint, token_program.key)
+            .map_err(|e| account_info_iter.error(e, expected_mint))?
+            .is_some()
+        {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, expected_mint));
+        }
+
+        let (receipt_mint_pda, bump) =
+            Pubkey::find_program_address(&[b"receipt", escrow_account.key.as_ref()], program_id);
+        if *receipt_mint.key != receipt_mint_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, receipt_mint));
+        }
+        let receipt_seeds: &[&[u8]] = &[&b"receipt"[..], escrow_account.key.as_ref(), &[bump]];
+
+        let create_mint_ix = system_instruction::create_account(
+            initializer.key,
+            receipt_mint.key,
+            Rent::get()?.minimum_balance(Mint::LEN),
+            Mint::LEN as u64,
+            token_program.key,
+        );
+        msg!("Calling the system program to create the receipt mint...");
+        invoke_signed(
+            &create_mint_ix,
+            &[
+                initializer.clone(),
+                receipt_mint.clone(),
+                system_program.clone(),
+            ],
+            &[receipt_seeds],
+        )?;
+
+        let init_mint_ix = spl_token_2022::instruction::initialize_mint(
+            token_program.key,
+            receipt_mint.key,
+            receipt_mint.key,
+            None,
+            0,
+        )?;
+        msg!("Calling the token program to initialize the receipt mint...");
+        invoke(
+            &init_mint_ix,
+            &[
+                receipt_mint.clone(),
+                rent_sysvar.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        msg!("Calling the associated token account program to create the receipt token account...");
+        invoke(
+            &create_associated_token_account(
+                initializer.key,
+                initializer.key,
+                receipt_mint.key,
+                token_program.key,
+            ),
+            &[
+                initializer.clone(),
+                receipt_token_account.clone(),
+                initializer.clone(),
+                receipt_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                rent_sysvar.clone(),
+                associated_token_program.clone(),
+            ],
+        )?;
+
+        msg!("Calling the associated token account program to create the proceeds account...");
+        invoke(
+            &create_associated_token_account(
+                initializer.key,
+                receipt_mint.key,
+                expected_mint.key,
+                token_program.key,
+            ),
+            &[
+                initializer.clone(),
+                proceeds_account.clone(),
+                receipt_mint.clone(),
+                expected_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                rent_sysvar.clone(),
+                associated_token_program.clone(),
+            ],
+        )?;
+
+        let mint_receipt_ix = spl_token_2022::instruction::mint_to(
+            token_program.key,
+            receipt_mint.key,
+            receipt_token_account.key,
+            receipt_mint.key,
+            &[],
+            1,
+        )?;
+        msg!("Calling the token program to mint the receipt...");
+        invoke_signed(
+            &mint_receipt_ix,
+            &[
+                receipt_mint.clone(),
+                receipt_token_account.clone(),
+                token_program.clone(),
+            ],
+            &[receipt_seeds],
+        )?;
+
+        // a single receipt per escrow, so nobody may mint another
+        let revoke_mint_authority_ix = spl_token_2022::instruction::set_authority(
+            token_program.key,
+            receipt_mint.key,
+            None,
+            spl_token_2022::instruction::AuthorityType::MintTokens,
+            receipt_mint.key,
+            &[],
+        )?;
+        invoke_signed(
+            &revoke_mint_authority_ix,
+            &[receipt_mint.clone(), token_program.clone()],
+            &[receipt_seeds],
+        )?;
+
+        escrow_info.flags.set(EscrowFlags::RECEIPT, true);
+        escrow_info.receipt_mint_bump = bump;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_claim_proceeds(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let holder = next_account_info(account_info_iter)?;
+
+        if !holder.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, holder));
+        }
+
+        let receipt_token_account = next_account_info(account_info_iter)?;
+        let receipt_mint = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+        let proceeds_account = next_account_info(account_info_iter)?;
+        let holder_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // burning the receipt of an escrow that's still open would give up the position for nothing
+        if escrow_account.owner == program_id && escrow_account.data_len() > 0 {
+            return Err(account_info_iter.error(EscrowError::EscrowNotSettled, escrow_account));
+        }
+
+        let (receipt_mint_pda, bump) =
+            Pubkey::find_program_address(&[b"receipt", escrow_account.key.as_ref()], program_id);
+        if *receipt_mint.key != receipt_mint_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, receipt_mint));
+        }
+        let proceeds_account_info = unpack_token_account(proceeds_account, token_program.key)
+            .map_err(|e| account_info_iter.error(e, proceeds_account))?;
+        if proceeds_account_info.owner != receipt_mint_pda
+            || get_associated_token_address_with_program_id(
+                &receipt_mint_pda,
+                &proceeds_account_info.mint,
+                token_program.key,
+            ) != *proceeds_account.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, proceeds_account));
+        }
+
+        let burn_ix = spl_token_2022::instruction::burn(
+            token_program.key,
+            receipt_token_account.key,
+            receipt_mint.key,
+            holder.key,
+            &[holder.key],
+            1,
+        )?;
+        msg!("Calling the token program to burn the receipt...");
+        invoke(
+            &burn_ix,
+            &[
+                receipt_token_account.clone(),
+                receipt_mint.clone(),
+                holder.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let transfer_proceeds_ix = token_transfer(
+            token_program.key,
+            proceeds_account.key,
+            holder_token_account.key,
+            receipt_mint.key,
+            proceeds_account_info.amount,
+        )?;
+        msg!("Calling the token program to transfer the proceeds to the receipt holder...");
+        invoke_signed(
+            &transfer_proceeds_ix,
+            &[
+                proceeds_account.clone(),
+                holder_token_account.clone(),
+                receipt_mint.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"receipt"[..], escrow_account.key.as_ref(), &[bump]]],
+        )?;
+
+        msg!("Calling the token program to close the proceeds account...");
+        close_proceeds_account(
+            token_program,
+            proceeds_account,
+            holder,
+            receipt_mint,
+            escrow_account.key,
+            bump,
+        )?;
+
+        Ok(())
+    }
+
+    /// Reap, or CancelExpired which closes offers past their expiry rather than their lifetime
+    /// and rewards the cranker
+    fn process_reap(accounts: &[AccountInfo], action: AuditAction, program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let reaper = next_account_info(account_info_iter)?;
+
+        if !reaper.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, reaper));
+        }
+
+        let pda_token_account = next_account_info(account_info_iter)?;
+        let initializer_main_account = next_account_info(account_info_iter)?;
+        let initializer_refund_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+
+        // cranks race each other to reap, the losers see the account already closed
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+
+        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        assert_escrow_matches(
+            &escrow_info,
+            account_info_iter,
+            pda_token_account,
+            initializer_main_account,
+        )?;
+        // the taker's deposit has to go back with it, which only Cancel does
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        let current_slot = Clock::get()?.slot;
+        let cancel_expired = action == AuditAction::CancelExpired;
+        if cancel_expired && current_slot < escrow_info.expiry_slot() {
+            return Err(account_info_iter.error(
+                EscrowError::TimeConstraintWasNotSatisfied,
+                escrow_account,
+            ));
+        }
+        if !cancel_expired && !escrow_info.is_past_lifetime(current_slot) {
+            return Err(account_info_iter.error(EscrowError::EscrowNotExpired, escrow_account));
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        let pda_token_account_info = unpack_token_account(pda_token_account, token_program.key)
+            .map_err(|e| account_info_iter.error(e, pda_token_account))?;
+        let deposit = escrow_info
+            .deposit(pda_token_account_info.amount)
+            .map_err(|e| account_info_iter.error(e, pda_token_account))?;
+        // the receipt holder owns the position, so it's refunded to them instead, once the
+        // receipt accounts say who they are
+        let has_receipt = escrow_info.flags.has_receipt();
+        if !has_receipt
+            && escrow_info.refund_destination(&pda_token_account_info.mint, token_program.key)
+                != *initializer_refund_account.key
+        {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                initializer_refund_account,
+            ));
+        }
+
+        let pda_account_info = next_account_info(account_info_iter)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account_info)
+            .map_err(|e| account_info_iter.error(e, pda_account_info))?;
+        let bump = escrow_info.vault_authority_bump;
+
+        let stats_account = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let deposit_decimals =
+            mint_decimals(deposit_mint, &pda_token_account_info.mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, pda_token_account)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            action,
+            escrow_account.key,
+            reaper.key,
+            escrow_info.deposit_mint_risk,
+        )?;
+
+        if escrow_info.flags.is_audited() {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                action,
+                escrow_account.key,
+                reaper.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        // nobody signs for the holder, so their receipt stays unburned, redeeming nothing
+        let mut refund_owner = escrow_info.initializer_pubkey;
+        if has_receipt {
+            let receipt_mint = next_account_info(account_info_iter)?;
+            let receipt_token_account = next_account_info(account_info_iter)?;
+            let proceeds_account = next_account_info(account_info_iter)?;
+            refund_owner = Self::receipt_holder(
+                account_info_iter,
+                escrow_account,
+                &escrow_info,
+                receipt_mint,
+                receipt_token_account,
+                proceeds_account,
+                token_program,
+                program_id,
+            )?;
+            if get_associated_token_address_with_program_id(
+                &refund_owner,
+                &pda_token_account_info.mint,
+                token_program.key,
+            ) != *initializer_refund_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializer_refund_account,
+                ));
+            }
+
+            msg!("Calling the token program to close the proceeds account...");
+            close_proceeds_account(
+                token_program,
+                proceeds_account,
+                initializer_main_account,
+                receipt_mint,
+                escrow_account.key,
+                escrow_info.receipt_mint_bump,
+            )?;
+        }
+
+        // an abandoned basket goes back with the deposit, rather than waiting on ReclaimBasket
+        if escrow_info.has_basket() {
+            let basket_account = next_account_info(account_info_iter)?;
+            let basket_info = Self::load_basket(basket_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, basket_account))?;
+            let legs = Self::basket_legs(
+                account_info_iter,
+                &basket_info,
+                &refund_owner,
+                token_program.key,
+            )?;
+            Self::release_basket(
+                basket_account,
+                &basket_info,
+                legs,
+                token_program,
+                initializer_main_account,
+                account_info_iter.as_slice(),
+            )?;
+        }
+
+        msg!("Calling the token program to refund the deposit...");
+        transfer_from_vault(
+            token_program,
+            pda_token_account,
+            Some((deposit_mint, deposit_decimals)),
+            initializer_refund_account,
+            pda_account_info,
+            account_info_iter.as_slice(),
+            bump,
+            deposit,
+        )?;
+
+        if pda_token_account_info.amount == deposit.0 {
+            msg!("Calling the token program to close the escrow token account...");
+            close_vault(
+                token_program,
+                pda_token_account,
+                initializer_main_account,
+                pda_account_info,
+                bump,
+            )?;
+        }
+
+        if cancel_expired {
+            let reward = CANCEL_EXPIRED_REWARD_LAMPORTS.min(escrow_account.lamports());
+            **escrow_account.try_borrow_mut_lamports()? -= reward;
+            **reaper.try_borrow_mut_lamports()? = reaper
+                .lamports()
+                .checked_add(reward)
+                .ok_or(EscrowError::AmountOverflow)?;
+        }
+
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializer_main_account)?;
+
+        Ok(())
+    }
+
+    fn process_recover_closed_mint(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let pda_token_account = next_account_info(account_info_iter)?;
+        let initializer_main_account = next_account_info(account_info_iter)?;
+        let initializer_refund_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+
+        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        assert_escrow_matches(
+            &escrow_info,
+            account_info_iter,
+            pda_token_account,
+            initializer_main_account,
+        )?;
+        // the taker's deposit has to go back with it, which only Cancel does
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        if escrow_info.flags.has_receipt() {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        // the first escrow of a shared vault to recover a closed deposit mint closes the emptied
+        // vault, the others find it gone with nothing left to refund
+        let pda_token_account_info = if escrow_info.shares_vault() && pda_token_account.data_is_empty() {
+            None
+        } else {
+            Some(unpack_token_account(pda_token_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, pda_token_account))?)
+        };
+        if let Some(pda_token_account_info) = &pda_token_account_info {
+            if escrow_info.refund_destination(&pda_token_account_info.mint, token_program.key)
+                != *initializer_refund_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializer_refund_account,
+                ));
+            }
+        }
+
+        let pda_account_info = next_account_info(account_info_iter)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account_info)
+            .map_err(|e| account_info_iter.error(e, pda_account_info))?;
+        let bump = escrow_info.vault_authority_bump;
+
+        let stats_account = next_account_info(account_info_iter)?;
+        let closed_mint = next_account_info(account_info_iter)?;
+        let is_deposit_mint = match &pda_token_account_info {
+            Some(pda_token_account_info) => *closed_mint.key == pda_token_account_info.mint,
+            None => {
+                Pubkey::find_program_address(
+                    &[b"vault", initializer.key.as_ref(), closed_mint.key.as_ref()],
+                    program_id,
+                )
+                .0 == *pda_token_account.key
+            }
+        };
+        if !is_deposit_mint && *closed_mint.key != escrow_info.expected_mint {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, closed_mint));
+        }
+        // a mint that still unpacks can be traded against, so the time lock still applies
+        if unpack_mint(closed_mint, token_program.key).is_ok() {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, closed_mint));
+        }
+        let deposit_mint = next_account_info(account_info_iter)?;
+
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, pda_token_account)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::Cancel,
+            escrow_account.key,
+            initializer.key,
+            escrow_info.deposit_mint_risk,
+        )?;
+
+        if escrow_info.flags.is_audited() {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                AuditAction::Cancel,
+                escrow_account.key,
+                initializer.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        if let Some(pda_token_account_info) = pda_token_account_info {
+            // a closed deposit mint means the vault was burned down to nothing already
+            let refund = if escrow_info.shares_vault() {
+                escrow_info.vault_deposit.min(DepositAmount(pda_token_account_info.amount))
+            } else {
+                DepositAmount(pda_token_account_info.amount)
+            };
+            if refund.0 > 0 {
+                // a closed deposit mint had no supply left, so this is the expected mint's case
+                let deposit_decimals =
+                    mint_decimals(deposit_mint, &pda_token_account_info.mint, token_program.key)
+                        .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+                msg!("Calling the token program to refund the initializer...");
+                transfer_from_vault(
+                    token_program,
+                    pda_token_account,
+                    Some((deposit_mint, deposit_decimals)),
+                    initializer_refund_account,
+                    pda_account_info,
+                    account_info_iter.as_slice(),
+                    bump,
+                    refund,
+                )?;
+            }
+
+            if pda_token_account_info.amount == refund.0 {
+                msg!("Calling the token program to close the escrow token account...");
+                close_vault(
+                    token_program,
+                    pda_token_account,
+                    initializer_main_account,
+                    pda_account_info,
+                    bump,
+                )?;
+            }
+        }
+
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializer_main_account)?;
+
+        Ok(())
+    }
+
+    fn process_force_close(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let pda_token_account = next_account_info(account_info_iter)?;
+        let initializer_main_account = next_account_info(account_info_iter)?;
+        let initializer_refund_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+
+        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        assert_escrow_matches(
+            &escrow_info,
+            account_info_iter,
+            pda_token_account,
+            initializer_main_account,
+        )?;
+        // the taker's deposit has to go back with it, which only Cancel does
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        if escrow_info.flags.has_receipt() {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        let pda_token_account_info = if pda_token_account.data_is_empty() {
+            None
+        } else {
+            Some(unpack_token_account(pda_token_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, pda_token_account))?)
+        };
+        if let Some(pda_token_account_info) = &pda_token_account_info {
+            if escrow_info.refund_destination(&pda_token_account_info.mint, token_program.key)
+                != *initializer_refund_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializer_refund_account,
+                ));
+            }
+        }
+
+        let pda_account_info = next_account_info(account_info_iter)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account_info)
+            .map_err(|e| account_info_iter.error(e, pda_account_info))?;
+        let bump = escrow_info.vault_authority_bump;
+
+        let stats_account = next_account_info(account_info_iter)?;
+        let admin = next_account_info(account_info_iter)?;
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+        let config_account = next_account_info(account_info_iter)?;
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        let deposit_mint = next_account_info(account_info_iter)?;
+
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, pda_token_account)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::ForceClose,
+            escrow_account.key,
+            admin.key,
+            escrow_info.deposit_mint_risk,
+        )?;
+
+        if escrow_info.flags.is_audited() {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                AuditAction::ForceClose,
+                escrow_account.key,
+                admin.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+
+        if let Some(pda_token_account_info) = pda_token_account_info {
+            // a shared vault short of this escrow's share gives up what it has, never more
+            let refund = if escrow_info.shares_vault() {
+                escrow_info.vault_deposit.min(DepositAmount(pda_token_account_info.amount))
+            } else {
+                DepositAmount(pda_token_account_info.amount)
+            };
+            if refund.0 > 0 {
+                // only read when there's something to refund, a closed mint leaves nothing
+                let deposit_decimals =
+                    mint_decimals(deposit_mint, &pda_token_account_info.mint, token_program.key)
+                        .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+                msg!("Calling the token program to refund the initializer...");
+                transfer_from_vault(
+                    token_program,
+                    pda_token_account,
+                    Some((deposit_mint, deposit_decimals)),
+                    initializer_refund_account,
+                    pda_account_info,
+                    account_info_iter.as_slice(),
+                    bump,
+                    refund,
+                )?;
+            }
+
+            if pda_token_account_info.amount == refund.0 {
+                msg!("Calling the token program to close the escrow token account...");
+                close_vault(
+                    token_program,
+                    pda_token_account,
+                    initializer_main_account,
+                    pda_account_info,
+                    bump,
+                )?;
+            }
+        }
+
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializer_main_account)?;
+
+        Ok(())
+    }
+
+    fn process_init_vault(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let vault = next_account_info(account_info_iter)?;
+        let mint = next_account_info(account_info_iter)?;
+        let vault_authority = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let rent_sysvar = next_account_info(account_info_iter)?;
+
+        let (vault_pda, bump) = Pubkey::find_program_address(
+            &[b"vault", initializer.key.as_ref(), mint.key.as_ref()],
+            program_id,
+        );
+        if *vault.key != vault_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, vault));
+        }
+        let (vault_authority_pda, _bump) =
+            Pubkey::find_program_address(&[b"escrow", vault.key.as_ref()], program_id);
+        if *vault_authority.key != vault_authority_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, vault_authority));
+        }
+        if !vault.data_is_empty() {
+            return Err(account_info_iter.error(ProgramError::AccountAlreadyInitialized, vault));
+        }
+
+        let create_vault_ix = system_instruction::create_account(
+            initializer.key,
+            vault.key,
+            Rent::get()?.minimum_balance(TokenAccount::LEN),
+            TokenAccount::LEN as u64,
+            token_program.key,
+        );
+        msg!("Calling the system program to create the shared vault...");
+        invoke_signed(
+            &create_vault_ix,
+            &[
+                initializer.clone(),
+                vault.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                &b"vault"[..],
+                initializer.key.as_ref(),
+                mint.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        let init_vault_ix = spl_token_2022::instruction::initialize_account(
+            token_program.key,
+            vault.key,
+            mint.key,
+            vault_authority.key,
+        )?;
+        msg!("Calling the token program to initialize the shared vault...");
+        invoke(
+            &init_vault_ix,
+            &[
+                vault.clone(),
+                mint.clone(),
+                vault_authority.clone(),
+                rent_sysvar.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_wrap_sol(accounts: &[AccountInfo], lamports: u64) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let owner = next_account_info(account_info_iter)?;
+        if !owner.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, owner));
+        }
+        let wsol_account = next_account_info(account_info_iter)?;
+        let mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        if !is_token_program(token_program.key) {
+            return Err(account_info_iter.error(ProgramError::IncorrectProgramId, token_program));
+        }
+        if *mint.key != native_mint(token_program.key) {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, mint));
+        }
+        let rent_lamports = Rent::get()?.minimum_balance(TokenAccount::LEN);
+
+        let create_wsol_ix = system_instruction::create_account(
+            owner.key,
+            wsol_account.key,
+            rent_lamports
+                .checked_add(lamports)
+                .ok_or(EscrowError::AmountOverflow)?,
+            TokenAccount::LEN as u64,
+            token_program.key,
+        );
+        msg!("Calling the system program to create the wSOL account...");
+        invoke(
+            &create_wsol_ix,
+            &[owner.clone(), wsol_account.clone(), system_program.clone()],
+        )?;
+
+        // a native account's balance is whatever it holds above rent exemption
+        let init_wsol_ix = spl_token_2022::instruction::initialize_account3(
+            token_program.key,
+            wsol_account.key,
+            mint.key,
+            owner.key,
+        )?;
+        msg!("Calling the token program to initialize the wSOL account...");
+        invoke(
+            &init_wsol_ix,
+            &[wsol_account.clone(), mint.clone(), token_program.clone()],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_init_insurance_pool(
+        accounts: &[AccountInfo],
+        premium_bps: u16,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        if premium_bps > MAX_INSURANCE_PREMIUM_BPS {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+
+        let insurance_pool_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (insurance_pool_pda, bump) = Pubkey::find_program_address(
+            &[b"insurance", config_account.key.as_ref()],
+            program_id,
+        );
+        if *insurance_pool_account.key != insurance_pool_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, insurance_pool_account));
+        }
+
+        // an existing pool only has its premium updated, keeping its claim count
+        let claims_paid = if insurance_pool_account.owner == program_id {
+            InsurancePool::unpack(&insurance_pool_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, insurance_pool_account))?
+                .claims_paid
+        } else {
+            let create_insurance_pool_ix = system_instruction::create_account(
+                admin.key,
+                insurance_pool_account.key,
+                Rent::get()?.minimum_balance(InsurancePool::LEN),
+                InsurancePool::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the insurance pool...");
+            invoke_signed(
+                &create_insurance_pool_ix,
+                &[
+                    admin.clone(),
+                    insurance_pool_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[&b"insurance"[..], config_account.key.as_ref(), &[bump]]],
+            )?;
+            0
+        };
+
+        let insurance_pool_info = InsurancePool {
+            is_initialized: true,
+            config_pubkey: *config_account.key,
+            premium_bps,
+            bump,
+            claims_paid,
+        };
+        InsurancePool::pack(
+            insurance_pool_info,
+            &mut insurance_pool_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_insure_escrow(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        // only an operator that has set up a pool can back the escrow's fills
+        let insurance_pool_account = next_account_info(account_info_iter)?;
+        Self::load_insurance_pool(insurance_pool_account, &escrow_info.config_pubkey, program_id)
+            .map_err(|e| account_info_iter.error(e, insurance_pool_account))?;
+
+        if !escrow_info.is_insured() {
+            escrow_info.features =
+                EscrowFeatures::from_bits(escrow_info.features.bits() | EscrowFeatures::INSURED)
+                    .ok_or(EscrowError::UnsupportedFeatures)?;
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_pay_claim(accounts: &[AccountInfo], amount: u64, program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        let insurance_pool_account = next_account_info(account_info_iter)?;
+        let mut insurance_pool_info =
+            Self::load_insurance_pool(insurance_pool_account, config_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, insurance_pool_account))?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let claimant_token_account = next_account_info(account_info_iter)?;
+        let mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let pool_token_info = unpack_token_account(pool_token_account, token_program.key)
+            .map_err(|e| account_info_iter.error(e, pool_token_account))?;
+        if pool_token_info.owner != *insurance_pool_account.key {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                pool_token_account,
+            ));
+        }
+        let decimals = mint_decimals(mint, &pool_token_info.mint, token_program.key)
+            .map_err(|e| account_info_iter.error(e, mint))?;
+
+        msg!("Calling the token program to pay the claim out of the insurance pool...");
+        invoke_transfer_checked(
+            token_program,
+            pool_token_account,
+            mint,
+            claimant_token_account,
+            insurance_pool_account,
+            account_info_iter.as_slice(),
+            amount,
+            decimals,
+            &[&[
+                &b"insurance"[..],
+                config_account.key.as_ref(),
+                &[insurance_pool_info.bump],
+            ]],
+        )?;
+
+        insurance_pool_info.claims_paid = insurance_pool_info.claims_paid.saturating_add(1);
+        InsurancePool::pack(
+            insurance_pool_info,
+            &mut insurance_pool_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_add_basket_asset(
+        accounts: &[AccountInfo],
+        amount: DepositAmount,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if amount.0 == 0 {
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        // a partial fill couldn't say how much of each asset it takes
+        let features =
+            EscrowFeatures::from_bits(escrow_info.features.bits() | EscrowFeatures::BASKET)
+                .ok_or(EscrowError::UnsupportedFeatures)?;
+
+        let basket_account = next_account_info(account_info_iter)?;
+        let source_token_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        let associated_token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (basket_pda, bump) =
+            Pubkey::find_program_address(&[b"basket", escrow_account.key.as_ref()], program_id);
+        if *basket_account.key != basket_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, basket_account));
+        }
+        let mut basket_info = if escrow_info.has_basket() {
+            Self::load_basket(basket_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, basket_account))?
+        } else {
+            // a basket an earlier escrow at the same address left behind has to be reclaimed first
+            if basket_account.owner == program_id {
+                return Err(account_info_iter.error(
+                    ProgramError::AccountAlreadyInitialized,
+                    basket_account,
+                ));
+            }
+            let create_basket_ix = system_instruction::create_account(
+                initializer.key,
+                basket_account.key,
+                Rent::get()?.minimum_balance(Basket::LEN),
+                Basket::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the basket...");
+            invoke_signed(
+                &create_basket_ix,
+                &[
+                    initializer.clone(),
+                    basket_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[&b"basket"[..], escrow_account.key.as_ref(), &[bump]]],
+            )?;
+            Basket {
+                is_initialized: true,
+                escrow_pubkey: *escrow_account.key,
+                initializer_pubkey: *initializer.key,
+                bump,
+                asset_count: 0,
+                assets: [BasketAsset::default(); MAX_BASKET_ASSETS],
+            }
+        };
+
+        let decimals = mint_decimals(mint, mint.key, token_program.key)
+            .map_err(|e| account_info_iter.error(e, mint))?;
+        if get_associated_token_address_with_program_id(&basket_pda, mint.key, token_program.key)
+            != *vault.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, vault));
+        }
+        if vault.data_is_empty() {
+            msg!("Calling the associated token account program to create the basket vault...");
+            invoke(
+                &create_associated_token_account(
+                    initializer.key,
+                    basket_account.key,
+                    mint.key,
+                    token_program.key,
+                ),
+                &[
+                    initializer.clone(),
+                    vault.clone(),
+                    basket_account.clone(),
+                    mint.clone(),
+                    system_program.clone(),
+                    token_program.clone(),
+                    associated_token_program.clone(),
+                ],
+            )?;
+        }
+        basket_info
+            .add(vault.key, mint.key, amount)
+            .map_err(|e| account_info_iter.error(e, basket_account))?;
+
+        msg!("Calling the token program to transfer the asset into the basket...");
+        invoke_transfer_checked(
+            token_program,
+            source_token_account,
+            mint,
+            vault,
+            initializer,
+            account_info_iter.as_slice(),
+            amount.0,
+            decimals,
+            &[],
+        )?;
+
+        Basket::pack(basket_info, &mut basket_account.try_borrow_mut_data()?)?;
+        if !escrow_info.has_basket() {
+            escrow_info.features = features;
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_reclaim_basket(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let basket_account = next_account_info(account_info_iter)?;
+        let basket_info = Self::load_basket(basket_account, escrow_account.key, program_id)
+            .map_err(|e| account_info_iter.error(e, basket_account))?;
+        if basket_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        // an open escrow's basket goes out with its fill, Cancel or reap; only one closed some
+        // other way, or since replaced by an escrow without a basket, is reclaimed here
+        if assert_escrow_not_settled(escrow_account).is_ok()
+            && escrow_account.owner == program_id
+            && Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?
+                .has_basket()
+        {
+            return Err(account_info_iter.error(EscrowError::EscrowNotSettled, escrow_account));
+        }
+
+        let token_program = next_account_info(account_info_iter)?;
+        let legs =
+            Self::basket_legs(account_info_iter, &basket_info, initializer.key, token_program.key)?;
+        Self::release_basket(
+            basket_account,
+            &basket_info,
+            legs,
+            token_program,
+            initializer,
+            account_info_iter.as_slice(),
+        )
+    }
+
+    fn process_multi_exchange(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let matcher = next_account_info(account_info_iter)?;
+        if !matcher.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, matcher));
+        }
+        let token_program = next_account_info(account_info_iter)?;
+        let stats_account = next_account_info(account_info_iter)?;
+        let slot = Clock::get()?.slot;
+
+        let mut legs: Vec<RingLeg> = Vec::with_capacity(RING_LEGS);
+        for _ in 0..RING_LEGS {
+            let escrow_account = next_account_info(account_info_iter)?;
+            let vault = next_account_info(account_info_iter)?;
+            let initializers_main_account = next_account_info(account_info_iter)?;
+            let initializers_token_to_receive_account = next_account_info(account_info_iter)?;
+            let pda_account = next_account_info(account_info_iter)?;
+            let config_account = next_account_info(account_info_iter)?;
+            let fee_token_account = next_account_info(account_info_iter)?;
+            let deposit_mint = next_account_info(account_info_iter)?;
+
+            assert_escrow_not_settled(escrow_account)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+            if legs.iter().any(|leg| leg.escrow_account.key == escrow_account.key) {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    escrow_account,
+                ));
+            }
+            let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+            // nobody takes a ring's legs, so there's no taker to check a commitment, allowlist or
+            // gate against, and each initializer is paid whole, with nothing to split or settle
+            if escrow_info.features.intersects(RING_UNSUPPORTED_FEATURES)
+                || escrow_info.flags.has_receipt()
+                || escrow_info.flags.is_audited()
+            {
+                return Err(account_info_iter.error(
+                    EscrowError::UnsupportedFeatures,
+                    escrow_account,
+                ));
+            }
+            assert_escrow_token_program(&escrow_info, token_program)
+                .map_err(|e| account_info_iter.error(e, token_program))?;
+            assert_escrow_matches(
+                &escrow_info,
+                account_info_iter,
+                vault,
+                initializers_main_account,
+            )?;
+            assert_vault_authority(program_id, &escrow_info, pda_account)
+                .map_err(|e| account_info_iter.error(e, pda_account))?;
+            if escrow_info.is_past_lifetime(slot) {
+                return Err(account_info_iter.error(EscrowError::EscrowExpired, escrow_account));
+            }
+            if escrow_info.is_cooling_down(slot) {
+                return Err(account_info_iter.error(EscrowError::FillCoolingDown, escrow_account));
+            }
+            let vault_info =
+                unpack_token_account(vault, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, vault))?;
+            if vault_info.amount == 0 {
+                return Err(account_info_iter.error(EscrowError::MintClosed, vault));
+            }
+            if vault_info.mint != escrow_info.deposit_mint {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, vault));
+            }
+            let deposit = escrow_info
+                .deposit(vault_info.amount)
+                .map_err(|e| account_info_iter.error(e, vault))?;
+            let payment = escrow_info
+                .fill_payment(deposit, deposit)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+
+            if escrow_info.initializer_token_to_receive_account_pubkey
+                != *initializers_token_to_receive_account.key
+                && get_associated_token_address_with_program_id(
+                    &escrow_info.initializer_pubkey,
+                    &escrow_info.expected_mint,
+                    token_program.key,
+                ) != *initializers_token_to_receive_account.key
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    initializers_token_to_receive_account,
+                ));
+            }
+            Self::assert_receive_account_usable(
+                initializers_token_to_receive_account,
+                token_program.key,
+                &escrow_info,
+            )
+            .map_err(|e| account_info_iter.error(e, initializers_token_to_receive_account))?;
+
+            if escrow_info.config_pubkey != *config_account.key {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    config_account,
+                ));
+            }
+            let config_info =
+                Self::load_config(config_account, program_id)
+                    .map_err(|e| account_info_iter.error(e, config_account))?;
+            if config_info.paused {
+                return Err(account_info_iter.error(EscrowError::ConfigPaused, config_account));
+            }
+            // a fee mint needs a taker to pay in it, and pair stats would need passing too
+            if config_info.has_fee_mint() || config_info.has_volume_cap() {
+                return Err(account_info_iter.error(
+                    EscrowError::UnsupportedFeatures,
+                    config_account,
+                ));
+            }
+            let deposit_decimals =
+                mint_decimals(deposit_mint, &escrow_info.deposit_mint, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+            // an NFT's royalties are owed out of a payment, which a ring doesn't make
+            if deposit_decimals == 0 && unpack_mint(deposit_mint, token_program.key)?.supply == 1 {
+                return Err(account_info_iter.error(EscrowError::UnsupportedFeatures, deposit_mint));
+            }
+
+            legs.push(RingLeg {
+                escrow_account,
+                vault,
+                initializers_main_account,
+                initializers_token_to_receive_account,
+                pda_account,
+                fee_token_account,
+                deposit_mint,
+                deposit_decimals,
+                vault_amount: vault_info.amount,
+                deposit,
+                payment,
+                fee_bps: config_info.fee_bps_for_age(slot.saturating_sub(escrow_info.init_slot)),
+                fee_recipient: config_info.fee_recipient_pubkey,
+                escrow_info,
+            });
+        }
+
+        // each initializer receives the whole deposit of the escrow after theirs, which must be
+        // in the mint they expect and cover what they asked for
+        let mut receipts = Vec::with_capacity(RING_LEGS);
+        for (i, leg) in legs.iter().enumerate() {
+            let next = &legs[(i + 1) % RING_LEGS];
+            if next.escrow_info.deposit_mint != leg.escrow_info.expected_mint
+                || next.deposit.0 < leg.payment.0
+            {
+                return Err(account_info_iter.error(EscrowError::RingMismatch, leg.escrow_account));
+            }
+            let received = PaymentAmount(next.deposit.0);
+            let proceeds = PaymentAmount(leg.escrow_info.rounding_mode.maker_share(
+                received.0,
+                TOTAL_PAYOUT_BPS.saturating_sub(leg.fee_bps) as u64,
+                TOTAL_PAYOUT_BPS as u64,
+            ));
+            let fee = received
+                .checked_sub(proceeds)
+                .ok_or(EscrowError::AmountOverflow)?;
+            if fee.0 > 0 {
+                let fee_token_info = unpack_token_account(leg.fee_token_account, token_program.key)
+                    .map_err(|e| account_info_iter.error(e, leg.fee_token_account))?;
+                if fee_token_info.owner != leg.fee_recipient
+                    || fee_token_info.mint != leg.escrow_info.expected_mint
+                {
+                    return Err(account_info_iter.error(
+                        ProgramError::InvalidAccountData,
+                        leg.fee_token_account,
+                    ));
+                }
+            }
+            receipts.push((received, proceeds, fee));
+        }
+
+        for (i, (leg, (_, proceeds, fee))) in legs.iter().zip(&receipts).enumerate() {
+            let next = &legs[(i + 1) % RING_LEGS];
+            if fee.0 > 0 {
+                msg!("Calling the token program to transfer the operator fee of leg {}...", i);
+                transfer_from_vault(
+                    token_program,
+                    next.vault,
+                    Some((next.deposit_mint, next.deposit_decimals)),
+                    leg.fee_token_account,
+                    next.pda_account,
+                    account_info_iter.as_slice(),
+                    next.escrow_info.vault_authority_bump,
+                    DepositAmount(fee.0),
+                )?;
+            }
+            msg!("Calling the token program to transfer tokens to the initializer of leg {}...", i);
+            transfer_from_vault(
+                token_program,
+                next.vault,
+                Some((next.deposit_mint, next.deposit_decimals)),
+                leg.initializers_token_to_receive_account,
+                next.pda_account,
+                account_info_iter.as_slice(),
+                next.escrow_info.vault_authority_bump,
+                DepositAmount(proceeds.0),
+            )?;
+        }
+
+        for (i, (leg, (received, _, _))) in legs.into_iter().zip(receipts).enumerate() {
+            let reclaimed_rent =
+                Self::escrow_rent(&leg.escrow_info, leg.escrow_account, leg.vault)?;
+            Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+                .map_err(|e| account_info_iter.error(e, stats_account))?;
+            Self::emit_event(
+                stats_account,
+                AuditAction::Exchange,
+                leg.escrow_account.key,
+                matcher.key,
+                leg.escrow_info.deposit_mint_risk,
+            )?;
+            let fill_event = FillEvent {
+                escrow_pubkey: *leg.escrow_account.key,
+                fill: leg.deposit,
+                payment: received,
+                max_payment: PaymentAmount(0),
+                price_improvement: PaymentAmount(0),
+            };
+            sol_log_data(&[&fill_event.pack()]);
+
+            // a shared vault stays open for the initializer's other escrows until it's empty
+            if leg.vault_amount == leg.deposit.0 {
+                msg!("Calling the token program to close the vault of leg {}...", i);
+                close_vault(
+                    token_program,
+                    leg.vault,
+                    leg.initializers_main_account,
+                    leg.pda_account,
+                    leg.escrow_info.vault_authority_bump,
+                )?;
+            }
+            close_state_account(leg.escrow_account, leg.initializers_main_account)?;
+            assert_one_leg_after_fill(
+                leg.escrow_account,
+                leg.vault,
+                token_program.key,
+                leg.vault_amount,
+                leg.deposit,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn process_deposit_initializer(
+        accounts: &[AccountInfo],
+        taker: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        // Settle pays the initializer whole, with no receipt holder or audit log to account to
+        if escrow_info.flags.has_receipt() || escrow_info.flags.is_audited() {
+            return Err(account_info_iter.error(EscrowError::UnsupportedFeatures, escrow_account));
+        }
+        let features =
+            EscrowFeatures::from_bits(escrow_info.features.bits() | EscrowFeatures::MUTUAL_DEPOSIT)
+                .ok_or_else(|| {
+                    account_info_iter.error(EscrowError::UnsupportedFeatures, escrow_account)
+                })?;
+
+        let mutual_deposit_account = next_account_info(account_info_iter)?;
+        let taker_vault = next_account_info(account_info_iter)?;
+        let expected_mint = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        let associated_token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        if escrow_info.expected_mint != *expected_mint.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, expected_mint));
+        }
+        // an NFT's royalties are owed out of a payment Settle doesn't split
+        let deposit_decimals =
+            mint_decimals(deposit_mint, &escrow_info.deposit_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+        if deposit_decimals == 0 && unpack_mint(deposit_mint, token_program.key)?.supply == 1 {
+            return Err(account_info_iter.error(EscrowError::UnsupportedFeatures, deposit_mint));
+        }
+
+        let (mutual_deposit_pda, bump) =
+            Pubkey::find_program_address(&[b"mutual", escrow_account.key.as_ref()], program_id);
+        if *mutual_deposit_account.key != mutual_deposit_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, mutual_deposit_account));
+        }
+        if mutual_deposit_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                mutual_deposit_account,
+            ));
+        }
+        if get_associated_token_address_with_program_id(
+            &mutual_deposit_pda,
+            expected_mint.key,
+            token_program.key,
+        ) != *taker_vault.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, taker_vault));
+        }
+
+        let create_mutual_deposit_ix = system_instruction::create_account(
+            initializer.key,
+            mutual_deposit_account.key,
+            Rent::get()?.minimum_balance(MutualDeposit::LEN),
+            MutualDeposit::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the mutual deposit...");
+        invoke_signed(
+            &create_mutual_deposit_ix,
+            &[
+                initializer.clone(),
+                mutual_deposit_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[&b"mutual"[..], escrow_account.key.as_ref(), &[bump]]],
+        )?;
+
+        msg!("Calling the associated token account program to create the taker vault...");
+        invoke(
+            &create_associated_token_account(
+                initializer.key,
+                mutual_deposit_account.key,
+                expected_mint.key,
+                token_program.key,
+            ),
+            &[
+                initializer.clone(),
+                taker_vault.clone(),
+                mutual_deposit_account.clone(),
+                expected_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                associated_token_program.clone(),
+            ],
+        )?;
+
+        MutualDeposit::pack(
+            MutualDeposit {
+                is_initialized: true,
+                escrow_pubkey: *escrow_account.key,
+                taker_pubkey: taker,
+                taker_token_to_receive_account_pubkey: Pubkey::default(),
+                taker_refund_account_pubkey: Pubkey::default(),
+                taker_deposit: PaymentAmount(0),
+                bump,
+            },
+            &mut mutual_deposit_account.try_borrow_mut_data()?,
+        )?;
+        escrow_info.features = features;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_deposit_taker(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let taker = next_account_info(account_info_iter)?;
+
+        if !taker.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, taker));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.is_past_lifetime(Clock::get()?.slot) {
+            return Err(account_info_iter.error(EscrowError::EscrowExpired, escrow_account));
+        }
+
+        let mutual_deposit_account = next_account_info(account_info_iter)?;
+        let mut mutual_deposit_info =
+            Self::load_mutual_deposit(mutual_deposit_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, mutual_deposit_account))?;
+        if mutual_deposit_info.taker_pubkey != *taker.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, taker));
+        }
+        if mutual_deposit_info.is_funded() {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                mutual_deposit_account,
+            ));
+        }
+
+        let takers_sending_token_account = next_account_info(account_info_iter)?;
+        let taker_vault = next_account_info(account_info_iter)?;
+        let takers_token_to_receive_account = next_account_info(account_info_iter)?;
+        let expected_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+
+        if get_associated_token_address_with_program_id(
+            mutual_deposit_account.key,
+            &escrow_info.expected_mint,
+            token_program.key,
+        ) != *taker_vault.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, taker_vault));
+        }
+        // checked now so Settle can't be left paying the deposit into an account that isn't
+        // the taker's
+        let takers_receive_info =
+            unpack_token_account(takers_token_to_receive_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, takers_token_to_receive_account))?;
+        if takers_receive_info.owner != *taker.key
+            || takers_receive_info.mint != escrow_info.deposit_mint
+        {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                takers_token_to_receive_account,
+            ));
+        }
+        let expected_decimals =
+            mint_decimals(expected_mint, &escrow_info.expected_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, expected_mint))?;
+
+        msg!("Calling the token program to transfer the taker's deposit...");
+        invoke_transfer_checked(
+            token_program,
+            takers_sending_token_account,
+            expected_mint,
+            taker_vault,
+            taker,
+            account_info_iter.as_slice(),
+            escrow_info.expected_amount.0,
+            expected_decimals,
+            &[],
+        )?;
+
+        mutual_deposit_info.taker_token_to_receive_account_pubkey = *takers_token_to_receive_account.key;
+        mutual_deposit_info.taker_refund_account_pubkey = *takers_sending_token_account.key;
+        mutual_deposit_info.taker_deposit = escrow_info.expected_amount;
+        MutualDeposit::pack(
+            mutual_deposit_info,
+            &mut mutual_deposit_account.try_borrow_mut_data()?,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_settle(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let settler = next_account_info(account_info_iter)?;
+
+        if !settler.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, settler));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let mutual_deposit_account = next_account_info(account_info_iter)?;
+        let taker_vault = next_account_info(account_info_iter)?;
+        let initializers_main_account = next_account_info(account_info_iter)?;
+        let initializers_token_to_receive_account = next_account_info(account_info_iter)?;
+        let takers_token_to_receive_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let stats_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let fee_token_account = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let expected_mint = next_account_info(account_info_iter)?;
+
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        assert_escrow_matches(&escrow_info, account_info_iter, vault, initializers_main_account)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account)
+            .map_err(|e| account_info_iter.error(e, pda_account))?;
+
+        let mutual_deposit_info =
+            Self::load_mutual_deposit(mutual_deposit_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, mutual_deposit_account))?;
+        if !mutual_deposit_info.is_funded() {
+            return Err(account_info_iter.error(
+                EscrowError::TakerDepositMissing,
+                mutual_deposit_account,
+            ));
+        }
+        // both sides are committed once the taker deposits, so expiry no longer stops the swap
+        // and the taker can always get their deposit's worth out
+        if mutual_deposit_info.taker_deposit != escrow_info.expected_amount {
+            return Err(account_info_iter.error(
+                EscrowError::ExpectedAmountMismatch,
+                mutual_deposit_account,
+            ));
+        }
+        if mutual_deposit_info.taker_token_to_receive_account_pubkey
+            != *takers_token_to_receive_account.key
+        {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                takers_token_to_receive_account,
+            ));
+        }
+        if get_associated_token_address_with_program_id(
+            mutual_deposit_account.key,
+            &escrow_info.expected_mint,
+            token_program.key,
+        ) != *taker_vault.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, taker_vault));
+        }
+
+        let vault_info =
+            unpack_token_account(vault, token_program.key)
+                .map_err(|e| account_info_iter.error(e, vault))?;
+        if vault_info.mint != escrow_info.deposit_mint {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, vault));
+        }
+        let deposit = escrow_info
+            .deposit(vault_info.amount)
+            .map_err(|e| account_info_iter.error(e, vault))?;
+
+        if escrow_info.initializer_token_to_receive_account_pubkey
+            != *initializers_token_to_receive_account.key
+            && get_associated_token_address_with_program_id(
+                &escrow_info.initializer_pubkey,
+                &escrow_info.expected_mint,
+                token_program.key,
+            ) != *initializers_token_to_receive_account.key
+        {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                initializers_token_to_receive_account,
+            ));
+        }
+        Self::assert_receive_account_usable(
+            initializers_token_to_receive_account,
+            token_program.key,
+            &escrow_info,
+        )
+        .map_err(|e| account_info_iter.error(e, initializers_token_to_receive_account))?;
+
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.paused {
+            return Err(account_info_iter.error(EscrowError::ConfigPaused, config_account));
+        }
+        // a fee mint needs a taker to pay in it at Settle, and pair stats would need passing too
+        if config_info.has_fee_mint() || config_info.has_volume_cap() {
+            return Err(account_info_iter.error(EscrowError::UnsupportedFeatures, config_account));
+        }
+        let fee_bps =
+            config_info.fee_bps_for_age(Clock::get()?.slot.saturating_sub(escrow_info.init_slot));
+        let payment = mutual_deposit_info.taker_deposit;
+        let proceeds = PaymentAmount(escrow_info.rounding_mode.maker_share(
+            payment.0,
+            TOTAL_PAYOUT_BPS.saturating_sub(fee_bps) as u64,
+            TOTAL_PAYOUT_BPS as u64,
+        ));
+        let fee = payment.checked_sub(proceeds).ok_or(EscrowError::AmountOverflow)?;
+        if fee.0 > 0 {
+            let fee_token_info = unpack_token_account(fee_token_account, token_program.key)
+                .map_err(|e| account_info_iter.error(e, fee_token_account))?;
+            if fee_token_info.owner != config_info.fee_recipient_pubkey
+                || fee_token_info.mint != escrow_info.expected_mint
+            {
+                return Err(account_info_iter.error(
+                    ProgramError::InvalidAccountData,
+                    fee_token_account,
+                ));
+            }
+        }
+        let deposit_decimals =
+            mint_decimals(deposit_mint, &escrow_info.deposit_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+        let expected_decimals =
+            mint_decimals(expected_mint, &escrow_info.expected_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, expected_mint))?;
+
+        msg!("Calling the token program to transfer the deposit to the taker...");
+        transfer_from_vault(
+            token_program,
+            vault,
+            Some((deposit_mint, deposit_decimals)),
+            takers_token_to_receive_account,
+            pda_account,
+            account_info_iter.as_slice(),
+            escrow_info.vault_authority_bump,
+            deposit,
+        )?;
+        Self::release_mutual_deposit(
+            mutual_deposit_account,
+            &mutual_deposit_info,
+            taker_vault,
+            expected_mint,
+            expected_decimals,
+            &[(fee_token_account, fee), (initializers_token_to_receive_account, proceeds)],
+            token_program,
+            initializers_main_account,
+            account_info_iter.as_slice(),
+        )?;
+
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, vault)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::Exchange,
+            escrow_account.key,
+            &mutual_deposit_info.taker_pubkey,
+            escrow_info.deposit_mint_risk,
+        )?;
+        let fill_event = FillEvent {
+            escrow_pubkey: *escrow_account.key,
+            fill: deposit,
+            payment,
+            max_payment: PaymentAmount(0),
+            price_improvement: PaymentAmount(0),
+        };
+        sol_log_data(&[&fill_event.pack()]);
+
+        // a shared vault stays open for the initializer's other escrows until it's empty
+        if vault_info.amount == deposit.0 {
+            msg!("Calling the token program to close the escrow token account...");
+            close_vault(
+                token_program,
+                vault,
+                initializers_main_account,
+                pda_account,
+                escrow_info.vault_authority_bump,
+            )?;
+        }
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializers_main_account)?;
+        assert_one_leg_after_fill(escrow_account, vault, token_program.key, vault_info.amount, deposit);
+
+        Ok(())
+    }
+
+    fn process_redeem(
+        accounts: &[AccountInfo],
+        preimage: &[u8; 32],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let recipient = next_account_info(account_info_iter)?;
+
+        if !recipient.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, recipient));
+        }
+
+        let recipients_token_to_receive_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let initializers_main_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let stats_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if !escrow_info.is_hash_locked() {
+            return Err(account_info_iter.error(EscrowError::UnsupportedFeatures, escrow_account));
+        }
+        // the preimage is public once sent, so only the recipient's signature releases the deposit
+        if escrow_info.hash_lock.recipient != *recipient.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, recipient));
+        }
+        if !escrow_info.hash_lock.unlocks(preimage) {
+            return Err(account_info_iter.error(EscrowError::PreimageMismatch, escrow_account));
+        }
+        // from expiry the deposit belongs to the initializer's refund, even before they cancel
+        let current_slot = Clock::get()?.slot;
+        if current_slot >= escrow_info.expiry_slot() || escrow_info.is_past_lifetime(current_slot) {
+            return Err(account_info_iter.error(EscrowError::EscrowExpired, escrow_account));
+        }
+        assert_escrow_token_program(&escrow_info, token_program)
+            .map_err(|e| account_info_iter.error(e, token_program))?;
+        assert_escrow_matches(&escrow_info, account_info_iter, vault, initializers_main_account)?;
+        assert_vault_authority(program_id, &escrow_info, pda_account)
+            .map_err(|e| account_info_iter.error(e, pda_account))?;
+
+        let vault_info =
+            unpack_token_account(vault, token_program.key)
+                .map_err(|e| account_info_iter.error(e, vault))?;
+        if vault_info.mint != escrow_info.deposit_mint {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, vault));
+        }
+        let deposit = escrow_info
+            .deposit(vault_info.amount)
+            .map_err(|e| account_info_iter.error(e, vault))?;
+
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.paused {
+            return Err(account_info_iter.error(EscrowError::ConfigPaused, config_account));
+        }
+        let deposit_decimals =
+            mint_decimals(deposit_mint, &escrow_info.deposit_mint, token_program.key)
+                .map_err(|e| account_info_iter.error(e, deposit_mint))?;
+
+        msg!("Calling the token program to transfer the deposit to the recipient...");
+        transfer_from_vault(
+            token_program,
+            vault,
+            Some((deposit_mint, deposit_decimals)),
+            recipients_token_to_receive_account,
+            pda_account,
+            account_info_iter.as_slice(),
+            escrow_info.vault_authority_bump,
+            deposit,
+        )?;
+
+        let reclaimed_rent = Self::escrow_rent(&escrow_info, escrow_account, vault)?;
+        Self::record_escrow_rent(stats_account, program_id, false, reclaimed_rent)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::Exchange,
+            escrow_account.key,
+            recipient.key,
+            escrow_info.deposit_mint_risk,
+        )?;
+        if escrow_info.flags.is_audited() {
+            let audit_log_account = next_account_info(account_info_iter)?;
+            Self::record_audit_entry(
+                audit_log_account,
+                &escrow_info.config_pubkey,
+                program_id,
+                AuditAction::Exchange,
+                escrow_account.key,
+                recipient.key,
+            )
+            .map_err(|e| account_info_iter.error(e, audit_log_account))?;
+        }
+        // the payment for it settles on the other chain, so there is none to report here
+        let fill_event = FillEvent {
+            escrow_pubkey: *escrow_account.key,
+            fill: deposit,
+            payment: PaymentAmount(0),
+            max_payment: PaymentAmount(0),
+            price_improvement: PaymentAmount(0),
+        };
+        sol_log_data(&[&fill_event.pack()]);
+
+        // a shared vault stays open for the initializer's other escrows until it's empty
+        if vault_info.amount == deposit.0 {
+            msg!("Calling the token program to close the escrow token account...");
+            close_vault(
+                token_program,
+                vault,
+                initializers_main_account,
+                pda_account,
+                escrow_info.vault_authority_bump,
+            )?;
+        }
+        msg!("Closing the escrow account...");
+        close_state_account(escrow_account, initializers_main_account)?;
+        assert_one_leg_after_fill(escrow_account, vault, token_program.key, vault_info.amount, deposit);
+
+        Ok(())
+    }
+
+    /// Reprices the escrow to the permit's payment, then fills it as an Exchange would, with
+    /// the same accounts and account indices. A failed fill reverts the repricing with it.
+    fn process_exchange_with_permit(
+        accounts: &[AccountInfo],
+        amount_expected_by_taker: DepositAmount,
+        payment: PaymentAmount,
+        valid_until_slot: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (instructions_sysvar, exchange_accounts) =
+            accounts.split_last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let cursor = AccountCursor::new(accounts);
+        if !sysvar::instructions::check_id(instructions_sysvar.key) {
+            return Err(cursor.error(ProgramError::InvalidArgument, instructions_sysvar));
+        }
+        let taker = exchange_accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let escrow_account = exchange_accounts.get(6).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let config_account = exchange_accounts.get(10).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        assert_escrow_not_settled(escrow_account).map_err(|e| cursor.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(cursor.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| cursor.error(e, escrow_account))?;
+        // the receipt holder bought the position at the escrow's price
+        if escrow_info.flags.has_receipt() {
+            return Err(cursor.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+        if escrow_info.is_mutual_deposit() {
+            return Err(cursor.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        // a taker who filled part of it did so at the price of the whole
+        if escrow_info.filled_amount.0 != 0 {
+            return Err(cursor.error(EscrowError::EscrowPartiallyFilled, escrow_account));
+        }
+        // its pass-through accounts would run into the instructions sysvar, and an oracle
+        // already prices the fill
+        if escrow_info.has_settlement_hook() || escrow_info.is_oracle_priced() {
+            return Err(cursor.error(EscrowError::UnsupportedFeatures, escrow_account));
+        }
+
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(cursor.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| cursor.error(e, config_account))?;
+        if !config_info.allows_expected_amount(payment) {
+            return Err(EscrowError::AmountOutOfBounds.into());
+        }
+
+        let permit = Permit {
+            escrow: *escrow_account.key,
+            init_slot: escrow_info.init_slot,
+            taker: *taker.key,
+            payment,
+            valid_until_slot,
+        };
+        let (permit_ix_index, permit_ix) =
+            load_previous_instruction(instructions_sysvar)
+                .map_err(|e| cursor.error(e, instructions_sysvar))?;
+        if permit_ix.program_id == secp256k1_program::id() {
+            assert_secp256k1_signed(
+                &permit_ix,
+                permit_ix_index,
+                &escrow_info.permit_eth_signer,
+                &permit.eth_message(program_id),
+            )
+        } else {
+            assert_ed25519_signed(&permit_ix, &escrow_info.initializer_pubkey, &permit.message(program_id))
+        }
+        .map_err(|e| cursor.error(e, instructions_sysvar))?;
+
+        escrow_info.expected_amount = payment;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Self::process_exchange(
+            exchange_accounts,
+            amount_expected_by_taker,
+            None,
+            valid_until_slot,
+            DepositAmount(0),
+            payment,
+            false,
+            program_id,
+        )
+    }
+
+    fn process_init_stats(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let payer = next_account_info(account_info_iter)?;
+
+        if !payer.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, payer));
+        }
+
+        let stats_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (stats_pda, bump) = Pubkey::find_program_address(&[b"stats"], program_id);
+        if *stats_account.key != stats_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, stats_account));
+        }
+        if stats_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                stats_account,
+            ));
+        }
+
+        let create_stats_ix = system_instruction::create_account(
+            payer.key,
+            stats_account.key,
+            Rent::get()?.minimum_balance(EscrowStats::LEN),
+            EscrowStats::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the stats account...");
+        invoke_signed(
+            &create_stats_ix,
+            &[
+                payer.clone(),
+                stats_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[&b"stats"[..], &[bump]]],
+        )?;
+
+        let stats_info = EscrowStats {
+            is_initialized: true,
+            open_escrows: 0,
+            locked_rent: 0,
+            event_sequence: 0,
+            bump,
+        };
+        EscrowStats::pack(stats_info, &mut stats_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_init_config(
+        accounts: &[AccountInfo],
+        fee_bps: u16,
+        min_expected_amount: PaymentAmount,
+        max_expected_amount: PaymentAmount,
+        fee_tiers: &[FeeTier],
+        max_lifetime_slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let fee_recipient = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (config_pda, bump) =
+            Pubkey::find_program_address(&[b"config", admin.key.as_ref()], program_id);
+        if *config_account.key != config_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, config_account));
+        }
+        if config_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                config_account,
+            ));
+        }
+        Self::validate_config_params(fee_bps, min_expected_amount, max_expected_amount, fee_tiers)?;
+
+        let create_config_ix = system_instruction::create_account(
+            admin.key,
+            config_account.key,
+            Rent::get()?.minimum_balance(Config::LEN),
+            Config::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the config account...");
+        invoke_signed(
+            &create_config_ix,
+            &[
+                admin.clone(),
+                config_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[&b"config"[..], admin.key.as_ref(), &[bump]]],
+        )?;
+
+        let mut config_info = Config {
+            is_initialized: true,
+            admin_pubkey: *admin.key,
+            fee_bps,
+            fee_recipient_pubkey: *fee_recipient.key,
+            min_expected_amount,
+            max_expected_amount,
+            fee_tier_count: fee_tiers.len() as u8,
+            fee_tiers: [FeeTier::default(); MAX_FEE_TIERS],
+            audit_log_enabled: false,
+            max_lifetime_slots,
+            fee_mint: Pubkey::default(),
+            fee_mint_rate: 0,
+            fee_rate_oracle: Pubkey::default(),
+            gated_roles: 0,
+            settlement_program: Pubkey::default(),
+            volume_window_slots: 0,
+            max_window_volume: 0,
+            paused: false,
+            min_unlock_slots: 0,
+            max_unlock_slots: 0,
+            max_inits_per_slot: 0,
+        };
+        config_info.fee_tiers[..fee_tiers.len()].copy_from_slice(fee_tiers);
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_update_config(
+        accounts: &[AccountInfo],
+        fee_bps: u16,
+        min_expected_amount: PaymentAmount,
+        max_expected_amount: PaymentAmount,
+        fee_tiers: &[FeeTier],
+        max_lifetime_slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let fee_recipient = next_account_info(account_info_iter)?;
+
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        Self::validate_config_params(fee_bps, min_expected_amount, max_expected_amount, fee_tiers)?;
+
+        config_info.fee_bps = fee_bps;
+        config_info.fee_recipient_pubkey = *fee_recipient.key;
+        config_info.min_expected_amount = min_expected_amount;
+        config_info.max_expected_amount = max_expected_amount;
+        config_info.fee_tier_count = fee_tiers.len() as u8;
+        config_info.fee_tiers = [FeeTier::default(); MAX_FEE_TIERS];
+        config_info.fee_tiers[..fee_tiers.len()].copy_from_slice(fee_tiers);
+        config_info.max_lifetime_slots = max_lifetime_slots;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_fee_mint(
+        accounts: &[AccountInfo],
+        fee_mint_rate: u64,
+        fee_rate_oracle: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        match account_info_iter.next() {
+            Some(fee_mint) => {
+                unpack_mint(fee_mint, fee_mint.owner)
+                    .map_err(|e| account_info_iter.error(e, fee_mint))?;
+                config_info.fee_mint = *fee_mint.key;
+                config_info.fee_mint_rate = fee_mint_rate;
+                config_info.fee_rate_oracle = fee_rate_oracle;
+            }
+            None => {
+                config_info.fee_mint = Pubkey::default();
+                config_info.fee_mint_rate = 0;
+                config_info.fee_rate_oracle = Pubkey::default();
+            }
+        }
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_settlement_program(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        config_info.settlement_program = match account_info_iter.next() {
+            Some(settlement_program) => {
+                // the hook runs with the settlement PDA's signature, never hand that to a
+                // program that could turn it into a token transfer or reenter this one
+                if !settlement_program.executable
+                    || is_token_program(settlement_program.key)
+                    || settlement_program.key == program_id
+                {
+                    return Err(account_info_iter.error(
+                        ProgramError::IncorrectProgramId,
+                        settlement_program,
+                    ));
+                }
+                *settlement_program.key
+            }
+            None => Pubkey::default(),
+        };
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_volume_cap(
+        accounts: &[AccountInfo],
+        volume_window_slots: u64,
+        max_window_volume: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        if max_window_volume != 0 && volume_window_slots == 0 {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+
+        config_info.volume_window_slots = volume_window_slots;
+        config_info.max_window_volume = max_window_volume;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_init_rate_limit(
+        accounts: &[AccountInfo],
+        max_inits_per_slot: u32,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        config_info.max_inits_per_slot = max_inits_per_slot;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_paused(accounts: &[AccountInfo], paused: bool, program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        config_info.paused = paused;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_unlock_bounds(
+        accounts: &[AccountInfo],
+        min_unlock_slots: u64,
+        max_unlock_slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        config_info.min_unlock_slots = min_unlock_slots;
+        config_info.max_unlock_slots = max_unlock_slots;
+        if config_info.unlock_slots_bounds().is_empty() {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_add_allowed_taker(
+        accounts: &[AccountInfo],
+        taker: &Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        let taker_allowlist_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (taker_allowlist_pda, bump) =
+            Pubkey::find_program_address(&[b"takers", escrow_account.key.as_ref()], program_id);
+        if *taker_allowlist_account.key != taker_allowlist_pda {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidSeeds,
+                taker_allowlist_account,
+            ));
+        }
+
+        if taker_allowlist_account.owner != program_id {
+            let create_taker_allowlist_ix = system_instruction::create_account(
+                initializer.key,
+                taker_allowlist_account.key,
+                Rent::get()?.minimum_balance(TakerAllowlist::LEN),
+                TakerAllowlist::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the taker allowlist...");
+            invoke_signed(
+                &create_taker_allowlist_ix,
+                &[
+                    initializer.clone(),
+                    taker_allowlist_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[&b"takers"[..], escrow_account.key.as_ref(), &[bump]]],
+            )?;
+        }
+
+        let mut taker_allowlist_info =
+            TakerAllowlist::unpack_unchecked(&taker_allowlist_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, taker_allowlist_account))?;
+        // a list left behind by an earlier escrow at the same address doesn't carry over
+        if !escrow_info.has_taker_allowlist() {
+            taker_allowlist_info = TakerAllowlist {
+                is_initialized: true,
+                escrow_pubkey: *escrow_account.key,
+                initializer_pubkey: *initializer.key,
+                bump,
+                taker_count: 0,
+                takers: [Pubkey::default(); MAX_ALLOWED_TAKERS],
+            };
+        }
+        taker_allowlist_info
+            .add(taker)
+            .map_err(|e| account_info_iter.error(e, taker_allowlist_account))?;
+        TakerAllowlist::pack(taker_allowlist_info, &mut taker_allowlist_account.try_borrow_mut_data()?)?;
+
+        if !escrow_info.has_taker_allowlist() {
+            escrow_info.features = EscrowFeatures::from_bits(
+                escrow_info.features.bits() | EscrowFeatures::TAKER_ALLOWLIST,
+            )
+            .ok_or(EscrowError::UnsupportedFeatures)?;
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_remove_allowed_taker(
+        accounts: &[AccountInfo],
+        taker: &Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let taker_allowlist_account = next_account_info(account_info_iter)?;
+        let mut taker_allowlist_info =
+            Self::load_taker_allowlist(taker_allowlist_account, escrow_account.key, program_id)
+                .map_err(|e| account_info_iter.error(e, taker_allowlist_account))?;
+        if taker_allowlist_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        // settling closes the escrow but leaves its allowlist to be cleaned up here
+        if assert_escrow_not_settled(escrow_account).is_err() {
+            return close_state_account(taker_allowlist_account, initializer);
+        }
+        if escrow_account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        taker_allowlist_info.remove(taker);
+        TakerAllowlist::pack(taker_allowlist_info, &mut taker_allowlist_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_make_counter_offer(
+        accounts: &[AccountInfo],
+        expected_amount: PaymentAmount,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let taker = next_account_info(account_info_iter)?;
+
+        if !taker.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, taker));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let counter_offer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (counter_offer_pda, bump) = Pubkey::find_program_address(
+            &[b"counter_offer", escrow_account.key.as_ref(), taker.key.as_ref()],
+            program_id,
+        );
+        if *counter_offer_account.key != counter_offer_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, counter_offer_account));
+        }
+
+        if expected_amount.0 == 0 {
+            if counter_offer_account.owner != program_id {
+                return Err(account_info_iter.error(
+                    ProgramError::UninitializedAccount,
+                    counter_offer_account,
+                ));
+            }
+            return close_state_account(counter_offer_account, taker);
+        }
+
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+
+        if counter_offer_account.owner != program_id {
+            let create_counter_offer_ix = system_instruction::create_account(
+                taker.key,
+                counter_offer_account.key,
+                Rent::get()?.minimum_balance(CounterOffer::LEN),
+                CounterOffer::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the counter-offer...");
+            invoke_signed(
+                &create_counter_offer_ix,
+                &[
+                    taker.clone(),
+                    counter_offer_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    &b"counter_offer"[..],
+                    escrow_account.key.as_ref(),
+                    taker.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+        }
+
+        let counter_offer_info = CounterOffer {
+            is_initialized: true,
+            escrow_pubkey: *escrow_account.key,
+            taker_pubkey: *taker.key,
+            expected_amount,
+            filled_amount: escrow_info.filled_amount,
+            bump,
+        };
+        CounterOffer::pack(counter_offer_info, &mut counter_offer_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_accept_counter_offer(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        // the receipt holder bought the position at the escrow's price
+        if escrow_info.flags.has_receipt() {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+        // the named taker deposits exactly the expected amount
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+
+        let counter_offer_account = next_account_info(account_info_iter)?;
+        let taker = next_account_info(account_info_iter)?;
+        if counter_offer_account.owner != program_id {
+            return Err(account_info_iter.error(
+                ProgramError::UninitializedAccount,
+                counter_offer_account,
+            ));
+        }
+        let counter_offer_info = CounterOffer::unpack(&counter_offer_account.try_borrow_data()?)
+            .map_err(|e| account_info_iter.error(e, counter_offer_account))?;
+        let counter_offer_pda = pda_with_bump(
+            program_id,
+            &[
+                b"counter_offer",
+                escrow_account.key.as_ref(),
+                taker.key.as_ref(),
+                &[counter_offer_info.bump],
+            ],
+        )
+        .map_err(|e| account_info_iter.error(e, counter_offer_account))?;
+        if *counter_offer_account.key != counter_offer_pda
+            || counter_offer_info.escrow_pubkey != *escrow_account.key
+            || counter_offer_info.taker_pubkey != *taker.key
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, counter_offer_account));
+        }
+        if counter_offer_info.filled_amount != escrow_info.filled_amount {
+            return Err(account_info_iter.error(
+                EscrowError::CounterOfferOutdated,
+                counter_offer_account,
+            ));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if !config_info.allows_expected_amount(counter_offer_info.expected_amount) {
+            return Err(EscrowError::AmountOutOfBounds.into());
+        }
+
+        escrow_info.expected_amount = counter_offer_info.expected_amount;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        close_state_account(counter_offer_account, taker)?;
+
+        Ok(())
+    }
+
+    fn process_set_permit_eth_signer(
+        accounts: &[AccountInfo],
+        eth_address: [u8; 20],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+
+        escrow_info.permit_eth_signer = eth_address;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_update_expected_amount(
+        accounts: &[AccountInfo],
+        expected_amount: PaymentAmount,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        assert_escrow_not_settled(escrow_account)
+            .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_account.owner != program_id || !escrow_account.is_writable {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let mut escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        // the receipt holder bought the position at the escrow's price
+        if escrow_info.flags.has_receipt() {
+            return Err(account_info_iter.error(EscrowError::ReceiptNotAllowed, escrow_account));
+        }
+        // the named taker deposits exactly the expected amount
+        if escrow_info.is_mutual_deposit() {
+            return Err(account_info_iter.error(EscrowError::MutualDepositOnly, escrow_account));
+        }
+        // a taker who filled part of it did so at the price of the whole
+        if escrow_info.filled_amount.0 != 0 {
+            return Err(account_info_iter.error(EscrowError::EscrowPartiallyFilled, escrow_account));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        if escrow_info.config_pubkey != *config_account.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, config_account));
+        }
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if !config_info.allows_expected_amount(expected_amount) {
+            return Err(EscrowError::AmountOutOfBounds.into());
+        }
+
+        escrow_info.expected_amount = expected_amount;
+        Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_escrow_tags(
+        accounts: &[AccountInfo],
+        tags: Vec<EscrowTag>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let escrow_account = next_account_info(account_info_iter)?;
+        let escrow_tags_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let stats_account = next_account_info(account_info_iter)?;
+
+        let (escrow_tags_pda, bump) =
+            Pubkey::find_program_address(&[b"tags", escrow_account.key.as_ref()], program_id);
+        if *escrow_tags_account.key != escrow_tags_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, escrow_tags_account));
+        }
+
+        let open_escrow = if assert_escrow_not_settled(escrow_account).is_ok()
+            && escrow_account.owner == program_id
+        {
+            Some(Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?)
+        } else {
+            None
+        };
+
+        if tags.is_empty() {
+            if escrow_tags_account.owner != program_id {
+                return Err(account_info_iter.error(
+                    ProgramError::UninitializedAccount,
+                    escrow_tags_account,
+                ));
+            }
+            let escrow_tags_info = EscrowTags::unpack(&escrow_tags_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_tags_account))?;
+            if escrow_tags_info.initializer_pubkey != *initializer.key {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+            }
+            // tags stay for as long as their escrow does
+            if matches!(&open_escrow, Some(escrow_info)
+                if escrow_tags_info.belong_to(escrow_account.key, escrow_info.init_slot))
+            {
+                return Err(account_info_iter.error(
+                    EscrowError::EscrowTagsFixed,
+                    escrow_tags_account,
+                ));
+            }
+            return close_state_account(escrow_tags_account, initializer);
+        }
+
+        let escrow_info = open_escrow
+            .ok_or_else(|| {
+                account_info_iter.error(EscrowError::EscrowAlreadySettled, escrow_account)
+            })?;
+        if escrow_info.initializer_pubkey != *initializer.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, initializer));
+        }
+        if Clock::get()?.slot != escrow_info.init_slot {
+            return Err(account_info_iter.error(EscrowError::EscrowTagsFixed, escrow_account));
+        }
+        for (i, tag) in tags.iter().enumerate() {
+            if tag.key == EscrowTag::default().key || tags[..i].iter().any(|other| other.key == tag.key) {
+                return Err(EscrowError::InvalidEscrowTags.into());
+            }
+        }
+
+        if escrow_tags_account.owner != program_id {
+            let create_escrow_tags_ix = system_instruction::create_account(
+                initializer.key,
+                escrow_tags_account.key,
+                Rent::get()?.minimum_balance(EscrowTags::LEN),
+                EscrowTags::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the escrow tags...");
+            invoke_signed(
+                &create_escrow_tags_ix,
+                &[
+                    initializer.clone(),
+                    escrow_tags_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[&b"tags"[..], escrow_account.key.as_ref(), &[bump]]],
+            )?;
+        }
+
+        let escrow_tags_info = EscrowTags::unpack_unchecked(&escrow_tags_account.try_borrow_data()?)
+            .map_err(|e| account_info_iter.error(e, escrow_tags_account))?;
+        // tags left behind by an earlier escrow at the same address are simply overwritten
+        if escrow_tags_info.belong_to(escrow_account.key, escrow_info.init_slot) {
+            return Err(account_info_iter.error(EscrowError::EscrowTagsFixed, escrow_tags_account));
+        }
+        let mut packed_tags = [EscrowTag::default(); MAX_ESCROW_TAGS];
+        packed_tags[..tags.len()].copy_from_slice(&tags);
+        EscrowTags::pack(
+            EscrowTags {
+                is_initialized: true,
+                escrow_pubkey: *escrow_account.key,
+                initializer_pubkey: *initializer.key,
+                init_slot: escrow_info.init_slot,
+                bump,
+                tag_count: tags.len() as u8,
+                tags: packed_tags,
+            },
+            &mut escrow_tags_account.try_borrow_mut_data()?,
+        )?;
+
+        Self::load_stats(stats_account, program_id)
+            .map_err(|e| account_info_iter.error(e, stats_account))?;
+        Self::emit_event(
+            stats_account,
+            AuditAction::SetEscrowTags,
+            escrow_account.key,
+            initializer.key,
+            escrow_info.deposit_mint_risk,
+        )?;
+        // the whole account, so indexers read the tags the same way from logs and from memcmp
+        sol_log_data(&[&escrow_tags_account.try_borrow_data()?]);
+
+        Ok(())
+    }
+
+    fn process_init_pair_stats(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let payer = next_account_info(account_info_iter)?;
+
+        if !payer.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, payer));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let pair_stats_account = next_account_info(account_info_iter)?;
+        let deposit_mint = next_account_info(account_info_iter)?;
+        let expected_mint = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        Self::load_config(config_account, program_id)
+            .map_err(|e| account_info_iter.error(e, config_account))?;
+        for mint in [deposit_mint, expected_mint] {
+            unpack_mint(mint, mint.owner).map_err(|e| account_info_iter.error(e, mint))?;
+        }
+
+        let (pair_stats_pda, bump) = Pubkey::find_program_address(
+            &[
+                b"pair_stats",
+                config_account.key.as_ref(),
+                deposit_mint.key.as_ref(),
+                expected_mint.key.as_ref(),
+            ],
+            program_id,
+        );
+        if *pair_stats_account.key != pair_stats_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, pair_stats_account));
+        }
+        if pair_stats_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                pair_stats_account,
+            ));
+        }
+
+        let create_pair_stats_ix = system_instruction::create_account(
+            payer.key,
+            pair_stats_account.key,
+            Rent::get()?.minimum_balance(PairStats::LEN),
+            PairStats::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the pair stats account...");
+        invoke_signed(
+            &create_pair_stats_ix,
+            &[
+                payer.clone(),
+                pair_stats_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                &b"pair_stats"[..],
+                config_account.key.as_ref(),
+                deposit_mint.key.as_ref(),
+                expected_mint.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        let pair_stats_info = PairStats {
+            is_initialized: true,
+            config_pubkey: *config_account.key,
+            deposit_mint: *deposit_mint.key,
+            expected_mint: *expected_mint.key,
+            window_start_slot: Clock::get()?.slot,
+            window_volume: 0,
+            bump,
+            limit_fills: 0,
+            total_price_improvement: PaymentAmount(0),
+        };
+        PairStats::pack(pair_stats_info, &mut pair_stats_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_init_rate_counter(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let payer = next_account_info(account_info_iter)?;
+
+        if !payer.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, payer));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let rate_counter_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        Self::load_config(config_account, program_id)
+            .map_err(|e| account_info_iter.error(e, config_account))?;
+
+        let (rate_counter_pda, bump) = Pubkey::find_program_address(
+            &[b"rate_counter", config_account.key.as_ref()],
+            program_id,
+        );
+        if *rate_counter_account.key != rate_counter_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, rate_counter_account));
+        }
+        if rate_counter_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                rate_counter_account,
+            ));
+        }
+
+        let create_rate_counter_ix = system_instruction::create_account(
+            payer.key,
+            rate_counter_account.key,
+            Rent::get()?.minimum_balance(RateCounter::LEN),
+            RateCounter::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the rate counter account...");
+        invoke_signed(
+            &create_rate_counter_ix,
+            &[
+                payer.clone(),
+                rate_counter_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[&b"rate_counter"[..], config_account.key.as_ref(), &[bump]]],
+        )?;
+
+        let rate_counter_info = RateCounter {
+            is_initialized: true,
+            config_pubkey: *config_account.key,
+            slot: Clock::get()?.slot,
+            inits: 0,
+            bump,
+        };
+        RateCounter::pack(rate_counter_info, &mut rate_counter_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_precreate_escrows(
+        accounts: &[AccountInfo],
+        first_index: u64,
+        count: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if count == 0 || count > MAX_PRECREATED_ESCROWS {
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let initializer = next_account_info(account_info_iter)?;
+
+        if !initializer.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                initializer,
+            ));
+        }
+
+        let system_program = next_account_info(account_info_iter)?;
+        let rent_lamports = Rent::get()?.minimum_balance(Escrow::LEN);
+
+        msg!("Calling the system program to create {} escrow accounts...", count);
+        for i in 0..count {
+            let escrow_account = next_account_info(account_info_iter)?;
+            let index = first_index
+                .checked_add(i as u64)
+                .ok_or(EscrowError::AmountOverflow)?
+                .to_le_bytes();
+            let (escrow_pda, bump) = Pubkey::find_program_address(
+                &[b"escrow_account", initializer.key.as_ref(), &index],
+                program_id,
+            );
+            if *escrow_account.key != escrow_pda {
+                return Err(account_info_iter.error(ProgramError::InvalidSeeds, escrow_account));
+            }
+            if escrow_account.owner == program_id {
+                return Err(account_info_iter.error(
+                    ProgramError::AccountAlreadyInitialized,
+                    escrow_account,
+                ));
+            }
+
+            // the address is public, so anyone can send it lamports first, which would make
+            // create_account fail: top it up to rent exemption, then allocate and assign it
+            let escrow_seeds: &[&[u8]] =
+                &[&b"escrow_account"[..], initializer.key.as_ref(), &index, &[bump]];
+            let top_up = rent_lamports.saturating_sub(escrow_account.lamports());
+            if top_up > 0 {
+                invoke(
+                    &system_instruction::transfer(initializer.key, escrow_account.key, top_up),
+                    &[
+                        initializer.clone(),
+                        escrow_account.clone(),
+                        system_program.clone(),
+                    ],
+                )?;
+            }
+            invoke_signed(
+                &system_instruction::allocate(escrow_account.key, Escrow::LEN as u64),
+                &[escrow_account.clone(), system_program.clone()],
+                &[escrow_seeds],
+            )?;
+            invoke_signed(
+                &system_instruction::assign(escrow_account.key, program_id),
+                &[escrow_account.clone(), system_program.clone()],
+                &[escrow_seeds],
+            )?;
+
+            let mut escrow_info = Escrow::unpack_unchecked(&escrow_account.try_borrow_data()?)?;
+            escrow_info.initializer_pubkey = *initializer.key;
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_update_fee_rate(
+        accounts: &[AccountInfo],
+        fee_mint_rate: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let updater = next_account_info(account_info_iter)?;
+
+        if !updater.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, updater));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if !config_info.has_fee_mint() {
+            return Err(account_info_iter.error(EscrowError::InvalidConfig, config_account));
+        }
+        if config_info.admin_pubkey != *updater.key
+            && (config_info.fee_rate_oracle == Pubkey::default()
+                || config_info.fee_rate_oracle != *updater.key)
+        {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, updater));
+        }
+
+        config_info.fee_mint_rate = fee_mint_rate;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_gated_roles(
+        accounts: &[AccountInfo],
+        gated_roles: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        if gated_roles & !AllowlistEntry::KNOWN_ROLES != 0 {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+
+        config_info.gated_roles = gated_roles;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_set_allowlist_entry(
+        accounts: &[AccountInfo],
+        roles: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let member = next_account_info(account_info_iter)?;
+        let allowlist_entry = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+        if roles & !AllowlistEntry::KNOWN_ROLES != 0 {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+
+        let (allowlist_entry_pda, bump) = Pubkey::find_program_address(
+            &[b"allowlist", config_account.key.as_ref(), member.key.as_ref()],
+            program_id,
+        );
+        if *allowlist_entry.key != allowlist_entry_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, allowlist_entry));
+        }
+
+        if allowlist_entry.owner != program_id {
+            let create_allowlist_entry_ix = system_instruction::create_account(
+                admin.key,
+                allowlist_entry.key,
+                Rent::get()?.minimum_balance(AllowlistEntry::LEN),
+                AllowlistEntry::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the allowlist entry...");
+            invoke_signed(
+                &create_allowlist_entry_ix,
+                &[
+                    admin.clone(),
+                    allowlist_entry.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    &b"allowlist"[..],
+                    config_account.key.as_ref(),
+                    member.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+        }
+
+        let allowlist_entry_info = AllowlistEntry {
+            is_initialized: true,
+            config_pubkey: *config_account.key,
+            member_pubkey: *member.key,
+            roles,
+        };
+        AllowlistEntry::pack(allowlist_entry_info, &mut allowlist_entry.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_init_audit_log(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let admin = next_account_info(account_info_iter)?;
+
+        if !admin.is_signer {
+            return Err(account_info_iter.error(ProgramError::MissingRequiredSignature, admin));
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let audit_log_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let mut config_info =
+            Self::load_config(config_account, program_id)
+                .map_err(|e| account_info_iter.error(e, config_account))?;
+        if config_info.admin_pubkey != *admin.key {
+            return Err(account_info_iter.error(ProgramError::InvalidAccountData, admin));
+        }
+
+        let (audit_log_pda, bump) =
+            Pubkey::find_program_address(&[b"audit", config_account.key.as_ref()], program_id);
+        if *audit_log_account.key != audit_log_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, audit_log_account));
+        }
+        if audit_log_account.owner == program_id {
+            return Err(account_info_iter.error(
+                ProgramError::AccountAlreadyInitialized,
+                audit_log_account,
+            ));
+        }
+
+        let create_audit_log_ix = system_instruction::create_account(
+            admin.key,
+            audit_log_account.key,
+            Rent::get()?.minimum_balance(AuditLog::LEN),
+            AuditLog::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the audit log account...");
+        invoke_signed(
+            &create_audit_log_ix,
+            &[
+                admin.clone(),
+                audit_log_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[&b"audit"[..], config_account.key.as_ref(), &[bump]]],
+        )?;
+
+        let audit_log_info = AuditLog {
+            is_initialized: true,
+            config_pubkey: *config_account.key,
+            total_entries: 0,
+            entries: [AuditEntry::default(); AUDIT_LOG_CAPACITY],
+            bump,
+        };
+        AuditLog::pack(audit_log_info, &mut audit_log_account.try_borrow_mut_data()?)?;
+
+        config_info.audit_log_enabled = true;
+        Config::pack(config_info, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    /// Writes the escrow's [TimeStatus](../state/struct.TimeStatus.html) to return_data without modifying any account
+    fn process_time_status(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let escrow_account = next_account_info(account_info_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, escrow_account));
+        }
+        let escrow_info =
+            Escrow::unpack(&escrow_account.try_borrow_data()?)
+                .map_err(|e| account_info_iter.error(e, escrow_account))?;
+
+        let time_status = TimeStatus::new(&escrow_info, Clock::get()?.slot);
+        set_return_data(&time_status.pack());
+
+        Ok(())
+    }
+
+    /// Validates the account as a `T` and writes it back out to return_data, repacked
+    fn process_view<T: Pack + IsInitialized>(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let account = next_account_info(account_info_iter)?;
+
+        if account.owner != program_id {
+            return Err(account_info_iter.error(ProgramError::IllegalOwner, account));
+        }
+        let state = T::unpack(&account.try_borrow_data()?)
+            .map_err(|e| account_info_iter.error(e, account))?;
+
+        let mut buf = vec![0u8; T::LEN];
+        T::pack(state, &mut buf)?;
+        set_return_data(&buf);
+
+        Ok(())
+    }
+
+    fn process_update_deployment(
+        accounts: &[AccountInfo],
+        major: u16,
+        minor: u16,
+        patch: u16,
+        migration_notes_hash: [u8; 32],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut AccountCursor::new(accounts);
+        let upgrade_authority = next_account_info(account_info_iter)?;
+
+        if !upgrade_authority.is_signer {
+            return Err(account_info_iter.error(
+                ProgramError::MissingRequiredSignature,
+                upgrade_authority,
+            ));
+        }
+
+        let deployment_account = next_account_info(account_info_iter)?;
+        let program_data_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (program_data, _bump) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+        if *program_data_account.key != program_data {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, program_data_account));
+        }
+        let (upgrade_slot, authority) =
+            Self::unpack_program_data(&program_data_account.try_borrow_data()?)
+                .ok_or_else(|| {
+                    account_info_iter.error(ProgramError::InvalidAccountData, program_data_account)
+                })?;
+        if authority != Some(*upgrade_authority.key) {
+            return Err(account_info_iter.error(
+                ProgramError::InvalidAccountData,
+                upgrade_authority,
+            ));
+        }
+
+        let (deployment_pda, bump) = Pubkey::find_program_address(&[b"deployment"], program_id);
+        if *deployment_account.key != deployment_pda {
+            return Err(account_info_iter.error(ProgramError::InvalidSeeds, deployment_account));
+        }
+        if deployment_account.owner != program_id {
+            let create_deployment_ix = system_instruction::create_account(
+                upgrade_authority.key,
+                deployment_account.key,
+                Rent::get()?.minimum_balance(Deployment::LEN),
+                Deployment::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the deployment account...");
+            invoke_signed(
+                &create_deployment_ix,
+                &[
+                    upgrade_authority.clone(),
+                    deployment_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[&b"deployment"[..], &[bump]]],
+            )?;
+        }
+
+        let deployment_info = Deployment {
+            is_initialized: true,
+            major,
+            minor,
+            patch,
+            upgrade_slot,
+            migration_notes_hash,
+        };
+        Deployment::pack(deployment_info, &mut deployment_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    /// Reads the deployment slot and upgrade authority out of a `ProgramData` account of the
+    /// upgradeable loader: a u32 variant tag of 3, the slot, then an optional authority
+    fn unpack_program_data(data: &[u8]) -> Option<(u64, Option<Pubkey>)> {
+        if data.get(..4)? != 3u32.to_le_bytes() {
+            return None;
+        }
+        let slot = u64::from_le_bytes(data.get(4..12)?.try_into().ok()?);
+        let authority = match data.get(12)? {
+            0 => None,
+            1 => Some(Pubkey::new_from_array(data.get(13..45)?.try_into().ok()?)),
+            _ => return None,
+        };
+        Some((slot, authority))
+    }
+
+    /// Fee tiers must be ordered by strictly increasing age and may only lower the fee
+    fn validate_config_params(
+        fee_bps: u16,
+        min_expected_amount: PaymentAmount,
+        max_expected_amount: PaymentAmount,
+        fee_tiers: &[FeeTier],
+    ) -> ProgramResult {
+        if fee_bps > TOTAL_PAYOUT_BPS
+            || (max_expected_amount.0 != 0 && min_expected_amount > max_expected_amount)
+            || fee_tiers.len() > MAX_FEE_TIERS
+        {
+            return Err(EscrowError::InvalidConfig.into());
+        }
+        let mut previous_age = None;
+        let mut previous_fee_bps = fee_bps;
+        for fee_tier in fee_tiers {
+            if fee_tier.fee_bps > previous_fee_bps
+                || previous_age.is_some_and(|age| fee_tier.min_age_slots <= age)
+            {
+                return Err(EscrowError::InvalidConfig.into());
+            }
+            previous_age = Some(fee_tier.min_age_slots);
+            previous_fee_bps = fee_tier.fee_bps;
+        }
+        Ok(())
+    }
+
+    /// Unpacks an operator config, checking it is owned by this program
+    /// Fails with `NotAllowlisted` unless `member` holds `role` in its allowlist entry under the
+    /// config. A missing entry counts as holding no roles.
+    fn assert_allowlisted(
+        allowlist_entry: &AccountInfo,
+        config: &Pubkey,
+        member: &Pubkey,
+        role: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (allowlist_entry_pda, _bump) = Pubkey::find_program_address(
+            &[b"allowlist", config.as_ref(), member.as_ref()],
+            program_id,
+        );
+        if *allowlist_entry.key != allowlist_entry_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if allowlist_entry.owner != program_id {
+            return Err(EscrowError::NotAllowlisted.into());
+        }
+        if !AllowlistEntry::unpack(&allowlist_entry.try_borrow_data()?)?.has_role(role) {
+            return Err(EscrowError::NotAllowlisted.into());
+        }
+        Ok(())
+    }
+
+    /// Loads the pair stats Exchange records a fill of `deposit_mint` for `expected_mint` in
+    fn load_pair_stats(
+        pair_stats_account: &AccountInfo,
+        config: &Pubkey,
+        deposit_mint: &Pubkey,
+        expected_mint: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<PairStats, ProgramError> {
+        if pair_stats_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let pair_stats_info = PairStats::unpack(&pair_stats_account.try_borrow_data()?)?;
+        let pair_stats_pda = pda_with_bump(
+            program_id,
+            &[
+                b"pair_stats",
+                config.as_ref(),
+                deposit_mint.as_ref(),
+                expected_mint.as_ref(),
+                &[pair_stats_info.bump],
+            ],
+        )?;
+        if *pair_stats_account.key != pair_stats_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(pair_stats_info)
+    }
+
+    /// Loads the rate counter InitEscrow counts `config`'s inits in
+    fn load_rate_counter(
+        rate_counter_account: &AccountInfo,
+        config: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<RateCounter, ProgramError> {
+        if rate_counter_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let rate_counter_info = RateCounter::unpack(&rate_counter_account.try_borrow_data()?)?;
+        let rate_counter_pda = pda_with_bump(
+            program_id,
+            &[b"rate_counter", config.as_ref(), &[rate_counter_info.bump]],
+        )?;
+        if *rate_counter_account.key != rate_counter_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(rate_counter_info)
+    }
+
+    /// Loads the taker allowlist of `escrow`, checking it sits at the escrow's PDA
+    fn load_taker_allowlist(
+        taker_allowlist_account: &AccountInfo,
+        escrow: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<TakerAllowlist, ProgramError> {
+        if taker_allowlist_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let taker_allowlist_info = TakerAllowlist::unpack(&taker_allowlist_account.try_borrow_data()?)?;
+        let taker_allowlist_pda =
+            pda_with_bump(program_id, &[b"takers", escrow.as_ref(), &[taker_allowlist_info.bump]])?;
+        if *taker_allowlist_account.key != taker_allowlist_pda
+            || taker_allowlist_info.escrow_pubkey != *escrow
+        {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(taker_allowlist_info)
+    }
+
+    /// Loads the basket of `escrow`, checking it sits at the escrow's PDA
+    fn load_basket(
+        basket_account: &AccountInfo,
+        escrow: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Basket, ProgramError> {
+        if basket_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let basket_info = Basket::unpack(&basket_account.try_borrow_data()?)?;
+        let basket_pda =
+            pda_with_bump(program_id, &[b"basket", escrow.as_ref(), &[basket_info.bump]])?;
+        if *basket_account.key != basket_pda || basket_info.escrow_pubkey != *escrow {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(basket_info)
+    }
+
+    /// Takes the vault, a token account of `recipient`'s and the mint of each asset in the
+    /// basket off `account_info_iter`, in the basket's order
+    fn basket_legs<'a, 'b>(
+        account_info_iter: &mut AccountCursor<'b, 'a>,
+        basket_info: &Basket,
+        recipient: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Result<Vec<BasketLeg<'a, 'b>>, ProgramError> {
+        let mut legs = Vec::with_capacity(basket_info.assets().len());
+        for asset in basket_info.assets() {
+            let vault = next_account_info(account_info_iter)?;
+            let destination = next_account_info(account_info_iter)?;
+            let mint = next_account_info(account_info_iter)?;
+            if *vault.key != asset.vault_pubkey {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, vault));
+            }
+            // whatever else landed in the vault goes along, so the vault can be closed
+            let amount = unpack_token_account(vault, token_program)
+                .map_err(|e| account_info_iter.error(e, vault))?
+                .amount;
+            let destination_info = unpack_token_account(destination, token_program)
+                .map_err(|e| account_info_iter.error(e, destination))?;
+            if destination_info.owner != *recipient || destination_info.mint != asset.mint {
+                return Err(account_info_iter.error(ProgramError::InvalidAccountData, destination));
+            }
+            let decimals = mint_decimals(mint, &asset.mint, token_program)
+                .map_err(|e| account_info_iter.error(e, mint))?;
+            legs.push(BasketLeg {
+                vault,
+                destination,
+                mint,
+                decimals,
+                amount,
+            });
+        }
+        Ok(legs)
+    }
+
+    /// Empties each of the basket's vaults into its leg's destination, then closes the vaults
+    /// and the basket, sending their rent to `rent_destination`. `hook_accounts` are the
+    /// instruction's remaining accounts, for assets with a transfer hook.
+    fn release_basket<'a>(
+        basket_account: &AccountInfo<'a>,
+        basket_info: &Basket,
+        legs: Vec<BasketLeg<'a, '_>>,
+        token_program: &AccountInfo<'a>,
+        rent_destination: &AccountInfo<'a>,
+        hook_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let signer_seeds: &[&[u8]] =
+            &[&b"basket"[..], basket_info.escrow_pubkey.as_ref(), &[basket_info.bump]];
+        for (i, leg) in legs.into_iter().enumerate() {
+            msg!("Calling the token program to transfer basket asset {}...", i);
+            invoke_transfer_checked(
+                token_program,
+                leg.vault,
+                leg.mint,
+                leg.destination,
+                basket_account,
+                hook_accounts,
+                leg.amount,
+                leg.decimals,
+                &[signer_seeds],
+            )?;
+
+            let close_ix = spl_token_2022::instruction::close_account(
+                token_program.key,
+                leg.vault.key,
+                rent_destination.key,
+                basket_account.key,
+                &[basket_account.key],
+            )?;
+            msg!("Calling the token program to close basket vault {}...", i);
+            invoke_signed(
+                &close_ix,
+                &[
+                    leg.vault.clone(),
+                    rent_destination.clone(),
+                    basket_account.clone(),
+                    token_program.clone(),
+                ],
+                &[signer_seeds],
+            )?;
+        }
+        close_state_account(basket_account, rent_destination)
+    }
+
+    /// Pays each of `payouts` out of a mutual deposit's taker vault, then closes the vault and
+    /// the mutual deposit PDA with their rent to `rent_destination`. Zero payouts are skipped.
+    #[allow(clippy::too_many_arguments)]
+    fn release_mutual_deposit<'a>(
+        mutual_deposit_account: &AccountInfo<'a>,
+        mutual_deposit_info: &MutualDeposit,
+        taker_vault: &AccountInfo<'a>,
+        expected_mint: &AccountInfo<'a>,
+        expected_decimals: u8,
+        payouts: &[(&AccountInfo<'a>, PaymentAmount)],
+        token_program: &AccountInfo<'a>,
+        rent_destination: &AccountInfo<'a>,
+        hook_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let signer_seeds: &[&[u8]] = &[
+            &b"mutual"[..],
+            mutual_deposit_info.escrow_pubkey.as_ref(),
+            &[mutual_deposit_info.bump],
+        ];
+        for (destination, amount) in payouts {
+            if amount.0 == 0 {
+                continue;
+            }
+            msg!("Calling the token program to transfer out of the taker vault...");
+            invoke_transfer_checked(
+                token_program,
+                taker_vault,
+                expected_mint,
+                destination,
+                mutual_deposit_account,
+                hook_accounts,
+                amount.0,
+                expected_decimals,
+                &[signer_seeds],
+            )?;
+        }
 
+        let close_ix = spl_token_2022::instruction::close_account(
+            token_program.key,
+            taker_vault.key,
+            rent_destination.key,
+            mutual_deposit_account.key,
+            &[mutual_deposit_account.key],
+        )?;
+        msg!("Calling the token program to close the taker vault...");
+        invoke_signed(
+            &close_ix,
+            &[
+                taker_vault.clone(),
+                rent_destination.clone(),
+                mutual_deposit_account.clone(),
+                token_program.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+        close_state_account(mutual_deposit_account, rent_destination)
+    }
+
+    /// Checks the receipt accounts passed for `escrow_account` and returns who holds its
+    /// receipt, the owner of `receipt_token_account`, which must hold the one receipt minted
+    #[allow(clippy::too_many_arguments)]
+    fn receipt_holder<'a>(
+        accounts: &AccountCursor<'_, 'a>,
+        escrow_account: &AccountInfo<'a>,
+        escrow_info: &Escrow,
+        receipt_mint: &AccountInfo<'a>,
+        receipt_token_account: &AccountInfo<'a>,
+        proceeds_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        program_id: &Pubkey,
+    ) -> Result<Pubkey, ProgramError> {
+        let receipt_mint_pda = pda_with_bump(
+            program_id,
+            &[b"receipt", escrow_account.key.as_ref(), &[escrow_info.receipt_mint_bump]],
+        )
+        .map_err(|e| accounts.error(e, receipt_mint))?;
+        if *receipt_mint.key != receipt_mint_pda {
+            return Err(accounts.error(ProgramError::InvalidSeeds, receipt_mint));
+        }
+        if get_associated_token_address_with_program_id(
+            &receipt_mint_pda,
+            &escrow_info.expected_mint,
+            token_program.key,
+        ) != *proceeds_account.key
+        {
+            return Err(accounts.error(ProgramError::InvalidAccountData, proceeds_account));
+        }
+        let receipt_token_info = unpack_token_account(receipt_token_account, token_program.key)
+            .map_err(|e| accounts.error(e, receipt_token_account))?;
+        if receipt_token_info.mint != receipt_mint_pda || receipt_token_info.amount != 1 {
+            return Err(accounts.error(ProgramError::InvalidAccountData, receipt_token_account));
+        }
+        Ok(receipt_token_info.owner)
+    }
+
+    /// Loads the mutual deposit of `escrow`, checking it sits at the escrow's PDA
+    fn load_mutual_deposit(
+        mutual_deposit_account: &AccountInfo,
+        escrow: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<MutualDeposit, ProgramError> {
+        if mutual_deposit_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let mutual_deposit_info = MutualDeposit::unpack(&mutual_deposit_account.try_borrow_data()?)?;
+        let mutual_deposit_pda = pda_with_bump(
+            program_id,
+            &[b"mutual", escrow.as_ref(), &[mutual_deposit_info.bump]],
+        )?;
+        if *mutual_deposit_account.key != mutual_deposit_pda
+            || mutual_deposit_info.escrow_pubkey != *escrow
+        {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(mutual_deposit_info)
+    }
+
+    /// Loads the insurance pool of `config`, checking it sits at the config's PDA
+    fn load_insurance_pool(
+        insurance_pool_account: &AccountInfo,
+        config: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<InsurancePool, ProgramError> {
+        if insurance_pool_account.owner != program_id {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let insurance_pool_info = InsurancePool::unpack(&insurance_pool_account.try_borrow_data()?)?;
+        let insurance_pool_pda = pda_with_bump(
+            program_id,
+            &[b"insurance", config.as_ref(), &[insurance_pool_info.bump]],
+        )?;
+        if *insurance_pool_account.key != insurance_pool_pda
+            || insurance_pool_info.config_pubkey != *config
+        {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(insurance_pool_info)
+    }
+
+    fn load_config(config_account: &AccountInfo, program_id: &Pubkey) -> Result<Config, ProgramError> {
+        if config_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Config::unpack(&config_account.try_borrow_data()?)
+    }
+
+    /// Rent an escrow counts as locked in the stats: its own account's, plus its vault's unless
+    /// the vault is shared
+    fn escrow_rent(
+        escrow_info: &Escrow,
+        escrow_account: &AccountInfo,
+        vault: &AccountInfo,
+    ) -> Result<u64, ProgramError> {
+        // the gas rebate isn't rent, it's the taker's or goes back to the initializer
+        let escrow_rent = escrow_account.lamports().saturating_sub(escrow_info.gas_rebate);
+        if escrow_info.shares_vault() {
+            return Ok(escrow_rent);
+        }
+        Ok(escrow_rent
+            .checked_add(vault.lamports())
+            .ok_or(EscrowError::AmountOverflow)?)
+    }
+
+    /// Unpacks the stats account, checking it is the program's stats PDA
+    fn load_stats(stats_account: &AccountInfo, program_id: &Pubkey) -> Result<EscrowStats, ProgramError> {
+        if stats_account.owner != program_id {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let stats_info = EscrowStats::unpack(&stats_account.try_borrow_data()?)?;
+        if *stats_account.key != pda_with_bump(program_id, &[b"stats", &[stats_info.bump]])? {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(stats_info)
+    }
+
+    /// Adds (on open) or removes (on close) an escrow's rent from the stats PDA.
+    /// Closing saturates at zero so lamports donated to an escrow after init
+    /// can't underflow the total.
+    fn record_escrow_rent(
+        stats_account: &AccountInfo,
+        program_id: &Pubkey,
+        opened: bool,
+        rent_lamports: u64,
+    ) -> ProgramResult {
+        let mut stats_info = Self::load_stats(stats_account, program_id)?;
+
+        if opened {
+            stats_info.open_escrows = stats_info
+                .open_escrows
+                .checked_add(1)
+                .ok_or(EscrowError::AmountOverflow)?;
+            stats_info.locked_rent = stats_info
+                .locked_rent
+                .checked_add(rent_lamports)
+                .ok_or(EscrowError::AmountOverflow)?;
+        } else {
+            stats_info.open_escrows = stats_info.open_escrows.saturating_sub(1);
+            stats_info.locked_rent = stats_info.locked_rent.saturating_sub(rent_lamports);
+        }
+        EscrowStats::pack(stats_info, &mut stats_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    /// Takes the next sequence number from the stats PDA and logs the event with it. Callers
+    /// pass the stats account only after `load_stats` or `record_escrow_rent` has validated it.
+    fn emit_event(
+        stats_account: &AccountInfo,
+        action: AuditAction,
+        escrow: &Pubkey,
+        actor: &Pubkey,
+        deposit_mint_risk: MintRiskFlags,
+    ) -> ProgramResult {
+        let mut stats_info = EscrowStats::unpack(&stats_account.try_borrow_data()?)?;
+        stats_info.event_sequence = stats_info
+            .event_sequence
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+        let event = EscrowEvent {
+            sequence: stats_info.event_sequence,
+            action: action as u8,
+            escrow_pubkey: *escrow,
+            actor_pubkey: *actor,
+            deposit_mint_risk: deposit_mint_risk.bits(),
+        };
+        EscrowStats::pack(stats_info, &mut stats_account.try_borrow_mut_data()?)?;
+
+        sol_log_data(&[&event.pack()]);
         Ok(())
     }
-    
 
+    /// Appends an entry to an operator's audit log, checking it is the PDA for `config`
+    fn record_audit_entry(
+        audit_log_account: &AccountInfo,
+        config: &Pubkey,
+        program_id: &Pubkey,
+        action: AuditAction,
+        escrow: &Pubkey,
+        actor: &Pubkey,
+    ) -> ProgramResult {
+        if audit_log_account.owner != program_id {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut audit_log_info = AuditLog::unpack(&audit_log_account.try_borrow_data()?)?;
+        let audit_log_pda =
+            pda_with_bump(program_id, &[b"audit", config.as_ref(), &[audit_log_info.bump]])?;
+        if *audit_log_account.key != audit_log_pda {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        audit_log_info.push(AuditEntry {
+            slot: Clock::get()?.slot,
+            action: action as u8,
+            escrow_pubkey: *escrow,
+            actor_pubkey: *actor,
+        });
+        AuditLog::pack(audit_log_info, &mut audit_log_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
 }
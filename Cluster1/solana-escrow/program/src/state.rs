@@ -1,83 +1,3164 @@
 use solana_program::{
+    clock::DEFAULT_MS_PER_SLOT,
+    hash::hashv,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
+    pubkey,
     pubkey::Pubkey,
 };
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::ops::RangeInclusive;
+
+use crate::error::EscrowError;
+
+/// Properties of the deposited mint a taker may want to be warned about, assessed once at
+/// InitEscrow and stored in the escrow
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct MintRiskFlags(u8);
+
+impl MintRiskFlags {
+    /// The mint has a freeze authority that can lock the taker's tokens after the fill
+    pub const FREEZABLE: u8 = 1 << 0;
+    /// The mint still has a mint authority that can inflate the supply
+    pub const MINT_AUTHORITY: u8 = 1 << 1;
+    /// Token-2022 mint withholding a fee on every transfer
+    pub const TRANSFER_FEE: u8 = 1 << 2;
+    /// Token-2022 mint calling a transfer hook program on every transfer, which can refuse the
+    /// escrow's transfers out, see `primitives::invoke_transfer_checked`
+    pub const TRANSFER_HOOK: u8 = 1 << 3;
+    /// The initializer didn't pass the mint to InitEscrow, so nothing above was checked
+    pub const UNASSESSED: u8 = 1 << 7;
+    const KNOWN: u8 = 0b1000_1111;
+
+    // Token-2022 appends an account type byte and type-length-value extensions to the
+    // base mint, padded to the size of a token account
+    const ACCOUNT_TYPE_OFFSET: usize = spl_token::state::Account::LEN;
+    const ACCOUNT_TYPE_MINT: u8 = 1;
+    const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+    const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::KNOWN != 0 {
+            return None;
+        }
+        Some(MintRiskFlags(bits))
+    }
+
+    pub fn unassessed() -> Self {
+        MintRiskFlags(Self::UNASSESSED)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, flag: u8) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Assesses a mint account's data, None if it isn't an initialized mint
+    pub fn assess(mint_data: &[u8]) -> Option<Self> {
+        if mint_data.len() < spl_token::state::Mint::LEN {
+            return None;
+        }
+        let mint = spl_token::state::Mint::unpack_from_slice(mint_data).ok()?;
+        if !mint.is_initialized {
+            return None;
+        }
+
+        let mut bits = 0;
+        if mint.freeze_authority.is_some() {
+            bits |= Self::FREEZABLE;
+        }
+        if mint.mint_authority.is_some() {
+            bits |= Self::MINT_AUTHORITY;
+        }
+        if mint_data.get(Self::ACCOUNT_TYPE_OFFSET) == Some(&Self::ACCOUNT_TYPE_MINT) {
+            let mut extensions = &mint_data[Self::ACCOUNT_TYPE_OFFSET + 1..];
+            while extensions.len() >= 4 {
+                let extension_type = u16::from_le_bytes([extensions[0], extensions[1]]);
+                let length = u16::from_le_bytes([extensions[2], extensions[3]]) as usize;
+                match extension_type {
+                    Self::EXTENSION_TRANSFER_FEE_CONFIG => bits |= Self::TRANSFER_FEE,
+                    Self::EXTENSION_TRANSFER_HOOK => bits |= Self::TRANSFER_HOOK,
+                    _ => {}
+                }
+                extensions = extensions.get(4 + length..).unwrap_or(&[]);
+            }
+        }
+        Some(MintRiskFlags(bits))
+    }
+}
+
+/// An amount of token X, the token the initializer deposits into the vault
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DepositAmount(pub u64);
+
+/// An amount of token Y, the token the taker pays the initializer with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PaymentAmount(pub u64);
+
+impl PaymentAmount {
+    /// The share of this amount given by `bps` basis points, rounded down
+    pub fn bps_share(self, bps: u16) -> PaymentAmount {
+        PaymentAmount((self.0 as u128 * bps as u128 / TOTAL_PAYOUT_BPS as u128) as u64)
+    }
+
+    pub fn checked_sub(self, other: PaymentAmount) -> Option<PaymentAmount> {
+        self.0.checked_sub(other.0).map(PaymentAmount)
+    }
+}
+
+/// How an escrow rounds amounts that are a fraction of another, chosen by the initializer at
+/// init. Only the maker's side is rounded and the other side gets the exact remainder, so the
+/// parts always add up to the whole. Each fill moves at most one base unit of dust between the
+/// parties. Payout splits are unaffected: every part goes to the maker, so they always round
+/// down and the last payout gets the remainder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RoundingMode {
+    /// Rounds in the maker's favour. The default, and how fees were rounded before modes existed.
+    #[default]
+    FloorForTaker = 0,
+    /// Rounds in the taker's favour, flooring what the maker receives
+    FloorForMaker = 1,
+    /// Rounds half to even, so neither side gains on average
+    HalfEven = 2,
+}
+
+
+impl RoundingMode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RoundingMode::FloorForTaker),
+            1 => Some(RoundingMode::FloorForMaker),
+            2 => Some(RoundingMode::HalfEven),
+            _ => None,
+        }
+    }
+
+    /// `amount * numerator / denominator` for an amount going to the maker, rounded by this
+    /// mode. `numerator` must not exceed `denominator`, so the result never exceeds `amount`.
+    pub fn maker_share(self, amount: u64, numerator: u64, denominator: u64) -> u64 {
+        self.maker_quotient(amount as u128 * numerator as u128, denominator as u128) as u64
+    }
+
+    /// `product / denominator` for an amount going to the maker, rounded by this mode
+    pub fn maker_quotient(self, product: u128, denominator: u128) -> u128 {
+        let (quotient, remainder) = (product / denominator, product % denominator);
+        let round_up = match self {
+            RoundingMode::FloorForTaker => remainder > 0,
+            RoundingMode::FloorForMaker => false,
+            RoundingMode::HalfEven => {
+                remainder * 2 > denominator || (remainder * 2 == denominator && quotient % 2 == 1)
+            }
+        };
+        quotient + round_up as u128
+    }
+}
+
+/// Seconds per year Token-2022's interest-bearing extension compounds its rates over
+const INTEREST_SECONDS_PER_YEAR: f64 = 31_556_736.;
+
+/// What an escrow's `expected_amount` is denominated in, chosen at init. Only the payment is
+/// affected; deposits are always raw amounts of the vault's mint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum QuoteConvention {
+    /// Raw base units of the expected mint, as every transfer moves them
+    #[default]
+    Raw = 0,
+    /// Base units of the expected mint's UI amount, which for a Token-2022 interest-bearing
+    /// mint includes the interest accrued up to the fill. Exchange charges the raw amount
+    /// that is worth at the time, see `raw_payment`. Other mints accrue nothing, so for them
+    /// this is the same as `Raw`.
+    InterestAdjusted = 1,
+}
+
+impl QuoteConvention {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(QuoteConvention::Raw),
+            1 => Some(QuoteConvention::InterestAdjusted),
+            _ => None,
+        }
+    }
+
+    /// Raw amount of the expected mint a taker pays for `quoted`, given the factor its UI
+    /// amounts have grown by over its raw ones, see `interest_scale`. Rounded up, so the
+    /// initializer is never paid less than they quoted. None if the scale is unusable or the
+    /// result doesn't fit.
+    pub fn raw_payment(self, quoted: PaymentAmount, interest_scale: f64) -> Option<PaymentAmount> {
+        match self {
+            QuoteConvention::Raw => Some(quoted),
+            QuoteConvention::InterestAdjusted => {
+                if !interest_scale.is_finite() || interest_scale <= 0. {
+                    return None;
+                }
+                let raw = (quoted.0 as f64 / interest_scale).ceil();
+                // u64::MAX rounds up to 2^64 as a float, which no longer fits
+                if raw >= u64::MAX as f64 {
+                    return None;
+                }
+                Some(PaymentAmount((raw as u64).max(1)))
+            }
+        }
+    }
+}
+
+/// Factor a Token-2022 interest-bearing mint's UI amounts have grown by over its raw amounts,
+/// compounded continuously as the extension does it: at `pre_update_rate_bps` a year for
+/// `pre_update_seconds`, up to the mint's last rate change, and at `current_rate_bps` for the
+/// `current_seconds` since
+pub fn interest_scale(
+    pre_update_rate_bps: i16,
+    pre_update_seconds: i64,
+    current_rate_bps: i16,
+    current_seconds: i64,
+) -> f64 {
+    let exponent = |rate_bps: i16, seconds: i64| {
+        (rate_bps as i128 * seconds as i128) as f64
+            / INTEREST_SECONDS_PER_YEAR
+            / TOTAL_PAYOUT_BPS as f64
+    };
+    exponent(pre_update_rate_bps, pre_update_seconds).exp()
+        * exponent(current_rate_bps, current_seconds).exp()
+}
+
+/// Slots after init an escrow stays cancellable for when InitEscrow doesn't say
+pub const DEFAULT_UNLOCK_SLOTS: u64 = 100;
+/// Shortest cancellation window InitEscrow accepts, so takers get a chance to see the offer
+pub const MIN_UNLOCK_SLOTS: u64 = 10;
+/// Longest cancellation window InitEscrow accepts, about a day at the default slot duration
+pub const MAX_UNLOCK_SLOTS: u64 = 216_000;
+
+/// Slots after `unlock_time` during which the initializer can't cancel; past them the offer has expired
+pub const EXPIRY_SLOTS: u64 = 1000;
+
+/// Slots that must pass between two ResetTimeLocks of the same escrow
+pub const MIN_RESET_INTERVAL_SLOTS: u64 = 1000;
+/// Most ResetTimeLocks an escrow accepts over its life, so the initializer can't keep the
+/// offer cancellable indefinitely
+pub const MAX_TIME_LOCK_RESETS: u8 = 3;
+
+/// Lamports CancelExpired pays its caller out of the escrow account's rent, covering a
+/// couple of signature fees so cleaning up abandoned escrows pays for itself
+pub const CANCEL_EXPIRED_REWARD_LAMPORTS: u64 = 10_000;
+
+/// Maximum number of payout accounts an initializer can split the taker's payment across
+pub const MAX_PAYOUTS: usize = 4;
+
+/// Most escrow accounts one PrecreateEscrows allocates, keeping it within a transaction's
+/// account and compute limits
+pub const MAX_PRECREATED_ESCROWS: u8 = 16;
+
+/// Basis points a payout split must add up to
+pub const TOTAL_PAYOUT_BPS: u16 = 10_000;
+
+#[derive(Clone, Copy, Default)]
+pub struct Payout {
+    pub token_account_pubkey: Pubkey,
+    pub bps: u16,
+}
+
+impl Payout {
+    const LEN: usize = 34;
+}
+
+/// Maximum number of age-based fee tiers an operator config can hold
+pub const MAX_FEE_TIERS: usize = 4;
+
+/// Fixed-point scale of `Config::fee_mint_rate`: fee mint base units per this many base units of token Y
+pub const FEE_RATE_SCALE: u64 = 1_000_000_000;
+
+/// Fee charged on offers that rested on-chain for at least `min_age_slots` before being taken
+#[derive(Clone, Copy, Default)]
+pub struct FeeTier {
+    pub min_age_slots: u64,
+    pub fee_bps: u16,
+}
+
+impl FeeTier {
+    const LEN: usize = 10;
+}
+
+/// Boolean escrow state packed into the escrow's first byte. Bit 0 is `INITIALIZED`, which keeps
+/// the byte compatible with accounts written when it held a plain bool. Bits 1 to 4 are reserved
+/// for future flags and rejected until then.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct EscrowFlags(u8);
+
+impl EscrowFlags {
+    pub const INITIALIZED: u8 = 1 << 0;
+    /// Set at init when the escrow's `deposit_mint_risk` isn't empty
+    pub const RISK_FLAGGED: u8 = 1 << 5;
+    /// Set at init when the operator keeps an audit log, so every later mutation must be logged too
+    pub const AUDITED: u8 = 1 << 6;
+    /// Set by MintReceipt: the position is held as a receipt token that Cancel and
+    /// ClaimProceeds burn, and Exchange pays into the receipt's proceeds account
+    pub const RECEIPT: u8 = 1 << 7;
+    const KNOWN: u8 = Self::INITIALIZED | Self::RISK_FLAGGED | Self::AUDITED | Self::RECEIPT;
+
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::KNOWN != 0 {
+            return None;
+        }
+        Some(EscrowFlags(bits))
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, flag: u8) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub fn set(&mut self, flag: u8, value: bool) {
+        if value {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+    }
+
+    pub fn is_initialized(self) -> bool {
+        self.contains(Self::INITIALIZED)
+    }
+
+    pub fn is_risk_flagged(self) -> bool {
+        self.contains(Self::RISK_FLAGGED)
+    }
+
+    pub fn is_audited(self) -> bool {
+        self.contains(Self::AUDITED)
+    }
+
+    pub fn has_receipt(self) -> bool {
+        self.contains(Self::RECEIPT)
+    }
+}
+
+/// Optional features an escrow was opened with, fixed at InitEscrow but for `TAKER_ALLOWLIST`,
+/// `INSURED`, `BASKET` and `MUTUAL_DEPOSIT`.
+/// Handlers and clients branch on these bits rather than inspecting the fields behind them,
+/// and an escrow combining features the program can't settle together is never created.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct EscrowFeatures(u16);
+
+impl EscrowFeatures {
+    /// The payment is split across `payouts`
+    pub const PAYOUTS: u16 = 1 << 0;
+    /// Only the taker behind `taker_commitment` may fill
+    pub const PRIVATE: u16 = 1 << 1;
+    /// The deposit sits in a shared vault, see `vault_deposit`
+    pub const SHARED_VAULT: u16 = 1 << 2;
+    /// Exchange hands the proceeds to `settlement_program`
+    pub const SETTLEMENT_HOOK: u16 = 1 << 3;
+    /// Opened by an allowlisted initializer under a config gating InitEscrow
+    pub const ALLOWLIST: u16 = 1 << 4;
+    /// Takers may fill less than the whole deposit, see `Escrow::filled_amount`
+    pub const PARTIAL_FILL: u16 = 1 << 5;
+    /// Only takers on the escrow's `TakerAllowlist` may fill. Set by the first
+    /// AddAllowedTaker, and kept when the list is emptied again.
+    pub const TAKER_ALLOWLIST: u16 = 1 << 6;
+    /// Takers must pass the escrow's `taker_gate`
+    pub const TAKER_GATE: u16 = 1 << 7;
+    /// Fills pay a premium to the config's `InsurancePool`. Set by InsureEscrow.
+    pub const INSURED: u16 = 1 << 8;
+    /// The deposit comes with the other assets of the escrow's `Basket`. Set by the first
+    /// AddBasketAsset.
+    pub const BASKET: u16 = 1 << 9;
+    /// The taker named in the escrow's `MutualDeposit` deposits their payment ahead of a
+    /// Settle, which is then the only way to fill it. Set by DepositInitializer.
+    pub const MUTUAL_DEPOSIT: u16 = 1 << 10;
+    /// Only the escrow's `hash_lock` recipient may take the deposit, by Redeem with the
+    /// preimage of its hash
+    pub const HASH_LOCK: u16 = 1 << 11;
+    /// The escrow account holds `gas_rebate` lamports above its rent, paid to the taker of
+    /// the fill
+    pub const GAS_REBATE: u16 = 1 << 12;
+    /// Exchange prices the fill off the Pyth price account in `price_band`, never below
+    /// `expected_amount`
+    pub const ORACLE_PRICE: u16 = 1 << 13;
+    /// `expected_amount` is quoted in UI-adjusted units of an interest-bearing mint, see
+    /// `QuoteConvention::InterestAdjusted`
+    pub const INTEREST_ADJUSTED: u16 = 1 << 14;
+    /// Only `designated_taker` may fill, signing as the taker
+    pub const DESIGNATED_TAKER: u16 = 1 << 15;
+    /// Features only their bit records, as opposed to the ones `Escrow::implied_features`
+    /// derives from other fields
+    pub const DECLARED: u16 = Self::ALLOWLIST
+        | Self::PARTIAL_FILL
+        | Self::TAKER_ALLOWLIST
+        | Self::INSURED
+        | Self::BASKET
+        | Self::MUTUAL_DEPOSIT;
+    /// Each feature with the ones it can't be combined with: a settlement hook takes the whole
+    /// payment, leaving nothing to split, a basket can't be divided between partial fills,
+    /// and neither can a gas rebate, which Exchange pays once. An oracle price is quoted for
+    /// the whole deposit, and a settlement program is only told the amount staged, not the
+    /// price it was filled at. Settle pays a mutual deposit's initializer whole, to the taker it
+    /// names, and Redeem hands a hash lock's recipient the whole deposit for no payment at all.
+    /// An oracle prices in raw amounts, which an interest-adjusted quote isn't, and Settle
+    /// takes exactly the quoted amount from the taker's deposit.
+    const CONFLICTS: [(u16, u16); 6] = [
+        (Self::SETTLEMENT_HOOK, Self::PAYOUTS),
+        (Self::BASKET, Self::PARTIAL_FILL),
+        (Self::GAS_REBATE, Self::PARTIAL_FILL),
+        (
+            Self::ORACLE_PRICE,
+            Self::SETTLEMENT_HOOK | Self::PARTIAL_FILL | Self::INTEREST_ADJUSTED,
+        ),
+        (
+            Self::MUTUAL_DEPOSIT,
+            Self::PAYOUTS
+                | Self::PRIVATE
+                | Self::DESIGNATED_TAKER
+                | Self::SETTLEMENT_HOOK
+                | Self::TAKER_ALLOWLIST
+                | Self::TAKER_GATE
+                | Self::INSURED
+                | Self::BASKET
+                | Self::GAS_REBATE
+                | Self::ORACLE_PRICE
+                | Self::INTEREST_ADJUSTED,
+        ),
+        (
+            Self::HASH_LOCK,
+            Self::PAYOUTS
+                | Self::PRIVATE
+                | Self::DESIGNATED_TAKER
+                | Self::SETTLEMENT_HOOK
+                | Self::PARTIAL_FILL
+                | Self::TAKER_ALLOWLIST
+                | Self::TAKER_GATE
+                | Self::INSURED
+                | Self::BASKET
+                | Self::MUTUAL_DEPOSIT
+                | Self::GAS_REBATE
+                | Self::ORACLE_PRICE,
+        ),
+    ];
+
+    /// None for a combination the program doesn't support. Every bit is a feature, so there
+    /// are no unknown ones left to reject.
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        if Self::CONFLICTS
+            .iter()
+            .any(|(feature, excluded)| bits & feature != 0 && bits & excluded != 0)
+        {
+            return None;
+        }
+        Some(EscrowFeatures(bits))
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn contains(self, feature: u16) -> bool {
+        self.0 & feature == feature
+    }
+
+    /// Whether any of `features` is used
+    pub fn intersects(self, features: u16) -> bool {
+        self.0 & features != 0
+    }
+}
+
+/// Lightweight bot filter on who may fill an escrow, set at InitEscrow. A taker passes by
+/// meeting either of the checks it sets; a gate setting neither admits anyone.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct TakerGate {
+    /// Lamports the taker's wallet must hold, 0 for no balance check
+    pub min_lamports: u64,
+    /// Mint of a badge token the taker must hold some of, the default pubkey for none
+    pub badge_mint: Pubkey,
+}
+
+impl TakerGate {
+    pub const LEN: usize = 40;
+
+    /// Whether the gate sets no check at all
+    pub fn is_open(&self) -> bool {
+        self.min_lamports == 0 && !self.has_badge()
+    }
+
+    /// Whether takers may qualify by holding the badge, passing their badge token account
+    pub fn has_badge(&self) -> bool {
+        self.badge_mint != Pubkey::default()
+    }
+
+    /// Whether a taker whose wallet holds `lamports` and whose badge account holds
+    /// `badge_amount` may fill
+    pub fn admits(&self, lamports: u64, badge_amount: u64) -> bool {
+        self.is_open()
+            || (self.min_lamports != 0 && lamports >= self.min_lamports)
+            || (self.has_badge() && badge_amount > 0)
+    }
+}
+
+/// Hash-timelock of a cross-chain swap, set at InitEscrow. Until the escrow expires, its
+/// `recipient` may Redeem the deposit by revealing a preimage of `hash`, which the other chain's
+/// leg of the swap then settles with. Naming the recipient keeps anyone who sees the preimage
+/// in flight from redeeming in their place.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct HashLock {
+    /// sha256 of the preimage, all zero for no lock
+    pub hash: [u8; 32],
+    /// The only signer Redeem releases the deposit to
+    pub recipient: Pubkey,
+}
+
+impl HashLock {
+    pub const LEN: usize = 64;
+
+    /// Whether the escrow is hash-locked at all
+    pub fn is_set(&self) -> bool {
+        self.hash != [0; 32]
+    }
+
+    /// Whether `preimage` unlocks the escrow
+    pub fn unlocks(&self, preimage: &[u8; 32]) -> bool {
+        self.is_set() && hashv(&[preimage]).to_bytes() == self.hash
+    }
+}
+
+/// Owner of the Pyth price accounts oracle-priced escrows read
+pub const PYTH_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+/// Most slots an oracle price may lag behind the fill, so a taker can't fill at a price the
+/// market has moved away from
+pub const MAX_ORACLE_STALENESS_SLOTS: u64 = 25;
+
+/// Widest confidence interval an oracle price may have, in basis points of the price
+pub const MAX_ORACLE_CONFIDENCE_BPS: u64 = 100;
+
+/// Prices an escrow's fill off a Pyth price account, quoting the deposit mint in the
+/// expected mint, moved by `offset_bps`. The escrow's `expected_amount` stays the least the
+/// initializer accepts, whatever the oracle says.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct PriceBand {
+    /// The Pyth price account, the default pubkey for a fixed price
+    pub price_account: Pubkey,
+    /// Basis points the payment sits above the oracle's price, or below it when negative.
+    /// Never -10000 or less, which would give the deposit away.
+    pub offset_bps: i16,
+}
+
+impl PriceBand {
+    pub const LEN: usize = 34;
+
+    /// Whether the escrow is priced off an oracle at all
+    pub fn is_set(&self) -> bool {
+        self.price_account != Pubkey::default()
+    }
+
+    /// What a fill of `fill` costs at `price`, given the deposit and expected mints'
+    /// decimals, rounded for the initializer by `rounding_mode`. None on overflow.
+    pub fn payment(
+        &self,
+        price: &OraclePrice,
+        fill: DepositAmount,
+        deposit_decimals: u8,
+        expected_decimals: u8,
+        rounding_mode: RoundingMode,
+    ) -> Option<PaymentAmount> {
+        let mut product = (fill.0 as u128)
+            .checked_mul(price.price as u128)?
+            .checked_mul((TOTAL_PAYOUT_BPS as i32 + self.offset_bps as i32) as u128)?;
+        let mut denominator = TOTAL_PAYOUT_BPS as u128;
+        let exponent = price.expo + expected_decimals as i32 - deposit_decimals as i32;
+        if exponent >= 0 {
+            product = product.checked_mul(10u128.checked_pow(exponent as u32)?)?;
+        } else {
+            denominator = denominator.checked_mul(10u128.checked_pow(exponent.unsigned_abs())?)?;
+        }
+        u64::try_from(rounding_mode.maker_quotient(product, denominator))
+            .ok()
+            .map(PaymentAmount)
+    }
+}
+
+/// The aggregate price of a Pyth price account: `price * 10^expo` units of the quote token
+/// per unit of the base token, give or take `confidence`
+pub struct OraclePrice {
+    pub price: i64,
+    pub confidence: u64,
+    pub expo: i32,
+    /// Slot the aggregate was last published in
+    pub publish_slot: u64,
+}
+
+impl OraclePrice {
+    const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+    const PYTH_VERSION: u32 = 2;
+    const PYTH_PRICE_ACCOUNT: u32 = 3;
+    const PYTH_STATUS_TRADING: u32 = 1;
+
+    /// Reads the aggregate price from a Pyth price account's data: magic, version and account
+    /// type words, then the exponent at 20 and the aggregate's price, confidence, status and
+    /// publish slot from 208. Fails unless the price is trading and positive.
+    pub fn unpack_pyth(data: &[u8]) -> Result<OraclePrice, ProgramError> {
+        let word = |at: usize| -> Result<[u8; 4], ProgramError> {
+            data.get(at..at + 4)
+                .and_then(|word| word.try_into().ok())
+                .ok_or(ProgramError::InvalidAccountData)
+        };
+        let double = |at: usize| -> Result<[u8; 8], ProgramError> {
+            data.get(at..at + 8)
+                .and_then(|double| double.try_into().ok())
+                .ok_or(ProgramError::InvalidAccountData)
+        };
+        if u32::from_le_bytes(word(0)?) != Self::PYTH_MAGIC
+            || u32::from_le_bytes(word(4)?) != Self::PYTH_VERSION
+            || u32::from_le_bytes(word(8)?) != Self::PYTH_PRICE_ACCOUNT
+            || u32::from_le_bytes(word(224)?) != Self::PYTH_STATUS_TRADING
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let price = OraclePrice {
+            price: i64::from_le_bytes(double(208)?),
+            confidence: u64::from_le_bytes(double(216)?),
+            expo: i32::from_le_bytes(word(20)?),
+            publish_slot: u64::from_le_bytes(double(232)?),
+        };
+        if price.price <= 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(price)
+    }
+
+    /// Fails with `OracleStale` if the price was published more than
+    /// `MAX_ORACLE_STALENESS_SLOTS` before `slot`, and with `OracleUncertain` if its
+    /// confidence interval is wider than `MAX_ORACLE_CONFIDENCE_BPS` of it
+    pub fn check_usable(&self, slot: u64) -> Result<(), EscrowError> {
+        if slot.saturating_sub(self.publish_slot) > MAX_ORACLE_STALENESS_SLOTS {
+            return Err(EscrowError::OracleStale);
+        }
+        if self.confidence as u128 * TOTAL_PAYOUT_BPS as u128
+            > self.price as u128 * MAX_ORACLE_CONFIDENCE_BPS as u128
+        {
+            return Err(EscrowError::OracleUncertain);
+        }
+        Ok(())
+    }
+}
 
 pub struct Escrow {
-    pub is_initialized: bool,
+    pub flags: EscrowFlags,
+    /// Who opened the escrow. PrecreateEscrows sets it ahead of init, reserving the account
+    /// for the initializer who paid its rent.
     pub initializer_pubkey: Pubkey,
     pub temp_token_account_pubkey: Pubkey,
     pub initializer_token_to_receive_account_pubkey: Pubkey,
-    pub expected_amount: u64,
+    pub expected_amount: PaymentAmount,
     pub unlock_time: u64,
+    pub payout_count: u8,
+    pub payouts: [Payout; MAX_PAYOUTS],
+    pub config_pubkey: Pubkey,
+    /// Mint of the token the initializer receives, recorded at init
+    pub expected_mint: Pubkey,
+    /// Slot the escrow was initialized in, used to age the offer for fee tiers
+    pub init_slot: u64,
+    pub rounding_mode: RoundingMode,
+    /// Slot from which the escrow can no longer be filled and anyone may reap it, 0 for never.
+    /// Set at init from the config's `max_lifetime_slots`.
+    pub lifetime_expiry_slot: u64,
+    /// Token account Cancel and Reap refund the deposit to, recorded at init. The default pubkey
+    /// means the initializer's associated token account for the deposited mint.
+    pub refund_token_account_pubkey: Pubkey,
+    /// `taker_commitment(taker, salt)` of the only taker allowed to fill the escrow, all zero
+    /// for an offer open to anyone
+    pub taker_commitment: [u8; 32],
+    /// What InitEscrow found risky about the deposited mint, see `EscrowFlags::RISK_FLAGGED`
+    pub deposit_mint_risk: MintRiskFlags,
+    /// The escrow's share of a shared vault, which other escrows of the same initializer and
+    /// mint deposit into as well. 0 when the escrow has a vault of its own and gets all of it.
+    pub vault_deposit: DepositAmount,
+    /// Program Exchange hands the initializer's proceeds to, whitelisted by the config at init.
+    /// The default pubkey pays them to the receive account as usual.
+    pub settlement_program: Pubkey,
+    /// Bump of the PDA owning the vault, derived from `[b"escrow", vault]`. Every vault has an
+    /// authority of its own, so escrows only share one when they share a vault.
+    pub vault_authority_bump: u8,
+    /// Bump of the receipt mint PDA, `[b"receipt", escrow]`, recorded by MintReceipt
+    pub receipt_mint_bump: u8,
+    /// Bump of the settlement PDA, recorded at init for escrows with a settlement hook
+    pub settlement_bump: u8,
+    /// What the escrow opted into, consistent with the fields above by construction
+    pub features: EscrowFeatures,
+    /// Token X released to takers by partial fills so far. What's left is the escrow's
+    /// `deposit`, which every fill draws down.
+    pub filled_amount: DepositAmount,
+    /// Slot of the last ResetTimeLock, 0 if there was none
+    pub last_reset_slot: u64,
+    /// ResetTimeLocks so far, at most `MAX_TIME_LOCK_RESETS`
+    pub reset_count: u8,
+    /// Who may fill the escrow, see `EscrowFeatures::TAKER_GATE`
+    pub taker_gate: TakerGate,
+    /// Mint of the token the initializer offers, recorded at init from the vault
+    pub deposit_mint: Pubkey,
+    /// Token program the vault belonged to at init. Every later token CPI for the escrow
+    /// requires this same program, whichever of spl-token and Token-2022 it is.
+    pub token_program: Pubkey,
+    /// Who may Redeem the deposit and with what, see `EscrowFeatures::HASH_LOCK`
+    pub hash_lock: HashLock,
+    /// Lamports the escrow account holds above its rent for the taker's transaction fees,
+    /// see `EscrowFeatures::GAS_REBATE`
+    pub gas_rebate: u64,
+    /// Ethereum address whose secp256k1 signature can stand in for the initializer's on a
+    /// permit, all zero for none. Set by SetPermitEthSigner.
+    pub permit_eth_signer: [u8; 20],
+    /// The oracle the escrow's fills are priced off, see `EscrowFeatures::ORACLE_PRICE`
+    pub price_band: PriceBand,
+    /// What `expected_amount` is denominated in, see `EscrowFeatures::INTEREST_ADJUSTED`
+    pub quote_convention: QuoteConvention,
+    /// Slots after a partial fill during which Exchange and MultiExchange turn takers away,
+    /// only ever set along with `EscrowFeatures::PARTIAL_FILL`
+    pub fill_cooldown_slots: u64,
+    /// Slot of the last partial fill, 0 if there was none
+    pub last_fill_slot: u64,
+    /// The only signer Exchange takes as the taker, the default pubkey for an offer open to
+    /// anyone. Unlike `taker_commitment` it's public, so clients can tell who an offer is for.
+    pub designated_taker: Pubkey,
+}
+
+/// Hash committing to a private offer's counterparty, revealed by the taker passing `salt`
+/// to Exchange
+pub fn taker_commitment(taker: &Pubkey, salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[taker.as_ref(), salt]).to_bytes()
+}
+
+/// An initializer's off-chain approval for `taker` to fill their escrow whole for `payment`,
+/// up to `valid_until_slot`. The initializer signs `message` and ExchangeWithPermit checks the
+/// signature through the ed25519 program, so they never pay for the repricing.
+pub struct Permit {
+    pub escrow: Pubkey,
+    /// The escrow's `init_slot`, so a permit dies with the escrow even if its address is
+    /// opened again
+    pub init_slot: u64,
+    pub taker: Pubkey,
+    pub payment: PaymentAmount,
+    pub valid_until_slot: u64,
+}
+
+impl Permit {
+    /// Starts every permit message, so a permit's signature can't be passed off as one over
+    /// anything else the initializer signs
+    pub const DOMAIN: &'static [u8; 20] = b"solana-escrow permit";
+    pub const MESSAGE_LEN: usize = 20 + 32 + 32 + 8 + 32 + 8 + 8;
+    /// What Ethereum wallets prepend to a message they sign, `personal_sign`'s prefix for a
+    /// message of `MESSAGE_LEN` bytes
+    pub const ETH_PREFIX: &'static [u8; 29] = b"\x19Ethereum Signed Message:\n140";
+    pub const ETH_MESSAGE_LEN: usize = 29 + Permit::MESSAGE_LEN;
+
+    /// The bytes the initializer signs, for the escrow program `program_id`
+    pub fn message(&self, program_id: &Pubkey) -> [u8; Permit::MESSAGE_LEN] {
+        let mut message = [0u8; Permit::MESSAGE_LEN];
+        let (domain, program, escrow, init_slot, taker, payment, valid_until_slot) =
+            mut_array_refs![&mut message, 20, 32, 32, 8, 32, 8, 8];
+        domain.copy_from_slice(Self::DOMAIN);
+        program.copy_from_slice(program_id.as_ref());
+        escrow.copy_from_slice(self.escrow.as_ref());
+        *init_slot = self.init_slot.to_le_bytes();
+        taker.copy_from_slice(self.taker.as_ref());
+        *payment = self.payment.0.to_le_bytes();
+        *valid_until_slot = self.valid_until_slot.to_le_bytes();
+        message
+    }
+
+    /// The bytes an Ethereum signer's secp256k1 signature covers: `message` as their wallet
+    /// signs it
+    pub fn eth_message(&self, program_id: &Pubkey) -> [u8; Permit::ETH_MESSAGE_LEN] {
+        let mut eth_message = [0u8; Permit::ETH_MESSAGE_LEN];
+        let (prefix, message) = mut_array_refs![&mut eth_message, 29, Permit::MESSAGE_LEN];
+        prefix.copy_from_slice(Self::ETH_PREFIX);
+        *message = self.message(program_id);
+        eth_message
+    }
+}
+
+impl Escrow {
+    /// The payout split registered at init, empty when the initializer's receive account gets everything
+    pub fn payouts(&self) -> &[Payout] {
+        &self.payouts[..self.payout_count as usize]
+    }
+
+    /// The only account the deposit may be refunded to, given the vault's mint and token program
+    pub fn refund_destination(&self, deposit_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+        if self.refund_token_account_pubkey == Pubkey::default() {
+            get_associated_token_address_with_program_id(&self.initializer_pubkey, deposit_mint, token_program)
+        } else {
+            self.refund_token_account_pubkey
+        }
+    }
+
+    /// The features the other fields imply, all but the `EscrowFeatures::DECLARED` ones
+    pub fn implied_features(&self) -> u16 {
+        let mut bits = 0;
+        if self.payout_count != 0 {
+            bits |= EscrowFeatures::PAYOUTS;
+        }
+        if self.taker_commitment != [0; 32] {
+            bits |= EscrowFeatures::PRIVATE;
+        }
+        if self.vault_deposit.0 != 0 {
+            bits |= EscrowFeatures::SHARED_VAULT;
+        }
+        if self.settlement_program != Pubkey::default() {
+            bits |= EscrowFeatures::SETTLEMENT_HOOK;
+        }
+        if !self.taker_gate.is_open() {
+            bits |= EscrowFeatures::TAKER_GATE;
+        }
+        if self.hash_lock.is_set() {
+            bits |= EscrowFeatures::HASH_LOCK;
+        }
+        if self.gas_rebate != 0 {
+            bits |= EscrowFeatures::GAS_REBATE;
+        }
+        if self.price_band.is_set() {
+            bits |= EscrowFeatures::ORACLE_PRICE;
+        }
+        if self.quote_convention != QuoteConvention::Raw {
+            bits |= EscrowFeatures::INTEREST_ADJUSTED;
+        }
+        if self.designated_taker != Pubkey::default() {
+            bits |= EscrowFeatures::DESIGNATED_TAKER;
+        }
+        bits
+    }
+
+    /// Whether only takers on the escrow's allowlist may fill it
+    pub fn has_taker_allowlist(&self) -> bool {
+        self.features.contains(EscrowFeatures::TAKER_ALLOWLIST)
+    }
+
+    /// Whether takers must pass the escrow's `taker_gate`
+    pub fn has_taker_gate(&self) -> bool {
+        self.features.contains(EscrowFeatures::TAKER_GATE)
+    }
+
+    /// Whether fills pay a premium to the config's insurance pool
+    pub fn is_insured(&self) -> bool {
+        self.features.contains(EscrowFeatures::INSURED)
+    }
+
+    /// Whether the deposit comes with a basket of other assets
+    pub fn has_basket(&self) -> bool {
+        self.features.contains(EscrowFeatures::BASKET)
+    }
+
+    /// Whether the escrow only fills by Settle, against its named taker's deposit
+    pub fn is_mutual_deposit(&self) -> bool {
+        self.features.contains(EscrowFeatures::MUTUAL_DEPOSIT)
+    }
+
+    /// Whether the escrow only fills by Redeem, to its hash lock's recipient
+    pub fn is_hash_locked(&self) -> bool {
+        self.features.contains(EscrowFeatures::HASH_LOCK)
+    }
+
+    /// Whether Exchange prices fills off the escrow's `price_band` oracle
+    pub fn is_oracle_priced(&self) -> bool {
+        self.features.contains(EscrowFeatures::ORACLE_PRICE)
+    }
+
+    /// Whether only a committed taker may fill the escrow
+    pub fn is_private(&self) -> bool {
+        self.features.contains(EscrowFeatures::PRIVATE)
+    }
+
+    /// Whether only `designated_taker` may fill the escrow
+    pub fn has_designated_taker(&self) -> bool {
+        self.features.contains(EscrowFeatures::DESIGNATED_TAKER)
+    }
+
+    /// Whether the vault holds other escrows' deposits too, see `vault_deposit`
+    pub fn shares_vault(&self) -> bool {
+        self.features.contains(EscrowFeatures::SHARED_VAULT)
+    }
+
+    /// What the escrow is owed out of a vault holding `vault_amount`: its own vault's whole
+    /// balance, or its recorded share of a shared one, which the vault must still cover
+    pub fn deposit(&self, vault_amount: u64) -> Result<DepositAmount, EscrowError> {
+        if !self.shares_vault() {
+            return Ok(DepositAmount(vault_amount));
+        }
+        if vault_amount < self.vault_deposit.0 {
+            return Err(EscrowError::VaultShortfall);
+        }
+        Ok(self.vault_deposit)
+    }
+
+    /// What a taker pays for `fill` out of the `remaining` deposit: the rest of
+    /// `expected_amount` for all of it, otherwise a share rounded by `rounding_mode`. A
+    /// partial fill must cost something and leave something to pay for the remainder, so
+    /// repeated fills can't round the price away.
+    pub fn fill_payment(
+        &self,
+        fill: DepositAmount,
+        remaining: DepositAmount,
+    ) -> Result<PaymentAmount, EscrowError> {
+        if fill == remaining {
+            return Ok(self.expected_amount);
+        }
+        if fill.0 == 0 || fill > remaining || !self.features.contains(EscrowFeatures::PARTIAL_FILL) {
+            return Err(EscrowError::InvalidFillAmount);
+        }
+        let payment = self
+            .rounding_mode
+            .maker_share(self.expected_amount.0, fill.0, remaining.0);
+        if payment == 0 || payment >= self.expected_amount.0 {
+            return Err(EscrowError::InvalidFillAmount);
+        }
+        Ok(PaymentAmount(payment))
+    }
+
+    /// Draws a partial fill of `fill` for `payment` at `slot` down from what's left of the
+    /// escrow
+    pub fn record_partial_fill(&mut self, fill: DepositAmount, payment: PaymentAmount, slot: u64) {
+        self.expected_amount.0 -= payment.0;
+        self.last_fill_slot = slot;
+        self.filled_amount.0 += fill.0;
+        if self.shares_vault() {
+            self.vault_deposit.0 -= fill.0;
+        }
+    }
+
+    /// Whether Exchange settles the proceeds into `settlement_program` instead of a wallet
+    pub fn has_settlement_hook(&self) -> bool {
+        self.features.contains(EscrowFeatures::SETTLEMENT_HOOK)
+    }
+
+    /// Whether the escrow's last partial fill was too recent for another one at `slot`
+    pub fn is_cooling_down(&self, slot: u64) -> bool {
+        self.filled_amount.0 != 0 && slot < self.last_fill_slot.saturating_add(self.fill_cooldown_slots)
+    }
+
+    /// Whether the escrow has outlived its config's maximum lifetime at `slot`
+    pub fn is_past_lifetime(&self, slot: u64) -> bool {
+        self.lifetime_expiry_slot != 0 && slot >= self.lifetime_expiry_slot
+    }
+
+    /// Last slot the initializer can cancel in before the offer locks
+    pub fn cancel_window_close_slot(&self) -> u64 {
+        self.unlock_time
+    }
+
+    /// Slot from which an unfilled offer has expired and can be cancelled again
+    pub fn expiry_slot(&self) -> u64 {
+        self.unlock_time.saturating_add(EXPIRY_SLOTS)
+    }
+}
+
+/// Time left until an escrow's deadlines, written to return_data by the `TimeStatus` instruction.
+/// Seconds are estimated from the slot counts at the default slot duration; a deadline
+/// that has passed reads as zero. The initializer's cancel window closes at unlock, see
+/// `Escrow::cancel_window_close_slot`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimeStatus {
+    pub current_slot: u64,
+    pub slots_until_unlock: u64,
+    pub slots_until_expiry: u64,
+    pub seconds_until_unlock: u64,
+    pub seconds_until_expiry: u64,
+}
+
+impl TimeStatus {
+    pub const LEN: usize = 40;
+
+    pub fn new(escrow: &Escrow, current_slot: u64) -> Self {
+        let slots_until = |slot: u64| slot.saturating_sub(current_slot);
+        let seconds = |slots: u64| slots.saturating_mul(DEFAULT_MS_PER_SLOT) / 1000;
+        let slots_until_unlock = slots_until(escrow.unlock_time);
+        let slots_until_expiry = slots_until(escrow.expiry_slot());
+        TimeStatus {
+            current_slot,
+            slots_until_unlock,
+            slots_until_expiry,
+            seconds_until_unlock: seconds(slots_until_unlock),
+            seconds_until_expiry: seconds(slots_until_expiry),
+        }
+    }
+
+    pub fn pack(&self) -> [u8; TimeStatus::LEN] {
+        let mut buf = [0u8; TimeStatus::LEN];
+        let fields = [
+            self.current_slot,
+            self.slots_until_unlock,
+            self.slots_until_expiry,
+            self.seconds_until_unlock,
+            self.seconds_until_expiry,
+        ];
+        for (field, field_dst) in fields.iter().zip(buf.chunks_mut(8)) {
+            field_dst.copy_from_slice(&field.to_le_bytes());
+        }
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Option<Self> {
+        if input.len() != TimeStatus::LEN {
+            return None;
+        }
+        let src = array_ref![input, 0, TimeStatus::LEN];
+        let (
+            current_slot,
+            slots_until_unlock,
+            slots_until_expiry,
+            seconds_until_unlock,
+            seconds_until_expiry,
+        ) = array_refs![src, 8, 8, 8, 8, 8];
+        Some(TimeStatus {
+            current_slot: u64::from_le_bytes(*current_slot),
+            slots_until_unlock: u64::from_le_bytes(*slots_until_unlock),
+            slots_until_expiry: u64::from_le_bytes(*slots_until_expiry),
+            seconds_until_unlock: u64::from_le_bytes(*seconds_until_unlock),
+            seconds_until_expiry: u64::from_le_bytes(*seconds_until_expiry),
+        })
+    }
+}
+
+/// Written to return_data by ValidateExchange when every Exchange check passes
+pub struct ExchangeQuote {
+    /// Amount of token X the taker would receive from the vault
+    pub deposit: DepositAmount,
+    /// Share of the expected amount that would go to the operator
+    pub fee: PaymentAmount,
+    /// What the initializer (or their payouts) would receive after the fee and royalties
+    pub initializer_proceeds: PaymentAmount,
+    /// Fee the taker would pay in the config's fee mint instead, 0 when it has none
+    pub fee_mint_amount: u64,
+    /// Share of the expected amount that would go to the creators of an NFT deposit
+    pub royalties: PaymentAmount,
+    /// Share of the expected amount that would go to the insurance pool of an insured escrow
+    pub insurance_premium: PaymentAmount,
+}
+
+impl ExchangeQuote {
+    pub const LEN: usize = 48;
+
+    pub fn pack(&self) -> [u8; ExchangeQuote::LEN] {
+        let mut buf = [0u8; ExchangeQuote::LEN];
+        let (
+            deposit_dst,
+            fee_dst,
+            initializer_proceeds_dst,
+            fee_mint_amount_dst,
+            royalties_dst,
+            insurance_premium_dst,
+        ) = mut_array_refs![&mut buf, 8, 8, 8, 8, 8, 8];
+        *deposit_dst = self.deposit.0.to_le_bytes();
+        *fee_dst = self.fee.0.to_le_bytes();
+        *initializer_proceeds_dst = self.initializer_proceeds.0.to_le_bytes();
+        *fee_mint_amount_dst = self.fee_mint_amount.to_le_bytes();
+        *royalties_dst = self.royalties.0.to_le_bytes();
+        *insurance_premium_dst = self.insurance_premium.0.to_le_bytes();
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Option<Self> {
+        if input.len() != ExchangeQuote::LEN {
+            return None;
+        }
+        let src = array_ref![input, 0, ExchangeQuote::LEN];
+        let (deposit, fee, initializer_proceeds, fee_mint_amount, royalties, insurance_premium) =
+            array_refs![src, 8, 8, 8, 8, 8, 8];
+        Some(ExchangeQuote {
+            deposit: DepositAmount(u64::from_le_bytes(*deposit)),
+            fee: PaymentAmount(u64::from_le_bytes(*fee)),
+            initializer_proceeds: PaymentAmount(u64::from_le_bytes(*initializer_proceeds)),
+            fee_mint_amount: u64::from_le_bytes(*fee_mint_amount),
+            royalties: PaymentAmount(u64::from_le_bytes(*royalties)),
+            insurance_premium: PaymentAmount(u64::from_le_bytes(*insurance_premium)),
+        })
+    }
+}
+
+/// Owner of the Metaplex token metadata accounts that carry an NFT's royalty terms
+pub const METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Most creators a Metaplex metadata account lists
+pub const MAX_ROYALTY_CREATORS: usize = 5;
+
+// Metaplex's `Key::MetadataV1`, the first byte of a metadata account
+const METADATA_V1_KEY: u8 = 4;
+
+/// A creator owed `share` percent of an NFT's royalties
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoyaltyCreator {
+    pub address: Pubkey,
+    pub share: u8,
+}
+
+/// The royalty terms of an NFT, read from its Metaplex metadata: `seller_fee_bps` of every sale,
+/// split between the creators by their shares. Creators with a zero share are left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Royalties {
+    pub seller_fee_bps: u16,
+    pub creators: Vec<RoyaltyCreator>,
+}
+
+impl Royalties {
+    /// Address of the metadata account of `mint`
+    pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+            &METADATA_PROGRAM_ID,
+        )
+        .0
+    }
+
+    /// Reads the royalty terms from the data of `mint`'s metadata account. It is Borsh encoded:
+    /// key, update authority and mint, then name, symbol and uri as length-prefixed strings,
+    /// the seller fee and an optional list of creators. Whatever follows is ignored.
+    pub fn unpack_metadata(data: &[u8], mint: &Pubkey) -> Result<Royalties, ProgramError> {
+        fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ProgramError> {
+            if data.len() < len {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let (taken, rest) = data.split_at(len);
+            *data = rest;
+            Ok(taken)
+        }
+        fn take_u32(data: &mut &[u8]) -> Result<u32, ProgramError> {
+            Ok(u32::from_le_bytes(*array_ref![take(data, 4)?, 0, 4]))
+        }
+
+        let mut data = data;
+        if take(&mut data, 1)? != [METADATA_V1_KEY] {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let _update_authority = take(&mut data, 32)?;
+        if take(&mut data, 32)? != mint.as_ref() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        for _string in 0..3 {
+            let len = take_u32(&mut data)? as usize;
+            take(&mut data, len)?;
+        }
+        let seller_fee_bps = u16::from_le_bytes(*array_ref![take(&mut data, 2)?, 0, 2]);
+        if seller_fee_bps > TOTAL_PAYOUT_BPS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut creators = Vec::new();
+        if take(&mut data, 1)? == [1] {
+            let count = take_u32(&mut data)? as usize;
+            if count > MAX_ROYALTY_CREATORS {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let mut total_share = 0u8;
+            for _ in 0..count {
+                let creator = take(&mut data, 34)?;
+                let (address, _verified, share) = array_refs![array_ref![creator, 0, 34], 32, 1, 1];
+                total_share = total_share.saturating_add(share[0]);
+                if share[0] > 0 {
+                    creators.push(RoyaltyCreator {
+                        address: Pubkey::new_from_array(*address),
+                        share: share[0],
+                    });
+                }
+            }
+            if total_share != 100 {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+        Ok(Royalties {
+            seller_fee_bps,
+            creators,
+        })
+    }
+
+    /// Each creator's royalty on `payment`, in the order of `creators`. Both the royalties and
+    /// every share of them round down, and the dust stays with the seller.
+    pub fn split(&self, payment: PaymentAmount) -> Vec<PaymentAmount> {
+        let total = payment.bps_share(self.seller_fee_bps);
+        self.creators
+            .iter()
+            .map(|creator| PaymentAmount((total.0 as u128 * creator.share as u128 / 100) as u64))
+            .collect()
+    }
 }
 
 impl Sealed for Escrow {}
 
 impl IsInitialized for Escrow {
     fn is_initialized(&self) -> bool {
-        self.is_initialized
+        self.flags.is_initialized()
     }
 }
 
 impl Pack for Escrow {
-    const LEN: usize = 105;
+    const LEN: usize = 737;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, Escrow::LEN];
         let (
-            is_initialized,
+            flags,
             initializer_pubkey,
             temp_token_account_pubkey,
             initializer_token_to_receive_account_pubkey,
             expected_amount,
             unlock_time,
-        ) = array_refs![src, 1, 32, 32, 32, 8, 8];
-        let is_initialized = match is_initialized {
-            [0] => false,
-            [1] => true,
-            _ => return Err(ProgramError::InvalidAccountData),
-        };
+            payout_count,
+            payouts_src,
+            config_pubkey,
+            expected_mint,
+            init_slot,
+            rounding_mode,
+            lifetime_expiry_slot,
+            refund_token_account_pubkey,
+            taker_commitment,
+            deposit_mint_risk,
+            vault_deposit,
+            settlement_program,
+            vault_authority_bump,
+            receipt_mint_bump,
+            settlement_bump,
+            features,
+            filled_amount,
+            last_reset_slot,
+            reset_count,
+            taker_min_lamports,
+            taker_badge_mint,
+            deposit_mint,
+            token_program,
+            hash_lock_hash,
+            hash_lock_recipient,
+            gas_rebate,
+            permit_eth_signer,
+            price_account,
+            price_offset_bps,
+            quote_convention,
+            fill_cooldown_slots,
+            last_fill_slot,
+            designated_taker,
+        ) = array_refs![src, 1, 32, 32, 32, 8, 8, 1, Payout::LEN * MAX_PAYOUTS, 32, 32, 8, 1, 8, 32, 32, 1, 8, 32, 1, 1, 1, 2, 8, 8, 1, 8, 32, 32, 32, 32, 32, 8, 20, 32, 2, 1, 8, 8, 32];
+        let flags = EscrowFlags::from_bits(flags[0]).ok_or(ProgramError::InvalidAccountData)?;
+        let rounding_mode =
+            RoundingMode::from_u8(rounding_mode[0]).ok_or(ProgramError::InvalidAccountData)?;
+        let quote_convention = QuoteConvention::from_u8(quote_convention[0])
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let deposit_mint_risk =
+            MintRiskFlags::from_bits(deposit_mint_risk[0]).ok_or(ProgramError::InvalidAccountData)?;
+        let features = EscrowFeatures::from_bits(u16::from_le_bytes(*features))
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let payout_count = payout_count[0];
+        if payout_count as usize > MAX_PAYOUTS {
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        Ok(Escrow {
-            is_initialized,
+        let mut payouts = [Payout::default(); MAX_PAYOUTS];
+        for (payout, payout_src) in payouts.iter_mut().zip(payouts_src.chunks(Payout::LEN)) {
+            let payout_src = array_ref![payout_src, 0, Payout::LEN];
+            let (token_account_pubkey, bps) = array_refs![payout_src, 32, 2];
+            payout.token_account_pubkey = Pubkey::new_from_array(*token_account_pubkey);
+            payout.bps = u16::from_le_bytes(*bps);
+        }
+
+        let escrow = Escrow {
+            flags,
             initializer_pubkey: Pubkey::new_from_array(*initializer_pubkey),
             temp_token_account_pubkey: Pubkey::new_from_array(*temp_token_account_pubkey),
             initializer_token_to_receive_account_pubkey: Pubkey::new_from_array(
                 *initializer_token_to_receive_account_pubkey,
             ),
-            expected_amount: u64::from_le_bytes(*expected_amount),
+            expected_amount: PaymentAmount(u64::from_le_bytes(*expected_amount)),
             unlock_time: u64::from_le_bytes(*unlock_time),
-        })
+            payout_count,
+            payouts,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            expected_mint: Pubkey::new_from_array(*expected_mint),
+            init_slot: u64::from_le_bytes(*init_slot),
+            rounding_mode,
+            lifetime_expiry_slot: u64::from_le_bytes(*lifetime_expiry_slot),
+            refund_token_account_pubkey: Pubkey::new_from_array(*refund_token_account_pubkey),
+            taker_commitment: *taker_commitment,
+            deposit_mint_risk,
+            vault_deposit: DepositAmount(u64::from_le_bytes(*vault_deposit)),
+            settlement_program: Pubkey::new_from_array(*settlement_program),
+            vault_authority_bump: vault_authority_bump[0],
+            receipt_mint_bump: receipt_mint_bump[0],
+            settlement_bump: settlement_bump[0],
+            features,
+            filled_amount: DepositAmount(u64::from_le_bytes(*filled_amount)),
+            last_reset_slot: u64::from_le_bytes(*last_reset_slot),
+            reset_count: reset_count[0],
+            taker_gate: TakerGate {
+                min_lamports: u64::from_le_bytes(*taker_min_lamports),
+                badge_mint: Pubkey::new_from_array(*taker_badge_mint),
+            },
+            deposit_mint: Pubkey::new_from_array(*deposit_mint),
+            token_program: Pubkey::new_from_array(*token_program),
+            hash_lock: HashLock {
+                hash: *hash_lock_hash,
+                recipient: Pubkey::new_from_array(*hash_lock_recipient),
+            },
+            gas_rebate: u64::from_le_bytes(*gas_rebate),
+            permit_eth_signer: *permit_eth_signer,
+            price_band: PriceBand {
+                price_account: Pubkey::new_from_array(*price_account),
+                offset_bps: i16::from_le_bytes(*price_offset_bps),
+            },
+            quote_convention,
+            fill_cooldown_slots: u64::from_le_bytes(*fill_cooldown_slots),
+            last_fill_slot: u64::from_le_bytes(*last_fill_slot),
+            designated_taker: Pubkey::new_from_array(*designated_taker),
+        };
+        if escrow.features.bits() & !EscrowFeatures::DECLARED != escrow.implied_features()
+            || ((escrow.filled_amount.0 != 0 || escrow.fill_cooldown_slots != 0)
+                && !escrow.features.contains(EscrowFeatures::PARTIAL_FILL))
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(escrow)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let dst = array_mut_ref![dst, 0, Escrow::LEN];
         let (
-            is_initialized_dst,
+            flags_dst,
             initializer_pubkey_dst,
             temp_token_account_pubkey_dst,
             initializer_token_to_receive_account_pubkey_dst,
             expected_amount_dst,
             unlock_time_dst,
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 8, 8];
+            payout_count_dst,
+            payouts_dst,
+            config_pubkey_dst,
+            expected_mint_dst,
+            init_slot_dst,
+            rounding_mode_dst,
+            lifetime_expiry_slot_dst,
+            refund_token_account_pubkey_dst,
+            taker_commitment_dst,
+            deposit_mint_risk_dst,
+            vault_deposit_dst,
+            settlement_program_dst,
+            vault_authority_bump_dst,
+            receipt_mint_bump_dst,
+            settlement_bump_dst,
+            features_dst,
+            filled_amount_dst,
+            last_reset_slot_dst,
+            reset_count_dst,
+            taker_min_lamports_dst,
+            taker_badge_mint_dst,
+            deposit_mint_dst,
+            token_program_dst,
+            hash_lock_hash_dst,
+            hash_lock_recipient_dst,
+            gas_rebate_dst,
+            permit_eth_signer_dst,
+            price_account_dst,
+            price_offset_bps_dst,
+            quote_convention_dst,
+            fill_cooldown_slots_dst,
+            last_fill_slot_dst,
+            designated_taker_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 32, 8, 8, 1, Payout::LEN * MAX_PAYOUTS, 32, 32, 8, 1, 8, 32, 32, 1, 8, 32, 1, 1, 1, 2, 8, 8, 1, 8, 32, 32, 32, 32, 32, 8, 20, 32, 2, 1, 8, 8, 32];
 
         let Escrow {
-            is_initialized,
+            flags,
             initializer_pubkey,
             temp_token_account_pubkey,
             initializer_token_to_receive_account_pubkey,
             expected_amount,
+            unlock_time,
+            payout_count,
+            payouts,
+            config_pubkey,
+            expected_mint,
+            init_slot,
+            rounding_mode,
+            lifetime_expiry_slot,
+            refund_token_account_pubkey,
+            taker_commitment,
+            deposit_mint_risk,
+            vault_deposit,
+            settlement_program,
+            vault_authority_bump,
+            receipt_mint_bump,
+            settlement_bump,
+            features,
+            filled_amount,
+            last_reset_slot,
+            reset_count,
+            taker_gate,
+            deposit_mint,
+            token_program,
+            hash_lock,
+            gas_rebate,
+            permit_eth_signer,
+            price_band,
+            quote_convention,
+            fill_cooldown_slots,
+            last_fill_slot,
+            designated_taker,
         } = self;
 
-        is_initialized_dst[0] = *is_initialized as u8;
+        flags_dst[0] = flags.bits();
         initializer_pubkey_dst.copy_from_slice(initializer_pubkey.as_ref());
         temp_token_account_pubkey_dst.copy_from_slice(temp_token_account_pubkey.as_ref());
         initializer_token_to_receive_account_pubkey_dst
             .copy_from_slice(initializer_token_to_receive_account_pubkey.as_ref());
-        *expected_amount_dst = expected_amount.to_le_bytes();
+        *expected_amount_dst = expected_amount.0.to_le_bytes();
         *unlock_time_dst = unlock_time.to_le_bytes();
+        payout_count_dst[0] = *payout_count;
+        for (payout, payout_dst) in payouts.iter().zip(payouts_dst.chunks_mut(Payout::LEN)) {
+            let payout_dst = array_mut_ref![payout_dst, 0, Payout::LEN];
+            let (token_account_pubkey_dst, bps_dst) = mut_array_refs![payout_dst, 32, 2];
+            token_account_pubkey_dst.copy_from_slice(payout.token_account_pubkey.as_ref());
+            *bps_dst = payout.bps.to_le_bytes();
+        }
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        expected_mint_dst.copy_from_slice(expected_mint.as_ref());
+        *init_slot_dst = init_slot.to_le_bytes();
+        rounding_mode_dst[0] = *rounding_mode as u8;
+        *lifetime_expiry_slot_dst = lifetime_expiry_slot.to_le_bytes();
+        refund_token_account_pubkey_dst.copy_from_slice(refund_token_account_pubkey.as_ref());
+        *taker_commitment_dst = *taker_commitment;
+        deposit_mint_risk_dst[0] = deposit_mint_risk.bits();
+        *vault_deposit_dst = vault_deposit.0.to_le_bytes();
+        settlement_program_dst.copy_from_slice(settlement_program.as_ref());
+        vault_authority_bump_dst[0] = *vault_authority_bump;
+        receipt_mint_bump_dst[0] = *receipt_mint_bump;
+        settlement_bump_dst[0] = *settlement_bump;
+        *features_dst = features.bits().to_le_bytes();
+        *filled_amount_dst = filled_amount.0.to_le_bytes();
+        *last_reset_slot_dst = last_reset_slot.to_le_bytes();
+        reset_count_dst[0] = *reset_count;
+        *taker_min_lamports_dst = taker_gate.min_lamports.to_le_bytes();
+        taker_badge_mint_dst.copy_from_slice(taker_gate.badge_mint.as_ref());
+        deposit_mint_dst.copy_from_slice(deposit_mint.as_ref());
+        token_program_dst.copy_from_slice(token_program.as_ref());
+        *hash_lock_hash_dst = hash_lock.hash;
+        hash_lock_recipient_dst.copy_from_slice(hash_lock.recipient.as_ref());
+        *gas_rebate_dst = gas_rebate.to_le_bytes();
+        *permit_eth_signer_dst = *permit_eth_signer;
+        price_account_dst.copy_from_slice(price_band.price_account.as_ref());
+        *price_offset_bps_dst = price_band.offset_bps.to_le_bytes();
+        quote_convention_dst[0] = *quote_convention as u8;
+        *fill_cooldown_slots_dst = fill_cooldown_slots.to_le_bytes();
+        *last_fill_slot_dst = last_fill_slot.to_le_bytes();
+        designated_taker_dst.copy_from_slice(designated_taker.as_ref());
+    }
+}
+
+pub struct EscrowStats {
+    pub is_initialized: bool,
+    pub open_escrows: u64,
+    pub locked_rent: u64,
+    /// Sequence number of the last emitted `EscrowEvent`, zero before the first
+    pub event_sequence: u64,
+    /// Bump of the stats PDA, found by InitStats
+    pub bump: u8,
+}
+
+impl Sealed for EscrowStats {}
+
+impl IsInitialized for EscrowStats {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for EscrowStats {
+    const LEN: usize = 26;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, EscrowStats::LEN];
+        let (is_initialized, open_escrows, locked_rent, event_sequence, bump) =
+            array_refs![src, 1, 8, 8, 8, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(EscrowStats {
+            is_initialized,
+            open_escrows: u64::from_le_bytes(*open_escrows),
+            locked_rent: u64::from_le_bytes(*locked_rent),
+            event_sequence: u64::from_le_bytes(*event_sequence),
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, EscrowStats::LEN];
+        let (is_initialized_dst, open_escrows_dst, locked_rent_dst, event_sequence_dst, bump_dst) =
+            mut_array_refs![dst, 1, 8, 8, 8, 1];
+
+        let EscrowStats {
+            is_initialized,
+            open_escrows,
+            locked_rent,
+            event_sequence,
+            bump,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        *open_escrows_dst = open_escrows.to_le_bytes();
+        *locked_rent_dst = locked_rent.to_le_bytes();
+        *event_sequence_dst = event_sequence.to_le_bytes();
+        bump_dst[0] = *bump;
+    }
+}
+
+/// Per-operator policy, at the PDA derived from `[b"config", admin]`. Each front-end
+/// sharing the deployment runs under its own config, chosen by the initializer at init.
+pub struct Config {
+    pub is_initialized: bool,
+    pub admin_pubkey: Pubkey,
+    /// Share of the taker's payment routed to the operator, in basis points
+    pub fee_bps: u16,
+    /// Wallet that must own the token accounts fees are paid into
+    pub fee_recipient_pubkey: Pubkey,
+    /// Smallest expected amount an escrow may ask for
+    pub min_expected_amount: PaymentAmount,
+    /// Largest expected amount an escrow may ask for, 0 for no limit
+    pub max_expected_amount: PaymentAmount,
+    pub fee_tier_count: u8,
+    /// Discounted fees for offers that rested on-chain longer, by ascending `min_age_slots`
+    pub fee_tiers: [FeeTier; MAX_FEE_TIERS],
+    /// Whether escrows under this config are recorded in its audit log PDA
+    pub audit_log_enabled: bool,
+    /// Slots after init at which escrows under this config expire, 0 to let them live forever
+    pub max_lifetime_slots: u64,
+    /// Mint the taker pays the fee in instead of token Y, the default pubkey for none
+    pub fee_mint: Pubkey,
+    /// Fee mint base units charged per `FEE_RATE_SCALE` base units of token Y fee
+    pub fee_mint_rate: u64,
+    /// Key allowed to update `fee_mint_rate` besides the admin, the default pubkey for none
+    pub fee_rate_oracle: Pubkey,
+    /// `AllowlistEntry` role bits whose instructions only allowlisted signers may call
+    pub gated_roles: u8,
+    /// Program escrows under this config may settle the initializer's proceeds into, e.g. a
+    /// stake pool or LP adapter, the default pubkey for none
+    pub settlement_program: Pubkey,
+    /// Length of the windows `max_window_volume` applies to, in slots
+    pub volume_window_slots: u64,
+    /// Most token X base units fills of one mint pair may release within a window, 0 for no cap
+    pub max_window_volume: u64,
+    /// Whether InitEscrow and Exchange are halted under this config. Cancels and the other
+    /// ways out stay open so paused escrows can still be unwound.
+    pub paused: bool,
+    /// Shortest cancellation window InitEscrow accepts under this config, 0 for `MIN_UNLOCK_SLOTS`
+    pub min_unlock_slots: u64,
+    /// Longest cancellation window InitEscrow accepts under this config, 0 for `MAX_UNLOCK_SLOTS`
+    pub max_unlock_slots: u64,
+    /// Most escrows InitEscrow may open under this config in one slot, 0 for no limit
+    pub max_inits_per_slot: u32,
+}
+
+impl Config {
+    /// Whether fees are charged in the fee mint rather than sliced from the taker's payment
+    pub fn has_fee_mint(&self) -> bool {
+        self.fee_mint != Pubkey::default()
+    }
+
+    /// Converts a fee valued in token Y into fee mint units at the configured rate, rounding down
+    pub fn fee_in_fee_mint(&self, fee: PaymentAmount) -> Option<u64> {
+        u64::try_from(fee.0 as u128 * self.fee_mint_rate as u128 / FEE_RATE_SCALE as u128).ok()
+    }
+
+    /// Whether escrows may opt into `program` as their settlement hook
+    pub fn allows_settlement_into(&self, program: &Pubkey) -> bool {
+        *program != Pubkey::default() && self.settlement_program == *program
+    }
+
+    /// Whether Exchange tracks each mint pair's volume in its `PairStats` and enforces
+    /// `max_window_volume`
+    pub fn has_volume_cap(&self) -> bool {
+        self.max_window_volume != 0
+    }
+
+    /// Whether InitEscrow counts inits in the config's `RateCounter` and enforces
+    /// `max_inits_per_slot`
+    pub fn has_init_rate_limit(&self) -> bool {
+        self.max_inits_per_slot != 0
+    }
+
+    /// Cancellation windows InitEscrow accepts: the config's own bounds, within the program's
+    pub fn unlock_slots_bounds(&self) -> RangeInclusive<u64> {
+        let min = self.min_unlock_slots.max(MIN_UNLOCK_SLOTS);
+        let max = match self.max_unlock_slots {
+            0 => MAX_UNLOCK_SLOTS,
+            max => max.min(MAX_UNLOCK_SLOTS),
+        };
+        min..=max
+    }
+
+    /// Whether escrows under the config may ask for `amount`, checked whenever their price is set
+    pub fn allows_expected_amount(&self, amount: PaymentAmount) -> bool {
+        amount >= self.min_expected_amount
+            && (self.max_expected_amount.0 == 0 || amount <= self.max_expected_amount)
+    }
+
+    /// Whether the instructions behind `role` are restricted to the config's allowlist
+    pub fn gates(&self, role: u8) -> bool {
+        self.gated_roles & role != 0
+    }
+
+    /// The fee tiers in use, empty when every offer pays `fee_bps`
+    pub fn fee_tiers(&self) -> &[FeeTier] {
+        &self.fee_tiers[..self.fee_tier_count as usize]
+    }
+
+    /// Fee for an offer taken `age_slots` after init: the last tier it has aged into, or `fee_bps`
+    pub fn fee_bps_for_age(&self, age_slots: u64) -> u16 {
+        self.fee_tiers()
+            .iter()
+            .rev()
+            .find(|tier| age_slots >= tier.min_age_slots)
+            .map_or(self.fee_bps, |tier| tier.fee_bps)
+    }
+}
+
+impl Sealed for Config {}
+
+impl IsInitialized for Config {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Config {
+    const LEN: usize = 275;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Config::LEN];
+        let (
+            is_initialized,
+            admin_pubkey,
+            fee_bps,
+            fee_recipient_pubkey,
+            min_expected_amount,
+            max_expected_amount,
+            fee_tier_count,
+            fee_tiers_src,
+            audit_log_enabled,
+            max_lifetime_slots,
+            fee_mint,
+            fee_mint_rate,
+            fee_rate_oracle,
+            gated_roles,
+            settlement_program,
+            volume_window_slots,
+            max_window_volume,
+            paused,
+            min_unlock_slots,
+            max_unlock_slots,
+            max_inits_per_slot,
+        ) = array_refs![src, 1, 32, 2, 32, 8, 8, 1, FeeTier::LEN * MAX_FEE_TIERS, 1, 8, 32, 8, 32, 1, 32, 8, 8, 1, 8, 8, 4];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let audit_log_enabled = match audit_log_enabled {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let paused = match paused {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let fee_tier_count = fee_tier_count[0];
+        if fee_tier_count as usize > MAX_FEE_TIERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut fee_tiers = [FeeTier::default(); MAX_FEE_TIERS];
+        for (fee_tier, fee_tier_src) in fee_tiers.iter_mut().zip(fee_tiers_src.chunks(FeeTier::LEN)) {
+            let fee_tier_src = array_ref![fee_tier_src, 0, FeeTier::LEN];
+            let (min_age_slots, fee_bps) = array_refs![fee_tier_src, 8, 2];
+            fee_tier.min_age_slots = u64::from_le_bytes(*min_age_slots);
+            fee_tier.fee_bps = u16::from_le_bytes(*fee_bps);
+        }
+
+        Ok(Config {
+            is_initialized,
+            admin_pubkey: Pubkey::new_from_array(*admin_pubkey),
+            fee_bps: u16::from_le_bytes(*fee_bps),
+            fee_recipient_pubkey: Pubkey::new_from_array(*fee_recipient_pubkey),
+            min_expected_amount: PaymentAmount(u64::from_le_bytes(*min_expected_amount)),
+            max_expected_amount: PaymentAmount(u64::from_le_bytes(*max_expected_amount)),
+            fee_tier_count,
+            fee_tiers,
+            audit_log_enabled,
+            max_lifetime_slots: u64::from_le_bytes(*max_lifetime_slots),
+            fee_mint: Pubkey::new_from_array(*fee_mint),
+            fee_mint_rate: u64::from_le_bytes(*fee_mint_rate),
+            fee_rate_oracle: Pubkey::new_from_array(*fee_rate_oracle),
+            gated_roles: gated_roles[0],
+            settlement_program: Pubkey::new_from_array(*settlement_program),
+            volume_window_slots: u64::from_le_bytes(*volume_window_slots),
+            max_window_volume: u64::from_le_bytes(*max_window_volume),
+            paused,
+            min_unlock_slots: u64::from_le_bytes(*min_unlock_slots),
+            max_unlock_slots: u64::from_le_bytes(*max_unlock_slots),
+            max_inits_per_slot: u32::from_le_bytes(*max_inits_per_slot),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Config::LEN];
+        let (
+            is_initialized_dst,
+            admin_pubkey_dst,
+            fee_bps_dst,
+            fee_recipient_pubkey_dst,
+            min_expected_amount_dst,
+            max_expected_amount_dst,
+            fee_tier_count_dst,
+            fee_tiers_dst,
+            audit_log_enabled_dst,
+            max_lifetime_slots_dst,
+            fee_mint_dst,
+            fee_mint_rate_dst,
+            fee_rate_oracle_dst,
+            gated_roles_dst,
+            settlement_program_dst,
+            volume_window_slots_dst,
+            max_window_volume_dst,
+            paused_dst,
+            min_unlock_slots_dst,
+            max_unlock_slots_dst,
+            max_inits_per_slot_dst,
+        ) = mut_array_refs![dst, 1, 32, 2, 32, 8, 8, 1, FeeTier::LEN * MAX_FEE_TIERS, 1, 8, 32, 8, 32, 1, 32, 8, 8, 1, 8, 8, 4];
+
+        let Config {
+            is_initialized,
+            admin_pubkey,
+            fee_bps,
+            fee_recipient_pubkey,
+            min_expected_amount,
+            max_expected_amount,
+            fee_tier_count,
+            fee_tiers,
+            audit_log_enabled,
+            max_lifetime_slots,
+            fee_mint,
+            fee_mint_rate,
+            fee_rate_oracle,
+            gated_roles,
+            settlement_program,
+            volume_window_slots,
+            max_window_volume,
+            paused,
+            min_unlock_slots,
+            max_unlock_slots,
+            max_inits_per_slot,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        admin_pubkey_dst.copy_from_slice(admin_pubkey.as_ref());
+        *fee_bps_dst = fee_bps.to_le_bytes();
+        fee_recipient_pubkey_dst.copy_from_slice(fee_recipient_pubkey.as_ref());
+        *min_expected_amount_dst = min_expected_amount.0.to_le_bytes();
+        *max_expected_amount_dst = max_expected_amount.0.to_le_bytes();
+        fee_tier_count_dst[0] = *fee_tier_count;
+        for (fee_tier, fee_tier_dst) in fee_tiers.iter().zip(fee_tiers_dst.chunks_mut(FeeTier::LEN)) {
+            let fee_tier_dst = array_mut_ref![fee_tier_dst, 0, FeeTier::LEN];
+            let (min_age_slots_dst, fee_bps_dst) = mut_array_refs![fee_tier_dst, 8, 2];
+            *min_age_slots_dst = fee_tier.min_age_slots.to_le_bytes();
+            *fee_bps_dst = fee_tier.fee_bps.to_le_bytes();
+        }
+        audit_log_enabled_dst[0] = *audit_log_enabled as u8;
+        *max_lifetime_slots_dst = max_lifetime_slots.to_le_bytes();
+        fee_mint_dst.copy_from_slice(fee_mint.as_ref());
+        *fee_mint_rate_dst = fee_mint_rate.to_le_bytes();
+        fee_rate_oracle_dst.copy_from_slice(fee_rate_oracle.as_ref());
+        gated_roles_dst[0] = *gated_roles;
+        settlement_program_dst.copy_from_slice(settlement_program.as_ref());
+        *volume_window_slots_dst = volume_window_slots.to_le_bytes();
+        *max_window_volume_dst = max_window_volume.to_le_bytes();
+        paused_dst[0] = *paused as u8;
+        *min_unlock_slots_dst = min_unlock_slots.to_le_bytes();
+        *max_unlock_slots_dst = max_unlock_slots.to_le_bytes();
+        *max_inits_per_slot_dst = max_inits_per_slot.to_le_bytes();
+    }
+}
+
+/// A signer's roles under an operator config, at the PDA derived from
+/// `[b"allowlist", config, member]`. Only consulted for the roles the config gates.
+pub struct AllowlistEntry {
+    pub is_initialized: bool,
+    pub config_pubkey: Pubkey,
+    pub member_pubkey: Pubkey,
+    pub roles: u8,
+}
+
+impl AllowlistEntry {
+    /// May open escrows with InitEscrow
+    pub const INIT_ESCROW: u8 = 1 << 0;
+    pub const KNOWN_ROLES: u8 = Self::INIT_ESCROW;
+
+    pub fn has_role(&self, role: u8) -> bool {
+        self.roles & role == role
+    }
+}
+
+impl Sealed for AllowlistEntry {}
+
+impl IsInitialized for AllowlistEntry {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for AllowlistEntry {
+    const LEN: usize = 66;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, AllowlistEntry::LEN];
+        let (is_initialized, config_pubkey, member_pubkey, roles) = array_refs![src, 1, 32, 32, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(AllowlistEntry {
+            is_initialized,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            member_pubkey: Pubkey::new_from_array(*member_pubkey),
+            roles: roles[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, AllowlistEntry::LEN];
+        let (is_initialized_dst, config_pubkey_dst, member_pubkey_dst, roles_dst) =
+            mut_array_refs![dst, 1, 32, 32, 1];
+
+        let AllowlistEntry {
+            is_initialized,
+            config_pubkey,
+            member_pubkey,
+            roles,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        member_pubkey_dst.copy_from_slice(member_pubkey.as_ref());
+        roles_dst[0] = *roles;
+    }
+}
+
+/// Fill volume of one mint pair under an operator config, at the PDA derived from
+/// `[b"pair_stats", config, deposit_mint, expected_mint]`. Only kept for configs with a
+/// volume cap, whose fills of the pair fail once a window's volume would exceed it.
+pub struct PairStats {
+    pub is_initialized: bool,
+    pub config_pubkey: Pubkey,
+    /// Mint of token X, which the volume is counted in
+    pub deposit_mint: Pubkey,
+    pub expected_mint: Pubkey,
+    /// Slot the current window started in
+    pub window_start_slot: u64,
+    /// Token X released by fills since `window_start_slot`
+    pub window_volume: u64,
+    /// Bump of the pair stats PDA, found by InitPairStats
+    pub bump: u8,
+    /// Fills of the pair that passed a `max_payment`, the ones price improvement is counted for
+    pub limit_fills: u64,
+    /// Token Y those fills came in under their takers' `max_payment`, all told
+    pub total_price_improvement: PaymentAmount,
+}
+
+impl PairStats {
+    /// Counts a fill releasing `deposit` at `slot`, rolling over to a new window once the
+    /// config's `volume_window_slots` have passed since the current one started
+    pub fn record_fill(
+        &mut self,
+        deposit: DepositAmount,
+        slot: u64,
+        config: &Config,
+    ) -> Result<(), EscrowError> {
+        if slot >= self.window_start_slot.saturating_add(config.volume_window_slots) {
+            self.window_start_slot = slot;
+            self.window_volume = 0;
+        }
+        let window_volume = self
+            .window_volume
+            .checked_add(deposit.0)
+            .ok_or(EscrowError::AmountOverflow)?;
+        if window_volume > config.max_window_volume {
+            return Err(EscrowError::VolumeCapExceeded);
+        }
+        self.window_volume = window_volume;
+        Ok(())
+    }
+
+    /// Counts a fill that passed a `max_payment`, and by how much it came in under it.
+    /// Saturates rather than failing the fill, these are only statistics.
+    pub fn record_price_improvement(&mut self, price_improvement: PaymentAmount) {
+        self.limit_fills = self.limit_fills.saturating_add(1);
+        self.total_price_improvement =
+            PaymentAmount(self.total_price_improvement.0.saturating_add(price_improvement.0));
+    }
+}
+
+impl Sealed for PairStats {}
+
+impl IsInitialized for PairStats {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for PairStats {
+    const LEN: usize = 130;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, PairStats::LEN];
+        let (
+            is_initialized,
+            config_pubkey,
+            deposit_mint,
+            expected_mint,
+            window_start_slot,
+            window_volume,
+            bump,
+            limit_fills,
+            total_price_improvement,
+        ) = array_refs![src, 1, 32, 32, 32, 8, 8, 1, 8, 8];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(PairStats {
+            is_initialized,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            deposit_mint: Pubkey::new_from_array(*deposit_mint),
+            expected_mint: Pubkey::new_from_array(*expected_mint),
+            window_start_slot: u64::from_le_bytes(*window_start_slot),
+            window_volume: u64::from_le_bytes(*window_volume),
+            bump: bump[0],
+            limit_fills: u64::from_le_bytes(*limit_fills),
+            total_price_improvement: PaymentAmount(u64::from_le_bytes(*total_price_improvement)),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, PairStats::LEN];
+        let (
+            is_initialized_dst,
+            config_pubkey_dst,
+            deposit_mint_dst,
+            expected_mint_dst,
+            window_start_slot_dst,
+            window_volume_dst,
+            bump_dst,
+            limit_fills_dst,
+            total_price_improvement_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 32, 8, 8, 1, 8, 8];
+
+        let PairStats {
+            is_initialized,
+            config_pubkey,
+            deposit_mint,
+            expected_mint,
+            window_start_slot,
+            window_volume,
+            bump,
+            limit_fills,
+            total_price_improvement,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        deposit_mint_dst.copy_from_slice(deposit_mint.as_ref());
+        expected_mint_dst.copy_from_slice(expected_mint.as_ref());
+        *window_start_slot_dst = window_start_slot.to_le_bytes();
+        *window_volume_dst = window_volume.to_le_bytes();
+        bump_dst[0] = *bump;
+        *limit_fills_dst = limit_fills.to_le_bytes();
+        *total_price_improvement_dst = total_price_improvement.0.to_le_bytes();
+    }
+}
+
+/// Escrows opened under an operator config in the latest slot that saw one, at the PDA
+/// derived from `[b"rate_counter", config]`. Only kept for configs with an init rate limit,
+/// whose InitEscrows fail with `RateLimited` once a slot's count reaches it.
+pub struct RateCounter {
+    pub is_initialized: bool,
+    pub config_pubkey: Pubkey,
+    /// Slot `inits` counts the InitEscrows of
+    pub slot: u64,
+    pub inits: u32,
+    /// Bump of the rate counter PDA, found by InitRateCounter
+    pub bump: u8,
+}
+
+impl RateCounter {
+    /// Counts an InitEscrow at `slot`, starting the count over in a new slot
+    pub fn record_init(&mut self, slot: u64, config: &Config) -> Result<(), EscrowError> {
+        if slot != self.slot {
+            self.slot = slot;
+            self.inits = 0;
+        }
+        if self.inits >= config.max_inits_per_slot {
+            return Err(EscrowError::RateLimited);
+        }
+        self.inits += 1;
+        Ok(())
+    }
+}
+
+impl Sealed for RateCounter {}
+
+impl IsInitialized for RateCounter {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for RateCounter {
+    const LEN: usize = 46;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, RateCounter::LEN];
+        let (is_initialized, config_pubkey, slot, inits, bump) = array_refs![src, 1, 32, 8, 4, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(RateCounter {
+            is_initialized,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            slot: u64::from_le_bytes(*slot),
+            inits: u32::from_le_bytes(*inits),
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RateCounter::LEN];
+        let (is_initialized_dst, config_pubkey_dst, slot_dst, inits_dst, bump_dst) =
+            mut_array_refs![dst, 1, 32, 8, 4, 1];
+
+        let RateCounter {
+            is_initialized,
+            config_pubkey,
+            slot,
+            inits,
+            bump,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        *slot_dst = slot.to_le_bytes();
+        *inits_dst = inits.to_le_bytes();
+        bump_dst[0] = *bump;
+    }
+}
+
+/// Highest premium an insurance pool can charge insured fills
+pub const MAX_INSURANCE_PREMIUM_BPS: u16 = 100;
+
+/// An operator's insurance pool, at the PDA derived from `[b"insurance", config]`. The pool
+/// PDA owns one token account per mint it covers, funded by the operator and topped up by the
+/// premiums of insured fills; the config's admin pays claims out of them with PayClaim.
+pub struct InsurancePool {
+    pub is_initialized: bool,
+    pub config_pubkey: Pubkey,
+    /// Share of an insured fill's payment routed to the pool
+    pub premium_bps: u16,
+    /// Bump of the pool PDA, found by InitInsurancePool
+    pub bump: u8,
+    /// Claims paid out of the pool so far
+    pub claims_paid: u64,
+}
+
+impl Sealed for InsurancePool {}
+
+impl IsInitialized for InsurancePool {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for InsurancePool {
+    const LEN: usize = 44;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, InsurancePool::LEN];
+        let (is_initialized, config_pubkey, premium_bps, bump, claims_paid) =
+            array_refs![src, 1, 32, 2, 1, 8];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(InsurancePool {
+            is_initialized,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            premium_bps: u16::from_le_bytes(*premium_bps),
+            bump: bump[0],
+            claims_paid: u64::from_le_bytes(*claims_paid),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, InsurancePool::LEN];
+        let (is_initialized_dst, config_pubkey_dst, premium_bps_dst, bump_dst, claims_paid_dst) =
+            mut_array_refs![dst, 1, 32, 2, 1, 8];
+
+        let InsurancePool {
+            is_initialized,
+            config_pubkey,
+            premium_bps,
+            bump,
+            claims_paid,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        *premium_bps_dst = premium_bps.to_le_bytes();
+        bump_dst[0] = *bump;
+        *claims_paid_dst = claims_paid.to_le_bytes();
+    }
+}
+
+/// The taker's side of a mutual-deposit escrow, at the PDA derived from `[b"mutual", escrow]`.
+/// The PDA owns the vault the taker deposits their payment into, its associated token account
+/// for the expected mint, and Settle swaps it for the escrow's deposit.
+pub struct MutualDeposit {
+    pub is_initialized: bool,
+    pub escrow_pubkey: Pubkey,
+    /// The only taker who may deposit, named by DepositInitializer
+    pub taker_pubkey: Pubkey,
+    /// The taker's token account for the deposit mint, which Settle pays the deposit into
+    pub taker_token_to_receive_account_pubkey: Pubkey,
+    /// The taker's token account their payment came from, and is refunded to by Cancel
+    pub taker_refund_account_pubkey: Pubkey,
+    /// What the taker deposited, 0 until DepositTaker
+    pub taker_deposit: PaymentAmount,
+    /// Bump of the mutual deposit PDA, found by DepositInitializer
+    pub bump: u8,
+}
+
+impl MutualDeposit {
+    pub fn is_funded(&self) -> bool {
+        self.taker_deposit.0 != 0
+    }
+}
+
+impl Sealed for MutualDeposit {}
+
+impl IsInitialized for MutualDeposit {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MutualDeposit {
+    const LEN: usize = 138;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, MutualDeposit::LEN];
+        let (
+            is_initialized,
+            escrow_pubkey,
+            taker_pubkey,
+            taker_token_to_receive_account_pubkey,
+            taker_refund_account_pubkey,
+            taker_deposit,
+            bump,
+        ) = array_refs![src, 1, 32, 32, 32, 32, 8, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(MutualDeposit {
+            is_initialized,
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            taker_pubkey: Pubkey::new_from_array(*taker_pubkey),
+            taker_token_to_receive_account_pubkey: Pubkey::new_from_array(
+                *taker_token_to_receive_account_pubkey,
+            ),
+            taker_refund_account_pubkey: Pubkey::new_from_array(*taker_refund_account_pubkey),
+            taker_deposit: PaymentAmount(u64::from_le_bytes(*taker_deposit)),
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, MutualDeposit::LEN];
+        let (
+            is_initialized_dst,
+            escrow_pubkey_dst,
+            taker_pubkey_dst,
+            taker_token_to_receive_account_pubkey_dst,
+            taker_refund_account_pubkey_dst,
+            taker_deposit_dst,
+            bump_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 32, 32, 8, 1];
+
+        let MutualDeposit {
+            is_initialized,
+            escrow_pubkey,
+            taker_pubkey,
+            taker_token_to_receive_account_pubkey,
+            taker_refund_account_pubkey,
+            taker_deposit,
+            bump,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        escrow_pubkey_dst.copy_from_slice(escrow_pubkey.as_ref());
+        taker_pubkey_dst.copy_from_slice(taker_pubkey.as_ref());
+        taker_token_to_receive_account_pubkey_dst
+            .copy_from_slice(taker_token_to_receive_account_pubkey.as_ref());
+        taker_refund_account_pubkey_dst.copy_from_slice(taker_refund_account_pubkey.as_ref());
+        *taker_deposit_dst = taker_deposit.0.to_le_bytes();
+        bump_dst[0] = *bump;
+    }
+}
+
+/// A taker's proposed price for an escrow, at the PDA derived from
+/// `[b"counter_offer", escrow, taker]`, until the initializer accepts it or the taker
+/// withdraws it
+pub struct CounterOffer {
+    pub is_initialized: bool,
+    pub escrow_pubkey: Pubkey,
+    pub taker_pubkey: Pubkey,
+    /// What the taker offers to pay for what's left of the deposit
+    pub expected_amount: PaymentAmount,
+    /// The escrow's `filled_amount` when the offer was made. A partial fill since then
+    /// leaves less deposit than the offer priced.
+    pub filled_amount: DepositAmount,
+    /// Bump of the counter-offer PDA, found by MakeCounterOffer
+    pub bump: u8,
+}
+
+impl Sealed for CounterOffer {}
+
+impl IsInitialized for CounterOffer {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for CounterOffer {
+    const LEN: usize = 82;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, CounterOffer::LEN];
+        let (is_initialized, escrow_pubkey, taker_pubkey, expected_amount, filled_amount, bump) =
+            array_refs![src, 1, 32, 32, 8, 8, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(CounterOffer {
+            is_initialized,
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            taker_pubkey: Pubkey::new_from_array(*taker_pubkey),
+            expected_amount: PaymentAmount(u64::from_le_bytes(*expected_amount)),
+            filled_amount: DepositAmount(u64::from_le_bytes(*filled_amount)),
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, CounterOffer::LEN];
+        let (
+            is_initialized_dst,
+            escrow_pubkey_dst,
+            taker_pubkey_dst,
+            expected_amount_dst,
+            filled_amount_dst,
+            bump_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 8, 8, 1];
+
+        let CounterOffer {
+            is_initialized,
+            escrow_pubkey,
+            taker_pubkey,
+            expected_amount,
+            filled_amount,
+            bump,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        escrow_pubkey_dst.copy_from_slice(escrow_pubkey.as_ref());
+        taker_pubkey_dst.copy_from_slice(taker_pubkey.as_ref());
+        *expected_amount_dst = expected_amount.0.to_le_bytes();
+        *filled_amount_dst = filled_amount.0.to_le_bytes();
+        bump_dst[0] = *bump;
+    }
+}
+
+/// Most takers an escrow's allowlist holds
+pub const MAX_ALLOWED_TAKERS: usize = 8;
+
+/// Takers allowed to fill an escrow, at the PDA derived from `[b"takers", escrow]`. Exchange
+/// only consults it for escrows with `EscrowFeatures::TAKER_ALLOWLIST`.
+pub struct TakerAllowlist {
+    pub is_initialized: bool,
+    pub escrow_pubkey: Pubkey,
+    /// Who paid for the allowlist, and may close it once the escrow is gone
+    pub initializer_pubkey: Pubkey,
+    /// Bump of the allowlist PDA, found by the first AddAllowedTaker
+    pub bump: u8,
+    pub taker_count: u8,
+    pub takers: [Pubkey; MAX_ALLOWED_TAKERS],
+}
+
+impl TakerAllowlist {
+    pub fn takers(&self) -> &[Pubkey] {
+        &self.takers[..self.taker_count as usize]
+    }
+
+    pub fn allows(&self, taker: &Pubkey) -> bool {
+        self.takers().contains(taker)
+    }
+
+    /// Adds `taker` unless it is already listed
+    pub fn add(&mut self, taker: &Pubkey) -> Result<(), EscrowError> {
+        if self.allows(taker) {
+            return Ok(());
+        }
+        if self.taker_count as usize == MAX_ALLOWED_TAKERS {
+            return Err(EscrowError::TakerAllowlistFull);
+        }
+        self.takers[self.taker_count as usize] = *taker;
+        self.taker_count += 1;
+        Ok(())
+    }
+
+    /// Removes `taker` if listed, moving the last taker into its slot
+    pub fn remove(&mut self, taker: &Pubkey) {
+        if let Some(position) = self.takers().iter().position(|listed| listed == taker) {
+            let last = self.taker_count as usize - 1;
+            self.takers[position] = self.takers[last];
+            self.takers[last] = Pubkey::default();
+            self.taker_count -= 1;
+        }
+    }
+}
+
+impl Sealed for TakerAllowlist {}
+
+impl IsInitialized for TakerAllowlist {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for TakerAllowlist {
+    const LEN: usize = 323;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, TakerAllowlist::LEN];
+        let (is_initialized, escrow_pubkey, initializer_pubkey, bump, taker_count, takers_src) =
+            array_refs![src, 1, 32, 32, 1, 1, 32 * MAX_ALLOWED_TAKERS];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if taker_count[0] as usize > MAX_ALLOWED_TAKERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut takers = [Pubkey::default(); MAX_ALLOWED_TAKERS];
+        for (taker, taker_src) in takers.iter_mut().zip(takers_src.chunks(32)) {
+            *taker = Pubkey::new_from_array(*array_ref![taker_src, 0, 32]);
+        }
+
+        Ok(TakerAllowlist {
+            is_initialized,
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            initializer_pubkey: Pubkey::new_from_array(*initializer_pubkey),
+            bump: bump[0],
+            taker_count: taker_count[0],
+            takers,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, TakerAllowlist::LEN];
+        let (
+            is_initialized_dst,
+            escrow_pubkey_dst,
+            initializer_pubkey_dst,
+            bump_dst,
+            taker_count_dst,
+            takers_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 1, 1, 32 * MAX_ALLOWED_TAKERS];
+
+        let TakerAllowlist {
+            is_initialized,
+            escrow_pubkey,
+            initializer_pubkey,
+            bump,
+            taker_count,
+            takers,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        escrow_pubkey_dst.copy_from_slice(escrow_pubkey.as_ref());
+        initializer_pubkey_dst.copy_from_slice(initializer_pubkey.as_ref());
+        bump_dst[0] = *bump;
+        taker_count_dst[0] = *taker_count;
+        for (taker_dst, taker) in takers_dst.chunks_mut(32).zip(takers.iter()) {
+            taker_dst.copy_from_slice(taker.as_ref());
+        }
+    }
+}
+
+/// Most assets a basket holds on top of the escrow's own deposit
+pub const MAX_BASKET_ASSETS: usize = 4;
+
+/// One asset of a basket: `amount` of `mint`, held in `vault`, the basket PDA's associated
+/// token account for the mint
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct BasketAsset {
+    pub vault_pubkey: Pubkey,
+    pub mint: Pubkey,
+    pub amount: DepositAmount,
+}
+
+impl BasketAsset {
+    const LEN: usize = 72;
+}
+
+/// Assets an escrow offers alongside its deposit, at the PDA derived from
+/// `[b"basket", escrow]`. A fill releases all of them to the taker and a Cancel refunds them,
+/// both for the one expected amount. Exchange only consults it for escrows with
+/// `EscrowFeatures::BASKET`.
+pub struct Basket {
+    pub is_initialized: bool,
+    pub escrow_pubkey: Pubkey,
+    /// Who deposited the assets, and gets them back if the escrow closes unfilled
+    pub initializer_pubkey: Pubkey,
+    /// Bump of the basket PDA, found by the first AddBasketAsset
+    pub bump: u8,
+    pub asset_count: u8,
+    pub assets: [BasketAsset; MAX_BASKET_ASSETS],
+}
+
+impl Basket {
+    pub fn assets(&self) -> &[BasketAsset] {
+        &self.assets[..self.asset_count as usize]
+    }
+
+    /// Adds `amount` of `mint` held in `vault`, topping up the asset if the basket already
+    /// holds the mint
+    pub fn add(&mut self, vault: &Pubkey, mint: &Pubkey, amount: DepositAmount) -> Result<(), EscrowError> {
+        let count = self.asset_count as usize;
+        if let Some(asset) = self.assets[..count].iter_mut().find(|asset| asset.mint == *mint) {
+            asset.amount = DepositAmount(
+                asset.amount.0.checked_add(amount.0).ok_or(EscrowError::AmountOverflow)?,
+            );
+            return Ok(());
+        }
+        if count == MAX_BASKET_ASSETS {
+            return Err(EscrowError::BasketFull);
+        }
+        self.assets[count] = BasketAsset {
+            vault_pubkey: *vault,
+            mint: *mint,
+            amount,
+        };
+        self.asset_count += 1;
+        Ok(())
+    }
+}
+
+impl Sealed for Basket {}
+
+impl IsInitialized for Basket {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Basket {
+    const LEN: usize = 355;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Basket::LEN];
+        let (is_initialized, escrow_pubkey, initializer_pubkey, bump, asset_count, assets_src) =
+            array_refs![src, 1, 32, 32, 1, 1, BasketAsset::LEN * MAX_BASKET_ASSETS];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if asset_count[0] as usize > MAX_BASKET_ASSETS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut assets = [BasketAsset::default(); MAX_BASKET_ASSETS];
+        for (asset, asset_src) in assets.iter_mut().zip(assets_src.chunks(BasketAsset::LEN)) {
+            let asset_src = array_ref![asset_src, 0, BasketAsset::LEN];
+            let (vault_pubkey, mint, amount) = array_refs![asset_src, 32, 32, 8];
+            *asset = BasketAsset {
+                vault_pubkey: Pubkey::new_from_array(*vault_pubkey),
+                mint: Pubkey::new_from_array(*mint),
+                amount: DepositAmount(u64::from_le_bytes(*amount)),
+            };
+        }
+
+        Ok(Basket {
+            is_initialized,
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            initializer_pubkey: Pubkey::new_from_array(*initializer_pubkey),
+            bump: bump[0],
+            asset_count: asset_count[0],
+            assets,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Basket::LEN];
+        let (
+            is_initialized_dst,
+            escrow_pubkey_dst,
+            initializer_pubkey_dst,
+            bump_dst,
+            asset_count_dst,
+            assets_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 1, 1, BasketAsset::LEN * MAX_BASKET_ASSETS];
+
+        let Basket {
+            is_initialized,
+            escrow_pubkey,
+            initializer_pubkey,
+            bump,
+            asset_count,
+            assets,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        escrow_pubkey_dst.copy_from_slice(escrow_pubkey.as_ref());
+        initializer_pubkey_dst.copy_from_slice(initializer_pubkey.as_ref());
+        bump_dst[0] = *bump;
+        asset_count_dst[0] = *asset_count;
+        for (asset_dst, asset) in assets_dst.chunks_mut(BasketAsset::LEN).zip(assets.iter()) {
+            asset_dst[..32].copy_from_slice(asset.vault_pubkey.as_ref());
+            asset_dst[32..64].copy_from_slice(asset.mint.as_ref());
+            asset_dst[64..].copy_from_slice(&asset.amount.0.to_le_bytes());
+        }
+    }
+}
+
+/// Most tags an escrow carries
+pub const MAX_ESCROW_TAGS: usize = 4;
+/// Bytes of an escrow tag's key, zero-padded
+pub const ESCROW_TAG_KEY_LEN: usize = 8;
+/// Bytes of an escrow tag's value, zero-padded
+pub const ESCROW_TAG_VALUE_LEN: usize = 16;
+
+/// A `(key, value)` label like `desk=alpha`, packed as the key then the value, each padded
+/// with zeros, so clients can match a whole tag with one memcmp filter
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct EscrowTag {
+    pub key: [u8; ESCROW_TAG_KEY_LEN],
+    pub value: [u8; ESCROW_TAG_VALUE_LEN],
+}
+
+impl EscrowTag {
+    pub const LEN: usize = ESCROW_TAG_KEY_LEN + ESCROW_TAG_VALUE_LEN;
+
+    /// Pads `key` and `value` with zeros, `None` if either is too long
+    pub fn new(key: &[u8], value: &[u8]) -> Option<Self> {
+        let mut tag = EscrowTag::default();
+        tag.key.get_mut(..key.len())?.copy_from_slice(key);
+        tag.value.get_mut(..value.len())?.copy_from_slice(value);
+        Some(tag)
+    }
+
+    pub fn pack(&self) -> [u8; EscrowTag::LEN] {
+        let mut buf = [0u8; EscrowTag::LEN];
+        let (key_dst, value_dst) = mut_array_refs![&mut buf, ESCROW_TAG_KEY_LEN, ESCROW_TAG_VALUE_LEN];
+        *key_dst = self.key;
+        *value_dst = self.value;
+        buf
+    }
+
+    pub fn unpack(input: &[u8; EscrowTag::LEN]) -> Self {
+        let (key, value) = array_refs![input, ESCROW_TAG_KEY_LEN, ESCROW_TAG_VALUE_LEN];
+        EscrowTag {
+            key: *key,
+            value: *value,
+        }
+    }
+}
+
+/// Tags an escrow was opened with, at the PDA derived from `[b"tags", escrow]`. Set by
+/// SetEscrowTags in the escrow's init slot and never changed after, so indexers and
+/// `getProgramAccounts` memcmp filters can segment escrows by desk or deal without an
+/// off-chain database. Tag `i` starts at byte `EscrowTags::TAGS_OFFSET + i * EscrowTag::LEN`.
+pub struct EscrowTags {
+    pub is_initialized: bool,
+    pub escrow_pubkey: Pubkey,
+    /// Who paid for the tags, and may close them once the escrow is gone
+    pub initializer_pubkey: Pubkey,
+    /// `init_slot` of the escrow the tags were set for, telling them apart from tags left
+    /// behind by an earlier escrow at the same address
+    pub init_slot: u64,
+    /// Bump of the tags PDA, found by SetEscrowTags
+    pub bump: u8,
+    pub tag_count: u8,
+    pub tags: [EscrowTag; MAX_ESCROW_TAGS],
+}
+
+impl EscrowTags {
+    pub const TAGS_OFFSET: usize = 75;
+
+    pub fn tags(&self) -> &[EscrowTag] {
+        &self.tags[..self.tag_count as usize]
+    }
+
+    /// Whether these are the tags of the escrow at `escrow` opened in `init_slot`
+    pub fn belong_to(&self, escrow: &Pubkey, init_slot: u64) -> bool {
+        self.is_initialized && self.escrow_pubkey == *escrow && self.init_slot == init_slot
+    }
+}
+
+impl Sealed for EscrowTags {}
+
+impl IsInitialized for EscrowTags {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for EscrowTags {
+    const LEN: usize = 171;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, EscrowTags::LEN];
+        let (is_initialized, escrow_pubkey, initializer_pubkey, init_slot, bump, tag_count, tags_src) =
+            array_refs![src, 1, 32, 32, 8, 1, 1, EscrowTag::LEN * MAX_ESCROW_TAGS];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if tag_count[0] as usize > MAX_ESCROW_TAGS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut tags = [EscrowTag::default(); MAX_ESCROW_TAGS];
+        for (tag, tag_src) in tags.iter_mut().zip(tags_src.chunks(EscrowTag::LEN)) {
+            *tag = EscrowTag::unpack(array_ref![tag_src, 0, EscrowTag::LEN]);
+        }
+
+        Ok(EscrowTags {
+            is_initialized,
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            initializer_pubkey: Pubkey::new_from_array(*initializer_pubkey),
+            init_slot: u64::from_le_bytes(*init_slot),
+            bump: bump[0],
+            tag_count: tag_count[0],
+            tags,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, EscrowTags::LEN];
+        let (
+            is_initialized_dst,
+            escrow_pubkey_dst,
+            initializer_pubkey_dst,
+            init_slot_dst,
+            bump_dst,
+            tag_count_dst,
+            tags_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 8, 1, 1, EscrowTag::LEN * MAX_ESCROW_TAGS];
+
+        let EscrowTags {
+            is_initialized,
+            escrow_pubkey,
+            initializer_pubkey,
+            init_slot,
+            bump,
+            tag_count,
+            tags,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        escrow_pubkey_dst.copy_from_slice(escrow_pubkey.as_ref());
+        initializer_pubkey_dst.copy_from_slice(initializer_pubkey.as_ref());
+        *init_slot_dst = init_slot.to_le_bytes();
+        bump_dst[0] = *bump;
+        tag_count_dst[0] = *tag_count;
+        for (tag_dst, tag) in tags_dst.chunks_mut(EscrowTag::LEN).zip(tags.iter()) {
+            tag_dst.copy_from_slice(&tag.pack());
+        }
+    }
+}
+
+/// Number of entries an audit log keeps before overwriting the oldest
+pub const AUDIT_LOG_CAPACITY: usize = 32;
+
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum AuditAction {
+    InitEscrow = 0,
+    Exchange = 1,
+    Cancel = 2,
+    Reap = 3,
+    CancelExpired = 4,
+    ForceClose = 5,
+    /// Only logged as an event, the audit log doesn't record tags
+    SetEscrowTags = 6,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct AuditEntry {
+    pub slot: u64,
+    /// An `AuditAction`, kept as a raw byte so entries written by newer versions still unpack
+    pub action: u8,
+    pub escrow_pubkey: Pubkey,
+    pub actor_pubkey: Pubkey,
+}
+
+impl AuditEntry {
+    const LEN: usize = 73;
+}
+
+/// Logged with `sol_log_data` on every escrow mutation. `sequence` is taken from the stats
+/// PDA and goes up by exactly one per event across the whole program, so an indexer that
+/// sees it jump has missed a log and should backfill.
+pub struct EscrowEvent {
+    pub sequence: u64,
+    /// An `AuditAction`
+    pub action: u8,
+    pub escrow_pubkey: Pubkey,
+    pub actor_pubkey: Pubkey,
+    /// The escrow's `deposit_mint_risk` bits, so indexers can warn takers from the
+    /// InitEscrow event alone
+    pub deposit_mint_risk: u8,
+}
+
+impl EscrowEvent {
+    pub const LEN: usize = 74;
+
+    pub fn pack(&self) -> [u8; EscrowEvent::LEN] {
+        let mut buf = [0u8; EscrowEvent::LEN];
+        let (sequence_dst, action_dst, escrow_pubkey_dst, actor_pubkey_dst, deposit_mint_risk_dst) =
+            mut_array_refs![&mut buf, 8, 1, 32, 32, 1];
+        *sequence_dst = self.sequence.to_le_bytes();
+        action_dst[0] = self.action;
+        escrow_pubkey_dst.copy_from_slice(self.escrow_pubkey.as_ref());
+        actor_pubkey_dst.copy_from_slice(self.actor_pubkey.as_ref());
+        deposit_mint_risk_dst[0] = self.deposit_mint_risk;
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Option<Self> {
+        if input.len() != EscrowEvent::LEN {
+            return None;
+        }
+        let src = array_ref![input, 0, EscrowEvent::LEN];
+        let (sequence, action, escrow_pubkey, actor_pubkey, deposit_mint_risk) =
+            array_refs![src, 8, 1, 32, 32, 1];
+        Some(EscrowEvent {
+            sequence: u64::from_le_bytes(*sequence),
+            action: action[0],
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            actor_pubkey: Pubkey::new_from_array(*actor_pubkey),
+            deposit_mint_risk: deposit_mint_risk[0],
+        })
+    }
+}
+
+/// Logged with `sol_log_data` by every Exchange, right after its `EscrowEvent`, so operators
+/// can publish price improvement metrics off the logs of any pair, capped or not
+pub struct FillEvent {
+    pub escrow_pubkey: Pubkey,
+    /// Token X the taker received
+    pub fill: DepositAmount,
+    /// Token Y the taker paid for it, fees included
+    pub payment: PaymentAmount,
+    /// The taker's `max_payment`, 0 if they set no limit
+    pub max_payment: PaymentAmount,
+    /// How far `payment` came in under `max_payment`, 0 without a limit
+    pub price_improvement: PaymentAmount,
+}
+
+impl FillEvent {
+    pub const LEN: usize = 64;
+
+    pub fn pack(&self) -> [u8; FillEvent::LEN] {
+        let mut buf = [0u8; FillEvent::LEN];
+        let (escrow_pubkey_dst, fill_dst, payment_dst, max_payment_dst, price_improvement_dst) =
+            mut_array_refs![&mut buf, 32, 8, 8, 8, 8];
+        escrow_pubkey_dst.copy_from_slice(self.escrow_pubkey.as_ref());
+        *fill_dst = self.fill.0.to_le_bytes();
+        *payment_dst = self.payment.0.to_le_bytes();
+        *max_payment_dst = self.max_payment.0.to_le_bytes();
+        *price_improvement_dst = self.price_improvement.0.to_le_bytes();
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Option<Self> {
+        if input.len() != FillEvent::LEN {
+            return None;
+        }
+        let src = array_ref![input, 0, FillEvent::LEN];
+        let (escrow_pubkey, fill, payment, max_payment, price_improvement) =
+            array_refs![src, 32, 8, 8, 8, 8];
+        Some(FillEvent {
+            escrow_pubkey: Pubkey::new_from_array(*escrow_pubkey),
+            fill: DepositAmount(u64::from_le_bytes(*fill)),
+            payment: PaymentAmount(u64::from_le_bytes(*payment)),
+            max_payment: PaymentAmount(u64::from_le_bytes(*max_payment)),
+            price_improvement: PaymentAmount(u64::from_le_bytes(*price_improvement)),
+        })
+    }
+}
+
+/// Ring buffer of the last `AUDIT_LOG_CAPACITY` escrow mutations under an operator config,
+/// at the PDA derived from `[b"audit", config]`. Only the program writes to it, and
+/// `total_entries` never wraps, so a gap in the sequence can't go unnoticed.
+pub struct AuditLog {
+    pub is_initialized: bool,
+    pub config_pubkey: Pubkey,
+    pub total_entries: u64,
+    pub entries: [AuditEntry; AUDIT_LOG_CAPACITY],
+    /// Bump of the audit log PDA, found by InitAuditLog
+    pub bump: u8,
+}
+
+impl AuditLog {
+    pub fn push(&mut self, entry: AuditEntry) {
+        self.entries[(self.total_entries % AUDIT_LOG_CAPACITY as u64) as usize] = entry;
+        self.total_entries += 1;
+    }
+}
+
+impl Sealed for AuditLog {}
+
+impl IsInitialized for AuditLog {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for AuditLog {
+    const LEN: usize = 2378;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, AuditLog::LEN];
+        let (is_initialized, config_pubkey, total_entries, entries_src, bump) =
+            array_refs![src, 1, 32, 8, AuditEntry::LEN * AUDIT_LOG_CAPACITY, 1];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let mut entries = [AuditEntry::default(); AUDIT_LOG_CAPACITY];
+        for (entry, entry_src) in entries.iter_mut().zip(entries_src.chunks(AuditEntry::LEN)) {
+            let entry_src = array_ref![entry_src, 0, AuditEntry::LEN];
+            let (slot, action, escrow_pubkey, actor_pubkey) = array_refs![entry_src, 8, 1, 32, 32];
+            entry.slot = u64::from_le_bytes(*slot);
+            entry.action = action[0];
+            entry.escrow_pubkey = Pubkey::new_from_array(*escrow_pubkey);
+            entry.actor_pubkey = Pubkey::new_from_array(*actor_pubkey);
+        }
+
+        Ok(AuditLog {
+            is_initialized,
+            config_pubkey: Pubkey::new_from_array(*config_pubkey),
+            total_entries: u64::from_le_bytes(*total_entries),
+            entries,
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, AuditLog::LEN];
+        let (is_initialized_dst, config_pubkey_dst, total_entries_dst, entries_dst, bump_dst) =
+            mut_array_refs![dst, 1, 32, 8, AuditEntry::LEN * AUDIT_LOG_CAPACITY, 1];
+
+        let AuditLog {
+            is_initialized,
+            config_pubkey,
+            total_entries,
+            entries,
+            bump,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        config_pubkey_dst.copy_from_slice(config_pubkey.as_ref());
+        *total_entries_dst = total_entries.to_le_bytes();
+        for (entry, entry_dst) in entries.iter().zip(entries_dst.chunks_mut(AuditEntry::LEN)) {
+            let entry_dst = array_mut_ref![entry_dst, 0, AuditEntry::LEN];
+            let (slot_dst, action_dst, escrow_pubkey_dst, actor_pubkey_dst) =
+                mut_array_refs![entry_dst, 8, 1, 32, 32];
+            *slot_dst = entry.slot.to_le_bytes();
+            action_dst[0] = entry.action;
+            escrow_pubkey_dst.copy_from_slice(entry.escrow_pubkey.as_ref());
+            actor_pubkey_dst.copy_from_slice(entry.actor_pubkey.as_ref());
+        }
+        bump_dst[0] = *bump;
+    }
+}
+
+/// Program-wide record of the deployed version, at the PDA derived from `[b"deployment"]`.
+/// The upgrade authority updates it after each upgrade so clients can tell whether the
+/// layouts and features they were built against still hold.
+pub struct Deployment {
+    pub is_initialized: bool,
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    /// Slot the deployed program was last upgraded in, read from its program data account
+    pub upgrade_slot: u64,
+    /// Hash of the release's migration notes
+    pub migration_notes_hash: [u8; 32],
+}
+
+impl Sealed for Deployment {}
+
+impl IsInitialized for Deployment {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Deployment {
+    const LEN: usize = 47;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Deployment::LEN];
+        let (is_initialized, major, minor, patch, upgrade_slot, migration_notes_hash) =
+            array_refs![src, 1, 2, 2, 2, 8, 32];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(Deployment {
+            is_initialized,
+            major: u16::from_le_bytes(*major),
+            minor: u16::from_le_bytes(*minor),
+            patch: u16::from_le_bytes(*patch),
+            upgrade_slot: u64::from_le_bytes(*upgrade_slot),
+            migration_notes_hash: *migration_notes_hash,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Deployment::LEN];
+        let (
+            is_initialized_dst,
+            major_dst,
+            minor_dst,
+            patch_dst,
+            upgrade_slot_dst,
+            migration_notes_hash_dst,
+        ) = mut_array_refs![dst, 1, 2, 2, 2, 8, 32];
+
+        let Deployment {
+            is_initialized,
+            major,
+            minor,
+            patch,
+            upgrade_slot,
+            migration_notes_hash,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        *major_dst = major.to_le_bytes();
+        *minor_dst = minor.to_le_bytes();
+        *patch_dst = patch.to_le_bytes();
+        *upgrade_slot_dst = upgrade_slot.to_le_bytes();
+        *migration_notes_hash_dst = *migration_notes_hash;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escrow_flags_round_trip_known_bits_only() {
+        for bits in 0..=u8::MAX {
+            match EscrowFlags::from_bits(bits) {
+                Some(flags) => {
+                    assert_eq!(bits & !EscrowFlags::KNOWN, 0);
+                    assert_eq!(flags.bits(), bits);
+                    assert_eq!(flags.is_initialized(), bits & EscrowFlags::INITIALIZED != 0);
+                    assert_eq!(flags.is_risk_flagged(), bits & EscrowFlags::RISK_FLAGGED != 0);
+                    assert_eq!(flags.is_audited(), bits & EscrowFlags::AUDITED != 0);
+                    assert_eq!(flags.has_receipt(), bits & EscrowFlags::RECEIPT != 0);
+                    let mut rebuilt = EscrowFlags::default();
+                    for flag in [
+                        EscrowFlags::INITIALIZED,
+                        EscrowFlags::RISK_FLAGGED,
+                        EscrowFlags::AUDITED,
+                        EscrowFlags::RECEIPT,
+                    ] {
+                        rebuilt.set(flag, flags.contains(flag));
+                    }
+                    assert!(rebuilt == flags);
+                }
+                None => assert_ne!(bits & !EscrowFlags::KNOWN, 0),
+            }
+        }
+    }
+
+    #[test]
+    fn interest_scale_compounds_both_periods() {
+        assert_eq!(interest_scale(0, 0, 0, 1_000_000), 1.);
+        let year = INTEREST_SECONDS_PER_YEAR as i64;
+        assert!((interest_scale(0, 0, 500, year) - 0.05f64.exp()).abs() < 1e-12);
+        assert!((interest_scale(500, year, -500, year) - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn raw_payment_rounds_up_for_the_initializer() {
+        let scale = 0.05f64.exp();
+        assert_eq!(
+            QuoteConvention::Raw.raw_payment(PaymentAmount(1051), scale),
+            Some(PaymentAmount(1051))
+        );
+        assert_eq!(
+            QuoteConvention::InterestAdjusted.raw_payment(PaymentAmount(1051), scale),
+            Some(PaymentAmount(1000))
+        );
+        assert_eq!(
+            QuoteConvention::InterestAdjusted.raw_payment(PaymentAmount(1052), scale),
+            Some(PaymentAmount(1001))
+        );
+        assert_eq!(
+            QuoteConvention::InterestAdjusted.raw_payment(PaymentAmount(1), 4.),
+            Some(PaymentAmount(1))
+        );
+    }
+
+    #[test]
+    fn raw_payment_rejects_unusable_scales() {
+        for scale in [0., -1., f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                QuoteConvention::InterestAdjusted.raw_payment(PaymentAmount(1), scale),
+                None
+            );
+        }
+        assert_eq!(
+            QuoteConvention::InterestAdjusted.raw_payment(PaymentAmount(u64::MAX), 0.5),
+            None
+        );
+    }
+
+    const ROUNDING_MODES: [RoundingMode; 3] =
+        [RoundingMode::FloorForTaker, RoundingMode::FloorForMaker, RoundingMode::HalfEven];
+
+    /// Deterministic pseudo-random numbers, so fill sequences are reproducible without a rand
+    /// dependency
+    fn lcg(seed: &mut u64) -> u64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        *seed >> 33
+    }
+
+    fn partial_fill_escrow(expected_amount: u64, rounding_mode: RoundingMode) -> Escrow {
+        let mut escrow = Escrow::unpack_unchecked(&[0; Escrow::LEN]).unwrap();
+        escrow.features = EscrowFeatures::from_bits(EscrowFeatures::PARTIAL_FILL).unwrap();
+        escrow.expected_amount = PaymentAmount(expected_amount);
+        escrow.rounding_mode = rounding_mode;
+        escrow
+    }
+
+    #[test]
+    fn maker_share_rounds_within_one_unit_in_mode_order() {
+        let mut seed = 1;
+        for _ in 0..10_000 {
+            let amount = lcg(&mut seed);
+            let denominator = lcg(&mut seed) % 1_000_000 + 1;
+            let numerator = lcg(&mut seed) % (denominator + 1);
+            let [taker, maker, half_even] =
+                ROUNDING_MODES.map(|mode| mode.maker_share(amount, numerator, denominator));
+            let exact = amount as u128 * numerator as u128;
+            assert_eq!(maker as u128, exact / denominator as u128);
+            assert!(maker <= half_even && half_even <= taker && taker - maker <= 1);
+            assert!(taker <= amount);
+            // half-even is the closest share, rounding the exact half to even
+            let doubled_error = (2 * half_even as u128 * denominator as u128).abs_diff(2 * exact);
+            assert!(doubled_error <= denominator as u128);
+            if doubled_error == denominator as u128 {
+                assert_eq!(half_even % 2, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn fee_split_conserves_the_payment() {
+        let mut seed = 2;
+        for _ in 0..10_000 {
+            let payment = lcg(&mut seed);
+            let fee_bps = (lcg(&mut seed) % (TOTAL_PAYOUT_BPS as u64 + 1)) as u16;
+            for mode in ROUNDING_MODES {
+                // how Exchange, MultiExchange and Settle take the fee out of a payment
+                let proceeds = mode.maker_share(
+                    payment,
+                    TOTAL_PAYOUT_BPS.saturating_sub(fee_bps) as u64,
+                    TOTAL_PAYOUT_BPS as u64,
+                );
+                let fee = payment.checked_sub(proceeds).unwrap();
+                assert_eq!(proceeds + fee, payment);
+                let exact_fee = payment as u128 * fee_bps as u128 / TOTAL_PAYOUT_BPS as u128;
+                assert!((fee as u128).abs_diff(exact_fee) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_fill_sequences_conserve_deposit_and_payment() {
+        let mut seed = 3;
+        for _ in 0..2_000 {
+            let deposit = lcg(&mut seed) % 1_000_000 + 2;
+            let expected_amount = lcg(&mut seed) % 1_000_000 + 2;
+            for mode in ROUNDING_MODES {
+                let mut escrow = partial_fill_escrow(expected_amount, mode);
+                let (mut remaining, mut paid, mut fills) = (deposit, 0u64, 0);
+                while remaining > 0 {
+                    let fill = match lcg(&mut seed) % 4 {
+                        0 => remaining,
+                        _ => lcg(&mut seed) % remaining + 1,
+                    };
+                    let payment =
+                        match escrow.fill_payment(DepositAmount(fill), DepositAmount(remaining)) {
+                            Ok(payment) => payment,
+                            // too small to price, or would leave nothing to pay for the rest
+                            Err(EscrowError::InvalidFillAmount) => continue,
+                            Err(e) => panic!("{:?}", e),
+                        };
+                    // each fill pays its share of what's left, up to a unit of dust
+                    let exact = escrow.expected_amount.0 as u128 * fill as u128;
+                    let paid_error = (payment.0 as u128 * remaining as u128).abs_diff(exact);
+                    if fill != remaining {
+                        assert!(paid_error < remaining as u128);
+                    }
+                    paid += payment.0;
+                    fills += fill;
+                    remaining -= fill;
+                    if remaining > 0 {
+                        escrow.record_partial_fill(DepositAmount(fill), payment, 0);
+                        assert!(escrow.expected_amount.0 > 0);
+                    }
+                }
+                assert_eq!(fills, deposit);
+                assert_eq!(paid, expected_amount);
+            }
+        }
     }
 }
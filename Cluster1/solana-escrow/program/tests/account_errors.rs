@@ -0,0 +1,221 @@
+//! The account index each handler writes to return data on failure, which must be where the
+//! failing account actually sits in the instruction, optional accounts before it included
+
+mod common;
+
+use common::{Market, OfferTerms, DEFAULT_FEE_BPS};
+use solana_escrow::{
+    error::FailureReason,
+    instruction::{cancel, deposit_taker, reset_time_lock, settle},
+};
+use solana_program::{
+    entrypoint::ProgramResult, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Asserts `result` is `error` and the failure was reported at `account`'s position in
+/// `instruction`
+fn assert_reported_at(
+    market: &Market,
+    instruction: &Instruction,
+    result: ProgramResult,
+    error: impl Into<ProgramError>,
+    account: &Pubkey,
+) {
+    let error = error.into();
+    assert_eq!(result, Err(error.clone()));
+    let positions: Vec<usize> = instruction
+        .accounts
+        .iter()
+        .enumerate()
+        .filter(|(_, meta)| meta.pubkey == *account)
+        .map(|(index, _)| index)
+        .collect();
+    assert_eq!(positions.len(), 1, "{account} is passed once");
+    assert_eq!(
+        FailureReason::unpack(&market.bank.return_data().unwrap()).unwrap(),
+        FailureReason {
+            error_code: u64::from(error),
+            account_index: positions[0] as u8,
+        }
+    );
+}
+
+#[test]
+fn init_escrow_reports_a_refund_account_of_someone_else() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+    let bobs_account = market.x_account(&bob);
+    let terms = OfferTerms {
+        refund_token_account: Some(bobs_account),
+        assess_deposit_mint: true,
+        ..OfferTerms::default()
+    };
+
+    let instruction = market.init_escrow_instruction(&offer, offer.vault, 300, terms);
+    let result = market.bank.process(&instruction, &[alice]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &bobs_account,
+    );
+}
+
+#[test]
+fn exchange_reports_a_fee_account_of_someone_else() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(10_000, 0);
+    let bob = market.party(0, 10_000);
+    let carol = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 10_000, 10_000, OfferTerms::default())
+        .unwrap();
+
+    let mut instruction = market.exchange_instruction(&offer, &bob, 0);
+    let fee_account = market.fee_account();
+    let carols_account = market.y_account(&carol);
+    for meta in &mut instruction.accounts {
+        if meta.pubkey == fee_account {
+            meta.pubkey = carols_account;
+        }
+    }
+    let result = market.bank.process(&instruction, &[bob]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &carols_account,
+    );
+}
+
+#[test]
+fn cancel_reports_a_taker_refund_account_the_taker_didnt_pay_from() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let carol = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    market.mutual_deposit(&offer, &bob).unwrap();
+    market.deposit_taker(&offer, &bob).unwrap();
+
+    let carols_account = market.y_account(&carol);
+    let instruction = cancel(
+        &market.program_id(),
+        &offer.initializer,
+        &offer.vault,
+        &market.x_account(&alice),
+        &offer.escrow_account,
+        &spl_token::id(),
+        &offer.deposit_mint,
+        None,
+        None,
+        &[],
+        Some((&carols_account, &offer.expected_mint)),
+    )
+    .unwrap();
+    let result = market.bank.process(&instruction, &[alice]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &carols_account,
+    );
+}
+
+#[test]
+fn deposit_taker_reports_a_receive_account_of_someone_else() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    market.mutual_deposit(&offer, &bob).unwrap();
+
+    let alices_account = market.x_account(&alice);
+    let instruction = deposit_taker(
+        &market.program_id(),
+        &bob,
+        &offer.escrow_account,
+        &market.y_account(&bob),
+        &alices_account,
+        &offer.expected_mint,
+        &spl_token::id(),
+    )
+    .unwrap();
+    let result = market.bank.process(&instruction, &[bob]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &alices_account,
+    );
+}
+
+#[test]
+fn settle_reports_a_fee_account_of_someone_else() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(10_000, 0);
+    let bob = market.party(0, 10_000);
+    let carol = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 10_000, 10_000, OfferTerms::default())
+        .unwrap();
+    market.mutual_deposit(&offer, &bob).unwrap();
+    market.deposit_taker(&offer, &bob).unwrap();
+
+    let carols_account = market.y_account(&carol);
+    let instruction = settle(
+        &market.program_id(),
+        &bob,
+        &offer.escrow_account,
+        &offer.vault,
+        &offer.initializer,
+        &market.y_account(&alice),
+        &market.x_account(&bob),
+        &spl_token::id(),
+        &market.config,
+        &carols_account,
+        &offer.deposit_mint,
+        &offer.expected_mint,
+    )
+    .unwrap();
+    let result = market.bank.process(&instruction, &[bob]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &carols_account,
+    );
+}
+
+#[test]
+fn reset_time_lock_reports_a_signer_who_isnt_the_initializer() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let instruction =
+        reset_time_lock(&market.program_id(), &bob, &offer.escrow_account, 200).unwrap();
+    let result = market.bank.process(&instruction, &[bob]);
+    assert_reported_at(
+        &market,
+        &instruction,
+        result,
+        ProgramError::InvalidAccountData,
+        &bob,
+    );
+}
@@ -0,0 +1,245 @@
+//! Every CPI Exchange and Cancel make, made to fail in turn, whether forced by the bank or by
+//! the token program refusing the transfer (a wrong authority, too small a balance, a frozen
+//! account). The instruction must abort as a whole: no account anywhere changes, and the same
+//! instruction goes through once the cause is gone.
+
+mod common;
+
+use common::{Market, Offer, OfferTerms, DEFAULT_FEE_BPS, FORCED_CPI_FAILURE};
+use solana_escrow::error::EscrowError;
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use spl_token::error::TokenError;
+
+/// Runs `instruction`, checking it fails with `error` and leaves every account as it was
+fn assert_aborts(
+    market: &mut Market,
+    instruction: &Instruction,
+    signer: Pubkey,
+    error: ProgramError,
+) {
+    let before = market.bank.accounts().clone();
+    assert_eq!(market.bank.process(instruction, &[signer]), Err(error));
+    assert!(
+        market.bank.accounts() == &before,
+        "a failed instruction left writes behind"
+    );
+}
+
+/// Fails each CPI `instruction` makes in turn, then runs it without faults, returning how many
+/// CPIs it made
+fn fail_each_cpi(market: &mut Market, instruction: &Instruction, signer: Pubkey) -> usize {
+    let mut index = 0;
+    loop {
+        market.bank.fail_cpi(Some(index));
+        let before = market.bank.accounts().clone();
+        let result = market.bank.process(instruction, &[signer]);
+        if market.bank.cpi_count() <= index {
+            // the instruction finished before reaching the CPI to fail
+            market.bank.fail_cpi(None);
+            assert_eq!(result, Ok(()));
+            return index;
+        }
+        assert_eq!(result, Err(FORCED_CPI_FAILURE), "CPI {index}");
+        assert!(
+            market.bank.accounts() == &before,
+            "failing CPI {index} left writes behind"
+        );
+        index += 1;
+    }
+}
+
+fn offer(market: &mut Market, partial_fill: bool) -> (Offer, Pubkey) {
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 10_000);
+    let terms = OfferTerms {
+        partial_fill,
+        ..OfferTerms::default()
+    };
+    // priced high enough for the default fee to round to more than nothing
+    let offer = market.init_escrow(&alice, 500, 1_000, terms).unwrap();
+    (offer, bob)
+}
+
+#[test]
+fn exchange_aborts_whichever_cpi_fails() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    // the fee, the payment, the vault transfer and closing the vault
+    assert_eq!(fail_each_cpi(&mut market, &instruction, bob), 4);
+    assert!(market.bank.account(&offer.escrow_account).is_none());
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+}
+
+#[test]
+fn partial_fill_aborts_whichever_cpi_fails() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, true);
+
+    let instruction = market.exchange_instruction(&offer, &bob, 200);
+    // the vault stays open for the rest of the offer
+    assert_eq!(fail_each_cpi(&mut market, &instruction, bob), 3);
+    assert_eq!(market.bank.token_balance(&offer.vault), 300);
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 200);
+}
+
+#[test]
+fn exchange_without_fee_aborts_whichever_cpi_fails() {
+    let mut market = Market::new(0);
+    let (offer, bob) = offer(&mut market, false);
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_eq!(fail_each_cpi(&mut market, &instruction, bob), 3);
+    assert!(market.bank.account(&offer.escrow_account).is_none());
+}
+
+#[test]
+fn cancel_aborts_whichever_cpi_fails() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, _) = offer(&mut market, false);
+
+    let instruction = market.cancel_instruction(&offer);
+    // the refund and closing the vault
+    assert_eq!(
+        fail_each_cpi(&mut market, &instruction, offer.initializer),
+        2
+    );
+    assert!(market.bank.account(&offer.escrow_account).is_none());
+    assert_eq!(
+        market
+            .bank
+            .token_balance(&market.x_account(&offer.initializer)),
+        1_000
+    );
+}
+
+#[test]
+fn taker_short_of_token_y_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 100);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        TokenError::InsufficientFunds.into(),
+    );
+}
+
+#[test]
+fn taker_paying_from_an_account_they_dont_own_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+    let carol = market.party(0, 10_000);
+
+    let mut instruction = market.exchange_instruction(&offer, &bob, 0);
+    instruction.accounts[1].pubkey = market.y_account(&carol);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        TokenError::OwnerMismatch.into(),
+    );
+}
+
+#[test]
+fn frozen_fee_account_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+    let (fee_account, mint_y) = (market.fee_account(), market.mint_y);
+    market.freeze(&fee_account, &mint_y).unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        TokenError::AccountFrozen.into(),
+    );
+    market.thaw(&fee_account, &mint_y).unwrap();
+    market.bank.process(&instruction, &[bob]).unwrap();
+}
+
+#[test]
+fn frozen_taker_account_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+    let (bob_y, mint_y) = (market.y_account(&bob), market.mint_y);
+    market.freeze(&bob_y, &mint_y).unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        TokenError::AccountFrozen.into(),
+    );
+    market.thaw(&bob_y, &mint_y).unwrap();
+    market.bank.process(&instruction, &[bob]).unwrap();
+}
+
+// frozen accounts the escrow pays into are refused before the first transfer
+#[test]
+fn frozen_initializer_account_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+    let (alice_y, mint_y) = (market.y_account(&offer.initializer), market.mint_y);
+    market.freeze(&alice_y, &mint_y).unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        EscrowError::UnsupportedTokenExtension.into(),
+    );
+    assert_eq!(market.bank.cpi_count(), 0);
+    market.thaw(&alice_y, &mint_y).unwrap();
+    market.bank.process(&instruction, &[bob]).unwrap();
+}
+
+#[test]
+fn frozen_taker_receive_account_aborts_the_exchange() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob) = offer(&mut market, false);
+    let (bob_x, mint_x) = (market.x_account(&bob), market.mint_x);
+    market.freeze(&bob_x, &mint_x).unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        bob,
+        EscrowError::UnsupportedTokenExtension.into(),
+    );
+    assert_eq!(market.bank.cpi_count(), 0);
+    market.thaw(&bob_x, &mint_x).unwrap();
+    market.bank.process(&instruction, &[bob]).unwrap();
+}
+
+#[test]
+fn frozen_refund_account_aborts_the_cancel() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, _) = offer(&mut market, false);
+    let (alice_x, mint_x) = (market.x_account(&offer.initializer), market.mint_x);
+    market.freeze(&alice_x, &mint_x).unwrap();
+
+    let instruction = market.cancel_instruction(&offer);
+    assert_aborts(
+        &mut market,
+        &instruction,
+        offer.initializer,
+        TokenError::AccountFrozen.into(),
+    );
+    market.thaw(&alice_x, &mint_x).unwrap();
+    market
+        .bank
+        .process(&instruction, &[offer.initializer])
+        .unwrap();
+}
@@ -0,0 +1,76 @@
+use solana_program::pubkey::Pubkey;
+
+use super::Bank;
+
+/// Snapshots token balances and lamports of named accounts, then checks the exact change of
+/// each against the expected deltas. Accounts missing from the expected deltas must be
+/// unchanged. Nothing pays transaction fees in the bank, so lamports are checked exactly too.
+pub struct BalanceAssert {
+    // name, address, whether it's the token balance or the lamports, and the snapshot
+    balances: Vec<(String, Pubkey, bool, u64)>,
+}
+
+fn balance(bank: &Bank, address: &Pubkey, is_token_balance: bool) -> u64 {
+    if is_token_balance {
+        bank.token_balance(address)
+    } else {
+        bank.lamports(address)
+    }
+}
+
+impl BalanceAssert {
+    /// Names must be unique across both lists. A closed account counts as holding nothing.
+    pub fn snapshot(
+        bank: &Bank,
+        token_accounts: &[(&str, Pubkey)],
+        accounts: &[(&str, Pubkey)],
+    ) -> Self {
+        let token_balances = token_accounts
+            .iter()
+            .map(|(name, address)| (name, address, true));
+        let lamports = accounts
+            .iter()
+            .map(|(name, address)| (name, address, false));
+        BalanceAssert {
+            balances: token_balances
+                .chain(lamports)
+                .map(|(name, address, is_token_balance)| {
+                    (
+                        name.to_string(),
+                        *address,
+                        is_token_balance,
+                        balance(bank, address, is_token_balance),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Panics listing every balance that didn't move by its expected delta
+    pub fn assert_deltas(&self, bank: &Bank, expected: &[(&str, i64)]) {
+        let mut mismatches = Vec::new();
+        for (name, address, is_token_balance, before) in &self.balances {
+            let expected_delta = expected
+                .iter()
+                .find(|(expected_name, _)| expected_name == name)
+                .map_or(0, |(_, delta)| *delta);
+            let delta = balance(bank, address, *is_token_balance) as i64 - *before as i64;
+            if delta != expected_delta {
+                mismatches.push(format!(
+                    "{name} moved by {delta}, expected {expected_delta}"
+                ));
+            }
+        }
+
+        let untracked: Vec<&str> = expected
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| !self.balances.iter().any(|(tracked, ..)| tracked == name))
+            .collect();
+        if !untracked.is_empty() {
+            mismatches.push(format!("no snapshot of {}", untracked.join(", ")));
+        }
+
+        assert!(mismatches.is_empty(), "{}", mismatches.join("; "));
+    }
+}
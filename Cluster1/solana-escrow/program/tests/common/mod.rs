@@ -0,0 +1,1056 @@
+//! Runs the escrow processor natively against an in-memory bank. solana-program-test can't be
+//! built here, it pins a yanked solana_rbpf, so the runtime parts the escrow relies on are
+//! stubbed instead: the clock and rent sysvars, return data, and CPIs into both token programs,
+//! the system and associated token account programs and a transfer hook, see `transfer_hook`,
+//! which run their own processors on the same accounts.
+//! Like the runtime, an instruction's writes are kept only if it succeeds, and a CPI failing
+//! fails the whole instruction. Any one CPI can be made to fail on purpose, see `Bank::fail_cpi`.
+#![allow(dead_code)]
+
+pub mod balance_assert;
+pub mod scenario;
+pub mod transfer_hook;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    sync::Once,
+};
+
+use solana_escrow::{
+    instruction::{
+        cancel, config_pda, deposit_initializer, deposit_taker, exchange, init_config, init_escrow,
+        init_stats, mint_receipt, receipt_mint_pda, reset_time_lock, settle, stats_pda,
+    },
+    processor::Processor,
+    state::{
+        DepositAmount, Escrow, HashLock, PaymentAmount, PriceBand, QuoteConvention, RoundingMode,
+        TakerGate, PYTH_PROGRAM_ID,
+    },
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+/// Slots per second at the default slot duration, for the clock's unix timestamp
+const SLOTS_PER_SECOND: u64 = 2;
+
+/// What a CPI made to fail by `Bank::fail_cpi` returns, a code no program here uses
+pub const FORCED_CPI_FAILURE: ProgramError = ProgramError::Custom(0xc0ffee);
+
+thread_local! {
+    static SLOT: Cell<u64> = const { Cell::new(0) };
+    // the program executing at each CPI depth, the last one signs for its PDAs
+    static PROGRAM_STACK: RefCell<Vec<Pubkey>> = const { RefCell::new(Vec::new()) };
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    // the first CPI to fail, which fails the instruction however its caller handled it
+    static CPI_ERROR: RefCell<Option<ProgramError>> = const { RefCell::new(None) };
+    // CPIs the running instruction made so far, nested ones included, and which one to fail
+    static CPI_COUNT: Cell<usize> = const { Cell::new(0) };
+    static FAILING_CPI: Cell<Option<usize>> = const { Cell::new(None) };
+    // what runs the escrow program's instructions, see `Bank::with_program`
+    static ENTRY: Cell<Entrypoint> = const { Cell::new(Processor::process) };
+}
+
+/// A program's processor, called with its id, its accounts and the instruction data
+pub type Entrypoint = fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult;
+
+struct NativeRuntime;
+
+impl SyscallStubs for NativeRuntime {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let slot = SLOT.with(Cell::get);
+        let clock = Clock {
+            slot,
+            unix_timestamp: (slot / SLOTS_PER_SECOND) as i64,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|return_data| return_data.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let program_id = PROGRAM_STACK.with(|stack| *stack.borrow().last().unwrap());
+        RETURN_DATA
+            .with(|return_data| *return_data.borrow_mut() = Some((program_id, data.to_vec())));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = PROGRAM_STACK.with(|stack| *stack.borrow().last().unwrap());
+        let pda_signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+
+        let mut accounts = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut account = account_infos
+                .iter()
+                .find(|account| *account.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            // an address listed more than once gets the privileges of all its mentions
+            let metas = instruction
+                .accounts
+                .iter()
+                .filter(|other| other.pubkey == meta.pubkey);
+            let is_signer = metas.clone().any(|meta| meta.is_signer);
+            let is_writable = metas.clone().any(|meta| meta.is_writable);
+            if is_signer && !account.is_signer && !pda_signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if is_writable && !account.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            account.is_signer = is_signer;
+            account.is_writable = is_writable;
+            accounts.push(account);
+        }
+
+        let index = CPI_COUNT.with(|count| count.replace(count.get() + 1));
+        let result = match FAILING_CPI.with(Cell::get) {
+            Some(failing) if failing == index => Err(FORCED_CPI_FAILURE),
+            _ => execute(&instruction.program_id, &accounts, &instruction.data),
+        };
+        if let Err(error) = &result {
+            CPI_ERROR.with(|cpi_error| {
+                cpi_error.borrow_mut().get_or_insert_with(|| error.clone());
+            });
+        }
+        result
+    }
+}
+
+/// Runs `program_id`'s processor, the escrow's unless it's one of the programs it calls
+fn execute(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    PROGRAM_STACK.with(|stack| stack.borrow_mut().push(*program_id));
+    let result = if *program_id == spl_token::id() {
+        spl_token::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == spl_token_2022::id() {
+        spl_token_2022::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == transfer_hook::PROGRAM_ID {
+        transfer_hook::process(accounts, data)
+    } else if *program_id == spl_associated_token_account::id() {
+        spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+    } else if *program_id == system_program::id() {
+        process_system_instruction(accounts, data)
+    } else {
+        ENTRY.with(Cell::get)(program_id, accounts, data)
+    };
+    PROGRAM_STACK.with(|stack| stack.borrow_mut().pop());
+    result
+}
+
+/// The system program instructions the escrow and the associated token account program use
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let tag = u32::from_le_bytes(data[..4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+    let allocate = |account: &AccountInfo, space: u64| {
+        if !account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if !account.data_is_empty() || account.owner != &system_program::id() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        *account.try_borrow_mut_data()? = Box::leak(vec![0; space as usize].into_boxed_slice());
+        Ok(())
+    };
+    let assign = |account: &AccountInfo, owner: &Pubkey| {
+        if !account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        account.assign(owner);
+        Ok(())
+    };
+    let transfer = |from: &AccountInfo, to: &AccountInfo, lamports: u64| {
+        if !from.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        **from.try_borrow_mut_lamports()? = from
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **to.try_borrow_mut_lamports()? += lamports;
+        Ok(())
+    };
+    match tag {
+        // CreateAccount
+        0 => {
+            if accounts[1].lamports() != 0 {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            transfer(&accounts[0], &accounts[1], u64_at(4))?;
+            allocate(&accounts[1], u64_at(12))?;
+            assign(&accounts[1], &pubkey_at(20))
+        }
+        // Assign
+        1 => assign(&accounts[0], &pubkey_at(4)),
+        // Transfer
+        2 => transfer(&accounts[0], &accounts[1], u64_at(4)),
+        // Allocate
+        8 => allocate(&accounts[0], u64_at(4)),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+}
+
+/// Accounts by address, and the slot instructions run at
+pub struct Bank {
+    pub program_id: Pubkey,
+    entry: Entrypoint,
+    accounts: HashMap<Pubkey, Account>,
+    slot: u64,
+    failing_cpi: Option<usize>,
+    cpi_count: usize,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bank {
+    pub fn new() -> Self {
+        Self::with_program(Pubkey::new_unique(), Processor::process)
+    }
+
+    /// A bank running the escrow at `program_id` with `entry` instead of the native processor
+    pub fn with_program(program_id: Pubkey, entry: Entrypoint) -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(NativeRuntime));
+        });
+        let mut bank = Bank {
+            program_id,
+            entry,
+            accounts: HashMap::new(),
+            slot: 1,
+            failing_cpi: None,
+            cpi_count: 0,
+        };
+        // read from its account by the token program's InitializeMint, laid out as bincode
+        let rent = Rent::default();
+        let mut data = rent.lamports_per_byte_year.to_le_bytes().to_vec();
+        data.extend(rent.exemption_threshold.to_le_bytes());
+        data.push(rent.burn_percent);
+        bank.set_account(
+            sysvar::rent::id(),
+            Account {
+                lamports: 1,
+                data,
+                owner: sysvar::id(),
+            },
+        );
+        bank
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    pub fn warp(&mut self, slots: u64) {
+        self.slot += slots;
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    /// Every account, to compare the whole bank before and after an instruction
+    pub fn accounts(&self) -> &HashMap<Pubkey, Account> {
+        &self.accounts
+    }
+
+    /// Makes the CPI at `index`, counting from zero in the order instructions make them, fail
+    /// with `FORCED_CPI_FAILURE` in every instruction until it's reset with `None`
+    pub fn fail_cpi(&mut self, index: Option<usize>) {
+        self.failing_cpi = index;
+    }
+
+    /// How many CPIs the last instruction made before it finished or failed
+    pub fn cpi_count(&self) -> usize {
+        self.cpi_count
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |account| account.lamports)
+    }
+
+    /// Lamports held across every account, which no instruction may change
+    pub fn total_lamports(&self) -> u64 {
+        self.accounts.values().map(|account| account.lamports).sum()
+    }
+
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*address)
+            .or_insert_with(|| Account {
+                lamports: 0,
+                data: Vec::new(),
+                owner: system_program::id(),
+            })
+            .lamports += lamports;
+    }
+
+    /// A new wallet holding `lamports`
+    pub fn wallet(&mut self, lamports: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.airdrop(&wallet, lamports);
+        wallet
+    }
+
+    pub fn create_mint(&mut self, decimals: u8, freeze_authority: Option<Pubkey>) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let mut data = vec![0; Mint::LEN];
+        Mint {
+            mint_authority: COption::None,
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: freeze_authority.into(),
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(mint, data);
+        mint
+    }
+
+    /// A trading Pyth price account quoting `price * 10^expo`, published at the current slot
+    /// with no confidence interval
+    pub fn create_price_account(&mut self, price: i64, expo: i32) -> Pubkey {
+        let address = Pubkey::new_unique();
+        let mut data = vec![0; 240];
+        data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        data[4..8].copy_from_slice(&2u32.to_le_bytes());
+        data[8..12].copy_from_slice(&3u32.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[224..228].copy_from_slice(&1u32.to_le_bytes());
+        data[232..240].copy_from_slice(&self.slot.to_le_bytes());
+        self.set_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: PYTH_PROGRAM_ID,
+            },
+        );
+        address
+    }
+
+    /// Opens a token account for `mint` at `address`, minting it `amount`
+    pub fn create_token_account(
+        &mut self,
+        address: Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(address, data);
+        self.update_mint(mint, |mint| mint.supply += amount);
+    }
+
+    /// `owner`'s associated token account for `mint`, opened holding `amount`
+    pub fn create_associated_token_account(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let address = get_associated_token_address(owner, mint);
+        self.create_token_account(address, mint, owner, amount);
+        address
+    }
+
+    fn set_token_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        let lamports = Rent::default().minimum_balance(data.len());
+        self.set_account(
+            address,
+            Account {
+                lamports,
+                data,
+                owner: spl_token::id(),
+            },
+        );
+    }
+
+    /// The token account at `address` under either token program, Token-2022's extensions
+    /// left out
+    pub fn token_account(&self, address: &Pubkey) -> Option<TokenAccount> {
+        let account = self.accounts.get(address)?;
+        if account.owner == spl_token_2022::id() {
+            return TokenAccount::unpack(account.data.get(..TokenAccount::LEN)?).ok();
+        }
+        Some(account)
+            .filter(|account| account.owner == spl_token::id())
+            .and_then(|account| TokenAccount::unpack(&account.data).ok())
+    }
+
+    /// The token account's balance, zero once it's closed
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        self.token_account(address)
+            .map_or(0, |account| account.amount)
+    }
+
+    pub fn mint(&self, address: &Pubkey) -> Mint {
+        Mint::unpack(&self.accounts[address].data).unwrap()
+    }
+
+    pub fn update_mint(&mut self, address: &Pubkey, update: impl FnOnce(&mut Mint)) {
+        let account = self.accounts.get_mut(address).unwrap();
+        let mut mint = Mint::unpack(&account.data).unwrap();
+        update(&mut mint);
+        Mint::pack(mint, &mut account.data).unwrap();
+    }
+
+    pub fn update_token_account(
+        &mut self,
+        address: &Pubkey,
+        update: impl FnOnce(&mut TokenAccount),
+    ) {
+        let account = self.accounts.get_mut(address).unwrap();
+        let mut token_account = TokenAccount::unpack(&account.data).unwrap();
+        update(&mut token_account);
+        TokenAccount::pack(token_account, &mut account.data).unwrap();
+    }
+
+    /// Tokens of `mint` held across every token account, which only minting and burning change
+    pub fn tokens_held(&self, mint: &Pubkey) -> u64 {
+        self.accounts
+            .values()
+            .filter(|account| {
+                account.owner == spl_token::id() && account.data.len() == TokenAccount::LEN
+            })
+            .filter_map(|account| TokenAccount::unpack(&account.data).ok())
+            .filter(|account| account.mint == *mint)
+            .map(|account| account.amount)
+            .sum()
+    }
+
+    /// The return data the last instruction left, if any
+    pub fn return_data(&self) -> Option<Vec<u8>> {
+        RETURN_DATA.with(|return_data| return_data.borrow().clone().map(|(_, data)| data))
+    }
+
+    /// Runs `instruction` as if `signers` signed its transaction, keeping its writes only if it
+    /// succeeds
+    pub fn process(&mut self, instruction: &Instruction, signers: &[Pubkey]) -> ProgramResult {
+        for meta in &instruction.accounts {
+            if meta.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+        }
+
+        // every mention of an address shares one account, with the privileges of all of them
+        let mut addresses: Vec<Pubkey> = Vec::new();
+        for meta in &instruction.accounts {
+            if !addresses.contains(&meta.pubkey) {
+                addresses.push(meta.pubkey);
+            }
+        }
+        let mut storage: Vec<(Pubkey, Account)> = addresses
+            .iter()
+            .map(|address| {
+                let account = self.accounts.get(address).cloned().unwrap_or(Account {
+                    lamports: 0,
+                    data: Vec::new(),
+                    owner: system_program::id(),
+                });
+                (*address, account)
+            })
+            .collect();
+        let lamports_before: u64 = storage.iter().map(|(_, account)| account.lamports).sum();
+
+        let infos: Vec<AccountInfo> = storage
+            .iter_mut()
+            .map(|(address, account)| {
+                let metas = instruction
+                    .accounts
+                    .iter()
+                    .filter(|meta| meta.pubkey == *address);
+                let is_writable = metas.clone().any(|meta| meta.is_writable);
+                let is_signer = metas.clone().any(|meta| meta.is_signer);
+                let executable = [
+                    spl_token::id(),
+                    spl_token_2022::id(),
+                    transfer_hook::PROGRAM_ID,
+                    spl_associated_token_account::id(),
+                    system_program::id(),
+                ]
+                .contains(address);
+                AccountInfo::new(
+                    address,
+                    is_signer,
+                    is_writable,
+                    &mut account.lamports,
+                    &mut account.data,
+                    &account.owner,
+                    executable,
+                    0,
+                )
+            })
+            .collect();
+        let accounts: Vec<AccountInfo> = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                infos[addresses
+                    .iter()
+                    .position(|address| *address == meta.pubkey)
+                    .unwrap()]
+                .clone()
+            })
+            .collect();
+
+        SLOT.with(|slot| slot.set(self.slot));
+        RETURN_DATA.with(|return_data| *return_data.borrow_mut() = None);
+        CPI_ERROR.with(|cpi_error| *cpi_error.borrow_mut() = None);
+        CPI_COUNT.with(|count| count.set(0));
+        FAILING_CPI.with(|failing| failing.set(self.failing_cpi));
+        ENTRY.with(|entry| entry.set(self.entry));
+        let result =
+            execute(&instruction.program_id, &accounts, &instruction.data).and_then(|()| {
+                CPI_ERROR
+                    .with(|cpi_error| cpi_error.borrow_mut().take())
+                    .map_or(Ok(()), Err)
+            });
+
+        let after: Vec<(Pubkey, Account)> = infos
+            .iter()
+            .map(|info| {
+                let account = Account {
+                    lamports: info.lamports(),
+                    data: info.data.borrow().to_vec(),
+                    owner: *info.owner,
+                };
+                (*info.key, account)
+            })
+            .collect();
+        drop(accounts);
+        drop(infos);
+        self.cpi_count = CPI_COUNT.with(Cell::get);
+        result?;
+
+        let lamports_after: u64 = after.iter().map(|(_, account)| account.lamports).sum();
+        assert_eq!(
+            lamports_before, lamports_after,
+            "instruction created or destroyed lamports"
+        );
+        for (address, account) in after {
+            let readonly = !instruction
+                .accounts
+                .iter()
+                .any(|meta| meta.pubkey == address && meta.is_writable);
+            if readonly {
+                assert_eq!(
+                    self.accounts.get(&address).map_or(0, |a| a.lamports),
+                    account.lamports
+                );
+                continue;
+            }
+            // the runtime garbage collects accounts left without lamports
+            if account.lamports == 0 {
+                self.accounts.remove(&address);
+            } else {
+                self.accounts.insert(address, account);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_FEE_BPS: u16 = 30;
+/// Lamports every party starts with, enough to pay for any accounts they open
+pub const WALLET_LAMPORTS: u64 = 10_000_000_000;
+pub const DEFAULT_UNLOCK_SLOTS: u64 = 100;
+
+/// An escrow's accounts, as InitEscrow opened them
+#[derive(Clone, Copy, Debug)]
+pub struct Offer {
+    pub initializer: Pubkey,
+    pub escrow_account: Pubkey,
+    pub vault: Pubkey,
+    pub deposit_mint: Pubkey,
+    pub expected_mint: Pubkey,
+}
+
+/// What an InitEscrow asks for beyond its deposit and price
+#[derive(Clone, Copy, Default)]
+pub struct OfferTerms {
+    pub rounding_mode: RoundingMode,
+    pub partial_fill: bool,
+    pub unlock_slots: u64,
+    /// The initializer's token X account Cancel refunds to, besides their associated one
+    pub refund_token_account: Option<Pubkey>,
+    /// Passes the deposited mint for InitEscrow to assess
+    pub assess_deposit_mint: bool,
+    /// The only taker who may fill the offer
+    pub designated_taker: Option<Pubkey>,
+    /// The Pyth price account fills are priced at, with no offset
+    pub price_account: Option<Pubkey>,
+}
+
+/// A bank with the escrow's stats and an operator config set up, trading token X for token Y
+pub struct Market {
+    pub bank: Bank,
+    pub admin: Pubkey,
+    pub config: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+}
+
+impl Market {
+    pub fn new(fee_bps: u16) -> Self {
+        Self::with_bank(Bank::new(), fee_bps)
+    }
+
+    /// A market set up in `bank`, through whatever runs its escrow program
+    pub fn with_bank(mut bank: Bank, fee_bps: u16) -> Self {
+        let program_id = bank.program_id;
+        let admin = bank.wallet(WALLET_LAMPORTS);
+        let fee_recipient = bank.wallet(WALLET_LAMPORTS);
+        bank.process(&init_stats(&program_id, &admin).unwrap(), &[admin])
+            .unwrap();
+        bank.process(
+            &init_config(
+                &program_id,
+                &admin,
+                &fee_recipient,
+                fee_bps,
+                PaymentAmount(0),
+                PaymentAmount(0),
+                &[],
+                0,
+            )
+            .unwrap(),
+            &[admin],
+        )
+        .unwrap();
+        let mint_x = bank.create_mint(6, Some(admin));
+        let mint_y = bank.create_mint(6, Some(admin));
+        bank.create_associated_token_account(&fee_recipient, &mint_y, 0);
+
+        Market {
+            bank,
+            admin,
+            config: config_pda(&program_id, &admin),
+            fee_recipient,
+            mint_x,
+            mint_y,
+        }
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.bank.program_id
+    }
+
+    /// A wallet with associated token accounts holding `x` of token X and `y` of token Y
+    pub fn party(&mut self, x: u64, y: u64) -> Pubkey {
+        let wallet = self.bank.wallet(WALLET_LAMPORTS);
+        let (mint_x, mint_y) = (self.mint_x, self.mint_y);
+        self.bank
+            .create_associated_token_account(&wallet, &mint_x, x);
+        self.bank
+            .create_associated_token_account(&wallet, &mint_y, y);
+        wallet
+    }
+
+    pub fn x_account(&self, wallet: &Pubkey) -> Pubkey {
+        get_associated_token_address(wallet, &self.mint_x)
+    }
+
+    pub fn y_account(&self, wallet: &Pubkey) -> Pubkey {
+        get_associated_token_address(wallet, &self.mint_y)
+    }
+
+    pub fn fee_account(&self) -> Pubkey {
+        self.y_account(&self.fee_recipient)
+    }
+
+    /// Moves `deposit` of the initializer's token X into a fresh vault and opens an escrow on
+    /// it asking `expected_amount` of token Y, the way clients do it in one transaction
+    pub fn init_escrow(
+        &mut self,
+        initializer: &Pubkey,
+        deposit: u64,
+        expected_amount: u64,
+        terms: OfferTerms,
+    ) -> Result<Offer, ProgramError> {
+        let offer = self.open_vault(initializer, deposit)?;
+        let instruction = self.init_escrow_instruction(&offer, offer.vault, expected_amount, terms);
+        self.bank.process(&instruction, &[*initializer])?;
+        Ok(offer)
+    }
+
+    /// Opens the vault and the escrow account an InitEscrow of `deposit` needs, without the
+    /// InitEscrow itself
+    pub fn open_vault(
+        &mut self,
+        initializer: &Pubkey,
+        deposit: u64,
+    ) -> Result<Offer, ProgramError> {
+        let program_id = self.program_id();
+        let vault = Pubkey::new_unique();
+        let escrow_account = Pubkey::new_unique();
+        let rent = Rent::default();
+        self.bank.process(
+            &system_instruction::create_account(
+                initializer,
+                &vault,
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                &spl_token::id(),
+            ),
+            &[*initializer, vault],
+        )?;
+        self.bank.process(
+            &spl_token::instruction::initialize_account3(
+                &spl_token::id(),
+                &vault,
+                &self.mint_x,
+                initializer,
+            )?,
+            &[],
+        )?;
+        let x_account = self.x_account(initializer);
+        self.bank.process(
+            &spl_token::instruction::transfer(
+                &spl_token::id(),
+                &x_account,
+                &vault,
+                initializer,
+                &[],
+                deposit,
+            )?,
+            &[*initializer],
+        )?;
+        self.bank.process(
+            &system_instruction::create_account(
+                initializer,
+                &escrow_account,
+                rent.minimum_balance(Escrow::LEN),
+                Escrow::LEN as u64,
+                &program_id,
+            ),
+            &[*initializer, escrow_account],
+        )?;
+
+        Ok(Offer {
+            initializer: *initializer,
+            escrow_account,
+            vault,
+            deposit_mint: self.mint_x,
+            expected_mint: self.mint_y,
+        })
+    }
+
+    /// InitEscrow for `offer`'s accounts, depositing from `temp_token_account`
+    pub fn init_escrow_instruction(
+        &self,
+        offer: &Offer,
+        temp_token_account: Pubkey,
+        expected_amount: u64,
+        terms: OfferTerms,
+    ) -> Instruction {
+        let unlock_slots = match terms.unlock_slots {
+            0 => DEFAULT_UNLOCK_SLOTS,
+            unlock_slots => unlock_slots,
+        };
+        init_escrow(
+            &self.program_id(),
+            &offer.initializer,
+            &temp_token_account,
+            &self.y_account(&offer.initializer),
+            &offer.escrow_account,
+            &spl_token::id(),
+            &self.config,
+            PaymentAmount(expected_amount),
+            &[],
+            terms.rounding_mode,
+            None,
+            unlock_slots,
+            None,
+            None,
+            None,
+            None,
+            terms.partial_fill,
+            TakerGate::default(),
+            HashLock::default(),
+            0,
+            PriceBand {
+                price_account: terms.price_account.unwrap_or_default(),
+                offset_bps: 0,
+            },
+            QuoteConvention::default(),
+            0,
+            terms.designated_taker.as_ref(),
+            None,
+            terms.refund_token_account.as_ref(),
+            terms.assess_deposit_mint.then_some(&self.mint_x),
+        )
+        .unwrap()
+    }
+
+    /// Exchange filling `fill` of what's left of `offer`'s deposit, all of it for zero
+    pub fn exchange_instruction(&self, offer: &Offer, taker: &Pubkey, fill: u64) -> Instruction {
+        let remaining = self.bank.token_balance(&offer.vault);
+        self.exchange_instruction_quoting(offer, taker, remaining, fill)
+    }
+
+    /// Exchange filling `fill` of `offer`, with the taker quoting `quoted_deposit` as what's
+    /// left of its deposit. An oracle-priced escrow's price account is passed along.
+    pub fn exchange_instruction_quoting(
+        &self,
+        offer: &Offer,
+        taker: &Pubkey,
+        quoted_deposit: u64,
+        fill: u64,
+    ) -> Instruction {
+        let price_account = self
+            .escrow(offer)
+            .map(|escrow| escrow.price_band)
+            .filter(PriceBand::is_set)
+            .map(|band| band.price_account);
+        exchange(
+            &self.program_id(),
+            taker,
+            &self.y_account(taker),
+            &self.x_account(taker),
+            &offer.vault,
+            &offer.initializer,
+            &self.y_account(&offer.initializer),
+            &offer.escrow_account,
+            &spl_token::id(),
+            &self.config,
+            &self.fee_account(),
+            &offer.deposit_mint,
+            &offer.expected_mint,
+            DepositAmount(quoted_deposit),
+            None,
+            0,
+            DepositAmount(fill),
+            PaymentAmount(0),
+            &[],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            &[],
+            None,
+            price_account.as_ref(),
+        )
+        .unwrap()
+    }
+
+    pub fn exchange(&mut self, offer: &Offer, taker: &Pubkey, fill: u64) -> ProgramResult {
+        let instruction = self.exchange_instruction(offer, taker, fill);
+        self.bank.process(&instruction, &[*taker])
+    }
+
+    pub fn cancel_instruction(&self, offer: &Offer) -> Instruction {
+        cancel(
+            &self.program_id(),
+            &offer.initializer,
+            &offer.vault,
+            &self.x_account(&offer.initializer),
+            &offer.escrow_account,
+            &spl_token::id(),
+            &offer.deposit_mint,
+            None,
+            None,
+            &[],
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Freezes `token_account` of `mint`, the admin being both mints' freeze authority
+    pub fn freeze(&mut self, token_account: &Pubkey, mint: &Pubkey) -> ProgramResult {
+        let instruction = spl_token::instruction::freeze_account(
+            &spl_token::id(),
+            token_account,
+            mint,
+            &self.admin,
+            &[],
+        )?;
+        self.bank.process(&instruction, &[self.admin])
+    }
+
+    pub fn thaw(&mut self, token_account: &Pubkey, mint: &Pubkey) -> ProgramResult {
+        let instruction = spl_token::instruction::thaw_account(
+            &spl_token::id(),
+            token_account,
+            mint,
+            &self.admin,
+            &[],
+        )?;
+        self.bank.process(&instruction, &[self.admin])
+    }
+
+    pub fn cancel(&mut self, offer: &Offer) -> ProgramResult {
+        let instruction = self.cancel_instruction(offer);
+        self.bank.process(&instruction, &[offer.initializer])
+    }
+
+    /// Cancels a mutual deposit, refunding the taker's payment to the token Y account they
+    /// paid from
+    pub fn cancel_mutual_deposit(&mut self, offer: &Offer, taker: &Pubkey) -> ProgramResult {
+        let instruction = cancel(
+            &self.program_id(),
+            &offer.initializer,
+            &offer.vault,
+            &self.x_account(&offer.initializer),
+            &offer.escrow_account,
+            &spl_token::id(),
+            &offer.deposit_mint,
+            None,
+            None,
+            &[],
+            Some((&self.y_account(taker), &offer.expected_mint)),
+        )
+        .unwrap();
+        self.bank.process(&instruction, &[offer.initializer])
+    }
+
+    /// Makes `offer` a mutual deposit only `taker` can pay into
+    pub fn mutual_deposit(&mut self, offer: &Offer, taker: &Pubkey) -> ProgramResult {
+        let instruction = deposit_initializer(
+            &self.program_id(),
+            &offer.initializer,
+            &offer.escrow_account,
+            taker,
+            &offer.expected_mint,
+            &offer.deposit_mint,
+            &spl_token::id(),
+        )
+        .unwrap();
+        self.bank.process(&instruction, &[offer.initializer])
+    }
+
+    /// `taker` pays the price of `offer` from their token Y account, to receive the deposit
+    /// into their token X account
+    pub fn deposit_taker(&mut self, offer: &Offer, taker: &Pubkey) -> ProgramResult {
+        let instruction = deposit_taker(
+            &self.program_id(),
+            taker,
+            &offer.escrow_account,
+            &self.y_account(taker),
+            &self.x_account(taker),
+            &offer.expected_mint,
+            &spl_token::id(),
+        )
+        .unwrap();
+        self.bank.process(&instruction, &[*taker])
+    }
+
+    /// `settler` swaps both sides of a mutual deposit `taker` paid into
+    pub fn settle(&mut self, offer: &Offer, settler: &Pubkey, taker: &Pubkey) -> ProgramResult {
+        let instruction = settle(
+            &self.program_id(),
+            settler,
+            &offer.escrow_account,
+            &offer.vault,
+            &offer.initializer,
+            &self.y_account(&offer.initializer),
+            &self.x_account(taker),
+            &spl_token::id(),
+            &self.config,
+            &self.fee_account(),
+            &offer.deposit_mint,
+            &offer.expected_mint,
+        )
+        .unwrap();
+        self.bank.process(&instruction, &[*settler])
+    }
+
+    pub fn reset_time_lock(&mut self, offer: &Offer, unlock_slots: u64) -> ProgramResult {
+        let instruction = reset_time_lock(
+            &self.program_id(),
+            &offer.initializer,
+            &offer.escrow_account,
+            unlock_slots,
+        )
+        .unwrap();
+        self.bank.process(&instruction, &[offer.initializer])
+    }
+
+    /// Mints `offer`'s receipt to its initializer, returning the receipt mint
+    pub fn mint_receipt(&mut self, offer: &Offer) -> Result<Pubkey, ProgramError> {
+        let instruction = mint_receipt(
+            &self.program_id(),
+            &offer.initializer,
+            &offer.escrow_account,
+            &offer.expected_mint,
+            &spl_token::id(),
+        )?;
+        self.bank.process(&instruction, &[offer.initializer])?;
+        Ok(receipt_mint_pda(&self.program_id(), &offer.escrow_account))
+    }
+
+    pub fn escrow(&self, offer: &Offer) -> Option<Escrow> {
+        self.bank
+            .account(&offer.escrow_account)
+            .and_then(|account| Escrow::unpack(&account.data).ok())
+    }
+
+    pub fn stats_pda(&self) -> Pubkey {
+        stats_pda(&self.program_id())
+    }
+}
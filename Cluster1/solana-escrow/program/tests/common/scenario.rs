@@ -0,0 +1,356 @@
+//! Scripts multi-party escrow sequences as readable steps, so a regression case from a bug
+//! report can be written down the way it was reported. Parties are named when they join
+//! ("alice"), their token accounts after them ("alice_x", "alice_y"), the operator's fee
+//! account is "fee_y", and offers are referred to by the label given to `init`.
+
+use std::collections::HashMap;
+
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+use super::{balance_assert::BalanceAssert, Market, Offer, OfferTerms, DEFAULT_FEE_BPS};
+
+/// State steps share while a scenario runs
+pub struct Context {
+    pub market: Market,
+    wallets: HashMap<String, Pubkey>,
+    offers: HashMap<String, Offer>,
+    balances: Option<BalanceAssert>,
+}
+
+impl Context {
+    pub fn wallet(&self, name: &str) -> Pubkey {
+        *self
+            .wallets
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown party {name}"))
+    }
+
+    pub fn offer(&self, label: &str) -> Offer {
+        *self
+            .offers
+            .get(label)
+            .unwrap_or_else(|| panic!("unknown offer {label}"))
+    }
+
+    /// The token account called `name`, a party's name followed by `_x` or `_y`
+    pub fn token_account(&self, name: &str) -> Pubkey {
+        if name == "fee_y" {
+            return self.market.fee_account();
+        }
+        match name.rsplit_once('_') {
+            Some((party, "x")) => self.market.x_account(&self.wallet(party)),
+            Some((party, "y")) => self.market.y_account(&self.wallet(party)),
+            _ => panic!("unknown token account {name}"),
+        }
+    }
+}
+
+enum Outcome {
+    Succeeds,
+    Fails,
+    FailsWith(ProgramError),
+}
+
+type Action = Box<dyn FnOnce(&mut Context) -> ProgramResult>;
+
+struct Step {
+    description: String,
+    outcome: Outcome,
+    action: Action,
+}
+
+pub struct Scenario {
+    name: String,
+    fee_bps: u16,
+    steps: Vec<Step>,
+    next_outcome: Outcome,
+}
+
+impl Scenario {
+    pub fn new(name: &str) -> Self {
+        Scenario {
+            name: name.to_string(),
+            fee_bps: DEFAULT_FEE_BPS,
+            steps: Vec::new(),
+            next_outcome: Outcome::Succeeds,
+        }
+    }
+
+    /// The operator fee the scenario's config charges, `DEFAULT_FEE_BPS` unless set
+    pub fn fee_bps(mut self, fee_bps: u16) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    pub fn step(
+        mut self,
+        description: String,
+        action: impl FnOnce(&mut Context) -> ProgramResult + 'static,
+    ) -> Self {
+        let outcome = std::mem::replace(&mut self.next_outcome, Outcome::Succeeds);
+        self.steps.push(Step {
+            description,
+            outcome,
+            action: Box::new(action),
+        });
+        self
+    }
+
+    /// Marks the next step as one the program must reject
+    pub fn expect_failure(mut self) -> Self {
+        self.next_outcome = Outcome::Fails;
+        self
+    }
+
+    /// Marks the next step as one the program must reject with `error`
+    pub fn expect_error(mut self, error: impl Into<ProgramError>) -> Self {
+        self.next_outcome = Outcome::FailsWith(error.into());
+        self
+    }
+
+    /// Adds a party holding `x` of token X and `y` of token Y
+    pub fn party(self, name: &str, x: u64, y: u64) -> Self {
+        let name = name.to_string();
+        self.step(format!("{name} joins with {x} X and {y} Y"), move |ctx| {
+            let wallet = ctx.market.party(x, y);
+            ctx.wallets.insert(name, wallet);
+            Ok(())
+        })
+    }
+
+    /// `initializer` offers `deposit` of token X for `expected_amount` of token Y
+    pub fn init(
+        self,
+        label: &str,
+        initializer: &str,
+        deposit: u64,
+        expected_amount: u64,
+        terms: OfferTerms,
+    ) -> Self {
+        let (label, initializer) = (label.to_string(), initializer.to_string());
+        self.step(
+            format!("{initializer} offers {deposit} X for {expected_amount} Y ({label})"),
+            move |ctx| {
+                let wallet = ctx.wallet(&initializer);
+                let offer = ctx
+                    .market
+                    .init_escrow(&wallet, deposit, expected_amount, terms)?;
+                ctx.offers.insert(label, offer);
+                Ok(())
+            },
+        )
+    }
+
+    /// `taker` takes all of what's left of `label`
+    pub fn exchange(self, label: &str, taker: &str) -> Self {
+        self.fill(label, taker, 0)
+    }
+
+    /// `taker` takes `fill` of what's left of `label`, zero for all of it
+    pub fn fill(self, label: &str, taker: &str, fill: u64) -> Self {
+        let (label, taker) = (label.to_string(), taker.to_string());
+        let description = match fill {
+            0 => format!("{taker} takes {label}"),
+            fill => format!("{taker} takes {fill} X of {label}"),
+        };
+        self.step(description, move |ctx| {
+            let (offer, wallet) = (ctx.offer(&label), ctx.wallet(&taker));
+            ctx.market.exchange(&offer, &wallet, fill)
+        })
+    }
+
+    /// `taker` takes `label` quoting a deposit of `quoted_deposit`, stale or not
+    pub fn exchange_quoting(self, label: &str, taker: &str, quoted_deposit: u64) -> Self {
+        let (label, taker) = (label.to_string(), taker.to_string());
+        self.step(
+            format!("{taker} takes {label} quoting {quoted_deposit} X"),
+            move |ctx| {
+                let (offer, wallet) = (ctx.offer(&label), ctx.wallet(&taker));
+                let instruction =
+                    ctx.market
+                        .exchange_instruction_quoting(&offer, &wallet, quoted_deposit, 0);
+                ctx.market.bank.process(&instruction, &[wallet])
+            },
+        )
+    }
+
+    pub fn cancel(self, label: &str) -> Self {
+        let label = label.to_string();
+        self.step(format!("initializer cancels {label}"), move |ctx| {
+            let offer = ctx.offer(&label);
+            ctx.market.cancel(&offer)
+        })
+    }
+
+    /// Makes `label` a mutual deposit only `taker` can pay into
+    pub fn mutual_deposit(self, label: &str, taker: &str) -> Self {
+        let (label, taker) = (label.to_string(), taker.to_string());
+        self.step(
+            format!("{label} becomes a mutual deposit for {taker}"),
+            move |ctx| {
+                let (offer, wallet) = (ctx.offer(&label), ctx.wallet(&taker));
+                ctx.market.mutual_deposit(&offer, &wallet)
+            },
+        )
+    }
+
+    /// `taker` pays the price of `label` from their `_y` account, naming their `_x` one to
+    /// receive the deposit
+    pub fn deposit_taker(self, label: &str, taker: &str) -> Self {
+        let (label, taker) = (label.to_string(), taker.to_string());
+        self.step(format!("{taker} deposits into {label}"), move |ctx| {
+            let (offer, wallet) = (ctx.offer(&label), ctx.wallet(&taker));
+            ctx.market.deposit_taker(&offer, &wallet)
+        })
+    }
+
+    pub fn settle(self, label: &str, settler: &str, taker: &str) -> Self {
+        let (label, settler, taker) = (label.to_string(), settler.to_string(), taker.to_string());
+        self.step(format!("{settler} settles {label}"), move |ctx| {
+            let offer = ctx.offer(&label);
+            let (settler, taker) = (ctx.wallet(&settler), ctx.wallet(&taker));
+            ctx.market.settle(&offer, &settler, &taker)
+        })
+    }
+
+    /// Cancels the mutual deposit `label`, refunding `taker`'s payment to their `_y` account
+    pub fn cancel_refunding(self, label: &str, taker: &str) -> Self {
+        let (label, taker) = (label.to_string(), taker.to_string());
+        self.step(
+            format!("initializer cancels {label}, refunding {taker}"),
+            move |ctx| {
+                let (offer, wallet) = (ctx.offer(&label), ctx.wallet(&taker));
+                ctx.market.cancel_mutual_deposit(&offer, &wallet)
+            },
+        )
+    }
+
+    pub fn reset_time_lock(self, label: &str, unlock_slots: u64) -> Self {
+        let label = label.to_string();
+        self.step(
+            format!("initializer resets the timelock of {label} to {unlock_slots} slots"),
+            move |ctx| {
+                let offer = ctx.offer(&label);
+                ctx.market.reset_time_lock(&offer, unlock_slots)
+            },
+        )
+    }
+
+    pub fn warp(self, slots: u64) -> Self {
+        self.step(format!("wait {slots} slots"), move |ctx| {
+            ctx.market.bank.warp(slots);
+            Ok(())
+        })
+    }
+
+    /// Records the balances `expect_deltas` compares against: of the named token accounts, and
+    /// the lamports of the named parties
+    pub fn snapshot_balances(self, token_accounts: &[&str], wallets: &[&str]) -> Self {
+        let token_accounts: Vec<String> =
+            token_accounts.iter().map(|name| name.to_string()).collect();
+        let wallets: Vec<String> = wallets.iter().map(|name| name.to_string()).collect();
+        self.step("snapshot balances".to_string(), move |ctx| {
+            let token_accounts: Vec<(&str, Pubkey)> = token_accounts
+                .iter()
+                .map(|name| (name.as_str(), ctx.token_account(name)))
+                .collect();
+            let wallets: Vec<(&str, Pubkey)> = wallets
+                .iter()
+                .map(|name| (name.as_str(), ctx.wallet(name)))
+                .collect();
+            ctx.balances = Some(BalanceAssert::snapshot(
+                &ctx.market.bank,
+                &token_accounts,
+                &wallets,
+            ));
+            Ok(())
+        })
+    }
+
+    /// Checks every snapshotted balance moved by exactly the given delta, or not at all
+    pub fn expect_deltas(self, deltas: &[(&str, i64)]) -> Self {
+        let deltas: Vec<(String, i64)> = deltas
+            .iter()
+            .map(|(name, delta)| (name.to_string(), *delta))
+            .collect();
+        let description = match deltas.is_empty() {
+            true => "nothing".to_string(),
+            false => deltas
+                .iter()
+                .map(|(name, delta)| format!("{name} {delta:+}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        self.step(format!("balances moved by {description}"), move |ctx| {
+            let deltas: Vec<(&str, i64)> = deltas
+                .iter()
+                .map(|(name, delta)| (name.as_str(), *delta))
+                .collect();
+            ctx.balances
+                .as_ref()
+                .expect("expect_deltas needs a snapshot_balances step first")
+                .assert_deltas(&ctx.market.bank, &deltas);
+            Ok(())
+        })
+    }
+
+    pub fn expect_balance(self, token_account: &str, amount: u64) -> Self {
+        let token_account = token_account.to_string();
+        self.step(format!("{token_account} holds {amount}"), move |ctx| {
+            let balance = ctx
+                .market
+                .bank
+                .token_balance(&ctx.token_account(&token_account));
+            assert_eq!(balance, amount, "{token_account} balance");
+            Ok(())
+        })
+    }
+
+    pub fn expect_closed(self, label: &str) -> Self {
+        let label = label.to_string();
+        self.step(format!("{label} is closed"), move |ctx| {
+            let offer = ctx.offer(&label);
+            assert!(ctx.market.bank.account(&offer.escrow_account).is_none());
+            assert!(ctx.market.bank.account(&offer.vault).is_none());
+            Ok(())
+        })
+    }
+
+    pub fn expect_open(self, label: &str) -> Self {
+        let label = label.to_string();
+        self.step(format!("{label} is open"), move |ctx| {
+            let offer = ctx.offer(&label);
+            assert!(ctx.market.escrow(&offer).is_some());
+            Ok(())
+        })
+    }
+
+    /// Runs every step in order, panicking on the first unexpected outcome
+    pub fn run(self) {
+        let mut ctx = Context {
+            market: Market::new(self.fee_bps),
+            wallets: HashMap::new(),
+            offers: HashMap::new(),
+            balances: None,
+        };
+        for (i, step) in self.steps.into_iter().enumerate() {
+            let result = (step.action)(&mut ctx);
+            let failure = match (step.outcome, result) {
+                (Outcome::Succeeds, Err(error)) => Some(format!("failed with {error:?}")),
+                (Outcome::Fails | Outcome::FailsWith(_), Ok(())) => {
+                    Some("succeeded, expected it to fail".to_string())
+                }
+                (Outcome::FailsWith(expected), Err(error)) if error != expected => {
+                    Some(format!("failed with {error:?}, expected {expected:?}"))
+                }
+                _ => None,
+            };
+            if let Some(failure) = failure {
+                panic!(
+                    "scenario \"{}\", step {} \"{}\" {failure}",
+                    self.name, i, step.description
+                );
+            }
+        }
+    }
+}
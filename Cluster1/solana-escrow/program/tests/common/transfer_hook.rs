@@ -0,0 +1,147 @@
+//! A transfer hook program counting the transfers it's called for, and Token-2022 mints and
+//! accounts that call it. Each hooked mint's `ExtraAccountMetaList` names one extra account,
+//! the mint's counter, which the hook checks it was passed and bumps.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::AccountMeta,
+    program_error::ProgramError, program_option::COption, pubkey::Pubkey, rent::Rent,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_token_2022::{
+    extension::{
+        transfer_hook::{TransferHook, TransferHookAccount},
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    },
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address,
+    instruction::{ExecuteInstruction, TransferHookInstruction},
+};
+
+use super::{Account, Bank};
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([0x70; 32]);
+
+/// The account counting `mint`'s hooked transfers
+pub fn counter_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"counter", mint.as_ref()], &PROGRAM_ID).0
+}
+
+/// What the hook's `ExtraAccountMetaList` for `mint` resolves to, for
+/// `instruction::append_transfer_hook_accounts`
+pub fn extra_accounts(mint: &Pubkey) -> Vec<AccountMeta> {
+    vec![AccountMeta::new(counter_address(mint), false)]
+}
+
+/// How many transfers of `mint` the hook ran for
+pub fn transfer_count(bank: &Bank, mint: &Pubkey) -> u64 {
+    let data = &bank.account(&counter_address(mint)).unwrap().data;
+    u64::from_le_bytes(data[..8].try_into().unwrap())
+}
+
+/// The hook program's `Execute`, counting the transfer in the counter the list named
+pub fn process(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if !matches!(
+        TransferHookInstruction::unpack(data)?,
+        TransferHookInstruction::Execute { .. }
+    ) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let [_source, mint, _destination, _authority, validation, counter, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if *validation.key != get_extra_account_metas_address(mint.key, &PROGRAM_ID)
+        || *counter.key != counter_address(mint.key)
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut count = counter.try_borrow_mut_data()?;
+    let transfers = u64::from_le_bytes(count[..8].try_into().unwrap()) + 1;
+    count[..8].copy_from_slice(&transfers.to_le_bytes());
+    Ok(())
+}
+
+fn set_account(bank: &mut Bank, address: Pubkey, data: Vec<u8>, owner: Pubkey) {
+    bank.set_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner,
+        },
+    );
+}
+
+/// A Token-2022 mint, calling the hook if `hooked`, with its validation and counter accounts
+pub fn create_mint(bank: &mut Bank, decimals: u8, supply: u64, hooked: bool) -> Pubkey {
+    let mint = Pubkey::new_unique();
+    let extensions: &[ExtensionType] = if hooked {
+        &[ExtensionType::TransferHook]
+    } else {
+        &[]
+    };
+    let mut data = vec![0; ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap()];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+    state.base = Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    if hooked {
+        state.init_account_type().unwrap();
+        state
+            .init_extension::<TransferHook>(true)
+            .unwrap()
+            .program_id = Some(PROGRAM_ID).try_into().unwrap();
+    }
+    set_account(bank, mint, data, spl_token_2022::id());
+
+    if hooked {
+        let metas =
+            [ExtraAccountMeta::new_with_pubkey(&counter_address(&mint), false, true).unwrap()];
+        let mut data = vec![0; ExtraAccountMetaList::size_of(metas.len()).unwrap()];
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &metas).unwrap();
+        let validation = get_extra_account_metas_address(&mint, &PROGRAM_ID);
+        set_account(bank, validation, data, PROGRAM_ID);
+        set_account(bank, counter_address(&mint), vec![0; 8], PROGRAM_ID);
+    }
+    mint
+}
+
+/// A Token-2022 account of `mint` at `address` holding `amount`, with the extension a hooked
+/// mint's accounts carry
+pub fn create_token_account(
+    bank: &mut Bank,
+    address: Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    hooked: bool,
+) {
+    let extensions: &[ExtensionType] = if hooked {
+        &[ExtensionType::TransferHookAccount]
+    } else {
+        &[]
+    };
+    let mut data =
+        vec![0; ExtensionType::try_calculate_account_len::<TokenAccount>(extensions).unwrap()];
+    let mut state =
+        StateWithExtensionsMut::<TokenAccount>::unpack_uninitialized(&mut data).unwrap();
+    state.base = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    state.pack_base();
+    if hooked {
+        state.init_account_type().unwrap();
+        state.init_extension::<TransferHookAccount>(true).unwrap();
+    }
+    set_account(bank, address, data, spl_token_2022::id());
+}
@@ -0,0 +1,188 @@
+//! Exchange and Cancel only move the escrow's tokens and lamports between the parties: every
+//! token the vault held and every lamport of rent the escrow locked ends up with someone.
+
+mod common;
+
+use common::{balance_assert::BalanceAssert, Market, OfferTerms, DEFAULT_FEE_BPS};
+use solana_escrow::state::{Escrow, RoundingMode};
+use solana_program::{program_pack::Pack, rent::Rent};
+
+const ROUNDING_MODES: [RoundingMode; 3] = [
+    RoundingMode::FloorForTaker,
+    RoundingMode::FloorForMaker,
+    RoundingMode::HalfEven,
+];
+
+/// Token X and token Y held across all token accounts, and lamports across all accounts
+fn totals(market: &Market) -> (u64, u64, u64) {
+    (
+        market.bank.tokens_held(&market.mint_x),
+        market.bank.tokens_held(&market.mint_y),
+        market.bank.total_lamports(),
+    )
+}
+
+fn locked_rent() -> u64 {
+    let rent = Rent::default();
+    rent.minimum_balance(Escrow::LEN) + rent.minimum_balance(spl_token::state::Account::LEN)
+}
+
+#[test]
+fn exchange_conserves_tokens_and_lamports() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let before = totals(&market);
+    let balances = BalanceAssert::snapshot(
+        &market.bank,
+        &[
+            ("alice_x", market.x_account(&alice)),
+            ("alice_y", market.y_account(&alice)),
+            ("bob_x", market.x_account(&bob)),
+            ("bob_y", market.y_account(&bob)),
+            ("fee_y", market.fee_account()),
+        ],
+        &[
+            ("alice", alice),
+            ("bob", bob),
+            ("escrow", offer.escrow_account),
+            ("vault", offer.vault),
+        ],
+    );
+    let escrow_rent = market.bank.lamports(&offer.escrow_account) as i64;
+    let vault_rent = market.bank.lamports(&offer.vault) as i64;
+
+    market.exchange(&offer, &bob, 0).unwrap();
+
+    assert_eq!(totals(&market), before);
+    // the fee is rounded in the maker's favour by default
+    let fee = 300 * DEFAULT_FEE_BPS as i64 / 10_000;
+    // the escrow and its vault are closed and their rent is back with the initializer
+    balances.assert_deltas(
+        &market.bank,
+        &[
+            ("alice_y", 300 - fee),
+            ("bob_x", 500),
+            ("bob_y", -300),
+            ("fee_y", fee),
+            ("alice", locked_rent() as i64),
+            ("escrow", -escrow_rent),
+            ("vault", -vault_rent),
+        ],
+    );
+    assert!(market.bank.account(&offer.escrow_account).is_none());
+    assert!(market.bank.account(&offer.vault).is_none());
+}
+
+#[test]
+fn cancel_conserves_tokens_and_lamports() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let before = totals(&market);
+    let balances = BalanceAssert::snapshot(
+        &market.bank,
+        &[
+            ("alice_x", market.x_account(&alice)),
+            ("vault_x", offer.vault),
+        ],
+        &[("alice", alice), ("escrow", offer.escrow_account)],
+    );
+
+    market.cancel(&offer).unwrap();
+
+    assert_eq!(totals(&market), before);
+    balances.assert_deltas(
+        &market.bank,
+        &[
+            ("alice_x", 500),
+            ("vault_x", -500),
+            ("alice", locked_rent() as i64),
+            (
+                "escrow",
+                -(Rent::default().minimum_balance(Escrow::LEN) as i64),
+            ),
+        ],
+    );
+    assert!(market.bank.account(&offer.escrow_account).is_none());
+    assert!(market.bank.account(&offer.vault).is_none());
+}
+
+#[test]
+fn partial_fills_conserve_tokens_and_lamports_in_every_rounding_mode() {
+    for rounding_mode in ROUNDING_MODES {
+        let mut market = Market::new(DEFAULT_FEE_BPS);
+        let alice = market.party(1_000, 0);
+        let bob = market.party(0, 10_000);
+        let terms = OfferTerms {
+            rounding_mode,
+            partial_fill: true,
+            ..OfferTerms::default()
+        };
+        let offer = market.init_escrow(&alice, 1_000, 777, terms).unwrap();
+        let before = totals(&market);
+        let escrow_lamports = market.bank.lamports(&offer.escrow_account);
+
+        // odd fills so every mode has remainders to round
+        for fill in [333, 101, 7, 259] {
+            market.exchange(&offer, &bob, fill).unwrap();
+
+            assert_eq!(totals(&market), before, "{rounding_mode:?}");
+            assert_eq!(market.bank.lamports(&offer.escrow_account), escrow_lamports);
+            let paid = 10_000 - market.bank.token_balance(&market.y_account(&bob));
+            let remaining_price = market.escrow(&offer).unwrap().expected_amount.0;
+            assert_eq!(paid + remaining_price, 777, "{rounding_mode:?}");
+        }
+        market.exchange(&offer, &bob, 0).unwrap();
+
+        assert_eq!(totals(&market), before, "{rounding_mode:?}");
+        assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 1_000);
+        // across every fill, the taker paid exactly the price and no more
+        let paid = 10_000 - market.bank.token_balance(&market.y_account(&bob));
+        assert_eq!(paid, 777, "{rounding_mode:?}");
+        assert_eq!(
+            market.bank.token_balance(&market.y_account(&alice))
+                + market.bank.token_balance(&market.fee_account()),
+            777,
+            "{rounding_mode:?}"
+        );
+        assert!(market.bank.account(&offer.escrow_account).is_none());
+    }
+}
+
+#[test]
+fn cancel_after_partial_fills_conserves_tokens_and_lamports() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 10_000);
+    let alice_lamports = market.bank.lamports(&alice);
+    let terms = OfferTerms {
+        partial_fill: true,
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 1_000, 777, terms).unwrap();
+    market.exchange(&offer, &bob, 333).unwrap();
+    let before = totals(&market);
+    let balances = BalanceAssert::snapshot(
+        &market.bank,
+        &[
+            ("alice_x", market.x_account(&alice)),
+            ("alice_y", market.y_account(&alice)),
+            ("bob_x", market.x_account(&bob)),
+            ("bob_y", market.y_account(&bob)),
+        ],
+        &[("bob", bob)],
+    );
+
+    market.cancel(&offer).unwrap();
+
+    assert_eq!(totals(&market), before);
+    // the unfilled rest of the deposit goes back, the filled part stays with the taker
+    balances.assert_deltas(&market.bank, &[("alice_x", 667)]);
+    assert_eq!(market.bank.lamports(&alice), alice_lamports);
+}
@@ -0,0 +1,114 @@
+//! Exchange's checks on who may fill an offer and at what price
+
+mod common;
+
+use common::{Market, OfferTerms, DEFAULT_FEE_BPS};
+use solana_escrow::{
+    error::{EscrowError, FailureReason},
+    state::EscrowFeatures,
+};
+use solana_program::program_error::ProgramError;
+
+#[test]
+fn only_the_designated_taker_can_fill() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let carol = market.party(0, 1_000);
+    let terms = OfferTerms {
+        designated_taker: Some(bob),
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 500, 300, terms).unwrap();
+
+    // anyone can read who the offer is for
+    let escrow = market.escrow(&offer).unwrap();
+    assert_eq!(escrow.designated_taker, bob);
+    assert!(escrow.features.contains(EscrowFeatures::DESIGNATED_TAKER));
+
+    let error: ProgramError = EscrowError::NotDesignatedTaker.into();
+    let instruction = market.exchange_instruction(&offer, &carol, 0);
+    let before = market.bank.accounts().clone();
+    assert_eq!(
+        market.bank.process(&instruction, &[carol]),
+        Err(error.clone())
+    );
+    assert_eq!(
+        FailureReason::unpack(&market.bank.return_data().unwrap()).unwrap(),
+        FailureReason {
+            error_code: u64::from(error),
+            account_index: 0,
+        }
+    );
+    assert!(market.bank.accounts() == &before);
+
+    market.exchange(&offer, &bob, 0).unwrap();
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+    assert!(market.escrow(&offer).is_none());
+}
+
+#[test]
+fn an_offer_without_a_designated_taker_is_open_to_anyone() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let carol = market.party(0, 1_000);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let escrow = market.escrow(&offer).unwrap();
+    assert_eq!(escrow.designated_taker, Default::default());
+    assert!(!escrow.features.contains(EscrowFeatures::DESIGNATED_TAKER));
+    market.exchange(&offer, &carol, 0).unwrap();
+}
+
+#[test]
+fn oracle_price_is_read_right_after_the_mints() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    // one token Y per token X, above the offer's floor
+    let price_account = market.bank.create_price_account(1, 0);
+    let terms = OfferTerms {
+        price_account: Some(price_account),
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 500, 300, terms).unwrap();
+
+    let instruction = market.exchange_instruction(&offer, &bob, 0);
+    assert_eq!(instruction.accounts[14].pubkey, price_account);
+    market.bank.process(&instruction, &[bob]).unwrap();
+    assert_eq!(market.bank.token_balance(&market.y_account(&bob)), 500);
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+}
+
+#[test]
+fn another_price_account_in_the_oracles_position_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let price_account = market.bank.create_price_account(2, 0);
+    let terms = OfferTerms {
+        price_account: Some(price_account),
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 500, 300, terms).unwrap();
+
+    // a genuine Pyth account, just not the one the escrow was opened with
+    let cheaper = market.bank.create_price_account(1, 0);
+    let mut instruction = market.exchange_instruction(&offer, &bob, 0);
+    instruction.accounts[14].pubkey = cheaper;
+    let error: ProgramError = EscrowError::OracleInvalid.into();
+    assert_eq!(
+        market.bank.process(&instruction, &[bob]),
+        Err(error.clone())
+    );
+    assert_eq!(
+        FailureReason::unpack(&market.bank.return_data().unwrap()).unwrap(),
+        FailureReason {
+            error_code: u64::from(error),
+            account_index: 14,
+        }
+    );
+    assert_eq!(market.bank.token_balance(&market.y_account(&bob)), 1_000);
+}
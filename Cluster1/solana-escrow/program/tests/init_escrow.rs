@@ -0,0 +1,204 @@
+//! InitEscrow's checks on the temp token account it takes the deposit from, and on the account
+//! the initializer is paid into
+
+mod common;
+
+use common::{Account, Market, Offer, OfferTerms, DEFAULT_FEE_BPS};
+use solana_escrow::{
+    error::FailureReason,
+    instruction::vault_authority_pda,
+    state::{Escrow, MintRiskFlags},
+};
+use solana_program::{
+    instruction::Instruction, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey,
+    rent::Rent, system_program,
+};
+use spl_token::state::Account as TokenAccount;
+
+/// Runs `instruction`, checking it fails with `error` blamed on the account at `account_index`
+/// and leaves the escrow account as it was
+fn assert_rejected(
+    market: &mut Market,
+    offer: &Offer,
+    instruction: &Instruction,
+    error: ProgramError,
+    account_index: u8,
+) {
+    let escrow_before = market.bank.account(&offer.escrow_account).cloned();
+    assert_eq!(
+        market.bank.process(instruction, &[offer.initializer]),
+        Err(error.clone())
+    );
+    let reason = FailureReason::unpack(&market.bank.return_data().unwrap()).unwrap();
+    assert_eq!(
+        reason,
+        FailureReason {
+            error_code: u64::from(error),
+            account_index,
+        }
+    );
+    assert_eq!(
+        market.bank.account(&offer.escrow_account).cloned(),
+        escrow_before
+    );
+    assert!(Escrow::unpack(&escrow_before.unwrap().data).is_err());
+}
+
+#[test]
+fn token_account_deposits_and_hands_the_vault_to_its_authority() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+
+    let vault = market.bank.token_account(&offer.vault).unwrap();
+    assert_eq!(
+        vault.owner,
+        vault_authority_pda(&market.program_id(), &offer.vault)
+    );
+    assert_eq!(vault.amount, 500);
+    assert_eq!(
+        market.escrow(&offer).unwrap().temp_token_account_pubkey,
+        offer.vault
+    );
+}
+
+#[test]
+fn system_owned_placeholder_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+    // sized like a token account, but never handed to the token program
+    let placeholder = Pubkey::new_unique();
+    market.bank.set_account(
+        placeholder,
+        Account {
+            lamports: Rent::default().minimum_balance(TokenAccount::LEN),
+            data: vec![0; TokenAccount::LEN],
+            owner: system_program::id(),
+        },
+    );
+
+    let instruction =
+        market.init_escrow_instruction(&offer, placeholder, 300, OfferTerms::default());
+    assert_rejected(
+        &mut market,
+        &offer,
+        &instruction,
+        ProgramError::IncorrectProgramId,
+        1,
+    );
+}
+
+#[test]
+fn temp_token_account_of_the_wrong_length_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+    // a valid token account with a trailing byte the token program would never have left
+    let mut vault = market.bank.account(&offer.vault).unwrap().clone();
+    vault.data.push(0);
+    market.bank.set_account(offer.vault, vault);
+
+    let instruction =
+        market.init_escrow_instruction(&offer, offer.vault, 300, OfferTerms::default());
+    assert_rejected(
+        &mut market,
+        &offer,
+        &instruction,
+        ProgramError::InvalidAccountData,
+        1,
+    );
+}
+
+#[test]
+fn temp_token_account_of_another_token_program_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+
+    // the token program passed must be the one owning the temp token account
+    let mut instruction =
+        market.init_escrow_instruction(&offer, offer.vault, 300, OfferTerms::default());
+    instruction.accounts[4].pubkey = spl_token_2022::id();
+    assert_rejected(
+        &mut market,
+        &offer,
+        &instruction,
+        ProgramError::IncorrectProgramId,
+        4,
+    );
+}
+
+#[test]
+fn system_owned_receive_account_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+
+    let mut instruction =
+        market.init_escrow_instruction(&offer, offer.vault, 300, OfferTerms::default());
+    instruction.accounts[2].pubkey = alice;
+    assert_rejected(
+        &mut market,
+        &offer,
+        &instruction,
+        ProgramError::IncorrectProgramId,
+        2,
+    );
+}
+
+#[test]
+fn deposit_mint_alone_is_assessed_rather_than_taken_for_a_refund_account() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let terms = OfferTerms {
+        assess_deposit_mint: true,
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 500, 300, terms).unwrap();
+
+    let escrow = market.escrow(&offer).unwrap();
+    assert_eq!(escrow.refund_token_account_pubkey, Pubkey::default());
+    // the admin can still freeze token X
+    assert_eq!(escrow.deposit_mint_risk.bits(), MintRiskFlags::FREEZABLE);
+}
+
+#[test]
+fn refund_account_and_deposit_mint_are_read_from_their_flagged_positions() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let alice_x = market.x_account(&alice);
+    let terms = OfferTerms {
+        refund_token_account: Some(alice_x),
+        assess_deposit_mint: true,
+        ..OfferTerms::default()
+    };
+    let offer = market.init_escrow(&alice, 500, 300, terms).unwrap();
+
+    let escrow = market.escrow(&offer).unwrap();
+    assert_eq!(escrow.refund_token_account_pubkey, alice_x);
+    assert!(!escrow.deposit_mint_risk.contains(MintRiskFlags::UNASSESSED));
+}
+
+#[test]
+fn deposit_mint_in_the_refund_accounts_position_is_rejected() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let alice = market.party(1_000, 0);
+    let offer = market.open_vault(&alice, 500).unwrap();
+    let terms = OfferTerms {
+        refund_token_account: Some(market.mint_x),
+        ..OfferTerms::default()
+    };
+
+    // the flag says a refund account follows, so the mint is read as one and fails as one
+    let instruction = market.init_escrow_instruction(&offer, offer.vault, 300, terms);
+    assert_rejected(
+        &mut market,
+        &offer,
+        &instruction,
+        ProgramError::InvalidAccountData,
+        7,
+    );
+}
@@ -0,0 +1,165 @@
+//! Unwinding an escrow whose receipt was sold on: only the receipt's holder can cancel it, and
+//! they are the one refunded, by Cancel or once it expires
+
+mod common;
+
+use common::{Market, Offer, OfferTerms, DEFAULT_FEE_BPS, DEFAULT_UNLOCK_SLOTS};
+use solana_escrow::{
+    error::FailureReason,
+    instruction::{cancel, cancel_expired},
+    state::EXPIRY_SLOTS,
+};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+/// Alice's offer of 500 token X for 300 token Y, its receipt sold to Bob for 250 token Y
+fn sold_receipt(market: &mut Market) -> (Offer, Pubkey, Pubkey) {
+    let alice = market.party(1_000, 0);
+    let bob = market.party(0, 1_000);
+    let offer = market
+        .init_escrow(&alice, 500, 300, OfferTerms::default())
+        .unwrap();
+    let receipt_mint = market.mint_receipt(&offer).unwrap();
+
+    let bobs_receipt = market
+        .bank
+        .create_associated_token_account(&bob, &receipt_mint, 0);
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &get_associated_token_address(&alice, &receipt_mint),
+        &bobs_receipt,
+        &alice,
+        &[],
+        1,
+    )
+    .unwrap();
+    market.bank.process(&transfer, &[alice]).unwrap();
+    let payment = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &market.y_account(&bob),
+        &market.y_account(&alice),
+        &bob,
+        &[],
+        250,
+    )
+    .unwrap();
+    market.bank.process(&payment, &[bob]).unwrap();
+
+    (offer, bob, receipt_mint)
+}
+
+#[test]
+fn bob_buys_the_receipt_and_cancels() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob, receipt_mint) = sold_receipt(&mut market);
+    let alice = offer.initializer;
+    let alice_lamports = market.bank.lamports(&alice);
+
+    let instruction = cancel(
+        &market.program_id(),
+        &alice,
+        &offer.vault,
+        &market.x_account(&bob),
+        &offer.escrow_account,
+        &spl_token::id(),
+        &offer.deposit_mint,
+        None,
+        Some((&bob, &offer.expected_mint)),
+        &[],
+        None,
+    )
+    .unwrap();
+    market.bank.process(&instruction, &[bob]).unwrap();
+
+    assert!(market.escrow(&offer).is_none());
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+    assert_eq!(market.bank.token_balance(&market.x_account(&alice)), 500);
+    assert_eq!(market.bank.mint(&receipt_mint).supply, 0);
+    // the initializer paid for the escrow's accounts and has their rent back
+    assert!(market.bank.lamports(&alice) > alice_lamports);
+}
+
+#[test]
+fn initializer_cant_cancel_once_the_receipt_is_sold() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, _, receipt_mint) = sold_receipt(&mut market);
+    let alice = offer.initializer;
+
+    let instruction = cancel(
+        &market.program_id(),
+        &alice,
+        &offer.vault,
+        &market.x_account(&alice),
+        &offer.escrow_account,
+        &spl_token::id(),
+        &offer.deposit_mint,
+        None,
+        Some((&alice, &offer.expected_mint)),
+        &[],
+        None,
+    )
+    .unwrap();
+    let error = ProgramError::InvalidAccountData;
+    assert_eq!(
+        market.bank.process(&instruction, &[alice]),
+        Err(error.clone())
+    );
+    // the receipt token account left holding nothing
+    assert_eq!(
+        FailureReason::unpack(&market.bank.return_data().unwrap()).unwrap(),
+        FailureReason {
+            error_code: u64::from(error),
+            account_index: 10,
+        }
+    );
+    assert_eq!(
+        instruction.accounts[10].pubkey,
+        get_associated_token_address(&alice, &receipt_mint)
+    );
+    assert!(market.escrow(&offer).is_some());
+}
+
+#[test]
+fn expired_receipt_escrow_is_refunded_to_the_holder() {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let (offer, bob, receipt_mint) = sold_receipt(&mut market);
+    let carol = market.party(0, 0);
+    let alice = offer.initializer;
+
+    let expire = |market: &Market, refund_account: &Pubkey| {
+        cancel_expired(
+            &market.program_id(),
+            &carol,
+            &offer.vault,
+            &alice,
+            refund_account,
+            &offer.escrow_account,
+            &spl_token::id(),
+            &offer.deposit_mint,
+            None,
+            Some((&bob, &offer.expected_mint)),
+            &[],
+        )
+        .unwrap()
+    };
+    market.bank.warp(DEFAULT_UNLOCK_SLOTS + EXPIRY_SLOTS);
+
+    // the deposit is the holder's, not the initializer's
+    let instruction = expire(&market, &market.x_account(&alice));
+    assert_eq!(
+        market.bank.process(&instruction, &[carol]),
+        Err(ProgramError::InvalidAccountData)
+    );
+
+    let instruction = expire(&market, &market.x_account(&bob));
+    market.bank.process(&instruction, &[carol]).unwrap();
+    assert!(market.escrow(&offer).is_none());
+    assert_eq!(market.bank.token_balance(&market.x_account(&bob)), 500);
+    // nobody signed to burn it, so the receipt stays with Bob
+    assert_eq!(
+        market
+            .bank
+            .token_balance(&get_associated_token_address(&bob, &receipt_mint)),
+        1
+    );
+}
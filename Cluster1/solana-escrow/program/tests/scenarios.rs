@@ -0,0 +1,191 @@
+//! Multi-party sequences written with the `Scenario` builder, one per regression case
+
+mod common;
+
+use common::{scenario::Scenario, OfferTerms, DEFAULT_UNLOCK_SLOTS};
+use solana_escrow::{
+    error::EscrowError,
+    state::{RoundingMode, EXPIRY_SLOTS, MAX_UNLOCK_SLOTS, MIN_RESET_INTERVAL_SLOTS},
+};
+
+const PARTIAL_FILL: OfferTerms = OfferTerms {
+    rounding_mode: RoundingMode::FloorForTaker,
+    partial_fill: true,
+    unlock_slots: 0,
+    refund_token_account: None,
+    assess_deposit_mint: false,
+    designated_taker: None,
+    price_account: None,
+};
+
+#[test]
+fn offer_is_filled_at_the_quoted_amounts() {
+    Scenario::new("offer is filled at the quoted amounts")
+        .fee_bps(0)
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .snapshot_balances(&["alice_x", "alice_y", "bob_x", "bob_y"], &["bob"])
+        .exchange("offer", "bob")
+        .expect_closed("offer")
+        .expect_deltas(&[("alice_y", 3), ("bob_x", 5), ("bob_y", -3)])
+        .expect_balance("alice_x", 5)
+        .run();
+}
+
+#[test]
+fn stale_quote_is_rejected_and_the_initializer_cancels() {
+    Scenario::new("taker quoting a stale amount is rejected, initializer cancels")
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .snapshot_balances(&["alice_x", "alice_y", "bob_x", "bob_y", "fee_y"], &[])
+        .expect_error(EscrowError::ExpectedAmountMismatch)
+        .exchange_quoting("offer", "bob", 6)
+        .expect_deltas(&[])
+        .cancel("offer")
+        .expect_closed("offer")
+        .expect_deltas(&[("alice_x", 5)])
+        .run();
+}
+
+#[test]
+fn partial_fills_by_two_takers_drain_the_offer() {
+    Scenario::new("two takers fill an offer in parts")
+        .fee_bps(0)
+        .party("alice", 100, 0)
+        .party("bob", 0, 100)
+        .party("carol", 0, 100)
+        .init("offer", "alice", 100, 50, PARTIAL_FILL)
+        .snapshot_balances(&["alice_y", "bob_x", "bob_y", "carol_x", "carol_y"], &[])
+        .fill("offer", "bob", 40)
+        .warp(5)
+        .fill("offer", "carol", 30)
+        .expect_open("offer")
+        // more than is left
+        .expect_error(EscrowError::InvalidFillAmount)
+        .fill("offer", "bob", 31)
+        .exchange("offer", "bob")
+        .expect_closed("offer")
+        .expect_deltas(&[
+            ("alice_y", 50),
+            ("bob_x", 70),
+            ("bob_y", -35),
+            ("carol_x", 30),
+            ("carol_y", -15),
+        ])
+        .run();
+}
+
+#[test]
+fn locked_offer_only_cancels_again_once_expired() {
+    Scenario::new("cancel is refused between unlock and expiry")
+        .party("alice", 10, 0)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .warp(DEFAULT_UNLOCK_SLOTS + 1)
+        .expect_error(EscrowError::TimeConstraintWasNotSatisfied)
+        .cancel("offer")
+        .warp(EXPIRY_SLOTS)
+        .cancel("offer")
+        .expect_closed("offer")
+        .expect_balance("alice_x", 10)
+        .run();
+}
+
+#[test]
+fn timelock_reset_keeps_the_cancel_window_open() {
+    Scenario::new("a timelock reset extends the cancel window")
+        .party("alice", 10, 0)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .warp(DEFAULT_UNLOCK_SLOTS / 2)
+        .reset_time_lock("offer", 2 * DEFAULT_UNLOCK_SLOTS)
+        // past the original unlock, still within the reset one
+        .warp(DEFAULT_UNLOCK_SLOTS)
+        .cancel("offer")
+        .expect_closed("offer")
+        .run();
+}
+
+#[test]
+fn timelock_resets_are_spaced_out() {
+    Scenario::new("a second reset within the minimum interval is refused")
+        .party("alice", 10, 0)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .reset_time_lock("offer", 2 * MIN_RESET_INTERVAL_SLOTS)
+        .warp(MIN_RESET_INTERVAL_SLOTS - 1)
+        .expect_error(EscrowError::TimeLockResetLimited)
+        .reset_time_lock("offer", 2 * MIN_RESET_INTERVAL_SLOTS)
+        .warp(1)
+        .reset_time_lock("offer", 2 * MIN_RESET_INTERVAL_SLOTS)
+        .run();
+}
+
+#[test]
+fn filled_offer_cannot_be_cancelled() {
+    Scenario::new("cancelling an offer after its fill reports it settled")
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .exchange("offer", "bob")
+        .expect_error(EscrowError::EscrowAlreadySettled)
+        .cancel("offer")
+        .expect_balance("alice_x", 5)
+        .expect_balance("bob_x", 5)
+        .run();
+}
+
+// Settle has no window of its own, so the initializer pushing their cancellation window back
+// after the taker committed can't shorten the time the taker has to complete the swap
+#[test]
+fn timelock_reset_after_the_takers_mutual_deposit_doesnt_block_settle() {
+    Scenario::new("timelock reset after the taker's mutual deposit doesn't block Settle")
+        .fee_bps(0)
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .snapshot_balances(&["alice_x", "alice_y", "bob_x", "bob_y"], &[])
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .mutual_deposit("offer", "bob")
+        .deposit_taker("offer", "bob")
+        .expect_deltas(&[("alice_x", -5), ("bob_y", -3)])
+        .reset_time_lock("offer", MAX_UNLOCK_SLOTS)
+        .settle("offer", "bob", "bob")
+        .expect_closed("offer")
+        .expect_deltas(&[("alice_x", -5), ("alice_y", 3), ("bob_x", 5), ("bob_y", -3)])
+        .run();
+}
+
+#[test]
+fn taker_settles_after_the_reset_window_expires() {
+    Scenario::new("a committed taker settles long after the reset window expired")
+        .fee_bps(0)
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .mutual_deposit("offer", "bob")
+        .deposit_taker("offer", "bob")
+        .reset_time_lock("offer", 2 * DEFAULT_UNLOCK_SLOTS)
+        .warp(2 * DEFAULT_UNLOCK_SLOTS + EXPIRY_SLOTS + 1)
+        .settle("offer", "alice", "bob")
+        .expect_closed("offer")
+        .expect_balance("alice_y", 3)
+        .expect_balance("bob_x", 5)
+        .run();
+}
+
+// while the window is open the initializer may still back out, refunding both deposits
+#[test]
+fn cancel_after_a_timelock_reset_refunds_the_takers_mutual_deposit() {
+    Scenario::new("cancel after a timelock reset refunds the taker's mutual deposit")
+        .party("alice", 10, 0)
+        .party("bob", 0, 10)
+        .snapshot_balances(&["alice_x", "alice_y", "bob_x", "bob_y", "fee_y"], &[])
+        .init("offer", "alice", 5, 3, OfferTerms::default())
+        .mutual_deposit("offer", "bob")
+        .deposit_taker("offer", "bob")
+        .reset_time_lock("offer", 2 * DEFAULT_UNLOCK_SLOTS)
+        .warp(DEFAULT_UNLOCK_SLOTS + 1)
+        .cancel_refunding("offer", "bob")
+        .expect_closed("offer")
+        .expect_deltas(&[])
+        .run();
+}
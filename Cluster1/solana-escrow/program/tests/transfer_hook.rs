@@ -0,0 +1,241 @@
+//! Escrows of a Token-2022 mint with a transfer hook, whose extra accounts come after the
+//! instruction's own and are checked against the hook's `ExtraAccountMetaList`
+
+mod common;
+
+use common::{transfer_hook, Market, Offer, DEFAULT_FEE_BPS, DEFAULT_UNLOCK_SLOTS};
+use solana_escrow::{
+    instruction::{append_transfer_hook_accounts, cancel, exchange, init_escrow},
+    state::{
+        DepositAmount, Escrow, HashLock, MintRiskFlags, PaymentAmount, PriceBand, QuoteConvention,
+        RoundingMode, TakerGate,
+    },
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_tlv_account_resolution::error::AccountResolutionError;
+use spl_transfer_hook_interface::error::TransferHookError;
+
+/// Alice's escrow of 500 hooked token X for 300 token Y, both Token-2022, and a Bob holding
+/// 1,000 token Y to fill it with
+struct HookedOffer {
+    market: Market,
+    offer: Offer,
+    bob: Pubkey,
+    bobs_x: Pubkey,
+    bobs_y: Pubkey,
+}
+
+fn token_2022_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(wallet, mint, &spl_token_2022::id())
+}
+
+fn hooked_offer() -> HookedOffer {
+    let mut market = Market::new(DEFAULT_FEE_BPS);
+    let program_id = market.program_id();
+    let bank = &mut market.bank;
+    let mint_x = transfer_hook::create_mint(bank, 6, 500, true);
+    let mint_y = transfer_hook::create_mint(bank, 6, 1_000, false);
+    let alice = bank.wallet(1_000_000_000);
+    let bob = bank.wallet(1_000_000_000);
+
+    let vault = Pubkey::new_unique();
+    transfer_hook::create_token_account(bank, vault, &mint_x, &alice, 500, true);
+    for (wallet, mint, amount, hooked) in [
+        (alice, mint_x, 0, true),
+        (alice, mint_y, 0, false),
+        (bob, mint_x, 0, true),
+        (bob, mint_y, 1_000, false),
+        (market.fee_recipient, mint_y, 0, false),
+    ] {
+        let address = token_2022_address(&wallet, &mint);
+        transfer_hook::create_token_account(bank, address, &mint, &wallet, amount, hooked);
+    }
+    let escrow_account = Pubkey::new_unique();
+    bank.set_account(
+        escrow_account,
+        common::Account {
+            lamports: Rent::default().minimum_balance(Escrow::LEN),
+            data: vec![0; Escrow::LEN],
+            owner: program_id,
+        },
+    );
+
+    let offer = Offer {
+        initializer: alice,
+        escrow_account,
+        vault,
+        deposit_mint: mint_x,
+        expected_mint: mint_y,
+    };
+    let instruction = init_escrow(
+        &market.program_id(),
+        &alice,
+        &vault,
+        &token_2022_address(&alice, &mint_y),
+        &escrow_account,
+        &spl_token_2022::id(),
+        &market.config,
+        PaymentAmount(300),
+        &[],
+        RoundingMode::default(),
+        None,
+        DEFAULT_UNLOCK_SLOTS,
+        None,
+        None,
+        None,
+        None,
+        false,
+        TakerGate::default(),
+        HashLock::default(),
+        0,
+        PriceBand::default(),
+        QuoteConvention::default(),
+        0,
+        None,
+        None,
+        None,
+        Some(&mint_x),
+    )
+    .unwrap();
+    market.bank.process(&instruction, &[alice]).unwrap();
+    // takers see the hook that could refuse their transfer out
+    let escrow = market.escrow(&offer).unwrap();
+    assert!(escrow
+        .deposit_mint_risk
+        .contains(MintRiskFlags::TRANSFER_HOOK));
+
+    HookedOffer {
+        offer,
+        bob,
+        bobs_x: token_2022_address(&bob, &mint_x),
+        bobs_y: token_2022_address(&bob, &mint_y),
+        market,
+    }
+}
+
+impl HookedOffer {
+    fn exchange_instruction(&self) -> Instruction {
+        exchange(
+            &self.market.program_id(),
+            &self.bob,
+            &self.bobs_y,
+            &self.bobs_x,
+            &self.offer.vault,
+            &self.offer.initializer,
+            &token_2022_address(&self.offer.initializer, &self.offer.expected_mint),
+            &self.offer.escrow_account,
+            &spl_token_2022::id(),
+            &self.market.config,
+            &token_2022_address(&self.market.fee_recipient, &self.offer.expected_mint),
+            &self.offer.deposit_mint,
+            &self.offer.expected_mint,
+            DepositAmount(500),
+            None,
+            0,
+            DepositAmount(0),
+            PaymentAmount(0),
+            &[],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            &[],
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn cancel_instruction(&self) -> Instruction {
+        cancel(
+            &self.market.program_id(),
+            &self.offer.initializer,
+            &self.offer.vault,
+            &token_2022_address(&self.offer.initializer, &self.offer.deposit_mint),
+            &self.offer.escrow_account,
+            &spl_token_2022::id(),
+            &self.offer.deposit_mint,
+            None,
+            None,
+            &[],
+            None,
+        )
+        .unwrap()
+    }
+
+    /// `instruction` with the hook's accounts for token X after its own
+    fn hooked(&self, mut instruction: Instruction, extra_accounts: &[AccountMeta]) -> Instruction {
+        append_transfer_hook_accounts(
+            &mut instruction,
+            &self.offer.deposit_mint,
+            &transfer_hook::PROGRAM_ID,
+            extra_accounts,
+        );
+        instruction
+    }
+
+    fn transfer_count(&self) -> u64 {
+        transfer_hook::transfer_count(&self.market.bank, &self.offer.deposit_mint)
+    }
+}
+
+#[test]
+fn exchange_runs_the_deposit_mints_hook() {
+    let mut test = hooked_offer();
+    let extra_accounts = transfer_hook::extra_accounts(&test.offer.deposit_mint);
+    let instruction = test.hooked(test.exchange_instruction(), &extra_accounts);
+    test.market.bank.process(&instruction, &[test.bob]).unwrap();
+
+    let bank = &test.market.bank;
+    assert_eq!(bank.token_balance(&test.bobs_x), 500);
+    assert_eq!(bank.token_balance(&test.bobs_y), 700);
+    assert!(test.market.escrow(&test.offer).is_none());
+    assert_eq!(test.transfer_count(), 1);
+}
+
+#[test]
+fn exchange_without_the_hooks_accounts_fails() {
+    let mut test = hooked_offer();
+    let instruction = test.exchange_instruction();
+    assert_eq!(
+        test.market.bank.process(&instruction, &[test.bob]),
+        Err(TransferHookError::IncorrectAccount.into())
+    );
+    assert_eq!(test.market.bank.token_balance(&test.offer.vault), 500);
+    assert_eq!(test.transfer_count(), 0);
+}
+
+#[test]
+fn an_account_the_list_doesnt_name_is_rejected() {
+    let mut test = hooked_offer();
+    let other_counter = AccountMeta::new(Pubkey::new_unique(), false);
+    let instruction = test.hooked(test.exchange_instruction(), &[other_counter]);
+    assert_eq!(
+        test.market.bank.process(&instruction, &[test.bob]),
+        Err(AccountResolutionError::IncorrectAccount.into())
+    );
+    assert_eq!(test.market.bank.token_balance(&test.offer.vault), 500);
+}
+
+#[test]
+fn cancel_runs_the_hook_refunding_the_deposit() {
+    let mut test = hooked_offer();
+    let alice = test.offer.initializer;
+    let extra_accounts = transfer_hook::extra_accounts(&test.offer.deposit_mint);
+    let instruction = test.hooked(test.cancel_instruction(), &extra_accounts);
+    test.market.bank.process(&instruction, &[alice]).unwrap();
+
+    let alices_x = token_2022_address(&alice, &test.offer.deposit_mint);
+    assert_eq!(test.market.bank.token_balance(&alices_x), 500);
+    assert!(test.market.escrow(&test.offer).is_none());
+    assert_eq!(test.transfer_count(), 1);
+}
@@ -0,0 +1,505 @@
+use std::str::FromStr;
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use solana_escrow::{
+    instruction,
+    state::{
+        self, DepositAmount, Escrow, HashLock, PaymentAmount, PriceBand, QuoteConvention,
+        RoundingMode, TakerGate, TimeStatus,
+    },
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+fn pubkey(value: &str) -> PyResult<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| PyValueError::new_err(format!("invalid pubkey {}", value)))
+}
+
+fn bytes32(value: &[u8]) -> PyResult<[u8; 32]> {
+    value
+        .try_into()
+        .map_err(|_| PyValueError::new_err(format!("expected 32 bytes, got {}", value.len())))
+}
+
+fn pubkeys(values: &[String]) -> PyResult<Vec<Pubkey>> {
+    values.iter().map(|value| pubkey(value)).collect()
+}
+
+fn basket_pairs(values: &[(String, String)]) -> PyResult<Vec<(Pubkey, Pubkey)>> {
+    values
+        .iter()
+        .map(|(mint, token_account)| Ok((pubkey(mint)?, pubkey(token_account)?)))
+        .collect()
+}
+
+fn program_error(error: ProgramError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Instructions are handed to Python as a dict of `program_id`, `accounts` as
+/// (pubkey, is_signer, is_writable) tuples, and the raw `data` bytes
+fn instruction_to_py(py: Python, ix: Instruction) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("program_id", ix.program_id.to_string())?;
+    let accounts: Vec<(String, bool, bool)> = ix
+        .accounts
+        .iter()
+        .map(|meta| (meta.pubkey.to_string(), meta.is_signer, meta.is_writable))
+        .collect();
+    dict.set_item("accounts", accounts)?;
+    dict.set_item("data", PyBytes::new(py, &ix.data))?;
+    Ok(dict.into())
+}
+
+/// `payouts` are (token account, bps) pairs splitting the taker's payment, empty to pay the receive account.
+/// `rounding_mode` is a `RoundingMode` discriminant, 0 for the default. Without a
+/// `refund_token_account` the deposit can only be refunded to the initializer's associated token account.
+/// `allowlist_entry` is required when the config gates InitEscrow. `taker_commitment` (see
+/// `taker_commitment`) makes the offer private to one taker. Passing the `deposit_mint` records
+/// its risk flags, otherwise the escrow is marked unassessed. `unlock_slots` defaults to
+/// `DEFAULT_UNLOCK_SLOTS`. `vault_deposit` is a (source token account, amount) pair moved into
+/// the initializer's shared vault, which is then the `temp_token_account`. `settlement_program`
+/// settles the proceeds into the program the config whitelists instead of the receive account.
+/// `partial_fill` lets takers fill less than the whole deposit. Takers must then hold at least
+/// `taker_min_lamports` or some of the `taker_badge_mint` token, if either is set. `hash_lock`
+/// is a (32-byte sha256 hash, recipient) pair making the escrow a hash-timelock only the
+/// recipient may Redeem. `gas_rebate` lamports are paid to the taker out of the escrow account,
+/// which must hold them on top of its rent. `price_band` is a (Pyth price account, offset bps)
+/// pair pricing fills at that oracle price instead. `fill_cooldown_slots` keeps a partially
+/// filled escrow from being filled again for that many slots. `designated_taker` is the only
+/// signer who may fill the offer, if set. `rate_counter` is required when the config limits
+/// inits per slot.
+#[pyfunction]
+#[pyo3(signature = (program_id, initializer, temp_token_account, receive_token_account, escrow_account, config, amount, payouts = Vec::new(), rounding_mode = 0, audit_log = None, refund_token_account = None, allowlist_entry = None, taker_commitment = None, deposit_mint = None, unlock_slots = None, vault_deposit = None, settlement_program = None, partial_fill = false, taker_min_lamports = 0, taker_badge_mint = None, hash_lock = None, gas_rebate = 0, price_band = None, fill_cooldown_slots = 0, designated_taker = None, rate_counter = None))]
+#[allow(clippy::too_many_arguments)]
+fn init_escrow(
+    py: Python,
+    program_id: &str,
+    initializer: &str,
+    temp_token_account: &str,
+    receive_token_account: &str,
+    escrow_account: &str,
+    config: &str,
+    amount: u64,
+    payouts: Vec<(String, u16)>,
+    rounding_mode: u8,
+    audit_log: Option<&str>,
+    refund_token_account: Option<&str>,
+    allowlist_entry: Option<&str>,
+    taker_commitment: Option<&[u8]>,
+    deposit_mint: Option<&str>,
+    unlock_slots: Option<u64>,
+    vault_deposit: Option<(String, u64)>,
+    settlement_program: Option<&str>,
+    partial_fill: bool,
+    taker_min_lamports: u64,
+    taker_badge_mint: Option<&str>,
+    hash_lock: Option<(Vec<u8>, String)>,
+    gas_rebate: u64,
+    price_band: Option<(String, i16)>,
+    fill_cooldown_slots: u64,
+    designated_taker: Option<&str>,
+    rate_counter: Option<&str>,
+) -> PyResult<PyObject> {
+    let rounding_mode = RoundingMode::from_u8(rounding_mode)
+        .ok_or_else(|| PyValueError::new_err(format!("invalid rounding mode {}", rounding_mode)))?;
+    let payouts = payouts
+        .iter()
+        .map(|(account, bps)| Ok((pubkey(account)?, *bps)))
+        .collect::<PyResult<Vec<_>>>()?;
+    let audit_log = audit_log.map(pubkey).transpose()?;
+    let refund_token_account = refund_token_account.map(pubkey).transpose()?;
+    let allowlist_entry = allowlist_entry.map(pubkey).transpose()?;
+    let taker_commitment = taker_commitment.map(bytes32).transpose()?;
+    let deposit_mint = deposit_mint.map(pubkey).transpose()?;
+    let vault_deposit = vault_deposit
+        .map(|(source, amount)| pubkey(&source).map(|source| (source, DepositAmount(amount))))
+        .transpose()?;
+    let settlement_program = settlement_program.map(pubkey).transpose()?;
+    let taker_gate = TakerGate {
+        min_lamports: taker_min_lamports,
+        badge_mint: taker_badge_mint.map(pubkey).transpose()?.unwrap_or_default(),
+    };
+    let hash_lock = match hash_lock {
+        Some((hash, recipient)) => HashLock {
+            hash: bytes32(&hash)?,
+            recipient: pubkey(&recipient)?,
+        },
+        None => HashLock::default(),
+    };
+    let price_band = match price_band {
+        Some((price_account, offset_bps)) => PriceBand {
+            price_account: pubkey(&price_account)?,
+            offset_bps,
+        },
+        None => PriceBand::default(),
+    };
+    let designated_taker = designated_taker.map(pubkey).transpose()?;
+    let rate_counter = rate_counter.map(pubkey).transpose()?;
+    let ix = instruction::init_escrow(
+        &pubkey(program_id)?,
+        &pubkey(initializer)?,
+        &pubkey(temp_token_account)?,
+        &pubkey(receive_token_account)?,
+        &pubkey(escrow_account)?,
+        &spl_token::id(),
+        &pubkey(config)?,
+        PaymentAmount(amount),
+        &payouts,
+        rounding_mode,
+        taker_commitment,
+        unlock_slots.unwrap_or(state::DEFAULT_UNLOCK_SLOTS),
+        audit_log.as_ref(),
+        allowlist_entry.as_ref(),
+        vault_deposit.as_ref().map(|(source, amount)| (source, *amount)),
+        settlement_program.as_ref(),
+        partial_fill,
+        taker_gate,
+        hash_lock,
+        gas_rebate,
+        price_band,
+        // the escrows built here are spl-token ones, whose mints accrue no interest
+        QuoteConvention::Raw,
+        fill_cooldown_slots,
+        designated_taker.as_ref(),
+        rate_counter.as_ref(),
+        refund_token_account.as_ref(),
+        deposit_mint.as_ref(),
+    )
+    .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// `amount` is the amount of token X the taker expects out of the vault, and `deposit_mint` and
+/// `expected_mint` the mints of tokens X and Y. `taker_fee_mint_account` and `fee_mint` are
+/// required when the config charges fees in a fee mint, and `taker_salt` to fill a private offer.
+/// The fill fails after `valid_until_slot`, unless it is 0. A non-zero `fill_amount` takes only
+/// that much of the deposit, and a non-zero `max_payment` caps the token Y the taker pays for it. `pair_stats` is required when the config has a volume cap, and `taker_allowlist` when the
+/// escrow restricts its takers. `taker_badge_account` is the taker's token account for the
+/// escrow's badge mint, required if its taker gate has one. `nft_metadata` and
+/// `royalty_creator_accounts` are required if token X is an NFT: its metadata account and each
+/// royalty creator's token account for token Y. `insurance_pool` and
+/// `insurance_pool_token_account`, the pool's token account for token Y, are required if the
+/// escrow is insured. `basket` pairs the mint of each of the escrow's basket assets, in its
+/// order, with the taker's token account receiving it. For an escrow with a settlement hook,
+/// `initializer_receive_token_account` is the staging account and `settlement_accounts` are the
+/// (pubkey, is_signer, is_writable) accounts `settlement_program` needs. `price_account` is
+/// required if the escrow is oracle-priced, its Pyth price account.
+#[pyfunction]
+#[pyo3(signature = (program_id, taker, taker_sending_token_account, taker_receiving_token_account, temp_token_account, initializer, initializer_receive_token_account, escrow_account, config, fee_token_account, deposit_mint, expected_mint, amount, payout_accounts = Vec::new(), audit_log = None, taker_fee_mint_account = None, fee_mint = None, taker_salt = None, valid_until_slot = 0, fill_amount = 0, max_payment = 0, pair_stats = None, taker_allowlist = None, taker_badge_account = None, nft_metadata = None, royalty_creator_accounts = Vec::new(), insurance_pool = None, insurance_pool_token_account = None, basket = Vec::new(), settlement_program = None, settlement_accounts = Vec::new(), price_account = None))]
+#[allow(clippy::too_many_arguments)]
+fn exchange(
+    py: Python,
+    program_id: &str,
+    taker: &str,
+    taker_sending_token_account: &str,
+    taker_receiving_token_account: &str,
+    temp_token_account: &str,
+    initializer: &str,
+    initializer_receive_token_account: &str,
+    escrow_account: &str,
+    config: &str,
+    fee_token_account: &str,
+    deposit_mint: &str,
+    expected_mint: &str,
+    amount: u64,
+    payout_accounts: Vec<String>,
+    audit_log: Option<&str>,
+    taker_fee_mint_account: Option<&str>,
+    fee_mint: Option<&str>,
+    taker_salt: Option<&[u8]>,
+    valid_until_slot: u64,
+    fill_amount: u64,
+    max_payment: u64,
+    pair_stats: Option<&str>,
+    taker_allowlist: Option<&str>,
+    taker_badge_account: Option<&str>,
+    nft_metadata: Option<&str>,
+    royalty_creator_accounts: Vec<String>,
+    insurance_pool: Option<&str>,
+    insurance_pool_token_account: Option<&str>,
+    basket: Vec<(String, String)>,
+    settlement_program: Option<&str>,
+    settlement_accounts: Vec<(String, bool, bool)>,
+    price_account: Option<&str>,
+) -> PyResult<PyObject> {
+    let price_account = price_account.map(pubkey).transpose()?;
+    let audit_log = audit_log.map(pubkey).transpose()?;
+    let nft_metadata = nft_metadata.map(pubkey).transpose()?;
+    let royalty_creator_accounts = pubkeys(&royalty_creator_accounts)?;
+    let pair_stats = pair_stats.map(pubkey).transpose()?;
+    let taker_allowlist = taker_allowlist.map(pubkey).transpose()?;
+    let taker_badge_account = taker_badge_account.map(pubkey).transpose()?;
+    let settlement_program = settlement_program.map(pubkey).transpose()?;
+    let settlement_accounts = settlement_accounts
+        .iter()
+        .map(|(account, is_signer, is_writable)| {
+            Ok(AccountMeta {
+                pubkey: pubkey(account)?,
+                is_signer: *is_signer,
+                is_writable: *is_writable,
+            })
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let taker_fee_mint = match (taker_fee_mint_account, fee_mint) {
+        (None, _) => None,
+        (Some(account), Some(fee_mint)) => Some((pubkey(account)?, pubkey(fee_mint)?)),
+        (Some(_), None) => {
+            return Err(PyValueError::new_err("fee_mint is required with taker_fee_mint_account"))
+        }
+    };
+    let insurance = match (insurance_pool, insurance_pool_token_account) {
+        (None, _) => None,
+        (Some(pool), Some(pool_token_account)) => Some((pubkey(pool)?, pubkey(pool_token_account)?)),
+        (Some(_), None) => {
+            return Err(PyValueError::new_err(
+                "insurance_pool_token_account is required with insurance_pool",
+            ))
+        }
+    };
+    let taker_salt = taker_salt.map(bytes32).transpose()?;
+    let ix = instruction::exchange(
+        &pubkey(program_id)?,
+        &pubkey(taker)?,
+        &pubkey(taker_sending_token_account)?,
+        &pubkey(taker_receiving_token_account)?,
+        &pubkey(temp_token_account)?,
+        &pubkey(initializer)?,
+        &pubkey(initializer_receive_token_account)?,
+        &pubkey(escrow_account)?,
+        &spl_token::id(),
+        &pubkey(config)?,
+        &pubkey(fee_token_account)?,
+        &pubkey(deposit_mint)?,
+        &pubkey(expected_mint)?,
+        DepositAmount(amount),
+        taker_salt,
+        valid_until_slot,
+        DepositAmount(fill_amount),
+        PaymentAmount(max_payment),
+        &pubkeys(&payout_accounts)?,
+        audit_log.as_ref(),
+        taker_fee_mint.as_ref().map(|(account, fee_mint)| (account, fee_mint)),
+        pair_stats.as_ref(),
+        taker_allowlist.as_ref(),
+        taker_badge_account.as_ref(),
+        nft_metadata
+            .as_ref()
+            .map(|metadata| (metadata, royalty_creator_accounts.as_slice())),
+        insurance.as_ref().map(|(pool, pool_token_account)| (pool, pool_token_account)),
+        &basket_pairs(&basket)?,
+        settlement_program
+            .as_ref()
+            .map(|program| (program, settlement_accounts.as_slice())),
+        price_account.as_ref(),
+    )
+    .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// `deposit_mint` is the mint of token X. `receipt_expected_mint` is the escrow's expected mint
+/// if it has a receipt, and `receipt_holder` whoever holds it, if not the initializer, signing
+/// in their place. `basket` pairs the mint of each of its basket assets with the
+/// initializer's token account to refund it to. `mutual_deposit` is the token account the taker
+/// paid from and the expected mint if the escrow is a mutual deposit.
+#[pyfunction]
+#[pyo3(signature = (program_id, initializer, temp_token_account, initializer_token_account, escrow_account, deposit_mint, audit_log = None, receipt_expected_mint = None, receipt_holder = None, basket = Vec::new(), mutual_deposit = None))]
+#[allow(clippy::too_many_arguments)]
+fn cancel(
+    py: Python,
+    program_id: &str,
+    initializer: &str,
+    temp_token_account: &str,
+    initializer_token_account: &str,
+    escrow_account: &str,
+    deposit_mint: &str,
+    audit_log: Option<&str>,
+    receipt_expected_mint: Option<&str>,
+    receipt_holder: Option<&str>,
+    basket: Vec<(String, String)>,
+    mutual_deposit: Option<(String, String)>,
+) -> PyResult<PyObject> {
+    let initializer = pubkey(initializer)?;
+    let audit_log = audit_log.map(pubkey).transpose()?;
+    let receipt_holder = receipt_holder.map(pubkey).transpose()?.unwrap_or(initializer);
+    let receipt_expected_mint = receipt_expected_mint.map(pubkey).transpose()?;
+    let mutual_deposit = match mutual_deposit {
+        Some((taker_refund_account, expected_mint)) => {
+            Some((pubkey(&taker_refund_account)?, pubkey(&expected_mint)?))
+        }
+        None => None,
+    };
+    let ix = instruction::cancel(
+        &pubkey(program_id)?,
+        &initializer,
+        &pubkey(temp_token_account)?,
+        &pubkey(initializer_token_account)?,
+        &pubkey(escrow_account)?,
+        &spl_token::id(),
+        &pubkey(deposit_mint)?,
+        audit_log.as_ref(),
+        receipt_expected_mint.as_ref().map(|expected_mint| (&receipt_holder, expected_mint)),
+        &basket_pairs(&basket)?,
+        mutual_deposit.as_ref().map(|(refund_account, expected_mint)| (refund_account, expected_mint)),
+    )
+    .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// Creates the initializer's shared vault for `mint`
+#[pyfunction]
+fn init_vault(py: Python, program_id: &str, initializer: &str, mint: &str) -> PyResult<PyObject> {
+    let ix = instruction::init_vault(
+        &pubkey(program_id)?,
+        &pubkey(initializer)?,
+        &pubkey(mint)?,
+        &spl_token::id(),
+    )
+    .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// Creates a wSOL account for `owner` holding `lamports` of their SOL, to pass to `init_escrow`
+/// as the temp token account or to `exchange` as the taker's sending account. `wsol_account`
+/// is a new keypair, which signs along with the owner.
+#[pyfunction]
+fn wrap_sol(
+    py: Python,
+    program_id: &str,
+    owner: &str,
+    wsol_account: &str,
+    lamports: u64,
+) -> PyResult<PyObject> {
+    let ix = instruction::wrap_sol(
+        &pubkey(program_id)?,
+        &pubkey(owner)?,
+        &pubkey(wsol_account)?,
+        &spl_token::id(),
+        lamports,
+    )
+    .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// Allocates the initializer's escrow accounts `first_index..first_index + count`, which
+/// `init_escrow` can then open offers in without creating an account
+#[pyfunction]
+fn precreate_escrows(
+    py: Python,
+    program_id: &str,
+    initializer: &str,
+    first_index: u64,
+    count: u8,
+) -> PyResult<PyObject> {
+    let ix =
+        instruction::precreate_escrows(&pubkey(program_id)?, &pubkey(initializer)?, first_index, count)
+            .map_err(program_error)?;
+    instruction_to_py(py, ix)
+}
+
+/// Address of the initializer's precreated escrow account at `index`, as a base58 string
+#[pyfunction]
+fn precreated_escrow_address(program_id: &str, initializer: &str, index: u64) -> PyResult<String> {
+    let address = instruction::precreated_escrow_pda(&pubkey(program_id)?, &pubkey(initializer)?, index);
+    Ok(address.to_string())
+}
+
+/// Decodes an initialized escrow account's data into a dict, pubkeys as base58 strings
+#[pyfunction]
+fn decode_escrow(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let escrow = Escrow::unpack(data).map_err(program_error)?;
+    let dict = PyDict::new(py);
+    dict.set_item("flags", escrow.flags.bits())?;
+    dict.set_item("initializer", escrow.initializer_pubkey.to_string())?;
+    dict.set_item("temp_token_account", escrow.temp_token_account_pubkey.to_string())?;
+    dict.set_item(
+        "receive_token_account",
+        escrow.initializer_token_to_receive_account_pubkey.to_string(),
+    )?;
+    dict.set_item("expected_amount", escrow.expected_amount.0)?;
+    dict.set_item("unlock_time", escrow.unlock_time)?;
+    let payouts: Vec<(String, u16)> = escrow
+        .payouts()
+        .iter()
+        .map(|payout| (payout.token_account_pubkey.to_string(), payout.bps))
+        .collect();
+    dict.set_item("payouts", payouts)?;
+    dict.set_item("config", escrow.config_pubkey.to_string())?;
+    dict.set_item("expected_mint", escrow.expected_mint.to_string())?;
+    dict.set_item("init_slot", escrow.init_slot)?;
+    dict.set_item("rounding_mode", escrow.rounding_mode as u8)?;
+    dict.set_item("lifetime_expiry_slot", escrow.lifetime_expiry_slot)?;
+    dict.set_item("refund_token_account", escrow.refund_token_account_pubkey.to_string())?;
+    dict.set_item("taker_commitment", PyBytes::new(py, &escrow.taker_commitment))?;
+    dict.set_item("deposit_mint_risk", escrow.deposit_mint_risk.bits())?;
+    dict.set_item("vault_deposit", escrow.vault_deposit.0)?;
+    dict.set_item("settlement_program", escrow.settlement_program.to_string())?;
+    dict.set_item("vault_authority_bump", escrow.vault_authority_bump)?;
+    dict.set_item("receipt_mint_bump", escrow.receipt_mint_bump)?;
+    dict.set_item("settlement_bump", escrow.settlement_bump)?;
+    dict.set_item("features", escrow.features.bits())?;
+    dict.set_item("filled_amount", escrow.filled_amount.0)?;
+    dict.set_item("last_reset_slot", escrow.last_reset_slot)?;
+    dict.set_item("reset_count", escrow.reset_count)?;
+    dict.set_item("taker_min_lamports", escrow.taker_gate.min_lamports)?;
+    dict.set_item("taker_badge_mint", escrow.taker_gate.badge_mint.to_string())?;
+    dict.set_item("deposit_mint", escrow.deposit_mint.to_string())?;
+    dict.set_item("token_program", escrow.token_program.to_string())?;
+    dict.set_item("hash_lock_hash", PyBytes::new(py, &escrow.hash_lock.hash))?;
+    dict.set_item("hash_lock_recipient", escrow.hash_lock.recipient.to_string())?;
+    dict.set_item("gas_rebate", escrow.gas_rebate)?;
+    dict.set_item("permit_eth_signer", PyBytes::new(py, &escrow.permit_eth_signer))?;
+    dict.set_item("price_account", escrow.price_band.price_account.to_string())?;
+    dict.set_item("price_offset_bps", escrow.price_band.offset_bps)?;
+    dict.set_item("quote_convention", escrow.quote_convention as u8)?;
+    dict.set_item("fill_cooldown_slots", escrow.fill_cooldown_slots)?;
+    dict.set_item("last_fill_slot", escrow.last_fill_slot)?;
+    dict.set_item("designated_taker", escrow.designated_taker.to_string())?;
+    Ok(dict.into())
+}
+
+/// Decodes the return_data of a TimeStatus instruction into a dict. The cancel window closes at
+/// unlock.
+#[pyfunction]
+fn decode_time_status(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let status = TimeStatus::unpack(data)
+        .ok_or_else(|| PyValueError::new_err("invalid TimeStatus return data"))?;
+    let dict = PyDict::new(py);
+    dict.set_item("current_slot", status.current_slot)?;
+    dict.set_item("slots_until_unlock", status.slots_until_unlock)?;
+    dict.set_item("slots_until_expiry", status.slots_until_expiry)?;
+    dict.set_item("seconds_until_unlock", status.seconds_until_unlock)?;
+    dict.set_item("seconds_until_expiry", status.seconds_until_expiry)?;
+    Ok(dict.into())
+}
+
+/// The commitment a private offer records for `taker`, who reveals `salt` to fill it
+#[pyfunction]
+fn taker_commitment(py: Python, taker: &str, salt: &[u8]) -> PyResult<PyObject> {
+    let commitment = state::taker_commitment(&pubkey(taker)?, &bytes32(salt)?);
+    Ok(PyBytes::new(py, &commitment).into())
+}
+
+#[pymodule]
+fn solana_escrow_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(init_escrow, m)?)?;
+    m.add_function(wrap_pyfunction!(exchange, m)?)?;
+    m.add_function(wrap_pyfunction!(cancel, m)?)?;
+    m.add_function(wrap_pyfunction!(init_vault, m)?)?;
+    m.add_function(wrap_pyfunction!(wrap_sol, m)?)?;
+    m.add_function(wrap_pyfunction!(precreate_escrows, m)?)?;
+    m.add_function(wrap_pyfunction!(precreated_escrow_address, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_escrow, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_time_status, m)?)?;
+    m.add_function(wrap_pyfunction!(taker_commitment, m)?)?;
+    m.add("ESCROW_LEN", Escrow::LEN)?;
+    Ok(())
+}
@@ -1,23 +1,22 @@
-import { AccountLayout, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
-import {
-  Connection,
-  Keypair,
-  PublicKey,
-  SystemProgram,
-  SYSVAR_RENT_PUBKEY,
-  Transaction,
-  TransactionInstruction,
-} from "@solana/web3.js";
-import BN = require("bn.js");
+import { Connection, PublicKey } from "@solana/web3.js";
+import { planInitEscrow, sendInitPlan } from "./init-plan";
+import { initEscrowInstruction } from "./instructions";
 import {
+  dumpInstruction,
+  dumpInstructionArgs,
   EscrowLayout,
   ESCROW_ACCOUNT_DATA_LAYOUT,
+  ESCROW_FLAGS,
   getKeypair,
+  getOperatorConfig,
   getProgramId,
   getPublicKey,
   getTerms,
   getTokenBalance,
+  hasEscrowFlag,
+  isDumpingInstructions,
   logError,
+  ROUNDING_MODES,
   writePublicKey,
 } from "./utils";
 
@@ -30,84 +29,68 @@ const alice = async () => {
   const XTokenMintPubkey = getPublicKey("mint_x");
   const aliceKeypair = getKeypair("alice");
 
-  const tempXTokenAccountKeypair = new Keypair();
-  const connection = new Connection("http://localhost:8899", "confirmed");
-  const createTempTokenAccountIx = SystemProgram.createAccount({
-    programId: TOKEN_PROGRAM_ID,
-    space: AccountLayout.span,
-    lamports: await connection.getMinimumBalanceForRentExemption(
-      AccountLayout.span
-    ),
-    fromPubkey: aliceKeypair.publicKey,
-    newAccountPubkey: tempXTokenAccountKeypair.publicKey,
-  });
-  const initTempAccountIx = Token.createInitAccountInstruction(
-    TOKEN_PROGRAM_ID,
-    XTokenMintPubkey,
-    tempXTokenAccountKeypair.publicKey,
-    aliceKeypair.publicKey
-  );
-  const transferXTokensToTempAccIx = Token.createTransferInstruction(
-    TOKEN_PROGRAM_ID,
-    aliceXTokenAccountPubkey,
-    tempXTokenAccountKeypair.publicKey,
-    aliceKeypair.publicKey,
-    [],
-    terms.bobExpectedAmount
-  );
-  const escrowKeypair = new Keypair();
-  const createEscrowAccountIx = SystemProgram.createAccount({
-    space: ESCROW_ACCOUNT_DATA_LAYOUT.span,
-    lamports: await connection.getMinimumBalanceForRentExemption(
-      ESCROW_ACCOUNT_DATA_LAYOUT.span
-    ),
-    fromPubkey: aliceKeypair.publicKey,
-    newAccountPubkey: escrowKeypair.publicKey,
-    programId: escrowProgramId,
-  });
-  const initEscrowIx = new TransactionInstruction({
-    programId: escrowProgramId,
-    keys: [
-      { pubkey: aliceKeypair.publicKey, isSigner: true, isWritable: false },
-      {
-        pubkey: tempXTokenAccountKeypair.publicKey,
-        isSigner: false,
-        isWritable: true,
-      },
-      {
-        pubkey: aliceYTokenAccountPubkey,
-        isSigner: false,
-        isWritable: false,
-      },
-      { pubkey: escrowKeypair.publicKey, isSigner: false, isWritable: true },
-      { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-    ],
-    data: Buffer.from(
-      Uint8Array.of(0, ...new BN(terms.aliceExpectedAmount).toArray("le", 8))
-    ),
-  });
+  const initEscrowIx = async (
+    tempTokenAccount: PublicKey,
+    escrowAccount: PublicKey
+  ) =>
+    initEscrowInstruction(
+      escrowProgramId,
+      aliceKeypair.publicKey,
+      tempTokenAccount,
+      aliceYTokenAccountPubkey,
+      escrowAccount,
+      await getOperatorConfig(escrowProgramId),
+      terms.aliceExpectedAmount,
+      [],
+      ROUNDING_MODES.floorForTaker,
+      undefined,
+      aliceXTokenAccountPubkey,
+      undefined,
+      undefined,
+      XTokenMintPubkey
+    );
 
-  const tx = new Transaction().add(
-    createTempTokenAccountIx,
-    initTempAccountIx,
-    transferXTokensToTempAccIx,
-    createEscrowAccountIx,
-    initEscrowIx
+  if (isDumpingInstructions()) {
+    // the transaction it goes into creates and funds these two, so they're passed in
+    const [tempTokenAccount, escrowAccount] = dumpInstructionArgs().map(
+      (arg) => new PublicKey(arg)
+    );
+    if (escrowAccount === undefined) {
+      logError(
+        "Usage: alice --dump-instruction <temp-token-account> <escrow-account>"
+      );
+      process.exit(1);
+    }
+    dumpInstruction(await initEscrowIx(tempTokenAccount, escrowAccount));
+    writePublicKey(tempTokenAccount, "escrow_vault");
+    writePublicKey(escrowAccount, "escrow");
+    return;
+  }
+
+  const connection = new Connection("http://localhost:8899", "confirmed");
+  const plan = await planInitEscrow(
+    connection,
+    escrowProgramId,
+    aliceKeypair,
+    {
+      config: await getOperatorConfig(escrowProgramId),
+      depositMint: XTokenMintPubkey,
+      sendingTokenAccount: aliceXTokenAccountPubkey,
+      receivingTokenAccount: aliceYTokenAccountPubkey,
+      depositAmount: terms.bobExpectedAmount,
+      expectedAmount: terms.aliceExpectedAmount,
+    },
+    (await connection.getRecentBlockhash()).blockhash
   );
-  console.log("Sending Alice's transaction...");
-  await connection.sendTransaction(
-    tx,
-    [aliceKeypair, tempXTokenAccountKeypair, escrowKeypair],
-    { skipPreflight: false, preflightCommitment: "confirmed" }
+  console.log(
+    `Sending Alice's transactions (${plan.transactions.length} of them)...`
   );
+  await sendInitPlan(connection, aliceKeypair, plan);
 
   // sleep to allow time to update
   await new Promise((resolve) => setTimeout(resolve, 1000));
 
-  const escrowAccount = await connection.getAccountInfo(
-    escrowKeypair.publicKey
-  );
+  const escrowAccount = await connection.getAccountInfo(plan.escrowAccount);
 
   if (escrowAccount === null || escrowAccount.data.length === 0) {
     logError("Escrow state account has not been initialized properly");
@@ -119,7 +102,7 @@ const alice = async () => {
     encodedEscrowState
   ) as EscrowLayout;
 
-  if (!decodedEscrowState.isInitialized) {
+  if (!hasEscrowFlag(decodedEscrowState.flags, ESCROW_FLAGS.initialized)) {
     logError("Escrow state initialization flag has not been set");
     process.exit(1);
   } else if (
@@ -142,7 +125,7 @@ const alice = async () => {
     process.exit(1);
   } else if (
     !new PublicKey(decodedEscrowState.initializerTempTokenAccountPubkey).equals(
-      tempXTokenAccountKeypair.publicKey
+      plan.tempTokenAccount
     )
   ) {
     logError(
@@ -153,7 +136,8 @@ const alice = async () => {
   console.log(
     `✨Escrow successfully initialized. Alice is offering ${terms.bobExpectedAmount}X for ${terms.aliceExpectedAmount}Y✨\n`
   );
-  writePublicKey(escrowKeypair.publicKey, "escrow");
+  writePublicKey(plan.escrowAccount, "escrow");
+  writePublicKey(plan.tempTokenAccount, "escrow_vault");
   console.table([
     {
       "Alice Token Account X": await getTokenBalance(
@@ -173,7 +157,7 @@ const alice = async () => {
         connection
       ),
       "Temporary Token Account X": await getTokenBalance(
-        tempXTokenAccountKeypair.publicKey,
+        plan.tempTokenAccount,
         connection
       ),
     },
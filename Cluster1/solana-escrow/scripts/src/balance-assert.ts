@@ -0,0 +1,83 @@
+import { Connection } from "@solana/web3.js";
+import { getPublicKey, getTokenBalance } from "./utils";
+
+/**
+ * Snapshots token and lamport balances of accounts named in the `keys` folder, then checks
+ * the exact change of each against the expected deltas. Token accounts missing from the
+ * expected deltas must be unchanged. Wallets are only checked when listed, since paying
+ * transaction fees moves their lamports.
+ */
+export class BalanceAssert {
+  private tokenBalances = new Map<string, number>();
+  private lamportBalances = new Map<string, number>();
+
+  constructor(
+    readonly tokenAccounts: string[],
+    readonly wallets: string[] = []
+  ) {}
+
+  async snapshot(connection: Connection) {
+    for (const name of this.tokenAccounts) {
+      this.tokenBalances.set(
+        name,
+        await getTokenBalance(getPublicKey(name), connection)
+      );
+    }
+    for (const name of this.wallets) {
+      this.lamportBalances.set(
+        name,
+        await connection.getBalance(getPublicKey(name))
+      );
+    }
+  }
+
+  /** Throws listing every account whose balance didn't move by the expected delta */
+  async assertDeltas(
+    connection: Connection,
+    expected: { [name: string]: number }
+  ) {
+    const mismatches: string[] = [];
+
+    for (const name of this.tokenAccounts) {
+      const before = this.tokenBalances.get(name);
+      if (before === undefined) {
+        throw new Error("assertDeltas called before snapshot");
+      }
+      const delta =
+        (await getTokenBalance(getPublicKey(name), connection)) - before;
+      if (delta !== (expected[name] ?? 0)) {
+        mismatches.push(
+          `${name} moved by ${delta}, expected ${expected[name] ?? 0}`
+        );
+      }
+    }
+    for (const name of this.wallets) {
+      const before = this.lamportBalances.get(name);
+      if (before === undefined) {
+        throw new Error("assertDeltas called before snapshot");
+      }
+      if (expected[name] === undefined) {
+        continue;
+      }
+      const delta = (await connection.getBalance(getPublicKey(name))) - before;
+      if (delta !== expected[name]) {
+        mismatches.push(
+          `${name} lamports moved by ${delta}, expected ${expected[name]}`
+        );
+      }
+    }
+
+    const untracked = Object.keys(expected).filter(
+      (name) =>
+        this.tokenAccounts.indexOf(name) === -1 &&
+        this.wallets.indexOf(name) === -1
+    );
+    if (untracked.length > 0) {
+      mismatches.push(`no snapshot of ${untracked.join(", ")}`);
+    }
+
+    if (mismatches.length > 0) {
+      throw new Error(mismatches.join("; "));
+    }
+  }
+}
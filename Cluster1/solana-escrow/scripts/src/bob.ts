@@ -1,19 +1,19 @@
-import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
-import {
-  Connection,
-  PublicKey,
-  Transaction,
-  TransactionInstruction,
-} from "@solana/web3.js";
+import { Connection, PublicKey, Transaction } from "@solana/web3.js";
 import BN = require("bn.js");
+import { exchangeInstruction } from "./instructions";
 import {
+  dumpInstruction,
   EscrowLayout,
   ESCROW_ACCOUNT_DATA_LAYOUT,
+  ESCROW_FLAGS,
   getKeypair,
+  getOperatorConfig,
   getProgramId,
   getPublicKey,
   getTerms,
   getTokenBalance,
+  hasEscrowFlag,
+  isDumpingInstructions,
   logError,
 } from "./utils";
 
@@ -25,6 +25,41 @@ const bob = async () => {
   const escrowProgramId = getProgramId();
   const terms = getTerms();
 
+  const exchangeIx = (
+    tempTokenAccount: PublicKey,
+    initializer: PublicKey,
+    initializerReceivingTokenAccount: PublicKey,
+    config: PublicKey
+  ) =>
+    exchangeInstruction(
+      escrowProgramId,
+      bobKeypair.publicKey,
+      bobYTokenAccountPubkey,
+      bobXTokenAccountPubkey,
+      tempTokenAccount,
+      initializer,
+      initializerReceivingTokenAccount,
+      escrowStateAccountPubkey,
+      config,
+      getPublicKey("fee_y"),
+      getPublicKey("mint_x"),
+      getPublicKey("mint_y"),
+      terms.bobExpectedAmount
+    );
+
+  if (isDumpingInstructions()) {
+    // the escrow's accounts as alice wrote them, instead of reading the escrow back
+    dumpInstruction(
+      await exchangeIx(
+        getPublicKey("escrow_vault"),
+        getPublicKey("alice"),
+        getPublicKey("alice_y"),
+        await getOperatorConfig(escrowProgramId)
+      )
+    );
+    return;
+  }
+
   const connection = new Connection("http://localhost:8899", "confirmed");
   const escrowAccount = await connection.getAccountInfo(
     escrowStateAccountPubkey
@@ -40,7 +75,10 @@ const bob = async () => {
   ) as EscrowLayout;
   const escrowState = {
     escrowAccountPubkey: escrowStateAccountPubkey,
-    isInitialized: !!decodedEscrowLayout.isInitialized,
+    isInitialized: hasEscrowFlag(
+      decodedEscrowLayout.flags,
+      ESCROW_FLAGS.initialized
+    ),
     initializerAccountPubkey: new PublicKey(
       decodedEscrowLayout.initializerPubkey
     ),
@@ -51,43 +89,9 @@ const bob = async () => {
       decodedEscrowLayout.initializerReceivingTokenAccountPubkey
     ),
     expectedAmount: new BN(decodedEscrowLayout.expectedAmount, 10, "le"),
+    configPubkey: new PublicKey(decodedEscrowLayout.configPubkey),
   };
 
-  const PDA = await PublicKey.findProgramAddress(
-    [Buffer.from("escrow")],
-    escrowProgramId
-  );
-
-  const exchangeInstruction = new TransactionInstruction({
-    programId: escrowProgramId,
-    data: Buffer.from(
-      Uint8Array.of(1, ...new BN(terms.bobExpectedAmount).toArray("le", 8))
-    ),
-    keys: [
-      { pubkey: bobKeypair.publicKey, isSigner: true, isWritable: false },
-      { pubkey: bobYTokenAccountPubkey, isSigner: false, isWritable: true },
-      { pubkey: bobXTokenAccountPubkey, isSigner: false, isWritable: true },
-      {
-        pubkey: escrowState.XTokenTempAccountPubkey,
-        isSigner: false,
-        isWritable: true,
-      },
-      {
-        pubkey: escrowState.initializerAccountPubkey,
-        isSigner: false,
-        isWritable: true,
-      },
-      {
-        pubkey: escrowState.initializerYTokenAccount,
-        isSigner: false,
-        isWritable: true,
-      },
-      { pubkey: escrowStateAccountPubkey, isSigner: false, isWritable: true },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: PDA[0], isSigner: false, isWritable: false },
-    ],
-  });
-
   const aliceYTokenAccountPubkey = getPublicKey("alice_y");
   const [aliceYbalance, bobXbalance] = await Promise.all([
     getTokenBalance(aliceYTokenAccountPubkey, connection),
@@ -96,7 +100,14 @@ const bob = async () => {
 
   console.log("Sending Bob's transaction...");
   await connection.sendTransaction(
-    new Transaction().add(exchangeInstruction),
+    new Transaction().add(
+      await exchangeIx(
+        escrowState.XTokenTempAccountPubkey,
+        escrowState.initializerAccountPubkey,
+        escrowState.initializerYTokenAccount,
+        escrowState.configPubkey
+      )
+    ),
     [bobKeypair],
     { skipPreflight: false, preflightCommitment: "confirmed" }
   );
@@ -0,0 +1,119 @@
+import {
+  AccountLayout,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  Token,
+  TOKEN_PROGRAM_ID,
+  u64,
+} from "@solana/spl-token";
+import { Connection, Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { sendEscrowTransaction } from "./errors";
+import {
+  EscrowLayout,
+  ESCROW_ACCOUNT_DATA_LAYOUT,
+  ESCROW_FLAGS,
+  hasEscrowFlag,
+} from "./utils";
+
+export interface OrphanedSetupCleanup {
+  /** Rent returned to the initializer by closing the temp token account, 0 if it wasn't orphaned */
+  reclaimedLamports: number;
+  /** Tokens moved from the temp token account back to the refund account */
+  returnedAmount: u64;
+  /**
+   * An escrow account that was created but never initialized. Only the program can debit it,
+   * so it can't be closed, but InitEscrow doesn't need its keypair: pass it to the retried
+   * `initEscrowInstruction` instead of creating a new one.
+   */
+  reusableEscrowAccount: PublicKey | null;
+}
+
+/**
+ * Recovers what a multi-transaction escrow setup leaves behind when InitEscrow fails after
+ * the temp token account was created and funded. InitEscrow hands the temp account to its
+ * vault authority in the same transaction it succeeds in, so one still owned by the initializer
+ * is orphaned: its tokens go back to `refundTokenAccount` (by default the initializer's
+ * associated token account for its mint) and it is closed to the initializer. Nothing is
+ * touched if the setup actually succeeded.
+ */
+export const cleanupOrphanedSetup = async (
+  connection: Connection,
+  programId: PublicKey,
+  initializer: Keypair,
+  tempTokenAccount: PublicKey,
+  escrowAccount?: PublicKey,
+  refundTokenAccount?: PublicKey
+): Promise<OrphanedSetupCleanup> => {
+  const result: OrphanedSetupCleanup = {
+    reclaimedLamports: 0,
+    returnedAmount: new u64(0),
+    reusableEscrowAccount: null,
+  };
+
+  if (escrowAccount !== undefined) {
+    const escrowInfo = await connection.getAccountInfo(escrowAccount);
+    if (
+      escrowInfo !== null &&
+      escrowInfo.owner.equals(programId) &&
+      escrowInfo.data.length === ESCROW_ACCOUNT_DATA_LAYOUT.span &&
+      !hasEscrowFlag(
+        (ESCROW_ACCOUNT_DATA_LAYOUT.decode(escrowInfo.data) as EscrowLayout)
+          .flags,
+        ESCROW_FLAGS.initialized
+      )
+    ) {
+      result.reusableEscrowAccount = escrowAccount;
+    }
+  }
+
+  const tempInfo = await connection.getAccountInfo(tempTokenAccount);
+  if (
+    tempInfo === null ||
+    !tempInfo.owner.equals(TOKEN_PROGRAM_ID) ||
+    tempInfo.data.length !== AccountLayout.span
+  ) {
+    return result;
+  }
+  const temp = AccountLayout.decode(tempInfo.data);
+  if (!new PublicKey(temp.owner).equals(initializer.publicKey)) {
+    // handed over to its vault authority, so the escrow exists and Cancel is the way out
+    return result;
+  }
+
+  const mint = new PublicKey(temp.mint);
+  const amount = u64.fromBuffer(temp.amount);
+  const tx = new Transaction();
+  if (!amount.isZero()) {
+    const destination =
+      refundTokenAccount ??
+      (await Token.getAssociatedTokenAddress(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        mint,
+        initializer.publicKey
+      ));
+    tx.add(
+      Token.createTransferInstruction(
+        TOKEN_PROGRAM_ID,
+        tempTokenAccount,
+        destination,
+        initializer.publicKey,
+        [],
+        amount
+      )
+    );
+  }
+  tx.add(
+    Token.createCloseAccountInstruction(
+      TOKEN_PROGRAM_ID,
+      tempTokenAccount,
+      initializer.publicKey,
+      initializer.publicKey,
+      []
+    )
+  );
+  await sendEscrowTransaction(connection, programId, tx, [initializer]);
+
+  result.reclaimedLamports = tempInfo.lamports;
+  result.returnedAmount = amount;
+  return result;
+};
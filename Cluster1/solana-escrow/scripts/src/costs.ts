@@ -0,0 +1,88 @@
+import { AccountLayout } from "@solana/spl-token";
+import { Connection } from "@solana/web3.js";
+import { ESCROW_ACCOUNT_DATA_LAYOUT } from "./utils";
+
+// alice's transaction is signed by the initializer and the new temp token and escrow accounts
+const OPEN_SIGNATURES = 3;
+const FILL_SIGNATURES = 1;
+
+/**
+ * Compute unit upper bounds per instruction, mirroring the `*_COMPUTE_UNITS` constants in the
+ * program's instruction.rs. Request these as the transaction's compute unit limit rather than
+ * the 200k default to pay lower priority fees.
+ */
+export const COMPUTE_UNITS = {
+  initEscrow: 40_000,
+  exchange: 70_000,
+  exchangePerPayout: 8_000,
+  cancel: 70_000,
+  mintReceipt: 150_000,
+  claimProceeds: 50_000,
+  reap: 60_000,
+  cancelExpired: 60_000,
+  forceClose: 60_000,
+};
+
+// opening also runs the system and token program instructions that create and fund the vault
+const OPEN_COMPUTE_UNITS = COMPUTE_UNITS.initEscrow + 10_000;
+
+export interface CostEstimate {
+  rent: number;
+  transactionFee: number;
+  priorityFee: number;
+  total: number;
+}
+
+const estimate = (
+  rent: number,
+  signatures: number,
+  lamportsPerSignature: number,
+  priorityFee: number
+): CostEstimate => {
+  const transactionFee = signatures * lamportsPerSignature;
+  return {
+    rent,
+    transactionFee,
+    priorityFee,
+    total: rent + transactionFee + priorityFee,
+  };
+};
+
+/**
+ * Lamports a user needs to open and to fill an escrow, for wallets to show before signing.
+ * Opening locks rent for the escrow and vault accounts, which the initializer gets back
+ * when the escrow is filled or cancelled. The priority fee is computed from the compute
+ * unit price (in micro-lamports) the transactions will be sent with, assuming they request
+ * the `COMPUTE_UNITS` bounds as their limit.
+ */
+export const estimateCosts = async (
+  connection: Connection,
+  computeUnitPrice = 0,
+  payoutCount = 0
+) => {
+  const { feeCalculator } = await connection.getRecentBlockhash();
+  const priorityFee = (computeUnits: number) =>
+    Math.ceil((computeUnitPrice * computeUnits) / 1_000_000);
+  const rent =
+    (await connection.getMinimumBalanceForRentExemption(
+      ESCROW_ACCOUNT_DATA_LAYOUT.span
+    )) +
+    (await connection.getMinimumBalanceForRentExemption(AccountLayout.span));
+
+  return {
+    open: estimate(
+      rent,
+      OPEN_SIGNATURES,
+      feeCalculator.lamportsPerSignature,
+      priorityFee(OPEN_COMPUTE_UNITS)
+    ),
+    fill: estimate(
+      0,
+      FILL_SIGNATURES,
+      feeCalculator.lamportsPerSignature,
+      priorityFee(
+        COMPUTE_UNITS.exchange + COMPUTE_UNITS.exchangePerPayout * payoutCount
+      )
+    ),
+  };
+};
@@ -0,0 +1,245 @@
+import {
+  AccountLayout,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  MintLayout,
+  Token,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Connection,
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { createHash } from "crypto";
+import { initEscrowInstruction } from "./instructions";
+import { ESCROW_ACCOUNT_DATA_LAYOUT, getOperatorConfig } from "./utils";
+
+/**
+ * The same keypair for the same label and seed on every run, so local environments can be
+ * rebuilt at known addresses. Never use these for anything holding real funds.
+ */
+export const deterministicKeypair = (label: string, seed = "devtools") =>
+  Keypair.fromSeed(
+    createHash("sha256").update(`escrow-devtools:${seed}:${label}`).digest()
+  );
+
+// each step builds on the accounts the previous one created, so wait for it to land
+const send = async (
+  connection: Connection,
+  tx: Transaction,
+  signers: Keypair[]
+) =>
+  connection.confirmTransaction(
+    await connection.sendTransaction(tx, signers, {
+      skipPreflight: false,
+      preflightCommitment: "confirmed",
+    })
+  );
+
+const associatedTokenAddress = (mint: PublicKey, owner: PublicKey) =>
+  Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+    mint,
+    owner
+  );
+
+// airdrops only exist on local and test networks, which is all these helpers are for
+const fund = async (connection: Connection, account: PublicKey) => {
+  if ((await connection.getBalance(account)) < LAMPORTS_PER_SOL) {
+    await connection.confirmTransaction(
+      await connection.requestAirdrop(account, LAMPORTS_PER_SOL * 10)
+    );
+  }
+};
+
+/**
+ * Creates a 0-decimal mint at a deterministic address, unless it already exists
+ */
+export const deterministicMint = async (
+  connection: Connection,
+  name: string,
+  authority: Keypair,
+  seed = "devtools"
+) => {
+  const mint = deterministicKeypair(`mint:${name}`, seed);
+  if ((await connection.getAccountInfo(mint.publicKey)) === null) {
+    await send(
+      connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: authority.publicKey,
+          newAccountPubkey: mint.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(
+            MintLayout.span
+          ),
+          space: MintLayout.span,
+          programId: TOKEN_PROGRAM_ID,
+        }),
+        Token.createInitMintInstruction(
+          TOKEN_PROGRAM_ID,
+          mint.publicKey,
+          0,
+          authority.publicKey,
+          null
+        )
+      ),
+      [authority, mint]
+    );
+  }
+  return mint.publicKey;
+};
+
+const fundedTokenAccount = async (
+  connection: Connection,
+  mint: PublicKey,
+  owner: PublicKey,
+  amount: number,
+  authority: Keypair
+) => {
+  const tokenAccount = await associatedTokenAddress(mint, owner);
+  const tx = new Transaction();
+  if ((await connection.getAccountInfo(tokenAccount)) === null) {
+    tx.add(
+      Token.createAssociatedTokenAccountInstruction(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        mint,
+        tokenAccount,
+        owner,
+        authority.publicKey
+      )
+    );
+  }
+  if (amount > 0) {
+    tx.add(
+      Token.createMintToInstruction(
+        TOKEN_PROGRAM_ID,
+        mint,
+        tokenAccount,
+        authority.publicKey,
+        [],
+        amount
+      )
+    );
+  }
+  if (tx.instructions.length > 0) {
+    await send(connection, tx, [authority]);
+  }
+  return tokenAccount;
+};
+
+export interface SpawnedOffer {
+  maker: PublicKey;
+  escrowAccount: PublicKey;
+  tempTokenAccount: PublicKey;
+  makerReceivingTokenAccount: PublicKey;
+  offerAmount: number;
+  expectedAmount: number;
+}
+
+/**
+ * Populates a local validator with `nOffers` open escrows of deterministic makers trading
+ * mint X for mint Y, with offer sizes and prices spread out like a real order book. The
+ * stats account and the "id" operator config must exist, see the setup script. Running it
+ * again with the same seed only opens the offers that are missing.
+ */
+export const spawnScenario = async (
+  connection: Connection,
+  programId: PublicKey,
+  nOffers: number,
+  seed = "devtools"
+) => {
+  const authority = deterministicKeypair("mint-authority", seed);
+  await fund(connection, authority.publicKey);
+  const mintX = await deterministicMint(connection, "x", authority, seed);
+  const mintY = await deterministicMint(connection, "y", authority, seed);
+  const config = await getOperatorConfig(programId);
+
+  const offers: SpawnedOffer[] = [];
+  for (let i = 0; i < nOffers; i++) {
+    const maker = deterministicKeypair(`maker:${i % 8}`, seed);
+    const tempTokenAccount = deterministicKeypair(`offer:${i}:vault`, seed);
+    const escrowAccount = deterministicKeypair(`offer:${i}:escrow`, seed);
+    // sizes cycle between 10 and 100 and prices between 0.8 and 1.2 Y per X
+    const offerAmount = 10 + ((i * 37) % 91);
+    const expectedAmount = Math.round(
+      (offerAmount * (80 + ((i * 13) % 41))) / 100
+    );
+
+    const makerReceivingTokenAccount = await associatedTokenAddress(
+      mintY,
+      maker.publicKey
+    );
+    offers.push({
+      maker: maker.publicKey,
+      escrowAccount: escrowAccount.publicKey,
+      tempTokenAccount: tempTokenAccount.publicKey,
+      makerReceivingTokenAccount,
+      offerAmount,
+      expectedAmount,
+    });
+    if ((await connection.getAccountInfo(escrowAccount.publicKey)) !== null) {
+      continue;
+    }
+
+    await fund(connection, maker.publicKey);
+    const makerTokenAccount = await fundedTokenAccount(
+      connection,
+      mintX,
+      maker.publicKey,
+      offerAmount,
+      authority
+    );
+    await fundedTokenAccount(connection, mintY, maker.publicKey, 0, authority);
+
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        programId: TOKEN_PROGRAM_ID,
+        space: AccountLayout.span,
+        lamports: await connection.getMinimumBalanceForRentExemption(
+          AccountLayout.span
+        ),
+        fromPubkey: maker.publicKey,
+        newAccountPubkey: tempTokenAccount.publicKey,
+      }),
+      Token.createInitAccountInstruction(
+        TOKEN_PROGRAM_ID,
+        mintX,
+        tempTokenAccount.publicKey,
+        maker.publicKey
+      ),
+      Token.createTransferInstruction(
+        TOKEN_PROGRAM_ID,
+        makerTokenAccount,
+        tempTokenAccount.publicKey,
+        maker.publicKey,
+        [],
+        offerAmount
+      ),
+      SystemProgram.createAccount({
+        space: ESCROW_ACCOUNT_DATA_LAYOUT.span,
+        lamports: await connection.getMinimumBalanceForRentExemption(
+          ESCROW_ACCOUNT_DATA_LAYOUT.span
+        ),
+        fromPubkey: maker.publicKey,
+        newAccountPubkey: escrowAccount.publicKey,
+        programId,
+      }),
+      await initEscrowInstruction(
+        programId,
+        maker.publicKey,
+        tempTokenAccount.publicKey,
+        makerReceivingTokenAccount,
+        escrowAccount.publicKey,
+        config,
+        expectedAmount
+      )
+    );
+    await send(connection, tx, [maker, tempTokenAccount, escrowAccount]);
+  }
+  return { mintX, mintY, offers };
+};
@@ -0,0 +1,262 @@
+import {
+  Connection,
+  Keypair,
+  PublicKey,
+  Transaction,
+  TransactionError,
+} from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { ESCROW_ALREADY_SETTLED_ERROR } from "./utils";
+
+/**
+ * `EscrowError` variants by custom error code, in the order they're declared in the program's
+ * error.rs
+ */
+export const ESCROW_ERRORS = [
+  { name: "InvalidInstruction", message: "Invalid Instruction" },
+  { name: "NotRentExempt", message: "Not Rent Exempt" },
+  { name: "ExpectedAmountMismatch", message: "Expected Amount Mismatch" },
+  { name: "AmountOverflow", message: "Amount Overflow" },
+  { name: "InvalidPayoutSplit", message: "Invalid Payout Split" },
+  { name: "InvalidConfig", message: "Invalid Config" },
+  {
+    name: "AmountOutOfBounds",
+    message: "Expected Amount Outside Operator Limits",
+  },
+  {
+    name: "ReceiveAccountUnavailable",
+    message: "Receive Account Unavailable",
+  },
+  { name: "ReceiptNotAllowed", message: "Receipt Not Allowed" },
+  { name: "EscrowNotSettled", message: "Escrow Not Settled" },
+  { name: "EscrowExpired", message: "Escrow Expired" },
+  { name: "EscrowNotExpired", message: "Escrow Not Expired" },
+  { name: "MintClosed", message: "Mint Closed" },
+  { name: "NotAllowlisted", message: "Not Allowlisted" },
+  { name: "EscrowAlreadySettled", message: "Escrow Already Settled" },
+  { name: "TakerCommitmentMismatch", message: "Taker Commitment Mismatch" },
+  { name: "UnlockSlotsOutOfBounds", message: "Unlock Slots Out Of Bounds" },
+  { name: "FillDeadlinePassed", message: "Fill Deadline Passed" },
+  {
+    name: "TimeConstraintWasNotSatisfied",
+    message: "Time Constraint Was Not Satisfied",
+  },
+  { name: "VaultShortfall", message: "Vault Shortfall" },
+  { name: "SettlementNotAllowed", message: "Settlement Not Allowed" },
+  { name: "SettlementIncomplete", message: "Settlement Incomplete" },
+  { name: "VolumeCapExceeded", message: "Volume Cap Exceeded" },
+  { name: "UnsupportedFeatures", message: "Unsupported Features" },
+  { name: "InvalidFillAmount", message: "Invalid Fill Amount" },
+  { name: "ConfigPaused", message: "Config Paused" },
+  { name: "TimeLockResetLimited", message: "Time Lock Reset Limited" },
+  { name: "TakerNotAllowed", message: "Taker Not Allowed" },
+  { name: "TakerAllowlistFull", message: "Taker Allowlist Full" },
+  { name: "CounterOfferOutdated", message: "Counter Offer Outdated" },
+  { name: "EscrowPartiallyFilled", message: "Escrow Partially Filled" },
+  { name: "InvalidEscrowTags", message: "Invalid Escrow Tags" },
+  { name: "EscrowTagsFixed", message: "Escrow Tags Fixed" },
+  { name: "TakerGateNotMet", message: "Taker Gate Not Met" },
+  { name: "PaymentAboveMax", message: "Payment Above Max" },
+  { name: "RoyaltiesExceedProceeds", message: "Royalties Exceed Proceeds" },
+  { name: "PremiumExceedsProceeds", message: "Premium Exceeds Proceeds" },
+  { name: "BasketFull", message: "Basket Full" },
+  { name: "RingMismatch", message: "Ring Mismatch" },
+  { name: "MutualDepositOnly", message: "Mutual Deposit Only" },
+  { name: "TakerDepositMissing", message: "Taker Deposit Missing" },
+  { name: "HashLocked", message: "Hash Locked" },
+  { name: "PreimageMismatch", message: "Preimage Mismatch" },
+  { name: "PermitInvalid", message: "Permit Invalid" },
+  { name: "RateLimited", message: "Rate Limited" },
+  { name: "GasRebateUnfunded", message: "Gas Rebate Unfunded" },
+  { name: "OracleInvalid", message: "Oracle Invalid" },
+  { name: "OracleStale", message: "Oracle Stale" },
+  { name: "OracleUncertain", message: "Oracle Uncertain" },
+  {
+    name: "UnsupportedTokenExtension",
+    message: "Unsupported Token Extension",
+  },
+  { name: "FillCoolingDown", message: "Fill Cooling Down" },
+  { name: "NotDesignatedTaker", message: "Not Designated Taker" },
+];
+
+// spl-token's TokenError, which the escrow's token program CPIs fail with
+const TOKEN_ERRORS = [
+  "Lamport balance below rent-exempt threshold",
+  "Insufficient funds",
+  "Invalid Mint",
+  "Account not associated with this Mint",
+  "Owner does not match",
+  "Fixed supply",
+  "Already in use",
+  "Invalid number of provided signers",
+  "Invalid number of required signers",
+  "State is uninitialized",
+  "Instruction does not support native tokens",
+  "Non-native account can only be closed if its balance is zero",
+  "Invalid instruction",
+  "State is invalid for requested operation",
+  "Operation overflowed",
+  "Account does not support specified authority type",
+  "This token mint cannot freeze accounts",
+  "Account is frozen",
+  "The provided decimals value different from the Mint decimals",
+];
+
+/**
+ * A failed escrow transaction, decoded from the runtime's `TransactionError` and logs.
+ * `program` is "escrow" or "token" when the custom error code could be attributed to either,
+ * so `errorName` is one of `ESCROW_ERRORS`' names or a token program message; anything else
+ * (signature checks, builtin `ProgramError`s, ...) keeps the runtime's own description.
+ */
+export class EscrowClientError extends Error {
+  constructor(
+    readonly program: "escrow" | "token" | "other",
+    readonly code: number | null,
+    readonly errorName: string,
+    readonly instructionIndex: number | null,
+    readonly logs: string[]
+  ) {
+    super(
+      instructionIndex === null
+        ? errorName
+        : `instruction ${instructionIndex}: ${errorName}`
+    );
+    this.name = "EscrowClientError";
+  }
+
+  /** Another transaction settled the escrow first, so there is nothing left to retry */
+  get isAlreadySettled() {
+    return (
+      this.program === "escrow" && this.code === ESCROW_ALREADY_SETTLED_ERROR
+    );
+  }
+}
+
+// the innermost program that failed is the one whose custom code this is
+const failingProgram = (logs: string[], programId: PublicKey) => {
+  const failure = logs
+    .slice()
+    .reverse()
+    .find((line) => / failed: /.test(line));
+  if (failure === undefined) {
+    return "escrow";
+  }
+  if (failure.includes(TOKEN_PROGRAM_ID.toBase58())) {
+    return "token";
+  }
+  return failure.includes(programId.toBase58()) ? "escrow" : "other";
+};
+
+const customError = (
+  code: number,
+  instructionIndex: number | null,
+  logs: string[],
+  programId: PublicKey
+) => {
+  const program = failingProgram(logs, programId);
+  const name =
+    program === "escrow"
+      ? ESCROW_ERRORS[code]?.name
+      : program === "token"
+      ? TOKEN_ERRORS[code]
+      : undefined;
+  return new EscrowClientError(
+    program,
+    code,
+    name ?? `custom program error: ${code}`,
+    instructionIndex,
+    logs
+  );
+};
+
+/**
+ * Maps a `TransactionError` as found in `simulateTransaction` or `confirmTransaction`
+ * results, or the error thrown by `sendTransaction` when preflight fails, to an
+ * `EscrowClientError`
+ */
+export const toEscrowClientError = (
+  err: TransactionError | unknown,
+  programId: PublicKey,
+  logs: string[] = []
+) => {
+  const withLogs = err as { logs?: string[] };
+  if (logs.length === 0 && Array.isArray(withLogs?.logs)) {
+    logs = withLogs.logs;
+  }
+
+  const instructionError = (err as { InstructionError?: [number, unknown] })
+    ?.InstructionError;
+  if (instructionError !== undefined) {
+    const [index, detail] = instructionError;
+    const custom = (detail as { Custom?: number })?.Custom;
+    return custom === undefined
+      ? new EscrowClientError(
+          "other",
+          null,
+          typeof detail === "string" ? detail : JSON.stringify(detail),
+          index,
+          logs
+        )
+      : customError(custom, index, logs, programId);
+  }
+
+  // preflight failures only come back as text, e.g.
+  // "... Error processing Instruction 0: custom program error: 0xe"
+  const message = err instanceof Error ? err.message : `${err}`;
+  const custom = /Instruction (\d+): custom program error: (0x[0-9a-f]+)/.exec(
+    message
+  );
+  if (custom !== null) {
+    return customError(
+      parseInt(custom[2], 16),
+      parseInt(custom[1], 10),
+      logs,
+      programId
+    );
+  }
+  return new EscrowClientError("other", null, message, null, logs);
+};
+
+/**
+ * Sends and confirms a transaction, throwing an `EscrowClientError` if preflight or the
+ * transaction itself fails
+ */
+export const sendEscrowTransaction = async (
+  connection: Connection,
+  programId: PublicKey,
+  tx: Transaction,
+  signers: Keypair[]
+) => {
+  let signature: string;
+  try {
+    signature = await connection.sendTransaction(tx, signers, {
+      skipPreflight: false,
+      preflightCommitment: "confirmed",
+    });
+  } catch (e) {
+    throw toEscrowClientError(e, programId);
+  }
+  const { value } = await connection.confirmTransaction(signature);
+  if (value.err !== null) {
+    const logs =
+      (await connection.getTransaction(signature))?.meta?.logMessages ?? [];
+    throw toEscrowClientError(value.err, programId, logs);
+  }
+  return signature;
+};
+
+/**
+ * Simulates a transaction, throwing an `EscrowClientError` if it would fail
+ */
+export const simulateEscrowTransaction = async (
+  connection: Connection,
+  programId: PublicKey,
+  tx: Transaction,
+  signers: Keypair[]
+) => {
+  const { value } = await connection.simulateTransaction(tx, signers);
+  if (value.err !== null) {
+    throw toEscrowClientError(value.err, programId, value.logs ?? []);
+  }
+  return value;
+};
@@ -0,0 +1,123 @@
+import { AccountInfo, Commitment, Connection, PublicKey } from "@solana/web3.js";
+import { EscrowLayout, ESCROW_ACCOUNT_DATA_LAYOUT } from "./utils";
+
+interface CacheEntry {
+  /** null once the escrow is closed, or if the account isn't an escrow of the program */
+  escrow: EscrowLayout | null;
+  /** Slot the escrow was last seen at, by a fetch or a websocket update */
+  slot: number;
+  subscriptionId: number;
+}
+
+/**
+ * Caches decoded escrows for bots that evaluate quotes far more often than escrows change.
+ * The first `getCachedOrFetch` of an escrow fetches it and subscribes to its account, and
+ * from then on websocket updates keep the entry current without further getAccountInfo calls.
+ * Each entry remembers the slot it was last seen at, so an update older than what is cached
+ * is dropped and callers can demand a minimum slot before trusting it.
+ */
+export class EscrowCache {
+  private entries = new Map<string, CacheEntry>();
+  private pending = new Map<string, Promise<CacheEntry>>();
+
+  constructor(
+    private connection: Connection,
+    private programId: PublicKey,
+    private commitment: Commitment = "confirmed"
+  ) {}
+
+  /**
+   * The cached escrow, fetched only if it isn't cached yet or was last seen before `minSlot`.
+   * Resolves to null for a closed escrow.
+   */
+  async getCachedOrFetch(
+    escrowAccount: PublicKey,
+    minSlot = 0
+  ): Promise<EscrowLayout | null> {
+    const key = escrowAccount.toBase58();
+    const cached = this.entries.get(key);
+    if (cached !== undefined && cached.slot >= minSlot) {
+      return cached.escrow;
+    }
+
+    let fetching = this.pending.get(key);
+    if (fetching === undefined) {
+      fetching = this.fetch(escrowAccount).finally(() =>
+        this.pending.delete(key)
+      );
+      this.pending.set(key, fetching);
+    }
+    return (await fetching).escrow;
+  }
+
+  /** Drops the escrow's entry and its subscription, e.g. once a bot stops quoting it */
+  async invalidate(escrowAccount: PublicKey) {
+    const key = escrowAccount.toBase58();
+    const entry = this.entries.get(key);
+    if (entry === undefined) {
+      return;
+    }
+    this.entries.delete(key);
+    await this.connection.removeAccountChangeListener(entry.subscriptionId);
+  }
+
+  /** Drops every entry and closes their subscriptions */
+  async clear() {
+    const entries = [...this.entries.values()];
+    this.entries.clear();
+    await Promise.all(
+      entries.map(({ subscriptionId }) =>
+        this.connection.removeAccountChangeListener(subscriptionId)
+      )
+    );
+  }
+
+  private decode(account: AccountInfo<Buffer> | null) {
+    if (
+      account === null ||
+      !account.owner.equals(this.programId) ||
+      account.data.length !== ESCROW_ACCOUNT_DATA_LAYOUT.span
+    ) {
+      return null;
+    }
+    return ESCROW_ACCOUNT_DATA_LAYOUT.decode(account.data) as EscrowLayout;
+  }
+
+  private async fetch(escrowAccount: PublicKey) {
+    const key = escrowAccount.toBase58();
+    let entry = this.entries.get(key);
+    if (entry === undefined) {
+      // subscribe before fetching, so an update landing in between isn't lost
+      entry = {
+        escrow: null,
+        slot: -1,
+        subscriptionId: this.connection.onAccountChange(
+          escrowAccount,
+          (account, { slot }) => this.update(key, account, slot),
+          this.commitment
+        ),
+      };
+      this.entries.set(key, entry);
+    }
+
+    const { context, value } = await this.connection.getAccountInfoAndContext(
+      escrowAccount,
+      this.commitment
+    );
+    this.update(key, value, context.slot);
+    return entry;
+  }
+
+  private update(
+    key: string,
+    account: AccountInfo<Buffer> | null,
+    slot: number
+  ) {
+    const entry = this.entries.get(key);
+    if (entry === undefined || slot < entry.slot) {
+      return;
+    }
+    entry.escrow = this.decode(account);
+    entry.slot = slot;
+  }
+}
@@ -0,0 +1,23 @@
+import { Connection, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { CostEstimate, estimateCosts } from "./costs";
+
+const toSol = (lamports: number) => (lamports / LAMPORTS_PER_SOL).toFixed(9);
+
+const printEstimate = (label: string, estimate: CostEstimate) => {
+  console.log(`${label}: ${toSol(estimate.total)} SOL`);
+  console.log(`  rent:            ${toSol(estimate.rent)} SOL`);
+  console.log(`  transaction fee: ${toSol(estimate.transactionFee)} SOL`);
+  console.log(`  priority fee:    ${toSol(estimate.priorityFee)} SOL`);
+};
+
+const printCosts = async () => {
+  const computeUnitPrice = Number(process.argv[2] ?? 0);
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  const costs = await estimateCosts(connection, computeUnitPrice);
+  printEstimate("Opening an escrow", costs.open);
+  printEstimate("Filling an escrow", costs.fill);
+  console.log("The rent is returned to the initializer when the escrow closes");
+};
+
+printCosts();
@@ -0,0 +1,118 @@
+import { Connection, PublicKey } from "@solana/web3.js";
+import BN = require("bn.js");
+import * as bs58 from "bs58";
+import * as fs from "fs";
+import * as path from "path";
+import { EscrowLayout, ESCROW_ACCOUNT_DATA_LAYOUT, getProgramId } from "./utils";
+
+const toCsv = (header: string[], rows: (string | number)[][]) =>
+  [header, ...rows].map((row) => row.join(",")).join("\n") + "\n";
+
+const exportEscrows = async (
+  connection: Connection,
+  programId: PublicKey,
+  outDir: string
+) => {
+  const programAccounts = await connection.getProgramAccounts(programId, {
+    filters: [{ dataSize: ESCROW_ACCOUNT_DATA_LAYOUT.span }],
+  });
+
+  const rows = programAccounts.map(({ pubkey, account }) => {
+    const state = ESCROW_ACCOUNT_DATA_LAYOUT.decode(
+      account.data
+    ) as EscrowLayout;
+    return [
+      pubkey.toBase58(),
+      new PublicKey(state.initializerPubkey).toBase58(),
+      new PublicKey(state.initializerTempTokenAccountPubkey).toBase58(),
+      new PublicKey(state.initializerReceivingTokenAccountPubkey).toBase58(),
+      new BN(state.expectedAmount, 10, "le").toString(),
+      new BN(state.unlockTime, 10, "le").toString(),
+      new PublicKey(state.configPubkey).toBase58(),
+      state.payoutCount,
+      new BN(state.lifetimeExpirySlot, 10, "le").toString(),
+      account.lamports,
+    ];
+  });
+
+  fs.writeFileSync(
+    path.join(outDir, "escrows.csv"),
+    toCsv(
+      [
+        "escrow",
+        "initializer",
+        "temp_token_account",
+        "receive_token_account",
+        "expected_amount",
+        "unlock_time",
+        "config",
+        "payout_count",
+        "lifetime_expiry_slot",
+        "lamports",
+      ],
+      rows
+    )
+  );
+  return rows.length;
+};
+
+// fills are recovered from the program's transaction history by looking for Exchange logs
+const exportFills = async (
+  connection: Connection,
+  programId: PublicKey,
+  outDir: string
+) => {
+  const signatures = await connection.getSignaturesForAddress(programId);
+  const rows: (string | number)[][] = [];
+
+  for (const { signature, err } of signatures) {
+    if (err !== null) {
+      continue;
+    }
+    const tx = await connection.getTransaction(signature);
+    if (tx === null || tx.meta === null) {
+      continue;
+    }
+    if (
+      !(tx.meta.logMessages ?? []).includes(
+        "Program log: Instruction: Exchange"
+      )
+    ) {
+      continue;
+    }
+    const { accountKeys, instructions } = tx.transaction.message;
+    instructions
+      .filter((ix) => accountKeys[ix.programIdIndex].equals(programId))
+      .filter((ix) => bs58.decode(ix.data)[0] === 1)
+      .forEach((ix) => {
+        // exchange accounts: 0 taker, 6 escrow, see EscrowInstruction::Exchange
+        rows.push([
+          signature,
+          tx.slot,
+          tx.blockTime ?? "",
+          accountKeys[ix.accounts[0]].toBase58(),
+          accountKeys[ix.accounts[6]].toBase58(),
+        ]);
+      });
+  }
+
+  fs.writeFileSync(
+    path.join(outDir, "fills.csv"),
+    toCsv(["signature", "slot", "block_time", "taker", "escrow"], rows)
+  );
+  return rows.length;
+};
+
+const exportActivity = async () => {
+  const outDir = process.argv[2] ?? ".";
+  const programId = getProgramId();
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  const escrowCount = await exportEscrows(connection, programId, outDir);
+  const fillCount = await exportFills(connection, programId, outDir);
+  console.log(
+    `✨Exported ${escrowCount} open escrows and ${fillCount} fills to ${outDir}✨\n`
+  );
+};
+
+exportActivity();
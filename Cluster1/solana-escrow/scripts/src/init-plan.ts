@@ -0,0 +1,247 @@
+import { AccountLayout, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  Connection,
+  Keypair,
+  PACKET_DATA_SIZE,
+  PublicKey,
+  Signer,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { COMPUTE_UNITS } from "./costs";
+import {
+  HashLock,
+  initEscrowInstruction,
+  Payout,
+  PriceBand,
+  TakerGate,
+} from "./instructions";
+import { ESCROW_ACCOUNT_DATA_LAYOUT, ROUNDING_MODES } from "./utils";
+
+// the default limit of a transaction that doesn't request one
+const MAX_TRANSACTION_COMPUTE_UNITS = 200_000;
+
+// what the setup instructions use at most, on top of `COMPUTE_UNITS.initEscrow`
+const SETUP_COMPUTE_UNITS = {
+  createAccount: 3_000,
+  initAccount: 5_000,
+  transfer: 5_000,
+};
+
+/** What an escrow is opened with, see `initEscrowInstruction` */
+export interface InitOffer {
+  config: PublicKey;
+  depositMint: PublicKey;
+  // the initializer's token X account the deposit comes out of, and refunds go back to
+  sendingTokenAccount: PublicKey;
+  receivingTokenAccount: PublicKey;
+  depositAmount: number;
+  expectedAmount: number;
+  payouts?: Payout[];
+  roundingMode?: number;
+  auditLog?: PublicKey;
+  allowlistEntry?: PublicKey;
+  takerCommitmentHash?: Buffer;
+  unlockSlots?: number;
+  partialFill?: boolean;
+  takerGate?: TakerGate;
+  hashLock?: HashLock;
+  // lamports the taker is paid on the fill, funded along with the escrow account's rent
+  gasRebate?: number;
+  priceBand?: PriceBand;
+  rateCounter?: PublicKey;
+  quoteConvention?: number;
+  fillCooldownSlots?: number;
+  designatedTaker?: PublicKey;
+}
+
+export interface PlannedTransaction {
+  transaction: Transaction;
+  // besides the initializer, who pays for and signs every transaction
+  signers: Signer[];
+}
+
+export interface InitPlan {
+  tempTokenAccount: PublicKey;
+  escrowAccount: PublicKey;
+  // send these in order, each after the one before it is confirmed
+  transactions: PlannedTransaction[];
+}
+
+interface Step {
+  instruction: TransactionInstruction;
+  signers: Signer[];
+  computeUnits: number;
+}
+
+const transactionOf = (
+  steps: Step[],
+  feePayer: PublicKey,
+  recentBlockhash: string
+) => {
+  const transaction = new Transaction({ feePayer, recentBlockhash });
+  steps.forEach(({ instruction }) => transaction.add(instruction));
+  return transaction;
+};
+
+// the wire size once signed: the signature count, the signatures and the message
+const transactionSize = (transaction: Transaction) => {
+  const message = transaction.compileMessage();
+  const signatures = message.header.numRequiredSignatures;
+  return (
+    (signatures < 0x80 ? 1 : 2) + signatures * 64 + message.serialize().length
+  );
+};
+
+const fits = (steps: Step[], feePayer: PublicKey, recentBlockhash: string) =>
+  steps.reduce((total, { computeUnits }) => total + computeUnits, 0) <=
+    MAX_TRANSACTION_COMPUTE_UNITS &&
+  transactionSize(transactionOf(steps, feePayer, recentBlockhash)) <=
+    PACKET_DATA_SIZE;
+
+/**
+ * Plans opening an escrow: creating and funding its temp token account, creating the escrow
+ * account and InitEscrow. All of it goes into one transaction when that fits the packet size
+ * and compute limits, so the offer opens atomically. Otherwise the steps are packed in order
+ * into as few transactions as fit, InitEscrow in the last; if a later one fails, the deposit
+ * waits in the temp token account, which the initializer still owns. The same initializer,
+ * offer, blockhash and new account keypairs always give the same transactions.
+ */
+export const planInitEscrow = async (
+  connection: Connection,
+  programId: PublicKey,
+  initializer: Keypair,
+  offer: InitOffer,
+  recentBlockhash: string,
+  tempTokenAccount = new Keypair(),
+  escrowAccount = new Keypair()
+): Promise<InitPlan> => {
+  const steps: Step[] = [
+    {
+      instruction: SystemProgram.createAccount({
+        programId: TOKEN_PROGRAM_ID,
+        space: AccountLayout.span,
+        lamports: await connection.getMinimumBalanceForRentExemption(
+          AccountLayout.span
+        ),
+        fromPubkey: initializer.publicKey,
+        newAccountPubkey: tempTokenAccount.publicKey,
+      }),
+      signers: [tempTokenAccount],
+      computeUnits: SETUP_COMPUTE_UNITS.createAccount,
+    },
+    {
+      instruction: Token.createInitAccountInstruction(
+        TOKEN_PROGRAM_ID,
+        offer.depositMint,
+        tempTokenAccount.publicKey,
+        initializer.publicKey
+      ),
+      signers: [],
+      computeUnits: SETUP_COMPUTE_UNITS.initAccount,
+    },
+    {
+      instruction: Token.createTransferInstruction(
+        TOKEN_PROGRAM_ID,
+        offer.sendingTokenAccount,
+        tempTokenAccount.publicKey,
+        initializer.publicKey,
+        [],
+        offer.depositAmount
+      ),
+      signers: [],
+      computeUnits: SETUP_COMPUTE_UNITS.transfer,
+    },
+    {
+      instruction: SystemProgram.createAccount({
+        space: ESCROW_ACCOUNT_DATA_LAYOUT.span,
+        lamports:
+          (await connection.getMinimumBalanceForRentExemption(
+            ESCROW_ACCOUNT_DATA_LAYOUT.span
+          )) + (offer.gasRebate ?? 0),
+        fromPubkey: initializer.publicKey,
+        newAccountPubkey: escrowAccount.publicKey,
+        programId,
+      }),
+      signers: [escrowAccount],
+      computeUnits: SETUP_COMPUTE_UNITS.createAccount,
+    },
+    {
+      instruction: await initEscrowInstruction(
+        programId,
+        initializer.publicKey,
+        tempTokenAccount.publicKey,
+        offer.receivingTokenAccount,
+        escrowAccount.publicKey,
+        offer.config,
+        offer.expectedAmount,
+        offer.payouts,
+        offer.roundingMode ?? ROUNDING_MODES.floorForTaker,
+        offer.auditLog,
+        offer.sendingTokenAccount,
+        offer.allowlistEntry,
+        offer.takerCommitmentHash,
+        offer.depositMint,
+        offer.unlockSlots,
+        undefined,
+        undefined,
+        offer.partialFill,
+        offer.takerGate,
+        offer.hashLock,
+        offer.gasRebate,
+        offer.priceBand,
+        offer.rateCounter,
+        offer.quoteConvention,
+        offer.fillCooldownSlots,
+        offer.designatedTaker
+      ),
+      signers: [],
+      computeUnits: COMPUTE_UNITS.initEscrow,
+    },
+  ];
+
+  const batches: Step[][] = [];
+  for (const step of steps) {
+    const batch = batches[batches.length - 1];
+    if (
+      batch !== undefined &&
+      fits([...batch, step], initializer.publicKey, recentBlockhash)
+    ) {
+      batch.push(step);
+    } else if (fits([step], initializer.publicKey, recentBlockhash)) {
+      batches.push([step]);
+    } else {
+      throw new Error(
+        "InitEscrow doesn't fit in a transaction, open the offer with fewer payouts"
+      );
+    }
+  }
+
+  return {
+    tempTokenAccount: tempTokenAccount.publicKey,
+    escrowAccount: escrowAccount.publicKey,
+    transactions: batches.map((batch) => ({
+      transaction: transactionOf(batch, initializer.publicKey, recentBlockhash),
+      signers: batch.flatMap(({ signers }) => signers),
+    })),
+  };
+};
+
+/** Sends a plan's transactions in order, signed by the initializer and their new accounts */
+export const sendInitPlan = async (
+  connection: Connection,
+  initializer: Keypair,
+  plan: InitPlan
+) => {
+  for (const { transaction, signers } of plan.transactions) {
+    transaction.sign(initializer, ...signers);
+    await connection.confirmTransaction(
+      await connection.sendRawTransaction(transaction.serialize(), {
+        skipPreflight: false,
+        preflightCommitment: "confirmed",
+      }),
+      "confirmed"
+    );
+  }
+};
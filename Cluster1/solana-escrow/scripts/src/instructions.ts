@@ -0,0 +1,2288 @@
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  NATIVE_MINT,
+  Token,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  AccountMeta,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SYSVAR_RENT_PUBKEY,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import BN = require("bn.js");
+import {
+  encodeEscrowTag,
+  getAllowlistEntryPda,
+  getBasketPda,
+  getAuditLogPda,
+  getConfigPda,
+  getCounterOfferPda,
+  getDeploymentPda,
+  getEscrowTagsPda,
+  getInsurancePoolPda,
+  getMutualDepositPda,
+  getPairStatsPda,
+  getPrecreatedEscrowPda,
+  getRateCounterPda,
+  getReceiptMintPda,
+  getSettlementPda,
+  getStatsPda,
+  getTakerAllowlistPda,
+  getVaultAuthorityPda,
+  getVaultPda,
+  QUOTE_CONVENTIONS,
+  ROUNDING_MODES,
+} from "./utils";
+
+/**
+ * Bits of InitEscrow's optional fields bitmask, see `InitEscrowFields` in the program's
+ * instruction.rs. The fields follow the bitmask in bit order.
+ */
+export const INIT_ESCROW_FIELDS = {
+  roundingMode: 1 << 0,
+  takerCommitment: 1 << 1,
+  unlockSlots: 1 << 2,
+  vaultDeposit: 1 << 3,
+  settlementProgram: 1 << 4,
+  partialFill: 1 << 5,
+  takerGate: 1 << 6,
+  hashLock: 1 << 7,
+  gasRebate: 1 << 8,
+  priceBand: 1 << 9,
+  quoteConvention: 1 << 10,
+  fillCooldownSlots: 1 << 11,
+  // no bytes, the bit says the refund token account is passed
+  refundTokenAccount: 1 << 12,
+  // no bytes, the bit says the deposited mint is passed
+  depositMint: 1 << 13,
+  designatedTaker: 1 << 14,
+};
+
+const amountData = (tag: number, amount: number) =>
+  Buffer.from(Uint8Array.of(tag, ...new BN(amount).toArray("le", 8)));
+
+// Exchange and ValidateExchange data. The salt is sent as zeros when only the deadline is
+// set, and the deadline as zero when only the fill amount is.
+const exchangeData = (
+  tag: number,
+  amount: number,
+  takerSalt?: Buffer,
+  validUntilSlot?: number,
+  fillAmount?: number,
+  maxPayment?: number
+) => {
+  if (maxPayment !== undefined) {
+    fillAmount ??= 0;
+  }
+  if (fillAmount !== undefined) {
+    validUntilSlot ??= 0;
+  }
+  return Buffer.concat([
+    amountData(tag, amount),
+    takerSalt ?? Buffer.alloc(validUntilSlot === undefined ? 0 : 32),
+    validUntilSlot === undefined
+      ? Buffer.alloc(0)
+      : Buffer.from(new BN(validUntilSlot).toArray("le", 8)),
+    fillAmount === undefined
+      ? Buffer.alloc(0)
+      : Buffer.from(new BN(fillAmount).toArray("le", 8)),
+    maxPayment === undefined
+      ? Buffer.alloc(0)
+      : Buffer.from(new BN(maxPayment).toArray("le", 8)),
+  ]);
+};
+
+// escrows under a config with an audit log must pass the log as their last account
+const auditLogKeys = (auditLog?: PublicKey) =>
+  auditLog === undefined
+    ? []
+    : [{ pubkey: auditLog, isSigner: false, isWritable: true }];
+
+// the receipt mint PDA owns the proceeds account, so the owner may be off the curve
+const associatedTokenAddress = (
+  owner: PublicKey,
+  mint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    tokenProgram,
+    mint,
+    owner,
+    true
+  );
+
+// Cancel must burn the receipt of an escrow that has one, Reap and CancelExpired only check who
+// holds it
+const receiptKeys = async (
+  programId: PublicKey,
+  holder: PublicKey,
+  escrowAccount: PublicKey,
+  receiptExpectedMint?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID,
+  burn = true
+) => {
+  if (receiptExpectedMint === undefined) {
+    return [];
+  }
+  const receiptMint = await getReceiptMintPda(programId, escrowAccount);
+  return [
+    { pubkey: receiptMint, isSigner: false, isWritable: true },
+    {
+      pubkey: await associatedTokenAddress(holder, receiptMint, tokenProgram),
+      isSigner: false,
+      isWritable: burn,
+    },
+    {
+      pubkey: await associatedTokenAddress(
+        receiptMint,
+        receiptExpectedMint,
+        tokenProgram
+      ),
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+};
+
+/**
+ * A basket asset's mint and the token account it goes to, the taker's on Exchange and the
+ * initializer's on Cancel, Reap, CancelExpired and ReclaimBasket. List them in the basket's
+ * order.
+ */
+export interface BasketAsset {
+  mint: PublicKey;
+  tokenAccount: PublicKey;
+}
+
+// the basket PDA, then each asset's vault, token account and mint; nothing without a basket
+const basketKeys = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey,
+  basket: BasketAsset[],
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  if (basket.length === 0) {
+    return [];
+  }
+  const basketPda = await getBasketPda(programId, escrowAccount);
+  const assetKeys = await Promise.all(
+    basket.map(async ({ mint, tokenAccount }) => [
+      {
+        pubkey: await associatedTokenAddress(basketPda, mint, tokenProgram),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: tokenAccount, isSigner: false, isWritable: true },
+      { pubkey: mint, isSigner: false, isWritable: false },
+    ])
+  );
+  return [
+    { pubkey: basketPda, isSigner: false, isWritable: true },
+    ...assetKeys.flat(),
+  ];
+};
+
+/** What Cancel needs to refund a mutual deposit's taker */
+export interface MutualDepositRefund {
+  // the token account the taker paid from
+  takerRefundAccount: PublicKey;
+  expectedMint: PublicKey;
+}
+
+const mutualDepositKeys = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey,
+  mutualDeposit?: MutualDepositRefund,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  if (mutualDeposit === undefined) {
+    return [];
+  }
+  const mutualDepositPda = await getMutualDepositPda(programId, escrowAccount);
+  return [
+    { pubkey: mutualDepositPda, isSigner: false, isWritable: true },
+    {
+      pubkey: await associatedTokenAddress(
+        mutualDepositPda,
+        mutualDeposit.expectedMint,
+        tokenProgram
+      ),
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: mutualDeposit.takerRefundAccount,
+      isSigner: false,
+      isWritable: true,
+    },
+    { pubkey: mutualDeposit.expectedMint, isSigner: false, isWritable: false },
+  ];
+};
+
+export interface Payout {
+  tokenAccount: PublicKey;
+  bps: number;
+}
+
+/**
+ * Deposits `amount` from `source` into the initializer's shared vault (see getVaultPda),
+ * which InitEscrow then takes as its temp token account
+ */
+export interface VaultDeposit {
+  source: PublicKey;
+  amount: number;
+}
+
+/**
+ * Who may fill an escrow: takers whose wallet holds at least `minLamports` (0 for no balance
+ * check), or who hold some of `badgeMint`. Exchange then needs their `takerBadgeAccount`.
+ */
+export interface TakerGate {
+  minLamports: number;
+  badgeMint?: PublicKey;
+}
+
+/**
+ * Makes an escrow a hash-timelock for a cross-chain swap: only `recipient` may take the
+ * deposit, with `redeemInstruction` and the preimage of `hash` (see `hashLockHash`), until the
+ * escrow expires and the initializer can cancel it.
+ */
+export interface HashLock {
+  hash: Buffer;
+  recipient: PublicKey;
+}
+
+/**
+ * Prices an escrow's fills at a Pyth price account's price of token X in token Y, moved by
+ * `offsetBps`. The escrow's expected amount stays the least a whole fill pays.
+ */
+export interface PriceBand {
+  priceAccount: PublicKey;
+  offsetBps: number;
+}
+
+/**
+ * The program an escrow's proceeds settle into, see `setSettlementProgramInstruction`, and
+ * the accounts it needs passed through by Exchange
+ */
+export interface Settlement {
+  program: PublicKey;
+  accounts: AccountMeta[];
+}
+
+/**
+ * The taker's token account for the config's fee mint, and the fee mint itself, which
+ * Exchange needs for its decimals
+ */
+export interface TakerFeeMint {
+  account: PublicKey;
+  mint: PublicKey;
+}
+
+/**
+ * The metadata account of an NFT deposit and its creators' token accounts for token Y, see
+ * `getNftRoyalties`
+ */
+export interface NftRoyalties {
+  metadata: PublicKey;
+  creatorTokenAccounts: PublicKey[];
+}
+
+/**
+ * The config's insurance pool and its token account for token Y, which an insured escrow's
+ * fills pay their premium into, see `getInsurance`
+ */
+export interface Insurance {
+  pool: PublicKey;
+  poolTokenAccount: PublicKey;
+}
+
+/**
+ * The accounts Exchange needs to pay an insured escrow's premium, for `exchangeInstruction`'s
+ * `insurance`. The operator creates the pool's associated token account for `expectedMint`
+ * when funding it.
+ */
+export const getInsurance = async (
+  programId: PublicKey,
+  config: PublicKey,
+  expectedMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const pool = await getInsurancePoolPda(programId, config);
+  return {
+    pool,
+    poolTokenAccount: await associatedTokenAddress(
+      pool,
+      expectedMint,
+      tokenProgram
+    ),
+  };
+};
+
+/**
+ * The staging account Exchange pays an escrow with a settlement hook into, passed as the
+ * initializer's receiving token account. Create it before the first fill.
+ */
+export const getSettlementStagingAccount = async (
+  programId: PublicKey,
+  expectedMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  associatedTokenAddress(
+    await getSettlementPda(programId),
+    expectedMint,
+    tokenProgram
+  );
+
+export const initEscrowInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  tempTokenAccount: PublicKey,
+  receivingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  config: PublicKey,
+  expectedAmount: number,
+  payouts: Payout[] = [],
+  roundingMode = ROUNDING_MODES.floorForTaker,
+  auditLog?: PublicKey,
+  // without it the deposit can only be refunded to the initializer's associated token account
+  refundTokenAccount?: PublicKey,
+  // required when the config gates InitEscrow, see getAllowlistEntryPda
+  allowlistEntry?: PublicKey,
+  // makes the offer private to one taker, see takerCommitment
+  takerCommitmentHash?: Buffer,
+  // the deposited mint, without it the escrow's depositMintRisk is MINT_RISK_FLAGS.unassessed
+  depositMint?: PublicKey,
+  // cancellation window within UNLOCK_SLOTS' bounds, UNLOCK_SLOTS.default if left out
+  unlockSlots?: number,
+  vaultDeposit?: VaultDeposit,
+  // must be the program the config whitelists, not available with payouts
+  settlementProgram?: PublicKey,
+  // lets takers fill less than the whole deposit, see exchangeInstruction's fillAmount
+  partialFill = false,
+  // a bot filter on who may fill, the offer is open to any taker if left out
+  takerGate?: TakerGate,
+  // only settles by redeemInstruction, not available with payouts, partial fills or a gate
+  hashLock?: HashLock,
+  // lamports paid to the taker on the fill, the escrow account must hold them on top of rent
+  gasRebate = 0,
+  // prices fills off a Pyth price, not with partial fills, a settlement hook or a hash lock
+  priceBand?: PriceBand,
+  // the config's getRateCounterPda, required if it limits inits per slot
+  rateCounter?: PublicKey,
+  // QUOTE_CONVENTIONS.interestAdjusted quotes an interest-bearing mint in UI amounts, not
+  // with a price band, partial fills or a mutual deposit
+  quoteConvention = QUOTE_CONVENTIONS.raw,
+  // slots a partially filled escrow can't be filled again for, only with partial fills
+  fillCooldownSlots = 0,
+  // the only signer exchangeInstruction may fill the offer for, open to any taker if left out
+  designatedTaker?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  // each optional field is sent under its INIT_ESCROW_FIELDS bit, in bit order
+  const fields: [number, Buffer | undefined][] = [
+    [
+      INIT_ESCROW_FIELDS.roundingMode,
+      roundingMode === ROUNDING_MODES.floorForTaker
+        ? undefined
+        : Buffer.from(Uint8Array.of(roundingMode)),
+    ],
+    [INIT_ESCROW_FIELDS.takerCommitment, takerCommitmentHash],
+    [
+      INIT_ESCROW_FIELDS.unlockSlots,
+      unlockSlots === undefined
+        ? undefined
+        : Buffer.from(new BN(unlockSlots).toArray("le", 8)),
+    ],
+    [
+      INIT_ESCROW_FIELDS.vaultDeposit,
+      vaultDeposit === undefined
+        ? undefined
+        : Buffer.from(new BN(vaultDeposit.amount).toArray("le", 8)),
+    ],
+    [INIT_ESCROW_FIELDS.settlementProgram, settlementProgram?.toBuffer()],
+    [INIT_ESCROW_FIELDS.partialFill, partialFill ? Buffer.alloc(0) : undefined],
+    [
+      INIT_ESCROW_FIELDS.takerGate,
+      takerGate === undefined
+        ? undefined
+        : Buffer.concat([
+            Buffer.from(new BN(takerGate.minLamports).toArray("le", 8)),
+            takerGate.badgeMint?.toBuffer() ?? Buffer.alloc(32),
+          ]),
+    ],
+    [
+      INIT_ESCROW_FIELDS.hashLock,
+      hashLock === undefined
+        ? undefined
+        : Buffer.concat([hashLock.hash, hashLock.recipient.toBuffer()]),
+    ],
+    [
+      INIT_ESCROW_FIELDS.gasRebate,
+      gasRebate === 0
+        ? undefined
+        : Buffer.from(new BN(gasRebate).toArray("le", 8)),
+    ],
+    [
+      INIT_ESCROW_FIELDS.priceBand,
+      priceBand === undefined
+        ? undefined
+        : Buffer.concat([
+            priceBand.priceAccount.toBuffer(),
+            Buffer.from(
+              new BN(priceBand.offsetBps).toTwos(16).toArray("le", 2)
+            ),
+          ]),
+    ],
+    [
+      INIT_ESCROW_FIELDS.quoteConvention,
+      quoteConvention === QUOTE_CONVENTIONS.raw
+        ? undefined
+        : Buffer.from(Uint8Array.of(quoteConvention)),
+    ],
+    [
+      INIT_ESCROW_FIELDS.fillCooldownSlots,
+      fillCooldownSlots === 0
+        ? undefined
+        : Buffer.from(new BN(fillCooldownSlots).toArray("le", 8)),
+    ],
+    [
+      INIT_ESCROW_FIELDS.refundTokenAccount,
+      refundTokenAccount === undefined ? undefined : Buffer.alloc(0),
+    ],
+    [
+      INIT_ESCROW_FIELDS.depositMint,
+      depositMint === undefined ? undefined : Buffer.alloc(0),
+    ],
+    [INIT_ESCROW_FIELDS.designatedTaker, designatedTaker?.toBuffer()],
+  ];
+  const present = fields.filter(([, bytes]) => bytes !== undefined);
+  const fieldBits = present.reduce((bits, [bit]) => bits | bit, 0);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: receivingTokenAccount, isSigner: false, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: config, isSigner: false, isWritable: false },
+      ...payouts.map(({ tokenAccount }) => ({
+        pubkey: tokenAccount,
+        isSigner: false,
+        isWritable: false,
+      })),
+      ...auditLogKeys(auditLog),
+      ...(allowlistEntry === undefined
+        ? []
+        : [{ pubkey: allowlistEntry, isSigner: false, isWritable: false }]),
+      ...(vaultDeposit === undefined
+        ? []
+        : [{ pubkey: vaultDeposit.source, isSigner: false, isWritable: true }]),
+      ...(rateCounter === undefined
+        ? []
+        : [{ pubkey: rateCounter, isSigner: false, isWritable: true }]),
+      ...(refundTokenAccount === undefined
+        ? []
+        : [
+            {
+              pubkey: refundTokenAccount,
+              isSigner: false,
+              isWritable: false,
+            },
+          ]),
+      ...(depositMint === undefined
+        ? []
+        : [{ pubkey: depositMint, isSigner: false, isWritable: false }]),
+    ],
+    data: Buffer.concat([
+      amountData(0, expectedAmount),
+      payouts.length === 0 && fieldBits === 0
+        ? Buffer.alloc(0)
+        : Buffer.from(
+            Uint8Array.of(
+              payouts.length,
+              ...payouts.flatMap(({ bps }) => new BN(bps).toArray("le", 2))
+            )
+          ),
+      fieldBits === 0
+        ? Buffer.alloc(0)
+        : Buffer.from(new BN(fieldBits).toArray("le", 2)),
+      ...present.map(([, bytes]) => bytes as Buffer),
+    ]),
+  });
+};
+
+/**
+ * Creates the initializer's shared vault for `mint`. Escrows opened with a `vaultDeposit`
+ * only pay for their own account's rent; the vault's is returned by whichever settlement
+ * empties it.
+ */
+export const initVaultInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  mint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const vault = await getVaultPda(programId, initializer, mint);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: vault, isSigner: false, isWritable: true },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, vault),
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(20)),
+  });
+};
+
+/**
+ * Allocates the initializer's escrow accounts `firstIndex..firstIndex + count`, at most 16
+ * (see getPrecreatedEscrowPda). Opening an offer in one then takes only
+ * `initEscrowInstruction`, without creating the account or signing with its keypair.
+ */
+export const precreateEscrowsInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  firstIndex: number,
+  count: number
+) => {
+  const escrowAccounts = await Promise.all(
+    Array.from({ length: count }, (_, i) =>
+      getPrecreatedEscrowPda(programId, initializer, firstIndex + i)
+    )
+  );
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      ...escrowAccounts.map((pubkey) => ({
+        pubkey,
+        isSigner: false,
+        isWritable: true,
+      })),
+    ],
+    data: Buffer.from(
+      Uint8Array.of(24, ...new BN(firstIndex).toArray("le", 8), count)
+    ),
+  });
+};
+
+export const exchangeInstruction = async (
+  programId: PublicKey,
+  taker: PublicKey,
+  takerSendingTokenAccount: PublicKey,
+  takerReceivingTokenAccount: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  initializerReceivingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  config: PublicKey,
+  feeTokenAccount: PublicKey,
+  // the escrow's token X and token Y mints, whose decimals every transfer is checked against
+  depositMint: PublicKey,
+  expectedMint: PublicKey,
+  takerExpectedAmount: number,
+  payoutAccounts: PublicKey[] = [],
+  auditLog?: PublicKey,
+  // required if the config charges fees in a fee mint
+  takerFeeMint?: TakerFeeMint,
+  // reveals the taker as the counterparty of a private offer
+  takerSalt?: Buffer,
+  // last slot the fill may land in, so a stale retry can't fill at an old price
+  validUntilSlot?: number,
+  // token X to take out of the deposit, all of it if left out. Less needs an escrow opened
+  // with partialFill, and takerExpectedAmount stays the whole deposit left.
+  fillAmount?: number,
+  // most token Y the taker will pay for the fill, fails with PaymentAboveMax above it
+  maxPayment?: number,
+  // the pair's getPairStatsPda, required if the config has a volume cap
+  pairStats?: PublicKey,
+  // the escrow's getTakerAllowlistPda, required if it restricts its takers
+  takerAllowlist?: PublicKey,
+  // the taker's token account for the badge mint, required if the escrow's takerGate has one
+  takerBadgeAccount?: PublicKey,
+  // required for an escrow with a settlement hook, whose initializerReceivingTokenAccount
+  // is then the getSettlementStagingAccount
+  settlement?: Settlement,
+  // required if token X is an NFT, whose creators are paid their royalties out of the payment
+  nftRoyalties?: NftRoyalties,
+  // required if the escrow is insured, whose fills pay the pool's premium out of the payment
+  insurance?: Insurance,
+  // required if the escrow has a basket, which goes to the taker along with the deposit
+  basket: BasketAsset[] = [],
+  // the escrow's Pyth price account, required if it's oracle-priced
+  priceAccount?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      // writable to take any SOL unwrapped by the fill
+      { pubkey: taker, isSigner: true, isWritable: true },
+      { pubkey: takerSendingTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: takerReceivingTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerReceivingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: feeTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+      ...(priceAccount === undefined
+        ? []
+        : [{ pubkey: priceAccount, isSigner: false, isWritable: false }]),
+      ...payoutAccounts.map((pubkey) => ({
+        pubkey,
+        isSigner: false,
+        isWritable: true,
+      })),
+      ...auditLogKeys(auditLog),
+      ...(takerFeeMint === undefined
+        ? []
+        : [
+            {
+              pubkey: takerFeeMint.account,
+              isSigner: false,
+              isWritable: true,
+            },
+            { pubkey: takerFeeMint.mint, isSigner: false, isWritable: false },
+          ]),
+      ...(pairStats === undefined
+        ? []
+        : [{ pubkey: pairStats, isSigner: false, isWritable: true }]),
+      ...(takerAllowlist === undefined
+        ? []
+        : [{ pubkey: takerAllowlist, isSigner: false, isWritable: false }]),
+      ...(takerBadgeAccount === undefined
+        ? []
+        : [{ pubkey: takerBadgeAccount, isSigner: false, isWritable: false }]),
+      ...(nftRoyalties === undefined
+        ? []
+        : [
+            {
+              pubkey: nftRoyalties.metadata,
+              isSigner: false,
+              isWritable: false,
+            },
+            ...nftRoyalties.creatorTokenAccounts.map((pubkey) => ({
+              pubkey,
+              isSigner: false,
+              isWritable: true,
+            })),
+          ]),
+      ...(insurance === undefined
+        ? []
+        : [
+            { pubkey: insurance.pool, isSigner: false, isWritable: false },
+            {
+              pubkey: insurance.poolTokenAccount,
+              isSigner: false,
+              isWritable: true,
+            },
+          ]),
+      ...(await basketKeys(programId, escrowAccount, basket, tokenProgram)),
+      ...(settlement === undefined
+        ? []
+        : [
+            {
+              pubkey: settlement.program,
+              isSigner: false,
+              isWritable: false,
+            },
+            {
+              pubkey: await getSettlementPda(programId),
+              isSigner: false,
+              isWritable: false,
+            },
+            ...settlement.accounts,
+          ]),
+    ],
+    data: exchangeData(
+      1,
+      takerExpectedAmount,
+      takerSalt,
+      validUntilSlot,
+      fillAmount,
+      maxPayment
+    ),
+  });
+
+/**
+ * Read-only dry run of `exchangeInstruction` for simulating a fill server-side. It fails
+ * the same way the exchange would, and writes an `ExchangeQuote` to return_data otherwise.
+ */
+export const validateExchangeInstruction = async (
+  programId: PublicKey,
+  taker: PublicKey,
+  takerSendingTokenAccount: PublicKey,
+  takerReceivingTokenAccount: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  initializerReceivingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  config: PublicKey,
+  feeTokenAccount: PublicKey,
+  depositMint: PublicKey,
+  expectedMint: PublicKey,
+  takerExpectedAmount: number,
+  payoutAccounts: PublicKey[] = [],
+  takerFeeMint?: TakerFeeMint,
+  takerSalt?: Buffer,
+  validUntilSlot?: number,
+  fillAmount?: number,
+  maxPayment?: number,
+  pairStats?: PublicKey,
+  takerAllowlist?: PublicKey,
+  takerBadgeAccount?: PublicKey,
+  // the settlement program is checked but not invoked, so it needs none of its accounts
+  settlementProgram?: PublicKey,
+  nftRoyalties?: NftRoyalties,
+  insurance?: Insurance,
+  basket: BasketAsset[] = [],
+  priceAccount?: PublicKey
+) => {
+  const exchangeIx = await exchangeInstruction(
+    programId,
+    taker,
+    takerSendingTokenAccount,
+    takerReceivingTokenAccount,
+    tempTokenAccount,
+    initializer,
+    initializerReceivingTokenAccount,
+    escrowAccount,
+    config,
+    feeTokenAccount,
+    depositMint,
+    expectedMint,
+    takerExpectedAmount,
+    payoutAccounts,
+    undefined,
+    takerFeeMint,
+    undefined,
+    undefined,
+    undefined,
+    undefined,
+    pairStats,
+    takerAllowlist,
+    takerBadgeAccount,
+    settlementProgram && { program: settlementProgram, accounts: [] },
+    nftRoyalties,
+    insurance,
+    basket,
+    priceAccount
+  );
+  return new TransactionInstruction({
+    programId,
+    keys: exchangeIx.keys.map((key) => ({ ...key, isWritable: false })),
+    data: exchangeData(
+      10,
+      takerExpectedAmount,
+      takerSalt,
+      validUntilSlot,
+      fillAmount,
+      maxPayment
+    ),
+  });
+};
+
+/**
+ * Extends the escrow's cancellation window to `unlockSlots` from now. Fails with
+ * `TimeConstraintWasNotSatisfied` once the window has closed, and with `UnlockSlotsOutOfBounds`
+ * if it would shrink.
+ */
+export const resetTimeLockInstruction = (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  unlockSlots: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+    ],
+    data: amountData(2, unlockSlots),
+  });
+
+export const cancelInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializerSendingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  // token X's mint, whose decimals the refund is checked against
+  depositMint: PublicKey,
+  auditLog?: PublicKey,
+  receiptExpectedMint?: PublicKey,
+  // whoever holds the receipt signs and burns it in the initializer's place, and is refunded to
+  // their own associated token account
+  receiptHolder = initializer,
+  // required if the escrow has a basket, refunded to the signer along with the deposit
+  basket: BasketAsset[] = [],
+  // required if the escrow is a mutual deposit, whose taker is refunded any deposit of theirs
+  mutualDeposit?: MutualDepositRefund,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: receiptHolder, isSigner: true, isWritable: false },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerSendingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+      ...(await receiptKeys(
+        programId,
+        receiptHolder,
+        escrowAccount,
+        receiptExpectedMint,
+        tokenProgram
+      )),
+      ...(await basketKeys(programId, escrowAccount, basket, tokenProgram)),
+      ...(await mutualDepositKeys(
+        programId,
+        escrowAccount,
+        mutualDeposit,
+        tokenProgram
+      )),
+    ],
+    data: Buffer.from(Uint8Array.of(3)),
+  });
+
+// anyone may reap an escrow past its config's max lifetime; the deposit goes back to the initializer
+export const reapInstruction = async (
+  programId: PublicKey,
+  reaper: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  initializerSendingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  // token X's mint, whose decimals the refund is checked against
+  depositMint: PublicKey,
+  auditLog?: PublicKey,
+  // required if the escrow has a receipt, whose holder is refunded instead of the initializer
+  receiptExpectedMint?: PublicKey,
+  receiptHolder = initializer,
+  // required if the escrow has a basket, refunded along with the deposit
+  basket: BasketAsset[] = [],
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: reaper, isSigner: true, isWritable: false },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerSendingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+      ...(await receiptKeys(
+        programId,
+        receiptHolder,
+        escrowAccount,
+        receiptExpectedMint,
+        tokenProgram,
+        false
+      )),
+      ...(await basketKeys(programId, escrowAccount, basket, tokenProgram)),
+    ],
+    data: Buffer.from(Uint8Array.of(13)),
+  });
+
+// anyone may cancel an offer past its expiry, earning a small reward out of the escrow's rent
+export const cancelExpiredInstruction = async (
+  programId: PublicKey,
+  cranker: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  initializerSendingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  // token X's mint, whose decimals the refund is checked against
+  depositMint: PublicKey,
+  auditLog?: PublicKey,
+  // required if the escrow has a receipt, whose holder is refunded instead of the initializer
+  receiptExpectedMint?: PublicKey,
+  receiptHolder = initializer,
+  // required if the escrow has a basket, refunded along with the deposit
+  basket: BasketAsset[] = [],
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: cranker, isSigner: true, isWritable: true },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerSendingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+      ...(await receiptKeys(
+        programId,
+        receiptHolder,
+        escrowAccount,
+        receiptExpectedMint,
+        tokenProgram,
+        false
+      )),
+      ...(await basketKeys(programId, escrowAccount, basket, tokenProgram)),
+    ],
+    data: Buffer.from(Uint8Array.of(25)),
+  });
+
+// `closedMint` is whichever of the escrow's two mints no longer exists, `depositMint` is token
+// X's mint, whose decimals any refund is checked against
+export const recoverClosedMintInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializerSendingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  closedMint: PublicKey,
+  depositMint: PublicKey,
+  auditLog?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerSendingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: closedMint, isSigner: false, isWritable: false },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+    ],
+    data: Buffer.from(Uint8Array.of(16)),
+  });
+
+// recovery for escrows left inconsistent by a program bug, signed by the initializer and the config admin
+export const forceCloseInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializerSendingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  admin: PublicKey,
+  config: PublicKey,
+  // token X's mint, whose decimals the refund is checked against
+  depositMint: PublicKey,
+  auditLog?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerSendingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: admin, isSigner: true, isWritable: false },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+    ],
+    data: Buffer.from(Uint8Array.of(26)),
+  });
+
+export const initStatsInstruction = async (
+  programId: PublicKey,
+  payer: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(4)),
+  });
+
+/**
+ * Lower fee charged once an offer has rested on-chain for `minAgeSlots`
+ */
+export interface FeeTier {
+  minAgeSlots: number;
+  feeBps: number;
+}
+
+const configData = (
+  tag: number,
+  feeBps: number,
+  minExpectedAmount: number,
+  maxExpectedAmount: number,
+  feeTiers: FeeTier[],
+  maxLifetimeSlots: number
+) =>
+  Buffer.from(
+    Uint8Array.of(
+      tag,
+      ...new BN(feeBps).toArray("le", 2),
+      ...new BN(minExpectedAmount).toArray("le", 8),
+      ...new BN(maxExpectedAmount).toArray("le", 8),
+      ...(feeTiers.length === 0 && maxLifetimeSlots === 0
+        ? []
+        : [
+            feeTiers.length,
+            ...feeTiers.flatMap(({ minAgeSlots, feeBps }) => [
+              ...new BN(minAgeSlots).toArray("le", 8),
+              ...new BN(feeBps).toArray("le", 2),
+            ]),
+          ]),
+      ...(maxLifetimeSlots === 0
+        ? []
+        : new BN(maxLifetimeSlots).toArray("le", 8))
+    )
+  );
+
+export const initConfigInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  feeRecipient: PublicKey,
+  feeBps: number,
+  minExpectedAmount: number,
+  maxExpectedAmount: number,
+  feeTiers: FeeTier[] = [],
+  maxLifetimeSlots = 0
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: true },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: feeRecipient, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: configData(
+      5,
+      feeBps,
+      minExpectedAmount,
+      maxExpectedAmount,
+      feeTiers,
+      maxLifetimeSlots
+    ),
+  });
+
+/**
+ * Charges the config's fees in `feeMint` at `feeMintRate` fee mint units per `FEE_RATE_SCALE`
+ * units of token Y, or back in token Y when `feeMint` is left out
+ */
+export const setFeeMintInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  feeMintRate: number,
+  feeRateOracle: PublicKey = PublicKey.default,
+  feeMint?: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+      ...(feeMint === undefined
+        ? []
+        : [{ pubkey: feeMint, isSigner: false, isWritable: false }]),
+    ],
+    data: Buffer.concat([
+      amountData(14, feeMintRate),
+      feeRateOracle.toBuffer(),
+    ]),
+  });
+
+/**
+ * Whitelists the program escrows under the admin's config may settle their proceeds into, e.g.
+ * a stake pool or LP adapter. Leaving it out stops new escrows from opting in, and fills of
+ * existing ones fail with `SettlementNotAllowed` until one is whitelisted again.
+ */
+export const setSettlementProgramInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  settlementProgram?: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+      ...(settlementProgram === undefined
+        ? []
+        : [{ pubkey: settlementProgram, isSigner: false, isWritable: false }]),
+    ],
+    data: Buffer.from(Uint8Array.of(21)),
+  });
+
+/**
+ * Caps the token X volume fills of any one mint pair under the admin's config may release
+ * per window of `volumeWindowSlots`. Fills then pass the pair's `getPairStatsPda` and fail
+ * with `VolumeCapExceeded` until the window rolls over. A `maxWindowVolume` of 0 lifts the cap.
+ */
+export const setVolumeCapInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  volumeWindowSlots: number,
+  maxWindowVolume: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.from(
+      Uint8Array.of(
+        22,
+        ...new BN(volumeWindowSlots).toArray("le", 8),
+        ...new BN(maxWindowVolume).toArray("le", 8)
+      )
+    ),
+  });
+
+/**
+ * Halts or resumes InitEscrow and Exchange under the admin's config. Cancel, Reap and the
+ * other ways out keep working while paused.
+ */
+export const setPausedInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  paused: boolean
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.from(Uint8Array.of(27, paused ? 1 : 0)),
+  });
+
+/**
+ * Narrows the cancellation windows (`unlockSlots`) InitEscrow accepts under the admin's
+ * config. 0 for either bound falls back to the program's own limit.
+ */
+export const setUnlockBoundsInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  minUnlockSlots: number,
+  maxUnlockSlots: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.from(
+      Uint8Array.of(
+        28,
+        ...new BN(minUnlockSlots).toArray("le", 8),
+        ...new BN(maxUnlockSlots).toArray("le", 8)
+      )
+    ),
+  });
+
+/**
+ * Lets `taker` fill the initializer's escrow. The first taker added restricts the escrow to
+ * its allowlist for good, and the initializer pays the allowlist's rent.
+ */
+export const addAllowedTakerInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  taker: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await getTakerAllowlistPda(programId, escrowAccount),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([Buffer.from(Uint8Array.of(29)), taker.toBuffer()]),
+  });
+
+/**
+ * Takes `taker` off the escrow's allowlist. Once the escrow is closed, this closes the
+ * allowlist instead and returns its rent to the initializer.
+ */
+export const removeAllowedTakerInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  taker: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      {
+        pubkey: await getTakerAllowlistPda(programId, escrowAccount),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.concat([Buffer.from(Uint8Array.of(30)), taker.toBuffer()]),
+  });
+
+/**
+ * Proposes `expectedAmount` of token Y for what's left of the escrow's deposit, replacing the
+ * taker's earlier counter-offer if any. 0 withdraws it and returns its rent.
+ */
+export const makeCounterOfferInstruction = async (
+  programId: PublicKey,
+  taker: PublicKey,
+  escrowAccount: PublicKey,
+  expectedAmount: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: taker, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      {
+        pubkey: await getCounterOfferPda(programId, escrowAccount, taker),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: amountData(31, expectedAmount),
+  });
+
+/**
+ * Reprices the initializer's escrow at `taker`'s counter-offer, returning its rent to the taker
+ */
+export const acceptCounterOfferInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  taker: PublicKey,
+  config: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await getCounterOfferPda(programId, escrowAccount, taker),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: taker, isSigner: false, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(32)),
+  });
+
+/**
+ * Reprices the initializer's escrow at `expectedAmount` of token Y, as long as nobody has
+ * filled any of it
+ */
+export const updateExpectedAmountInstruction = (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  config: PublicKey,
+  expectedAmount: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+    ],
+    data: amountData(33, expectedAmount),
+  });
+
+/**
+ * Tags the initializer's escrow with `key=value` pairs. Only works in the same transaction as
+ * its InitEscrow. No tags close the tags of an escrow that has since been settled.
+ */
+export const setEscrowTagsInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  tags: [string, string][]
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      {
+        pubkey: await getEscrowTagsPda(programId, escrowAccount),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.concat([
+      Buffer.from([34, tags.length]),
+      ...tags.map(([key, value]) => encodeEscrowTag(key, value)),
+    ]),
+  });
+
+/**
+ * Creates a wSOL token account for `owner` holding `lamports` of their SOL, to pass to
+ * `initEscrowInstruction` as the temp token account or to `exchangeInstruction` as the taker's
+ * sending account. `wsolAccount` is a new keypair, which signs along with the owner.
+ */
+export const wrapSolInstruction = (
+  programId: PublicKey,
+  owner: PublicKey,
+  wsolAccount: PublicKey,
+  lamports: number,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: owner, isSigner: true, isWritable: true },
+      { pubkey: wsolAccount, isSigner: true, isWritable: true },
+      { pubkey: NATIVE_MINT, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: amountData(35, lamports),
+  });
+
+/**
+ * Sets up the admin's config's insurance pool, or changes its premium, which insured fills
+ * pay out of the initializer's proceeds: `premiumBps` of the payment, at most 100. Fund the
+ * pool by transferring tokens to its associated token accounts.
+ */
+export const initInsurancePoolInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  premiumBps: number
+) => {
+  const config = await getConfigPda(programId, admin);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+      {
+        pubkey: await getInsurancePoolPda(programId, config),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(
+      Uint8Array.of(36, ...new BN(premiumBps).toArray("le", 2))
+    ),
+  });
+};
+
+/**
+ * Opts the initializer's escrow into its config's insurance pool for good. Its fills then
+ * need `getInsurance`'s accounts.
+ */
+export const insureEscrowInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  config: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await getInsurancePoolPda(programId, config),
+        isSigner: false,
+        isWritable: false,
+      },
+    ],
+    data: Buffer.from(Uint8Array.of(37)),
+  });
+
+/**
+ * Pays a claim of `amount` out of the admin's config's insurance pool, from the pool's
+ * associated token account for `mint` into `claimantTokenAccount`
+ */
+export const payClaimInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  claimantTokenAccount: PublicKey,
+  mint: PublicKey,
+  amount: number,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const config = await getConfigPda(programId, admin);
+  const pool = await getInsurancePoolPda(programId, config);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: pool, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(pool, mint, tokenProgram),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: claimantTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+    ],
+    data: amountData(38, amount),
+  });
+};
+
+/**
+ * Moves `amount` of `mint` from `sourceTokenAccount` into the escrow's basket, creating the
+ * basket and the asset's vault as needed. The escrow then fills only whole.
+ */
+export const addBasketAssetInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  sourceTokenAccount: PublicKey,
+  mint: PublicKey,
+  amount: number,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const basketPda = await getBasketPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: basketPda, isSigner: false, isWritable: true },
+      { pubkey: sourceTokenAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(basketPda, mint, tokenProgram),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: amountData(39, amount),
+  });
+};
+
+/**
+ * Takes back the basket of an escrow that closed without paying it out, by Reap,
+ * CancelExpired, ForceClose or RecoverClosedMint
+ */
+export const reclaimBasketInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  basket: BasketAsset[],
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      {
+        pubkey: await getBasketPda(programId, escrowAccount),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      // the basket PDA comes first, before the token program
+      ...(await basketKeys(
+        programId,
+        escrowAccount,
+        basket,
+        tokenProgram
+      )).slice(1),
+    ],
+    data: Buffer.from(Uint8Array.of(40)),
+  });
+
+/** One escrow of a `multiExchangeInstruction` ring */
+export interface RingLeg {
+  escrowAccount: PublicKey;
+  vault: PublicKey;
+  initializer: PublicKey;
+  // the initializer's token account for their expected mint, receiving the next leg's deposit
+  initializerReceivingTokenAccount: PublicKey;
+  config: PublicKey;
+  // the config fee recipient's token account for the escrow's expected mint
+  feeTokenAccount: PublicKey;
+  depositMint: PublicKey;
+}
+
+/**
+ * Settles three escrows whose mints chain into a ring in one go: each leg's initializer
+ * receives the next leg's whole deposit, less their operator fee, and all three close. Fails
+ * with RingMismatch unless every deposit covers what the leg before it expects. Anyone may
+ * send it.
+ */
+export const multiExchangeInstruction = async (
+  programId: PublicKey,
+  matcher: PublicKey,
+  legs: [RingLeg, RingLeg, RingLeg],
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: matcher, isSigner: true, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      ...(
+        await Promise.all(
+          legs.map(async (leg) => [
+            { pubkey: leg.escrowAccount, isSigner: false, isWritable: true },
+            { pubkey: leg.vault, isSigner: false, isWritable: true },
+            { pubkey: leg.initializer, isSigner: false, isWritable: true },
+            {
+              pubkey: leg.initializerReceivingTokenAccount,
+              isSigner: false,
+              isWritable: true,
+            },
+            {
+              pubkey: await getVaultAuthorityPda(programId, leg.vault),
+              isSigner: false,
+              isWritable: false,
+            },
+            { pubkey: leg.config, isSigner: false, isWritable: false },
+            { pubkey: leg.feeTokenAccount, isSigner: false, isWritable: true },
+            { pubkey: leg.depositMint, isSigner: false, isWritable: false },
+          ])
+        )
+      ).flat(),
+    ],
+    data: Buffer.from(Uint8Array.of(41)),
+  });
+
+/**
+ * Turns an open escrow into a mutual deposit for `taker`, who deposits their payment with
+ * `depositTakerInstruction` before `settleInstruction` swaps the two. The escrow then only
+ * fills by Settle and only closes by Cancel.
+ */
+export const depositInitializerInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  taker: PublicKey,
+  expectedMint: PublicKey,
+  depositMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const mutualDepositPda = await getMutualDepositPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: mutualDepositPda, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(
+          mutualDepositPda,
+          expectedMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([Buffer.from(Uint8Array.of(42)), taker.toBuffer()]),
+  });
+};
+
+/**
+ * The mutual deposit's taker pays the escrow's expected amount into the taker vault, naming
+ * `takerReceivingTokenAccount` for the deposit at Settle. Cancel refunds the payment to
+ * `takerSendingTokenAccount`.
+ */
+export const depositTakerInstruction = async (
+  programId: PublicKey,
+  taker: PublicKey,
+  escrowAccount: PublicKey,
+  takerSendingTokenAccount: PublicKey,
+  takerReceivingTokenAccount: PublicKey,
+  expectedMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const mutualDepositPda = await getMutualDepositPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: taker, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      { pubkey: mutualDepositPda, isSigner: false, isWritable: true },
+      { pubkey: takerSendingTokenAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(
+          mutualDepositPda,
+          expectedMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: takerReceivingTokenAccount,
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(43)),
+  });
+};
+
+/**
+ * Swaps a mutual deposit once the taker deposited: the escrow's deposit goes to the account
+ * the taker named, their payment to the initializer less the operator fee, and everything
+ * closes. Anyone may send it, even after the escrow expired.
+ */
+export const settleInstruction = async (
+  programId: PublicKey,
+  settler: PublicKey,
+  escrowAccount: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  initializerReceivingTokenAccount: PublicKey,
+  takerReceivingTokenAccount: PublicKey,
+  config: PublicKey,
+  feeTokenAccount: PublicKey,
+  depositMint: PublicKey,
+  expectedMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const mutualDepositPda = await getMutualDepositPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: settler, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: mutualDepositPda, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(
+          mutualDepositPda,
+          expectedMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      {
+        pubkey: initializerReceivingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: takerReceivingTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: feeTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(44)),
+  });
+};
+
+/**
+ * Redeems a hash-locked escrow: its recipient reveals `preimage` and takes the whole deposit
+ * into `recipientReceivingTokenAccount`, and the escrow closes. Only before the escrow expires.
+ */
+export const redeemInstruction = async (
+  programId: PublicKey,
+  recipient: PublicKey,
+  recipientReceivingTokenAccount: PublicKey,
+  escrowAccount: PublicKey,
+  tempTokenAccount: PublicKey,
+  initializer: PublicKey,
+  config: PublicKey,
+  depositMint: PublicKey,
+  preimage: Buffer,
+  auditLog?: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: recipient, isSigner: true, isWritable: false },
+      {
+        pubkey: recipientReceivingTokenAccount,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: tempTokenAccount, isSigner: false, isWritable: true },
+      {
+        pubkey: await getVaultAuthorityPda(programId, tempTokenAccount),
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: initializer, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: await getStatsPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      ...auditLogKeys(auditLog),
+    ],
+    data: Buffer.concat([Buffer.from(Uint8Array.of(45)), preimage]),
+  });
+
+/**
+ * Turns an Exchange of the whole deposit from `exchangeInstruction` into an ExchangeWithPermit
+ * at the initializer's permitted `payment`. The transaction must carry the initializer's
+ * ed25519 signature over `permitMessage` in the instruction right before it, or a secp256k1
+ * instruction with the escrow's permit Ethereum signer's signature over `ethPermitMessage`.
+ */
+export const exchangeWithPermitInstruction = (
+  exchangeIx: TransactionInstruction,
+  payment: number,
+  validUntilSlot: number
+) =>
+  new TransactionInstruction({
+    programId: exchangeIx.programId,
+    keys: [
+      ...exchangeIx.keys,
+      { pubkey: SYSVAR_INSTRUCTIONS_PUBKEY, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([
+      Buffer.from(Uint8Array.of(46)),
+      exchangeIx.data.subarray(1, 9),
+      Buffer.from(new BN(payment).toArray("le", 8)),
+      Buffer.from(new BN(validUntilSlot).toArray("le", 8)),
+    ]),
+  });
+
+/**
+ * Creates the pair stats a capped config's fills of `depositMint` for `expectedMint` need.
+ * Anyone may pay for it.
+ */
+export const initPairStatsInstruction = async (
+  programId: PublicKey,
+  payer: PublicKey,
+  config: PublicKey,
+  depositMint: PublicKey,
+  expectedMint: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+      {
+        pubkey: await getPairStatsPda(
+          programId,
+          config,
+          depositMint,
+          expectedMint
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: depositMint, isSigner: false, isWritable: false },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(23)),
+  });
+
+/**
+ * Limits how many escrows InitEscrow may open under the admin's config per slot. Inits then
+ * pass the config's `getRateCounterPda` and fail with `RateLimited` once a slot has had its
+ * share. A `maxInitsPerSlot` of 0 lifts the limit.
+ */
+export const setInitRateLimitInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  maxInitsPerSlot: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.from(
+      Uint8Array.of(47, ...new BN(maxInitsPerSlot).toArray("le", 4))
+    ),
+  });
+
+/**
+ * Lets the 20-byte Ethereum address `ethAddress` sign the escrow's permits, see
+ * `ethPermitMessage`. All zero removes it.
+ */
+export const setPermitEthSignerInstruction = (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  ethAddress: Buffer
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: false },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+    ],
+    data: Buffer.concat([Buffer.from(Uint8Array.of(49)), ethAddress]),
+  });
+
+/** Creates the rate counter inits under a rate-limited config need. Anyone may pay for it. */
+export const initRateCounterInstruction = async (
+  programId: PublicKey,
+  payer: PublicKey,
+  config: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+      {
+        pubkey: await getRateCounterPda(programId, config),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(48)),
+  });
+
+export const updateFeeRateInstruction = (
+  programId: PublicKey,
+  updater: PublicKey,
+  config: PublicKey,
+  feeMintRate: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: updater, isSigner: true, isWritable: false },
+      { pubkey: config, isSigner: false, isWritable: true },
+    ],
+    data: amountData(15, feeMintRate),
+  });
+
+/**
+ * Restricts the instructions behind `gatedRoles` (a mask of `ALLOWLIST_ROLES`) to signers
+ * on the admin config's allowlist, 0 to open them up again
+ */
+export const setGatedRolesInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  gatedRoles: number
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: false },
+      {
+        pubkey: await getConfigPda(programId, admin),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    data: Buffer.from(Uint8Array.of(17, gatedRoles)),
+  });
+
+// sets `member`'s `ALLOWLIST_ROLES` under the admin's config, 0 revokes them all
+export const setAllowlistEntryInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey,
+  member: PublicKey,
+  roles: number
+) => {
+  const config = await getConfigPda(programId, admin);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: false },
+      { pubkey: member, isSigner: false, isWritable: false },
+      {
+        pubkey: await getAllowlistEntryPda(programId, config, member),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(18, roles)),
+  });
+};
+
+export const initAuditLogInstruction = async (
+  programId: PublicKey,
+  admin: PublicKey
+) => {
+  const config = await getConfigPda(programId, admin);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: admin, isSigner: true, isWritable: true },
+      { pubkey: config, isSigner: false, isWritable: true },
+      {
+        pubkey: await getAuditLogPda(programId, config),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(7)),
+  });
+};
+
+export const buildInfoInstruction = (programId: PublicKey) =>
+  new TransactionInstruction({
+    programId,
+    keys: [],
+    data: Buffer.from(Uint8Array.of(19)),
+  });
+
+export const timeStatusInstruction = (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [{ pubkey: escrowAccount, isSigner: false, isWritable: false }],
+    data: Buffer.from(Uint8Array.of(8)),
+  });
+
+const VIEW_TAGS = { GetEscrow: 200, GetConfig: 201, GetPairStats: 202 };
+
+/**
+ * Read-only instruction writing `account`'s state to return_data in its account layout, e.g.
+ * `ESCROW_ACCOUNT_DATA_LAYOUT` for GetEscrow. Meant for simulation and for programs reading
+ * escrow state through CPI.
+ */
+export const viewInstruction = (
+  programId: PublicKey,
+  view: keyof typeof VIEW_TAGS,
+  account: PublicKey
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [{ pubkey: account, isSigner: false, isWritable: false }],
+    data: Buffer.from(Uint8Array.of(VIEW_TAGS[view])),
+  });
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+export const updateDeploymentInstruction = async (
+  programId: PublicKey,
+  upgradeAuthority: PublicKey,
+  version: { major: number; minor: number; patch: number },
+  migrationNotesHash: Buffer
+) =>
+  new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: upgradeAuthority, isSigner: true, isWritable: true },
+      {
+        pubkey: await getDeploymentPda(programId),
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: (
+          await PublicKey.findProgramAddress(
+            [programId.toBuffer()],
+            BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+          )
+        )[0],
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([
+      Buffer.from(
+        Uint8Array.of(
+          9,
+          ...new BN(version.major).toArray("le", 2),
+          ...new BN(version.minor).toArray("le", 2),
+          ...new BN(version.patch).toArray("le", 2)
+        )
+      ),
+      migrationNotesHash,
+    ]),
+  });
+
+export const mintReceiptInstruction = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  escrowAccount: PublicKey,
+  expectedMint: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const receiptMint = await getReceiptMintPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: initializer, isSigner: true, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: true },
+      { pubkey: receiptMint, isSigner: false, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(
+          initializer,
+          receiptMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: await associatedTokenAddress(
+          receiptMint,
+          expectedMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: expectedMint, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+      {
+        pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(11)),
+  });
+};
+
+export const claimProceedsInstruction = async (
+  programId: PublicKey,
+  holder: PublicKey,
+  escrowAccount: PublicKey,
+  expectedMint: PublicKey,
+  holderTokenAccount: PublicKey,
+  tokenProgram = TOKEN_PROGRAM_ID
+) => {
+  const receiptMint = await getReceiptMintPda(programId, escrowAccount);
+  return new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: holder, isSigner: true, isWritable: true },
+      {
+        pubkey: await associatedTokenAddress(holder, receiptMint, tokenProgram),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: receiptMint, isSigner: false, isWritable: true },
+      { pubkey: escrowAccount, isSigner: false, isWritable: false },
+      {
+        pubkey: await associatedTokenAddress(
+          receiptMint,
+          expectedMint,
+          tokenProgram
+        ),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: holderTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from(Uint8Array.of(12)),
+  });
+};
@@ -0,0 +1,182 @@
+import { Connection, PublicKey } from "@solana/web3.js";
+import BN = require("bn.js");
+import * as http from "http";
+import {
+  decodeEscrowEvents,
+  EscrowLayout,
+  ESCROW_ACCOUNT_DATA_LAYOUT,
+  ESCROW_EVENT_ACTIONS,
+  getProgramId,
+  getStatsPda,
+  logError,
+  StatsLayout,
+  STATS_ACCOUNT_DATA_LAYOUT,
+} from "./utils";
+
+const POLL_INTERVAL_MS = 15_000;
+const FILL_RATE_WINDOW_MS = 60_000;
+
+interface Metrics {
+  openEscrows: number;
+  lockedRent: number;
+  eventSequence: number;
+  // escrows past their lifetime that nobody has reaped yet, and how long the oldest has waited
+  expiredEscrows: number;
+  crankLagSlots: number;
+  events: Map<string, number>;
+  errors: Map<string, number>;
+  fillTimes: number[];
+  lastPollSucceeded: boolean;
+}
+
+const increment = (counters: Map<string, number>, label: string) =>
+  counters.set(label, (counters.get(label) ?? 0) + 1);
+
+// "custom program error: 0x6" style messages are grouped by code, anything else by its text
+const errorLabel = (logs: string[], err: unknown) => {
+  const failure = logs.find((line) => line.includes("failed: "));
+  return failure === undefined
+    ? JSON.stringify(err)
+    : failure.slice(failure.indexOf("failed: ") + "failed: ".length);
+};
+
+const render = (metrics: Metrics) => {
+  const now = Date.now();
+  metrics.fillTimes = metrics.fillTimes.filter(
+    (time) => now - time < FILL_RATE_WINDOW_MS
+  );
+  const lines = [
+    "# HELP escrow_open_escrows Open escrows according to the stats account",
+    "# TYPE escrow_open_escrows gauge",
+    `escrow_open_escrows ${metrics.openEscrows}`,
+    "# HELP escrow_locked_rent_lamports Rent locked in open escrows",
+    "# TYPE escrow_locked_rent_lamports gauge",
+    `escrow_locked_rent_lamports ${metrics.lockedRent}`,
+    "# HELP escrow_event_sequence Last event sequence number in the stats account",
+    "# TYPE escrow_event_sequence gauge",
+    `escrow_event_sequence ${metrics.eventSequence}`,
+    "# HELP escrow_events_total Escrow events seen since the exporter started",
+    "# TYPE escrow_events_total counter",
+    ...ESCROW_EVENT_ACTIONS.map(
+      (action) =>
+        `escrow_events_total{action="${action}"} ${
+          metrics.events.get(action) ?? 0
+        }`
+    ),
+    "# HELP escrow_fills_per_minute Exchanges over the last minute",
+    "# TYPE escrow_fills_per_minute gauge",
+    `escrow_fills_per_minute ${metrics.fillTimes.length}`,
+    "# HELP escrow_failed_transactions_total Failed program transactions by error",
+    "# TYPE escrow_failed_transactions_total counter",
+    ...Array.from(metrics.errors.entries()).map(
+      ([error, count]) =>
+        `escrow_failed_transactions_total{error=${JSON.stringify(
+          error
+        )}} ${count}`
+    ),
+    "# HELP escrow_expired_escrows Escrows past their lifetime awaiting a Reap",
+    "# TYPE escrow_expired_escrows gauge",
+    `escrow_expired_escrows ${metrics.expiredEscrows}`,
+    "# HELP escrow_crank_lag_slots Slots the oldest expired escrow has waited for a Reap",
+    "# TYPE escrow_crank_lag_slots gauge",
+    `escrow_crank_lag_slots ${metrics.crankLagSlots}`,
+    "# HELP escrow_exporter_up Whether the last RPC poll succeeded",
+    "# TYPE escrow_exporter_up gauge",
+    `escrow_exporter_up ${metrics.lastPollSucceeded ? 1 : 0}`,
+  ];
+  return lines.join("\n") + "\n";
+};
+
+const poll = async (
+  connection: Connection,
+  programId: PublicKey,
+  metrics: Metrics
+) => {
+  const statsAccount = await connection.getAccountInfo(
+    await getStatsPda(programId)
+  );
+  if (statsAccount !== null) {
+    const stats = STATS_ACCOUNT_DATA_LAYOUT.decode(
+      statsAccount.data
+    ) as StatsLayout;
+    metrics.openEscrows = new BN(stats.openEscrows, 10, "le").toNumber();
+    metrics.lockedRent = new BN(stats.lockedRent, 10, "le").toNumber();
+    metrics.eventSequence = new BN(stats.eventSequence, 10, "le").toNumber();
+  }
+
+  const slot = await connection.getSlot();
+  const expirySlots = (
+    await connection.getProgramAccounts(programId, {
+      filters: [{ dataSize: ESCROW_ACCOUNT_DATA_LAYOUT.span }],
+    })
+  )
+    .map(({ account }) =>
+      new BN(
+        (ESCROW_ACCOUNT_DATA_LAYOUT.decode(account.data) as EscrowLayout)
+          .lifetimeExpirySlot,
+        10,
+        "le"
+      ).toNumber()
+    )
+    .filter((expirySlot) => expirySlot !== 0 && expirySlot <= slot);
+  metrics.expiredEscrows = expirySlots.length;
+  metrics.crankLagSlots =
+    expirySlots.length === 0 ? 0 : slot - Math.min(...expirySlots);
+};
+
+const metricsExporter = async () => {
+  const port = Number(process.argv[2] ?? 9464);
+  const programId = getProgramId();
+  const connection = new Connection("http://localhost:8899", "confirmed");
+  const metrics: Metrics = {
+    openEscrows: 0,
+    lockedRent: 0,
+    eventSequence: 0,
+    expiredEscrows: 0,
+    crankLagSlots: 0,
+    events: new Map(),
+    errors: new Map(),
+    fillTimes: [],
+    lastPollSucceeded: false,
+  };
+
+  connection.onLogs(programId, ({ err, logs }) => {
+    if (err !== null) {
+      increment(metrics.errors, errorLabel(logs, err));
+      return;
+    }
+    decodeEscrowEvents(logs).forEach(({ action }) => {
+      const name = ESCROW_EVENT_ACTIONS[action] ?? `${action}`;
+      increment(metrics.events, name);
+      if (name === "Exchange") {
+        metrics.fillTimes.push(Date.now());
+      }
+    });
+  });
+
+  const pollOnce = () =>
+    poll(connection, programId, metrics)
+      .then(() => {
+        metrics.lastPollSucceeded = true;
+      })
+      .catch((e) => {
+        metrics.lastPollSucceeded = false;
+        logError(`Polling the RPC node failed: ${e}`);
+      });
+  await pollOnce();
+  setInterval(pollOnce, POLL_INTERVAL_MS);
+
+  http
+    .createServer((req, res) => {
+      if (req.url !== "/metrics") {
+        res.writeHead(404).end();
+        return;
+      }
+      res.writeHead(200, { "Content-Type": "text/plain; version=0.0.4" });
+      res.end(render(metrics));
+    })
+    .listen(port);
+  console.log(`Serving escrow metrics on http://localhost:${port}/metrics`);
+};
+
+metricsExporter();
@@ -0,0 +1,89 @@
+import { Connection, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import BN = require("bn.js");
+import {
+  EscrowLayout,
+  ESCROW_ACCOUNT_DATA_LAYOUT,
+  ESCROW_FLAGS,
+  getProgramId,
+  getStatsPda,
+  hasEscrowFlag,
+  logError,
+  StatsLayout,
+  STATS_ACCOUNT_DATA_LAYOUT,
+} from "./utils";
+
+const toSol = (lamports: number) => (lamports / LAMPORTS_PER_SOL).toFixed(9);
+
+const rentReport = async () => {
+  const escrowProgramId = getProgramId();
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  const statsPda = await getStatsPda(escrowProgramId);
+  const statsAccount = await connection.getAccountInfo(statsPda);
+  if (statsAccount === null) {
+    logError("Stats account has not been created, run the setup script first");
+    process.exit(1);
+  }
+  const decodedStats = STATS_ACCOUNT_DATA_LAYOUT.decode(
+    statsAccount.data
+  ) as StatsLayout;
+
+  const programAccounts = await connection.getProgramAccounts(escrowProgramId);
+  const escrows = programAccounts
+    .filter(({ pubkey }) => !pubkey.equals(statsPda))
+    .map(({ account }) => ({
+      lamports: account.lamports,
+      state: ESCROW_ACCOUNT_DATA_LAYOUT.decode(account.data) as EscrowLayout,
+    }))
+    .filter(({ state }) =>
+      hasEscrowFlag(state.flags, ESCROW_FLAGS.initialized)
+    );
+
+  const tempAccounts = await connection.getMultipleAccountsInfo(
+    escrows.map(
+      ({ state }) => new PublicKey(state.initializerTempTokenAccountPubkey)
+    )
+  );
+
+  // rent that goes back to each initializer once their escrows are settled or cancelled,
+  // counting a shared vault once however many of their escrows deposit into it
+  const byInitializer = new Map<string, { escrows: number; lamports: number }>();
+  const countedVaults = new Set<string>();
+  escrows.forEach(({ lamports, state }, i) => {
+    const initializer = new PublicKey(state.initializerPubkey).toBase58();
+    const entry = byInitializer.get(initializer) ?? { escrows: 0, lamports: 0 };
+    const vault = new PublicKey(
+      state.initializerTempTokenAccountPubkey
+    ).toBase58();
+    entry.escrows += 1;
+    entry.lamports += lamports;
+    if (!countedVaults.has(vault)) {
+      entry.lamports += tempAccounts[i]?.lamports ?? 0;
+      countedVaults.add(vault);
+    }
+    byInitializer.set(initializer, entry);
+  });
+
+  console.log("Rent locked according to the stats account:");
+  console.table([
+    {
+      "Open escrows": new BN(decodedStats.openEscrows, 10, "le").toString(),
+      "Locked rent (SOL)": toSol(
+        new BN(decodedStats.lockedRent, 10, "le").toNumber()
+      ),
+      "Events emitted": new BN(decodedStats.eventSequence, 10, "le").toString(),
+    },
+  ]);
+
+  console.log("Reclaimable rent by initializer:");
+  console.table(
+    Array.from(byInitializer.entries()).map(([initializer, entry]) => ({
+      Initializer: initializer,
+      "Open escrows": entry.escrows,
+      "Reclaimable rent (SOL)": toSol(entry.lamports),
+    }))
+  );
+  console.log("");
+};
+
+rentReport();
@@ -0,0 +1,663 @@
+import { AccountLayout, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  Connection,
+  Ed25519Program,
+  Keypair,
+  PublicKey,
+  Secp256k1Program,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import BN = require("bn.js");
+import { BalanceAssert } from "./balance-assert";
+import { sendEscrowTransaction } from "./errors";
+import {
+  cancelInstruction,
+  depositInitializerInstruction,
+  depositTakerInstruction,
+  exchangeInstruction,
+  exchangeWithPermitInstruction,
+  initEscrowInstruction,
+  redeemInstruction,
+  resetTimeLockInstruction,
+  setPermitEthSignerInstruction,
+  settleInstruction,
+  TakerGate,
+} from "./instructions";
+import {
+  ESCROW_ACCOUNT_DATA_LAYOUT,
+  getKeypair,
+  getOperatorConfig,
+  getProgramId,
+  getPublicKey,
+  ethPermitMessage,
+  getTokenBalance,
+  hashLockHash,
+  permitMessage,
+  ROUNDING_MODES,
+  UNLOCK_SLOTS,
+} from "./utils";
+
+interface Offer {
+  initializer: Keypair;
+  escrowAccount: PublicKey;
+  config: PublicKey;
+  tempTokenAccount: PublicKey;
+  offerTokenAccount: PublicKey;
+  receivingTokenAccount: PublicKey;
+  depositMint: PublicKey;
+  expectedMint: PublicKey;
+  expectedAmount: number;
+  offerAmount: number;
+  priceAccount?: PublicKey;
+}
+
+interface AccountSnapshot {
+  lamports: number;
+  data: Buffer;
+}
+
+interface Context {
+  connection: Connection;
+  programId: PublicKey;
+  offers: Map<string, Offer>;
+  balances: BalanceAssert | null;
+  // escrow and vault accounts of each snapshotted offer, null once closed
+  offerSnapshots: Map<string, (AccountSnapshot | null)[]>;
+}
+
+interface Step {
+  description: string;
+  expectFailure: boolean;
+  action: (ctx: Context) => Promise<void>;
+}
+
+// failures surface as EscrowClientErrors, so failing steps print the escrow error's name
+const send = (ctx: Context, tx: Transaction, signers: Keypair[]) =>
+  sendEscrowTransaction(ctx.connection, ctx.programId, tx, signers);
+
+const snapshotOffer = async (ctx: Context, offer: Offer) =>
+  (
+    await ctx.connection.getMultipleAccountsInfo([
+      offer.escrowAccount,
+      offer.tempTokenAccount,
+    ])
+  ).map(
+    (account) =>
+      account && { lamports: account.lamports, data: Buffer.from(account.data) }
+  );
+
+const getOffer = (ctx: Context, label: string) => {
+  const offer = ctx.offers.get(label);
+  if (offer === undefined) {
+    throw new Error(`Unknown offer ${label}`);
+  }
+  return offer;
+};
+
+/**
+ * Scripts a multi-party sequence of escrow instructions against a running validator.
+ * Parties and token accounts are referred to by their names in the `keys` folder
+ * (e.g. "alice", "bob_x"), offers by the label given to `init`.
+ */
+export class Scenario {
+  private steps: Step[] = [];
+  private nextStepFails = false;
+
+  constructor(readonly name: string) {}
+
+  private step(description: string, action: Step["action"]) {
+    this.steps.push({
+      description,
+      expectFailure: this.nextStepFails,
+      action,
+    });
+    this.nextStepFails = false;
+    return this;
+  }
+
+  /** Marks the next step as one the program must reject */
+  expectFailure() {
+    this.nextStepFails = true;
+    return this;
+  }
+
+  init(
+    label: string,
+    initializer: string,
+    offer: {
+      mint: string;
+      offerTokenAccount: string;
+      receivingTokenAccount: string;
+      offerAmount: number;
+      expectedAmount: number;
+      takerGate?: TakerGate;
+      // only `recipient` may take the deposit, by redeeming with `preimage`
+      hashLock?: { preimage: Buffer; recipient: string };
+      // lamports the taker is paid on the fill, funded on top of the escrow account's rent
+      gasRebate?: number;
+      // fills are priced at this Pyth price account, moved by `offsetBps`
+      priceBand?: { priceAccount: string; offsetBps: number };
+    }
+  ) {
+    return this.step(
+      `${initializer} offers ${offer.offerAmount} of ${offer.mint} for ${offer.expectedAmount} (${label})`,
+      async (ctx) => {
+        const initializerKeypair = getKeypair(initializer);
+        const priceAccount =
+          offer.priceBand && getPublicKey(offer.priceBand.priceAccount);
+        const tempTokenAccountKeypair = new Keypair();
+        const escrowKeypair = new Keypair();
+        const config = await getOperatorConfig(ctx.programId);
+        const offerTokenAccount = getPublicKey(offer.offerTokenAccount);
+        const receivingTokenAccount = getPublicKey(offer.receivingTokenAccount);
+        const receivingAccountInfo = await ctx.connection.getAccountInfo(
+          receivingTokenAccount
+        );
+        if (receivingAccountInfo === null) {
+          throw new Error(`Missing ${offer.receivingTokenAccount}`);
+        }
+        const expectedMint = new PublicKey(
+          AccountLayout.decode(receivingAccountInfo.data).mint
+        );
+
+        const tx = new Transaction().add(
+          SystemProgram.createAccount({
+            programId: TOKEN_PROGRAM_ID,
+            space: AccountLayout.span,
+            lamports: await ctx.connection.getMinimumBalanceForRentExemption(
+              AccountLayout.span
+            ),
+            fromPubkey: initializerKeypair.publicKey,
+            newAccountPubkey: tempTokenAccountKeypair.publicKey,
+          }),
+          Token.createInitAccountInstruction(
+            TOKEN_PROGRAM_ID,
+            getPublicKey(offer.mint),
+            tempTokenAccountKeypair.publicKey,
+            initializerKeypair.publicKey
+          ),
+          Token.createTransferInstruction(
+            TOKEN_PROGRAM_ID,
+            offerTokenAccount,
+            tempTokenAccountKeypair.publicKey,
+            initializerKeypair.publicKey,
+            [],
+            offer.offerAmount
+          ),
+          SystemProgram.createAccount({
+            space: ESCROW_ACCOUNT_DATA_LAYOUT.span,
+            lamports:
+              (await ctx.connection.getMinimumBalanceForRentExemption(
+                ESCROW_ACCOUNT_DATA_LAYOUT.span
+              )) + (offer.gasRebate ?? 0),
+            fromPubkey: initializerKeypair.publicKey,
+            newAccountPubkey: escrowKeypair.publicKey,
+            programId: ctx.programId,
+          }),
+          await initEscrowInstruction(
+            ctx.programId,
+            initializerKeypair.publicKey,
+            tempTokenAccountKeypair.publicKey,
+            receivingTokenAccount,
+            escrowKeypair.publicKey,
+            config,
+            offer.expectedAmount,
+            [],
+            ROUNDING_MODES.floorForTaker,
+            undefined,
+            offerTokenAccount,
+            undefined,
+            undefined,
+            undefined,
+            undefined,
+            undefined,
+            undefined,
+            false,
+            offer.takerGate,
+            offer.hashLock && {
+              hash: hashLockHash(offer.hashLock.preimage),
+              recipient: getKeypair(offer.hashLock.recipient).publicKey,
+            },
+            offer.gasRebate,
+            priceAccount && {
+              priceAccount,
+              offsetBps: offer.priceBand?.offsetBps ?? 0,
+            }
+          )
+        );
+        await send(ctx, tx, [
+          initializerKeypair,
+          tempTokenAccountKeypair,
+          escrowKeypair,
+        ]);
+
+        ctx.offers.set(label, {
+          initializer: initializerKeypair,
+          escrowAccount: escrowKeypair.publicKey,
+          config,
+          tempTokenAccount: tempTokenAccountKeypair.publicKey,
+          offerTokenAccount,
+          receivingTokenAccount,
+          depositMint: getPublicKey(offer.mint),
+          expectedMint,
+          expectedAmount: offer.expectedAmount,
+          offerAmount: offer.offerAmount,
+          priceAccount,
+        });
+      }
+    );
+  }
+
+  exchange(
+    label: string,
+    taker: string,
+    accounts: { sendingTokenAccount: string; receivingTokenAccount: string },
+    takerExpectedAmount?: number,
+    maxPayment?: number
+  ) {
+    return this.step(`${taker} takes ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      const takerKeypair = getKeypair(taker);
+      const ix = await exchangeInstruction(
+        ctx.programId,
+        takerKeypair.publicKey,
+        getPublicKey(accounts.sendingTokenAccount),
+        getPublicKey(accounts.receivingTokenAccount),
+        offer.tempTokenAccount,
+        offer.initializer.publicKey,
+        offer.receivingTokenAccount,
+        offer.escrowAccount,
+        offer.config,
+        getPublicKey("fee_y"),
+        offer.depositMint,
+        offer.expectedMint,
+        takerExpectedAmount ?? offer.offerAmount,
+        [],
+        undefined,
+        undefined,
+        undefined,
+        undefined,
+        undefined,
+        maxPayment,
+        undefined,
+        undefined,
+        undefined,
+        undefined,
+        undefined,
+        undefined,
+        [],
+        offer.priceAccount
+      );
+      await send(ctx, new Transaction().add(ix), [takerKeypair]);
+    });
+  }
+
+  /**
+   * `taker` fills `label` for `payment` under a permit its initializer signs for them, valid
+   * for `validForSlots` from now. With `ethPrivateKey` the permit is signed by that Ethereum
+   * key instead, see `setPermitEthSigner`.
+   */
+  exchangeWithPermit(
+    label: string,
+    taker: string,
+    accounts: { sendingTokenAccount: string; receivingTokenAccount: string },
+    payment: number,
+    validForSlots = 100,
+    ethPrivateKey?: Buffer
+  ) {
+    return this.step(
+      `${taker} takes ${label} for ${payment} under a permit`,
+      async (ctx) => {
+        const offer = getOffer(ctx, label);
+        const takerKeypair = getKeypair(taker);
+        const escrowInfo = await ctx.connection.getAccountInfo(
+          offer.escrowAccount
+        );
+        if (escrowInfo === null) {
+          throw new Error(`${label} is closed`);
+        }
+        const { initSlot } = ESCROW_ACCOUNT_DATA_LAYOUT.decode(
+          escrowInfo.data
+        );
+        const validUntilSlot = (await ctx.connection.getSlot()) + validForSlots;
+        const message = permitMessage(
+          ctx.programId,
+          offer.escrowAccount,
+          new BN(initSlot, 10, "le").toNumber(),
+          takerKeypair.publicKey,
+          payment,
+          validUntilSlot
+        );
+        const permitIx =
+          ethPrivateKey === undefined
+            ? Ed25519Program.createInstructionWithPrivateKey({
+                privateKey: offer.initializer.secretKey,
+                message,
+              })
+            : Secp256k1Program.createInstructionWithPrivateKey({
+                privateKey: ethPrivateKey,
+                message: ethPermitMessage(message),
+              });
+        const exchangeIx = await exchangeInstruction(
+          ctx.programId,
+          takerKeypair.publicKey,
+          getPublicKey(accounts.sendingTokenAccount),
+          getPublicKey(accounts.receivingTokenAccount),
+          offer.tempTokenAccount,
+          offer.initializer.publicKey,
+          offer.receivingTokenAccount,
+          offer.escrowAccount,
+          offer.config,
+          getPublicKey("fee_y"),
+          offer.depositMint,
+          offer.expectedMint,
+          offer.offerAmount
+        );
+        await send(
+          ctx,
+          new Transaction().add(
+            permitIx,
+            exchangeWithPermitInstruction(exchangeIx, payment, validUntilSlot)
+          ),
+          [takerKeypair]
+        );
+      }
+    );
+  }
+
+  /** `takerRefundAccount` is the account a mutual deposit's taker paid from */
+  cancel(label: string, takerRefundAccount?: string) {
+    return this.step(`initializer cancels ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      const ix = await cancelInstruction(
+        ctx.programId,
+        offer.initializer.publicKey,
+        offer.tempTokenAccount,
+        offer.offerTokenAccount,
+        offer.escrowAccount,
+        offer.depositMint,
+        undefined,
+        undefined,
+        undefined,
+        [],
+        takerRefundAccount === undefined
+          ? undefined
+          : {
+              takerRefundAccount: getPublicKey(takerRefundAccount),
+              expectedMint: offer.expectedMint,
+            }
+      );
+      await send(ctx, new Transaction().add(ix), [offer.initializer]);
+    });
+  }
+
+  resetTimeLock(label: string, unlockSlots = UNLOCK_SLOTS.default) {
+    return this.step(
+      `initializer resets the timelock of ${label} to ${unlockSlots} slots`,
+      async (ctx) => {
+        const offer = getOffer(ctx, label);
+        const ix = resetTimeLockInstruction(
+          ctx.programId,
+          offer.initializer.publicKey,
+          offer.escrowAccount,
+          unlockSlots
+        );
+        await send(ctx, new Transaction().add(ix), [offer.initializer]);
+      }
+    );
+  }
+
+  /** The initializer lets the 20-byte `ethAddress` sign permits for `label` */
+  setPermitEthSigner(label: string, ethAddress: Buffer) {
+    return this.step(
+      `initializer lets 0x${ethAddress.toString("hex")} sign permits for ${label}`,
+      async (ctx) => {
+        const offer = getOffer(ctx, label);
+        const ix = setPermitEthSignerInstruction(
+          ctx.programId,
+          offer.initializer.publicKey,
+          offer.escrowAccount,
+          ethAddress
+        );
+        await send(ctx, new Transaction().add(ix), [offer.initializer]);
+      }
+    );
+  }
+
+  /** Makes the offer a mutual deposit that only `taker` can pay into */
+  mutualDeposit(label: string, taker: string) {
+    return this.step(
+      `${label} becomes a mutual deposit for ${taker}`,
+      async (ctx) => {
+        const offer = getOffer(ctx, label);
+        const ix = await depositInitializerInstruction(
+          ctx.programId,
+          offer.initializer.publicKey,
+          offer.escrowAccount,
+          getPublicKey(taker),
+          offer.expectedMint,
+          offer.depositMint
+        );
+        await send(ctx, new Transaction().add(ix), [offer.initializer]);
+      }
+    );
+  }
+
+  depositTaker(
+    label: string,
+    taker: string,
+    accounts: { sendingTokenAccount: string; receivingTokenAccount: string }
+  ) {
+    return this.step(`${taker} deposits into ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      const takerKeypair = getKeypair(taker);
+      const ix = await depositTakerInstruction(
+        ctx.programId,
+        takerKeypair.publicKey,
+        offer.escrowAccount,
+        getPublicKey(accounts.sendingTokenAccount),
+        getPublicKey(accounts.receivingTokenAccount),
+        offer.expectedMint
+      );
+      await send(ctx, new Transaction().add(ix), [takerKeypair]);
+    });
+  }
+
+  /** `takerReceivingTokenAccount` is the account the taker named at `depositTaker` */
+  settle(label: string, settler: string, takerReceivingTokenAccount: string) {
+    return this.step(`${settler} settles ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      const settlerKeypair = getKeypair(settler);
+      const ix = await settleInstruction(
+        ctx.programId,
+        settlerKeypair.publicKey,
+        offer.escrowAccount,
+        offer.tempTokenAccount,
+        offer.initializer.publicKey,
+        offer.receivingTokenAccount,
+        getPublicKey(takerReceivingTokenAccount),
+        offer.config,
+        getPublicKey("fee_y"),
+        offer.depositMint,
+        offer.expectedMint
+      );
+      await send(ctx, new Transaction().add(ix), [settlerKeypair]);
+    });
+  }
+
+  redeem(
+    label: string,
+    recipient: string,
+    receivingTokenAccount: string,
+    preimage: Buffer
+  ) {
+    return this.step(`${recipient} redeems ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      const recipientKeypair = getKeypair(recipient);
+      const ix = await redeemInstruction(
+        ctx.programId,
+        recipientKeypair.publicKey,
+        getPublicKey(receivingTokenAccount),
+        offer.escrowAccount,
+        offer.tempTokenAccount,
+        offer.initializer.publicKey,
+        offer.config,
+        offer.depositMint,
+        preimage
+      );
+      await send(ctx, new Transaction().add(ix), [recipientKeypair]);
+    });
+  }
+
+  /** Waits until the validator has advanced by `slots` slots */
+  waitSlots(slots: number) {
+    return this.step(`wait ${slots} slots`, async (ctx) => {
+      const target = (await ctx.connection.getSlot()) + slots;
+      while ((await ctx.connection.getSlot()) < target) {
+        await new Promise((resolve) => setTimeout(resolve, 400));
+      }
+    });
+  }
+
+  expectBalance(tokenAccount: string, amount: number) {
+    return this.step(`${tokenAccount} holds ${amount}`, async (ctx) => {
+      const balance = await getTokenBalance(
+        getPublicKey(tokenAccount),
+        ctx.connection
+      );
+      if (balance !== amount) {
+        throw new Error(`${tokenAccount} holds ${balance}, expected ${amount}`);
+      }
+    });
+  }
+
+  /** Records the balances `expectDeltas` will compare against */
+  snapshotBalances(tokenAccounts: string[], wallets: string[] = []) {
+    return this.step(`snapshot balances`, async (ctx) => {
+      ctx.balances = new BalanceAssert(tokenAccounts, wallets);
+      await ctx.balances.snapshot(ctx.connection);
+    });
+  }
+
+  /** Checks every snapshotted balance moved by exactly the given delta, or not at all */
+  expectDeltas(deltas: { [name: string]: number }) {
+    const description = Object.keys(deltas)
+      .map((name) => `${name} ${deltas[name] >= 0 ? "+" : ""}${deltas[name]}`)
+      .join(", ");
+    return this.step(
+      `balances moved by ${description || "nothing"}`,
+      async (ctx) => {
+        if (ctx.balances === null) {
+          throw new Error("expectDeltas needs a snapshotBalances step first");
+        }
+        await ctx.balances.assertDeltas(ctx.connection, deltas);
+      }
+    );
+  }
+
+  expectClosed(label: string) {
+    return this.step(`${label} is closed`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      if ((await ctx.connection.getAccountInfo(offer.escrowAccount)) !== null) {
+        throw new Error(`Escrow account of ${label} has not been closed`);
+      }
+    });
+  }
+
+  /**
+   * Freezes a token account with the mint's freeze authority, the setup's `id` keypair, so
+   * token program CPIs touching it fail. Thaw it again before the scenario ends, since the
+   * next one starts from the same accounts.
+   */
+  freeze(tokenAccount: string, mint: string) {
+    return this.step(`freeze ${tokenAccount}`, async (ctx) => {
+      const authority = getKeypair("id");
+      const ix = Token.createFreezeAccountInstruction(
+        TOKEN_PROGRAM_ID,
+        getPublicKey(tokenAccount),
+        getPublicKey(mint),
+        authority.publicKey,
+        []
+      );
+      await send(ctx, new Transaction().add(ix), [authority]);
+    });
+  }
+
+  thaw(tokenAccount: string, mint: string) {
+    return this.step(`thaw ${tokenAccount}`, async (ctx) => {
+      const authority = getKeypair("id");
+      const ix = Token.createThawAccountInstruction(
+        TOKEN_PROGRAM_ID,
+        getPublicKey(tokenAccount),
+        getPublicKey(mint),
+        authority.publicKey,
+        []
+      );
+      await send(ctx, new Transaction().add(ix), [authority]);
+    });
+  }
+
+  /** Records the escrow and vault accounts of an offer for `expectOfferUnchanged` */
+  snapshotOffer(label: string) {
+    return this.step(`snapshot ${label}`, async (ctx) => {
+      const offer = getOffer(ctx, label);
+      ctx.offerSnapshots.set(label, await snapshotOffer(ctx, offer));
+    });
+  }
+
+  /**
+   * Checks the offer's escrow and vault accounts hold exactly the lamports and data they
+   * did at `snapshotOffer`. After a failed step this shows the instruction aborted as a
+   * whole, with no write from before the failing CPI left behind.
+   */
+  expectOfferUnchanged(label: string) {
+    return this.step(`${label} is unchanged`, async (ctx) => {
+      const before = ctx.offerSnapshots.get(label);
+      if (before === undefined) {
+        throw new Error(
+          "expectOfferUnchanged needs a snapshotOffer step first"
+        );
+      }
+      const after = await snapshotOffer(ctx, getOffer(ctx, label));
+      const changed = ["escrow account", "vault"].filter((name, i) => {
+        const [a, b] = [before[i], after[i]];
+        return a === null || b === null
+          ? a !== b
+          : a.lamports !== b.lamports || !a.data.equals(b.data);
+      });
+      if (changed.length > 0) {
+        throw new Error(`${changed.join(" and ")} of ${label} changed`);
+      }
+    });
+  }
+
+  /** Runs every step in order, returning false on the first unexpected outcome */
+  async run(connection: Connection) {
+    const ctx: Context = {
+      connection,
+      programId: getProgramId(),
+      offers: new Map(),
+      balances: null,
+      offerSnapshots: new Map(),
+    };
+    console.log(`Scenario: ${this.name}`);
+    for (const step of this.steps) {
+      let error: unknown = null;
+      try {
+        await step.action(ctx);
+      } catch (e) {
+        error = e;
+      }
+      if (step.expectFailure === (error === null)) {
+        console.log(`  ✗ ${step.description}`);
+        console.log(
+          error === null ? "    expected the step to fail" : `    ${error}`
+        );
+        return false;
+      }
+      console.log(`  ✓ ${step.description}`);
+    }
+    return true;
+  }
+}
@@ -0,0 +1,372 @@
+import { Connection } from "@solana/web3.js";
+import { randomBytes } from "crypto";
+import { Scenario } from "./scenario";
+import { UNLOCK_SLOTS } from "./utils";
+
+// each scenario starts from the balances left behind by the previous one, run `setup` first
+const tokenAccounts = ["alice_x", "alice_y", "bob_x", "bob_y", "fee_y"];
+
+// the secret of a cross-chain swap, which bob learns once its other leg is locked
+const swapPreimage = randomBytes(32);
+// the Ethereum key with private key 1, and its address
+const aliceEthKey = Buffer.from("00".repeat(31) + "01", "hex");
+const aliceEthAddress = Buffer.from(
+  "7e5f4552091a69125d5dfcb7b8c2659029395bdf",
+  "hex"
+);
+
+const scenarios = [
+  new Scenario("offer is filled at the quoted amounts")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, alice_y: 3, bob_x: 5, bob_y: -3 }),
+
+  new Scenario("taker quoting a stale amount is rejected, initializer cancels")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .expectFailure()
+    .exchange(
+      "offer",
+      "bob",
+      { sendingTokenAccount: "bob_y", receivingTokenAccount: "bob_x" },
+      4
+    )
+    .cancel("offer")
+    .expectClosed("offer")
+    .expectDeltas({}),
+
+  new Scenario("fill priced above the taker's max payment is rejected")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .expectFailure()
+    .exchange(
+      "offer",
+      "bob",
+      { sendingTokenAccount: "bob_y", receivingTokenAccount: "bob_x" },
+      undefined,
+      2
+    )
+    .cancel("offer")
+    .expectClosed("offer")
+    .expectDeltas({}),
+
+  new Scenario("taker short of the gate's lamports is rejected")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+      // more SOL than any test wallet is airdropped
+      takerGate: { minLamports: 1_000_000 * 1_000_000_000 },
+    })
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .cancel("offer")
+    .expectClosed("offer")
+    .expectDeltas({}),
+
+  // each CPI of Exchange and Cancel made to fail in turn: the instruction must abort as a
+  // whole, leaving the escrow, its vault and every balance as they were
+  new Scenario("taker short of token Y fails the payment transfer")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 1_000_000,
+    })
+    .snapshotOffer("offer")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectOfferUnchanged("offer")
+    .cancel("offer")
+    .expectDeltas({}),
+
+  new Scenario(
+    "taker paying from an account they don't own fails the payment transfer"
+  )
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .snapshotOffer("offer")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "alice_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectOfferUnchanged("offer")
+    .cancel("offer")
+    .expectDeltas({}),
+
+  new Scenario("frozen initializer account fails the payment transfer")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .snapshotOffer("offer")
+    .freeze("alice_y", "mint_y")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .thaw("alice_y", "mint_y")
+    .expectOfferUnchanged("offer")
+    .cancel("offer")
+    .expectDeltas({}),
+
+  // the payment has already moved when the vault transfer fails, and must be rolled back
+  new Scenario("frozen taker account fails the vault transfer after the payment")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .snapshotOffer("offer")
+    .freeze("bob_x", "mint_x")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .thaw("bob_x", "mint_x")
+    .expectOfferUnchanged("offer")
+    .expectDeltas({ alice_x: -5 })
+    .cancel("offer")
+    .expectDeltas({}),
+
+  new Scenario("frozen refund account fails the cancel's vault transfer")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .snapshotOffer("offer")
+    .freeze("alice_x", "mint_x")
+    .expectFailure()
+    .cancel("offer")
+    .thaw("alice_x", "mint_x")
+    .expectOfferUnchanged("offer")
+    .cancel("offer")
+    .expectClosed("offer")
+    .expectDeltas({}),
+
+  // Settle has no window of its own, so the initializer pushing their cancellation window
+  // back after the taker committed can't shorten the time the taker has to complete the swap
+  new Scenario(
+    "timelock reset after the taker's mutual deposit doesn't block Settle"
+  )
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .mutualDeposit("offer", "bob")
+    .depositTaker("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectDeltas({ alice_x: -5, bob_y: -3 })
+    .resetTimeLock("offer", UNLOCK_SLOTS.max)
+    .settle("offer", "bob", "bob_x")
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, alice_y: 3, bob_x: 5, bob_y: -3 }),
+
+  // while the window is open the initializer may still back out, refunding both deposits
+  new Scenario(
+    "cancel after a timelock reset refunds the taker's mutual deposit"
+  )
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .mutualDeposit("offer", "bob")
+    .depositTaker("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .resetTimeLock("offer")
+    .cancel("offer", "bob_y")
+    .expectClosed("offer")
+    .expectDeltas({}),
+
+  // the recipient has until expiry to redeem, so the initializer can't pull the deposit back
+  // in the usual cancellation window once the other chain's leg may have been claimed
+  new Scenario("hash-locked offer only releases to its recipient's preimage")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+      hashLock: { preimage: swapPreimage, recipient: "bob" },
+    })
+    .expectFailure()
+    .cancel("offer")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectFailure()
+    .redeem("offer", "bob", "bob_x", randomBytes(32))
+    .redeem("offer", "bob", "bob_x", swapPreimage)
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, bob_x: 5 }),
+
+  new Scenario("taker fills at the price of the initializer's permit")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .exchangeWithPermit(
+      "offer",
+      "bob",
+      { sendingTokenAccount: "bob_y", receivingTokenAccount: "bob_x" },
+      2
+    )
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, alice_y: 2, bob_x: 5, bob_y: -2 }),
+
+  new Scenario("taker fills at the price of a permit signed by an Ethereum key")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+    })
+    .expectFailure()
+    .exchangeWithPermit(
+      "offer",
+      "bob",
+      { sendingTokenAccount: "bob_y", receivingTokenAccount: "bob_x" },
+      2,
+      100,
+      aliceEthKey
+    )
+    .setPermitEthSigner("offer", aliceEthAddress)
+    .exchangeWithPermit(
+      "offer",
+      "bob",
+      { sendingTokenAccount: "bob_y", receivingTokenAccount: "bob_x" },
+      2,
+      100,
+      aliceEthKey
+    )
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, alice_y: 2, bob_x: 5, bob_y: -2 }),
+
+  // the rebate leaves the escrow account with the fill, before it closes to alice
+  new Scenario("taker of an offer with a gas rebate is paid it on the fill")
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+      gasRebate: 10_000,
+    })
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectClosed("offer")
+    .expectDeltas({ alice_x: -5, alice_y: 3, bob_x: 5, bob_y: -3 }),
+
+  // no Pyth program on the test validator, so only the rejection of a spoofed price is covered
+  new Scenario(
+    "oracle-priced offer can't be filled against a non-Pyth price account"
+  )
+    .snapshotBalances(tokenAccounts)
+    .init("offer", "alice", {
+      mint: "mint_x",
+      offerTokenAccount: "alice_x",
+      receivingTokenAccount: "alice_y",
+      offerAmount: 5,
+      expectedAmount: 3,
+      priceBand: { priceAccount: "mint_y", offsetBps: 0 },
+    })
+    .snapshotOffer("offer")
+    .expectFailure()
+    .exchange("offer", "bob", {
+      sendingTokenAccount: "bob_y",
+      receivingTokenAccount: "bob_x",
+    })
+    .expectOfferUnchanged("offer")
+    .cancel("offer")
+    .expectDeltas({}),
+];
+
+const run = async () => {
+  const connection = new Connection("http://localhost:8899", "confirmed");
+  for (const scenario of scenarios) {
+    if (!(await scenario.run(connection))) {
+      process.exit(1);
+    }
+  }
+  console.log("✨All scenarios passed✨\n");
+};
+
+run();
@@ -3,13 +3,20 @@ import {
   LAMPORTS_PER_SOL,
   PublicKey,
   Signer,
+  Transaction,
 } from "@solana/web3.js";
 
 import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { initConfigInstruction, initStatsInstruction } from "./instructions";
 import {
+  dumpInstruction,
   getKeypair,
+  getOperatorConfig,
+  getProgramId,
   getPublicKey,
+  getStatsPda,
   getTokenBalance,
+  isDumpingInstructions,
   writePublicKey,
 } from "./utils";
 
@@ -24,7 +31,8 @@ const createMint = (
       secretKey,
     },
     publicKey,
-    null,
+    // lets the scenarios freeze token accounts to make the escrow's transfers fail
+    publicKey,
     0,
     TOKEN_PROGRAM_ID
   );
@@ -57,6 +65,25 @@ const setup = async () => {
   const bobPublicKey = getPublicKey("bob");
   const clientKeypair = getKeypair("id");
 
+  const escrowProgramId = getProgramId();
+  const initStatsIx = await initStatsInstruction(
+    escrowProgramId,
+    clientKeypair.publicKey
+  );
+  const initConfigIx = await initConfigInstruction(
+    escrowProgramId,
+    clientKeypair.publicKey,
+    clientKeypair.publicKey,
+    0,
+    0,
+    0
+  );
+  if (isDumpingInstructions()) {
+    dumpInstruction(initStatsIx);
+    dumpInstruction(initConfigIx);
+    return;
+  }
+
   const connection = new Connection("http://localhost:8899", "confirmed");
   console.log("Requesting SOL for Alice...");
   // some networks like the local network provide an airdrop function (mainnet of course does not)
@@ -69,6 +96,28 @@ const setup = async () => {
     LAMPORTS_PER_SOL * 10
   );
 
+  const statsPda = await getStatsPda(escrowProgramId);
+  if ((await connection.getAccountInfo(statsPda)) === null) {
+    console.log("Creating the escrow stats account...");
+    await connection.sendTransaction(
+      new Transaction().add(initStatsIx),
+      [clientKeypair],
+      { skipPreflight: false, preflightCommitment: "confirmed" }
+    );
+  }
+  if (
+    (await connection.getAccountInfo(
+      await getOperatorConfig(escrowProgramId)
+    )) === null
+  ) {
+    console.log("Creating the operator config (no fee, no limits)...");
+    await connection.sendTransaction(
+      new Transaction().add(initConfigIx),
+      [clientKeypair],
+      { skipPreflight: false, preflightCommitment: "confirmed" }
+    );
+  }
+
   const [mintX, aliceTokenAccountForX, bobTokenAccountForX] = await setupMint(
     "X",
     connection,
@@ -86,6 +135,11 @@ const setup = async () => {
     bobPublicKey,
     clientKeypair
   );
+  console.log("Creating the operator's fee TokenAccount for Y...");
+  writePublicKey(
+    await mintY.createAccount(clientKeypair.publicKey),
+    "fee_y"
+  );
   console.log("Sending 50Y to Bob's Y TokenAccount...");
   await mintY.mintTo(bobTokenAccountForY, clientKeypair.publicKey, [], 50);
 
@@ -0,0 +1,28 @@
+import { Connection } from "@solana/web3.js";
+import { spawnScenario } from "./devtools";
+import { getProgramId } from "./utils";
+
+const spawn = async () => {
+  const nOffers = Number(process.argv[2] ?? 10);
+  const seed = process.argv[3] ?? "devtools";
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  const { mintX, mintY, offers } = await spawnScenario(
+    connection,
+    getProgramId(),
+    nOffers,
+    seed
+  );
+  console.log(`✨${offers.length} open escrows trading ${mintX} for ${mintY}✨`);
+  console.table(
+    offers.map((offer) => ({
+      Escrow: offer.escrowAccount.toBase58(),
+      Maker: offer.maker.toBase58(),
+      "Offer (X)": offer.offerAmount,
+      "Expected (Y)": offer.expectedAmount,
+    }))
+  );
+  console.log("");
+};
+
+spawn();
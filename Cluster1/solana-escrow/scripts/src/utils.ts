@@ -1,7 +1,22 @@
-import { Connection, Keypair, PublicKey } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  MintLayout,
+  Token,
+  TOKEN_PROGRAM_ID,
+  u64,
+} from "@solana/spl-token";
+import {
+  Connection,
+  Keypair,
+  PublicKey,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import BN = require("bn.js");
+import * as bs58 from "bs58";
 //@ts-expect-error missing types
 import * as BufferLayout from "buffer-layout";
 
+import { createHash } from "crypto";
 import * as fs from "fs";
 
 export const logError = (msg: string) => {
@@ -47,6 +62,34 @@ export const getTerms = (): {
   return JSON.parse(fs.readFileSync(`./terms.json`) as unknown as string);
 };
 
+/**
+ * Whether a script was run with `--dump-instruction`, in which case it prints the escrow
+ * instructions it would send with `dumpInstruction` and exits without touching any RPC
+ */
+export const isDumpingInstructions = () =>
+  process.argv.includes("--dump-instruction");
+
+/** The arguments after `--dump-instruction` */
+export const dumpInstructionArgs = () =>
+  process.argv.slice(process.argv.indexOf("--dump-instruction") + 1);
+
+/**
+ * Prints an instruction as one line of JSON (program id, account metas, base64 data), for
+ * other tools to compose into the transactions they build
+ */
+export const dumpInstruction = (ix: TransactionInstruction) =>
+  console.log(
+    JSON.stringify({
+      programId: ix.programId.toBase58(),
+      accounts: ix.keys.map(({ pubkey, isSigner, isWritable }) => ({
+        pubkey: pubkey.toBase58(),
+        isSigner,
+        isWritable,
+      })),
+      data: ix.data.toString("base64"),
+    })
+  );
+
 export const getTokenBalance = async (
   pubkey: PublicKey,
   connection: Connection
@@ -70,18 +113,943 @@ const uint64 = (property = "uint64") => {
   return BufferLayout.blob(8, property);
 };
 
+/**
+ * Maximum number of payout accounts an escrow can split the taker's payment across
+ */
+export const MAX_PAYOUTS = 4;
+
+/**
+ * Bits of the escrow's `flags` byte, see `EscrowFlags` in the program's state.rs. Bits 1 to 4
+ * are reserved.
+ */
+export const ESCROW_FLAGS = {
+  initialized: 1 << 0,
+  riskFlagged: 1 << 5,
+  audited: 1 << 6,
+  receipt: 1 << 7,
+};
+
+/**
+ * Values of the escrow's `roundingMode`, see `RoundingMode` in the program's state.rs
+ */
+export const ROUNDING_MODES = {
+  floorForTaker: 0,
+  floorForMaker: 1,
+  halfEven: 2,
+};
+
+/**
+ * Values of the escrow's `quoteConvention`, see `QuoteConvention` in the program's state.rs
+ */
+export const QUOTE_CONVENTIONS = {
+  raw: 0,
+  // amounts are the interest-bearing mint's UI amounts, converted to raw ones at each fill
+  interestAdjusted: 1,
+};
+
+/**
+ * Bounds and default of InitEscrow's cancellation window, see `DEFAULT_UNLOCK_SLOTS` in the
+ * program's state.rs
+ */
+export const UNLOCK_SLOTS = {
+  default: 100,
+  min: 10,
+  max: 216_000,
+};
+
+/**
+ * Commitment a private offer records for its only allowed taker, who reveals `salt` (32 random
+ * bytes) to fill it. See `taker_commitment` in the program's state.rs.
+ */
+export const takerCommitment = (taker: PublicKey, salt: Buffer) =>
+  createHash("sha256").update(taker.toBuffer()).update(salt).digest();
+
+/**
+ * Hash a hash-locked escrow records, which its recipient redeems by revealing `preimage` (32
+ * secret bytes). The other chain's leg of the swap must lock against the same sha256 hash.
+ */
+export const hashLockHash = (preimage: Buffer) =>
+  createHash("sha256").update(preimage).digest();
+
+/**
+ * What an initializer signs to let `taker` fill their escrow whole for `payment` until
+ * `validUntilSlot`, see `Permit` in the program's state.rs. `initSlot` is the escrow's.
+ */
+export const permitMessage = (
+  programId: PublicKey,
+  escrowAccount: PublicKey,
+  initSlot: number,
+  taker: PublicKey,
+  payment: number,
+  validUntilSlot: number
+) =>
+  Buffer.concat([
+    Buffer.from("solana-escrow permit"),
+    programId.toBuffer(),
+    escrowAccount.toBuffer(),
+    Buffer.from(new BN(initSlot).toArray("le", 8)),
+    taker.toBuffer(),
+    Buffer.from(new BN(payment).toArray("le", 8)),
+    Buffer.from(new BN(validUntilSlot).toArray("le", 8)),
+  ]);
+
+/**
+ * What an escrow's permit Ethereum signer signs for a `permitMessage`: the message with
+ * `personal_sign`'s prefix, so an Ethereum wallet's signature over it verifies as is
+ */
+export const ethPermitMessage = (message: Buffer) =>
+  Buffer.concat([
+    Buffer.from(`\x19Ethereum Signed Message:\n${message.length}`),
+    message,
+  ]);
+
+/**
+ * Bits of the escrow's `depositMintRisk`, see `MintRiskFlags` in the program's state.rs. Any of
+ * them also sets `ESCROW_FLAGS.riskFlagged`.
+ */
+export const MINT_RISK_FLAGS = {
+  freezable: 1 << 0,
+  mintAuthority: 1 << 1,
+  transferFee: 1 << 2,
+  transferHook: 1 << 3,
+  unassessed: 1 << 7,
+};
+
+/**
+ * Bits of the escrow's `features`, the optional features it was opened with, see
+ * `EscrowFeatures` in the program's state.rs. Payouts and a settlement hook can't be combined.
+ */
+export const ESCROW_FEATURES = {
+  payouts: 1 << 0,
+  private: 1 << 1,
+  sharedVault: 1 << 2,
+  settlementHook: 1 << 3,
+  allowlist: 1 << 4,
+  partialFill: 1 << 5,
+  takerAllowlist: 1 << 6,
+  takerGate: 1 << 7,
+  insured: 1 << 8,
+  basket: 1 << 9,
+  mutualDeposit: 1 << 10,
+  hashLock: 1 << 11,
+  gasRebate: 1 << 12,
+  oraclePrice: 1 << 13,
+  interestAdjusted: 1 << 14,
+  designatedTaker: 1 << 15,
+};
+
+export const hasEscrowFlag = (flags: number, flag: number) =>
+  (flags & flag) === flag;
+
 export const ESCROW_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
-  BufferLayout.u8("isInitialized"),
+  BufferLayout.u8("flags"),
   publicKey("initializerPubkey"),
   publicKey("initializerTempTokenAccountPubkey"),
   publicKey("initializerReceivingTokenAccountPubkey"),
   uint64("expectedAmount"),
+  uint64("unlockTime"),
+  BufferLayout.u8("payoutCount"),
+  BufferLayout.blob(MAX_PAYOUTS * 34, "payouts"),
+  publicKey("configPubkey"),
+  publicKey("expectedMint"),
+  uint64("initSlot"),
+  BufferLayout.u8("roundingMode"),
+  uint64("lifetimeExpirySlot"),
+  publicKey("refundTokenAccountPubkey"),
+  BufferLayout.blob(32, "takerCommitment"),
+  BufferLayout.u8("depositMintRisk"),
+  uint64("vaultDeposit"),
+  publicKey("settlementProgram"),
+  BufferLayout.u8("vaultAuthorityBump"),
+  BufferLayout.u8("receiptMintBump"),
+  BufferLayout.u8("settlementBump"),
+  BufferLayout.u16("features"),
+  uint64("filledAmount"),
+  uint64("lastResetSlot"),
+  BufferLayout.u8("resetCount"),
+  uint64("takerMinLamports"),
+  publicKey("takerBadgeMint"),
+  publicKey("depositMint"),
+  publicKey("tokenProgram"),
+  BufferLayout.blob(32, "hashLockHash"),
+  publicKey("hashLockRecipient"),
+  uint64("gasRebate"),
+  BufferLayout.blob(20, "permitEthSigner"),
+  publicKey("priceAccount"),
+  BufferLayout.s16("priceOffsetBps"),
+  BufferLayout.u8("quoteConvention"),
+  uint64("fillCooldownSlots"),
+  uint64("lastFillSlot"),
+  publicKey("designatedTaker"),
 ]);
 
-export interface EscrowLayout {
+export const STATS_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  uint64("openEscrows"),
+  uint64("lockedRent"),
+  uint64("eventSequence"),
+  BufferLayout.u8("bump"),
+]);
+
+export interface StatsLayout {
   isInitialized: number;
+  openEscrows: Uint8Array;
+  lockedRent: Uint8Array;
+  eventSequence: Uint8Array;
+  bump: number;
+}
+
+export const PAIR_STATS_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  publicKey("configPubkey"),
+  publicKey("depositMint"),
+  publicKey("expectedMint"),
+  uint64("windowStartSlot"),
+  uint64("windowVolume"),
+  BufferLayout.u8("bump"),
+  uint64("limitFills"),
+  uint64("totalPriceImprovement"),
+]);
+
+export interface PairStatsLayout {
+  isInitialized: number;
+  configPubkey: Uint8Array;
+  depositMint: Uint8Array;
+  expectedMint: Uint8Array;
+  windowStartSlot: Uint8Array;
+  /** Token X released by fills since `windowStartSlot` */
+  windowVolume: Uint8Array;
+  bump: number;
+  /** Fills of the pair that passed a `maxPayment` */
+  limitFills: Uint8Array;
+  /** Token Y those fills came in under their `maxPayment`, all told */
+  totalPriceImprovement: Uint8Array;
+}
+
+export const INSURANCE_POOL_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  publicKey("configPubkey"),
+  BufferLayout.u16("premiumBps"),
+  BufferLayout.u8("bump"),
+  uint64("claimsPaid"),
+]);
+
+export interface InsurancePoolLayout {
+  isInitialized: number;
+  configPubkey: Uint8Array;
+  /** Share of an insured fill's payment routed to the pool, at most 100 */
+  premiumBps: number;
+  bump: number;
+  /** Claims PayClaim has paid out of the pool so far */
+  claimsPaid: Uint8Array;
+}
+
+export const BASKET_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  publicKey("escrowPubkey"),
+  publicKey("initializerPubkey"),
+  BufferLayout.u8("bump"),
+  BufferLayout.u8("assetCount"),
+  BufferLayout.seq(
+    BufferLayout.struct([
+      publicKey("vaultPubkey"),
+      publicKey("mint"),
+      uint64("amount"),
+    ]),
+    4,
+    "assets"
+  ),
+]);
+
+export const MUTUAL_DEPOSIT_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  publicKey("escrowPubkey"),
+  publicKey("takerPubkey"),
+  publicKey("takerTokenToReceiveAccountPubkey"),
+  publicKey("takerRefundAccountPubkey"),
+  uint64("takerDeposit"),
+  BufferLayout.u8("bump"),
+]);
+
+export interface MutualDepositLayout {
+  isInitialized: number;
+  escrowPubkey: Uint8Array;
+  takerPubkey: Uint8Array;
+  takerTokenToReceiveAccountPubkey: Uint8Array;
+  takerRefundAccountPubkey: Uint8Array;
+  /** 0 until the taker deposits */
+  takerDeposit: Uint8Array;
+  bump: number;
+}
+
+export interface BasketLayout {
+  isInitialized: number;
+  escrowPubkey: Uint8Array;
+  initializerPubkey: Uint8Array;
+  bump: number;
+  /** How many of `assets` are in use, in the order they were added */
+  assetCount: number;
+  assets: { vaultPubkey: Uint8Array; mint: Uint8Array; amount: Uint8Array }[];
+}
+
+/**
+ * Layout of the `Program data:` log every escrow mutation emits. `sequence` goes up by one
+ * per event, so a gap means logs were missed and should be backfilled.
+ */
+export const ESCROW_EVENT_LAYOUT = BufferLayout.struct([
+  uint64("sequence"),
+  BufferLayout.u8("action"),
+  publicKey("escrowPubkey"),
+  publicKey("actorPubkey"),
+  BufferLayout.u8("depositMintRisk"),
+]);
+
+export interface EscrowEventLayout {
+  sequence: Uint8Array;
+  action: number;
+  escrowPubkey: Uint8Array;
+  actorPubkey: Uint8Array;
+  depositMintRisk: number;
+}
+
+/**
+ * Values of an event's `action`, see `AuditAction` in the program's state.rs
+ */
+export const ESCROW_EVENT_ACTIONS = [
+  "InitEscrow",
+  "Exchange",
+  "Cancel",
+  "Reap",
+  "CancelExpired",
+  "ForceClose",
+  "SetEscrowTags",
+];
+
+/**
+ * Decodes the escrow events in a transaction's log messages
+ */
+export const decodeEscrowEvents = (logMessages: string[]) =>
+  logMessages
+    .filter((line) => line.startsWith("Program data: "))
+    .map((line) => Buffer.from(line.slice("Program data: ".length), "base64"))
+    .filter((data) => data.length === ESCROW_EVENT_LAYOUT.span)
+    .map((data) => ESCROW_EVENT_LAYOUT.decode(data) as EscrowEventLayout);
+
+/**
+ * Layout of the `Program data:` log every Exchange emits after its escrow event, see
+ * `FillEvent` in the program's state.rs
+ */
+export const FILL_EVENT_LAYOUT = BufferLayout.struct([
+  publicKey("escrowPubkey"),
+  uint64("fill"),
+  uint64("payment"),
+  uint64("maxPayment"),
+  uint64("priceImprovement"),
+]);
+
+export interface FillEventLayout {
+  escrowPubkey: Uint8Array;
+  /** Token X the taker received */
+  fill: Uint8Array;
+  /** Token Y the taker paid for it, fees included */
+  payment: Uint8Array;
+  /** Zero if the taker set no limit */
+  maxPayment: Uint8Array;
+  /** How far `payment` came in under `maxPayment`, zero without a limit */
+  priceImprovement: Uint8Array;
+}
+
+/**
+ * Decodes the fill events in a transaction's log messages
+ */
+export const decodeFillEvents = (logMessages: string[]) =>
+  logMessages
+    .filter((line) => line.startsWith("Program data: "))
+    .map((line) => Buffer.from(line.slice("Program data: ".length), "base64"))
+    .filter((data) => data.length === FILL_EVENT_LAYOUT.span)
+    .map((data) => FILL_EVENT_LAYOUT.decode(data) as FillEventLayout);
+
+export const DEPLOYMENT_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+  BufferLayout.u8("isInitialized"),
+  BufferLayout.u16("major"),
+  BufferLayout.u16("minor"),
+  BufferLayout.u16("patch"),
+  uint64("upgradeSlot"),
+  BufferLayout.blob(32, "migrationNotesHash"),
+]);
+
+export interface DeploymentLayout {
+  isInitialized: number;
+  major: number;
+  minor: number;
+  patch: number;
+  upgradeSlot: Uint8Array;
+  migrationNotesHash: Uint8Array;
+}
+
+export const getDeploymentPda = async (programId: PublicKey) =>
+  (await PublicKey.findProgramAddress([Buffer.from("deployment")], programId))[0];
+
+/**
+ * Layout of the return_data written by the TimeStatus instruction. The initializer's cancel
+ * window closes at unlock.
+ */
+export const TIME_STATUS_LAYOUT = BufferLayout.struct([
+  uint64("currentSlot"),
+  uint64("slotsUntilUnlock"),
+  uint64("slotsUntilExpiry"),
+  uint64("secondsUntilUnlock"),
+  uint64("secondsUntilExpiry"),
+]);
+
+export interface TimeStatusLayout {
+  currentSlot: Uint8Array;
+  slotsUntilUnlock: Uint8Array;
+  slotsUntilExpiry: Uint8Array;
+  secondsUntilUnlock: Uint8Array;
+  secondsUntilExpiry: Uint8Array;
+}
+
+/**
+ * Layout of the return_data written by the BuildInfo instruction. Strings are zero-padded, see
+ * `decodeBuildInfo`.
+ */
+export const BUILD_INFO_LAYOUT = BufferLayout.struct([
+  BufferLayout.blob(16, "version"),
+  BufferLayout.blob(40, "gitHash"),
+  BufferLayout.u8("gitDirty"),
+  BufferLayout.blob(64, "rustcVersion"),
+  BufferLayout.blob(64, "features"),
+]);
+
+const paddedString = (bytes: Uint8Array) =>
+  Buffer.from(bytes).toString("utf8").replace(/\0+$/, "");
+
+/**
+ * Decodes the BuildInfo instruction's return_data, to compare the deployed program's commit,
+ * compiler and features against an audited build
+ */
+export const decodeBuildInfo = (data: Buffer) => {
+  const info = BUILD_INFO_LAYOUT.decode(data);
+  return {
+    version: paddedString(info.version),
+    gitHash: paddedString(info.gitHash),
+    gitDirty: info.gitDirty !== 0,
+    rustcVersion: paddedString(info.rustcVersion),
+    features: paddedString(info.features)
+      .split(",")
+      .filter((feature) => feature !== ""),
+  };
+};
+
+/**
+ * Layout of the return_data written by a successful ValidateExchange
+ */
+export const EXCHANGE_QUOTE_LAYOUT = BufferLayout.struct([
+  uint64("deposit"),
+  uint64("fee"),
+  uint64("initializerProceeds"),
+  uint64("feeMintAmount"),
+  uint64("royalties"),
+  uint64("insurancePremium"),
+]);
+
+export interface ExchangeQuoteLayout {
+  deposit: Uint8Array;
+  fee: Uint8Array;
+  /** After the fee, the royalties and the insurance premium */
+  initializerProceeds: Uint8Array;
+  feeMintAmount: Uint8Array;
+  /** Paid to the creators of an NFT deposit, zero for other escrows */
+  royalties: Uint8Array;
+  /** Paid to the config's insurance pool, zero unless the escrow is insured */
+  insurancePremium: Uint8Array;
+}
+
+/**
+ * Fixed-point scale of a config's fee mint rate, see `FEE_RATE_SCALE` in the program's state.rs
+ */
+export const FEE_RATE_SCALE = 1_000_000_000;
+
+/**
+ * Custom program error code of `EscrowError::EscrowAlreadySettled`: another transaction filled,
+ * cancelled or reaped the escrow first, so a retry has nothing left to do
+ */
+export const ESCROW_ALREADY_SETTLED_ERROR = 14;
+
+export const getStatsPda = async (programId: PublicKey) =>
+  (await PublicKey.findProgramAddress([Buffer.from("stats")], programId))[0];
+
+/**
+ * The initializer's shared vault for `mint`, created by InitVault and deposited into by
+ * escrows opened with a `vaultDeposit`
+ */
+export const getVaultPda = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  mint: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("vault"), initializer.toBuffer(), mint.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * Owner of `vault`, an escrow's temp token account or a shared vault. Exchange, Cancel, Reap
+ * and RecoverClosedMint sign with it to move the vault's tokens.
+ */
+export const getVaultAuthorityPda = async (
+  programId: PublicKey,
+  vault: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("escrow"), vault.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * The initializer's escrow account at `index`, allocated by `precreateEscrowsInstruction` for
+ * a later `initEscrowInstruction`
+ */
+export const getPrecreatedEscrowPda = async (
+  programId: PublicKey,
+  initializer: PublicKey,
+  index: number
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [
+        Buffer.from("escrow_account"),
+        initializer.toBuffer(),
+        Buffer.from(new BN(index).toArray("le", 8)),
+      ],
+      programId
+    )
+  )[0];
+
+/**
+ * Holds the takers allowed to fill an escrow, once its initializer has added one with
+ * addAllowedTakerInstruction
+ */
+export const getTakerAllowlistPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("takers"), escrowAccount.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * Holds `taker`'s counter-offer for an escrow, made with makeCounterOfferInstruction
+ */
+export const getCounterOfferPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey,
+  taker: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [
+        Buffer.from("counter_offer"),
+        escrowAccount.toBuffer(),
+        taker.toBuffer(),
+      ],
+      programId
+    )
+  )[0];
+
+export const MAX_ESCROW_TAGS = 4;
+export const ESCROW_TAG_KEY_LEN = 8;
+export const ESCROW_TAG_VALUE_LEN = 16;
+const ESCROW_TAG_LEN = ESCROW_TAG_KEY_LEN + ESCROW_TAG_VALUE_LEN;
+// is_initialized, escrow, initializer, init slot, bump and tag count come first
+const ESCROW_TAGS_OFFSET = 75;
+export const ESCROW_TAGS_SPAN =
+  ESCROW_TAGS_OFFSET + MAX_ESCROW_TAGS * ESCROW_TAG_LEN;
+
+/**
+ * Holds the tags an escrow was opened with, set by setEscrowTagsInstruction
+ */
+export const getEscrowTagsPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("tags"), escrowAccount.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * Encodes a tag like `desk=alpha` the way the program stores it: the key, then the value,
+ * each padded with zeros
+ */
+export const encodeEscrowTag = (key: string, value: string) => {
+  const keyBytes = Buffer.from(key);
+  const valueBytes = Buffer.from(value);
+  if (
+    keyBytes.length === 0 ||
+    keyBytes.length > ESCROW_TAG_KEY_LEN ||
+    valueBytes.length > ESCROW_TAG_VALUE_LEN
+  ) {
+    throw new Error(`Invalid escrow tag ${key}=${value}`);
+  }
+  const tag = Buffer.alloc(ESCROW_TAG_LEN);
+  keyBytes.copy(tag);
+  valueBytes.copy(tag, ESCROW_TAG_KEY_LEN);
+  return tag;
+};
+
+const trimZeros = (bytes: Buffer) =>
+  bytes.toString("utf8").replace(/\0+$/, "");
+
+/**
+ * Decodes a tags PDA's data, or the same bytes logged after a SetEscrowTags event
+ */
+export const decodeEscrowTags = (data: Buffer) => ({
+  escrowPubkey: new PublicKey(data.subarray(1, 33)),
+  tags: Array.from({ length: data[ESCROW_TAGS_OFFSET - 1] }, (_, i) => {
+    const tag = data.subarray(
+      ESCROW_TAGS_OFFSET + i * ESCROW_TAG_LEN,
+      ESCROW_TAGS_OFFSET + (i + 1) * ESCROW_TAG_LEN
+    );
+    return {
+      key: trimZeros(tag.subarray(0, ESCROW_TAG_KEY_LEN)),
+      value: trimZeros(tag.subarray(ESCROW_TAG_KEY_LEN)),
+    };
+  }),
+});
+
+/**
+ * Decodes the tags logged by the SetEscrowTags instructions in a transaction's log messages
+ */
+export const decodeEscrowTagLogs = (logMessages: string[]) =>
+  logMessages
+    .filter((line) => line.startsWith("Program data: "))
+    .map((line) => Buffer.from(line.slice("Program data: ".length), "base64"))
+    .filter((data) => data.length === ESCROW_TAGS_SPAN)
+    .map(decodeEscrowTags);
+
+/**
+ * getProgramAccounts filters matching tags PDAs with `key=value` in tag slot `slot`. A tag
+ * can sit in any of the MAX_ESCROW_TAGS slots, see findEscrowsByTag.
+ */
+export const escrowTagFilters = (
+  key: string,
+  value: string,
+  slot: number
+) => [
+  { dataSize: ESCROW_TAGS_SPAN },
+  {
+    memcmp: {
+      offset: ESCROW_TAGS_OFFSET + slot * ESCROW_TAG_LEN,
+      bytes: bs58.encode(encodeEscrowTag(key, value)),
+    },
+  },
+];
+
+/**
+ * Escrows tagged `key=value`, found with one memcmp query per tag slot. Tags of settled
+ * escrows linger until their initializer closes them, so check the escrow still exists.
+ */
+export const findEscrowsByTag = async (
+  connection: Connection,
+  programId: PublicKey,
+  key: string,
+  value: string
+) =>
+  (
+    await Promise.all(
+      Array.from({ length: MAX_ESCROW_TAGS }, (_, slot) =>
+        connection.getProgramAccounts(programId, {
+          filters: escrowTagFilters(key, value, slot),
+        })
+      )
+    )
+  )
+    .flat()
+    .map(({ account }) => decodeEscrowTags(account.data).escrowPubkey);
+
+/**
+ * Owner of the staging accounts Exchange pays an escrow's proceeds into before its settlement
+ * program takes them
+ */
+export const getSettlementPda = async (programId: PublicKey) =>
+  (
+    await PublicKey.findProgramAddress([Buffer.from("settlement")], programId)
+  )[0];
+
+export const getReceiptMintPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("receipt"), escrowAccount.toBuffer()],
+      programId
+    )
+  )[0];
+
+export const getConfigPda = async (programId: PublicKey, admin: PublicKey) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("config"), admin.toBuffer()],
+      programId
+    )
+  )[0];
+
+export const getAllowlistEntryPda = async (
+  programId: PublicKey,
+  config: PublicKey,
+  member: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("allowlist"), config.toBuffer(), member.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * Tracks fills of `depositMint` for `expectedMint` under `config`. Exchange needs it when the
+ * config has a volume cap, after InitPairStats created it.
+ */
+export const getPairStatsPda = async (
+  programId: PublicKey,
+  config: PublicKey,
+  depositMint: PublicKey,
+  expectedMint: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [
+        Buffer.from("pair_stats"),
+        config.toBuffer(),
+        depositMint.toBuffer(),
+        expectedMint.toBuffer(),
+      ],
+      programId
+    )
+  )[0];
+
+/**
+ * Counts `config`'s InitEscrows per slot. InitEscrow needs it when the config limits inits
+ * per slot, after InitRateCounter created it.
+ */
+export const getRateCounterPda = async (programId: PublicKey, config: PublicKey) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("rate_counter"), config.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * The basket of further assets an escrow hands its taker along with the deposit, owning a
+ * vault per asset: its associated token account for the asset's mint
+ */
+export const getMutualDepositPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("mutual"), escrowAccount.toBuffer()],
+      programId
+    )
+  )[0];
+
+export const getBasketPda = async (
+  programId: PublicKey,
+  escrowAccount: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("basket"), escrowAccount.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * The operator's insurance pool under `config`, owning the token accounts insured fills pay
+ * their premiums into and PayClaim pays out of
+ */
+export const getInsurancePoolPda = async (
+  programId: PublicKey,
+  config: PublicKey
+) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("insurance"), config.toBuffer()],
+      programId
+    )
+  )[0];
+
+// role bits of an allowlist entry, mirroring AllowlistEntry's constants in the program's state.rs
+export const ALLOWLIST_ROLES = {
+  initEscrow: 1 << 0,
+};
+
+export const getAuditLogPda = async (programId: PublicKey, config: PublicKey) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("audit"), config.toBuffer()],
+      programId
+    )
+  )[0];
+
+/**
+ * Owner of Metaplex metadata accounts, see `METADATA_PROGRAM_ID` in the program's state.rs
+ */
+export const METADATA_PROGRAM_ID = new PublicKey(
+  "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+);
+
+export const getMetadataPda = async (mint: PublicKey) =>
+  (
+    await PublicKey.findProgramAddress(
+      [Buffer.from("metadata"), METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      METADATA_PROGRAM_ID
+    )
+  )[0];
+
+/**
+ * The accounts Exchange needs to pay the royalties of an NFT deposit, for `exchangeInstruction`'s
+ * `nftRoyalties`: the mint's metadata account, and the associated token account for token Y of
+ * each creator with a share, in the metadata's order. Undefined if token X isn't an NFT.
+ * Creators without such an account need one before the escrow can be filled.
+ */
+export const getNftRoyalties = async (
+  connection: Connection,
+  depositMint: PublicKey,
+  expectedMint: PublicKey
+) => {
+  const mintInfo = await connection.getAccountInfo(depositMint);
+  if (mintInfo === null) {
+    return undefined;
+  }
+  const mint = MintLayout.decode(mintInfo.data);
+  if (mint.decimals !== 0 || !u64.fromBuffer(mint.supply).eqn(1)) {
+    return undefined;
+  }
+
+  const metadata = await getMetadataPda(depositMint);
+  const metadataInfo = await connection.getAccountInfo(metadata);
+  const creators: PublicKey[] = [];
+  if (metadataInfo !== null && metadataInfo.owner.equals(METADATA_PROGRAM_ID)) {
+    // key, update authority and mint, then name, symbol and uri, then the seller fee
+    const data = metadataInfo.data;
+    let offset = 1 + 32 + 32;
+    for (let i = 0; i < 3; i++) {
+      offset += 4 + data.readUInt32LE(offset);
+    }
+    offset += 2;
+    if (data[offset] === 1) {
+      const count = data.readUInt32LE(offset + 1);
+      offset += 5;
+      // address, verified, share
+      for (let i = 0; i < count; i++, offset += 34) {
+        if (data[offset + 33] > 0) {
+          creators.push(new PublicKey(data.subarray(offset, offset + 32)));
+        }
+      }
+    }
+  }
+  return {
+    metadata,
+    creatorTokenAccounts: await Promise.all(
+      creators.map((creator) =>
+        Token.getAssociatedTokenAddress(
+          ASSOCIATED_TOKEN_PROGRAM_ID,
+          TOKEN_PROGRAM_ID,
+          expectedMint,
+          creator,
+          true
+        )
+      )
+    ),
+  };
+};
+
+/**
+ * The scripts run their escrows under the config of the "id" keypair, created by the setup script
+ */
+export const getOperatorConfig = (programId: PublicKey) =>
+  getConfigPda(programId, getPublicKey("id"));
+
+export interface EscrowLayout {
+  flags: number;
   initializerPubkey: Uint8Array;
   initializerReceivingTokenAccountPubkey: Uint8Array;
   initializerTempTokenAccountPubkey: Uint8Array;
   expectedAmount: Uint8Array;
+  unlockTime: Uint8Array;
+  payoutCount: number;
+  payouts: Uint8Array;
+  configPubkey: Uint8Array;
+  expectedMint: Uint8Array;
+  initSlot: Uint8Array;
+  roundingMode: number;
+  /** Slot from which the escrow can only be reaped, zero when its config sets no max lifetime */
+  lifetimeExpirySlot: Uint8Array;
+  /** All zeros when refunds go to the initializer's associated token account */
+  refundTokenAccountPubkey: Uint8Array;
+  /** All zeros unless the escrow is a private offer, see `takerCommitment` */
+  takerCommitment: Uint8Array;
+  /** `MINT_RISK_FLAGS` bits, assessed at init */
+  depositMintRisk: number;
+  /** The escrow's share of a shared vault, zero when the temp token account is its own */
+  vaultDeposit: Uint8Array;
+  /** All zeros unless Exchange settles the proceeds into this program */
+  settlementProgram: Uint8Array;
+  /** Bump of `getVaultAuthorityPda` for the escrow's temp token account */
+  vaultAuthorityBump: number;
+  /** Bump of `getReceiptMintPda`, set once the escrow has a receipt */
+  receiptMintBump: number;
+  /** Bump of `getSettlementPda`, set if the escrow has a settlement hook */
+  settlementBump: number;
+  /** `ESCROW_FEATURES` bits, fixed at init but for `takerAllowlist`, `insured` and `basket` */
+  features: number;
+  /** Token X partial fills have released so far, the rest is still up for a taker */
+  filledAmount: Uint8Array;
+  /** Slot of the last ResetTimeLock, zero if there was none */
+  lastResetSlot: Uint8Array;
+  /** ResetTimeLocks so far, at most 3 spaced at least 1000 slots apart */
+  resetCount: number;
+  /** Lamports a taker's wallet must hold to fill, zero for no balance check */
+  takerMinLamports: Uint8Array;
+  /** Badge token takers may hold instead, all zero for none */
+  takerBadgeMint: Uint8Array;
+  /** Mint of the token the initializer offers, recorded at init */
+  depositMint: Uint8Array;
+  /** Token program the escrow was opened with, which its later instructions must pass */
+  tokenProgram: Uint8Array;
+  /** sha256 a Redeem's preimage must hash to, all zero for no hash lock */
+  hashLockHash: Uint8Array;
+  /** The only signer Redeem releases the deposit to */
+  hashLockRecipient: Uint8Array;
+  /** Lamports Exchange pays the taker out of the escrow account, 0 for none */
+  gasRebate: Uint8Array;
+  /** Ethereum address that may sign permits for the initializer, all zero for none */
+  permitEthSigner: Uint8Array;
+  /** Pyth price account fills are priced at, all zero for a fixed price */
+  priceAccount: Uint8Array;
+  /** Basis points the oracle price is moved by */
+  priceOffsetBps: number;
+  /** 0 for raw amounts, 1 for an interest-bearing token Y mint's UI amounts */
+  quoteConvention: number;
+  /** Slots after a partial fill before the next one, 0 for none */
+  fillCooldownSlots: Uint8Array;
+  /** Slot of the last partial fill, 0 if there was none */
+  lastFillSlot: Uint8Array;
+  /** The only taker who may fill the offer, all zero for any taker */
+  designatedTaker: Uint8Array;
 }
@@ -0,0 +1,163 @@
+import { Connection, PublicKey } from "@solana/web3.js";
+import BN = require("bn.js");
+import { createHmac } from "crypto";
+import * as http from "http";
+import * as https from "https";
+import {
+  decodeEscrowEvents,
+  EscrowEventLayout,
+  ESCROW_EVENT_ACTIONS,
+  getProgramId,
+  logError,
+} from "./utils";
+
+const MAX_ATTEMPTS = 8;
+const INITIAL_BACKOFF_MS = 1_000;
+const MAX_BACKOFF_MS = 5 * 60_000;
+
+// the webhook sent for each escrow event a merchant backend settles orders on
+const WEBHOOK_TYPES: Record<string, string> = {
+  InitEscrow: "EscrowCreated",
+  Exchange: "EscrowFilled",
+  Cancel: "EscrowCancelled",
+  Reap: "EscrowExpired",
+  CancelExpired: "EscrowExpired",
+};
+
+interface EscrowWebhook {
+  /** Unique per event, for receivers to drop the duplicates a retry can cause */
+  id: string;
+  type: string;
+  sequence: string;
+  escrow: string;
+  actor: string;
+  transaction: string;
+  slot: number;
+}
+
+/**
+ * Hex HMAC-SHA256 of `${timestamp}.${body}`, sent as `X-Escrow-Signature: sha256=<hex>` next to
+ * `X-Escrow-Timestamp`. Receivers recompute it with the shared secret and reject stale
+ * timestamps, so a captured webhook can't be replayed.
+ */
+const signWebhook = (secret: string, timestamp: number, body: string) =>
+  createHmac("sha256", secret).update(`${timestamp}.${body}`).digest("hex");
+
+const toWebhook = (
+  event: EscrowEventLayout,
+  transaction: string,
+  slot: number
+): EscrowWebhook | null => {
+  const type = WEBHOOK_TYPES[ESCROW_EVENT_ACTIONS[event.action]];
+  if (type === undefined) {
+    return null;
+  }
+  const sequence = new BN(event.sequence, 10, "le").toString();
+  return {
+    id: `escrow-event-${sequence}`,
+    type,
+    sequence,
+    escrow: new PublicKey(event.escrowPubkey).toBase58(),
+    actor: new PublicKey(event.actorPubkey).toBase58(),
+    transaction,
+    slot,
+  };
+};
+
+// resolves to the response status, rejects on network errors
+const post = (url: URL, body: string, headers: Record<string, string>) =>
+  new Promise<number>((resolve, reject) => {
+    const request = (url.protocol === "https:" ? https : http).request(
+      url,
+      {
+        method: "POST",
+        headers: {
+          "Content-Type": "application/json",
+          "Content-Length": Buffer.byteLength(body),
+          ...headers,
+        },
+      },
+      (res) => {
+        res.resume();
+        resolve(res.statusCode ?? 0);
+      }
+    );
+    request.on("error", reject);
+    request.end(body);
+  });
+
+// 2xx is delivered; 408, 429, 5xx and network errors are retried; any other status is final
+const isRetryable = (status: number) =>
+  status === 408 || status === 429 || status >= 500;
+
+/**
+ * POSTs a webhook, retrying with exponential backoff up to MAX_ATTEMPTS times. Each attempt
+ * is signed afresh, so its timestamp stays current.
+ */
+const deliverWebhook = async (
+  url: URL,
+  secret: string,
+  webhook: EscrowWebhook
+) => {
+  const body = JSON.stringify(webhook);
+  let backoff = INITIAL_BACKOFF_MS;
+  for (let attempt = 1; attempt <= MAX_ATTEMPTS; attempt++) {
+    const timestamp = Math.floor(Date.now() / 1000);
+    let failure: string;
+    try {
+      const status = await post(url, body, {
+        "X-Escrow-Event-Id": webhook.id,
+        "X-Escrow-Timestamp": `${timestamp}`,
+        "X-Escrow-Signature": `sha256=${signWebhook(secret, timestamp, body)}`,
+      });
+      if (status >= 200 && status < 300) {
+        return true;
+      }
+      failure = `status ${status}`;
+      if (!isRetryable(status)) {
+        logError(`Webhook ${webhook.id} rejected with ${failure}`);
+        return false;
+      }
+    } catch (e) {
+      failure = `${e}`;
+    }
+    if (attempt < MAX_ATTEMPTS) {
+      logError(
+        `Webhook ${webhook.id} failed (${failure}), retrying in ${backoff}ms`
+      );
+      await new Promise((resolve) => setTimeout(resolve, backoff));
+      backoff = Math.min(backoff * 2, MAX_BACKOFF_MS);
+    }
+  }
+  logError(`Webhook ${webhook.id} dropped after ${MAX_ATTEMPTS} attempts`);
+  return false;
+};
+
+const webhooks = async () => {
+  const url = process.argv[2];
+  const secret = process.env.ESCROW_WEBHOOK_SECRET;
+  if (url === undefined || secret === undefined || secret === "") {
+    logError(
+      "Usage: ESCROW_WEBHOOK_SECRET=<secret> npm run webhooks -- <url>"
+    );
+    process.exit(1);
+  }
+  const endpoint = new URL(url);
+  const programId = getProgramId();
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  connection.onLogs(programId, ({ err, logs, signature }, { slot }) => {
+    if (err !== null) {
+      return;
+    }
+    decodeEscrowEvents(logs).forEach((event) => {
+      const webhook = toWebhook(event, signature, slot);
+      if (webhook !== null) {
+        deliverWebhook(endpoint, secret, webhook);
+      }
+    });
+  });
+  console.log(`Posting escrow webhooks to ${endpoint}`);
+};
+
+webhooks();